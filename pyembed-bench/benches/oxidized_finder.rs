@@ -38,6 +38,7 @@ fn python_resources_state_resolve_modules(
     for name in modules {
         state
             .resolve_importable_module(name, BytecodeOptimizationLevel::Zero)
+            .expect("failed to resolve module")
             .expect("failed to retrieve module");
     }
 
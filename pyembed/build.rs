@@ -2,6 +2,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use pyo3_build_config::BuildFlag;
+
 fn main() {
     // We're always able to derive this. So always set it, even though it is likely
     // only used by test mode.
@@ -27,6 +29,27 @@ fn main() {
 
     let interpreter_config = pyo3_build_config::get();
 
+    // Emit Py_3_N cfgs so config fields only available on newer Python versions
+    // can be conditionally wired up.
+    interpreter_config.emit_pyo3_cfgs();
+    for minor in 6..=12 {
+        println!("cargo:rustc-check-cfg=cfg(Py_3_{})", minor);
+    }
+    println!("cargo:rustc-check-cfg=cfg(PyPy)");
+
+    // Free-threaded builds have a different object and PyConfig layout, which
+    // our PyO3 version doesn't know about. Building against one would produce
+    // a binary corrupting memory. So refuse to.
+    if interpreter_config
+        .build_flags
+        .0
+        .contains(&BuildFlag::Other("Py_GIL_DISABLED".to_string()))
+    {
+        panic!("free-threaded (Py_GIL_DISABLED) Python builds are not supported: the PyO3 version used by pyembed doesn't support their ABI");
+    }
+    println!("cargo:rustc-check-cfg=cfg(Py_LIMITED_API)");
+    println!("cargo:rustc-check-cfg=cfg(py_sys_config, values(any()))");
+
     // Re-export the path to the configured Python interpreter. Tests can
     // use this to derive a useful default config that leverages it.
     let python_interpreter = interpreter_config
@@ -7,15 +7,24 @@
 use {
     crate::NewInterpreterError,
     oxidized_importer::{PackedResourcesSource, PythonResourcesState},
-    pyo3::ffi as pyffi,
+    pyo3::{
+        ffi as pyffi,
+        types::{PyDict, PyTuple},
+        PyAny, PyObject, PyResult, Python,
+    },
     python_packaging::interpreter::{
-        MemoryAllocatorBackend, MultiprocessingStartMethod, PythonInterpreterConfig,
-        PythonInterpreterProfile, TerminfoResolution,
+        ImportPrecedence, MemoryAllocatorBackend, MultiprocessingStartMethod,
+        PythonInterpreterConfig, PythonInterpreterProfile, TerminfoResolution,
     },
+    python_packaging::resource::BytecodeOptimizationLevel,
     std::{
+        collections::BTreeMap,
         ffi::{CString, OsString},
+        fmt::{Debug, Formatter},
+        io::Write,
         ops::Deref,
-        path::PathBuf,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
     },
 };
 
@@ -35,6 +44,87 @@ pub struct ExtensionModule {
     pub init_func: unsafe extern "C" fn() -> *mut pyffi::PyObject,
 }
 
+/// A Rust function callable from Python.
+///
+/// Receives the positional and keyword arguments of the call.
+pub type RustFunction =
+    for<'py> fn(Python<'py>, &'py PyTuple, Option<&'py PyDict>) -> PyResult<PyObject>;
+
+/// Defines a Python module whose functions are implemented in Rust.
+///
+/// Unlike [ExtensionModule], no initialization function is needed: the
+/// module object is constructed from the functions during interpreter
+/// initialization.
+#[derive(Clone, Debug)]
+pub struct RustModule {
+    /// Name of the module.
+    pub name: String,
+
+    /// Functions to define on the module, keyed by attribute name.
+    pub functions: BTreeMap<String, RustFunction>,
+}
+
+/// A Rust function that handles unhandled Python exceptions.
+///
+/// Receives the exception type, value, and traceback, as passed to
+/// `sys.excepthook`.
+pub type ExceptHook = for<'py> fn(Python<'py>, &'py PyAny, &'py PyAny, &'py PyAny);
+
+/// A Rust function called after Python pre-initialization.
+///
+/// The Python C API isn't usable yet. But functions documented as safe to
+/// call before interpreter initialization, such as `PySys_AddAuditHook()`, are.
+pub type PreInitializeHook = fn() -> Result<(), NewInterpreterError>;
+
+/// A Rust function providing the key to decrypt packed resources with.
+///
+/// This allows the key to come from outside the binary, such as a hardware
+/// security module, an OS keychain, or a license server.
+pub type DecryptionKeyHook = fn() -> Result<[u8; 32], NewInterpreterError>;
+
+/// A Rust function called with the GIL held during interpreter initialization.
+pub type InitializeHook = for<'py> fn(Python<'py>) -> PyResult<()>;
+
+/// A Rust destination for text written to a Python stdio stream.
+///
+/// Wraps a [Write] implementation. Clones share the same underlying writer.
+#[derive(Clone)]
+pub struct OutputStream(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl OutputStream {
+    /// Construct an instance from a [Write] implementation.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(Box::new(writer))))
+    }
+
+    /// Write UTF-8 encoded text to the underlying writer.
+    pub fn write_str(&self, value: &str) -> std::io::Result<()> {
+        self.write_bytes(value.as_bytes())
+    }
+
+    /// Write bytes to the underlying writer.
+    pub fn write_bytes(&self, value: &[u8]) -> std::io::Result<()> {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write_all(value)
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).flush()
+    }
+}
+
+impl Debug for OutputStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OutputStream")
+    }
+}
+
+/// A Rust function called with the GIL held during interpreter finalization.
+pub type AtExitHook = for<'py> fn(Python<'py>) -> PyResult<()>;
+
 /// Configuration for a Python interpreter.
 ///
 /// This type is used to create a [crate::MainPythonInterpreter], which manages
@@ -268,6 +358,22 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// [`sys.path_hooks`]: https://docs.python.org/3/library/sys.html#sys.path_hooks
     pub filesystem_importer: bool,
 
+    /// Whether to import modules from a zip archive appended to the executable.
+    ///
+    /// This allows adding modules to a built binary, such as localization
+    /// packs, without rebuilding it and without files on the filesystem.
+    /// e.g. with Python's `zipfile.ZipFile(exe, "a")`. The offset of the
+    /// archive is discovered when the interpreter is initialized.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [true] and [Self::exe] ends with
+    /// a zip archive, an `OxidizedZipFinder` for the archive is appended to
+    /// `sys.meta_path`, making it a fallback for modules no other finder can
+    /// find. If [Self::exe] doesn't end with a zip archive, this value has no
+    /// effect. An error occurs if the `zipimport` crate feature isn't enabled.
+    pub appended_zip_importer: bool,
+
     /// References to packed resources data.
     ///
     /// The format of the data is defined by the ``python-packed-resources``
@@ -276,15 +382,26 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// `oxidized_importer=true`. If `oxidized_importer=false`, this field
     /// is ignored.
     ///
+    /// Sources are indexed in order and merged into a single index. When
+    /// multiple sources define a resource with the same name, fields set by a
+    /// later source overwrite those set by earlier sources. This allows e.g.
+    /// the standard library, the application, and optional feature packs to
+    /// be distributed as separate resources files, with later files overriding
+    /// resources of earlier ones.
+    ///
+    /// [PackedResourcesSource::CurrentExecutable] loads resources data injected
+    /// into the current executable after it was built. Placing it last allows
+    /// injected resources to override resources embedded at build time.
+    ///
     /// If paths are relative, that will be evaluated relative to the process's
     /// current working directory following the operating system's standard
     /// path expansion behavior.
     ///
     /// Default value: `vec![]`
     ///
-    /// [Self::resolve()] behavior: [PackedResourcesSource::MemoryMappedPath] members
-    /// have the special string `$ORIGIN` expanded to the string value that
-    /// [Self::origin] resolves to.
+    /// [Self::resolve()] behavior: [PackedResourcesSource::MemoryMappedPath] and
+    /// [PackedResourcesSource::Path] members have the special string `$ORIGIN`
+    /// expanded to the string value that [Self::origin] resolves to.
     ///
     /// This field is ignored during serialization.
     #[cfg_attr(feature = "serialization", serde(skip))]
@@ -304,6 +421,19 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     #[cfg_attr(feature = "serialization", serde(skip))]
     pub extra_extension_modules: Option<Vec<ExtensionModule>>,
 
+    /// Modules implemented by Rust functions to make available to the interpreter.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Interpreter initialization behavior: after core initialization and
+    /// before main initialization, a module object is created for each entry
+    /// and registered in `sys.modules`, making it importable by name. Each
+    /// function is exposed as a callable attribute of the module.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub rust_modules: Vec<RustModule>,
+
     /// Command line arguments to initialize `sys.argv` with.
     ///
     /// Default value: [None]
@@ -312,8 +442,12 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// [PythonInterpreterConfig::argv] is used if set. Otherwise
     /// [std::env::args_os()] is called.
     ///
+    /// Embedders parsing their own command line can set this to pass only the
+    /// remaining arguments to Python. The first element should still be the
+    /// executable.
+    ///
     /// Interpreter initialization behavior: the resolved [Some] value is used
-    /// to populate `PyConfig.argv`.
+    /// to populate `PyConfig.argv` and [Self::argvb].
     pub argv: Option<Vec<OsString>>,
 
     /// Whether to set `sys.argvb` with bytes versions of process arguments.
@@ -336,6 +470,74 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// of elements.
     pub argvb: bool,
 
+    /// Path to a PEP 441 zip application (`.pyz` file) to execute.
+    ///
+    /// This emulates `python app.pyz`: the archive is prepended to `sys.path`
+    /// and its `__main__` module is executed.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: the special string `$ORIGIN` is expanded to the
+    /// string value that [Self::origin] resolves to. The result is stored in
+    /// [PythonInterpreterConfig::run_filename], which causes the interpreter to
+    /// run the archive, and replaces the first element of the resolved `sys.argv`.
+    /// An error is returned if another of [PythonInterpreterConfig::run_command],
+    /// [PythonInterpreterConfig::run_module], or [PythonInterpreterConfig::run_filename]
+    /// is set or if [Self::filesystem_importer] is disabled, as the archive is
+    /// imported using the standard library's `zipimport` path hook.
+    pub run_zipapp: Option<PathBuf>,
+
+    /// Name of a module in [Self::packed_resources] to execute as `__main__`.
+    ///
+    /// The module's bytecode is loaded directly from the indexed resources and
+    /// executed in the `__main__` module's namespace, bypassing the import
+    /// machinery. If only source is available, it is compiled first. This can
+    /// shave startup overhead for small tools.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: an error is returned if another of
+    /// [PythonInterpreterConfig::run_command], [PythonInterpreterConfig::run_module],
+    /// [PythonInterpreterConfig::run_filename], or [Self::run_zipapp] is set.
+    ///
+    /// When set, [crate::MainPythonInterpreter::py_runmain()] executes the module
+    /// via [crate::MainPythonInterpreter::run_outcome()] instead of `Py_RunMain()`.
+    pub run_frozen_main: Option<String>,
+
+    /// Busybox-style mapping of command names to modules to run.
+    ///
+    /// This allows a single executable to provide multiple commands, with each
+    /// command name being a hard link or symlink to the executable.
+    ///
+    /// Default value: empty
+    ///
+    /// [Self::resolve()] behavior: if the file name of `sys.argv[0]` (without the
+    /// platform's executable suffix) is a key, its value is stored in
+    /// [PythonInterpreterConfig::run_module]. Otherwise, if `sys.argv[1]` is a key,
+    /// it is removed from `sys.argv` and its value is used the same way, allowing
+    /// invocations like `tool <command> [args]`. A match replaces any other
+    /// configured run mode. If nothing matches, the configured run mode is used.
+    pub multicall: BTreeMap<String, String>,
+
+    /// Python source code to execute before the configured run mode.
+    ///
+    /// Entries are executed in order in the `__main__` module's namespace by
+    /// [crate::MainPythonInterpreter::py_runmain()] before the interpreter runs
+    /// whatever [PythonInterpreterConfig::run_command],
+    /// [PythonInterpreterConfig::run_module], or [PythonInterpreterConfig::run_filename]
+    /// define (and before entering the REPL if [PythonInterpreterConfig::inspect]
+    /// is set).
+    ///
+    /// Each entry is compiled as its own unit with the filename `<startup-N>`, so
+    /// tracebacks report accurate line numbers. If an entry raises, the exception
+    /// is printed, subsequent entries and the run mode are not executed, and the
+    /// interpreter exits with code `1`. If an entry raises `SystemExit` (e.g.
+    /// via `sys.exit()`), nothing is printed and the interpreter exits with the
+    /// code it carries, as it would for the run mode.
+    ///
+    /// Default value: `vec![]`
+    pub startup_commands: Vec<String>,
+
     /// Automatically detect and run in `multiprocessing` mode.
     ///
     /// If set, [crate::MainPythonInterpreter::run()] will detect when the invoked
@@ -358,6 +560,100 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// when `multiprocessing` is imported. If [false], this value has no effect.
     pub multiprocessing_start_method: MultiprocessingStartMethod,
 
+    /// Whether to set `__file__` on modules imported from memory.
+    ///
+    /// Modules imported from memory don't have a `__file__` by default. Setting
+    /// this gives Python source and bytecode modules a `__file__` under the
+    /// current executable, as if it were a directory. e.g.
+    /// `/usr/bin/myapp/foo/bar.py` for `foo.bar`. The path doesn't exist on the
+    /// filesystem. But it helps code that requires `__file__` to be defined.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [Self::oxidized_importer] is [true],
+    /// the `OxidizedImporter` will set `ModuleSpec.origin` for modules imported
+    /// from memory. If [false], this value has no effect.
+    pub synthesize_dunder_file: bool,
+
+    /// Where to look for the modules of packages, keyed by package name.
+    ///
+    /// By default, modules indexed by `OxidizedFinder` take precedence over
+    /// modules on the filesystem. This allows overriding that for a package and
+    /// its sub-packages. e.g. to import a newer copy of an embedded package from
+    /// a plugins directory on `sys.path`, or to prevent a package from ever being
+    /// imported from the filesystem.
+    ///
+    /// Default value: empty
+    ///
+    /// Interpreter initialization behavior: if [Self::oxidized_importer] is [true],
+    /// the `OxidizedImporter` will be taught the precedence of each package. If
+    /// [false], this value has no effect.
+    pub package_precedence: BTreeMap<String, ImportPrecedence>,
+
+    /// Whether `importlib.reload()` prefers modules on the filesystem.
+    ///
+    /// By default, reloading a module imported from memory re-executes its
+    /// indexed source or bytecode. Setting this makes `importlib.reload()` look
+    /// for the module on the filesystem first, falling back to memory. This
+    /// allows iterating on a copy of a module during interactive use of a
+    /// built binary. Packages with [ImportPrecedence::MemoryOnly] are always
+    /// reloaded from memory.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [Self::oxidized_importer] is [true],
+    /// the `OxidizedImporter` will look for reloaded modules on the filesystem.
+    /// If [false], this value has no effect.
+    pub reload_from_filesystem: bool,
+
+    /// Whether to verify digests of embedded resources data.
+    ///
+    /// Packed resources produced by PyOxidizer record a SHA-256 digest of the
+    /// in-memory data of each resource. When set, the data of a resource is
+    /// verified against its digest the first time the resource is accessed.
+    /// Corrupted data results in an error instead of being used.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [Self::oxidized_importer] is [true],
+    /// the `OxidizedImporter` will verify resources it loads. If [false], this value
+    /// has no effect.
+    pub verify_resources: bool,
+
+    /// ed25519 public key that packed resources data must be signed with.
+    ///
+    /// When set, the signature of each entry in [Self::packed_resources] is
+    /// verified before it is indexed. Data that isn't signed by the
+    /// corresponding secret key results in an error during interpreter
+    /// initialization. PyOxidizer bakes the public key into the binary when
+    /// building with a packed resources signing key.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if [Self::oxidized_importer] is [true],
+    /// packed resources are verified before use. If [false], this value has no effect.
+    pub packed_resources_public_key: Option<[u8; 32]>,
+
+    /// A Rust function providing the key to decrypt encrypted packed resources with.
+    ///
+    /// Packed resources can have their in-memory data encrypted at build time.
+    /// Encrypted data is decrypted with the returned key the first time a
+    /// resource is accessed. Without a key, accessing encrypted resources fails.
+    ///
+    /// Note that the decrypted data is held in process memory. So this protects
+    /// resources at rest, not from someone able to inspect the running process.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if [Self::oxidized_importer] is [true]
+    /// and this is [Some], the function is called when packed resources are
+    /// indexed and an error aborts interpreter initialization. If [false], this
+    /// value has no effect.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub packed_resources_decryption_key_hook: Option<DecryptionKeyHook>,
+
     /// Whether to set sys.frozen=True.
     ///
     /// Setting this will enable Python to emulate "frozen" binaries, such as
@@ -383,6 +679,196 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// `sys._MEIPASS` will not be defined.
     pub sys_meipass: bool,
 
+    /// Whether to print unhandled exceptions with the `traceback` module.
+    ///
+    /// Python's default `sys.excepthook` reads source lines of tracebacks from
+    /// files on the filesystem. So tracebacks through modules imported from
+    /// memory display file names and line numbers without source lines. The
+    /// `traceback` module obtains source lines via `linecache`, which asks the
+    /// module's loader, allowing `OxidizedFinder` to provide embedded source.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [true] and [Self::excepthook] is
+    /// [None], `sys.excepthook` is set to a callable calling
+    /// `traceback.print_exception()`. Code replacing `sys.excepthook` itself,
+    /// such as crash reporters, is unaffected either way.
+    pub traceback_excepthook: bool,
+
+    /// A Rust function to install as `sys.excepthook`.
+    ///
+    /// Python calls `sys.excepthook` with the type, value, and traceback of
+    /// exceptions that aren't handled, such as those raised by the code
+    /// run by [crate::MainPythonInterpreter::py_runmain()]. This allows routing
+    /// them to Rust code, such as a crash reporter, as structured objects rather
+    /// than text written to stderr. The function replaces the default hook, which
+    /// remains available as `sys.__excepthook__`.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if [Some], `sys.excepthook` is set
+    /// to a callable that invokes the function. This takes precedence over
+    /// [Self::traceback_excepthook].
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub excepthook: Option<ExceptHook>,
+
+    /// A Rust function to call after `Py_PreInitialize()`.
+    ///
+    /// This runs after the memory allocators are configured and before the
+    /// core of the interpreter is initialized. It can be used to register audit
+    /// hooks that observe everything the interpreter does.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if [Some], the function is called
+    /// and an error aborts interpreter initialization.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub pre_initialize_hook: Option<PreInitializeHook>,
+
+    /// A Rust function to call after core initialization of the interpreter.
+    ///
+    /// This runs after `Py_InitializeFromConfig()` and the installation of
+    /// the custom importer but before `_Py_InitializeMain()`. `sys` and
+    /// `builtins` are available but only built-in and frozen modules can be
+    /// imported. It can be used to inject builtins or tweak `sys` attributes
+    /// before `site`, `sys.path` importers, and other modules are initialized.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if [Some], the function is called
+    /// with the GIL held and an error aborts interpreter initialization.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub core_initialize_hook: Option<InitializeHook>,
+
+    /// A Rust function to call after the interpreter is fully initialized.
+    ///
+    /// This runs after all other initialization performed by this crate and
+    /// before any code is run by [crate::MainPythonInterpreter::py_runmain()]
+    /// and similar functions.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if [Some], the function is called
+    /// with the GIL held and an error aborts interpreter initialization.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub post_initialize_hook: Option<InitializeHook>,
+
+    /// Whether `SIGINT` and `SIGTERM` are handled by Rust instead of Python.
+    ///
+    /// By default, Python installs its own `SIGINT` handler (subject to
+    /// [PythonInterpreterConfig::install_signal_handlers]), taking over
+    /// Ctrl-C handling from the host application.
+    ///
+    /// When enabled, pyembed installs handlers for `SIGINT` and `SIGTERM` that
+    /// record the signal and raise `KeyboardInterrupt` in Python, allowing the
+    /// running Python code to unwind gracefully. The most recently received
+    /// signal can be obtained via [crate::MainPythonInterpreter::received_signal()].
+    /// The previous handlers are restored when the interpreter is finalized.
+    ///
+    /// Python code calling `signal.signal()` can still replace the handlers.
+    ///
+    /// Default value: [false]
+    ///
+    /// [Self::resolve()] behavior: if [true],
+    /// [PythonInterpreterConfig::install_signal_handlers] is set to `Some(false)`.
+    pub rust_signal_handlers: bool,
+
+    /// Rust functions to call when the interpreter is finalized.
+    ///
+    /// The functions are called by `Py_FinalizeEx()` after all functions
+    /// registered with Python's `atexit` module have run, while the interpreter
+    /// is still fully functional. This is the last point at which Rust code can
+    /// access Python objects, e.g. to flush telemetry referencing Python data.
+    ///
+    /// Functions are called in order. Errors are printed and don't prevent
+    /// subsequent functions from being called.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Interpreter initialization behavior: if non-empty, a function calling
+    /// them is registered with `atexit` before any other code can register
+    /// functions. Since `atexit` calls functions in reverse order of registration,
+    /// it runs last.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub atexit_hooks: Vec<AtExitHook>,
+
+    /// Rust destination for text written to `sys.stdout`.
+    ///
+    /// By default, `sys.stdout` writes to the process's standard output handle.
+    /// Windows applications without a console don't have one, so Python output
+    /// is lost. When set, `sys.stdout` is replaced by a text stream that writes
+    /// UTF-8 encoded text to the Rust writer, such as a file, channel, or log
+    /// sink.
+    ///
+    /// Only output written via `sys.stdout` is captured. Output written
+    /// directly to the file descriptor, e.g. by C code or subprocesses, is not.
+    /// Bytes written to the stream's binary `buffer` attribute are written to
+    /// the Rust writer as is.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if [Some], `sys.stdout` is replaced
+    /// after the interpreter is initialized. `sys.__stdout__` is not modified.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub stdout_writer: Option<OutputStream>,
+
+    /// Rust destination for text written to `sys.stderr`.
+    ///
+    /// This is like [Self::stdout_writer] except for `sys.stderr`. Tracebacks
+    /// of unhandled exceptions are written to `sys.stderr`.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if [Some], `sys.stderr` is replaced
+    /// after the interpreter is initialized. `sys.__stderr__` is not modified.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub stderr_writer: Option<OutputStream>,
+
+    /// Whether to enable line editing and tab completion in the interactive REPL.
+    ///
+    /// CPython's `site` module only configures `readline` for the REPL when not
+    /// running in isolated mode. When this is set, `sys.__interactivehook__` is
+    /// replaced with a hook that enables `readline` tab completion regardless.
+    /// Completion covers Python names via `rlcompleter` and, after `import` or
+    /// `from`, the names of modules discoverable via `pkgutil.iter_modules()`,
+    /// which includes modules in [Self::packed_resources] when
+    /// [Self::oxidized_importer] is enabled.
+    ///
+    /// The hook silently does nothing if the `readline` module isn't available.
+    /// This is the case on Windows, where the standard library doesn't provide
+    /// one: the REPL only gets the console's native line editing, there is no
+    /// tab completion, and [Self::repl_history_file] is ignored. A `readline`
+    /// compatible package, such as `pyreadline3`, is used if it is importable.
+    ///
+    /// Default value: [false]
+    pub repl_line_editing: bool,
+
+    /// File to load and save interactive REPL history from.
+    ///
+    /// Only used if [Self::repl_line_editing] is enabled and the `readline`
+    /// module is available, which it isn't on Windows by default. History is
+    /// read when the REPL starts and written when the interpreter exits.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: the special string `$ORIGIN` is expanded to the
+    /// string value that [Self::origin] resolves to.
+    pub repl_history_file: Option<PathBuf>,
+
     /// How to resolve the `terminfo` database.
     ///
     /// Default value: [TerminfoResolution::Dynamic]
@@ -443,14 +929,37 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
             set_missing_path_configuration: true,
             oxidized_importer: false,
             filesystem_importer: true,
+            appended_zip_importer: false,
             packed_resources: vec![],
             extra_extension_modules: None,
+            rust_modules: vec![],
             argv: None,
             argvb: false,
+            run_zipapp: None,
+            run_frozen_main: None,
+            multicall: BTreeMap::new(),
+            startup_commands: vec![],
             multiprocessing_auto_dispatch: true,
             multiprocessing_start_method: MultiprocessingStartMethod::Auto,
+            synthesize_dunder_file: false,
+            package_precedence: BTreeMap::new(),
+            reload_from_filesystem: false,
+            verify_resources: false,
+            packed_resources_public_key: None,
+            packed_resources_decryption_key_hook: None,
             sys_frozen: false,
             sys_meipass: false,
+            traceback_excepthook: false,
+            excepthook: None,
+            pre_initialize_hook: None,
+            core_initialize_hook: None,
+            post_initialize_hook: None,
+            rust_signal_handlers: false,
+            atexit_hooks: vec![],
+            stdout_writer: None,
+            stderr_writer: None,
+            repl_line_editing: false,
+            repl_history_file: None,
             terminfo_resolution: TerminfoResolution::Dynamic,
             tcl_library: None,
             write_modules_directory_env: None,
@@ -459,11 +968,111 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
 }
 
 impl<'a> OxidizedPythonInterpreterConfig<'a> {
+    /// Obtain a [OxidizedPythonInterpreterConfigBuilder] seeded with default values.
+    pub fn builder() -> OxidizedPythonInterpreterConfigBuilder<'a> {
+        OxidizedPythonInterpreterConfigBuilder::default()
+    }
+
+    /// Apply overrides defined by environment variables having the given prefix.
+    ///
+    /// This allows operators to tune an interpreter in the field without
+    /// rebuilding the binary embedding it. Unlike Python's own `PYTHON*`
+    /// environment variables, these variables are honored regardless of
+    /// [PythonInterpreterConfig::use_environment] and [PythonInterpreterConfig::isolated],
+    /// since the application opts into them by calling this function.
+    ///
+    /// The following variables are recognized (shown with a prefix of `MYAPP_PYTHON_`):
+    ///
+    /// `MYAPP_PYTHON_OPT_LEVEL`
+    ///    Sets [PythonInterpreterConfig::optimization_level]. Must be `0`, `1`, or `2`.
+    ///
+    /// `MYAPP_PYTHON_SYS_PATHS`
+    ///    Paths to append to [PythonInterpreterConfig::module_search_paths]. Multiple
+    ///    paths are delimited by the platform's path separator (`:` on UNIX and `;`
+    ///    on Windows). The special token `$ORIGIN` is expanded during [Self::resolve()].
+    ///    Enables [Self::filesystem_importer], as entries would be ignored otherwise.
+    ///
+    /// `MYAPP_PYTHON_UNBUFFERED`
+    ///    Boolean. If true, [PythonInterpreterConfig::buffered_stdio] is disabled.
+    ///
+    /// `MYAPP_PYTHON_VERBOSE`
+    ///    Boolean. Sets [PythonInterpreterConfig::verbose].
+    ///
+    /// `MYAPP_PYTHON_DONT_WRITE_BYTECODE`
+    ///    Boolean. If true, [PythonInterpreterConfig::write_bytecode] is disabled.
+    ///
+    /// Boolean values are `1`, `true`, or `yes` for true and `0`, `false`, `no`, or
+    /// an empty string for false. Unset variables leave the config untouched.
+    /// An error is returned if a variable has an invalid value.
+    pub fn apply_environment_overrides(&mut self, prefix: &str) -> Result<(), NewInterpreterError> {
+        let var = |name: &str| std::env::var_os(format!("{}{}", prefix, name));
+
+        let bool_var = |name: &str| -> Result<Option<bool>, NewInterpreterError> {
+            if let Some(value) = var(name) {
+                match value.to_string_lossy().to_lowercase().as_str() {
+                    "1" | "true" | "yes" => Ok(Some(true)),
+                    "" | "0" | "false" | "no" => Ok(Some(false)),
+                    other => Err(NewInterpreterError::Dynamic(format!(
+                        "invalid boolean value for {}{}: {}",
+                        prefix, name, other
+                    ))),
+                }
+            } else {
+                Ok(None)
+            }
+        };
+
+        if let Some(value) = var("OPT_LEVEL") {
+            let level = value
+                .to_string_lossy()
+                .parse::<i32>()
+                .map_err(|_| ())
+                .and_then(|v| BytecodeOptimizationLevel::try_from(v).map_err(|_| ()))
+                .map_err(|_| {
+                    NewInterpreterError::Dynamic(format!(
+                        "invalid value for {}OPT_LEVEL: {}",
+                        prefix,
+                        value.to_string_lossy()
+                    ))
+                })?;
+
+            self.interpreter_config.optimization_level = Some(level);
+        }
+
+        if let Some(value) = var("SYS_PATHS") {
+            let paths = std::env::split_paths(&value)
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect::<Vec<_>>();
+
+            if !paths.is_empty() {
+                self.interpreter_config
+                    .module_search_paths
+                    .get_or_insert_with(Vec::new)
+                    .extend(paths);
+                self.filesystem_importer = true;
+            }
+        }
+
+        if let Some(value) = bool_var("UNBUFFERED")? {
+            self.interpreter_config.buffered_stdio = Some(!value);
+        }
+
+        if let Some(value) = bool_var("VERBOSE")? {
+            self.interpreter_config.verbose = Some(value);
+        }
+
+        if let Some(value) = bool_var("DONT_WRITE_BYTECODE")? {
+            self.interpreter_config.write_bytecode = Some(!value);
+        }
+
+        Ok(())
+    }
+
     /// Create a new type with all values resolved.
     pub fn resolve(
         self,
     ) -> Result<ResolvedOxidizedPythonInterpreterConfig<'a>, NewInterpreterError> {
-        let argv = if let Some(args) = self.argv {
+        let mut argv = if let Some(args) = self.argv {
             Some(args)
         } else if self.interpreter_config.argv.is_some() {
             None
@@ -500,12 +1109,17 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
             .packed_resources
             .into_iter()
             .map(|entry| match entry {
-                PackedResourcesSource::Memory(_) => entry,
+                PackedResourcesSource::Memory(_) | PackedResourcesSource::CurrentExecutable => {
+                    entry
+                }
                 PackedResourcesSource::MemoryMappedPath(p) => {
                     PackedResourcesSource::MemoryMappedPath(PathBuf::from(
                         p.display().to_string().replace("$ORIGIN", &origin_string),
                     ))
                 }
+                PackedResourcesSource::Path(p) => PackedResourcesSource::Path(PathBuf::from(
+                    p.display().to_string().replace("$ORIGIN", &origin_string),
+                )),
             })
             .collect::<Vec<_>>();
 
@@ -526,47 +1140,677 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
             .as_ref()
             .map(|x| PathBuf::from(x.display().to_string().replace("$ORIGIN", &origin_string)));
 
+        let repl_history_file = self
+            .repl_history_file
+            .as_ref()
+            .map(|x| PathBuf::from(x.display().to_string().replace("$ORIGIN", &origin_string)));
+
+        let mut run_zipapp = self
+            .run_zipapp
+            .as_ref()
+            .map(|x| PathBuf::from(x.display().to_string().replace("$ORIGIN", &origin_string)));
+
+        let mut run_command = self.interpreter_config.run_command.clone();
+        let mut run_module = self.interpreter_config.run_module.clone();
+        let mut run_filename = self.interpreter_config.run_filename.clone();
+        let mut run_frozen_main = self.run_frozen_main.clone();
+
+        if !self.multicall.is_empty() {
+            let mut args = argv
+                .take()
+                .or_else(|| self.interpreter_config.argv.clone())
+                .unwrap_or_default();
+
+            let program = args
+                .first()
+                .and_then(|arg| Path::new(arg).file_name())
+                .and_then(|name| name.to_str())
+                .map(|name| {
+                    name.strip_suffix(std::env::consts::EXE_SUFFIX)
+                        .unwrap_or(name)
+                });
+
+            let module = if let Some(module) = program.and_then(|name| self.multicall.get(name)) {
+                Some(module.clone())
+            } else if let Some(module) = args
+                .get(1)
+                .and_then(|arg| arg.to_str())
+                .and_then(|name| self.multicall.get(name))
+            {
+                let module = module.clone();
+                args.remove(1);
+                Some(module)
+            } else {
+                None
+            };
+
+            if let Some(module) = module {
+                run_command = None;
+                run_module = Some(module);
+                run_filename = None;
+                run_zipapp = None;
+                run_frozen_main = None;
+            }
+
+            argv = Some(args);
+        }
+
+        if let Some(zipapp) = &run_zipapp {
+            if run_command.is_some() || run_module.is_some() || run_filename.is_some() {
+                return Err(NewInterpreterError::Simple(
+                    "run_zipapp cannot be combined with run_command, run_module, or run_filename",
+                ));
+            }
+
+            if !self.filesystem_importer {
+                return Err(NewInterpreterError::Simple(
+                    "run_zipapp requires filesystem_importer to be enabled",
+                ));
+            }
+
+            // Like `python app.pyz`, sys.argv[0] is the path to the archive.
+            let mut args = argv
+                .take()
+                .or_else(|| self.interpreter_config.argv.clone())
+                .unwrap_or_default();
+            if args.is_empty() {
+                args.push(zipapp.clone().into_os_string());
+            } else {
+                args[0] = zipapp.clone().into_os_string();
+            }
+            argv = Some(args);
+
+            run_filename = Some(zipapp.clone());
+        }
+
+        if run_frozen_main.is_some()
+            && (run_command.is_some() || run_module.is_some() || run_filename.is_some())
+        {
+            return Err(NewInterpreterError::Simple(
+                "run_frozen_main cannot be combined with run_command, run_module, run_filename, or run_zipapp",
+            ));
+        }
+
         Ok(ResolvedOxidizedPythonInterpreterConfig {
             inner: Self {
                 exe: Some(exe),
                 origin: Some(origin),
                 interpreter_config: PythonInterpreterConfig {
                     module_search_paths,
+                    install_signal_handlers: if self.rust_signal_handlers {
+                        Some(false)
+                    } else {
+                        self.interpreter_config.install_signal_handlers
+                    },
+                    run_command,
+                    run_filename,
+                    run_module,
                     ..self.interpreter_config
                 },
                 argv,
                 packed_resources,
                 tcl_library,
+                repl_history_file,
+                run_zipapp,
+                run_frozen_main,
                 ..self
             },
         })
     }
 }
 
-/// An `OxidizedPythonInterpreterConfig` that has fields resolved.
-pub struct ResolvedOxidizedPythonInterpreterConfig<'a> {
-    inner: OxidizedPythonInterpreterConfig<'a>,
+/// Builder type to construct [OxidizedPythonInterpreterConfig] instances.
+///
+/// Instances can be obtained via [OxidizedPythonInterpreterConfig::builder()],
+/// which is seeded from [OxidizedPythonInterpreterConfig::default()], or via one
+/// of the preset constructors like [Self::isolated()] and
+/// [Self::standalone_application()].
+///
+/// Setters can be chained. [Self::build()] validates the combination of
+/// settings and rejects configurations that are known to be invalid or to
+/// silently not do what was requested.
+#[derive(Clone, Debug, Default)]
+pub struct OxidizedPythonInterpreterConfigBuilder<'a> {
+    config: OxidizedPythonInterpreterConfig<'a>,
 }
 
-impl<'a> Deref for ResolvedOxidizedPythonInterpreterConfig<'a> {
-    type Target = OxidizedPythonInterpreterConfig<'a>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+impl<'a> From<OxidizedPythonInterpreterConfig<'a>> for OxidizedPythonInterpreterConfigBuilder<'a> {
+    fn from(config: OxidizedPythonInterpreterConfig<'a>) -> Self {
+        Self { config }
     }
 }
 
-impl<'a> TryFrom<OxidizedPythonInterpreterConfig<'a>>
-    for ResolvedOxidizedPythonInterpreterConfig<'a>
-{
-    type Error = NewInterpreterError;
+impl<'a> OxidizedPythonInterpreterConfigBuilder<'a> {
+    /// Construct an instance seeded from [OxidizedPythonInterpreterConfig::default()].
+    ///
+    /// The interpreter behaves like `python` and is not isolated from the
+    /// environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    fn try_from(value: OxidizedPythonInterpreterConfig<'a>) -> Result<Self, Self::Error> {
-        value.resolve()
+    /// Construct an instance using the isolated interpreter profile.
+    ///
+    /// The interpreter is isolated from the system: environment variables,
+    /// the user site directory, and `sys.argv` are not consulted to
+    /// configure the interpreter.
+    ///
+    /// `configure_locale` is enabled, as isolated mode otherwise disables it and
+    /// this breaks the decoding of non-ASCII arguments.
+    pub fn isolated() -> Self {
+        Self::new()
+            .profile(PythonInterpreterProfile::Isolated)
+            .configure_locale(true)
     }
-}
 
-impl<'a> ResolvedOxidizedPythonInterpreterConfig<'a> {
+    /// Construct an instance suitable for a self-contained application.
+    ///
+    /// This is an isolated interpreter (see [Self::isolated()]) which imports
+    /// modules via `oxidized_importer` and not from the filesystem and which
+    /// sets `sys.frozen`. This mirrors the defaults used by PyOxidizer when it
+    /// produces executables.
+    pub fn standalone_application() -> Self {
+        Self::isolated()
+            .oxidized_importer(true)
+            .filesystem_importer(false)
+            .sys_frozen(true)
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::exe].
+    #[must_use]
+    pub fn exe(mut self, value: impl AsRef<Path>) -> Self {
+        self.config.exe = Some(value.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::origin].
+    #[must_use]
+    pub fn origin(mut self, value: impl AsRef<Path>) -> Self {
+        self.config.origin = Some(value.as_ref().to_path_buf());
+        self
+    }
+
+    /// Replace [OxidizedPythonInterpreterConfig::interpreter_config].
+    #[must_use]
+    pub fn interpreter_config(mut self, value: PythonInterpreterConfig) -> Self {
+        self.config.interpreter_config = value;
+        self
+    }
+
+    /// Set [PythonInterpreterConfig::profile].
+    #[must_use]
+    pub fn profile(mut self, value: PythonInterpreterProfile) -> Self {
+        self.config.interpreter_config.profile = value;
+        self
+    }
+
+    /// Set [PythonInterpreterConfig::configure_locale].
+    #[must_use]
+    pub fn configure_locale(mut self, value: bool) -> Self {
+        self.config.interpreter_config.configure_locale = Some(value);
+        self
+    }
+
+    /// Set [PythonInterpreterConfig::module_search_paths].
+    #[must_use]
+    pub fn module_search_paths(
+        mut self,
+        paths: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Self {
+        self.config.interpreter_config.module_search_paths = Some(
+            paths
+                .into_iter()
+                .map(|p| p.as_ref().to_path_buf())
+                .collect(),
+        );
+        self
+    }
+
+    /// Append an entry to [PythonInterpreterConfig::module_search_paths].
+    #[must_use]
+    pub fn module_search_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.config
+            .interpreter_config
+            .module_search_paths
+            .get_or_insert_with(Vec::new)
+            .push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set [PythonInterpreterConfig::run_command].
+    #[must_use]
+    pub fn run_command(mut self, value: impl ToString) -> Self {
+        self.config.interpreter_config.run_command = Some(value.to_string());
+        self
+    }
+
+    /// Set [PythonInterpreterConfig::run_module].
+    #[must_use]
+    pub fn run_module(mut self, value: impl ToString) -> Self {
+        self.config.interpreter_config.run_module = Some(value.to_string());
+        self
+    }
+
+    /// Set [PythonInterpreterConfig::run_filename].
+    #[must_use]
+    pub fn run_filename(mut self, value: impl AsRef<Path>) -> Self {
+        self.config.interpreter_config.run_filename = Some(value.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::allocator_backend].
+    #[must_use]
+    pub fn allocator_backend(mut self, value: MemoryAllocatorBackend) -> Self {
+        self.config.allocator_backend = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::allocator_raw].
+    #[must_use]
+    pub fn allocator_raw(mut self, value: bool) -> Self {
+        self.config.allocator_raw = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::allocator_mem].
+    #[must_use]
+    pub fn allocator_mem(mut self, value: bool) -> Self {
+        self.config.allocator_mem = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::allocator_obj].
+    #[must_use]
+    pub fn allocator_obj(mut self, value: bool) -> Self {
+        self.config.allocator_obj = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::allocator_pymalloc_arena].
+    #[must_use]
+    pub fn allocator_pymalloc_arena(mut self, value: bool) -> Self {
+        self.config.allocator_pymalloc_arena = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::allocator_debug].
+    #[must_use]
+    pub fn allocator_debug(mut self, value: bool) -> Self {
+        self.config.allocator_debug = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::set_missing_path_configuration].
+    #[must_use]
+    pub fn set_missing_path_configuration(mut self, value: bool) -> Self {
+        self.config.set_missing_path_configuration = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::oxidized_importer].
+    #[must_use]
+    pub fn oxidized_importer(mut self, value: bool) -> Self {
+        self.config.oxidized_importer = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::filesystem_importer].
+    #[must_use]
+    pub fn filesystem_importer(mut self, value: bool) -> Self {
+        self.config.filesystem_importer = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::appended_zip_importer].
+    #[must_use]
+    pub fn appended_zip_importer(mut self, value: bool) -> Self {
+        self.config.appended_zip_importer = value;
+        self
+    }
+
+    /// Append an entry to [OxidizedPythonInterpreterConfig::packed_resources].
+    #[must_use]
+    pub fn packed_resources(mut self, source: PackedResourcesSource<'a>) -> Self {
+        self.config.packed_resources.push(source);
+        self
+    }
+
+    /// Append an entry to [OxidizedPythonInterpreterConfig::extra_extension_modules].
+    #[must_use]
+    pub fn extra_extension_module(mut self, module: ExtensionModule) -> Self {
+        self.config
+            .extra_extension_modules
+            .get_or_insert_with(Vec::new)
+            .push(module);
+        self
+    }
+
+    /// Append an entry to [OxidizedPythonInterpreterConfig::rust_modules].
+    #[must_use]
+    pub fn rust_module(mut self, module: RustModule) -> Self {
+        self.config.rust_modules.push(module);
+        self
+    }
+
+    /// Define a function on a module in [OxidizedPythonInterpreterConfig::rust_modules].
+    ///
+    /// The module is added if it isn't already defined.
+    #[must_use]
+    pub fn rust_function(
+        mut self,
+        module: impl ToString,
+        name: impl ToString,
+        function: RustFunction,
+    ) -> Self {
+        let module = module.to_string();
+
+        let index = match self
+            .config
+            .rust_modules
+            .iter()
+            .position(|m| m.name == module)
+        {
+            Some(index) => index,
+            None => {
+                self.config.rust_modules.push(RustModule {
+                    name: module,
+                    functions: BTreeMap::new(),
+                });
+                self.config.rust_modules.len() - 1
+            }
+        };
+
+        self.config.rust_modules[index]
+            .functions
+            .insert(name.to_string(), function);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::argv].
+    #[must_use]
+    pub fn argv(mut self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        self.config.argv = Some(args.into_iter().map(|x| x.into()).collect());
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::argvb].
+    #[must_use]
+    pub fn argvb(mut self, value: bool) -> Self {
+        self.config.argvb = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::run_zipapp].
+    #[must_use]
+    pub fn run_zipapp(mut self, value: impl AsRef<Path>) -> Self {
+        self.config.run_zipapp = Some(value.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::run_frozen_main].
+    #[must_use]
+    pub fn run_frozen_main(mut self, value: impl ToString) -> Self {
+        self.config.run_frozen_main = Some(value.to_string());
+        self
+    }
+
+    /// Add an entry to [OxidizedPythonInterpreterConfig::multicall].
+    #[must_use]
+    pub fn multicall(mut self, command: impl ToString, module: impl ToString) -> Self {
+        self.config
+            .multicall
+            .insert(command.to_string(), module.to_string());
+        self
+    }
+
+    /// Append an entry to [OxidizedPythonInterpreterConfig::startup_commands].
+    #[must_use]
+    pub fn startup_command(mut self, source: impl ToString) -> Self {
+        self.config.startup_commands.push(source.to_string());
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::multiprocessing_auto_dispatch].
+    #[must_use]
+    pub fn multiprocessing_auto_dispatch(mut self, value: bool) -> Self {
+        self.config.multiprocessing_auto_dispatch = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::multiprocessing_start_method].
+    #[must_use]
+    pub fn multiprocessing_start_method(mut self, value: MultiprocessingStartMethod) -> Self {
+        self.config.multiprocessing_start_method = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::synthesize_dunder_file].
+    #[must_use]
+    pub fn synthesize_dunder_file(mut self, value: bool) -> Self {
+        self.config.synthesize_dunder_file = value;
+        self
+    }
+
+    /// Add an entry to [OxidizedPythonInterpreterConfig::package_precedence].
+    #[must_use]
+    pub fn package_precedence(mut self, package: impl ToString, value: ImportPrecedence) -> Self {
+        self.config
+            .package_precedence
+            .insert(package.to_string(), value);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::reload_from_filesystem].
+    #[must_use]
+    pub fn reload_from_filesystem(mut self, value: bool) -> Self {
+        self.config.reload_from_filesystem = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::verify_resources].
+    #[must_use]
+    pub fn verify_resources(mut self, value: bool) -> Self {
+        self.config.verify_resources = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::packed_resources_public_key].
+    #[must_use]
+    pub fn packed_resources_public_key(mut self, key: [u8; 32]) -> Self {
+        self.config.packed_resources_public_key = Some(key);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::packed_resources_decryption_key_hook].
+    #[must_use]
+    pub fn packed_resources_decryption_key_hook(mut self, value: DecryptionKeyHook) -> Self {
+        self.config.packed_resources_decryption_key_hook = Some(value);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::sys_frozen].
+    #[must_use]
+    pub fn sys_frozen(mut self, value: bool) -> Self {
+        self.config.sys_frozen = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::sys_meipass].
+    #[must_use]
+    pub fn sys_meipass(mut self, value: bool) -> Self {
+        self.config.sys_meipass = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::traceback_excepthook].
+    #[must_use]
+    pub fn traceback_excepthook(mut self, value: bool) -> Self {
+        self.config.traceback_excepthook = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::excepthook].
+    #[must_use]
+    pub fn excepthook(mut self, value: ExceptHook) -> Self {
+        self.config.excepthook = Some(value);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::pre_initialize_hook].
+    #[must_use]
+    pub fn pre_initialize_hook(mut self, value: PreInitializeHook) -> Self {
+        self.config.pre_initialize_hook = Some(value);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::core_initialize_hook].
+    #[must_use]
+    pub fn core_initialize_hook(mut self, value: InitializeHook) -> Self {
+        self.config.core_initialize_hook = Some(value);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::post_initialize_hook].
+    #[must_use]
+    pub fn post_initialize_hook(mut self, value: InitializeHook) -> Self {
+        self.config.post_initialize_hook = Some(value);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::rust_signal_handlers].
+    #[must_use]
+    pub fn rust_signal_handlers(mut self, value: bool) -> Self {
+        self.config.rust_signal_handlers = value;
+        self
+    }
+
+    /// Append an entry to [OxidizedPythonInterpreterConfig::atexit_hooks].
+    #[must_use]
+    pub fn atexit_hook(mut self, value: AtExitHook) -> Self {
+        self.config.atexit_hooks.push(value);
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::stdout_writer].
+    #[must_use]
+    pub fn stdout_writer(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.config.stdout_writer = Some(OutputStream::new(writer));
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::stderr_writer].
+    #[must_use]
+    pub fn stderr_writer(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.config.stderr_writer = Some(OutputStream::new(writer));
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::repl_line_editing].
+    #[must_use]
+    pub fn repl_line_editing(mut self, value: bool) -> Self {
+        self.config.repl_line_editing = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::repl_history_file].
+    #[must_use]
+    pub fn repl_history_file(mut self, value: impl AsRef<Path>) -> Self {
+        self.config.repl_history_file = Some(value.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::terminfo_resolution].
+    #[must_use]
+    pub fn terminfo_resolution(mut self, value: TerminfoResolution) -> Self {
+        self.config.terminfo_resolution = value;
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::tcl_library].
+    #[must_use]
+    pub fn tcl_library(mut self, value: impl AsRef<Path>) -> Self {
+        self.config.tcl_library = Some(value.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set [OxidizedPythonInterpreterConfig::write_modules_directory_env].
+    #[must_use]
+    pub fn write_modules_directory_env(mut self, value: impl ToString) -> Self {
+        self.config.write_modules_directory_env = Some(value.to_string());
+        self
+    }
+
+    /// Validate settings and obtain the built [OxidizedPythonInterpreterConfig].
+    pub fn build(self) -> Result<OxidizedPythonInterpreterConfig<'a>, NewInterpreterError> {
+        let config = self.config;
+
+        // Entries on sys.path are serviced by the path-based importer. Without it,
+        // they would be silently ignored.
+        if !config.filesystem_importer
+            && matches!(&config.interpreter_config.module_search_paths, Some(paths) if !paths.is_empty())
+        {
+            return Err(NewInterpreterError::Simple(
+                "module_search_paths requires filesystem_importer to be enabled",
+            ));
+        }
+
+        if !config.oxidized_importer && !config.packed_resources.is_empty() {
+            return Err(NewInterpreterError::Simple(
+                "packed_resources requires oxidized_importer to be enabled",
+            ));
+        }
+
+        if config.allocator_pymalloc_arena && (config.allocator_mem || config.allocator_obj) {
+            return Err(NewInterpreterError::Simple(
+                "allocator_pymalloc_arena cannot be used with allocator_mem or allocator_obj",
+            ));
+        }
+
+        let run_count = [
+            config.interpreter_config.run_command.is_some(),
+            config.interpreter_config.run_module.is_some(),
+            config.interpreter_config.run_filename.is_some(),
+            config.run_zipapp.is_some(),
+            config.run_frozen_main.is_some(),
+        ]
+        .iter()
+        .filter(|x| **x)
+        .count();
+
+        if run_count > 1 {
+            return Err(NewInterpreterError::Simple(
+                "only 1 of run_command, run_module, run_filename, run_zipapp, and run_frozen_main can be set",
+            ));
+        }
+
+        Ok(config)
+    }
+}
+
+/// An `OxidizedPythonInterpreterConfig` that has fields resolved.
+pub struct ResolvedOxidizedPythonInterpreterConfig<'a> {
+    inner: OxidizedPythonInterpreterConfig<'a>,
+}
+
+impl<'a> Deref for ResolvedOxidizedPythonInterpreterConfig<'a> {
+    type Target = OxidizedPythonInterpreterConfig<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<'a> TryFrom<OxidizedPythonInterpreterConfig<'a>>
+    for ResolvedOxidizedPythonInterpreterConfig<'a>
+{
+    type Error = NewInterpreterError;
+
+    fn try_from(value: OxidizedPythonInterpreterConfig<'a>) -> Result<Self, Self::Error> {
+        value.resolve()
+    }
+}
+
+impl<'a> ResolvedOxidizedPythonInterpreterConfig<'a> {
     /// Obtain the value for the current executable.
     pub fn exe(&self) -> &PathBuf {
         self.inner.exe.as_ref().expect("exe should have a value")
@@ -592,14 +1836,10 @@ impl<'a> ResolvedOxidizedPythonInterpreterConfig<'a> {
     }
 
     /// Resolve the value to use for `sys.argvb`.
+    ///
+    /// This uses the same arguments as `sys.argv`.
     pub fn resolve_sys_argvb(&self) -> Vec<OsString> {
-        if let Some(args) = &self.inner.interpreter_config.argv {
-            args.clone()
-        } else if let Some(args) = &self.inner.argv {
-            args.clone()
-        } else {
-            std::env::args_os().collect::<Vec<_>>()
-        }
+        self.resolve_sys_argv().to_vec()
     }
 }
 
@@ -614,6 +1854,12 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
         let mut state = Self::default();
         state.set_current_exe(config.exe().to_path_buf());
         state.set_origin(config.origin().to_path_buf());
+        state.set_verify_resources(config.verify_resources);
+        state.set_packed_resources_public_key(config.packed_resources_public_key);
+
+        if let Some(hook) = config.packed_resources_decryption_key_hook {
+            state.set_decryption_key(Some(hook()?));
+        }
 
         for source in &config.packed_resources {
             match source {
@@ -627,6 +1873,16 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
                         .index_path_memory_mapped(path)
                         .map_err(NewInterpreterError::Dynamic)?;
                 }
+                PackedResourcesSource::Path(path) => {
+                    state
+                        .index_path(path)
+                        .map_err(NewInterpreterError::Dynamic)?;
+                }
+                PackedResourcesSource::CurrentExecutable => {
+                    state
+                        .index_path_injected(config.exe())
+                        .map_err(NewInterpreterError::Dynamic)?;
+                }
             }
         }
 
@@ -675,16 +1931,323 @@ mod tests {
             .push(PackedResourcesSource::MemoryMappedPath(PathBuf::from(
                 "$ORIGIN/lib/packed-resources",
             )));
+        config
+            .packed_resources
+            .push(PackedResourcesSource::Path(PathBuf::from(
+                "$ORIGIN/lib/extra-resources",
+            )));
 
         let resolved = config.resolve()?;
 
         assert_eq!(
             resolved.packed_resources,
-            vec![PackedResourcesSource::MemoryMappedPath(PathBuf::from(
-                "/other/origin/lib/packed-resources"
-            ))]
+            vec![
+                PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                    "/other/origin/lib/packed-resources"
+                )),
+                PackedResourcesSource::Path(PathBuf::from("/other/origin/lib/extra-resources")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_defaults() -> Result<()> {
+        let config = OxidizedPythonInterpreterConfig::builder().build()?;
+        let default = OxidizedPythonInterpreterConfig::default();
+
+        assert_eq!(
+            config.interpreter_config.profile,
+            default.interpreter_config.profile
+        );
+        assert_eq!(config.oxidized_importer, default.oxidized_importer);
+        assert_eq!(config.filesystem_importer, default.filesystem_importer);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_standalone_application() -> Result<()> {
+        let config = OxidizedPythonInterpreterConfigBuilder::standalone_application()
+            .packed_resources(PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                "$ORIGIN/packed-resources",
+            )))
+            .argv(["prog", "arg"])
+            .build()?;
+
+        assert_eq!(
+            config.interpreter_config.profile,
+            PythonInterpreterProfile::Isolated
+        );
+        assert_eq!(config.interpreter_config.configure_locale, Some(true));
+        assert!(config.oxidized_importer);
+        assert!(!config.filesystem_importer);
+        assert!(config.sys_frozen);
+        assert_eq!(config.packed_resources.len(), 1);
+        assert_eq!(
+            config.argv,
+            Some(vec![OsString::from("prog"), OsString::from("arg")])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_module_search_path() -> Result<()> {
+        let config = OxidizedPythonInterpreterConfig::builder()
+            .filesystem_importer(true)
+            .module_search_path("$ORIGIN/lib")
+            .module_search_path("/usr/lib/python")
+            .build()?;
+
+        assert_eq!(
+            config.interpreter_config.module_search_paths,
+            Some(vec![
+                PathBuf::from("$ORIGIN/lib"),
+                PathBuf::from("/usr/lib/python")
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(OxidizedPythonInterpreterConfig::builder()
+            .filesystem_importer(false)
+            .module_search_path("lib")
+            .build()
+            .is_err());
+
+        assert!(OxidizedPythonInterpreterConfig::builder()
+            .oxidized_importer(false)
+            .packed_resources(PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                "packed-resources"
+            )))
+            .build()
+            .is_err());
+
+        assert!(OxidizedPythonInterpreterConfig::builder()
+            .allocator_pymalloc_arena(true)
+            .allocator_mem(true)
+            .build()
+            .is_err());
+
+        assert!(OxidizedPythonInterpreterConfig::builder()
+            .run_command("print('hello')")
+            .run_module("foo")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_environment_overrides_unset() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig {
+            filesystem_importer: false,
+            ..Default::default()
+        };
+        config.apply_environment_overrides("PYEMBED_TEST_UNSET_")?;
+
+        assert_eq!(config.interpreter_config.optimization_level, None);
+        assert_eq!(config.interpreter_config.module_search_paths, None);
+        assert_eq!(config.interpreter_config.buffered_stdio, None);
+        assert!(!config.filesystem_importer);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_environment_overrides() -> Result<()> {
+        let paths = std::env::join_paths(["/foo", "$ORIGIN/lib"])?;
+
+        std::env::set_var("PYEMBED_TEST_APPLY_OPT_LEVEL", "2");
+        std::env::set_var("PYEMBED_TEST_APPLY_SYS_PATHS", &paths);
+        std::env::set_var("PYEMBED_TEST_APPLY_UNBUFFERED", "1");
+        std::env::set_var("PYEMBED_TEST_APPLY_VERBOSE", "false");
+        std::env::set_var("PYEMBED_TEST_APPLY_DONT_WRITE_BYTECODE", "yes");
+
+        let mut config = OxidizedPythonInterpreterConfig {
+            filesystem_importer: false,
+            ..Default::default()
+        };
+        config.apply_environment_overrides("PYEMBED_TEST_APPLY_")?;
+
+        assert_eq!(
+            config.interpreter_config.optimization_level,
+            Some(BytecodeOptimizationLevel::Two)
+        );
+        assert_eq!(
+            config.interpreter_config.module_search_paths,
+            Some(vec![PathBuf::from("/foo"), PathBuf::from("$ORIGIN/lib")])
+        );
+        assert!(config.filesystem_importer);
+        assert_eq!(config.interpreter_config.buffered_stdio, Some(false));
+        assert_eq!(config.interpreter_config.verbose, Some(false));
+        assert_eq!(config.interpreter_config.write_bytecode, Some(false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_environment_overrides_invalid() {
+        std::env::set_var("PYEMBED_TEST_INVALID_OPT_LEVEL", "3");
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        assert!(config
+            .apply_environment_overrides("PYEMBED_TEST_INVALID_")
+            .is_err());
+
+        std::env::set_var("PYEMBED_TEST_INVALID_BOOL_VERBOSE", "maybe");
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        assert!(config
+            .apply_environment_overrides("PYEMBED_TEST_INVALID_BOOL_")
+            .is_err());
+    }
+
+    #[test]
+    fn test_run_zipapp_resolve() -> Result<()> {
+        let config = OxidizedPythonInterpreterConfig {
+            origin: Some(PathBuf::from("/origin")),
+            argv: Some(vec!["prog".into(), "arg".into()]),
+            run_zipapp: Some(PathBuf::from("$ORIGIN/app.pyz")),
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(
+            resolved.interpreter_config.run_filename,
+            Some(PathBuf::from("/origin/app.pyz"))
+        );
+        assert_eq!(
+            resolved.resolve_sys_argv(),
+            &[OsString::from("/origin/app.pyz"), OsString::from("arg")]
         );
 
+        let config = OxidizedPythonInterpreterConfig {
+            run_zipapp: Some(PathBuf::from("app.pyz")),
+            interpreter_config: PythonInterpreterConfig {
+                run_module: Some("foo".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(config.resolve().is_err());
+
+        let config = OxidizedPythonInterpreterConfig {
+            run_zipapp: Some(PathBuf::from("app.pyz")),
+            filesystem_importer: false,
+            ..Default::default()
+        };
+        assert!(config.resolve().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_frozen_main_conflicts() {
+        let config = OxidizedPythonInterpreterConfig {
+            run_frozen_main: Some("main".into()),
+            run_zipapp: Some(PathBuf::from("app.pyz")),
+            ..Default::default()
+        };
+        assert!(config.resolve().is_err());
+
+        let config = OxidizedPythonInterpreterConfig {
+            run_frozen_main: Some("main".into()),
+            interpreter_config: PythonInterpreterConfig {
+                run_command: Some("pass".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(config.resolve().is_err());
+
+        assert!(OxidizedPythonInterpreterConfig::builder()
+            .run_frozen_main("main")
+            .run_module("foo")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_builder_rust_function() -> Result<()> {
+        fn noop(py: Python, _args: &PyTuple, _kwargs: Option<&PyDict>) -> PyResult<PyObject> {
+            Ok(py.None())
+        }
+
+        let config = OxidizedPythonInterpreterConfig::builder()
+            .rust_function("foo", "a", noop)
+            .rust_function("bar", "a", noop)
+            .rust_function("foo", "b", noop)
+            .build()?;
+
+        assert_eq!(config.rust_modules.len(), 2);
+        assert_eq!(config.rust_modules[0].name, "foo");
+        assert_eq!(
+            config.rust_modules[0].functions.keys().collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(config.rust_modules[1].name, "bar");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multicall_resolve() -> Result<()> {
+        let multicall = BTreeMap::from([
+            ("foo".to_string(), "foo_main".to_string()),
+            ("bar".to_string(), "bar_main".to_string()),
+        ]);
+
+        let config = OxidizedPythonInterpreterConfig {
+            argv: Some(vec![
+                format!("/usr/bin/foo{}", std::env::consts::EXE_SUFFIX).into(),
+                "bar".into(),
+            ]),
+            multicall: multicall.clone(),
+            interpreter_config: PythonInterpreterConfig {
+                run_command: Some("pass".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("foo_main".into())
+        );
+        assert_eq!(resolved.interpreter_config.run_command, None);
+        assert_eq!(resolved.resolve_sys_argv().len(), 2);
+
+        let config = OxidizedPythonInterpreterConfig {
+            argv: Some(vec!["/usr/bin/tool".into(), "bar".into(), "arg".into()]),
+            multicall: multicall.clone(),
+            ..Default::default()
+        };
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("bar_main".into())
+        );
+        assert_eq!(
+            resolved.resolve_sys_argv(),
+            &[OsString::from("/usr/bin/tool"), OsString::from("arg")]
+        );
+
+        let config = OxidizedPythonInterpreterConfig {
+            argv: Some(vec!["/usr/bin/tool".into(), "baz".into()]),
+            multicall,
+            interpreter_config: PythonInterpreterConfig {
+                run_command: Some("pass".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let resolved = config.resolve()?;
+        assert_eq!(resolved.interpreter_config.run_module, None);
+        assert_eq!(resolved.interpreter_config.run_command, Some("pass".into()));
+
         Ok(())
     }
 }
@@ -6,7 +6,9 @@
 
 use {
     crate::{
-        config::{OxidizedPythonInterpreterConfig, ResolvedOxidizedPythonInterpreterConfig},
+        config::{
+            OutputStream, OxidizedPythonInterpreterConfig, ResolvedOxidizedPythonInterpreterConfig,
+        },
         conversion::osstring_to_bytes,
         error::NewInterpreterError,
         osutils::resolve_terminfo_dirs,
@@ -19,22 +21,264 @@ use {
         OXIDIZED_IMPORTER_NAME_STR,
     },
     pyo3::{
-        exceptions::PyRuntimeError, ffi as pyffi, prelude::*, types::PyDict, AsPyPointer,
-        PyTypeInfo,
+        exceptions::{PyOSError, PyRuntimeError, PySystemExit},
+        ffi as pyffi,
+        prelude::*,
+        types::{PyCFunction, PyDict, PyTuple},
+        AsPyPointer, PyTypeInfo,
+    },
+    python_packaging::{
+        interpreter::{MemoryAllocatorBackend, MultiprocessingStartMethod, TerminfoResolution},
+        resource::BytecodeOptimizationLevel,
     },
-    python_packaging::interpreter::{MultiprocessingStartMethod, TerminfoResolution},
     std::{
+        cell::Cell,
         collections::BTreeSet,
         env, fs,
         io::Write,
+        marker::PhantomData,
         os::raw::c_char,
         path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicI32, Ordering},
+            Arc, RwLock, RwLockReadGuard, TryLockError,
+        },
     },
 };
 
+#[cfg(feature = "zipimport")]
+use oxidized_importer::OxidizedZipFinder;
+
+/// The most recent signal received by [rust_signal_handler()].
+static RECEIVED_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// Signal handler installed when [OxidizedPythonInterpreterConfig::rust_signal_handlers] is set.
+///
+/// Records the signal and arranges for `KeyboardInterrupt` to be raised in the
+/// main thread. `PyErr_SetInterrupt()` is async-signal-safe.
+extern "C" fn rust_signal_handler(signum: libc::c_int) {
+    RECEIVED_SIGNAL.store(signum, Ordering::SeqCst);
+
+    // The C runtime on Windows resets the handler before invoking it.
+    #[cfg(windows)]
+    unsafe {
+        libc::signal(
+            signum,
+            rust_signal_handler as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+
+    unsafe {
+        pyffi::PyErr_SetInterrupt();
+    }
+}
+
+/// Python source defining the text stream type used for [OutputStream].
+///
+/// The module is executed to define `RustStream`, whose instances forward
+/// writes and flushes to Rust functions. Its `buffer` attribute is a
+/// `RustBinaryStream` writing bytes to the same Rust functions.
+const RUST_STREAM_SOURCE: &str = r#"
+import io
+
+class RustBinaryStream(io.BufferedIOBase):
+    def __init__(self, name, write, flush):
+        self._name = name
+        self._write = write
+        self._flush = flush
+
+    @property
+    def name(self):
+        return self._name
+
+    def writable(self):
+        return True
+
+    def isatty(self):
+        return False
+
+    def write(self, b):
+        data = memoryview(b).tobytes()
+
+        self._check_closed()
+        self._write(data)
+
+        return len(data)
+
+    def flush(self):
+        self._check_closed()
+        self._flush()
+
+    def _check_closed(self):
+        if self.closed:
+            raise ValueError("I/O operation on closed file.")
+
+class RustStream(io.TextIOBase):
+    def __init__(self, name, write, write_bytes, flush):
+        self._name = name
+        self._write = write
+        self._flush = flush
+        self._buffer = RustBinaryStream(name, write_bytes, flush)
+
+    @property
+    def buffer(self):
+        return self._buffer
+
+    @property
+    def name(self):
+        return self._name
+
+    @property
+    def encoding(self):
+        return "utf-8"
+
+    @property
+    def errors(self):
+        return "strict"
+
+    def writable(self):
+        return True
+
+    def isatty(self):
+        return False
+
+    def write(self, s):
+        if not isinstance(s, str):
+            raise TypeError("write() argument must be str, not %s" % type(s).__name__)
+
+        self._check_closed()
+        self._write(s)
+
+        return len(s)
+
+    def flush(self):
+        self._check_closed()
+        self._flush()
+
+    def close(self):
+        super().close()
+        self._buffer.close()
+
+    def _check_closed(self):
+        if self.closed:
+            raise ValueError("I/O operation on closed file.")
+"#;
+
+/// Python source defining the `sys.__interactivehook__` used for REPL line editing.
+///
+/// The module is executed with `history_file` defined as a `str` or `None`.
+const REPL_INTERACTIVE_HOOK: &str = r#"
+def __interactivehook__():
+    import pkgutil
+    import sys
+
+    try:
+        import readline
+        import rlcompleter
+    except ImportError:
+        return
+
+    completer = rlcompleter.Completer()
+    module_names = []
+
+    def complete(text, state):
+        line = readline.get_line_buffer().lstrip()
+
+        if line.startswith(("import ", "from ")) and " import " not in line:
+            if not module_names:
+                names = {m.name for m in pkgutil.iter_modules()}
+                names.update(sys.builtin_module_names)
+                module_names.extend(sorted(names))
+
+            matches = [name for name in module_names if name.startswith(text)]
+
+            return matches[state] if state < len(matches) else None
+
+        return completer.complete(text, state)
+
+    readline.set_completer(complete)
+
+    if readline.__doc__ and "libedit" in readline.__doc__:
+        readline.parse_and_bind("bind ^I rl_complete")
+    else:
+        readline.parse_and_bind("tab: complete")
+
+    if history_file is not None:
+        import atexit
+
+        try:
+            readline.read_history_file(history_file)
+        except OSError:
+            pass
+
+        def write_history():
+            try:
+                readline.write_history_file(history_file)
+            except OSError:
+                pass
+
+        atexit.register(write_history)
+"#;
+
 static GLOBAL_INTERPRETER_GUARD: Lazy<std::sync::Mutex<()>> =
     Lazy::new(|| std::sync::Mutex::new(()));
 
+/// Memory allocator settings installed into the Python runtime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct AllocatorSettings {
+    backend: MemoryAllocatorBackend,
+    raw: bool,
+    mem: bool,
+    obj: bool,
+    pymalloc_arena: bool,
+    debug: bool,
+}
+
+/// The memory allocator installed by the first interpreter in this process.
+struct InstalledAllocator {
+    settings: AllocatorSettings,
+    allocator: Option<&'static PythonMemoryAllocator>,
+}
+
+/// Tracks the memory allocator installed into the Python runtime.
+///
+/// `Py_FinalizeEx()` doesn't release all memory obtained from the Python
+/// allocators and leaves the allocator domains installed. Memory allocated
+/// by one allocator must not be released by another. So the allocator
+/// installed by the first interpreter is leaked and reused by all subsequent
+/// interpreters in the process.
+static INSTALLED_ALLOCATOR: Lazy<std::sync::Mutex<Option<InstalledAllocator>>> =
+    Lazy::new(|| std::sync::Mutex::new(None));
+
+/// The result of running Python code via [MainPythonInterpreter::run_outcome()].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PythonRunOutcome {
+    /// The exit code the `python` executable would have exited with.
+    pub exit_code: i32,
+
+    /// Whether execution stopped because `SystemExit` was raised.
+    pub system_exit: bool,
+}
+
+/// Resolve the process exit code carried by a `SystemExit` exception.
+///
+/// This mirrors the semantics of CPython's handling of `SystemExit.code`.
+fn system_exit_code(py: Python, e: &PyErr) -> PyResult<i32> {
+    let code = e.value(py).getattr("code")?;
+
+    if code.is_none() {
+        Ok(0)
+    } else if let Ok(value) = code.extract::<i32>() {
+        Ok(value)
+    } else {
+        py.import("sys")?
+            .getattr("stderr")?
+            .call_method1("write", (format!("{}\n", code.str()?),))?;
+
+        Ok(1)
+    }
+}
+
 /// Manages an embedded Python interpreter.
 ///
 /// Python interpreters have global state and there can only be a single
@@ -89,9 +333,15 @@ pub struct MainPythonInterpreter<'interpreter, 'resources: 'interpreter> {
     // interpreter is finalized/dropped.
     config: ResolvedOxidizedPythonInterpreterConfig<'resources>,
     interpreter_guard: Option<std::sync::MutexGuard<'interpreter, ()>>,
-    pub(crate) allocator: Option<PythonMemoryAllocator>,
+    pub(crate) allocator: Option<&'static PythonMemoryAllocator>,
     /// File to write containing list of modules when the interpreter finalizes.
     write_modules_path: Option<PathBuf>,
+    /// Environment variables we modified and their original values.
+    original_env: Vec<(&'static str, Option<std::ffi::OsString>)>,
+    /// Whether the interpreter can service [InterpreterHandle] instances.
+    alive: Arc<RwLock<bool>>,
+    /// Signal handlers replaced by [rust_signal_handler()].
+    previous_signal_handlers: Vec<(libc::c_int, libc::sighandler_t)>,
 }
 
 impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
@@ -103,23 +353,14 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
     ) -> Result<MainPythonInterpreter<'interpreter, 'resources>, NewInterpreterError> {
         let config: ResolvedOxidizedPythonInterpreterConfig<'resources> = config.try_into()?;
 
-        match config.terminfo_resolution {
-            TerminfoResolution::Dynamic => {
-                if let Some(v) = resolve_terminfo_dirs() {
-                    env::set_var("TERMINFO_DIRS", &v);
-                }
-            }
-            TerminfoResolution::Static(ref v) => {
-                env::set_var("TERMINFO_DIRS", v);
-            }
-            TerminfoResolution::None => {}
-        }
-
         let mut res = MainPythonInterpreter {
             config,
             interpreter_guard: None,
             allocator: None,
             write_modules_path: None,
+            original_env: vec![],
+            alive: Arc::new(RwLock::new(false)),
+            previous_signal_handlers: vec![],
         };
 
         res.init()?;
@@ -145,8 +386,21 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             NewInterpreterError::Simple("unable to acquire global interpreter guard")
         })?);
 
-        if let Some(tcl_library) = &self.config.tcl_library {
-            std::env::set_var("TCL_LIBRARY", tcl_library);
+        match &self.config.terminfo_resolution {
+            TerminfoResolution::Dynamic => {
+                if let Some(v) = resolve_terminfo_dirs() {
+                    self.set_env_var("TERMINFO_DIRS", v);
+                }
+            }
+            TerminfoResolution::Static(v) => {
+                let v = v.clone();
+                self.set_env_var("TERMINFO_DIRS", v);
+            }
+            TerminfoResolution::None => {}
+        }
+
+        if let Some(tcl_library) = self.config.tcl_library.clone() {
+            self.set_env_var("TCL_LIBRARY", tcl_library);
         }
 
         set_pyimport_inittab(&self.config);
@@ -165,37 +419,10 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             }
         };
 
-        // Set the memory allocator domains if they are configured.
-        self.allocator = PythonMemoryAllocator::from_backend(self.config.allocator_backend);
+        self.allocator = self.install_allocator()?;
 
-        if let Some(allocator) = &self.allocator {
-            if self.config.allocator_raw {
-                allocator.set_allocator(pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_RAW);
-            }
-
-            if self.config.allocator_mem {
-                allocator.set_allocator(pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_MEM);
-            }
-
-            if self.config.allocator_obj {
-                allocator.set_allocator(pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_OBJ);
-            }
-
-            if self.config.allocator_pymalloc_arena {
-                if self.config.allocator_mem || self.config.allocator_obj {
-                    return Err(NewInterpreterError::Simple("A custom pymalloc arena allocator cannot be used with custom `mem` or `obj` domain allocators"));
-                }
-
-                allocator.set_arena_allocator();
-            }
-        }
-
-        // Debug hooks apply to all allocator domains and work with or without
-        // custom domain allocators.
-        if self.config.allocator_debug {
-            unsafe {
-                pyffi::PyMem_SetupDebugHooks();
-            }
+        if let Some(hook) = self.config.pre_initialize_hook {
+            hook()?;
         }
 
         let mut py_config: pyffi::PyConfig = (&self.config).try_into()?;
@@ -223,6 +450,19 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         let oxidized_finder_loaded =
             unsafe { Python::with_gil_unchecked(|py| self.inject_oxidized_importer(py))? };
 
+        unsafe { Python::with_gil_unchecked(|py| self.register_rust_modules(py))? };
+        unsafe { Python::with_gil_unchecked(|py| self.register_atexit_hooks(py))? };
+
+        if let Some(hook) = self.config.core_initialize_hook {
+            unsafe {
+                Python::with_gil_unchecked(|py| {
+                    hook(py).map_err(|e| {
+                        NewInterpreterError::new_from_pyerr(py, e, "running core initialize hook")
+                    })
+                })?;
+            }
+        }
+
         // The GIL is still held after calling into PyO3.
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 1);
 
@@ -249,8 +489,234 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         self.write_modules_path =
             self.with_gil(|py| self.init_post_main(py, oxidized_finder_loaded))?;
 
+        if self.config.rust_signal_handlers {
+            self.install_rust_signal_handlers()?;
+        }
+
+        if let Some(hook) = self.config.post_initialize_hook {
+            self.with_gil(|py| {
+                hook(py).map_err(|e| {
+                    NewInterpreterError::new_from_pyerr(py, e, "running post initialize hook")
+                })
+            })?;
+        }
+
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 0);
 
+        *self.alive.write().unwrap_or_else(|e| e.into_inner()) = true;
+
+        Ok(())
+    }
+
+    /// Install [rust_signal_handler()] for `SIGINT` and `SIGTERM`.
+    fn install_rust_signal_handlers(&mut self) -> Result<(), NewInterpreterError> {
+        // Python ignores interrupts for signals it doesn't have a Python-level
+        // handler for. Register the default handler, which raises
+        // KeyboardInterrupt, then replace the C-level handler it installs.
+        self.with_gil(|py| {
+            py.run(
+                "import signal\nsignal.signal(signal.SIGINT, signal.default_int_handler)",
+                None,
+                None,
+            )
+            .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "registering SIGINT handler"))
+        })?;
+
+        RECEIVED_SIGNAL.store(0, Ordering::SeqCst);
+
+        for signum in [libc::SIGINT, libc::SIGTERM] {
+            let previous = unsafe {
+                libc::signal(
+                    signum,
+                    rust_signal_handler as extern "C" fn(libc::c_int) as libc::sighandler_t,
+                )
+            };
+            if previous == libc::SIG_ERR {
+                return Err(NewInterpreterError::Simple(
+                    "unable to install signal handler",
+                ));
+            }
+
+            self.previous_signal_handlers.push((signum, previous));
+        }
+
+        Ok(())
+    }
+
+    /// The most recent signal received by the handlers installed via
+    /// [OxidizedPythonInterpreterConfig::rust_signal_handlers].
+    ///
+    /// Returns [None] if no signal has been received or Rust signal handlers
+    /// aren't enabled.
+    pub fn received_signal(&self) -> Option<i32> {
+        if self.config.rust_signal_handlers {
+            match RECEIVED_SIGNAL.load(Ordering::SeqCst) {
+                0 => None,
+                signum => Some(signum),
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Restore signal handlers and environment variables modified during initialization.
+    fn restore_process_state(&mut self) {
+        for (signum, handler) in self.previous_signal_handlers.drain(..) {
+            unsafe {
+                libc::signal(signum, handler);
+            }
+        }
+
+        for (key, value) in self.original_env.drain(..).rev() {
+            match value {
+                Some(value) => env::set_var(key, value),
+                None => env::remove_var(key),
+            }
+        }
+    }
+
+    /// Set an environment variable, recording its original value for restoration.
+    fn set_env_var(&mut self, key: &'static str, value: impl AsRef<std::ffi::OsStr>) {
+        if !self.original_env.iter().any(|(k, _)| *k == key) {
+            self.original_env.push((key, env::var_os(key)));
+        }
+
+        env::set_var(key, value);
+    }
+
+    /// Set the memory allocator domains if they are configured.
+    ///
+    /// Only the first interpreter in a process installs allocators. Subsequent
+    /// interpreters reuse them and must use the same allocator settings.
+    fn install_allocator(
+        &self,
+    ) -> Result<Option<&'static PythonMemoryAllocator>, NewInterpreterError> {
+        let settings = AllocatorSettings {
+            backend: self.config.allocator_backend,
+            raw: self.config.allocator_raw,
+            mem: self.config.allocator_mem,
+            obj: self.config.allocator_obj,
+            pymalloc_arena: self.config.allocator_pymalloc_arena,
+            debug: self.config.allocator_debug,
+        };
+
+        let mut installed = INSTALLED_ALLOCATOR
+            .lock()
+            .map_err(|_| NewInterpreterError::Simple("unable to acquire allocator lock"))?;
+
+        if let Some(installed) = installed.as_ref() {
+            return if installed.settings == settings {
+                Ok(installed.allocator)
+            } else {
+                Err(NewInterpreterError::Simple(
+                    "memory allocator settings cannot change after the first interpreter in a process is initialized",
+                ))
+            };
+        }
+
+        let allocator = PythonMemoryAllocator::from_backend(settings.backend)
+            .map(|allocator| &*Box::leak(Box::new(allocator)));
+
+        if let Some(allocator) = allocator {
+            if settings.raw {
+                allocator.set_allocator(pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_RAW);
+            }
+
+            if settings.mem {
+                allocator.set_allocator(pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_MEM);
+            }
+
+            if settings.obj {
+                allocator.set_allocator(pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_OBJ);
+            }
+
+            if settings.pymalloc_arena {
+                if settings.mem || settings.obj {
+                    return Err(NewInterpreterError::Simple("A custom pymalloc arena allocator cannot be used with custom `mem` or `obj` domain allocators"));
+                }
+
+                allocator.set_arena_allocator();
+            }
+        }
+
+        // Debug hooks apply to all allocator domains and work with or without
+        // custom domain allocators.
+        if settings.debug {
+            unsafe {
+                pyffi::PyMem_SetupDebugHooks();
+            }
+        }
+
+        *installed = Some(InstalledAllocator {
+            settings,
+            allocator,
+        });
+
+        Ok(allocator)
+    }
+
+    /// Register modules defined by [OxidizedPythonInterpreterConfig::rust_modules] in `sys.modules`.
+    fn register_rust_modules(&self, py: Python) -> Result<(), NewInterpreterError> {
+        if self.config.rust_modules.is_empty() {
+            return Ok(());
+        }
+
+        let modules = py
+            .import("sys")
+            .and_then(|sys| sys.getattr("modules"))
+            .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "resolving sys.modules"))?;
+
+        for definition in &self.config.rust_modules {
+            let module = PyModule::new(py, &definition.name)
+                .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "creating Rust module"))?;
+
+            for (name, function) in &definition.functions {
+                let function = *function;
+                PyCFunction::new_closure(
+                    move |args: &PyTuple, kwargs: Option<&PyDict>| -> PyResult<PyObject> {
+                        function(args.py(), args, kwargs)
+                    },
+                    py,
+                )
+                .and_then(|func| module.add(name, func))
+                .map_err(|e| {
+                    NewInterpreterError::new_from_pyerr(py, e, "defining Rust module function")
+                })?;
+            }
+
+            modules.set_item(&definition.name, module).map_err(|e| {
+                NewInterpreterError::new_from_pyerr(py, e, "registering Rust module")
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Register [OxidizedPythonInterpreterConfig::atexit_hooks] with `atexit`.
+    ///
+    /// This is called during core initialization so the registered function is
+    /// the first registered and therefore the last called.
+    fn register_atexit_hooks(&self, py: Python) -> Result<(), NewInterpreterError> {
+        if self.config.atexit_hooks.is_empty() {
+            return Ok(());
+        }
+
+        let hooks = self.config.atexit_hooks.clone();
+        PyCFunction::new_closure(
+            move |args: &PyTuple, _kwargs: Option<&PyDict>| {
+                let py = args.py();
+
+                for hook in &hooks {
+                    if let Err(e) = hook(py) {
+                        e.print(py);
+                    }
+                }
+            },
+            py,
+        )
+        .and_then(|func| py.import("atexit")?.call_method1("register", (func,)))
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "registering atexit hooks"))?;
+
         Ok(())
     }
 
@@ -272,29 +738,38 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             NewInterpreterError::new_from_pyerr(py, err, "import of oxidized importer module")
         })?;
 
-        let cb = |importer_state: &mut ImporterState| match self.config.multiprocessing_start_method
-        {
-            MultiprocessingStartMethod::None => {}
-            MultiprocessingStartMethod::Fork
-            | MultiprocessingStartMethod::ForkServer
-            | MultiprocessingStartMethod::Spawn => {
-                importer_state.set_multiprocessing_set_start_method(Some(
-                    self.config.multiprocessing_start_method.to_string(),
-                ));
+        let cb = |importer_state: &mut ImporterState| {
+            importer_state.set_synthesize_dunder_file(self.config.synthesize_dunder_file);
+
+            for (package, precedence) in &self.config.package_precedence {
+                importer_state.set_package_precedence(package, *precedence);
             }
-            MultiprocessingStartMethod::Auto => {
-                // Windows uses "spawn" because "fork" isn't available.
-                // Everywhere else uses "fork." The default on macOS is "spawn." This
-                // is due to https://bugs.python.org/issue33725, which only affects
-                // Python framework builds. Our assumption is we aren't using a Python
-                // framework, so "spawn" is safe.
-                let method = if cfg!(target_family = "windows") {
-                    "spawn"
-                } else {
-                    "fork"
-                };
 
-                importer_state.set_multiprocessing_set_start_method(Some(method.to_string()));
+            importer_state.set_reload_from_filesystem(self.config.reload_from_filesystem);
+
+            match self.config.multiprocessing_start_method {
+                MultiprocessingStartMethod::None => {}
+                MultiprocessingStartMethod::Fork
+                | MultiprocessingStartMethod::ForkServer
+                | MultiprocessingStartMethod::Spawn => {
+                    importer_state.set_multiprocessing_set_start_method(Some(
+                        self.config.multiprocessing_start_method.to_string(),
+                    ));
+                }
+                MultiprocessingStartMethod::Auto => {
+                    // Windows uses "spawn" because "fork" isn't available.
+                    // Everywhere else uses "fork." The default on macOS is "spawn." This
+                    // is due to https://bugs.python.org/issue33725, which only affects
+                    // Python framework builds. Our assumption is we aren't using a Python
+                    // framework, so "spawn" is safe.
+                    let method = if cfg!(target_family = "windows") {
+                        "spawn"
+                    } else {
+                        "fork"
+                    };
+
+                    importer_state.set_multiprocessing_set_start_method(Some(method.to_string()));
+                }
             }
         };
 
@@ -315,16 +790,16 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         Ok(true)
     }
 
-    /// Performs interpreter configuration after main interpreter initialization.
-    fn init_post_main(
+    /// Finalizes the importers on `sys.meta_path` and `sys.path_hooks`.
+    ///
+    /// Called after main initialization of an interpreter, as that process
+    /// registers the stdlib's "external" importers.
+    fn configure_importers(
         &self,
         py: Python,
+        sys_module: &PyModule,
         oxidized_finder_loaded: bool,
-    ) -> Result<Option<PathBuf>, NewInterpreterError> {
-        let sys_module = py
-            .import("sys")
-            .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "obtaining sys module"))?;
-
+    ) -> Result<(), NewInterpreterError> {
         // When the main initialization ran, it initialized the "external"
         // importer (importlib._bootstrap_external), mutating `sys.meta_path`
         // and `sys.path_hooks`.
@@ -394,6 +869,68 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             })?;
         }
 
+        if self.config.appended_zip_importer {
+            self.install_appended_zip_finder(py, sys_module)?;
+        }
+
+        Ok(())
+    }
+
+    /// Appends a finder for a zip archive appended to the executable to `sys.meta_path`.
+    #[cfg(feature = "zipimport")]
+    fn install_appended_zip_finder(
+        &self,
+        py: Python,
+        sys_module: &PyModule,
+    ) -> Result<(), NewInterpreterError> {
+        let finder =
+            OxidizedZipFinder::new_from_appended_path(py, self.config.exe()).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "indexing zip archive appended to exe")
+            })?;
+
+        if let Some(finder) = finder {
+            let finder = PyCell::new(py, finder).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "creating OxidizedZipFinder")
+            })?;
+
+            sys_module
+                .getattr("meta_path")
+                .and_then(|meta_path| meta_path.call_method1("append", (finder,)))
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(
+                        py,
+                        err,
+                        "installing OxidizedZipFinder in sys.meta_path",
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "zipimport"))]
+    fn install_appended_zip_finder(
+        &self,
+        _py: Python,
+        _sys_module: &PyModule,
+    ) -> Result<(), NewInterpreterError> {
+        Err(NewInterpreterError::Simple(
+            "appended_zip_importer requires the zipimport feature; try `cargo build --features zipimport`",
+        ))
+    }
+
+    /// Performs interpreter configuration after main interpreter initialization.
+    fn init_post_main(
+        &self,
+        py: Python,
+        oxidized_finder_loaded: bool,
+    ) -> Result<Option<PathBuf>, NewInterpreterError> {
+        let sys_module = py
+            .import("sys")
+            .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "obtaining sys module"))?;
+
+        self.configure_importers(py, sys_module, oxidized_finder_loaded)?;
+
         if self.config.argvb {
             let args_objs = self
                 .config
@@ -450,6 +987,109 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             }
         }
 
+        let streams = [
+            ("stdout", &self.config.stdout_writer),
+            ("stderr", &self.config.stderr_writer),
+        ];
+
+        if streams.iter().any(|(_, writer)| writer.is_some()) {
+            let globals = PyDict::new(py);
+            py.run(RUST_STREAM_SOURCE, Some(globals), None)
+                .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "defining RustStream"))?;
+            let stream_type = globals
+                .get_item("RustStream")
+                .ok_or(NewInterpreterError::Simple("unable to obtain RustStream"))?;
+
+            for (name, writer) in streams {
+                if let Some(writer) = writer {
+                    let stream =
+                        new_rust_stream(py, stream_type, name, writer.clone()).map_err(|e| {
+                            NewInterpreterError::new_from_pyerr(py, e, "creating RustStream")
+                        })?;
+
+                    sys_module.setattr(name, stream).map_err(|e| {
+                        NewInterpreterError::new_from_pyerr(py, e, "replacing stdio stream")
+                    })?;
+                }
+            }
+        }
+
+        let excepthook_func = if let Some(hook) = self.config.excepthook {
+            Some(PyCFunction::new_closure(
+                move |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<()> {
+                    let (exc_type, value, traceback) =
+                        args.extract::<(&PyAny, &PyAny, &PyAny)>()?;
+                    hook(args.py(), exc_type, value, traceback);
+
+                    Ok(())
+                },
+                py,
+            ))
+        } else if self.config.traceback_excepthook {
+            // The default hook reads source lines from files on the filesystem. So
+            // tracebacks through modules imported from memory have blank lines. The
+            // traceback module resolves them via linecache, which calls the module
+            // loader's get_source().
+            Some(PyCFunction::new_closure(
+                |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<()> {
+                    args.py()
+                        .import("traceback")?
+                        .call_method1("print_exception", args)?;
+
+                    Ok(())
+                },
+                py,
+            ))
+        } else {
+            None
+        }
+        .transpose()
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "creating sys.excepthook"))?;
+
+        if let Some(func) = excepthook_func {
+            let excepthook = b"excepthook\0";
+
+            match unsafe {
+                pyffi::PySys_SetObject(excepthook.as_ptr() as *const c_char, func.as_ptr())
+            } {
+                0 => (),
+                _ => return Err(NewInterpreterError::Simple("unable to set sys.excepthook")),
+            }
+        }
+
+        if self.config.repl_line_editing {
+            let globals = PyDict::new(py);
+            globals
+                .set_item(
+                    "history_file",
+                    self.config
+                        .repl_history_file
+                        .as_ref()
+                        .map(|p| p.as_os_str()),
+                )
+                .map_err(|e| {
+                    NewInterpreterError::new_from_pyerr(py, e, "setting REPL history file")
+                })?;
+
+            py.run(REPL_INTERACTIVE_HOOK, Some(globals), None)
+                .map_err(|e| {
+                    NewInterpreterError::new_from_pyerr(py, e, "defining sys.__interactivehook__")
+                })?;
+
+            let hook =
+                globals
+                    .get_item("__interactivehook__")
+                    .ok_or(NewInterpreterError::Simple(
+                        "unable to obtain sys.__interactivehook__",
+                    ))?;
+
+            sys_module
+                .setattr("__interactivehook__", hook)
+                .map_err(|e| {
+                    NewInterpreterError::new_from_pyerr(py, e, "setting sys.__interactivehook__")
+                })?;
+        }
+
         let write_modules_path = if let Some(key) = &self.config.write_modules_directory_env {
             if let Ok(path) = std::env::var(key) {
                 let path = PathBuf::from(path);
@@ -502,6 +1142,22 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         Python::with_gil(f)
     }
 
+    /// Obtain a handle for running Python code from other threads.
+    ///
+    /// See [InterpreterHandle] for more.
+    pub fn handle(&self) -> InterpreterHandle {
+        InterpreterHandle {
+            alive: self.alive.clone(),
+        }
+    }
+
+    /// Mark the interpreter as no longer servicing [InterpreterHandle] instances.
+    ///
+    /// Blocks until all in-progress [InterpreterHandle::with_gil()] calls complete.
+    fn disable_handles(&self) {
+        *self.alive.write().unwrap_or_else(|e| e.into_inner()) = false;
+    }
+
     /// Runs `Py_RunMain()` and finalizes the interpreter.
     ///
     /// This will execute whatever is configured by the Python interpreter config
@@ -513,6 +1169,39 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
     /// the evaluation result, consider calling a function on the interpreter handle
     /// that executes code.
     pub fn py_runmain(self) -> i32 {
+        if self.config.run_frozen_main.is_some() {
+            return match self.run_outcome() {
+                Ok(outcome) => outcome.exit_code,
+                Err(e) => {
+                    self.with_gil(|py| {
+                        e.print(py);
+                    });
+
+                    1
+                }
+            };
+        }
+
+        if let Err(e) = self.run_startup_commands() {
+            // PyErr::print() would call Py_Exit() on SystemExit, terminating the
+            // process without tearing down the interpreter properly.
+            return self.with_gil(|py| {
+                if e.is_instance_of::<PySystemExit>(py) {
+                    if let Ok(exit_code) = system_exit_code(py, &e) {
+                        return exit_code;
+                    }
+                }
+
+                e.print(py);
+
+                1
+            });
+        }
+
+        // Py_RunMain() finalizes the interpreter when it returns. So we can't
+        // allow handles to call into Python while it runs.
+        self.disable_handles();
+
         unsafe {
             // GIL must be acquired before calling Py_RunMain(). And Py_RunMain()
             // finalizes the interpreter. So we don't need to release the GIL
@@ -522,6 +1211,191 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         }
     }
 
+    /// Execute [OxidizedPythonInterpreterConfig::startup_commands] in `__main__`.
+    ///
+    /// Each entry is compiled separately with a distinct filename and its source
+    /// is registered with `linecache`, so tracebacks report accurate file names,
+    /// line numbers, and source lines.
+    ///
+    /// This is called automatically by [Self::py_runmain()] before the configured
+    /// run mode is executed.
+    pub fn run_startup_commands(&self) -> PyResult<()> {
+        if self.config.startup_commands.is_empty() {
+            return Ok(());
+        }
+
+        self.with_gil(|py| {
+            let builtins = py.import("builtins")?;
+            let linecache = py.import("linecache")?;
+            let linecache_cache = linecache.getattr("cache")?;
+            let globals = py.import("__main__")?.dict();
+
+            for (i, source) in self.config.startup_commands.iter().enumerate() {
+                let filename = format!("<startup-{}>", i);
+
+                let lines = source
+                    .split_inclusive('\n')
+                    .map(|l| {
+                        if l.ends_with('\n') {
+                            l.to_string()
+                        } else {
+                            format!("{}\n", l)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                linecache_cache.set_item(&filename, (source.len(), py.None(), lines, &filename))?;
+
+                let code =
+                    builtins
+                        .getattr("compile")?
+                        .call1((source.as_str(), &filename, "exec"))?;
+                builtins.getattr("exec")?.call1((code, globals))?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Run the configured run mode and report the outcome without exiting the process.
+    ///
+    /// Unlike [Self::py_runmain()], this does not finalize the interpreter and
+    /// does not terminate the process when `SystemExit` is raised. Instead, the
+    /// exit code and whether `SystemExit` was raised are returned. Any other
+    /// unhandled exception is returned as the `Err` variant, with the exception
+    /// object unformatted.
+    ///
+    /// [OxidizedPythonInterpreterConfig::startup_commands] are executed first.
+    /// Then [OxidizedPythonInterpreterConfig::run_frozen_main] is executed,
+    /// [PythonInterpreterConfig::run_command] is executed in `__main__`,
+    /// [PythonInterpreterConfig::run_module] is run via `runpy` like `python -m`,
+    /// or [PythonInterpreterConfig::run_filename] is run via `runpy.run_path()`.
+    /// An error is returned if none of these are set, as the interactive REPL is
+    /// not supported by this function.
+    ///
+    /// [PythonInterpreterConfig::run_command]: python_packaging::interpreter::PythonInterpreterConfig::run_command
+    /// [PythonInterpreterConfig::run_module]: python_packaging::interpreter::PythonInterpreterConfig::run_module
+    /// [PythonInterpreterConfig::run_filename]: python_packaging::interpreter::PythonInterpreterConfig::run_filename
+    pub fn run_outcome(&self) -> PyResult<PythonRunOutcome> {
+        let res = self.run_startup_commands().and_then(|_| {
+            self.with_gil(|py| {
+                let interpreter_config = &self.config.interpreter_config;
+
+                if let Some(name) = &self.config.run_frozen_main {
+                    self.exec_frozen_main(py, name)?;
+                } else if let Some(command) = &interpreter_config.run_command {
+                    let builtins = py.import("builtins")?;
+                    let globals = py.import("__main__")?.dict();
+                    let code = builtins.getattr("compile")?.call1((
+                        command.as_str(),
+                        "<string>",
+                        "exec",
+                    ))?;
+                    builtins.getattr("exec")?.call1((code, globals))?;
+                } else if let Some(module) = &interpreter_config.run_module {
+                    py.import("runpy")?
+                        .getattr("_run_module_as_main")?
+                        .call1((module.as_str(), true))?;
+                } else if let Some(filename) = &interpreter_config.run_filename {
+                    py.import("runpy")?.getattr("run_path")?.call1((
+                        filename.as_os_str(),
+                        py.None(),
+                        "__main__",
+                    ))?;
+                } else {
+                    return Err(PyRuntimeError::new_err(
+                        "run_outcome() requires run_command, run_module, run_filename, or run_frozen_main to be set",
+                    ));
+                }
+
+                Ok(())
+            })
+        });
+
+        match res {
+            Ok(()) => Ok(PythonRunOutcome {
+                exit_code: 0,
+                system_exit: false,
+            }),
+            Err(e) => self.with_gil(|py| {
+                if !e.is_instance_of::<PySystemExit>(py) {
+                    return Err(e);
+                }
+
+                Ok(PythonRunOutcome {
+                    exit_code: system_exit_code(py, &e)?,
+                    system_exit: true,
+                })
+            }),
+        }
+    }
+
+    /// Execute the named module from the packed resources as `__main__`.
+    fn exec_frozen_main(&self, py: Python, name: &str) -> PyResult<()> {
+        // Reuse the resources indexed by the installed OxidizedFinder. Only parse
+        // the packed resources ourselves if the oxidized importer is disabled.
+        let mut finder = None;
+        for entry in py.import("sys")?.getattr("meta_path")?.iter()? {
+            if let Ok(cell) = entry?.downcast::<PyCell<OxidizedFinder>>() {
+                finder = Some(cell.borrow());
+                break;
+            }
+        }
+
+        let parsed_state;
+        let resources_state = match &finder {
+            Some(finder) => finder.get_resources_state(),
+            None => {
+                parsed_state = PythonResourcesState::try_from(&self.config)
+                    .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                &parsed_state
+            }
+        };
+
+        let optimize_level = py
+            .import("sys")?
+            .getattr("flags")?
+            .getattr("optimize")?
+            .extract::<i32>()?;
+        let optimize_level =
+            BytecodeOptimizationLevel::try_from(optimize_level).map_err(PyRuntimeError::new_err)?;
+
+        let mut module = resources_state
+            .resolve_importable_module(name, optimize_level)
+            .map_err(PyRuntimeError::new_err)?
+            .ok_or_else(|| {
+                PyRuntimeError::new_err(format!("module {} not found in packed resources", name))
+            })?;
+
+        // resolve_bytecode() expects the signature of oxidized_importer.decode_source(),
+        // which may not be available if the oxidized importer is disabled.
+        let namespace = PyDict::new(py);
+        namespace.set_item(
+            "decode_source",
+            py.import("importlib._bootstrap_external")?
+                .getattr("decode_source")?,
+        )?;
+        let decode_source = py.eval(
+            "lambda io_module, data: decode_source(data)",
+            Some(namespace),
+            None,
+        )?;
+        let io_module = py.import("io")?;
+
+        let bytecode = module
+            .resolve_bytecode(py, optimize_level, decode_source, io_module)?
+            .ok_or_else(|| {
+                PyRuntimeError::new_err(format!("no bytecode or source available for {}", name))
+            })?;
+
+        let code = py.import("marshal")?.getattr("loads")?.call1((bytecode,))?;
+        let globals = py.import("__main__")?.dict();
+        py.import("builtins")?
+            .getattr("exec")?
+            .call1((code, globals))?;
+
+        Ok(())
+    }
+
     /// Run in "multiprocessing worker" mode.
     ///
     /// This should be called when `sys.argv[1] == "--multiprocessing-fork"`. It
@@ -590,6 +1464,63 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         argv.len() >= 2 && argv[1] == "--multiprocessing-fork"
     }
 
+    /// Create a new sub-interpreter.
+    ///
+    /// The sub-interpreter has its own `sys.modules`, `__main__` module, and
+    /// importers, including its own `OxidizedFinder` indexing the configured packed
+    /// resources if [OxidizedPythonInterpreterConfig::oxidized_importer] is enabled.
+    /// See [SubInterpreter] for more.
+    pub fn new_sub_interpreter(&self) -> Result<SubInterpreter<'_>, NewInterpreterError> {
+        let (thread_state, interpreter_state) = unsafe {
+            // Py_NewInterpreter() requires the GIL, which is acquired with a
+            // temporary thread state of the main interpreter.
+            let main_guard = TemporaryThreadStateGuard::new(pyffi::PyInterpreterState_Main());
+
+            // On success, the new interpreter's thread state is made current.
+            let thread_state = pyffi::Py_NewInterpreter();
+
+            if thread_state.is_null() {
+                return Err(NewInterpreterError::Simple(
+                    "unable to create sub-interpreter",
+                ));
+            }
+
+            let py = Python::assume_gil_acquired();
+            let pool = py.new_pool();
+
+            let res =
+                self.inject_oxidized_importer(pool.python())
+                    .and_then(|oxidized_finder_loaded| {
+                        let py = pool.python();
+                        let sys_module = py.import("sys").map_err(|e| {
+                            NewInterpreterError::new_from_pyerr(py, e, "obtaining sys module")
+                        })?;
+
+                        self.configure_importers(py, sys_module, oxidized_finder_loaded)
+                    });
+
+            std::mem::drop(pool);
+
+            let interpreter_state = current_interpreter_state();
+
+            if res.is_err() {
+                pyffi::Py_EndInterpreter(thread_state);
+            }
+
+            std::mem::drop(main_guard);
+
+            res?;
+
+            (thread_state, interpreter_state)
+        };
+
+        Ok(SubInterpreter {
+            thread_state,
+            interpreter_state,
+            _main: PhantomData,
+        })
+    }
+
     /// Runs the Python interpreter.
     ///
     /// If multiprocessing dispatch is enabled, this will check if the
@@ -615,6 +1546,298 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
     }
 }
 
+/// A CPython sub-interpreter created by a [MainPythonInterpreter].
+///
+/// Sub-interpreters have their own modules and importer state, which provides
+/// some isolation between code running in different sub-interpreters without
+/// spawning processes. They are not a security boundary: all sub-interpreters
+/// share the process, the GIL, and process-wide state like environment variables
+/// and file descriptors. Some extension modules don't support being loaded in
+/// multiple interpreters.
+///
+/// Instances are obtained via [MainPythonInterpreter::new_sub_interpreter()] and
+/// cannot outlive the [MainPythonInterpreter] that created them. The sub-interpreter
+/// is destroyed via `Py_EndInterpreter()` when the instance is dropped.
+///
+/// A sub-interpreter's thread state is bound to the thread that created it, so
+/// instances can't be sent to other threads.
+///
+/// # Safety Invariants
+///
+/// Sub-interpreters are entered by acquiring the GIL with one of their thread
+/// states. The `PyGILState_*` APIs, which [Python::with_gil()] uses, don't
+/// support sub-interpreters and aren't used to enter them. Entering a
+/// sub-interpreter, including dropping it, panics if the current thread already
+/// holds the GIL, since acquiring it again would deadlock. So don't use a
+/// sub-interpreter from within [MainPythonInterpreter::with_gil()] or another
+/// sub-interpreter.
+///
+/// PyO3 creates the type objects of `#[pyclass]` types, such as `OxidizedFinder`,
+/// once per process and shares them between interpreters. This relies on
+/// sub-interpreters sharing the GIL and memory allocator of the main
+/// interpreter, which is the case for all sub-interpreters created via
+/// `Py_NewInterpreter()`. Type objects are never freed, so they remain valid
+/// when the sub-interpreter that created them is destroyed.
+pub struct SubInterpreter<'a> {
+    thread_state: *mut pyffi::PyThreadState,
+    interpreter_state: *mut pyffi::PyInterpreterState,
+    _main: PhantomData<&'a ()>,
+}
+
+/// Obtain the interpreter state of the current thread state.
+#[cfg(Py_3_9)]
+unsafe fn current_interpreter_state() -> *mut pyffi::PyInterpreterState {
+    pyffi::PyInterpreterState_Get()
+}
+
+#[cfg(not(Py_3_9))]
+unsafe fn current_interpreter_state() -> *mut pyffi::PyInterpreterState {
+    extern "C" {
+        fn _PyInterpreterState_Get() -> *mut pyffi::PyInterpreterState;
+    }
+
+    _PyInterpreterState_Get()
+}
+
+thread_local! {
+    /// Number of thread states entered by pyembed on the current thread.
+    static ENTERED_THREAD_STATES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Obtain the thread state holding the GIL, if any.
+unsafe fn current_thread_state() -> *mut pyffi::PyThreadState {
+    extern "C" {
+        fn _PyThreadState_UncheckedGet() -> *mut pyffi::PyThreadState;
+    }
+
+    _PyThreadState_UncheckedGet()
+}
+
+/// Panics if the current thread holds the GIL.
+///
+/// Thread states are entered via `PyEval_RestoreThread()`, which deadlocks if
+/// the current thread already holds the GIL.
+fn assert_gil_not_held() {
+    let held = ENTERED_THREAD_STATES.with(|count| count.get() > 0)
+        || unsafe {
+            let current = current_thread_state();
+
+            !current.is_null() && current == pyffi::PyGILState_GetThisThreadState()
+        };
+
+    if held {
+        panic!("sub-interpreters can't be entered from a thread holding the GIL");
+    }
+}
+
+/// Acquires the GIL with an existing thread state and releases it when dropped.
+struct ThreadStateGuard;
+
+impl ThreadStateGuard {
+    /// Acquire the GIL and make `thread_state` current.
+    unsafe fn new(thread_state: *mut pyffi::PyThreadState) -> Self {
+        assert_gil_not_held();
+        pyffi::PyEval_RestoreThread(thread_state);
+        ENTERED_THREAD_STATES.with(|count| count.set(count.get() + 1));
+
+        Self
+    }
+}
+
+impl Drop for ThreadStateGuard {
+    fn drop(&mut self) {
+        unsafe {
+            pyffi::PyEval_SaveThread();
+        }
+        ENTERED_THREAD_STATES.with(|count| count.set(count.get() - 1));
+    }
+}
+
+/// A handle for running Python code in a [MainPythonInterpreter] from any thread.
+///
+/// Instances are obtained via [MainPythonInterpreter::handle()]. Unlike
+/// [MainPythonInterpreter], handles are [Send], [Sync], and [Clone] and don't
+/// borrow the interpreter. So they can be moved to other threads, such as
+/// worker threads of an async runtime (e.g. via `tokio::task::spawn_blocking()`).
+///
+/// [InterpreterHandle::with_gil()] acquires the GIL on the calling thread and
+/// blocks until it is available. Code holding the GIL on another thread must
+/// release it (e.g. via [Python::allow_threads()]) while waiting on work
+/// scheduled through a handle or a deadlock will occur.
+///
+/// Once the interpreter is finalized, either because the [MainPythonInterpreter]
+/// is dropped or because [MainPythonInterpreter::py_runmain()] is called, the
+/// handle no longer runs functions. Finalization waits for in-progress calls
+/// to complete. Calls made while finalization is pending don't wait and don't
+/// run the function.
+#[derive(Clone, Debug)]
+pub struct InterpreterHandle {
+    alive: Arc<RwLock<bool>>,
+}
+
+impl InterpreterHandle {
+    /// Run a function with the GIL held on the current thread.
+    ///
+    /// Returns [None] without calling the function if the interpreter has been
+    /// finalized or finalization is pending.
+    pub fn with_gil<F, R>(&self, f: F) -> Option<R>
+    where
+        F: for<'py> FnOnce(Python<'py>) -> R,
+    {
+        // The guard is held while acquiring the GIL so the interpreter isn't
+        // finalized in the meantime. Waiting for the lock could deadlock: the
+        // current thread may hold the GIL while finalization waits for another
+        // handle, which waits for the GIL. So the lock is never waited for.
+        // Failing to obtain it means finalization is pending.
+        match self.try_alive() {
+            Some(alive) if *alive => Some(Python::with_gil(f)),
+            _ => None,
+        }
+    }
+
+    /// Whether the interpreter is still available to run functions.
+    pub fn is_alive(&self) -> bool {
+        self.try_alive().is_some_and(|alive| *alive)
+    }
+
+    /// Obtain the liveness flag without blocking.
+    ///
+    /// Returns [None] if the interpreter is being finalized.
+    fn try_alive(&self) -> Option<RwLockReadGuard<'_, bool>> {
+        match self.alive.try_read() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+}
+
+impl<'a> SubInterpreter<'a> {
+    /// Run a function with the GIL held and this sub-interpreter active.
+    ///
+    /// This is the equivalent of [MainPythonInterpreter::with_gil()] for the
+    /// sub-interpreter. Python objects must not be shared across interpreters,
+    /// so don't let objects obtained here escape the function.
+    ///
+    /// Panics if the current thread holds the GIL.
+    pub fn with_gil<F, R>(&self, f: F) -> R
+    where
+        F: for<'py> FnOnce(Python<'py>) -> R,
+    {
+        let _guard = unsafe { ThreadStateGuard::new(self.thread_state) };
+        let pool = unsafe { Python::assume_gil_acquired().new_pool() };
+
+        f(pool.python())
+    }
+
+    /// Run a function with the GIL held in this sub-interpreter on the current thread.
+    ///
+    /// Unlike [Self::with_gil()], this creates a thread state for the current
+    /// thread and destroys it afterwards. So it can be called from any thread.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure the sub-interpreter isn't dropped while the function
+    /// runs.
+    pub(crate) unsafe fn with_gil_any_thread<F, R>(&self, f: F) -> R
+    where
+        F: for<'py> FnOnce(Python<'py>) -> R,
+    {
+        let _guard = TemporaryThreadStateGuard::new(self.interpreter_state);
+        let pool = Python::assume_gil_acquired().new_pool();
+
+        f(pool.python())
+    }
+}
+
+/// Acquires the GIL with a new thread state for an interpreter.
+///
+/// The thread state is destroyed and the GIL released when dropped.
+struct TemporaryThreadStateGuard {
+    thread_state: *mut pyffi::PyThreadState,
+}
+
+impl TemporaryThreadStateGuard {
+    /// Acquire the GIL and make a new thread state for `interpreter_state` current.
+    unsafe fn new(interpreter_state: *mut pyffi::PyInterpreterState) -> Self {
+        assert_gil_not_held();
+        let thread_state = pyffi::PyThreadState_New(interpreter_state);
+        pyffi::PyEval_RestoreThread(thread_state);
+        ENTERED_THREAD_STATES.with(|count| count.set(count.get() + 1));
+
+        Self { thread_state }
+    }
+}
+
+impl Drop for TemporaryThreadStateGuard {
+    fn drop(&mut self) {
+        unsafe {
+            // Another thread state may have been made current, e.g. by
+            // Py_NewInterpreter(). Clearing requires the GIL. Deleting requires
+            // the thread state to not be current.
+            pyffi::PyThreadState_Swap(self.thread_state);
+            pyffi::PyThreadState_Clear(self.thread_state);
+            pyffi::PyEval_SaveThread();
+            pyffi::PyThreadState_Delete(self.thread_state);
+        }
+        ENTERED_THREAD_STATES.with(|count| count.set(count.get() - 1));
+    }
+}
+
+impl<'a> Drop for SubInterpreter<'a> {
+    fn drop(&mut self) {
+        // Py_EndInterpreter() requires the sub-interpreter's thread state to be
+        // current and leaves no thread state current. The guard restores a
+        // thread state of the main interpreter to release the GIL.
+        let _guard = unsafe { TemporaryThreadStateGuard::new(pyffi::PyInterpreterState_Main()) };
+
+        unsafe {
+            pyffi::PyThreadState_Swap(self.thread_state);
+            pyffi::Py_EndInterpreter(self.thread_state);
+        }
+    }
+}
+
+/// Construct a `RustStream` instance forwarding to an [OutputStream].
+fn new_rust_stream<'py>(
+    py: Python<'py>,
+    stream_type: &'py PyAny,
+    name: &str,
+    writer: OutputStream,
+) -> PyResult<&'py PyAny> {
+    let bytes_writer = writer.clone();
+    let flush_writer = writer.clone();
+
+    let write = PyCFunction::new_closure(
+        move |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<()> {
+            let (value,) = args.extract::<(&str,)>()?;
+            writer
+                .write_str(value)
+                .map_err(|e| PyOSError::new_err(e.to_string()))
+        },
+        py,
+    )?;
+    let write_bytes = PyCFunction::new_closure(
+        move |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<()> {
+            let (value,) = args.extract::<(&[u8],)>()?;
+            bytes_writer
+                .write_bytes(value)
+                .map_err(|e| PyOSError::new_err(e.to_string()))
+        },
+        py,
+    )?;
+    let flush = PyCFunction::new_closure(
+        move |_args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<()> {
+            flush_writer
+                .flush()
+                .map_err(|e| PyOSError::new_err(e.to_string()))
+        },
+        py,
+    )?;
+
+    stream_type.call1((format!("<{}>", name), write, write_bytes, flush))
+}
+
 static mut ORIGINAL_BUILTIN_EXTENSIONS: Option<Vec<pyffi::_inittab>> = None;
 static mut REPLACED_BUILTIN_EXTENSIONS: Option<Vec<pyffi::_inittab>> = None;
 
@@ -732,9 +1955,12 @@ impl<'interpreter, 'resources> Drop for MainPythonInterpreter<'interpreter, 'res
         // muck with the interpreter after finalization because this will likely result
         // in a segfault.
         if unsafe { pyffi::Py_IsInitialized() } == 0 {
+            self.restore_process_state();
             return;
         }
 
+        self.disable_handles();
+
         if let Some(path) = self.write_modules_path.as_ref() {
             match self.with_gil(|py| write_modules_to_path(py, path)) {
                 Ok(_) => {}
@@ -748,5 +1974,7 @@ impl<'interpreter, 'resources> Drop for MainPythonInterpreter<'interpreter, 'res
             pyffi::PyGILState_Ensure();
             pyffi::Py_FinalizeEx();
         }
+
+        self.restore_process_state();
     }
 }
@@ -187,6 +187,37 @@ fn set_legacy_windows_stdio(config: &mut pyffi::PyConfig, value: bool) {
     config.legacy_windows_stdio = if value { 1 } else { 0 };
 }
 
+#[cfg(Py_3_9)]
+fn set_platlibdir(config: &pyffi::PyConfig, value: &str) -> Result<(), NewInterpreterError> {
+    set_config_string_from_str(config, &config.platlibdir, value, "setting platlibdir")
+}
+
+#[cfg(not(Py_3_9))]
+fn set_platlibdir(_config: &pyffi::PyConfig, _value: &str) -> Result<(), NewInterpreterError> {
+    Err(NewInterpreterError::Simple(
+        "platlibdir is only supported on Python 3.9+",
+    ))
+}
+
+#[cfg(Py_3_10)]
+fn set_warn_default_encoding(
+    config: &mut pyffi::PyConfig,
+    value: bool,
+) -> Result<(), NewInterpreterError> {
+    config.warn_default_encoding = if value { 1 } else { 0 };
+    Ok(())
+}
+
+#[cfg(not(Py_3_10))]
+fn set_warn_default_encoding(
+    _config: &mut pyffi::PyConfig,
+    _value: bool,
+) -> Result<(), NewInterpreterError> {
+    Err(NewInterpreterError::Simple(
+        "warn_default_encoding is only supported on Python 3.10+",
+    ))
+}
+
 #[cfg(target_family = "unix")]
 pub fn set_argv(
     config: &mut pyffi::PyConfig,
@@ -335,6 +366,10 @@ pub fn python_interpreter_config_to_py_config(
     if let Some(tracemalloc) = value.tracemalloc {
         config.tracemalloc = if tracemalloc { 1 } else { 0 };
     }
+    if let Some(frames) = value.tracemalloc_frames {
+        config.tracemalloc = c_int::try_from(frames)
+            .map_err(|_| NewInterpreterError::Simple("tracemalloc_frames value is out of range"))?;
+    }
     if let Some(import_time) = value.import_time {
         config.import_time = if import_time { 1 } else { 0 };
     }
@@ -427,6 +462,9 @@ pub fn python_interpreter_config_to_py_config(
     if let Some(verbose) = value.verbose {
         config.verbose = if verbose { 1 } else { 0 };
     }
+    if let Some(warn_default_encoding) = value.warn_default_encoding {
+        set_warn_default_encoding(&mut config, warn_default_encoding)?;
+    }
     if let Some(quiet) = value.quiet {
         config.quiet = if quiet { 1 } else { 0 };
     }
@@ -474,6 +512,9 @@ pub fn python_interpreter_config_to_py_config(
     if let Some(pathconfig_warnings) = value.pathconfig_warnings {
         config.pathconfig_warnings = if pathconfig_warnings { 1 } else { 0 };
     }
+    if let Some(platlibdir) = &value.platlibdir {
+        set_platlibdir(&config, platlibdir)?;
+    }
     if let Some(python_path_env) = &value.python_path_env {
         set_config_string_from_str(
             &config,
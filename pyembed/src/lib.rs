@@ -48,6 +48,31 @@ The optional `allocator-snmalloc` feature controls support for using
 [snmalloc](https://github.com/microsoft/snmalloc) as Python's memory allocator.
 The feature behaves similarly to `jemalloc`, which is documented above.
 
+The default `resources-zstd` feature controls support for zstandard
+compressed in-memory resource data. It pulls in the `zstd` crate.
+
+The default `resources-encryption` feature controls support for encrypted
+in-memory resource data, whose key is provided by
+[OxidizedPythonInterpreterConfig::packed_resources_decryption_key_hook]. It pulls
+in the `aes`, `hmac`, and `sha2` crates.
+
+The default `resources-sha256` feature controls support for verifying SHA-256
+digests of in-memory resource data, as enabled by
+[OxidizedPythonInterpreterConfig::verify_resources]. It pulls in the `sha2`
+crate.
+
+The default `resources-signatures` feature controls support for verifying
+ed25519 signatures of packed resources data, as enabled by
+[OxidizedPythonInterpreterConfig::packed_resources_public_key]. It pulls in the
+`ed25519-dalek` crate.
+
+The default `resources-patch` feature provides [apply_packed_resources_patch()].
+It implies `resources-signatures` and pulls in the `sha2` crate.
+
+Packed resources data using a feature that isn't compiled in fails to load
+with an error. To keep binaries small, disable default features and only
+enable the features in use.
+
 The optional `serialization` feature controls whether configuration types
 (such as [OxidizedPythonInterpreterConfig]) implement `Serialize` and
 `Deserialize`.
@@ -60,29 +85,37 @@ mod error;
 mod interpreter;
 mod interpreter_config;
 mod osutils;
+mod pool;
 mod pyalloc;
 pub mod technotes;
 #[cfg(test)]
 mod test;
+#[cfg(feature = "resources-patch")]
+mod update;
 
 #[allow(unused_imports)]
 pub use {
     crate::{
         config::{
-            ExtensionModule, OxidizedPythonInterpreterConfig,
-            ResolvedOxidizedPythonInterpreterConfig,
+            AtExitHook, DecryptionKeyHook, ExceptHook, ExtensionModule, InitializeHook,
+            OutputStream, OxidizedPythonInterpreterConfig, OxidizedPythonInterpreterConfigBuilder,
+            PreInitializeHook, ResolvedOxidizedPythonInterpreterConfig, RustFunction, RustModule,
         },
         error::NewInterpreterError,
-        interpreter::MainPythonInterpreter,
+        interpreter::{InterpreterHandle, MainPythonInterpreter, PythonRunOutcome, SubInterpreter},
+        pool::InterpreterPool,
         pyalloc::PythonMemoryAllocator,
     },
     oxidized_importer::{PackedResourcesSource, PythonResourcesState},
     python_packaging::{
         interpreter::{
-            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-            MultiprocessingStartMethod, PythonInterpreterConfig, PythonInterpreterProfile,
-            TerminfoResolution,
+            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, ImportPrecedence,
+            MemoryAllocatorBackend, MultiprocessingStartMethod, PythonInterpreterConfig,
+            PythonInterpreterProfile, TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
 };
+
+#[cfg(feature = "resources-patch")]
+pub use crate::update::apply_packed_resources_patch;
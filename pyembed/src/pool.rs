@@ -0,0 +1,159 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Pools of pre-initialized sub-interpreters.
+
+use {
+    crate::{
+        error::NewInterpreterError,
+        interpreter::{MainPythonInterpreter, SubInterpreter},
+    },
+    pyo3::{prelude::*, PyResult},
+    std::sync::{Condvar, Mutex},
+};
+
+/// A pool of sub-interpreters for running Python code from worker threads.
+///
+/// Creating an interpreter and importing modules is expensive. A pool creates
+/// a fixed number of sub-interpreters up front via
+/// [MainPythonInterpreter::new_sub_interpreter()], so they have importers and
+/// packed resources configured. Each call to [Self::with_interpreter()] checks
+/// out an idle sub-interpreter, blocking until one is available, and runs a
+/// function in it on the calling thread.
+///
+/// Between uses, the `__main__` module of the sub-interpreter is replaced by an
+/// empty module. Other state, such as imported modules and their attributes, is
+/// preserved so the cost of importing is amortized across uses. Code must not
+/// rely on changes to such state for isolation between uses.
+///
+/// All sub-interpreters share the GIL. So a pool allows many threads to use
+/// Python without sharing Python state between them, not parallel execution of
+/// Python code.
+///
+/// A pool can be shared with other threads (e.g. via [std::thread::scope()]) but
+/// isn't [Send]: its sub-interpreters are destroyed when it is dropped, which
+/// must happen on the thread that created them. See [SubInterpreter] for the
+/// invariants of using sub-interpreters, which also apply to
+/// [Self::with_interpreter()].
+pub struct InterpreterPool<'a> {
+    idle: Mutex<Vec<SubInterpreter<'a>>>,
+    available: Condvar,
+    size: usize,
+}
+
+// Other threads only move sub-interpreters in and out of the pool and enter
+// them via thread states created for the calling thread. The thread states
+// owned by sub-interpreters are only used when they are dropped along with the
+// pool, which isn't Send and so is dropped on the thread that created them.
+unsafe impl<'a> Sync for InterpreterPool<'a> {}
+
+impl<'a> InterpreterPool<'a> {
+    /// Create a pool of `size` sub-interpreters of a [MainPythonInterpreter].
+    pub fn new(
+        interpreter: &'a MainPythonInterpreter<'_, '_>,
+        size: usize,
+    ) -> Result<Self, NewInterpreterError> {
+        if size == 0 {
+            return Err(NewInterpreterError::Simple(
+                "interpreter pool must have at least 1 interpreter",
+            ));
+        }
+
+        let interpreters = (0..size)
+            .map(|_| interpreter.new_sub_interpreter())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            idle: Mutex::new(interpreters),
+            available: Condvar::new(),
+            size,
+        })
+    }
+
+    /// The number of sub-interpreters in the pool.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Run a function with the GIL held in an idle sub-interpreter.
+    ///
+    /// Blocks until a sub-interpreter is available. Python objects must not
+    /// escape the function.
+    ///
+    /// Panics if the current thread holds the GIL.
+    pub fn with_interpreter<F, R>(&self, f: F) -> R
+    where
+        F: for<'py> FnOnce(Python<'py>) -> R,
+    {
+        let checkout = self.checkout();
+
+        // The interpreter was checked out of the pool, so no other thread is
+        // using it. And it isn't dropped until it is returned to the pool.
+        unsafe {
+            checkout.interpreter().with_gil_any_thread(|py| {
+                let res = f(py);
+
+                if let Err(e) = reset_main_module(py) {
+                    e.print(py);
+                }
+
+                res
+            })
+        }
+    }
+
+    fn checkout(&self) -> Checkout<'_, 'a> {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+
+        loop {
+            if let Some(interpreter) = idle.pop() {
+                return Checkout {
+                    pool: self,
+                    interpreter: Some(interpreter),
+                };
+            }
+
+            idle = self.available.wait(idle).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+/// A sub-interpreter checked out of an [InterpreterPool].
+///
+/// Returns the sub-interpreter to the pool when dropped.
+struct Checkout<'pool, 'a> {
+    pool: &'pool InterpreterPool<'a>,
+    interpreter: Option<SubInterpreter<'a>>,
+}
+
+impl<'pool, 'a> Checkout<'pool, 'a> {
+    fn interpreter(&self) -> &SubInterpreter<'a> {
+        self.interpreter
+            .as_ref()
+            .expect("interpreter should be checked out")
+    }
+}
+
+impl<'pool, 'a> Drop for Checkout<'pool, 'a> {
+    fn drop(&mut self) {
+        if let Some(interpreter) = self.interpreter.take() {
+            self.pool
+                .idle
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(interpreter);
+            self.pool.available.notify_one();
+        }
+    }
+}
+
+/// Replace `sys.modules["__main__"]` with a new, empty module.
+fn reset_main_module(py: Python) -> PyResult<()> {
+    let main = PyModule::new(py, "__main__")?;
+    main.setattr("__builtins__", py.import("builtins")?)?;
+
+    py.import("sys")?
+        .getattr("modules")?
+        .set_item("__main__", main)
+}
@@ -470,6 +470,11 @@ pub struct PythonMemoryAllocator {
     instance: AllocatorInstance,
 }
 
+// The raw pointers held by the allocator are only handed to the Python
+// interpreter and the allocation tracker is protected by a mutex.
+unsafe impl Send for PythonMemoryAllocator {}
+unsafe impl Sync for PythonMemoryAllocator {}
+
 impl PythonMemoryAllocator {
     /// Construct an instance from a `MemoryAllocatorBackend`.
     ///
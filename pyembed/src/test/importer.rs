@@ -77,6 +77,12 @@ rusty_fork_test! {
         });
     }
 
+    /// Run test_importer_audit.py.
+    #[test]
+    fn importer_audit_py() {
+        run_py_test("test_importer_audit.py").unwrap()
+    }
+
     /// Run test_importer_builtins.py.
     #[test]
     fn builtins_py() {
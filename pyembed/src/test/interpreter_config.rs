@@ -419,6 +419,43 @@ rusty_fork_test! {
         });
     }
 
+    /// `parse_argv` makes the interpreter process `python` command line flags.
+    #[test]
+    fn test_parse_argv_python_flags() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.parse_argv = Some(true);
+        for arg in ["-O", "-B", "-c", "pass", "foo"] {
+            config.argv.as_mut().unwrap().push(OsString::from(arg));
+        }
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+            let flags = sys.getattr("flags").unwrap();
+
+            assert_eq!(
+                flags.getattr("optimize").unwrap().extract::<i32>().unwrap(),
+                1
+            );
+            assert_eq!(
+                flags
+                    .getattr("dont_write_bytecode")
+                    .unwrap()
+                    .extract::<i32>()
+                    .unwrap(),
+                1
+            );
+
+            let argv = sys
+                .getattr("argv")
+                .unwrap()
+                .extract::<Vec<String>>()
+                .unwrap();
+            assert_eq!(argv, vec!["-c", "foo"]);
+        });
+    }
+
     #[test]
     fn test_argvb_utf8() {
         let mut config = default_interpreter_config();
@@ -449,6 +486,35 @@ rusty_fork_test! {
         });
     }
 
+    #[test]
+    fn test_argvb_override() {
+        let mut config = default_interpreter_config();
+        config.argv.as_mut().unwrap().push(OsString::from("foo"));
+        config.interpreter_config.argv = Some(vec![OsString::from("should-be-ignored")]);
+        config.argvb = true;
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+
+            let argvb_raw = sys.getattr("argvb").unwrap();
+            let argvb = argvb_raw.cast_as::<PyList>().unwrap();
+            assert_eq!(argvb.len(), 2);
+
+            let value_raw = argvb.get_item(1).unwrap();
+            let value_bytes = value_raw.cast_as::<PyBytes>().unwrap();
+            assert_eq!(
+                value_bytes.as_bytes().to_vec(),
+                if cfg!(windows) {
+                    b"f\0o\0o\0".to_vec()
+                } else {
+                    b"foo".to_vec()
+                }
+            );
+        });
+    }
+
     #[test]
     fn test_argv_utf8() {
         let mut config = default_interpreter_config();
@@ -570,6 +636,70 @@ rusty_fork_test! {
         });
     }
 
+    #[test]
+    fn test_fault_handler() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.fault_handler = Some(true);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let faulthandler = py.import("faulthandler").unwrap();
+
+            assert!(faulthandler
+                .call_method0("is_enabled")
+                .unwrap()
+                .extract::<bool>()
+                .unwrap());
+        });
+    }
+
+    #[test]
+    fn test_tracemalloc_frames() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.tracemalloc_frames = Some(5);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let tracemalloc = py.import("tracemalloc").unwrap();
+
+            assert!(tracemalloc
+                .call_method0("is_tracing")
+                .unwrap()
+                .extract::<bool>()
+                .unwrap());
+            assert_eq!(
+                tracemalloc
+                    .call_method0("get_traceback_limit")
+                    .unwrap()
+                    .extract::<i64>()
+                    .unwrap(),
+                5
+            );
+        });
+    }
+
+    #[test]
+    fn test_pycache_prefix() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.pycache_prefix = Some(PathBuf::from("/pycache"));
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+
+            assert_eq!(
+                sys.getattr("pycache_prefix")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "/pycache"
+            );
+        });
+    }
+
     #[test]
     fn test_use_environment() {
         let mut config = default_interpreter_config();
@@ -601,6 +731,27 @@ rusty_fork_test! {
         });
     }
 
+    #[cfg(Py_3_9)]
+    #[test]
+    fn test_platlibdir() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.platlibdir = Some("lib".into());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+
+            assert_eq!(
+                sys.getattr("platlibdir")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "lib"
+            );
+        });
+    }
+
     #[test]
     fn test_bytes_warning_warn() {
         let mut config = default_interpreter_config();
@@ -779,4 +930,35 @@ rusty_fork_test! {
             assert_eq!(flags.getattr("dont_write_bytecode").unwrap().extract::<i64>().unwrap(), 1);
         });
     }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_repl_line_editing() {
+        let temp_dir = std::env::temp_dir().join(format!("pyembed-repl-{}", std::process::id()));
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let history_file = temp_dir.join("history");
+        std::fs::write(&history_file, "x = 1\n").unwrap();
+
+        let mut config = default_interpreter_config();
+        config.repl_line_editing = true;
+        config.repl_history_file = Some(history_file);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+            sys.getattr("__interactivehook__").unwrap().call0().unwrap();
+
+            let readline = py.import("readline").unwrap();
+            assert_eq!(
+                readline.getattr("get_current_history_length").unwrap().call0().unwrap().extract::<i64>().unwrap(),
+                1
+            );
+
+            let completer = readline.getattr("get_completer").unwrap().call0().unwrap();
+            assert_eq!(completer.call1(("isinst", 0)).unwrap().extract::<String>().unwrap(), "isinstance(");
+        });
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
 }
@@ -4,11 +4,58 @@
 
 use {
     super::{default_interpreter_config, run_py_test},
-    crate::MainPythonInterpreter,
-    pyo3::ffi as pyffi,
+    crate::{
+        ExtensionModule, InterpreterPool, MainPythonInterpreter, NewInterpreterError, OutputStream,
+        PythonRunOutcome, RustFunction, RustModule,
+    },
+    oxidized_importer::{PackedResourcesSource, PythonResourcesState},
+    pyo3::{
+        exceptions::PyValueError,
+        ffi as pyffi,
+        prelude::*,
+        types::{PyDict, PyTuple},
+    },
+    python_packaging::interpreter::MemoryAllocatorBackend,
+    python_packed_resources::Resource,
     rusty_fork::rusty_fork_test,
+    std::{
+        ffi::CString,
+        io::Write,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+    },
 };
 
+#[pymodule]
+fn pyembed_test_module(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("value", 42)?;
+
+    Ok(())
+}
+
+/// A [std::io::Write] appending to a shared buffer.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 rusty_fork_test! {
     #[test]
     fn test_instantiate_interpreter() {
@@ -35,6 +82,677 @@ rusty_fork_test! {
         std::mem::drop(interp);
     }
 
+    #[test]
+    fn run_zipapp() {
+        let temp_dir = std::env::temp_dir().join(format!("pyembed-zipapp-{}", std::process::id()));
+        let source_dir = temp_dir.join("app");
+        std::fs::create_dir_all(&source_dir).unwrap();
+        std::fs::write(
+            source_dir.join("__main__.py"),
+            "import sys\nsys.exit(42 if sys.argv[0].endswith('app.pyz') and sys.path[0] == sys.argv[0] else 1)\n",
+        )
+        .unwrap();
+        let archive = temp_dir.join("app.pyz");
+
+        let status = std::process::Command::new(super::PYTHON_INTERPRETER_PATH)
+            .arg("-m")
+            .arg("zipapp")
+            .arg(&source_dir)
+            .arg("-o")
+            .arg(&archive)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let mut config = default_interpreter_config();
+        config.run_zipapp = Some(archive);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        let code = interp.run();
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+        assert_eq!(code, 42);
+    }
+
+    #[test]
+    fn startup_commands() {
+        let mut config = default_interpreter_config();
+        config.startup_commands = vec![
+            "value = 20".to_string(),
+            "value += 22\nimport linecache\nsource = linecache.getline('<startup-1>', 2)".to_string(),
+        ];
+        config.interpreter_config.run_command =
+            Some("assert value == 42 and source == 'import linecache\\n'".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(interp.run(), 0);
+    }
+
+    #[test]
+    fn startup_commands_error() {
+        let mut config = default_interpreter_config();
+        config.startup_commands = vec!["raise ValueError('startup')".to_string()];
+        config.interpreter_config.run_command = Some("import sys; sys.exit(42)".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(interp.run(), 1);
+    }
+
+    #[test]
+    fn startup_commands_system_exit() {
+        let mut config = default_interpreter_config();
+        config.startup_commands = vec!["import sys; sys.exit(3)".to_string()];
+        config.interpreter_config.run_command = Some("import sys; sys.exit(42)".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(interp.run(), 3);
+    }
+
+    #[test]
+    fn run_outcome_success() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.run_command = Some("value = 42".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(
+            interp.run_outcome().unwrap(),
+            PythonRunOutcome {
+                exit_code: 0,
+                system_exit: false
+            }
+        );
+
+        interp.with_gil(|py| {
+            let main = py.import("__main__").unwrap();
+            assert_eq!(
+                main.getattr("value").unwrap().extract::<i32>().unwrap(),
+                42
+            );
+        });
+    }
+
+    #[test]
+    fn run_outcome_system_exit() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.run_command = Some("import sys; sys.exit(7)".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(
+            interp.run_outcome().unwrap(),
+            PythonRunOutcome {
+                exit_code: 7,
+                system_exit: true
+            }
+        );
+    }
+
+    #[test]
+    fn run_outcome_exception() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.run_module = Some("json.tool".to_string());
+        config.startup_commands = vec!["raise ValueError('startup')".to_string()];
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        let err = interp.run_outcome().unwrap_err();
+
+        interp.with_gil(|py| {
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert_eq!(err.value(py).to_string(), "startup");
+        });
+    }
+
+    #[test]
+    fn excepthook() {
+        fn hook(py: Python, exc_type: &PyAny, value: &PyAny, traceback: &PyAny) {
+            assert!(exc_type.is(py.get_type::<PyValueError>()));
+            assert!(!traceback.is_none());
+            py.import("builtins")
+                .unwrap()
+                .setattr("hooked", value.str().unwrap())
+                .unwrap();
+        }
+
+        let mut config = default_interpreter_config();
+        config.excepthook = Some(hook);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            py.run(
+                "import sys\ntry:\n    raise ValueError('boom')\nexcept ValueError:\n    sys.excepthook(*sys.exc_info())",
+                None,
+                None,
+            )
+            .unwrap();
+
+            let builtins = py.import("builtins").unwrap();
+            assert_eq!(
+                builtins.getattr("hooked").unwrap().extract::<String>().unwrap(),
+                "boom"
+            );
+        });
+    }
+
+    #[test]
+    fn excepthook_source_lines() {
+        let mut state = PythonResourcesState::default();
+        state
+            .add_resource(Resource {
+                name: "boom".into(),
+                is_python_module: true,
+                in_memory_source: Some(
+                    b"def boom():\n    raise ValueError('boom')\n".to_vec().into(),
+                ),
+                ..Default::default()
+            })
+            .unwrap();
+        let data = state.serialize_resources(true, true).unwrap();
+
+        let mut config = default_interpreter_config();
+        config.oxidized_importer = true;
+        config.packed_resources.push(PackedResourcesSource::Memory(&data));
+
+        // The default hook is kept unless requested otherwise.
+        {
+            let interp = MainPythonInterpreter::new(config.clone()).unwrap();
+            interp.with_gil(|py| {
+                let sys = py.import("sys").unwrap();
+                assert!(sys
+                    .getattr("excepthook")
+                    .unwrap()
+                    .is(sys.getattr("__excepthook__").unwrap()));
+            });
+        }
+
+        let stderr = SharedBuffer::default();
+
+        config.traceback_excepthook = true;
+        config.stderr_writer = Some(OutputStream::new(stderr.clone()));
+        config.interpreter_config.run_command = Some("import boom; boom.boom()".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(interp.run(), 1);
+
+        let output = stderr.contents();
+        assert!(
+            output.contains("  File \"boom\", line 2, in boom\n    raise ValueError('boom')\n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn appended_zip_importer() {
+        let exe = std::env::temp_dir().join(format!("pyembed-appended-zip-{}", std::process::id()));
+
+        let mut f = std::fs::File::create(&exe).unwrap();
+        f.write_all(b"not a zip archive\n").unwrap();
+        let mut zf = zip::ZipWriter::new(f);
+        zf.start_file("appended.py", zip::write::FileOptions::default())
+            .unwrap();
+        zf.write_all(b"value = 42\n").unwrap();
+        zf.finish().unwrap();
+
+        let mut config = default_interpreter_config();
+        config.exe = Some(exe.clone());
+        config.appended_zip_importer = true;
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let m = py.import("appended").unwrap();
+            assert_eq!(m.getattr("value").unwrap().extract::<i64>().unwrap(), 42);
+            assert_eq!(
+                m.getattr("__loader__").unwrap().get_type().name().unwrap(),
+                "OxidizedZipFinder"
+            );
+        });
+
+        std::fs::remove_file(&exe).unwrap();
+    }
+
+    #[test]
+    fn appended_zip_importer_no_archive() {
+        let mut config = default_interpreter_config();
+        config.appended_zip_importer = true;
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let meta_path = py.import("sys").unwrap().getattr("meta_path").unwrap();
+
+            for finder in meta_path.iter().unwrap() {
+                assert_ne!(finder.unwrap().get_type().name().unwrap(), "OxidizedZipFinder");
+            }
+        });
+    }
+
+    #[test]
+    fn run_frozen_main() {
+        let mut state = PythonResourcesState::default();
+        state
+            .add_resource(Resource {
+                name: "frozen_main".into(),
+                is_python_module: true,
+                in_memory_source: Some(
+                    b"import sys\nsys.exit(42 if __name__ == '__main__' else 1)\n"
+                        .to_vec()
+                        .into(),
+                ),
+                ..Default::default()
+            })
+            .unwrap();
+        let data = state.serialize_resources(true, true).unwrap();
+
+        let mut config = default_interpreter_config();
+        config.packed_resources.push(PackedResourcesSource::Memory(&data));
+        config.run_frozen_main = Some("frozen_main".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(interp.run(), 42);
+    }
+
+    #[test]
+    fn zstd_compressed_resources() {
+        let mut package_resources = std::collections::HashMap::new();
+        package_resources.insert("data.txt".into(), b"resource data".to_vec().into());
+
+        let mut state = PythonResourcesState::default();
+        state
+            .add_resource(
+                Resource {
+                    name: "compressed".into(),
+                    is_python_module: true,
+                    is_python_package: true,
+                    in_memory_source: Some(b"value = 42\n".to_vec().into()),
+                    in_memory_package_resources: Some(package_resources),
+                    ..Default::default()
+                }
+                .zstd_compress(3)
+                .unwrap(),
+            )
+            .unwrap();
+        let data = state.serialize_resources(true, true).unwrap();
+
+        let mut config = default_interpreter_config();
+        config.oxidized_importer = true;
+        config.packed_resources.push(PackedResourcesSource::Memory(&data));
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let module = py.import("compressed").unwrap();
+            assert_eq!(
+                module.getattr("value").unwrap().extract::<i32>().unwrap(),
+                42
+            );
+
+            let data = py
+                .import("importlib.resources")
+                .unwrap()
+                .call_method1("read_binary", ("compressed", "data.txt"))
+                .unwrap()
+                .extract::<Vec<u8>>()
+                .unwrap();
+            assert_eq!(data, b"resource data");
+        });
+    }
+
+    #[test]
+    fn sub_interpreter() {
+        let config = default_interpreter_config();
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        let sub = interp.new_sub_interpreter().unwrap();
+        sub.with_gil(|py| {
+            py.run("import json\nvalue = 42", None, None).unwrap();
+        });
+        sub.with_gil(|py| {
+            assert_eq!(py.eval("value", None, None).unwrap().extract::<i32>().unwrap(), 42);
+        });
+
+        interp.with_gil(|py| {
+            assert!(py.eval("value", None, None).is_err());
+            let modules = py.import("sys").unwrap().getattr("modules").unwrap();
+            assert!(!modules.contains("json").unwrap());
+        });
+
+        let other = interp.new_sub_interpreter().unwrap();
+        other.with_gil(|py| {
+            assert!(py.eval("value", None, None).is_err());
+        });
+
+        // Entering a sub-interpreter while holding the GIL would deadlock.
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            interp.with_gil(|_| sub.with_gil(|_| ()));
+        }));
+        assert!(res.is_err());
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            sub.with_gil(|_| other.with_gil(|_| ()));
+        }));
+        assert!(res.is_err());
+
+        std::mem::drop(sub);
+        std::mem::drop(other);
+
+        interp.with_gil(|py| {
+            py.import("sys").unwrap();
+        });
+    }
+
+    #[test]
+    fn extension_module_pyo3() {
+        let mut config = default_interpreter_config();
+        config.extra_extension_modules = Some(vec![ExtensionModule {
+            name: CString::new("pyembed_test_module").unwrap(),
+            init_func: pyembed_test_module::init,
+        }]);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        interp.with_gil(|py| {
+            let module = py.import("pyembed_test_module").unwrap();
+            assert_eq!(module.getattr("value").unwrap().extract::<i32>().unwrap(), 42);
+        });
+    }
+
+    #[test]
+    fn rust_module() {
+        fn add(py: Python, args: &PyTuple, _kwargs: Option<&PyDict>) -> PyResult<PyObject> {
+            let (a, b) = args.extract::<(i64, i64)>()?;
+            Ok((a + b).into_py(py))
+        }
+
+        let mut config = default_interpreter_config();
+        config.rust_modules.push(RustModule {
+            name: "pyembed_rust".to_string(),
+            functions: [("add".to_string(), add as RustFunction)].into_iter().collect(),
+        });
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        interp.with_gil(|py| {
+            py.run(
+                "import pyembed_rust\nassert pyembed_rust.add(40, 2) == 42",
+                None,
+                None,
+            )
+            .unwrap();
+            assert!(py.run("import pyembed_rust\npyembed_rust.add('a')", None, None).is_err());
+        });
+    }
+
+    #[test]
+    fn initialize_hooks() {
+        static PRE_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+        fn pre_initialize() -> Result<(), NewInterpreterError> {
+            PRE_INITIALIZED.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn core_initialize(py: Python) -> PyResult<()> {
+            assert!(PRE_INITIALIZED.load(Ordering::SeqCst));
+            py.run(
+                "import builtins, sys\nbuiltins.pyembed_core = 'site' not in sys.modules",
+                None,
+                None,
+            )
+        }
+
+        fn post_initialize(py: Python) -> PyResult<()> {
+            py.run("import sys\nsys.pyembed_post = pyembed_core", None, None)
+        }
+
+        let mut config = default_interpreter_config();
+        config.pre_initialize_hook = Some(pre_initialize);
+        config.core_initialize_hook = Some(core_initialize);
+        config.post_initialize_hook = Some(post_initialize);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        interp.with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+            assert!(sys.getattr("pyembed_post").unwrap().extract::<bool>().unwrap());
+        });
+    }
+
+    #[test]
+    fn stdio_writers() {
+        let stdout = SharedBuffer::default();
+        let stderr = SharedBuffer::default();
+
+        let mut config = default_interpreter_config();
+        config.stdout_writer = Some(OutputStream::new(stdout.clone()));
+        config.stderr_writer = Some(OutputStream::new(stderr.clone()));
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        interp.with_gil(|py| {
+            py.run(
+                "import sys\n\
+                 print('hello', '\u{e9}')\n\
+                 sys.stderr.write('error\\n')\n\
+                 sys.stdout.flush()\n\
+                 assert sys.stdout.buffer.write(b'bytes\\n') == 6\n\
+                 sys.stderr.buffer.write(memoryview(b'raw'))\n\
+                 sys.stdout.buffer.flush()\n\
+                 assert sys.stdout.writable()\n\
+                 assert sys.__stdout__ is not sys.stdout\n",
+                None,
+                None,
+            )
+            .unwrap();
+            assert!(py.run("import sys\nsys.stdout.write(b'x')", None, None).is_err());
+        });
+
+        assert_eq!(stdout.contents(), "hello \u{e9}\nbytes\n");
+        assert_eq!(stderr.contents(), "error\nraw");
+    }
+
+    #[test]
+    fn atexit_hooks() {
+        static CALLED_AFTER_PYTHON: AtomicBool = AtomicBool::new(false);
+
+        fn hook(py: Python) -> PyResult<()> {
+            let ran = py
+                .eval("__import__('builtins').pyembed_atexit_ran", None, None)?
+                .extract::<bool>()?;
+            CALLED_AFTER_PYTHON.store(ran, Ordering::SeqCst);
+
+            Ok(())
+        }
+
+        fn failing_hook(_py: Python) -> PyResult<()> {
+            Err(PyValueError::new_err("hook failed"))
+        }
+
+        let mut config = default_interpreter_config();
+        config.atexit_hooks = vec![failing_hook, hook];
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        interp.with_gil(|py| {
+            py.run(
+                "import atexit, builtins\n\
+                 builtins.pyembed_atexit_ran = False\n\
+                 atexit.register(lambda: setattr(builtins, 'pyembed_atexit_ran', True))\n",
+                None,
+                None,
+            )
+            .unwrap();
+        });
+        assert!(!CALLED_AFTER_PYTHON.load(Ordering::SeqCst));
+
+        std::mem::drop(interp);
+
+        assert!(CALLED_AFTER_PYTHON.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn initialize_hook_error() {
+        fn post_initialize(_py: Python) -> PyResult<()> {
+            Err(PyValueError::new_err("hook failed"))
+        }
+
+        let mut config = default_interpreter_config();
+        config.post_initialize_hook = Some(post_initialize);
+
+        let err = MainPythonInterpreter::new(config).err().unwrap();
+        assert!(err.to_string().contains("hook failed"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn rust_signal_handlers() {
+        let mut config = default_interpreter_config();
+        config.rust_signal_handlers = true;
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(interp.received_signal(), None);
+
+        interp.with_gil(|py| {
+            py.run(
+                "import signal\n\
+                 try:\n    \
+                     signal.raise_signal(signal.SIGTERM)\n    \
+                     raise AssertionError('not interrupted')\n\
+                 except KeyboardInterrupt:\n    \
+                     pass\n",
+                None,
+                None,
+            )
+            .unwrap();
+        });
+
+        assert_eq!(interp.received_signal(), Some(libc::SIGTERM));
+
+        let handler = unsafe { libc::signal(libc::SIGTERM, libc::SIG_DFL) };
+        assert_ne!(handler, libc::SIG_DFL);
+        unsafe {
+            libc::signal(libc::SIGTERM, handler);
+        }
+
+        std::mem::drop(interp);
+
+        let handler = unsafe { libc::signal(libc::SIGTERM, libc::SIG_DFL) };
+        assert_eq!(handler, libc::SIG_DFL);
+    }
+
+    #[test]
+    fn interpreter_pool() {
+        let config = default_interpreter_config();
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        assert!(InterpreterPool::new(&interp, 0).is_err());
+
+        let pool = InterpreterPool::new(&interp, 2).unwrap();
+        assert_eq!(pool.size(), 2);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for i in 0..5 {
+                        let value = pool.with_interpreter(|py| {
+                            py.run(
+                                "import json\nassert 'value' not in globals()\nvalue = 42",
+                                None,
+                                None,
+                            )
+                            .unwrap();
+                            py.eval(&format!("value + {}", i), None, None)
+                                .unwrap()
+                                .extract::<i32>()
+                                .unwrap()
+                        });
+                        assert_eq!(value, 42 + i);
+                    }
+                });
+            }
+        });
+
+        interp.with_gil(|py| {
+            assert!(py.eval("value", None, None).is_err());
+        });
+
+        std::mem::drop(pool);
+
+        interp.with_gil(|py| {
+            py.import("sys").unwrap();
+        });
+    }
+
+    #[test]
+    fn interpreter_handle() {
+        let config = default_interpreter_config();
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        interp.with_gil(|py| {
+            py.run("import builtins\nbuiltins.pyembed_value = 42", None, None).unwrap();
+        });
+
+        let handle = interp.handle();
+        assert!(handle.is_alive());
+
+        let threads = (0..4)
+            .map(|i| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    handle
+                        .with_gil(|py| {
+                            py.eval(&format!("pyembed_value + {}", i), None, None)
+                                .unwrap()
+                                .extract::<i32>()
+                                .unwrap()
+                        })
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let values = threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![42, 43, 44, 45]);
+
+        std::mem::drop(interp);
+
+        assert!(!handle.is_alive());
+        assert!(handle.with_gil(|_| ()).is_none());
+    }
+
+    #[test]
+    fn reinitialize_interpreter() {
+        for i in 0..3 {
+            let mut config = default_interpreter_config();
+            config.allocator_backend = MemoryAllocatorBackend::Rust;
+            config.allocator_raw = true;
+            config.tcl_library = Some("/pyembed-tcl".into());
+
+            let interp = MainPythonInterpreter::new(config).unwrap();
+            assert_eq!(std::env::var("TCL_LIBRARY").unwrap(), "/pyembed-tcl");
+            interp.with_gil(|py| {
+                let locals = pyo3::types::PyDict::new(py);
+                locals.set_item("i", i).unwrap();
+                py.run(
+                    "import decimal, json\nassert json.loads(json.dumps([i])) == [i]\nassert decimal.Decimal(i) == i",
+                    None,
+                    Some(locals),
+                )
+                .unwrap();
+            });
+            std::mem::drop(interp);
+
+            assert!(std::env::var_os("TCL_LIBRARY").is_none());
+        }
+    }
+
+    #[test]
+    fn reinitialize_allocator_change() {
+        let config = default_interpreter_config();
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        std::mem::drop(interp);
+
+        let mut config = default_interpreter_config();
+        config.allocator_backend = MemoryAllocatorBackend::Rust;
+        config.allocator_raw = true;
+        assert!(MainPythonInterpreter::new(config).is_err());
+    }
+
     #[test]
     fn multiprocessing_py() {
         run_py_test("test_multiprocessing.py").unwrap()
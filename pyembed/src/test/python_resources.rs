@@ -46,6 +46,250 @@ fn multiple_resource_blobs() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn zstd_compressed_resources() -> Result<()> {
+    let resource = Resource {
+        name: "foo".into(),
+        is_python_module: true,
+        in_memory_source: Some(b"import io\n".to_vec().into()),
+        ..Default::default()
+    };
+
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(resource.clone().zstd_compress(3)?)
+        .unwrap();
+    let data0 = state0.serialize_resources(true, true)?;
+
+    let mut resources = PythonResourcesState::default();
+    resources.index_data(&data0).unwrap();
+
+    let entry = resources.get_resource("foo").unwrap().unwrap();
+    assert!(!entry.in_memory_zstd_compressed);
+    assert_eq!(entry.in_memory_source, resource.in_memory_source);
+
+    // Decompressed data is retained.
+    assert!(std::ptr::eq(
+        entry,
+        resources.get_resource("foo").unwrap().unwrap()
+    ));
+
+    // Uncompressed data for the same resource can be merged.
+    let mut state1 = PythonResourcesState::default();
+    state1
+        .add_resource(Resource {
+            name: "foo".into(),
+            relative_path_module_source: Some(std::path::Path::new("foo.py").into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data1 = state1.serialize_resources(true, true)?;
+    resources.index_data(&data1).unwrap();
+
+    let entry = resources.get_resource("foo").unwrap().unwrap();
+    assert!(!entry.in_memory_zstd_compressed);
+    assert_eq!(entry.in_memory_source, resource.in_memory_source);
+    assert!(entry.relative_path_module_source.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn name_indexed_resources() -> Result<()> {
+    let resources = (0..100)
+        .map(|i| Resource {
+            name: format!("module{}", i).into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![i as u8].into()),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut data0 = vec![];
+    python_packed_resources::write_packed_resources_with_options(
+        &resources,
+        &mut data0,
+        &python_packed_resources::WriteOptions {
+            name_index: true,
+            ..Default::default()
+        },
+    )?;
+
+    let mut state = PythonResourcesState::default();
+    state.index_data(&data0).unwrap();
+
+    for resource in &resources {
+        assert!(state.has_resource(&resource.name));
+        assert_eq!(state.get_resource(&resource.name).unwrap(), Some(resource));
+    }
+    assert!(!state.has_resource("missing"));
+
+    // Resources of other data are merged into indexed resources.
+    let mut state1 = PythonResourcesState::default();
+    for name in ["module42", "other"] {
+        state1
+            .add_resource(Resource {
+                name: name.into(),
+                is_python_package: true,
+                ..Default::default()
+            })
+            .unwrap();
+    }
+    let data1 = state1.serialize_resources(true, true)?;
+    state.index_data(&data1).unwrap();
+
+    let entry = state.get_resource("module42").unwrap().unwrap();
+    assert!(entry.is_python_package);
+    assert_eq!(entry.in_memory_source, resources[42].in_memory_source);
+    assert!(state.has_resource("other"));
+    assert!(state.is_package("other"));
+
+    Ok(())
+}
+
+#[test]
+fn verify_resources() -> Result<()> {
+    let resource = Resource {
+        name: "foo".into(),
+        is_python_module: true,
+        in_memory_source: Some(b"import io\n".to_vec().into()),
+        ..Default::default()
+    }
+    .with_in_memory_sha256();
+
+    let mut state0 = PythonResourcesState::default();
+    state0.add_resource(resource.clone()).unwrap();
+    let data0 = state0.serialize_resources(true, true)?;
+
+    let mut resources = PythonResourcesState::default();
+    resources.set_verify_resources(true);
+    resources.index_data(&data0).unwrap();
+    let entry = resources.get_resource("foo").unwrap().unwrap();
+    assert_eq!(entry.in_memory_sha256, resource.in_memory_sha256);
+    assert_eq!(entry.in_memory_source, resource.in_memory_source);
+
+    let mut corrupted = data0.clone();
+    let offset = corrupted
+        .windows(9)
+        .position(|window| window == b"import io")
+        .unwrap();
+    corrupted[offset] = b'x';
+
+    // Corruption is only detected when verification is enabled.
+    let mut resources = PythonResourcesState::default();
+    resources.index_data(&corrupted).unwrap();
+    assert!(resources.get_resource("foo").unwrap().is_some());
+
+    let mut resources = PythonResourcesState::default();
+    resources.set_verify_resources(true);
+    resources.index_data(&corrupted).unwrap();
+    assert_eq!(
+        resources.get_resource("foo"),
+        Err("packed resources data is corrupt: resource data does not match its SHA-256 digest")
+    );
+
+    // Replacing module data discards the digest.
+    resources
+        .add_module_source("foo", b"import os\n".to_vec(), false)
+        .unwrap();
+    assert!(resources.get_resource("foo").unwrap().is_some());
+
+    Ok(())
+}
+
+#[test]
+fn signed_packed_resources() -> Result<()> {
+    let secret_key = [42; 32];
+    let public_key = python_packed_resources::ed25519_public_key(&secret_key)?;
+
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data0 = state0.serialize_resources(true, true)?;
+    let signed = python_packed_resources::sign_packed_resources(&data0, &secret_key)?;
+
+    let config = OxidizedPythonInterpreterConfig::builder()
+        .oxidized_importer(true)
+        .packed_resources_public_key(public_key)
+        .packed_resources(PackedResourcesSource::Memory(&signed))
+        .build()?
+        .resolve()?;
+    let resources = PythonResourcesState::try_from(&config)?;
+    assert!(resources.has_resource("foo"));
+
+    // Unsigned data is rejected.
+    let config = OxidizedPythonInterpreterConfig::builder()
+        .oxidized_importer(true)
+        .packed_resources_public_key(public_key)
+        .packed_resources(PackedResourcesSource::Memory(&data0))
+        .build()?
+        .resolve()?;
+    assert!(PythonResourcesState::try_from(&config).is_err());
+
+    // So is data signed with a different key.
+    let other = python_packed_resources::sign_packed_resources(&data0, &[1; 32])?;
+    let mut resources = PythonResourcesState::default();
+    resources.set_packed_resources_public_key(Some(public_key));
+    assert_eq!(
+        resources.index_data(&other),
+        Err("packed resources signature verification failed")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn encrypted_resources() -> Result<()> {
+    fn decryption_key() -> Result<[u8; 32], crate::NewInterpreterError> {
+        Ok([42; 32])
+    }
+
+    let resource = Resource {
+        name: "foo".into(),
+        is_python_module: true,
+        in_memory_source: Some(b"import io\n".to_vec().into()),
+        ..Default::default()
+    };
+
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(resource.clone().zstd_compress(3)?.encrypt(&[42; 32])?)
+        .unwrap();
+    let data0 = state0.serialize_resources(true, true)?;
+
+    let config = OxidizedPythonInterpreterConfig::builder()
+        .oxidized_importer(true)
+        .packed_resources_decryption_key_hook(decryption_key)
+        .packed_resources(PackedResourcesSource::Memory(&data0))
+        .build()?
+        .resolve()?;
+    let resources = PythonResourcesState::try_from(&config)?;
+
+    let entry = resources.get_resource("foo").unwrap().unwrap();
+    assert!(!entry.in_memory_encrypted);
+    assert!(!entry.in_memory_zstd_compressed);
+    assert_eq!(entry.in_memory_source, resource.in_memory_source);
+
+    // Encrypted data can't be accessed without the key.
+    let mut resources = PythonResourcesState::default();
+    resources.index_data(&data0).unwrap();
+    assert!(resources.get_resource("foo").is_err());
+
+    resources.set_decryption_key(Some([1; 32]));
+    assert_eq!(
+        resources.get_resource("foo"),
+        Err("error decrypting resource data")
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_memory_mapped_file_resources() -> Result<()> {
     let current_dir = std::env::current_exe()?
@@ -99,6 +343,220 @@ fn test_memory_mapped_file_resources() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_file_resources_precedence() -> Result<()> {
+    let current_dir = std::env::current_exe()?
+        .parent()
+        .ok_or_else(|| anyhow!("unable to find current exe parent"))?
+        .to_path_buf();
+
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data0 = state0.serialize_resources(true, true)?;
+
+    let mut state1 = PythonResourcesState::default();
+    state1
+        .add_resource(Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42, 42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    state1
+        .add_resource(Resource {
+            name: "bar".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data1 = state1.serialize_resources(true, true)?;
+
+    let resources_dir = current_dir.join("resources");
+    if !resources_dir.exists() {
+        std::fs::create_dir(&resources_dir)?;
+    }
+
+    let resources_path = resources_dir.join("test_file_resources_precedence");
+    std::fs::write(&resources_path, &data1)?;
+
+    // Later sources take precedence over earlier ones.
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config
+        .packed_resources
+        .push(PackedResourcesSource::Memory(&data0));
+    config
+        .packed_resources
+        .push(PackedResourcesSource::Path(resources_path.clone()));
+
+    let resolved = config.resolve()?;
+    let resources = PythonResourcesState::try_from(&resolved)?;
+
+    assert!(resources.has_resource("bar"));
+    assert_eq!(
+        resources
+            .get_resource("foo")
+            .unwrap()
+            .unwrap()
+            .in_memory_source,
+        Some(vec![42, 42].into())
+    );
+
+    // Missing files are an error.
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config
+        .packed_resources
+        .push(PackedResourcesSource::Path(resources_dir.join("missing")));
+    let resolved = config.resolve()?;
+    assert!(PythonResourcesState::try_from(&resolved).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn injected_resources() -> Result<()> {
+    let current_dir = std::env::current_exe()?
+        .parent()
+        .ok_or_else(|| anyhow!("unable to find current exe parent"))?
+        .to_path_buf();
+
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data0 = state0.serialize_resources(true, true)?;
+
+    let resources_dir = current_dir.join("resources");
+    if !resources_dir.exists() {
+        std::fs::create_dir(&resources_dir)?;
+    }
+
+    let exe_path = resources_dir.join("injected_resources");
+    std::fs::write(&exe_path, b"binary content")?;
+
+    // A binary without injected resources loads nothing.
+    let config = OxidizedPythonInterpreterConfig::builder()
+        .oxidized_importer(true)
+        .exe(&exe_path)
+        .packed_resources(PackedResourcesSource::CurrentExecutable)
+        .build()?
+        .resolve()?;
+    let resources = PythonResourcesState::try_from(&config)?;
+    assert!(!resources.has_resource("foo"));
+
+    let injected = python_packed_resources::inject_packed_resources(b"binary content", &data0)?;
+    std::fs::write(&exe_path, &injected)?;
+
+    let resources = PythonResourcesState::try_from(&config)?;
+    assert!(resources.has_resource("foo"));
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_packed_resources_patch() -> Result<()> {
+    let current_dir = std::env::current_exe()?
+        .parent()
+        .ok_or_else(|| anyhow!("unable to find current exe parent"))?
+        .to_path_buf();
+
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data0 = state0.serialize_resources(true, true)?;
+
+    let mut state1 = PythonResourcesState::default();
+    state1
+        .add_resource(Resource {
+            name: "bar".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data1 = state1.serialize_resources(true, true)?;
+
+    let resources_dir = current_dir.join("resources");
+    if !resources_dir.exists() {
+        std::fs::create_dir(&resources_dir)?;
+    }
+
+    let secret_key = [42; 32];
+    let public_key = python_packed_resources::ed25519_public_key(&secret_key)?;
+    let data0 = python_packed_resources::sign_packed_resources(&data0, &secret_key)?;
+
+    let resources_path = resources_dir.join("test_apply_packed_resources_patch");
+    std::fs::write(&resources_path, &data0)?;
+
+    let patch = python_packed_resources::create_packed_resources_patch(
+        &data0,
+        &data1,
+        &Default::default(),
+        Some(&secret_key),
+    )?;
+    let signed = python_packed_resources::sign_packed_resources(&patch, &secret_key)?;
+
+    // Unsigned patches are rejected when a key is given.
+    assert!(
+        crate::apply_packed_resources_patch(&resources_path, &patch, Some(&public_key)).is_err()
+    );
+    assert_eq!(std::fs::read(&resources_path)?, data0);
+
+    // So are patches producing unsigned data.
+    let unsigned_result = python_packed_resources::sign_packed_resources(
+        &python_packed_resources::create_packed_resources_patch(
+            &data0,
+            &data1,
+            &Default::default(),
+            None,
+        )?,
+        &secret_key,
+    )?;
+    assert!(crate::apply_packed_resources_patch(
+        &resources_path,
+        &unsigned_result,
+        Some(&public_key)
+    )
+    .is_err());
+    assert_eq!(std::fs::read(&resources_path)?, data0);
+
+    crate::apply_packed_resources_patch(&resources_path, &signed, Some(&public_key))?;
+
+    let config = OxidizedPythonInterpreterConfig {
+        packed_resources: vec![PackedResourcesSource::Path(resources_path.clone())],
+        packed_resources_public_key: Some(public_key),
+        ..Default::default()
+    };
+    let resolved = config.resolve()?;
+    let resources = PythonResourcesState::try_from(&resolved)?;
+    assert!(resources.has_resource("bar"));
+    assert!(!resources.has_resource("foo"));
+
+    // The patch no longer applies to the updated file.
+    assert!(crate::apply_packed_resources_patch(&resources_path, &patch, None).is_err());
+
+    Ok(())
+}
+
 fn get_interpreter<'interp, 'rsrc>() -> crate::MainPythonInterpreter<'interp, 'rsrc> {
     let mut config = crate::OxidizedPythonInterpreterConfig::default();
     config.interpreter_config.parse_argv = Some(false);
@@ -0,0 +1,134 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import importlib
+import os
+import pathlib
+import sys
+import tempfile
+import unittest
+
+from oxidized_importer import (
+    OxidizedFinder,
+    OxidizedResourceCollector,
+    find_resources_in_path,
+)
+
+# Audit hooks can't be removed. So a single hook records events while a test
+# is running.
+EVENTS = None
+
+
+def audit_hook(event, args):
+    if EVENTS is not None and event.startswith("oxidized_importer."):
+        EVENTS.append((event, args))
+
+
+sys.addaudithook(audit_hook)
+
+
+class TestImporterAudit(unittest.TestCase):
+    def setUp(self):
+        global EVENTS
+        EVENTS = []
+
+        self.raw_temp_dir = tempfile.TemporaryDirectory(
+            prefix="oxidized_importer-test-"
+        )
+        self.td = pathlib.Path(self.raw_temp_dir.name)
+        self.old_meta_path = list(sys.meta_path)
+        self.modules_before = set(sys.modules)
+
+    def tearDown(self):
+        global EVENTS
+        EVENTS = None
+
+        sys.meta_path[:] = self.old_meta_path
+        for name in set(sys.modules) - self.modules_before:
+            del sys.modules[name]
+
+        self.raw_temp_dir.cleanup()
+        del self.raw_temp_dir
+        del self.td
+
+    def _finder_from_td(self):
+        collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
+        for r in find_resources_in_path(self.td):
+            collector.add_in_memory(r)
+
+        f = OxidizedFinder()
+        f.add_resources(collector.oxidize()[0])
+
+        return f
+
+    def test_exec_module(self):
+        with (self.td / "my_module.py").open("wb") as fh:
+            fh.write(b"value = 42\n")
+
+        f = self._finder_from_td()
+        sys.meta_path.insert(0, f)
+
+        m = importlib.import_module("my_module")
+        self.assertEqual(m.value, 42)
+
+        self.assertEqual(
+            EVENTS, [("oxidized_importer.exec_module", ("my_module", None))]
+        )
+
+    def test_resource_read(self):
+        p = self.td / "my_package"
+        p.mkdir()
+
+        with (p / "__init__.py").open("wb"):
+            pass
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(b"my resource")
+
+        f = self._finder_from_td()
+        reader = f.get_resource_reader("my_package")
+        EVENTS.clear()
+
+        with reader.open_resource("resource.txt") as fh:
+            self.assertEqual(fh.read(), b"my resource")
+
+        self.assertEqual(
+            EVENTS,
+            [("oxidized_importer.resource_read", ("my_package", "resource.txt"))],
+        )
+
+        # Missing resources don't raise an event.
+        EVENTS.clear()
+
+        with self.assertRaises(FileNotFoundError):
+            reader.open_resource("missing.txt")
+
+        self.assertEqual(EVENTS, [])
+
+    def test_get_data(self):
+        f = OxidizedFinder()
+        path = os.path.join(f.path_hook_base_str, "missing.txt")
+
+        with self.assertRaises(OSError):
+            f.get_data(path)
+
+        self.assertEqual(EVENTS, [("oxidized_importer.get_data", (path,))])
+
+    def test_hook_aborts(self):
+        f = OxidizedFinder()
+        f.add_module_source("blocked_module", "value = 42\n")
+        sys.meta_path.insert(0, f)
+
+        def hook(event, args):
+            if event == "oxidized_importer.exec_module" and args[0] == "blocked_module":
+                raise PermissionError("blocked")
+
+        sys.addaudithook(hook)
+
+        with self.assertRaises(PermissionError):
+            importlib.import_module("blocked_module")
+
+
+if __name__ == "__main__":
+    unittest.main()
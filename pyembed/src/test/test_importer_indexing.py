@@ -11,6 +11,7 @@ import unittest
 from oxidized_importer import (
     OxidizedResourceCollector,
     OxidizedFinder,
+    OxidizedResource,
     find_resources_in_path,
 )
 
@@ -66,6 +67,26 @@ class TestImporterConstruction(unittest.TestCase):
 
         f.index_bytes(self.get_resources_data())
 
+    def test_index_bytes_metadata(self):
+        resource = OxidizedResource()
+        resource.name = "foo"
+        resource.is_module = True
+        resource.metadata = {"license": "MIT", "wheel": "foo-1.0-py3-none-any.whl"}
+
+        f = OxidizedFinder()
+        f.add_resource(resource)
+        data = f.serialize_indexed_resources()
+
+        f = OxidizedFinder()
+        f.index_bytes(data)
+
+        resources = [r for r in f.indexed_resources() if r.name == "foo"]
+        self.assertEqual(len(resources), 1)
+        self.assertEqual(
+            resources[0].metadata,
+            {"license": "MIT", "wheel": "foo-1.0-py3-none-any.whl"},
+        )
+
     def test_index_file_memory_mapped_no_file(self):
         f = OxidizedFinder()
 
@@ -166,6 +166,43 @@ class TestImporterIterModules(unittest.TestCase):
                 prefix = on.__name__ + "."
                 assert_iter_modules(prefix, prefixed, on.__path__, prefix)
 
+    def test_walk_packages(self):
+        self._make_package("a.b")
+        (self.td / "a" / "b" / "mod.py").touch()
+        (self.td / "a" / "c.py").touch()
+        (self.td / "ns").mkdir()
+        (self.td / "ns" / "leaf.py").touch()
+        (self.td / "one.py").touch()
+
+        f = self._finder_from_td()
+
+        sys.path = [sys.executable]
+        sys.meta_path = [f]
+        with patch.dict(sys.modules), patch.dict(
+            sys.path_importer_cache, clear=True
+        ), patch.object(sys, "path_hooks", [f.path_hook]):
+            res = list(pkgutil.walk_packages())
+            self.assertCountEqual(
+                [(mi.name, mi.ispkg) for mi in res],
+                [
+                    ("a", True),
+                    ("a.b", True),
+                    ("a.b.mod", False),
+                    ("a.c", False),
+                    ("ns", True),
+                    ("ns.leaf", False),
+                    ("one", False),
+                ],
+            )
+
+            import a
+
+            res = list(pkgutil.walk_packages(a.__path__, a.__name__ + "."))
+            self.assertCountEqual(
+                [(mi.name, mi.ispkg) for mi in res],
+                [("a.b", True), ("a.b.mod", False), ("a.c", False)],
+            )
+
 
 if __name__ == "__main__":
     unittest.main()
@@ -291,6 +291,91 @@ class TestImporterMetadata(unittest.TestCase):
         self.assertIsInstance(requires, list)
         self.assertEqual(requires, ["foo"])
 
+    def test_files_missing(self):
+        self._write_metadata()
+        f = self._finder_from_td()
+
+        dists = list(f.find_distributions())
+        self.assertIsNone(dists[0].files)
+
+    def test_files_record(self):
+        self._write_metadata()
+
+        record_path = self.td / "my_package-1.0.dist-info" / "RECORD"
+        with record_path.open("w", encoding="utf-8") as fh:
+            fh.write("my_package/__init__.py,sha256=abcd,42\n")
+            fh.write("my_package-1.0.dist-info/RECORD,,\n")
+
+        f = self._finder_from_td()
+
+        dist = list(f.find_distributions())[0]
+        files = dist.files
+        self.assertIsInstance(files, list)
+        self.assertEqual(len(files), 2)
+
+        self.assertIsInstance(files[0], importlib.metadata.PackagePath)
+        self.assertEqual(str(files[0]), "my_package/__init__.py")
+        self.assertEqual(files[0].hash.mode, "sha256")
+        self.assertEqual(files[0].hash.value, "abcd")
+        self.assertEqual(files[0].size, 42)
+        self.assertIs(files[0].dist, dist)
+
+        self.assertEqual(str(files[1]), "my_package-1.0.dist-info/RECORD")
+        self.assertIsNone(files[1].hash)
+        self.assertIsNone(files[1].size)
+
+    def test_files_sources(self):
+        metadata_path = self.td / "my_package.egg-info" / "PKG-INFO"
+        metadata_path.parent.mkdir()
+
+        with metadata_path.open("w", encoding="utf-8") as fh:
+            fh.write("Name: my_package\n")
+            fh.write("Version: 1.0\n")
+
+        with (metadata_path.parent / "SOURCES.txt").open("w", encoding="utf-8") as fh:
+            fh.write("my_package/__init__.py\n")
+            fh.write("my_package/a,b.py\n")
+
+        f = self._finder_from_td()
+
+        files = list(f.find_distributions())[0].files
+        self.assertEqual(
+            [str(p) for p in files], ["my_package/__init__.py", "my_package/a,b.py"]
+        )
+        self.assertIsNone(files[0].hash)
+
+    def test_importlib_metadata_functions(self):
+        self._write_metadata()
+
+        dist_info = self.td / "my_package-1.0.dist-info"
+        with (dist_info / "entry_points.txt").open("w", encoding="utf-8") as fh:
+            fh.write("[console_scripts]\n")
+            fh.write("my-script = my_package:main\n")
+        with (dist_info / "RECORD").open("w", encoding="utf-8") as fh:
+            fh.write("my_package/__init__.py,,\n")
+
+        f = self._finder_from_td()
+
+        sys.meta_path = [f]
+        sys.path = []
+
+        self.assertEqual(importlib.metadata.version("my_package"), "1.0")
+        metadata = importlib.metadata.metadata("my_package")
+        self.assertEqual(metadata["Name"], "my_package")
+        self.assertEqual(
+            [str(p) for p in importlib.metadata.files("my_package")],
+            ["my_package/__init__.py"],
+        )
+
+        eps = importlib.metadata.entry_points()
+        if hasattr(eps, "select"):
+            eps = eps.select(group="console_scripts")
+        else:
+            eps = eps["console_scripts"]
+
+        self.assertEqual([ep.name for ep in eps], ["my-script"])
+        self.assertEqual([ep.value for ep in eps], ["my_package:main"])
+
     def test_distribution_locate_file(self):
         self._write_metadata()
         f = self._finder_from_td()
@@ -18,6 +18,8 @@ SYMBOL_ATTRIBUTES = {
         "version",
     },
     "OxidizedFinder": {
+        "add_module_bytecode",
+        "add_module_source",
         "add_resource",
         "add_resources",
         "create_module",
@@ -40,10 +42,14 @@ SYMBOL_ATTRIBUTES = {
         "iter_modules",
         "multiprocessing_set_start_method",
         "origin",
+        "package_precedence",
         "path_hook",
         "path_hook_base_str",
+        "path_hook_virtual_str",
         "pkg_resources_import_auto_register",
+        "reload_from_filesystem",
         "serialize_indexed_resources",
+        "synthesize_dunder_file",
     },
     "OxidizedPathEntryFinder": {
         "_package",
@@ -81,6 +87,7 @@ SYMBOL_ATTRIBUTES = {
         "is_namespace_package",
         "is_package",
         "is_shared_library",
+        "metadata",
         "name",
         "relative_path_distribution_resources",
         "relative_path_extension_module_shared_library",
@@ -97,12 +104,47 @@ SYMBOL_ATTRIBUTES = {
         "allowed_locations",
         "oxidize",
     },
+    "OxidizedResourceFile": {
+        "__enter__",
+        "__exit__",
+        "__iter__",
+        "__next__",
+        "close",
+        "closed",
+        "flush",
+        "getbuffer",
+        "getvalue",
+        "isatty",
+        "read",
+        "read1",
+        "readable",
+        "readinto",
+        "readline",
+        "readlines",
+        "seek",
+        "seekable",
+        "tell",
+        "writable",
+    },
     "OxidizedResourceReader": {
         "contents",
+        "files",
         "is_resource",
         "open_resource",
         "resource_path",
     },
+    "OxidizedTraversable": {
+        "__rtruediv__",
+        "__truediv__",
+        "is_dir",
+        "is_file",
+        "iterdir",
+        "joinpath",
+        "name",
+        "open",
+        "read_bytes",
+        "read_text",
+    },
     "OxidizedZipFinder": {
         "create_module",
         "exec_module",
@@ -2,18 +2,24 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import _imp
 import importlib.machinery
 import importlib.util
 import importlib
+import linecache
 import marshal
 import os
 import pathlib
+import py_compile
 import sys
 import tempfile
+import traceback
 import unittest
+import unittest.mock
 
 from oxidized_importer import (
     OxidizedFinder,
+    OxidizedResource,
     OxidizedResourceCollector,
     find_resources_in_path,
 )
@@ -45,12 +51,12 @@ class TestImporterModuleLoading(unittest.TestCase):
 
         return package_path
 
-    def _finder_from_td(self):
+    def _finder_from_td(self, **kwargs):
         collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
         for r in find_resources_in_path(self.td):
             collector.add_in_memory(r)
 
-        f = OxidizedFinder()
+        f = OxidizedFinder(**kwargs)
         f.add_resources(collector.oxidize()[0])
 
         return f
@@ -181,6 +187,437 @@ class TestImporterModuleLoading(unittest.TestCase):
         self.assertIn("dotinit", sys.modules)
         self.assertIn("dotinit.bar", sys.modules)
         self.assertNotIn("dotinit.__init__", sys.modules)
+    def test_namespace_package(self):
+        p = self.td / "namespace"
+        p.mkdir()
+
+        with (p / "child.py").open("wb") as fh:
+            fh.write(b"value = 42\n")
+
+        f = self._finder_from_td()
+
+        spec = f.find_spec("namespace", None)
+        self.assertIsInstance(spec, importlib.machinery.ModuleSpec)
+        self.assertEqual(spec.name, "namespace")
+        self.assertIsNone(spec.loader)
+        self.assertIsNone(spec.origin)
+        self.assertEqual(len(spec.submodule_search_locations), 1)
+
+        sys.meta_path.insert(0, f)
+
+        try:
+            namespace = importlib.import_module("namespace")
+            self.assertIsNone(namespace.__file__)
+            self.assertEqual(
+                list(namespace.__path__), list(spec.submodule_search_locations)
+            )
+
+            child = importlib.import_module("namespace.child")
+            self.assertEqual(child.value, 42)
+            self.assertIsInstance(child.__loader__, OxidizedFinder)
+        finally:
+            sys.modules.pop("namespace", None)
+            sys.modules.pop("namespace.child", None)
+
+    def test_namespace_package_split(self):
+        p = self.td / "namespace"
+        p.mkdir()
+
+        with (p / "embedded.py").open("wb") as fh:
+            fh.write(b"value = 'embedded'\n")
+
+        f = self._finder_from_td()
+
+        with tempfile.TemporaryDirectory(prefix="oxidized_importer-test-") as td:
+            fs_path = pathlib.Path(td) / "namespace"
+            fs_path.mkdir()
+
+            with (fs_path / "filesystem.py").open("wb") as fh:
+                fh.write(b"value = 'filesystem'\n")
+
+            old_path = list(sys.path)
+            sys.path.insert(0, td)
+            sys.meta_path.insert(0, f)
+            importlib.invalidate_caches()
+
+            try:
+                namespace = importlib.import_module("namespace")
+                self.assertIn(str(fs_path), list(namespace.__path__))
+
+                embedded = importlib.import_module("namespace.embedded")
+                self.assertEqual(embedded.value, "embedded")
+                self.assertIsInstance(embedded.__loader__, OxidizedFinder)
+
+                filesystem = importlib.import_module("namespace.filesystem")
+                self.assertEqual(filesystem.value, "filesystem")
+                self.assertNotIsInstance(filesystem.__loader__, OxidizedFinder)
+            finally:
+                sys.path[:] = old_path
+                for name in ("namespace", "namespace.embedded", "namespace.filesystem"):
+                    sys.modules.pop(name, None)
+
+    def test_synthesize_dunder_file(self):
+        p = self._make_package("my_package")
+
+        with (p / "mod.py").open("wb") as fh:
+            fh.write(b"import io\n")
+
+        with (p / "data.txt").open("wb") as fh:
+            fh.write(b"data")
+
+        f = self._finder_from_td()
+        self.assertFalse(f.synthesize_dunder_file)
+
+        f = self._finder_from_td(synthesize_dunder_file=True)
+        self.assertTrue(f.synthesize_dunder_file)
+
+        package_file = os.path.join(f.path_hook_base_str, "my_package", "__init__.py")
+        module_file = os.path.join(f.path_hook_base_str, "my_package", "mod.py")
+
+        spec = f.find_spec("my_package", None)
+        self.assertEqual(spec.origin, package_file)
+        self.assertTrue(spec.has_location)
+        self.assertEqual(
+            spec.submodule_search_locations,
+            [os.path.join(f.path_hook_base_str, "my_package")],
+        )
+
+        m = importlib.util.module_from_spec(spec)
+        self.assertEqual(m.__file__, package_file)
+        self.assertEqual(m.__cached__, importlib.util.cache_from_source(package_file))
+        self.assertEqual(f.get_filename("my_package"), package_file)
+
+        spec = f.find_spec("my_package.mod", None)
+        self.assertEqual(spec.origin, module_file)
+
+        m = importlib.util.module_from_spec(spec)
+        self.assertEqual(m.__file__, module_file)
+        self.assertEqual(f.get_filename("my_package.mod"), module_file)
+
+        # The synthesized path can be used to resolve resource data.
+        self.assertEqual(
+            f.get_data(os.path.join(os.path.dirname(m.__file__), "data.txt")),
+            b"data",
+        )
+
+    def test_traceback_source(self):
+        p = self._make_package("my_package")
+
+        with (p / "boom.py").open("wb") as fh:
+            fh.write(b"def boom():\n    raise ValueError('boom')\n")
+
+        f = self._finder_from_td()
+        sys.meta_path.insert(0, f)
+
+        try:
+            m = importlib.import_module("my_package.boom")
+            filename = m.boom.__code__.co_filename
+
+            self.assertEqual(
+                linecache.getline(filename, 2, m.__dict__),
+                "    raise ValueError('boom')\n",
+            )
+
+            try:
+                m.boom()
+            except ValueError:
+                lines = traceback.format_exception(*sys.exc_info())
+            else:
+                self.fail("exception not raised")
+
+            self.assertIn(
+                '  File "%s", line 2, in boom\n'
+                "    raise ValueError('boom')\n" % filename,
+                lines,
+            )
+        finally:
+            linecache.clearcache()
+            sys.modules.pop("my_package", None)
+            sys.modules.pop("my_package.boom", None)
+
+    def test_add_module_source(self):
+        f = OxidizedFinder()
+        sys.meta_path.insert(0, f)
+
+        try:
+            f.add_module_source("my_package", b"", is_package=True)
+            f.add_module_source("my_package.plugin", "value = 42\n")
+
+            m = importlib.import_module("my_package.plugin")
+            self.assertEqual(m.value, 42)
+            self.assertIsInstance(m.__loader__, OxidizedFinder)
+            self.assertEqual(f.get_source("my_package.plugin"), "value = 42\n")
+            self.assertEqual(
+                sys.modules["my_package"].__path__,
+                [os.path.join(f.path_hook_base_str, "my_package")],
+            )
+        finally:
+            sys.modules.pop("my_package", None)
+            sys.modules.pop("my_package.plugin", None)
+
+    def test_add_module_bytecode(self):
+        f = OxidizedFinder()
+        sys.meta_path.insert(0, f)
+
+        f.add_module_source("my_module", "value = 'source'\n")
+        code = compile("value = 'bytecode'", "my_module", "exec")
+        f.add_module_bytecode("my_module", marshal.dumps(code))
+
+        try:
+            m = importlib.import_module("my_module")
+            self.assertEqual(m.value, "bytecode")
+            self.assertEqual(f.get_source("my_module"), "value = 'source'\n")
+
+            # Adding source replaces the bytecode.
+            f.add_module_source("my_module", "value = 'new'\n")
+            m = importlib.reload(m)
+            self.assertEqual(m.value, "new")
+        finally:
+            sys.modules.pop("my_module", None)
+
+    def test_index_bytes_after_startup(self):
+        p = self._make_package("my_package")
+
+        with (p / "plugin.py").open("wb") as fh:
+            fh.write(b"value = 42\n")
+
+        data = self._finder_from_td().serialize_indexed_resources()
+
+        f = OxidizedFinder()
+        sys.meta_path.insert(0, f)
+
+        try:
+            with self.assertRaises(ImportError):
+                importlib.import_module("my_package.plugin")
+
+            f.index_bytes(data)
+
+            m = importlib.import_module("my_package.plugin")
+            self.assertEqual(m.value, 42)
+        finally:
+            sys.modules.pop("my_package", None)
+            sys.modules.pop("my_package.plugin", None)
+
+    def test_package_precedence(self):
+        f = OxidizedFinder()
+        self.assertEqual(f.package_precedence, {})
+
+        f = OxidizedFinder(
+            package_precedence={"foo": "filesystem-first", "bar": "memory-only"}
+        )
+        self.assertEqual(
+            f.package_precedence, {"foo": "filesystem-first", "bar": "memory-only"}
+        )
+
+        with self.assertRaises(ValueError):
+            OxidizedFinder(package_precedence={"foo": "invalid"})
+
+    def _precedence_test(self, precedence):
+        p = self._make_package("my_package")
+
+        with (p / "__init__.py").open("wb") as fh:
+            fh.write(b"value = 'memory'\n")
+
+        with (p / "memory.py").open("wb") as fh:
+            fh.write(b"value = 'memory'\n")
+
+        f = self._finder_from_td(package_precedence={"my_package": precedence})
+
+        td = tempfile.TemporaryDirectory(prefix="oxidized_importer-test-")
+        self.addCleanup(td.cleanup)
+
+        fs_path = pathlib.Path(td.name) / "my_package"
+        fs_path.mkdir()
+
+        with (fs_path / "__init__.py").open("wb") as fh:
+            fh.write(b"value = 'filesystem'\n")
+
+        with (fs_path / "filesystem.py").open("wb") as fh:
+            fh.write(b"value = 'filesystem'\n")
+
+        old_path = list(sys.path)
+        sys.path.insert(0, td.name)
+        sys.meta_path.insert(0, f)
+        importlib.invalidate_caches()
+
+        def cleanup():
+            sys.path[:] = old_path
+            for name in ("my_package", "my_package.memory", "my_package.filesystem"):
+                sys.modules.pop(name, None)
+
+        self.addCleanup(cleanup)
+
+        return fs_path
+
+    def test_package_precedence_memory_first(self):
+        self._precedence_test("memory-first")
+
+        m = importlib.import_module("my_package")
+        self.assertEqual(m.value, "memory")
+        self.assertIsInstance(m.__loader__, OxidizedFinder)
+
+    def test_package_precedence_filesystem_first(self):
+        fs_path = self._precedence_test("filesystem-first")
+
+        m = importlib.import_module("my_package")
+        self.assertEqual(m.value, "filesystem")
+        self.assertNotIsInstance(m.__loader__, OxidizedFinder)
+        self.assertEqual(m.__path__, [str(fs_path)])
+
+        m = importlib.import_module("my_package.filesystem")
+        self.assertEqual(m.value, "filesystem")
+
+        # Modules missing from the filesystem fall back to memory.
+        m = importlib.import_module("my_package.memory")
+        self.assertEqual(m.value, "memory")
+        self.assertIsInstance(m.__loader__, OxidizedFinder)
+
+    def test_package_precedence_memory_only(self):
+        self._precedence_test("memory-only")
+
+        m = importlib.import_module("my_package")
+        self.assertEqual(m.value, "memory")
+
+        f = sys.meta_path[0]
+
+        with self.assertRaises(ModuleNotFoundError) as e:
+            f.find_spec("my_package.filesystem", [os.path.dirname(__file__)])
+
+        self.assertEqual(e.exception.name, "my_package.filesystem")
+
+    def test_reload(self):
+        f = OxidizedFinder()
+        f.add_module_source("my_module", "value = 1\n")
+        sys.meta_path.insert(0, f)
+        self.addCleanup(sys.modules.pop, "my_module", None)
+
+        m = importlib.import_module("my_module")
+        self.assertEqual(m.value, 1)
+
+        f.add_module_source("my_module", "value = 2\n")
+        self.assertIs(importlib.reload(m), m)
+        self.assertEqual(m.value, 2)
+        self.assertIsInstance(m.__loader__, OxidizedFinder)
+
+    def test_reload_from_filesystem(self):
+        f = OxidizedFinder(reload_from_filesystem=True)
+        self.assertTrue(f.reload_from_filesystem)
+        f.add_module_source("my_module", "value = 'memory'\n")
+
+        with (self.td / "my_module.py").open("wb") as fh:
+            fh.write(b"value = 'filesystem'\n")
+
+        old_path = list(sys.path)
+        sys.path.insert(0, str(self.td))
+        sys.meta_path.insert(0, f)
+        importlib.invalidate_caches()
+
+        def cleanup():
+            sys.path[:] = old_path
+            sys.modules.pop("my_module", None)
+
+        self.addCleanup(cleanup)
+
+        # Imports are serviced from memory. Reloads prefer the filesystem.
+        m = importlib.import_module("my_module")
+        self.assertEqual(m.value, "memory")
+
+        importlib.reload(m)
+        self.assertEqual(m.value, "filesystem")
+        self.assertNotIsInstance(m.__loader__, OxidizedFinder)
+        self.assertEqual(m.__file__, str(self.td / "my_module.py"))
+
+        # Modules missing from the filesystem are reloaded from memory.
+        (self.td / "my_module.py").unlink()
+        importlib.invalidate_caches()
+        importlib.reload(m)
+        self.assertEqual(m.value, "memory")
+
+    def _finder_with_pyc(self, name, invalidation_mode):
+        source_path = self.td / ("%s.py" % name)
+        bytecode_path = self.td / ("%s.pyc" % name)
+
+        with source_path.open("wb") as fh:
+            fh.write(b"value = 'bytecode'\n")
+
+        py_compile.compile(
+            str(source_path),
+            cfile=str(bytecode_path),
+            invalidation_mode=invalidation_mode,
+        )
+
+        # Make the source newer than the bytecode.
+        with source_path.open("wb") as fh:
+            fh.write(b"value = 'source'\n")
+
+        resource = OxidizedResource()
+        resource.is_module = True
+        resource.name = name
+        resource.relative_path_module_source = source_path.name
+        resource.relative_path_module_bytecode = bytecode_path.name
+
+        f = OxidizedFinder(relative_path_origin=str(self.td))
+        f.add_resource(resource)
+        sys.meta_path.insert(0, f)
+        self.addCleanup(sys.modules.pop, name, None)
+
+        return f
+
+    def test_checked_hash_pyc_stale(self):
+        self._finder_with_pyc("my_module", py_compile.PycInvalidationMode.CHECKED_HASH)
+
+        m = importlib.import_module("my_module")
+        self.assertEqual(m.value, "source")
+
+    def test_checked_hash_pyc_never(self):
+        self._finder_with_pyc("my_module", py_compile.PycInvalidationMode.CHECKED_HASH)
+
+        with unittest.mock.patch.object(_imp, "check_hash_based_pycs", "never"):
+            m = importlib.import_module("my_module")
+        self.assertEqual(m.value, "bytecode")
+
+    def test_unchecked_hash_pyc_stale(self):
+        self._finder_with_pyc(
+            "my_module", py_compile.PycInvalidationMode.UNCHECKED_HASH
+        )
+
+        m = importlib.import_module("my_module")
+        self.assertEqual(m.value, "bytecode")
+
+    def test_unchecked_hash_pyc_always(self):
+        self._finder_with_pyc(
+            "my_module", py_compile.PycInvalidationMode.UNCHECKED_HASH
+        )
+
+        with unittest.mock.patch.object(_imp, "check_hash_based_pycs", "always"):
+            m = importlib.import_module("my_module")
+        self.assertEqual(m.value, "source")
+
+    @unittest.skipUnless(sys.platform == "linux", "memfd_create() is Linux only")
+    def test_in_memory_extension_module(self):
+        spec = importlib.machinery.PathFinder.find_spec("_statistics")
+        if not isinstance(spec.loader, importlib.machinery.ExtensionFileLoader):
+            self.skipTest("_statistics isn't a shared library extension module")
+
+        with open(spec.origin, "rb") as fh:
+            library_data = fh.read()
+
+        r = OxidizedResource()
+        r.name = "_statistics"
+        r.is_extension_module = True
+        r.in_memory_extension_module_shared_library = library_data
+
+        f = OxidizedFinder()
+        f.add_resource(r)
+
+        spec = f.find_spec("_statistics", None)
+        self.assertIsInstance(spec.loader, OxidizedFinder)
+
+        m = importlib.util.module_from_spec(spec)
+        spec.loader.exec_module(m)
+
+        self.assertIsNone(spec.origin)
+        self.assertIsInstance(m._normal_dist_inv_cdf(0.5, 0.0, 1.0), float)
 
 
 if __name__ == "__main__":
@@ -23,6 +23,7 @@ if TYPE_CHECKING:
 PathLike = Union[str, bytes, os.PathLike]
 
 PATH_HOOK_BASE_STR = OxidizedFinder().path_hook_base_str
+PATH_HOOK_VIRTUAL_STR = "oxidized://resources"
 
 
 def make_finder(*modules: Tuple[str, str, bool]) -> OxidizedFinder:
@@ -152,6 +153,20 @@ class TestImporterPathEntryFinder(unittest.TestCase):
         with self.assertRaises(ImportError):
             f.path_hook(os.path.dirname(PATH_HOOK_BASE_STR))
 
+    def test_path_hook_virtual_str(self):
+        f = OxidizedFinder()
+        self.assertEqual(f.path_hook_virtual_str, PATH_HOOK_VIRTUAL_STR)
+        self.assertIsInstance(
+            f.path_hook(PATH_HOOK_VIRTUAL_STR), OxidizedPathEntryFinder
+        )
+
+    def test_path_hook_virtual_str_prefix_rejected(self):
+        f = OxidizedFinder()
+
+        for path in ("oxidized://", "oxidized://resourcesfoo", "oxidized://other"):
+            with self.assertRaises(ImportError):
+                f.path_hook(path)
+
     def test_path_hook_empty_rejected(self):
         f = OxidizedFinder()
 
@@ -232,6 +247,12 @@ class TestImporterPathEntryFinder(unittest.TestCase):
     def test_find_spec_top_level(self):
         self.assert_find_spec_top_level(PATH_HOOK_BASE_STR)
 
+    def test_find_spec_virtual_top_level(self):
+        self.assert_find_spec_top_level(PATH_HOOK_VIRTUAL_STR)
+
+    def test_find_spec_virtual_subdir(self):
+        self.assert_find_spec_nested(PATH_HOOK_VIRTUAL_STR + "/on")
+
     def assert_unicode_path(self, path: os.PathLike) -> None:
         finder = self.finder(path, "on.tשo")
         self.assert_spec(finder.find_spec("on.tשo.۳"), "on.tשo.۳", is_pkg=False)
@@ -264,6 +285,21 @@ class TestImporterPathEntryFinder(unittest.TestCase):
             spec, "pwd", is_pkg=False, Loader=sys.__spec__.loader, origin="built-in"
         )
 
+    def test_path_hook_installed_virtual(self):
+        if not PathFinder.find_spec("pwd"):
+            raise unittest.SkipTest("PathFinder failed to import pwd")
+
+        with patch("sys.path", sys.path):
+            sys.path = [p for p in sys.path if p != PATH_HOOK_BASE_STR]
+            PathFinder.invalidate_caches()
+            self.assertIsNone(PathFinder.find_spec("pwd"))
+
+            sys.path.append(PATH_HOOK_VIRTUAL_STR)
+            spec = PathFinder.find_spec("pwd")
+        self.assert_spec(
+            spec, "pwd", is_pkg=False, Loader=sys.__spec__.loader, origin="built-in"
+        )
+
     ############################################################################
     # Error Handling
 
@@ -239,7 +239,7 @@ class TestImporterPkgResources(unittest.TestCase):
             provider.get_resource_stream(None, "missing")
 
         fh = provider.get_resource_stream(None, "resource0.txt")
-        self.assertIsInstance(fh, io.BytesIO)
+        self.assertIsInstance(fh, io.BufferedIOBase)
         self.assertEqual(fh.read(), b"line0\nline1\n")
 
         with self.assertRaises(IOError):
@@ -254,6 +254,7 @@ class TestImporterPkgResources(unittest.TestCase):
         self.assertTrue(provider.has_resource("resource0.txt"))
         self.assertTrue(provider.has_resource("subdir/child0.txt"))
         self.assertTrue(provider.has_resource("subdir/grandchild/grandchild.txt"))
+        self.assertTrue(provider.has_resource("subdir\\grandchild\\grandchild.txt"))
 
         self.assertFalse(provider.resource_isdir("missing"))
         self.assertFalse(provider.resource_isdir("resource0.txt"))
@@ -267,10 +268,16 @@ class TestImporterPkgResources(unittest.TestCase):
         self.assertTrue(provider.resource_isdir("subdir\\grandchild\\"))
 
         self.assertEqual(provider.resource_listdir("missing"), [])
-        self.assertEqual(provider.resource_listdir(""), ["resource0.txt"])
-        self.assertEqual(provider.resource_listdir("subdir"), ["child0.txt"])
-        self.assertEqual(provider.resource_listdir("subdir/"), ["child0.txt"])
-        self.assertEqual(provider.resource_listdir("subdir\\"), ["child0.txt"])
+        self.assertEqual(provider.resource_listdir(""), ["resource0.txt", "subdir"])
+        self.assertEqual(
+            provider.resource_listdir("subdir"), ["child0.txt", "grandchild"]
+        )
+        self.assertEqual(
+            provider.resource_listdir("subdir/"), ["child0.txt", "grandchild"]
+        )
+        self.assertEqual(
+            provider.resource_listdir("subdir\\"), ["child0.txt", "grandchild"]
+        )
         self.assertEqual(
             provider.resource_listdir("subdir/grandchild"), ["grandchild.txt"]
         )
@@ -410,10 +417,11 @@ class TestImporterPkgResources(unittest.TestCase):
             pkg_resources.resource_exists("missing_package", "irrelevant")
 
         self.assertIsInstance(
-            pkg_resources.resource_stream("package0", "file0"), io.BytesIO
+            pkg_resources.resource_stream("package0", "file0"), io.BufferedIOBase
         )
         self.assertIsInstance(
-            pkg_resources.resource_stream("package0.p0child0", "childfile0"), io.BytesIO
+            pkg_resources.resource_stream("package0.p0child0", "childfile0"),
+            io.BufferedIOBase,
         )
         with self.assertRaises(OSError):
             pkg_resources.resource_stream("package0", "missing")
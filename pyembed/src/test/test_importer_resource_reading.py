@@ -2,16 +2,21 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import gc
+import importlib.resources
 import io
 import os
 import pathlib
+import sys
 import tempfile
 import unittest
 
 from oxidized_importer import (
     OxidizedFinder,
     OxidizedResourceCollector,
+    OxidizedResourceFile,
     OxidizedResourceReader,
+    OxidizedTraversable,
     find_resources_in_path,
 )
 
@@ -95,7 +100,8 @@ class TestImporterResourceReading(unittest.TestCase):
             r.open_resource("missing")
 
         f = r.open_resource("resource.txt")
-        self.assertIsInstance(f, io.BytesIO)
+        self.assertIsInstance(f, OxidizedResourceFile)
+        self.assertIsInstance(f, io.BufferedIOBase)
         self.assertEqual(f.getvalue(), b"my resource")
 
     def test_child_directory(self):
@@ -125,6 +131,225 @@ class TestImporterResourceReading(unittest.TestCase):
         self.assertEqual(r.open_resource("child0/a.txt").getvalue(), b"a")
         self.assertEqual(r.open_resource("child1/b.txt").getvalue(), b"b")
 
+    def test_nested_directory_windows_separators(self):
+        p = self._make_package("my_package")
+
+        (p / "data" / "sub").mkdir(parents=True)
+
+        with (p / "data" / "sub" / "file.json").open("wb") as fh:
+            fh.write(b"{}")
+
+        f = self._finder_from_td()
+        r = f.get_resource_reader("my_package")
+
+        self.assertEqual(r.contents(), ["data/sub/file.json"])
+        self.assertTrue(r.is_resource("data\\sub\\file.json"))
+        self.assertEqual(r.open_resource("data\\sub\\file.json").read(), b"{}")
+
+    def _open_packed_resource(self, data):
+        p = self._make_package("my_package")
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(data)
+
+        # Indexing serialized resources makes the finder reference resource
+        # data in the serialized buffer instead of owning copies of it.
+        f = OxidizedFinder()
+        f.index_bytes(self._finder_from_td().serialize_indexed_resources())
+
+        return f.get_resource_reader("my_package").open_resource("resource.txt")
+
+    def test_resource_file_read(self):
+        fh = self._open_packed_resource(b"line0\nline1\nline2")
+
+        self.assertTrue(fh.readable())
+        self.assertTrue(fh.seekable())
+        self.assertFalse(fh.writable())
+        self.assertEqual(fh.read(2), b"li")
+        self.assertEqual(fh.tell(), 2)
+        self.assertEqual(fh.readline(), b"ne0\n")
+        self.assertEqual(fh.read1(), b"line1\nline2")
+        self.assertEqual(fh.read(), b"")
+
+        self.assertEqual(fh.seek(-5, io.SEEK_END), 12)
+        self.assertEqual(fh.read(), b"line2")
+        self.assertEqual(fh.seek(0), 0)
+        self.assertEqual(fh.readlines(), [b"line0\n", b"line1\n", b"line2"])
+        fh.seek(0)
+        self.assertEqual(list(fh), [b"line0\n", b"line1\n", b"line2"])
+
+        fh.seek(6)
+        b = bytearray(8)
+        self.assertEqual(fh.readinto(b), 8)
+        self.assertEqual(b, b"line1\nli")
+        self.assertEqual(fh.readinto(b), 3)
+        self.assertEqual(b[0:3], b"ne2")
+
+        with self.assertRaises(ValueError):
+            fh.seek(-1)
+
+    def test_resource_file_text(self):
+        fh = self._open_packed_resource(b"line0\nline1\n")
+
+        with io.TextIOWrapper(fh, encoding="utf-8") as text:
+            self.assertEqual(text.readline(), "line0\n")
+            self.assertEqual(text.read(), "line1\n")
+
+        self.assertTrue(fh.closed)
+
+    def test_resource_file_buffer(self):
+        fh = self._open_packed_resource(b"my resource")
+        gc.collect()
+
+        view = fh.getbuffer()
+        self.assertTrue(view.readonly)
+        self.assertIs(view.obj, fh)
+        self.assertEqual(bytes(view[3:]), b"resource")
+        self.assertEqual(bytes(memoryview(fh)), b"my resource")
+
+        # Data can't be released while it is exported.
+        with self.assertRaises(BufferError):
+            fh.close()
+
+        view.release()
+        fh.close()
+        self.assertTrue(fh.closed)
+
+        for method in (fh.read, fh.getbuffer, fh.tell):
+            with self.assertRaises(ValueError):
+                method()
+
+    def test_resource_file_context_manager(self):
+        with self._open_packed_resource(b"my resource") as fh:
+            self.assertFalse(fh.closed)
+            self.assertEqual(fh.read(), b"my resource")
+
+        self.assertTrue(fh.closed)
+
+    def test_files(self):
+        p = self._make_package("my_package")
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(b"my resource")
+
+        f = self._finder_from_td()
+        r = f.get_resource_reader("my_package")
+
+        root = r.files()
+        self.assertIsInstance(root, OxidizedTraversable)
+        self.assertEqual(root.name, "my_package")
+        self.assertTrue(root.is_dir())
+        self.assertFalse(root.is_file())
+
+        with self.assertRaises(FileNotFoundError):
+            root.read_bytes()
+
+        children = list(root.iterdir())
+        self.assertEqual([c.name for c in children], ["resource.txt"])
+
+        resource = root / "resource.txt"
+        self.assertEqual(resource.name, "resource.txt")
+        self.assertTrue(resource.is_file())
+        self.assertFalse(resource.is_dir())
+        self.assertEqual(resource.read_bytes(), b"my resource")
+        self.assertEqual(resource.read_text(), "my resource")
+        self.assertEqual(resource.read_text(encoding="utf-8"), "my resource")
+
+        with self.assertRaises(NotADirectoryError):
+            resource.iterdir()
+
+        with resource.open("rb") as fh:
+            self.assertEqual(fh.read(), b"my resource")
+
+        with resource.open() as fh:
+            self.assertIsInstance(fh, io.TextIOWrapper)
+            self.assertEqual(fh.read(), "my resource")
+
+        with self.assertRaises(ValueError):
+            resource.open("w")
+
+        missing = root.joinpath("missing.txt")
+        self.assertFalse(missing.is_file())
+        self.assertFalse(missing.is_dir())
+
+        with self.assertRaises(FileNotFoundError):
+            missing.read_bytes()
+
+    def test_files_nested(self):
+        p = self._make_package("my_package")
+        self._make_package("my_package.child")
+
+        child0_path = p / "child0"
+        child0_path.mkdir()
+        (child0_path / "grandchild").mkdir()
+
+        with (child0_path / "a.txt").open("wb") as fh:
+            fh.write(b"a")
+        with (child0_path / "grandchild" / "b.txt").open("wb") as fh:
+            fh.write(b"b")
+        with (p / "child" / "c.txt").open("wb") as fh:
+            fh.write(b"c")
+
+        f = self._finder_from_td()
+        root = f.get_resource_reader("my_package").files()
+
+        self.assertEqual(sorted(c.name for c in root.iterdir()), ["child", "child0"])
+
+        child0 = root / "child0"
+        self.assertTrue(child0.is_dir())
+        self.assertEqual(
+            [(c.name, c.is_dir()) for c in child0.iterdir()],
+            [("a.txt", False), ("grandchild", True)],
+        )
+
+        self.assertEqual(
+            root.joinpath("child0", "grandchild", "b.txt").read_bytes(), b"b"
+        )
+        self.assertEqual((root / "child0/grandchild/b.txt").read_bytes(), b"b")
+
+        package = root / "child"
+        self.assertTrue(package.is_dir())
+        self.assertEqual([c.name for c in package.iterdir()], ["c.txt"])
+        self.assertEqual((package / "c.txt").read_bytes(), b"c")
+
+    def test_files_as_file(self):
+        p = self._make_package("my_package")
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(b"my resource")
+
+        f = self._finder_from_td()
+        resource = f.get_resource_reader("my_package").files() / "resource.txt"
+
+        with importlib.resources.as_file(resource) as path:
+            self.assertIsInstance(path, pathlib.Path)
+            self.assertTrue(path.name.endswith("resource.txt"))
+            self.assertEqual(path.read_bytes(), b"my resource")
+
+        self.assertFalse(path.exists())
+
+    def test_importlib_resources_files(self):
+        p = self._make_package("my_package")
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(b"my resource")
+
+        f = self._finder_from_td()
+
+        old_finders = list(sys.meta_path)
+        sys.meta_path.insert(0, f)
+        try:
+            root = importlib.resources.files("my_package")
+            self.assertIsInstance(root, OxidizedTraversable)
+            self.assertEqual(
+                importlib.resources.read_text("my_package", "resource.txt"),
+                "my resource",
+            )
+            self.assertEqual(root.joinpath("resource.txt").read_text(), "my resource")
+        finally:
+            sys.meta_path[:] = old_finders
+            sys.modules.pop("my_package", None)
+
 
 if __name__ == "__main__":
     unittest.main()
@@ -55,6 +55,7 @@ class TestImporterResources(unittest.TestCase):
         self.assertIsNone(resource.relative_path_extension_module_shared_library)
         self.assertIsNone(resource.relative_path_package_resources)
         self.assertIsNone(resource.relative_path_distribution_resources)
+        self.assertIsNone(resource.metadata)
 
     def test_resources_frozen(self):
         f = OxidizedFinder()
@@ -588,6 +589,31 @@ class TestImporterResources(unittest.TestCase):
         with self.assertRaises(TypeError):
             resource.relative_path_distribution_resources = {"foo": None}
 
+    def test_metadata(self):
+        resource = OxidizedResource()
+
+        resource.metadata = {}
+        self.assertEqual(resource.metadata, {})
+
+        resource.metadata = None
+        self.assertIsNone(resource.metadata)
+
+        resource.metadata = {"license": "MIT"}
+        self.assertEqual(resource.metadata, {"license": "MIT"})
+
+        # Updating the dict does *not* work.
+        resource.metadata["license"] = "ignored"
+        self.assertEqual(resource.metadata, {"license": "MIT"})
+
+        with self.assertRaises(AttributeError):
+            del resource.metadata
+
+        with self.assertRaises(TypeError):
+            resource.metadata = True
+
+        with self.assertRaises(TypeError):
+            resource.metadata = {"license": None}
+
     def test_add_resource_bad_type(self):
         f = OxidizedFinder()
 
@@ -0,0 +1,68 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Updating packed resources files.
+
+use std::{
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+/// Apply a delta patch to a packed resources file.
+///
+/// `patch` is created by [python_packed_resources::create_packed_resources_patch()].
+/// It is only applied if it was created from the current content of the file
+/// and produces exactly the data it was created to produce.
+///
+/// If `public_key` is set, the patch must be signed with the corresponding
+/// ed25519 secret key via [python_packed_resources::sign_packed_resources()]
+/// and its signature is verified before anything else is done. The patch must
+/// also be created with that secret key, so it carries the signature of the
+/// patched data. That signature is verified before the file is replaced. So
+/// the patched file passes
+/// [crate::OxidizedPythonInterpreterConfig::packed_resources_public_key]
+/// verification.
+///
+/// The patched data is written to a temporary file in the same directory,
+/// which then replaces the original file. So the file either has its new
+/// content or is left untouched. On Windows, a file can't be replaced while it
+/// is memory mapped. So patches must be applied before an interpreter loads
+/// the file, e.g. before constructing it or from another process.
+pub fn apply_packed_resources_patch(
+    path: &Path,
+    patch: &[u8],
+    public_key: Option<&[u8; 32]>,
+) -> std::io::Result<()> {
+    let patch = if let Some(key) = public_key {
+        python_packed_resources::verify_packed_resources_signature(patch, key)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+    } else {
+        patch
+    };
+
+    let data = std::fs::read(path)?;
+    let patched = python_packed_resources::apply_packed_resources_patch(&data, patch)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:#}", e)))?;
+
+    if let Some(key) = public_key {
+        python_packed_resources::verify_packed_resources_signature(&patched, key)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    }
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path does not refer to a file"))?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(".update");
+    let temp_path = path.with_file_name(temp_name);
+
+    std::fs::write(&temp_path, &patched)?;
+
+    if let Err(e) = std::fs::rename(&temp_path, path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    Ok(())
+}
@@ -11,6 +11,7 @@ use {
     clap::{value_parser, Arg, ArgAction, ArgMatches, Command},
     std::{
         collections::HashMap,
+        io::Write,
         path::{Path, PathBuf},
     },
 };
@@ -23,6 +24,48 @@ existing PyOxidizer enabled project.
 
 This command will invoke Rust's build system tool (Cargo) to build
 the project.
+
+With --dev, packages registered with PythonExecutable.add_dev_overlay()
+are imported from their source directories instead of being embedded. So
+changes to their Python code take effect without rebuilding.
+
+With --offline-cache, Python packages are only obtained from a directory
+populated by `pyoxidizer download-deps` and the network isn't accessed.
+";
+
+const CHECK_ABOUT: &str = "\
+Check the configuration of a PyOxidizer project without building it.
+
+The PATH argument is a filesystem path to a directory containing an
+existing PyOxidizer enabled project.
+
+This command evaluates the project's configuration file and resolves
+targets, reporting errors such as invalid Starlark, unknown target names,
+and target dependencies that don't exist. If no targets are given, all
+targets are resolved.
+
+Nothing is downloaded or compiled. Python distributions must already be
+cached, e.g. by a prior build or `pyoxidizer download-deps`. Operations
+fetching or building Python packages, such as PythonExecutable.pip_install(),
+are skipped and return no resources. Commands run by target functions still
+run.
+";
+
+const DOWNLOAD_DEPS_ABOUT: &str = "\
+Download the Python package dependencies of a PyOxidizer project.
+
+The PATH argument is a filesystem path to a directory containing an
+existing PyOxidizer enabled project.
+
+This command evaluates the project's configuration file like `build` does
+without building anything. Python packages fetched by packaging operations
+such as PythonExecutable.pip_install() are downloaded into the --cache-dir
+directory.
+
+The directory can then be passed to `pyoxidizer build --offline-cache` to
+build the project without network access. Python distributions are cached
+in PyOxidizer's user-specific cache directory, which can be set with the
+PYOXIDIZER_CACHE_DIR environment variable.
 ";
 
 const INIT_RUST_PROJECT_ABOUT: &str = "\
@@ -98,6 +141,52 @@ conversion is critical for properly packaging Python applications and
 bugs can result in incorrect install layouts, missing resources, etc.
 ";
 
+const INJECT_RESOURCES_ABOUT: &str = "\
+Inject packed resources data into a built binary.
+
+This command appends packed resources data to an already linked executable,
+replacing resources previously injected into it. So localized or
+customer-specific resources can be stamped onto a single built binary
+without rebuilding it.
+
+The binary must have been built with
+`PythonExecutable.packed_resources_load_injected = True` to load injected
+resources at run-time. Injected resources override resources of the same
+name embedded at build time.
+
+Injecting resources invalidates code signatures. So binaries should be signed
+after resources are injected. Mach-O binaries and binaries ending with a zip
+archive are rejected.
+";
+
+const RESOURCES_ABOUT: &str = "\
+Show packed resources embedded in a binary or packed resources file.
+
+This command finds packed resources data in the given file and prints
+the name, flags, in-memory size, and SHA-256 digest of in-memory data of
+every resource. The file can be a built executable or library embedding
+packed resources or a standalone packed resources file.
+
+The `--json` argument emits a machine readable JSON document, allowing
+build tooling and CI to assert on the content of built binaries without
+running them.
+";
+
+const TEST_ABOUT: &str = "\
+Build and run the test targets of a PyOxidizer project.
+
+The PATH argument is a filesystem path to a directory containing an
+existing PyOxidizer enabled project.
+
+Test targets are registered in the configuration file with
+register_test_target(). Their functions typically return a PythonTest,
+obtained from PythonExecutable.to_test(), which runs a Python module such
+as pytest inside the built executable.
+
+Each test target is built and run. If no targets are given, all test
+targets are run. The command fails if any test target fails.
+";
+
 const VAR_HELP: &str = "\
 Defines a single string key to set in the VARS global dict.
 
@@ -222,6 +311,15 @@ pub fn run_cli() -> Result<()> {
                 .global(true)
                 .action(ArgAction::Count)
                 .help("Increase logging verbosity. Can be specified multiple times"),
+        )
+        .arg(
+            Arg::new("log_format")
+                .long("log-format")
+                .global(true)
+                .action(ArgAction::Set)
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Format of log messages"),
         );
 
     let app = app.subcommand(
@@ -250,6 +348,22 @@ pub fn run_cli() -> Result<()> {
                     .action(ArgAction::SetTrue)
                     .help("Build a release binary"),
             )
+            .arg(
+                Arg::new("dev")
+                    .long("dev")
+                    .action(ArgAction::SetTrue)
+                    .help("Build a development binary"),
+            )
+            .arg(
+                Arg::new("offline_cache")
+                    .long("offline-cache")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("DIR")
+                    .help(
+                        "Only obtain Python packages from a directory populated by download-deps",
+                    ),
+            )
             .arg(
                 Arg::new("path")
                     .long("path")
@@ -268,9 +382,86 @@ pub fn run_cli() -> Result<()> {
             ),
     ));
 
+    let app = app.subcommand(add_env_args(
+        Command::new("check")
+            .about("Check the configuration of a PyOxidizer project without building it")
+            .long_about(CHECK_ABOUT)
+            .arg(
+                Arg::new("target_triple")
+                    .long("target-triple")
+                    .action(ArgAction::Set)
+                    .help("Rust target triple to check the configuration for"),
+            )
+            .arg(
+                Arg::new("release")
+                    .long("release")
+                    .action(ArgAction::SetTrue)
+                    .help("Evaluate the configuration for a release build"),
+            )
+            .arg(
+                Arg::new("path")
+                    .long("path")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .default_value(".")
+                    .value_name("PATH")
+                    .help("Directory containing project to check"),
+            )
+            .arg(
+                Arg::new("targets")
+                    .value_name("TARGET")
+                    .action(ArgAction::Append)
+                    .num_args(0..)
+                    .help("Target to check"),
+            ),
+    ));
+
     let app =
         app.subcommand(Command::new("cache-clear").about("Clear PyOxidizer's user-specific cache"));
 
+    let app = app.subcommand(add_env_args(
+        Command::new("download-deps")
+            .about("Download the Python package dependencies of a PyOxidizer project")
+            .long_about(DOWNLOAD_DEPS_ABOUT)
+            .arg(
+                Arg::new("target_triple")
+                    .long("target-triple")
+                    .action(ArgAction::Set)
+                    .help("Rust target triple to download dependencies for"),
+            )
+            .arg(
+                Arg::new("release")
+                    .long("release")
+                    .action(ArgAction::SetTrue)
+                    .help("Evaluate the configuration for a release build"),
+            )
+            .arg(
+                Arg::new("cache_dir")
+                    .long("cache-dir")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .required(true)
+                    .value_name("DIR")
+                    .help("Directory to download dependencies into"),
+            )
+            .arg(
+                Arg::new("path")
+                    .long("path")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .default_value(".")
+                    .value_name("PATH")
+                    .help("Directory containing project to evaluate"),
+            )
+            .arg(
+                Arg::new("targets")
+                    .value_name("TARGET")
+                    .action(ArgAction::Append)
+                    .num_args(0..)
+                    .help("Target to resolve"),
+            ),
+    ));
+
     let app = app.subcommand(
         Command::new("find-resources")
             .about("Find resources in a file or directory")
@@ -369,6 +560,36 @@ pub fn run_cli() -> Result<()> {
             ),
     );
 
+    let app = app.subcommand(
+        Command::new("inject-resources")
+            .about("Inject packed resources data into a built binary")
+            .long_about(INJECT_RESOURCES_ABOUT)
+            .arg(
+                Arg::new("output")
+                    .long("output")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("PATH")
+                    .help("Path to write the binary to instead of modifying it in place"),
+            )
+            .arg(
+                Arg::new("binary")
+                    .action(ArgAction::Set)
+                    .required(true)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("BINARY")
+                    .help("Path to binary to inject resources into"),
+            )
+            .arg(
+                Arg::new("resources")
+                    .action(ArgAction::Set)
+                    .required(true)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("RESOURCES")
+                    .help("Path to packed resources file to inject"),
+            ),
+    );
+
     let app = app.subcommand(
         Command::new("list-targets")
             .about("List targets available to resolve in a configuration file")
@@ -430,6 +651,26 @@ pub fn run_cli() -> Result<()> {
             ),
     );
 
+    let app = app.subcommand(
+        Command::new("resources")
+            .about("Show packed resources in a binary or packed resources file")
+            .long_about(RESOURCES_ABOUT)
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit JSON instead of human readable text"),
+            )
+            .arg(
+                Arg::new("path")
+                    .action(ArgAction::Set)
+                    .required(true)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("PATH")
+                    .help("Path to file to inspect"),
+            ),
+    );
+
     let app = app.subcommand(add_env_args(
         Command::new("run-build-script")
             .about("Run functionality that a build script would perform")
@@ -464,6 +705,22 @@ pub fn run_cli() -> Result<()> {
                     .action(ArgAction::SetTrue)
                     .help("Run a release binary"),
             )
+            .arg(
+                Arg::new("dev")
+                    .long("dev")
+                    .action(ArgAction::SetTrue)
+                    .help("Run a development binary"),
+            )
+            .arg(
+                Arg::new("offline_cache")
+                    .long("offline-cache")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("DIR")
+                    .help(
+                        "Only obtain Python packages from a directory populated by download-deps",
+                    ),
+            )
             .arg(
                 Arg::new("path")
                     .long("path")
@@ -511,6 +768,50 @@ pub fn run_cli() -> Result<()> {
             ),
     );
 
+    let app = app.subcommand(add_env_args(
+        Command::new("test")
+            .about("Build and run the test targets of a PyOxidizer project")
+            .long_about(TEST_ABOUT)
+            .arg(
+                Arg::new("target_triple")
+                    .long("target-triple")
+                    .action(ArgAction::Set)
+                    .help("Rust target triple to build for"),
+            )
+            .arg(
+                Arg::new("release")
+                    .long("release")
+                    .action(ArgAction::SetTrue)
+                    .help("Build and test a release binary"),
+            )
+            .arg(
+                Arg::new("offline_cache")
+                    .long("offline-cache")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("DIR")
+                    .help(
+                        "Only obtain Python packages from a directory populated by download-deps",
+                    ),
+            )
+            .arg(
+                Arg::new("path")
+                    .long("path")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .default_value(".")
+                    .value_name("PATH")
+                    .help("Directory containing project to test"),
+            )
+            .arg(
+                Arg::new("targets")
+                    .value_name("TARGET")
+                    .action(ArgAction::Append)
+                    .num_args(0..)
+                    .help("Test target to run"),
+            ),
+    ));
+
     let matches = app.get_matches();
 
     let verbose = matches.contains_id("verbose");
@@ -526,10 +827,24 @@ pub fn run_cli() -> Result<()> {
         env_logger::Env::default().default_filter_or(log_level.as_str()),
     );
 
-    builder
-        .format_timestamp(None)
-        .format_level(false)
-        .format_target(false);
+    if matches.get_one::<String>("log_format").map(|x| x.as_str()) == Some("json") {
+        builder.format(|buf, record| {
+            writeln!(
+                buf,
+                "{}",
+                serde_json::json!({
+                    "level": record.level().as_str().to_lowercase(),
+                    "target": record.target(),
+                    "message": record.args().to_string(),
+                })
+            )
+        });
+    } else {
+        builder
+            .format_timestamp(None)
+            .format_level(false)
+            .format_target(false);
+    }
 
     builder.init();
 
@@ -553,6 +868,8 @@ pub fn run_cli() -> Result<()> {
         "build" => {
             let starlark_vars = starlark_vars(args)?;
             let release = args.get_flag("release");
+            let dev = args.get_flag("dev");
+            let offline_cache = args.get_one::<PathBuf>("offline_cache");
             let target_triple = args.get_one::<String>("target_triple");
             let path = args.get_one::<PathBuf>("path").unwrap();
             let resolve_targets = args
@@ -566,12 +883,58 @@ pub fn run_cli() -> Result<()> {
                 resolve_targets,
                 starlark_vars,
                 release,
+                dev,
+                offline_cache.map(|x| x.as_path()),
                 verbose,
             )
         }
 
         "cache-clear" => projectmgmt::cache_clear(&env),
 
+        "check" => {
+            let starlark_vars = starlark_vars(args)?;
+            let release = args.get_flag("release");
+            let target_triple = args.get_one::<String>("target_triple");
+            let path = args.get_one::<PathBuf>("path").unwrap();
+            let resolve_targets = args
+                .get_many::<String>("targets")
+                .map(|x| x.cloned().collect::<Vec<_>>());
+
+            projectmgmt::check(
+                &env,
+                path,
+                target_triple.map(|x| x.as_str()),
+                resolve_targets,
+                starlark_vars,
+                release,
+                verbose,
+            )
+        }
+
+        "download-deps" => {
+            let starlark_vars = starlark_vars(args)?;
+            let release = args.get_flag("release");
+            let target_triple = args.get_one::<String>("target_triple");
+            let cache_dir = args
+                .get_one::<PathBuf>("cache_dir")
+                .expect("argument is required");
+            let path = args.get_one::<PathBuf>("path").unwrap();
+            let resolve_targets = args
+                .get_many::<String>("targets")
+                .map(|x| x.cloned().collect::<Vec<_>>());
+
+            projectmgmt::download_deps(
+                &env,
+                path,
+                target_triple.map(|x| x.as_str()),
+                resolve_targets,
+                starlark_vars,
+                release,
+                cache_dir,
+                verbose,
+            )
+        }
+
         "find-resources" => {
             let path = args.get_one::<PathBuf>("path");
             let distributions_dir = args.get_one::<PathBuf>("distributions_dir");
@@ -634,6 +997,18 @@ pub fn run_cli() -> Result<()> {
             )
         }
 
+        "inject-resources" => {
+            let binary = args.get_one::<PathBuf>("binary").unwrap();
+            let resources = args.get_one::<PathBuf>("resources").unwrap();
+            let output = args.get_one::<PathBuf>("output");
+
+            projectmgmt::inject_packed_resources(
+                binary,
+                resources,
+                output.map(|x| x.as_path()).unwrap_or(binary),
+            )
+        }
+
         "list-targets" => {
             let path = args.get_one::<String>("path").unwrap();
 
@@ -679,6 +1054,13 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::python_distribution_licenses(&env, path)
         }
 
+        "resources" => {
+            let path = args.get_one::<PathBuf>("path").unwrap();
+            let json = args.get_flag("json");
+
+            projectmgmt::inspect_packed_resources(path, json)
+        }
+
         "run-build-script" => {
             let starlark_vars = starlark_vars(args)?;
             let build_script = args.get_one::<String>("build-script-name").unwrap();
@@ -696,6 +1078,8 @@ pub fn run_cli() -> Result<()> {
             let starlark_vars = starlark_vars(args)?;
             let target_triple = args.get_one::<String>("target_triple");
             let release = args.get_flag("release");
+            let dev = args.get_flag("dev");
+            let offline_cache = args.get_one::<PathBuf>("offline_cache");
             let path = args.get_one::<String>("path").unwrap();
             let target = args.get_one::<String>("target");
             let extra = args
@@ -709,9 +1093,11 @@ pub fn run_cli() -> Result<()> {
                 Path::new(path),
                 target_triple.map(|x| x.as_str()),
                 release,
+                dev,
                 target.map(|x| x.as_str()),
                 starlark_vars,
                 &extra,
+                offline_cache.map(|x| x.as_path()),
                 verbose,
             )
         }
@@ -733,6 +1119,28 @@ pub fn run_cli() -> Result<()> {
             )
         }
 
+        "test" => {
+            let starlark_vars = starlark_vars(args)?;
+            let release = args.get_flag("release");
+            let target_triple = args.get_one::<String>("target_triple");
+            let offline_cache = args.get_one::<PathBuf>("offline_cache");
+            let path = args.get_one::<PathBuf>("path").unwrap();
+            let test_targets = args
+                .get_many::<String>("targets")
+                .map(|x| x.cloned().collect::<Vec<_>>());
+
+            projectmgmt::test(
+                &env,
+                path,
+                target_triple.map(|x| x.as_str()),
+                test_targets,
+                starlark_vars,
+                release,
+                offline_cache.map(|x| x.as_path()),
+                verbose,
+            )
+        }
+
         _ => Err(anyhow!("invalid sub-command")),
     }
 }
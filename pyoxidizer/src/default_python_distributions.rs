@@ -23,6 +23,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -32,6 +33,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "aarch64-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -41,6 +43,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -50,6 +53,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64_v2-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -59,6 +63,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64_v3-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -68,6 +73,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "aarch64-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -77,6 +83,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -86,6 +93,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64_v2-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -95,6 +103,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64_v3-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
 
         // Linux musl.
@@ -106,6 +115,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-unknown-linux-musl".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -115,6 +125,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-unknown-linux-musl".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -124,6 +135,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64_v2-unknown-linux-musl".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -133,6 +145,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64_v3-unknown-linux-musl".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -142,6 +155,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-unknown-linux-musl".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -151,6 +165,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64_v2-unknown-linux-musl".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -160,6 +175,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64_v3-unknown-linux-musl".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
 
         // The order here is important because we will choose the
@@ -178,6 +194,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "i686-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -187,6 +204,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "i686-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -196,6 +214,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "i686-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.8".to_string(),
@@ -205,6 +224,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -214,6 +234,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -223,6 +244,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
 
         // Windows static.
@@ -234,6 +256,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "i686-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -243,6 +266,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "i686-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -252,6 +276,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "i686-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.8".to_string(),
@@ -261,6 +286,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -270,6 +296,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -279,6 +306,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-pc-windows-msvc".to_string(),
             supports_prebuilt_extension_modules: false,
+            free_threaded: false,
         },
 
         // macOS.
@@ -290,6 +318,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "aarch64-apple-darwin".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -299,6 +328,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "aarch64-apple-darwin".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -308,6 +338,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "aarch64-apple-darwin".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.8".to_string(),
@@ -317,6 +348,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-apple-darwin".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
@@ -326,6 +358,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-apple-darwin".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
         PythonDistributionRecord {
             python_major_minor_version: "3.10".to_string(),
@@ -335,6 +368,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
             },
             target_triple: "x86_64-apple-darwin".to_string(),
             supports_prebuilt_extension_modules: true,
+            free_threaded: false,
         },
     ];
 
@@ -5,7 +5,10 @@
 //! Resolve details about the PyOxidizer execution environment.
 
 use {
-    crate::{project_layout::PyembedLocation, py_packaging::distribution::AppleSdkInfo},
+    crate::{
+        project_layout::PyembedLocation,
+        py_packaging::{artifact_cache::ArtifactCache, distribution::AppleSdkInfo},
+    },
     anyhow::{anyhow, Context, Result},
     apple_sdk::{AppleSdk, ParsedSdk, SdkSearch, SdkSearchLocation, SdkSorting},
     log::{info, warn},
@@ -115,6 +118,7 @@ pub static MACOS_TARGET_TRIPLES: Lazy<Vec<&'static str>> =
 /// Target triples for Windows.
 pub static WINDOWS_TARGET_TRIPLES: Lazy<Vec<&'static str>> = Lazy::new(|| {
     vec![
+        "aarch64-pc-windows-msvc",
         "i686-pc-windows-gnu",
         "i686-pc-windows-msvc",
         "x86_64-pc-windows-gnu",
@@ -145,9 +149,14 @@ pub fn canonicalize_path(path: &Path) -> Result<PathBuf, std::io::Error> {
 pub fn default_target_triple() -> &'static str {
     match env!("TARGET") {
         // Release binaries are typically musl. But Linux GNU is a more
-        // user friendly target to build for. So we perform this mapping.
-        "aarch64-unknown-linux-musl" => "aarch64-unknown-linux-gnu",
-        "x86_64-unknown-linux-musl" => "x86_64-unknown-linux-gnu",
+        // user friendly target to build for. So we perform this mapping
+        // unless the machine lacks glibc's dynamic loader, as on Alpine Linux.
+        "aarch64-unknown-linux-musl" if Path::new("/lib/ld-linux-aarch64.so.1").exists() => {
+            "aarch64-unknown-linux-gnu"
+        }
+        "x86_64-unknown-linux-musl" if Path::new("/lib64/ld-linux-x86-64.so.2").exists() => {
+            "x86_64-unknown-linux-gnu"
+        }
         v => v,
     }
 }
@@ -271,6 +280,9 @@ pub struct Environment {
     ///
     /// Cached because lookups may be expensive.
     rust_environment: Arc<RwLock<Option<RustEnvironment>>>,
+
+    /// Artifacts shared by executables built by this process.
+    artifact_cache: ArtifactCache,
 }
 
 impl Environment {
@@ -294,6 +306,7 @@ impl Environment {
             cache_dir,
             managed_rust,
             rust_environment: Arc::new(RwLock::new(None)),
+            artifact_cache: ArtifactCache::default(),
         })
     }
 
@@ -304,11 +317,21 @@ impl Environment {
         &self.cache_dir
     }
 
+    /// Cache of artifacts shared by executables built by this process.
+    pub fn artifact_cache(&self) -> &ArtifactCache {
+        &self.artifact_cache
+    }
+
     /// Directory to use for storing Python distributions.
     pub fn python_distributions_dir(&self) -> PathBuf {
         self.cache_dir.join("python_distributions")
     }
 
+    /// Directory to use for storing files fetched by `download_file()`.
+    pub fn downloads_dir(&self) -> PathBuf {
+        self.cache_dir.join("downloads")
+    }
+
     /// Directory to hold Rust toolchains.
     pub fn rust_dir(&self) -> PathBuf {
         self.cache_dir.join("rust")
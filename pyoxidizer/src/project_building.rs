@@ -6,9 +6,11 @@ use {
     crate::{
         environment::{canonicalize_path, Environment, RustEnvironment},
         licensing::{licenses_from_cargo_manifest, log_licensing_info},
-        project_layout::initialize_project,
+        project_layout::{
+            c_library_prefix, initialize_project, render_c_library_header, write_new_c_library,
+        },
         py_packaging::{
-            binary::{LibpythonLinkMode, PythonBinaryBuilder},
+            binary::{CargoBuildSettings, LibpythonLinkMode, PythonBinaryBuilder},
             distribution::AppleSdkInfo,
             embedding::{EmbeddedPythonContext, DEFAULT_PYTHON_CONFIG_FILENAME},
         },
@@ -20,11 +22,12 @@ use {
     log::warn,
     starlark_dialect_build_targets::ResolvedTarget,
     std::{
-        collections::HashMap,
+        collections::{BTreeMap, HashMap},
         fs::create_dir_all,
         io::{BufRead, BufReader},
         path::{Path, PathBuf},
     },
+    tugger_binary_analysis::find_elf_dependencies,
 };
 
 /// Find a pyoxidizer.toml configuration file by walking directory ancestry.
@@ -190,6 +193,12 @@ impl BuildEnvironment {
             );
         }
 
+        // musl libc targets link the C runtime statically by default. But
+        // we rely on it for producing fully static binaries, so be explicit.
+        if target_triple.contains("-linux-musl") {
+            rust_flags.extend(["-C".to_string(), "target-feature=+crt-static".to_string()]);
+        }
+
         if !rust_flags.is_empty() {
             let extra_flags = rust_flags.join(" ");
 
@@ -215,9 +224,46 @@ impl BuildEnvironment {
             environment_vars: envs,
         })
     }
+
+    /// Apply cargo build settings of a binary to the environment.
+    ///
+    /// Rust flags are appended to `RUSTFLAGS`. Profile settings are defined
+    /// via `CARGO_PROFILE_<PROFILE>_*` environment variables, which take
+    /// precedence over the profiles in the project's `Cargo.toml`.
+    pub fn apply_cargo_build_settings(&mut self, settings: &CargoBuildSettings, release: bool) {
+        if !settings.rust_flags.is_empty() {
+            let extra_flags = settings.rust_flags.join(" ");
+
+            let value = if let Some(value) = self.environment_vars.get("RUSTFLAGS") {
+                format!("{} {}", value, extra_flags)
+            } else {
+                extra_flags
+            };
+
+            self.environment_vars.insert("RUSTFLAGS".to_string(), value);
+        }
+
+        let profile = if release { "RELEASE" } else { "DEV" };
+
+        for (setting, value) in [
+            ("LTO", settings.lto.clone()),
+            (
+                "CODEGEN_UNITS",
+                settings.codegen_units.map(|x| x.to_string()),
+            ),
+            ("PANIC", settings.panic.clone()),
+        ] {
+            if let Some(value) = value {
+                self.environment_vars
+                    .insert(format!("CARGO_PROFILE_{}_{}", profile, setting), value);
+            }
+        }
+    }
 }
 
 /// Derive cargo features for project building.
+///
+/// Features from the binary's cargo build settings are included.
 pub fn cargo_features(exe: &dyn PythonBinaryBuilder) -> Vec<&str> {
     let mut res = vec!["build-mode-prebuilt-artifacts"];
 
@@ -234,9 +280,55 @@ pub fn cargo_features(exe: &dyn PythonBinaryBuilder) -> Vec<&str> {
         res.push("allocator-snmalloc");
     }
 
+    res.extend(exe.pyembed_resources_features());
+
+    res.extend(
+        exe.cargo_build_settings()
+            .features
+            .iter()
+            .map(|x| x.as_str()),
+    );
+
     res
 }
 
+/// Run cargo in a project directory, logging its output.
+///
+/// Lines of output are returned.
+fn run_cargo(
+    build_env: &BuildEnvironment,
+    project_path: &Path,
+    args: &[&str],
+) -> Result<Vec<String>> {
+    // TODO force cargo to colorize output under certain circumstances?
+    let command = cmd(&build_env.rust_environment.cargo_exe, args)
+        .dir(project_path)
+        .full_env(&build_env.environment_vars)
+        .stderr_to_stdout()
+        .unchecked()
+        .reader()
+        .context("invoking cargo command")?;
+
+    let mut lines = vec![];
+    {
+        let reader = BufReader::new(&command);
+        for line in reader.lines() {
+            let line = line.context("reading cargo output")?;
+            warn!("{}", line);
+            lines.push(line);
+        }
+    }
+    let output = command
+        .try_wait()
+        .context("waiting on cargo process")?
+        .ok_or_else(|| anyhow!("unable to wait on command"))?;
+    if !output.status.success() {
+        return Err(anyhow!("cargo build failed"));
+    }
+
+    Ok(lines)
+}
+
 /// Holds results from building an executable.
 pub struct BuiltExecutable<'a> {
     /// Path to built executable file.
@@ -252,11 +344,177 @@ pub struct BuiltExecutable<'a> {
     pub binary_data: EmbeddedPythonContext<'a>,
 }
 
-/// Build an executable embedding Python using an existing Rust project.
+/// Verify an executable is statically linked.
+///
+/// Binaries targeting musl libc are meant to run on any Linux machine. A
+/// dependency on a shared library, such as one pulled in by a crate's build
+/// script, defeats that. So we reject them.
+fn verify_static_executable(exe_path: &Path, exe_data: &[u8]) -> Result<()> {
+    let dependencies = find_elf_dependencies(exe_data)
+        .with_context(|| format!("analyzing {}", exe_path.display()))?;
+
+    if dependencies.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} is not statically linked; it depends on: {}",
+            exe_path.display(),
+            dependencies.join(", ")
+        ))
+    }
+}
+
+/// A cargo invocation building a Rust project.
+///
+/// Running it doesn't require the [PythonBinaryBuilder] the project was
+/// derived from. So cargo builds of different projects can run in parallel.
+pub struct CargoBuild {
+    /// Path to the Rust project to build.
+    pub project_path: PathBuf,
+
+    /// Directory holding Rust build state.
+    pub build_path: PathBuf,
+
+    build_env: BuildEnvironment,
+    args: Vec<String>,
+}
+
+impl CargoBuild {
+    /// Run cargo, returning lines of output.
+    pub fn run(&self) -> Result<Vec<String>> {
+        let args = self.args.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+
+        run_cargo(&self.build_env, &self.project_path, &args)
+    }
+}
+
+/// Run cargo builds, in parallel where possible.
+///
+/// Builds sharing a build directory run one after the other, as cargo only
+/// builds one project at a time in a directory. Builds in different
+/// directories run in parallel.
+pub fn run_cargo_builds(builds: &[&CargoBuild]) -> Result<()> {
+    let mut groups: BTreeMap<&Path, Vec<&CargoBuild>> = BTreeMap::new();
+    for build in builds {
+        groups.entry(&build.build_path).or_default().push(build);
+    }
+
+    std::thread::scope(|scope| {
+        let handles = groups
+            .into_values()
+            .map(|group| {
+                scope.spawn(move || -> Result<()> {
+                    for build in group {
+                        build.run()?;
+                    }
+
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| anyhow!("cargo build thread panicked"))?
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+
+    Ok(())
+}
+
+/// An executable embedding Python whose Rust project is ready to be built.
+pub struct PreparedExecutable<'a> {
+    /// The cargo invocation building the executable.
+    pub cargo: CargoBuild,
+
+    exe: &'a (dyn PythonBinaryBuilder + 'a),
+    bin_name: String,
+    target_triple: String,
+    release: bool,
+    include_self_license: bool,
+    embedded_data: EmbeddedPythonContext<'a>,
+
+    /// Temporary directory holding the Rust project, if we created it.
+    temp_dir: Option<tempfile::TempDir>,
+}
+
+impl<'a> PreparedExecutable<'a> {
+    /// Collect the executable produced by running [Self::cargo].
+    pub fn finish(self) -> Result<BuiltExecutable<'a>> {
+        let exe_name = if self.target_triple.contains("pc-windows") {
+            format!("{}.exe", self.bin_name)
+        } else {
+            self.bin_name.clone()
+        };
+
+        let exe_path = self
+            .cargo
+            .build_path
+            .join("target")
+            .join(&self.target_triple)
+            .join(if self.release { "release" } else { "debug" })
+            .join(&exe_name);
+
+        if !exe_path.exists() {
+            return Err(anyhow!("{} does not exist", exe_path.display()));
+        }
+
+        let exe_data =
+            std::fs::read(&exe_path).with_context(|| format!("reading {}", exe_path.display()))?;
+        let exe_name = exe_path.file_name().unwrap().to_string_lossy().to_string();
+
+        if self.target_triple.contains("-linux-musl") {
+            verify_static_executable(&exe_path, &exe_data)?;
+        }
+
+        let mut embedded_data = self.embedded_data;
+
+        // Construct unified licensing info by combining the Python licensing metadata
+        // with the dynamically derived licensing info for Rust crates from the Cargo manifest.
+        for component in licenses_from_cargo_manifest(
+            self.cargo.project_path.join("Cargo.toml"),
+            false,
+            cargo_features(self.exe),
+            Some(&self.target_triple),
+            &self.cargo.build_env.rust_environment,
+            self.include_self_license,
+        )?
+        .into_components()
+        {
+            embedded_data.add_licensed_component(component)?;
+        }
+
+        // Inform user about licensing info.
+        log_licensing_info(embedded_data.licensing());
+
+        let exe_path = if let Some(temp_dir) = self.temp_dir {
+            temp_dir.close().context("closing temporary directory")?;
+
+            // The path was in the temporary directory.
+            None
+        } else {
+            Some(exe_path)
+        };
+
+        Ok(BuiltExecutable {
+            exe_path,
+            exe_name,
+            exe_data,
+            binary_data: embedded_data,
+        })
+    }
+}
+
+/// Prepare building an executable embedding Python using an existing Rust project.
 ///
-/// The path to the produced executable is returned.
+/// The returned value's [PreparedExecutable::cargo] needs to be run before
+/// calling [PreparedExecutable::finish()].
 #[allow(clippy::too_many_arguments)]
-pub fn build_executable_with_rust_project<'a>(
+pub fn prepare_executable_with_rust_project<'a>(
     env: &Environment,
     project_path: &Path,
     bin_name: &str,
@@ -268,139 +526,90 @@ pub fn build_executable_with_rust_project<'a>(
     release: bool,
     locked: bool,
     include_self_license: bool,
-) -> Result<BuiltExecutable<'a>> {
-    create_dir_all(&artifacts_path).context("creating directory for PyOxidizer build artifacts")?;
+) -> Result<PreparedExecutable<'a>> {
+    create_dir_all(artifacts_path).context("creating directory for PyOxidizer build artifacts")?;
 
     // Derive and write the artifacts needed to build a binary embedding Python.
-    let mut embedded_data = exe
+    let embedded_data = exe
         .to_embedded_python_context(env, opt_level)
         .context("obtaining embedded python context")?;
     embedded_data
         .write_files(artifacts_path)
         .context("writing embedded python context files")?;
 
-    let build_env = BuildEnvironment::new(
+    let mut build_env = BuildEnvironment::new(
         env,
         exe.target_triple(),
         artifacts_path,
-        embedded_data.pyo3_config_path(&artifacts_path),
+        embedded_data.pyo3_config_path(artifacts_path),
         exe.libpython_link_mode(),
         exe.apple_sdk_info(),
     )
     .context("resolving build environment")?;
+    build_env.apply_cargo_build_settings(exe.cargo_build_settings(), release);
 
     warn!(
         "building with Rust {}",
         build_env.rust_environment.rust_version.semver
     );
 
-    let target_base_path = build_path.join("target");
-    let target_triple_base_path =
-        target_base_path
-            .join(target_triple)
-            .join(if release { "release" } else { "debug" });
-
-    let mut args = vec!["build", "--target", target_triple];
-
-    let target_dir = target_base_path.display().to_string();
-    args.push("--target-dir");
-    args.push(&target_dir);
-
-    args.push("--bin");
-    args.push(bin_name);
+    let mut args = vec![
+        "build".to_string(),
+        "--target".to_string(),
+        target_triple.to_string(),
+        "--target-dir".to_string(),
+        build_path.join("target").display().to_string(),
+        "--bin".to_string(),
+        bin_name.to_string(),
+    ];
 
     if locked {
-        args.push("--locked");
+        args.push("--locked".to_string());
     }
 
     if release {
-        args.push("--release");
+        args.push("--release".to_string());
     }
 
-    args.push("--no-default-features");
+    args.push("--no-default-features".to_string());
 
     let features = cargo_features(exe).join(" ");
 
     if !features.is_empty() {
-        args.push("--features");
-        args.push(&features);
-    }
-
-    // TODO force cargo to colorize output under certain circumstances?
-    let command = cmd(&build_env.rust_environment.cargo_exe, &args)
-        .dir(&project_path)
-        .full_env(&build_env.environment_vars)
-        .stderr_to_stdout()
-        .unchecked()
-        .reader()
-        .context("invoking cargo command")?;
-    {
-        let reader = BufReader::new(&command);
-        for line in reader.lines() {
-            warn!("{}", line.context("reading cargo output")?);
-        }
-    }
-    let output = command
-        .try_wait()
-        .context("waiting on cargo process")?
-        .ok_or_else(|| anyhow!("unable to wait on command"))?;
-    if !output.status.success() {
-        return Err(anyhow!("cargo build failed"));
-    }
-
-    let exe_name = if target_triple.contains("pc-windows") {
-        format!("{}.exe", bin_name)
-    } else {
-        bin_name.to_string()
-    };
-
-    let exe_path = target_triple_base_path.join(&exe_name);
-
-    if !exe_path.exists() {
-        return Err(anyhow!("{} does not exist", exe_path.display()));
+        args.push("--features".to_string());
+        args.push(features);
     }
 
-    let exe_data =
-        std::fs::read(&exe_path).with_context(|| format!("reading {}", exe_path.display()))?;
-    let exe_name = exe_path.file_name().unwrap().to_string_lossy().to_string();
-
-    // Construct unified licensing info by combining the Python licensing metadata
-    // with the dynamically derived licensing info for Rust crates from the Cargo manifest.
-    for component in licenses_from_cargo_manifest(
-        project_path.join("Cargo.toml"),
-        false,
-        cargo_features(exe),
-        Some(target_triple),
-        &build_env.rust_environment,
+    Ok(PreparedExecutable {
+        cargo: CargoBuild {
+            project_path: project_path.to_path_buf(),
+            build_path: build_path.to_path_buf(),
+            build_env,
+            args,
+        },
+        exe,
+        bin_name: bin_name.to_string(),
+        target_triple: target_triple.to_string(),
+        release,
         include_self_license,
-    )?
-    .into_components()
-    {
-        embedded_data.add_licensed_component(component)?;
-    }
-
-    // Inform user about licensing info.
-    log_licensing_info(embedded_data.licensing());
-
-    Ok(BuiltExecutable {
-        exe_path: Some(exe_path),
-        exe_name,
-        exe_data,
-        binary_data: embedded_data,
+        embedded_data,
+        temp_dir: None,
     })
 }
 
-/// Build a Python executable using a temporary Rust project.
+/// Prepare building a Python executable using a temporary Rust project.
 ///
-/// Returns the binary data constituting the built executable.
-pub fn build_python_executable<'a>(
+/// This is [build_python_executable()] without running cargo and collecting
+/// the built executable. See [PreparedExecutable] for how to do that.
+pub fn prepare_python_executable<'a>(
     env: &Environment,
     bin_name: &str,
     exe: &'a (dyn PythonBinaryBuilder + 'a),
     target_triple: &str,
     opt_level: &str,
     release: bool,
-) -> Result<BuiltExecutable<'a>> {
+    shared_build_path: Option<&Path>,
+) -> Result<PreparedExecutable<'a>> {
     let cargo_exe = env
         .ensure_rust_toolchain(Some(target_triple))
         .context("resolving Rust toolchain")?
@@ -410,7 +619,10 @@ pub fn build_python_executable<'a>(
 
     // Directory needs to have name of project.
     let project_path = temp_dir.path().join(bin_name);
-    let build_path = temp_dir.path().join("build");
+    let build_path = match shared_build_path {
+        Some(path) => path.to_path_buf(),
+        None => temp_dir.path().join("build"),
+    };
     let artifacts_path = temp_dir.path().join("artifacts");
 
     initialize_project(
@@ -423,7 +635,7 @@ pub fn build_python_executable<'a>(
     )
     .context("initializing project")?;
 
-    let mut build = build_executable_with_rust_project(
+    let mut prepared = prepare_executable_with_rust_project(
         env,
         &project_path,
         bin_name,
@@ -440,14 +652,208 @@ pub fn build_python_executable<'a>(
         // licensing isn't material.
         false,
     )
-    .context("building executable with Rust project")?;
+    .context("preparing executable with Rust project")?;
+
+    prepared.temp_dir = Some(temp_dir);
+
+    Ok(prepared)
+}
+
+/// Build a Python executable using a temporary Rust project.
+///
+/// `shared_build_path` is a directory holding Rust build state that is
+/// reused across builds. Rust crates not depending on the Python
+/// distribution are compiled once and reused when building other
+/// executables. If not defined, a temporary directory is used.
+///
+/// Returns the binary data constituting the built executable.
+pub fn build_python_executable<'a>(
+    env: &Environment,
+    bin_name: &str,
+    exe: &'a (dyn PythonBinaryBuilder + 'a),
+    target_triple: &str,
+    opt_level: &str,
+    release: bool,
+    shared_build_path: Option<&Path>,
+) -> Result<BuiltExecutable<'a>> {
+    let prepared = prepare_python_executable(
+        env,
+        bin_name,
+        exe,
+        target_triple,
+        opt_level,
+        release,
+        shared_build_path,
+    )?;
+
+    prepared
+        .cargo
+        .run()
+        .context("building executable with Rust project")?;
+
+    prepared.finish()
+}
+
+/// Holds results from building a static library exposing a C API.
+pub struct BuiltCEmbeddingLibrary<'a> {
+    /// File name of the static library.
+    pub library_name: String,
+
+    /// Holds raw content of the static library.
+    pub library_data: Vec<u8>,
+
+    /// File name of the C header declaring the C API.
+    pub header_name: String,
+
+    /// Holds content of the C header.
+    pub header_data: Vec<u8>,
+
+    /// Holds state generated from building.
+    pub binary_data: EmbeddedPythonContext<'a>,
+}
+
+/// Build a static library exposing a C API to an embedded Python interpreter.
+///
+/// A temporary Rust project is built like with [build_python_executable()].
+/// Its library target defines functions to initialize the interpreter, run
+/// the configured code and finalize the interpreter. These are declared by
+/// a generated C header.
+pub fn build_c_embedding_library<'a>(
+    env: &Environment,
+    name: &str,
+    exe: &'a (dyn PythonBinaryBuilder + 'a),
+    target_triple: &str,
+    opt_level: &str,
+    release: bool,
+    shared_build_path: Option<&Path>,
+) -> Result<BuiltCEmbeddingLibrary<'a>> {
+    let cargo_exe = env
+        .ensure_rust_toolchain(Some(target_triple))
+        .context("resolving Rust toolchain")?
+        .cargo_exe;
+
+    let temp_dir = env.temporary_directory("pyoxidizer")?;
+
+    // Directory needs to have name of project.
+    let project_path = temp_dir.path().join(name);
+    let build_path = match shared_build_path {
+        Some(path) => path.to_path_buf(),
+        None => temp_dir.path().join("build"),
+    };
+    let artifacts_path = temp_dir.path().join("artifacts");
+
+    initialize_project(
+        &env.pyoxidizer_source,
+        &project_path,
+        &cargo_exe,
+        None,
+        &[],
+        exe.windows_subsystem(),
+    )
+    .context("initializing project")?;
+    write_new_c_library(&project_path, name).context("writing C library")?;
+
+    create_dir_all(&artifacts_path).context("creating directory for PyOxidizer build artifacts")?;
+
+    let mut embedded_data = exe
+        .to_embedded_python_context(env, opt_level)
+        .context("obtaining embedded python context")?;
+    embedded_data
+        .write_files(&artifacts_path)
+        .context("writing embedded python context files")?;
+
+    let mut build_env = BuildEnvironment::new(
+        env,
+        exe.target_triple(),
+        &artifacts_path,
+        embedded_data.pyo3_config_path(&artifacts_path),
+        exe.libpython_link_mode(),
+        exe.apple_sdk_info(),
+    )
+    .context("resolving build environment")?;
+    build_env.apply_cargo_build_settings(exe.cargo_build_settings(), release);
+
+    warn!(
+        "building with Rust {}",
+        build_env.rust_environment.rust_version.semver
+    );
+
+    let target_base_path = build_path.join("target");
+    let target_dir = target_base_path.display().to_string();
+    let features = cargo_features(exe).join(" ");
+
+    // `cargo rustc` allows passing arguments to rustc for the library. rustc
+    // then reports the system libraries consumers of the library must link.
+    let mut args = vec![
+        "rustc",
+        "--lib",
+        "--target",
+        target_triple,
+        "--target-dir",
+        &target_dir,
+        // The Cargo.lock was created with the project.
+        "--locked",
+    ];
+
+    if release {
+        args.push("--release");
+    }
+
+    args.push("--no-default-features");
+    args.push("--features");
+    args.push(&features);
+    args.extend(["--", "--print", "native-static-libs"]);
+
+    let output = run_cargo(&build_env, &project_path, &args)?;
+
+    let native_static_libs = output.iter().find_map(|line| {
+        line.split_once("native-static-libs:")
+            .map(|(_, libs)| libs.trim().to_string())
+    });
+
+    let crate_name = name.replace('-', "_");
+    let library_name = if target_triple.contains("pc-windows") {
+        format!("{}.lib", crate_name)
+    } else {
+        format!("lib{}.a", crate_name)
+    };
 
-    // Blank out the path since it is in the temporary directory.
-    build.exe_path = None;
+    let library_path = target_base_path
+        .join(target_triple)
+        .join(if release { "release" } else { "debug" })
+        .join(&library_name);
+
+    let library_data = std::fs::read(&library_path)
+        .with_context(|| format!("reading {}", library_path.display()))?;
+
+    let header_name = format!("{}.h", c_library_prefix(name));
+    let header_data =
+        render_c_library_header(name, &library_name, native_static_libs.as_deref())?.into_bytes();
+
+    for component in licenses_from_cargo_manifest(
+        project_path.join("Cargo.toml"),
+        false,
+        cargo_features(exe),
+        Some(target_triple),
+        &build_env.rust_environment,
+        false,
+    )?
+    .into_components()
+    {
+        embedded_data.add_licensed_component(component)?;
+    }
+
+    log_licensing_info(embedded_data.licensing());
 
     temp_dir.close().context("closing temporary directory")?;
 
-    Ok(build)
+    Ok(BuiltCEmbeddingLibrary {
+        library_name,
+        library_data,
+        header_name,
+        header_data,
+        binary_data: embedded_data,
+    })
 }
 
 /// Build artifacts needed by the pyembed crate.
@@ -672,7 +1078,11 @@ mod tests {
             py_packaging::standalone_builder::tests::StandalonePythonExecutableBuilderOptions,
             testutil::*,
         },
-        python_packaging::interpreter::MemoryAllocatorBackend,
+        python_packaging::{
+            interpreter::MemoryAllocatorBackend, location::ConcreteResourceLocation,
+            resource::PythonModuleSource, resource_collection::PythonResourceAddCollectionContext,
+        },
+        simple_file_manifest::FileData,
     };
 
     #[cfg(target_env = "msvc")]
@@ -691,6 +1101,34 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cargo_build_settings() -> Result<()> {
+        let env = get_env()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut pre_built = options.new_builder()?;
+
+        pre_built.set_cargo_build_settings(CargoBuildSettings {
+            rust_flags: vec!["-C".to_string(), "debuginfo=0".to_string()],
+            lto: Some("thin".to_string()),
+            codegen_units: Some(1),
+            panic: Some("abort".to_string()),
+            ..Default::default()
+        });
+
+        build_python_executable(
+            &env,
+            "myapp",
+            pre_built.as_ref(),
+            default_target_triple(),
+            "0",
+            false,
+            None,
         )?;
 
         Ok(())
@@ -714,6 +1152,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
@@ -735,11 +1174,68 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
     }
 
+    #[test]
+    fn test_c_embedding_library() -> Result<()> {
+        let env = get_env()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut pre_built = options.new_builder()?;
+
+        pre_built.add_python_module_source(
+            &PythonModuleSource {
+                name: "my_app_module".to_string(),
+                source: FileData::Memory(vec![]),
+                is_package: false,
+                cache_tag: pre_built.cache_tag().to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            Some(PythonResourceAddCollectionContext {
+                include: true,
+                location: ConcreteResourceLocation::RelativePath("lib".to_string()),
+                location_fallback: None,
+                store_source: true,
+                optimize_level_zero: false,
+                optimize_level_one: false,
+                optimize_level_two: false,
+                metadata: Default::default(),
+            }),
+        )?;
+
+        let built = build_c_embedding_library(
+            &env,
+            "my-app",
+            pre_built.as_ref(),
+            default_target_triple(),
+            "0",
+            false,
+            None,
+        )?;
+
+        assert!(!built.library_data.is_empty());
+        assert_eq!(built.header_name, "my_app.h");
+
+        let header = String::from_utf8(built.header_data)?;
+        assert!(header.contains("int my_app_init(int argc, const char *const *argv);"));
+        assert!(header.contains("int my_app_run(void);"));
+        assert!(header.contains("void my_app_finalize(void);"));
+
+        assert!(
+            built
+                .binary_data
+                .extra_files
+                .has_path(Path::new("lib").join("my_app_module.py")),
+            "filesystem-relative resource should be installed next to the library"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_empty_project_system_rust() -> Result<()> {
         let mut env = get_env()?;
@@ -754,6 +1250,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
@@ -776,6 +1273,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
@@ -799,6 +1297,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
@@ -822,6 +1321,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
@@ -845,6 +1345,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
@@ -872,6 +1373,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
@@ -899,6 +1401,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
@@ -27,6 +27,9 @@ static HANDLEBARS: Lazy<Handlebars<'static>> = Lazy::new(|| {
             include_str!("templates/application-manifest.rc.hbs"),
         )
         .unwrap();
+    handlebars
+        .register_template_string("c-library.h", include_str!("templates/c-library.h.hbs"))
+        .unwrap();
     handlebars
         .register_template_string(
             "cargo-extra.toml",
@@ -45,6 +48,12 @@ static HANDLEBARS: Lazy<Handlebars<'static>> = Lazy::new(|| {
             include_str!("templates/new-cargo-config.hbs"),
         )
         .unwrap();
+    handlebars
+        .register_template_string(
+            "new-c-library.rs",
+            include_str!("templates/new-c-library.rs.hbs"),
+        )
+        .unwrap();
     handlebars
         .register_template_string("new-main.rs", include_str!("templates/new-main.rs.hbs"))
         .unwrap();
@@ -278,6 +287,72 @@ pub fn write_new_main_rs(path: &Path, windows_subsystem: &str) -> Result<()> {
     Ok(())
 }
 
+/// Derive the prefix of C API symbols from a library name.
+///
+/// Characters not valid in C identifiers are replaced by `_`.
+pub fn c_library_prefix(name: &str) -> String {
+    let prefix = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+
+    if prefix.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", prefix)
+    } else {
+        prefix
+    }
+}
+
+/// Turn a new Rust project into one also producing a static library exposing a C API.
+///
+/// `src/lib.rs` defining the C API is written and the library target is
+/// added to `Cargo.toml`. `name` is the name of the library, which the C
+/// API symbols are prefixed with.
+pub fn write_new_c_library(project_path: &Path, name: &str) -> Result<()> {
+    let prefix = c_library_prefix(name);
+
+    let mut data: BTreeMap<String, String> = BTreeMap::new();
+    data.insert("prefix".to_string(), prefix.clone());
+    data.insert("header_filename".to_string(), format!("{}.h", prefix));
+    let t = HANDLEBARS.render("new-c-library.rs", &data)?;
+
+    let lib_path = project_path.join("src").join("lib.rs");
+    println!("writing {}", lib_path.display());
+    std::fs::write(&lib_path, t)?;
+
+    let cargo_toml_path = project_path.join("Cargo.toml");
+    let mut content = std::fs::read_to_string(&cargo_toml_path)?;
+    content.push_str("\n[lib]\ncrate-type = [\"staticlib\"]\n");
+    std::fs::write(&cargo_toml_path, content)?;
+
+    Ok(())
+}
+
+/// Render the C header declaring the C API of a static library.
+///
+/// `native_static_libs` are system libraries consumers of the library need
+/// to link with, as reported by rustc.
+pub fn render_c_library_header(
+    name: &str,
+    library_filename: &str,
+    native_static_libs: Option<&str>,
+) -> Result<String> {
+    let prefix = c_library_prefix(name);
+
+    let mut data: BTreeMap<String, String> = BTreeMap::new();
+    data.insert(
+        "guard".to_string(),
+        format!("{}_H", prefix.to_ascii_uppercase()),
+    );
+    data.insert("prefix".to_string(), prefix);
+    data.insert("library_filename".to_string(), library_filename.to_string());
+    if let Some(libs) = native_static_libs {
+        data.insert("native_static_libs".to_string(), libs.to_string());
+    }
+
+    Ok(HANDLEBARS.render("c-library.h", &data)?)
+}
+
 /// Writes default PyOxidizer config files into a project directory.
 pub fn write_new_pyoxidizer_config_file(
     source: &PyOxidizerSource,
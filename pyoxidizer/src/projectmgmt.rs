@@ -11,6 +11,7 @@ use {
         project_building::find_pyoxidizer_config_file_env,
         project_layout::{initialize_project, write_new_pyoxidizer_config_file},
         py_packaging::{
+            dependency_cache::{DependencyCache, DependencyCacheMode},
             distribution::{
                 default_distribution_location, resolve_distribution,
                 resolve_python_distribution_archive, BinaryLibpythonLinkMode, DistributionCache,
@@ -29,7 +30,9 @@ use {
         resource::PythonResource,
         wheel::WheelArchive,
     },
+    python_packed_resources::{find_packed_resources, ResourceSummary},
     simple_file_manifest::{FileData, FileManifest},
+    starlark_dialect_build_targets::RunMode,
     std::{
         collections::HashMap,
         fs::create_dir_all,
@@ -48,6 +51,8 @@ pub fn default_target() -> Result<String> {
             Ok("x86_64-unknown-linux-gnu".to_string())
         }
     } else if cfg!(target_os = "windows") {
+        // ARM64 hosts default to x86_64, which they can run, until there are
+        // default ARM64 distributions and WiX support for them.
         Ok("x86_64-pc-windows-msvc".to_string())
     } else if cfg!(target_os = "macos") {
         if cfg!(target_arch = "aarch64") {
@@ -113,6 +118,8 @@ pub fn build(
     resolve_targets: Option<Vec<String>>,
     extra_vars: HashMap<String, Option<String>>,
     release: bool,
+    dev: bool,
+    offline_cache: Option<&Path>,
     verbose: bool,
 ) -> Result<()> {
     let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
@@ -126,15 +133,16 @@ pub fn build(
     let mut context = EvaluationContextBuilder::new(env, config_path.clone(), target_triple)
         .extra_vars(extra_vars)
         .release(release)
+        .dev(dev)
+        .dependency_cache(offline_dependency_cache(offline_cache)?)
         .verbose(verbose)
         .resolve_targets_optional(resolve_targets)
         .into_context()?;
 
     context.evaluate_file(&config_path)?;
 
-    for target in context.targets_to_resolve()? {
-        context.build_resolved_target(&target)?;
-    }
+    let targets = context.targets_to_resolve()?;
+    context.build_resolved_targets(&targets)?;
 
     Ok(())
 }
@@ -145,9 +153,11 @@ pub fn run(
     project_path: &Path,
     target_triple: Option<&str>,
     release: bool,
+    dev: bool,
     target: Option<&str>,
     extra_vars: HashMap<String, Option<String>>,
     _extra_args: &[&str],
+    offline_cache: Option<&Path>,
     verbose: bool,
 ) -> Result<()> {
     let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
@@ -161,6 +171,8 @@ pub fn run(
     let mut context = EvaluationContextBuilder::new(env, config_path.clone(), target_triple)
         .extra_vars(extra_vars)
         .release(release)
+        .dev(dev)
+        .dependency_cache(offline_dependency_cache(offline_cache)?)
         .verbose(verbose)
         .resolve_target_optional(target)
         .into_context()?;
@@ -170,6 +182,225 @@ pub fn run(
     context.run_target(target)
 }
 
+/// Resolve the dependency cache used by offline builds.
+fn offline_dependency_cache(path: Option<&Path>) -> Result<Option<DependencyCache>> {
+    path.map(|path| DependencyCache::new(path, DependencyCacheMode::Offline))
+        .transpose()
+}
+
+/// Download the dependencies of a PyOxidizer project into a directory.
+///
+/// The configuration file is evaluated with packaging operations fetching
+/// packages into the directory. Nothing is built. The directory can then be
+/// given to `build --offline-cache` to build without network access.
+#[allow(clippy::too_many_arguments)]
+pub fn download_deps(
+    env: &Environment,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    resolve_targets: Option<Vec<String>>,
+    extra_vars: HashMap<String, Option<String>>,
+    release: bool,
+    cache_dir: &Path,
+    verbose: bool,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+    let cache = DependencyCache::new(cache_dir, DependencyCacheMode::Populate)?;
+
+    let mut context = EvaluationContextBuilder::new(env, config_path.clone(), target_triple)
+        .extra_vars(extra_vars)
+        .release(release)
+        .dependency_cache(Some(cache.clone()))
+        .verbose(verbose)
+        .resolve_targets_optional(resolve_targets)
+        .into_context()?;
+
+    context.evaluate_file(&config_path)?;
+
+    println!("dependencies downloaded to {}", cache.path().display());
+
+    Ok(())
+}
+
+/// Check a PyOxidizer project's configuration without building it.
+///
+/// The configuration file is evaluated and targets are resolved, validating
+/// target names and dependencies and applying resource policies. Python
+/// distributions must already be cached. Packages aren't downloaded or
+/// built and executables aren't compiled.
+#[allow(clippy::too_many_arguments)]
+pub fn check(
+    env: &Environment,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    resolve_targets: Option<Vec<String>>,
+    extra_vars: HashMap<String, Option<String>>,
+    release: bool,
+    verbose: bool,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let mut context = EvaluationContextBuilder::new(env, config_path.clone(), target_triple)
+        .extra_vars(extra_vars)
+        .release(release)
+        .verbose(verbose)
+        .check_mode(true)
+        .resolve_targets(vec![])
+        .into_context()?;
+
+    context.evaluate_file(&config_path)?;
+
+    let target_names = context.target_names()?;
+
+    let unknown = context.unknown_target_dependencies()?;
+    if !unknown.is_empty() {
+        return Err(anyhow!(
+            "targets depend on targets that don't exist: {}",
+            unknown
+                .iter()
+                .map(|(target, depend)| format!("{} -> {}", target, depend))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let targets = if let Some(targets) = resolve_targets {
+        let missing = targets
+            .iter()
+            .filter(|t| !target_names.contains(t))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "targets don't exist: {}; available targets: {}",
+                missing.join(", "),
+                target_names.join(", ")
+            ));
+        }
+
+        targets
+    } else {
+        target_names
+    };
+
+    for target in &targets {
+        let value = context.resolve_target(target)?;
+        println!("{}: {}", target, value.get_type());
+    }
+
+    println!("{} checked successfully", config_path.display());
+
+    Ok(())
+}
+
+/// Build and run the test targets of a PyOxidizer project.
+///
+/// Test targets are registered with `register_test_target()`. Each one is
+/// built and then run. An error is returned if any test target fails.
+#[allow(clippy::too_many_arguments)]
+pub fn test(
+    env: &Environment,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    test_targets: Option<Vec<String>>,
+    extra_vars: HashMap<String, Option<String>>,
+    release: bool,
+    offline_cache: Option<&Path>,
+    verbose: bool,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let mut context = EvaluationContextBuilder::new(env, config_path.clone(), target_triple)
+        .extra_vars(extra_vars)
+        .release(release)
+        .dependency_cache(offline_dependency_cache(offline_cache)?)
+        .verbose(verbose)
+        .resolve_targets(vec![])
+        .into_context()?;
+
+    context.evaluate_file(&config_path)?;
+
+    let registered = context.test_target_names()?;
+
+    let targets = if let Some(targets) = test_targets {
+        let missing = targets
+            .iter()
+            .filter(|t| !registered.contains(t))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "test targets don't exist: {}; available test targets: {}",
+                missing.join(", "),
+                registered.join(", ")
+            ));
+        }
+
+        targets
+    } else {
+        registered
+    };
+
+    if targets.is_empty() {
+        return Err(anyhow!(
+            "{} doesn't register any test targets",
+            config_path.display()
+        ));
+    }
+
+    let mut failed = vec![];
+
+    for target in &targets {
+        context.resolve_target(target)?;
+        let resolved = context.build_resolved_target(target)?;
+
+        if resolved.run_mode == RunMode::None {
+            return Err(anyhow!("test target {} can't be run", target));
+        }
+
+        println!("running test target {}", target);
+        match resolved.run() {
+            Ok(()) => println!("{}: passed", target),
+            Err(e) => {
+                println!("{}: failed: {}", target, e);
+                failed.push(target.clone());
+            }
+        }
+    }
+
+    println!(
+        "{} passed; {} failed",
+        targets.len() - failed.len(),
+        failed.len()
+    );
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("test targets failed: {}", failed.join(", ")))
+    }
+}
+
 pub fn cache_clear(env: &Environment) -> Result<()> {
     let cache_dir = env.cache_dir();
 
@@ -570,6 +801,101 @@ pub fn python_distribution_licenses(env: &Environment, path: &str) -> Result<()>
     Ok(())
 }
 
+/// Inject packed resources data into a built binary.
+///
+/// The binary with injected resources is written to `output`, which can be
+/// the path of the original binary.
+pub fn inject_packed_resources(binary: &Path, resources: &Path, output: &Path) -> Result<()> {
+    let binary_data =
+        std::fs::read(binary).with_context(|| format!("reading {}", binary.display()))?;
+    let resources_data =
+        std::fs::read(resources).with_context(|| format!("reading {}", resources.display()))?;
+
+    let data = python_packed_resources::inject_packed_resources(&binary_data, &resources_data)?;
+
+    let permissions = std::fs::metadata(binary)?.permissions();
+    std::fs::write(output, &data).with_context(|| format!("writing {}", output.display()))?;
+    std::fs::set_permissions(output, permissions)?;
+
+    println!(
+        "injected {} bytes of packed resources into {}",
+        resources_data.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Print the packed resources found in a file.
+pub fn inspect_packed_resources(path: &Path, json: bool) -> Result<()> {
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+    let found = find_packed_resources(&data);
+    if found.is_empty() {
+        return Err(anyhow!("no packed resources found in {}", path.display()));
+    }
+
+    if json {
+        let blobs = found
+            .iter()
+            .map(|blob| {
+                let resources = blob
+                    .resources
+                    .iter()
+                    .map(|resource| {
+                        let summary = ResourceSummary::from(resource);
+
+                        serde_json::json!({
+                            "name": summary.name,
+                            "flags": summary.flags,
+                            "in_memory_size": summary.in_memory_size,
+                            "relative_paths": summary.relative_paths,
+                            "in_memory_sha256": summary.in_memory_sha256.map(hex::encode),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                serde_json::json!({
+                    "offset": blob.offset,
+                    "format_version": blob.format_version,
+                    "resources": resources,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        println!("{}", serde_json::to_string_pretty(&blobs)?);
+
+        return Ok(());
+    }
+
+    for blob in found {
+        println!(
+            "packed resources at offset {} (format version {}; {} resources)",
+            blob.offset,
+            blob.format_version,
+            blob.resources.len()
+        );
+
+        for resource in &blob.resources {
+            let summary = ResourceSummary::from(resource);
+
+            println!("{}", summary.name);
+            println!("  flags: {}", summary.flags.join(", "));
+            println!("  in-memory size: {}", summary.in_memory_size);
+            for path in &summary.relative_paths {
+                println!("  relative path: {}", path);
+            }
+            if let Some(digest) = &summary.in_memory_sha256 {
+                println!("  in-memory sha256: {}", hex::encode(digest));
+            }
+        }
+
+        println!();
+    }
+
+    Ok(())
+}
+
 /// Generate artifacts for embedding Python in a binary.
 pub fn generate_python_embedding_artifacts(
     env: &Environment,
@@ -0,0 +1,320 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Caching of artifacts shared by executables.
+
+A configuration file often defines several executables from the same Python
+distribution with mostly identical resources. Their custom libpython and the
+bytecode of their modules are then identical too. An [ArtifactCache] holds
+these artifacts in memory so they are only produced once per process.
+*/
+
+use {
+    crate::py_packaging::{distribution::AppleSdkInfo, libpython::LibpythonInfo},
+    anyhow::{anyhow, Result},
+    python_packaging::{
+        bytecode::{CompileMode, PythonBytecodeCompiler},
+        libpython::LibPythonBuildContext,
+        resource::BytecodeOptimizationLevel,
+    },
+    sha2::{Digest, Sha256},
+    simple_file_manifest::FileData,
+    std::{
+        collections::HashMap,
+        path::Path,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Feeds values into a hasher so distinct sequences produce distinct digests.
+struct KeyHasher(Sha256);
+
+impl KeyHasher {
+    fn new(kind: &str) -> Self {
+        let mut hasher = Self(Sha256::new());
+        hasher.str(kind);
+
+        hasher
+    }
+
+    fn bytes(&mut self, data: &[u8]) {
+        self.0.update((data.len() as u64).to_le_bytes());
+        self.0.update(data);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    fn path(&mut self, path: &Path) {
+        self.str(&path.display().to_string());
+    }
+
+    /// Hash file data.
+    ///
+    /// Files on disk are identified by their path, as files a build reads
+    /// from a Python distribution don't change.
+    fn file_data(&mut self, data: &FileData) {
+        match data {
+            FileData::Path(path) => {
+                self.str("path");
+                self.path(path);
+            }
+            FileData::Memory(data) => {
+                self.str("memory");
+                self.bytes(data);
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        hex::encode(self.0.finalize())
+    }
+}
+
+/// Artifacts shared by executables built in the same process.
+///
+/// Cloned instances share the same cache.
+#[derive(Clone, Debug, Default)]
+pub struct ArtifactCache {
+    libpython: Arc<Mutex<HashMap<String, LibpythonInfo>>>,
+    bytecode: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl ArtifactCache {
+    /// Obtain a custom libpython, linking it if an identical one isn't cached.
+    ///
+    /// `link` is called to link the library if it isn't cached.
+    pub fn libpython(
+        &self,
+        context: &LibPythonBuildContext,
+        host_triple: &str,
+        target_triple: &str,
+        opt_level: &str,
+        apple_sdk_info: Option<&AppleSdkInfo>,
+        link: impl FnOnce() -> Result<LibpythonInfo>,
+    ) -> Result<LibpythonInfo> {
+        let mut hasher = KeyHasher::new("libpython");
+        hasher.str(host_triple);
+        hasher.str(target_triple);
+        hasher.str(opt_level);
+        hasher.str(&format!("{:?}", apple_sdk_info));
+        hasher.str(&format!("{:?}", context.inittab_cflags));
+        for (path, data) in &context.includes {
+            hasher.path(path);
+            hasher.file_data(data);
+        }
+        hasher.str("objects");
+        for data in &context.object_files {
+            hasher.file_data(data);
+        }
+        for values in [
+            &context.system_libraries,
+            &context.dynamic_libraries,
+            &context.static_libraries,
+            &context.frameworks,
+        ] {
+            hasher.str(&format!("{:?}", values));
+        }
+        hasher.str(&format!("{:?}", context.init_functions));
+        let key = hasher.finish();
+
+        if let Some(info) = self.lock_libpython()?.get(&key) {
+            log::warn!("reusing custom Python library linked for another executable");
+            return Ok(info.clone());
+        }
+
+        let info = link()?;
+        self.lock_libpython()?.insert(key, info.clone());
+
+        Ok(info)
+    }
+
+    fn lock_libpython(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, LibpythonInfo>>> {
+        self.libpython
+            .lock()
+            .map_err(|e| anyhow!("unable to lock libpython cache: {}", e))
+    }
+
+    /// Wrap a bytecode compiler so its results are cached.
+    ///
+    /// `python_exe` is the interpreter `compiler` runs, as bytecode differs
+    /// between Python versions.
+    pub fn bytecode_compiler<'a>(
+        &self,
+        compiler: &'a mut dyn PythonBytecodeCompiler,
+        python_exe: &Path,
+    ) -> CachingBytecodeCompiler<'a> {
+        CachingBytecodeCompiler {
+            inner: compiler,
+            python_exe: python_exe.display().to_string(),
+            cache: self.bytecode.clone(),
+        }
+    }
+}
+
+/// A bytecode compiler reusing bytecode compiled by other executables.
+pub struct CachingBytecodeCompiler<'a> {
+    inner: &'a mut dyn PythonBytecodeCompiler,
+    python_exe: String,
+    cache: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl PythonBytecodeCompiler for CachingBytecodeCompiler<'_> {
+    fn get_magic_number(&self) -> u32 {
+        self.inner.get_magic_number()
+    }
+
+    fn compile(
+        &mut self,
+        source: &[u8],
+        filename: &str,
+        optimize: BytecodeOptimizationLevel,
+        output_mode: CompileMode,
+    ) -> Result<Vec<u8>> {
+        let mut hasher = KeyHasher::new("bytecode");
+        hasher.str(&self.python_exe);
+        hasher.bytes(source);
+        hasher.str(filename);
+        hasher.str(&i32::from(optimize).to_string());
+        hasher.str(match output_mode {
+            CompileMode::Bytecode => "bytecode",
+            CompileMode::PycCheckedHash => "checked",
+            CompileMode::PycUncheckedHash => "unchecked",
+        });
+        let key = hasher.finish();
+
+        if let Some(data) = self
+            .cache
+            .lock()
+            .map_err(|e| anyhow!("unable to lock bytecode cache: {}", e))?
+            .get(&key)
+        {
+            return Ok(data.clone());
+        }
+
+        let data = self
+            .inner
+            .compile(source, filename, optimize, output_mode)?;
+
+        self.cache
+            .lock()
+            .map_err(|e| anyhow!("unable to lock bytecode cache: {}", e))?
+            .insert(key, data.clone());
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A compiler counting invocations.
+    struct CountingCompiler {
+        count: usize,
+    }
+
+    impl PythonBytecodeCompiler for CountingCompiler {
+        fn get_magic_number(&self) -> u32 {
+            42
+        }
+
+        fn compile(
+            &mut self,
+            source: &[u8],
+            _filename: &str,
+            _optimize: BytecodeOptimizationLevel,
+            _output_mode: CompileMode,
+        ) -> Result<Vec<u8>> {
+            self.count += 1;
+
+            Ok(source.to_vec())
+        }
+    }
+
+    #[test]
+    fn test_bytecode_reuse() -> Result<()> {
+        let cache = ArtifactCache::default();
+        let python_exe = Path::new("python3");
+
+        let mut inner = CountingCompiler { count: 0 };
+        {
+            let mut compiler = cache.bytecode_compiler(&mut inner, python_exe);
+            assert_eq!(compiler.get_magic_number(), 42);
+
+            for _ in 0..2 {
+                compiler.compile(
+                    b"foo",
+                    "foo.py",
+                    BytecodeOptimizationLevel::Zero,
+                    CompileMode::Bytecode,
+                )?;
+            }
+            compiler.compile(
+                b"foo",
+                "foo.py",
+                BytecodeOptimizationLevel::One,
+                CompileMode::Bytecode,
+            )?;
+        }
+        assert_eq!(inner.count, 2);
+
+        // Clones share the cache. Other interpreters don't.
+        let mut inner = CountingCompiler { count: 0 };
+        {
+            let cloned = cache.clone();
+            let mut compiler = cloned.bytecode_compiler(&mut inner, python_exe);
+            compiler.compile(
+                b"foo",
+                "foo.py",
+                BytecodeOptimizationLevel::Zero,
+                CompileMode::Bytecode,
+            )?;
+
+            let mut compiler = cloned.bytecode_compiler(&mut inner, Path::new("python3.10"));
+            compiler.compile(
+                b"foo",
+                "foo.py",
+                BytecodeOptimizationLevel::Zero,
+                CompileMode::Bytecode,
+            )?;
+        }
+        assert_eq!(inner.count, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_libpython_reuse() -> Result<()> {
+        let cache = ArtifactCache::default();
+
+        let mut context = LibPythonBuildContext::default();
+        context
+            .object_files
+            .push(FileData::Memory(b"object".to_vec()));
+
+        let mut count = 0;
+        let mut link = |context: &LibPythonBuildContext| {
+            cache.libpython(context, "host", "target", "0", None, || {
+                count += 1;
+                Ok(LibpythonInfo {
+                    libpython_data: vec![42],
+                    linking_annotations: vec![],
+                })
+            })
+        };
+
+        assert_eq!(link(&context)?.libpython_data, vec![42]);
+        link(&context)?;
+
+        context.system_libraries.insert("m".to_string());
+        link(&context)?;
+
+        assert_eq!(count, 2);
+
+        Ok(())
+    }
+}
@@ -9,7 +9,10 @@ Defining and manipulating binaries embedding Python.
 use {
     crate::{
         environment::Environment,
-        py_packaging::{distribution::AppleSdkInfo, embedding::EmbeddedPythonContext},
+        py_packaging::{
+            dependency_cache::DependencyCache, distribution::AppleSdkInfo,
+            embedding::EmbeddedPythonContext,
+        },
     },
     anyhow::Result,
     python_packaging::{
@@ -23,8 +26,12 @@ use {
             AddResourceAction, PrePackagedResource, PythonResourceAddCollectionContext,
         },
     },
-    simple_file_manifest::File,
-    std::{collections::HashMap, path::Path, sync::Arc},
+    simple_file_manifest::{File, FileEntry},
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
     tugger_windows::VcRedistributablePlatform,
 };
 
@@ -143,6 +150,25 @@ impl TryFrom<&str> for WindowsRuntimeDllsMode {
     }
 }
 
+/// Settings influencing how cargo builds the Rust project producing a binary.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CargoBuildSettings {
+    /// Cargo features to enable in addition to the ones PyOxidizer enables.
+    pub features: Vec<String>,
+
+    /// Flags to pass to rustc via `RUSTFLAGS`.
+    pub rust_flags: Vec<String>,
+
+    /// Value of the `lto` profile setting.
+    pub lto: Option<String>,
+
+    /// Value of the `codegen-units` profile setting.
+    pub codegen_units: Option<u32>,
+
+    /// Value of the `panic` profile setting.
+    pub panic: Option<String>,
+}
+
 /// A callable that can influence PythonResourceAddCollectionContext.
 pub type ResourceAddCollectionContextCallback<'a> = Box<
     dyn Fn(
@@ -174,6 +200,11 @@ pub trait PythonBinaryBuilder {
     /// Rust target triple the binary will run on.
     fn target_triple(&self) -> &str;
 
+    /// Set the Rust target triple the binary will run on.
+    ///
+    /// The triple must be the one the Python distribution targets.
+    fn set_target_triple(&mut self, value: &str) -> Result<()>;
+
     /// Obtain run-time requirements for the Visual C++ Redistributable.
     ///
     /// If `None`, there is no dependency on `vcruntimeXXX.dll` files. If `Some`,
@@ -184,6 +215,9 @@ pub trait PythonBinaryBuilder {
     /// Obtain the cache tag to apply to Python bytecode modules.
     fn cache_tag(&self) -> &str;
 
+    /// Obtain the full version string of the Python distribution being embedded.
+    fn python_version(&self) -> &str;
+
     /// Obtain the `PythonPackagingPolicy` for the builder.
     fn python_packaging_policy(&self) -> &PythonPackagingPolicy;
 
@@ -235,6 +269,49 @@ pub trait PythonBinaryBuilder {
     /// Set how packed Python resources will be loaded by the binary.
     fn set_packed_resources_load_mode(&mut self, load_mode: PackedResourcesLoadMode);
 
+    /// Whether the binary loads packed resources injected into it after it is built.
+    fn packed_resources_load_injected(&self) -> bool;
+
+    /// Set whether the binary loads packed resources injected into it after it is built.
+    fn set_packed_resources_load_injected(&mut self, value: bool);
+
+    /// Whether the binary is built in development mode.
+    ///
+    /// In development mode, packages registered via `add_dev_overlay()` are
+    /// imported from the filesystem instead of being embedded.
+    fn dev_mode(&self) -> bool;
+
+    /// Set whether the binary is built in development mode.
+    fn set_dev_mode(&mut self, value: bool);
+
+    /// Settings for the cargo build of the binary's Rust project.
+    fn cargo_build_settings(&self) -> &CargoBuildSettings;
+
+    /// Set the settings for the cargo build of the binary's Rust project.
+    fn set_cargo_build_settings(&mut self, value: CargoBuildSettings);
+
+    /// Run a Python module as `__main__` when the binary starts.
+    ///
+    /// This replaces the command, file or module the embedded interpreter is
+    /// configured to run.
+    fn set_run_module(&mut self, module: &str);
+
+    /// Register packages to import from a directory in development mode.
+    ///
+    /// `path` must be an absolute path. It is added to `sys.path` of
+    /// development binaries and resources of `packages` and their
+    /// sub-packages aren't embedded in them.
+    fn add_dev_overlay(&mut self, path: &Path, packages: &[String]) -> Result<()>;
+
+    /// The cache of package dependencies that packaging operations use, if any.
+    fn dependency_cache(&self) -> Option<&DependencyCache>;
+
+    /// Set the cache of package dependencies that packaging operations use.
+    ///
+    /// When populating the cache, packages are downloaded into it. When
+    /// offline, packages are only obtained from it.
+    fn set_dependency_cache(&mut self, cache: Option<DependencyCache>);
+
     /// Obtain an iterator over all resource entries that will be embedded in the binary.
     ///
     /// This likely does not return extension modules that are statically linked
@@ -285,6 +362,24 @@ pub trait PythonBinaryBuilder {
     /// Read Python resources from a populated virtualenv directory.
     fn read_virtualenv(&mut self, path: &Path) -> Result<Vec<PythonResource>>;
 
+    /// Read Python resources from a conda package file.
+    fn read_conda_package(&mut self, path: &Path) -> Result<Vec<PythonResource>>;
+
+    /// Read Python resources from the packages of a conda explicit lock file.
+    fn read_conda_lock(&mut self, path: &Path) -> Result<Vec<PythonResource>>;
+
+    /// Resolve requirements for the target and read Python resources from the resolved wheels.
+    ///
+    /// Unlike `pip`, markers and wheel tags are evaluated for the target
+    /// distribution rather than the host. Wheels are found in the index at
+    /// `index_url`, if any, and in the `find_links` directories.
+    fn resolve_requirements(
+        &mut self,
+        requirements: &[String],
+        index_url: Option<&str>,
+        find_links: &[PathBuf],
+    ) -> Result<Vec<PythonResource>>;
+
     /// Runs `python setup.py install` using the binary builder's settings.
     ///
     /// Returns resources discovered as part of performing an install.
@@ -373,6 +468,12 @@ pub trait PythonBinaryBuilder {
         add_context: Option<PythonResourceAddCollectionContext>,
     ) -> Result<Vec<AddResourceAction>>;
 
+    /// Add a data file accessible at run-time via `oxidized_importer.open_data()`.
+    ///
+    /// `name` is a namespace and a relative path separated by `/`. Data files
+    /// are always embedded in the packed resources.
+    fn add_data_file(&mut self, name: &str, entry: &FileEntry) -> Result<Vec<AddResourceAction>>;
+
     /// Filter embedded resources against names in files.
     ///
     /// `files` is files to read names from.
@@ -393,6 +494,11 @@ pub trait PythonBinaryBuilder {
     /// Whether the binary requires the Snmalloc library.
     fn requires_snmalloc(&self) -> bool;
 
+    /// Features of the `pyembed` crate needed to load the binary's packed resources.
+    ///
+    /// Values are Cargo feature names like `pyembed/resources-zstd`.
+    fn pyembed_resources_features(&self) -> Vec<&'static str>;
+
     /// Obtain software licensing information.
     fn licensed_components(&self) -> Result<LicensedComponents>;
 
@@ -0,0 +1,583 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Interaction with conda packages.
+
+A conda package is an archive of files to install into the prefix of a conda
+environment, plus an `info/` directory describing the package. Packages come
+in 2 formats: a bzip2 compressed tar archive (`.tar.bz2`) and a zip archive
+(`.conda`) holding zstd compressed tar archives of the `info/` directory and
+of the other files.
+
+Files installed into the `site-packages` directory of an environment are
+classified as Python resources. Other files, such as shared libraries, are
+kept as files relative to the environment prefix.
+*/
+
+use {
+    super::distribution::PythonDistribution,
+    anyhow::{anyhow, Context, Result},
+    python_packaging::{
+        filesystem_scanning::PythonResourceIterator, module_util::PythonModuleSuffixes,
+        policy::PythonPackagingPolicy, resource::PythonResource,
+    },
+    serde::Deserialize,
+    sha2::Digest,
+    simple_file_manifest::{File, FileEntry, FileManifest},
+    std::{
+        io::{Cursor, Read},
+        path::{Component, Path, PathBuf},
+    },
+};
+
+/// Name of conda packages providing the Python interpreter.
+///
+/// The Python distribution being embedded provides the interpreter. So these
+/// packages are ignored when reading lock files.
+const PYTHON_PACKAGE_NAMES: &[&str] = &["python"];
+
+#[derive(Deserialize)]
+struct CondaIndex {
+    name: String,
+    version: String,
+    subdir: String,
+    noarch: Option<String>,
+}
+
+/// A conda package read into memory.
+pub struct CondaPackage {
+    /// Name of the package.
+    pub name: String,
+
+    /// Version of the package.
+    pub version: String,
+
+    /// Conda platform the package targets, e.g. `linux-64` or `noarch`.
+    pub subdir: String,
+
+    /// Whether the package is a `noarch: python` package.
+    noarch_python: bool,
+
+    /// Files of the package other than `info/` files, keyed by path relative
+    /// to the environment prefix.
+    files: FileManifest,
+}
+
+/// Resolve the conda platform of a Rust target triple.
+pub fn conda_subdir(target_triple: &str) -> Option<&'static str> {
+    match target_triple {
+        "aarch64-apple-darwin" => Some("osx-arm64"),
+        "aarch64-pc-windows-msvc" => Some("win-arm64"),
+        "aarch64-unknown-linux-gnu" => Some("linux-aarch64"),
+        "i686-pc-windows-msvc" => Some("win-32"),
+        "x86_64-apple-darwin" => Some("osx-64"),
+        "x86_64-pc-windows-msvc" => Some("win-64"),
+        "x86_64-unknown-linux-gnu" => Some("linux-64"),
+        _ => None,
+    }
+}
+
+/// Read the files of a tar archive into a manifest.
+///
+/// Symlinks to files in the archive are resolved to copies of their targets
+/// because symlinks can't be represented as resources.
+fn read_tar(reader: impl Read, files: &mut FileManifest) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    let mut links = vec![];
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+
+        match entry.header().entry_type() {
+            tar::EntryType::Regular => {
+                let mut data = vec![];
+                entry.read_to_end(&mut data)?;
+                let executable = entry.header().mode()? & 0o111 != 0;
+
+                files.add_file_entry(&path, FileEntry::new_from_data(data, executable))?;
+            }
+            tar::EntryType::Symlink => {
+                if let Some(target) = entry.link_name()? {
+                    links.push((path, target.to_path_buf()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (path, target) in links {
+        let mut resolved = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        for component in target.components() {
+            match component {
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::Normal(part) => resolved.push(part),
+                _ => {}
+            }
+        }
+
+        if let Some(entry) = files.get(&resolved).cloned() {
+            files.add_file_entry(&path, entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl CondaPackage {
+    /// Read a conda package from archive data.
+    ///
+    /// `basename` is the file name of the package, which determines its format.
+    pub fn from_data(data: &[u8], basename: &str) -> Result<Self> {
+        let mut files = FileManifest::default();
+
+        if basename.ends_with(".tar.bz2") {
+            read_tar(bzip2::read::BzDecoder::new(data), &mut files)
+                .context("reading tar.bz2 conda package")?;
+        } else if basename.ends_with(".conda") {
+            let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+
+            for i in 0..archive.len() {
+                let file = archive.by_index(i)?;
+
+                if file.name().ends_with(".tar.zst") {
+                    read_tar(zstd::stream::read::Decoder::new(file)?, &mut files)
+                        .with_context(|| format!("reading {} in conda package", basename))?;
+                }
+            }
+        } else {
+            return Err(anyhow!(
+                "{} is not a conda package; expected a .conda or .tar.bz2 file",
+                basename
+            ));
+        }
+
+        let index = files
+            .remove("info/index.json")
+            .ok_or_else(|| anyhow!("conda package does not have an info/index.json file"))?;
+        let index: CondaIndex =
+            serde_json::from_slice(&index.resolve_content()?).context("parsing info/index.json")?;
+
+        for path in files
+            .iter_entries()
+            .map(|(path, _)| path.clone())
+            .filter(|path| path.starts_with("info"))
+            .collect::<Vec<_>>()
+        {
+            files.remove(path);
+        }
+
+        Ok(Self {
+            name: index.name,
+            version: index.version,
+            subdir: index.subdir,
+            noarch_python: index.noarch.as_deref() == Some("python"),
+            files,
+        })
+    }
+
+    /// Read a conda package from a file.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+        let basename = path
+            .file_name()
+            .ok_or_else(|| anyhow!("could not derive file name of {}", path.display()))?
+            .to_string_lossy();
+
+        Self::from_data(&data, &basename)
+    }
+
+    /// Resolve the path of the `site-packages` directory in the package.
+    ///
+    /// Errors if the package installs files for a different Python version.
+    fn site_packages_path(&self, target_triple: &str, python_version: &str) -> Result<PathBuf> {
+        if self.noarch_python {
+            return Ok(PathBuf::from("site-packages"));
+        }
+
+        if target_triple.contains("-windows-") {
+            return Ok(PathBuf::from("Lib/site-packages"));
+        }
+
+        let site_packages = PathBuf::from(format!("lib/python{}/site-packages", python_version));
+
+        for (path, _) in self.files.iter_entries() {
+            let mut components = path.components();
+            if let (Some(lib), Some(Component::Normal(python)), Some(site)) =
+                (components.next(), components.next(), components.next())
+            {
+                if lib.as_os_str() == "lib"
+                    && site.as_os_str() == "site-packages"
+                    && python.to_string_lossy() != format!("python{}", python_version)
+                {
+                    return Err(anyhow!(
+                        "conda package {} {} is built for {}, not Python {}",
+                        self.name,
+                        self.version,
+                        python.to_string_lossy(),
+                        python_version
+                    ));
+                }
+            }
+        }
+
+        Ok(site_packages)
+    }
+
+    /// Obtain `PythonResource` for files of the package.
+    ///
+    /// Files in the `site-packages` directory are classified like files in a
+    /// wheel. Other files are emitted as `PythonResource::File` relative to
+    /// the environment prefix.
+    fn python_resources_for<'a>(
+        &self,
+        site_packages: &Path,
+        cache_tag: &str,
+        suffixes: &PythonModuleSuffixes,
+        emit_files: bool,
+        classify_files: bool,
+    ) -> Result<Vec<PythonResource<'a>>> {
+        let mut inputs = vec![];
+        let mut res = vec![];
+
+        for file in self.files.iter_files() {
+            if let Ok(path) = file.path().strip_prefix(site_packages) {
+                inputs.push(File::new(path, file.entry()));
+            } else {
+                res.push(File::new(file.path(), file.entry()).into());
+            }
+        }
+
+        let mut resources = PythonResourceIterator::from_data_locations(
+            &inputs,
+            cache_tag,
+            suffixes,
+            emit_files,
+            classify_files,
+        )?
+        .collect::<Result<Vec<_>>>()?;
+        resources.extend(res);
+
+        Ok(resources)
+    }
+
+    /// Obtain `PythonResource` for files of the package for a Python distribution.
+    pub fn python_resources<'a>(
+        &self,
+        dist: &dyn PythonDistribution,
+        policy: &PythonPackagingPolicy,
+    ) -> Result<Vec<PythonResource<'a>>> {
+        if !self.noarch_python && self.subdir != "noarch" {
+            let target_subdir = conda_subdir(dist.target_triple());
+
+            if target_subdir != Some(self.subdir.as_str()) {
+                return Err(anyhow!(
+                    "conda package {} {} targets {}, which doesn't match target {}",
+                    self.name,
+                    self.version,
+                    self.subdir,
+                    dist.target_triple()
+                ));
+            }
+        }
+
+        let site_packages =
+            self.site_packages_path(dist.target_triple(), &dist.python_major_minor_version())?;
+
+        self.python_resources_for(
+            &site_packages,
+            dist.cache_tag(),
+            &dist.python_module_suffixes()?,
+            policy.file_scanner_emit_files(),
+            policy.file_scanner_classify_files(),
+        )
+    }
+}
+
+/// A package referenced by a conda explicit lock file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CondaLockEntry {
+    /// Local path of the package file.
+    pub path: PathBuf,
+
+    /// SHA-256 of the package file, if pinned.
+    pub sha256: Option<String>,
+}
+
+/// Parse a conda explicit lock file, as written by `conda list --explicit`.
+///
+/// Packages are resolved to local files because the build shouldn't require
+/// network access: `file://` URLs and paths are used as is, with relative
+/// paths resolved against `base`. Packages with other URLs are resolved to
+/// the file having the same name in `base`, such as a directory mirroring a
+/// conda package cache.
+pub fn parse_conda_explicit_lock(data: &str, base: &Path) -> Result<Vec<CondaLockEntry>> {
+    let mut explicit = false;
+    let mut res = vec![];
+
+    for line in data.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line == "@EXPLICIT" {
+            explicit = true;
+            continue;
+        }
+
+        if !explicit {
+            return Err(anyhow!(
+                "conda lock file is not an explicit lock file; missing @EXPLICIT line"
+            ));
+        }
+
+        let (location, fragment) = match line.split_once('#') {
+            Some((location, fragment)) => (location, Some(fragment)),
+            None => (line, None),
+        };
+
+        // Fragments are an MD5 digest or a `sha256:` prefixed SHA-256 digest.
+        let sha256 = fragment
+            .and_then(|fragment| fragment.strip_prefix("sha256:"))
+            .map(|digest| digest.to_lowercase());
+
+        let path = if let Some(path) = location.strip_prefix("file://") {
+            PathBuf::from(path)
+        } else if location.contains("://") {
+            let basename = location
+                .rsplit('/')
+                .next()
+                .filter(|basename| !basename.is_empty())
+                .ok_or_else(|| anyhow!("invalid conda package URL: {}", location))?;
+
+            base.join(basename)
+        } else {
+            base.join(location)
+        };
+
+        res.push(CondaLockEntry { path, sha256 });
+    }
+
+    Ok(res)
+}
+
+/// Read Python resources from a conda package file.
+pub fn read_conda_package<'a>(
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    path: &Path,
+) -> Result<Vec<PythonResource<'a>>> {
+    CondaPackage::from_path(path)?.python_resources(dist, policy)
+}
+
+/// Read Python resources from the packages of a conda explicit lock file.
+///
+/// Packages providing the Python interpreter are ignored.
+pub fn read_conda_explicit_lock<'a>(
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    path: &Path,
+) -> Result<Vec<PythonResource<'a>>> {
+    let data =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let entries = parse_conda_explicit_lock(&data, path.parent().unwrap_or_else(|| Path::new("")))?;
+
+    let mut res = vec![];
+
+    for entry in entries {
+        let data = std::fs::read(&entry.path)
+            .with_context(|| format!("reading conda package {}", entry.path.display()))?;
+
+        if let Some(expected) = &entry.sha256 {
+            let actual = hex::encode(sha2::Sha256::digest(&data));
+
+            if &actual != expected {
+                return Err(anyhow!(
+                    "SHA-256 of {} is {}; expected {}",
+                    entry.path.display(),
+                    actual,
+                    expected
+                ));
+            }
+        }
+
+        let basename = entry
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let package = CondaPackage::from_data(&data, &basename)
+            .with_context(|| format!("reading conda package {}", entry.path.display()))?;
+
+        if PYTHON_PACKAGE_NAMES.contains(&package.name.as_str()) {
+            continue;
+        }
+
+        res.extend(package.python_resources(dist, policy)?);
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::io::Write};
+
+    fn tar_data(files: &[(&str, &[u8])], links: &[(&str, &str)]) -> Result<Vec<u8>> {
+        let mut builder = tar::Builder::new(vec![]);
+
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, *data)?;
+        }
+
+        for (path, target) in links {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            builder.append_link(&mut header, path, target)?;
+        }
+
+        Ok(builder.into_inner()?)
+    }
+
+    const INDEX: &[u8] = br#"{"name": "foo", "version": "1.0", "subdir": "linux-64"}"#;
+
+    #[test]
+    fn test_read_tar_bz2() -> Result<()> {
+        let tar = tar_data(
+            &[
+                ("info/index.json", INDEX),
+                (
+                    "lib/python3.10/site-packages/foo/__init__.py",
+                    b"print('hello')",
+                ),
+                ("lib/libfoo.so.1", b"library"),
+            ],
+            &[("lib/libfoo.so", "libfoo.so.1")],
+        )?;
+
+        let mut encoder = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+        encoder.write_all(&tar)?;
+        let data = encoder.finish()?;
+
+        let package = CondaPackage::from_data(&data, "foo-1.0-py310_0.tar.bz2")?;
+        assert_eq!(package.name, "foo");
+        assert_eq!(package.version, "1.0");
+        assert_eq!(package.subdir, "linux-64");
+        assert!(!package.noarch_python);
+
+        let site_packages = package.site_packages_path("x86_64-unknown-linux-gnu", "3.10")?;
+        assert_eq!(site_packages, PathBuf::from("lib/python3.10/site-packages"));
+        assert!(package
+            .site_packages_path("x86_64-unknown-linux-gnu", "3.9")
+            .is_err());
+
+        let suffixes = PythonModuleSuffixes {
+            source: vec![".py".to_string()],
+            bytecode: vec![".pyc".to_string()],
+            debug_bytecode: vec![],
+            optimized_bytecode: vec![],
+            extension: vec![],
+        };
+
+        let resources =
+            package.python_resources_for(&site_packages, "cpython-310", &suffixes, false, true)?;
+
+        assert_eq!(resources.len(), 3);
+        assert!(resources
+            .iter()
+            .any(|r| matches!(r, PythonResource::ModuleSource(m) if m.name == "foo")));
+
+        let files = resources
+            .iter()
+            .filter_map(|r| match r {
+                PythonResource::File(f) => Some(f),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files.iter().map(|f| f.path()).collect::<Vec<_>>(),
+            vec![Path::new("lib/libfoo.so"), Path::new("lib/libfoo.so.1")]
+        );
+        assert_eq!(files[0].entry().resolve_content()?, b"library");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_conda() -> Result<()> {
+        let info = tar_data(
+            &[(
+                "info/index.json",
+                br#"{"name": "bar", "version": "2.0", "subdir": "noarch", "noarch": "python"}"#,
+            )],
+            &[],
+        )?;
+        let pkg = tar_data(&[("site-packages/bar.py", b"")], &[])?;
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        writer.start_file("metadata.json", options)?;
+        writer.write_all(br#"{"conda_pkg_format_version": 2}"#)?;
+        writer.start_file("info-bar-2.0-pyhd8ed1ab_0.tar.zst", options)?;
+        writer.write_all(&zstd::encode_all(info.as_slice(), 3)?)?;
+        writer.start_file("pkg-bar-2.0-pyhd8ed1ab_0.tar.zst", options)?;
+        writer.write_all(&zstd::encode_all(pkg.as_slice(), 3)?)?;
+        let data = writer.finish()?.into_inner();
+
+        let package = CondaPackage::from_data(&data, "bar-2.0-pyhd8ed1ab_0.conda")?;
+        assert_eq!(package.name, "bar");
+        assert!(package.noarch_python);
+        assert_eq!(
+            package.site_packages_path("x86_64-pc-windows-msvc", "3.10")?,
+            PathBuf::from("site-packages")
+        );
+
+        assert!(CondaPackage::from_data(&data, "bar-2.0.zip").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_conda_explicit_lock() -> Result<()> {
+        let data = concat!(
+            "# platform: linux-64\n",
+            "@EXPLICIT\n",
+            "https://conda.anaconda.org/conda-forge/linux-64/numpy-1.23.4-py310h53a5b5f_1.conda#0123456789abcdef0123456789abcdef\n",
+            "file:///opt/pkgs/foo-1.0-0.tar.bz2#sha256:AABB\n",
+            "vendor/bar-2.0-0.conda\n",
+        );
+
+        assert_eq!(
+            parse_conda_explicit_lock(data, Path::new("/lock"))?,
+            vec![
+                CondaLockEntry {
+                    path: PathBuf::from("/lock/numpy-1.23.4-py310h53a5b5f_1.conda"),
+                    sha256: None,
+                },
+                CondaLockEntry {
+                    path: PathBuf::from("/opt/pkgs/foo-1.0-0.tar.bz2"),
+                    sha256: Some("aabb".to_string()),
+                },
+                CondaLockEntry {
+                    path: PathBuf::from("/lock/vendor/bar-2.0-0.conda"),
+                    sha256: None,
+                },
+            ]
+        );
+
+        assert!(parse_conda_explicit_lock("numpy=1.23.4\n", Path::new("/lock")).is_err());
+
+        Ok(())
+    }
+}
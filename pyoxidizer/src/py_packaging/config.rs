@@ -11,13 +11,14 @@ use {
     itertools::Itertools,
     python_packaging::{
         interpreter::{
-            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-            MultiprocessingStartMethod, PythonInterpreterConfig, PythonInterpreterProfile,
-            TerminfoResolution,
+            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, ImportPrecedence,
+            MemoryAllocatorBackend, MultiprocessingStartMethod, PythonInterpreterConfig,
+            PythonInterpreterProfile, TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
     std::{
+        collections::BTreeMap,
         io::Write,
         path::{Path, PathBuf},
     },
@@ -29,8 +30,13 @@ pub fn default_memory_allocator(target_triple: &str) -> MemoryAllocatorBackend {
     //
     // We don't use Jemalloc by default in the test environment because it slows down
     // builds of test projects.
+    //
+    // Jemalloc's initial-exec TLS model is problematic in static musl binaries
+    // and musl's own allocator is notoriously slow. So we use mimalloc there.
     if target_triple.ends_with("-pc-windows-msvc") || cfg!(test) {
         MemoryAllocatorBackend::Default
+    } else if target_triple.contains("-linux-musl") {
+        MemoryAllocatorBackend::Mimalloc
     } else {
         MemoryAllocatorBackend::Jemalloc
     }
@@ -50,6 +56,29 @@ fn optional_string_to_string(value: &Option<String>) -> String {
     }
 }
 
+fn package_precedence_to_string(value: &BTreeMap<String, ImportPrecedence>) -> String {
+    if value.is_empty() {
+        return "Default::default()".to_string();
+    }
+
+    format!(
+        "[{}].into_iter().collect()",
+        value
+            .iter()
+            .map(|(package, precedence)| format!(
+                "(\"{}\".to_string(), {})",
+                package.escape_default(),
+                match precedence {
+                    ImportPrecedence::MemoryFirst => "pyembed::ImportPrecedence::MemoryFirst",
+                    ImportPrecedence::FilesystemFirst =>
+                        "pyembed::ImportPrecedence::FilesystemFirst",
+                    ImportPrecedence::MemoryOnly => "pyembed::ImportPrecedence::MemoryOnly",
+                }
+            ))
+            .join(", ")
+    )
+}
+
 fn path_to_string(value: &Path) -> String {
     format!(
         "std::path::PathBuf::from(\"{}\")",
@@ -87,6 +116,12 @@ pub enum PyembedPackedResourcesSource {
     ///
     /// The string `$ORIGIN` is expanded at runtime.
     MemoryMappedPath(PathBuf),
+    /// Load from a file by reading it into memory.
+    ///
+    /// The string `$ORIGIN` is expanded at runtime.
+    Path(PathBuf),
+    /// Load data injected into the executable after it was built.
+    CurrentExecutable,
 }
 
 impl ToString for PyembedPackedResourcesSource {
@@ -104,6 +139,13 @@ impl ToString for PyembedPackedResourcesSource {
                     path_to_string(path)
                 )
             }
+            Self::Path(path) => {
+                format!(
+                    "pyembed::PackedResourcesSource::Path({})",
+                    path_to_string(path)
+                )
+            }
+            Self::CurrentExecutable => "pyembed::PackedResourcesSource::CurrentExecutable".into(),
         }
     }
 }
@@ -126,12 +168,19 @@ pub struct PyembedPythonInterpreterConfig {
     pub set_missing_path_configuration: bool,
     pub oxidized_importer: bool,
     pub filesystem_importer: bool,
+    pub appended_zip_importer: bool,
     pub packed_resources: Vec<PyembedPackedResourcesSource>,
     pub argvb: bool,
     pub multiprocessing_auto_dispatch: bool,
     pub multiprocessing_start_method: MultiprocessingStartMethod,
+    pub synthesize_dunder_file: bool,
+    pub package_precedence: BTreeMap<String, ImportPrecedence>,
+    pub reload_from_filesystem: bool,
+    pub verify_resources: bool,
+    pub packed_resources_public_key: Option<[u8; 32]>,
     pub sys_frozen: bool,
     pub sys_meipass: bool,
+    pub traceback_excepthook: bool,
     pub terminfo_resolution: TerminfoResolution,
     pub tcl_library: Option<PathBuf>,
     pub write_modules_directory_env: Option<String>,
@@ -161,12 +210,19 @@ impl Default for PyembedPythonInterpreterConfig {
             set_missing_path_configuration: true,
             oxidized_importer: true,
             filesystem_importer: false,
+            appended_zip_importer: false,
             packed_resources: vec![],
             argvb: false,
             multiprocessing_auto_dispatch: true,
             multiprocessing_start_method: MultiprocessingStartMethod::Auto,
+            synthesize_dunder_file: false,
+            package_precedence: BTreeMap::new(),
+            reload_from_filesystem: false,
+            verify_resources: false,
+            packed_resources_public_key: None,
             sys_frozen: true,
             sys_meipass: false,
+            traceback_excepthook: false,
             terminfo_resolution: TerminfoResolution::None,
             tcl_library: None,
             write_modules_directory_env: None,
@@ -221,6 +277,7 @@ impl PyembedPythonInterpreterConfig {
             optimization_level: {},\n        \
             parser_debug: {},\n        \
             pathconfig_warnings: {},\n        \
+            platlibdir: {},\n        \
             prefix: {},\n        \
             program_name: {},\n        \
             pycache_prefix: {},\n        \
@@ -235,8 +292,10 @@ impl PyembedPythonInterpreterConfig {
             stdio_encoding: {},\n        \
             stdio_errors: {},\n        \
             tracemalloc: {},\n        \
+            tracemalloc_frames: {},\n        \
             user_site_directory: {},\n        \
             verbose: {},\n        \
+            warn_default_encoding: {},\n        \
             warn_options: {},\n        \
             write_bytecode: {},\n        \
             x_options: {},\n        \
@@ -250,14 +309,37 @@ impl PyembedPythonInterpreterConfig {
             set_missing_path_configuration: {},\n    \
             oxidized_importer: {},\n    \
             filesystem_importer: {},\n    \
+            appended_zip_importer: {},\n    \
             packed_resources: {},\n    \
             extra_extension_modules: None,\n    \
+            rust_modules: vec![],\n    \
             argv: None,\n    \
             argvb: {},\n    \
+            run_zipapp: None,\n    \
+            run_frozen_main: None,\n    \
+            multicall: Default::default(),\n    \
+            startup_commands: vec![],\n    \
             multiprocessing_auto_dispatch: {},\n    \
             multiprocessing_start_method: {},\n    \
+            synthesize_dunder_file: {},\n    \
+            package_precedence: {},\n    \
+            reload_from_filesystem: {},\n    \
+            verify_resources: {},\n    \
+            packed_resources_public_key: {},\n    \
+            packed_resources_decryption_key_hook: None,\n    \
             sys_frozen: {},\n    \
             sys_meipass: {},\n    \
+            traceback_excepthook: {},\n    \
+            excepthook: None,\n    \
+            pre_initialize_hook: None,\n    \
+            core_initialize_hook: None,\n    \
+            post_initialize_hook: None,\n    \
+            rust_signal_handlers: false,\n    \
+            atexit_hooks: vec![],\n    \
+            stdout_writer: None,\n    \
+            stderr_writer: None,\n    \
+            repl_line_editing: false,\n    \
+            repl_history_file: None,\n    \
             terminfo_resolution: {},\n    \
             tcl_library: {},\n    \
             write_modules_directory_env: {},\n    \
@@ -348,6 +430,7 @@ impl PyembedPythonInterpreterConfig {
             },
             optional_bool_to_string(&self.config.parser_debug),
             optional_bool_to_string(&self.config.pathconfig_warnings),
+            optional_string_to_string(&self.config.platlibdir),
             optional_pathbuf_to_string(&self.config.prefix),
             optional_pathbuf_to_string(&self.config.program_name),
             optional_pathbuf_to_string(&self.config.pycache_prefix),
@@ -362,8 +445,13 @@ impl PyembedPythonInterpreterConfig {
             optional_string_to_string(&self.config.stdio_encoding),
             optional_string_to_string(&self.config.stdio_errors),
             optional_bool_to_string(&self.config.tracemalloc),
+            match &self.config.tracemalloc_frames {
+                Some(value) => format!("Some({})", value),
+                None => "None".to_string(),
+            },
             optional_bool_to_string(&self.config.user_site_directory),
             optional_bool_to_string(&self.config.verbose),
+            optional_bool_to_string(&self.config.warn_default_encoding),
             optional_vec_string_to_string(&self.config.warn_options),
             optional_bool_to_string(&self.config.write_bytecode),
             optional_vec_string_to_string(&self.config.x_options),
@@ -382,6 +470,7 @@ impl PyembedPythonInterpreterConfig {
             self.set_missing_path_configuration,
             self.oxidized_importer,
             self.filesystem_importer,
+            self.appended_zip_importer,
             format!(
                 "vec![{}]",
                 self.packed_resources
@@ -403,8 +492,17 @@ impl PyembedPythonInterpreterConfig {
                 MultiprocessingStartMethod::Auto =>
                     "pyembed::MultiprocessingStartMethod::Auto".to_string(),
             },
+            self.synthesize_dunder_file,
+            package_precedence_to_string(&self.package_precedence),
+            self.reload_from_filesystem,
+            self.verify_resources,
+            match self.packed_resources_public_key {
+                Some(key) => format!("Some({:?})", key),
+                None => "None".to_string(),
+            },
             self.sys_frozen,
             self.sys_meipass,
+            self.traceback_excepthook,
             match self.terminfo_resolution {
                 TerminfoResolution::Dynamic => "pyembed::TerminfoResolution::Dynamic".to_string(),
                 TerminfoResolution::None => "pyembed::TerminfoResolution::None".to_string(),
@@ -562,6 +660,7 @@ mod tests {
                 optimization_level: Some(BytecodeOptimizationLevel::One),
                 parser_debug: Some(true),
                 pathconfig_warnings: Some(false),
+                platlibdir: Some("lib".into()),
                 prefix: Some("prefix".into()),
                 program_name: Some("program_name".into()),
                 pycache_prefix: Some("prefix".into()),
@@ -576,8 +675,10 @@ mod tests {
                 stdio_encoding: Some("encoding".into()),
                 stdio_errors: Some("errors".into()),
                 tracemalloc: Some(false),
+                tracemalloc_frames: Some(25),
                 user_site_directory: Some(false),
                 verbose: Some(true),
+                warn_default_encoding: Some(false),
                 warn_options: Some(vec!["option0".into(), "option1".into()]),
                 write_bytecode: Some(true),
                 x_options: Some(vec!["x0".into(), "x1".into()]),
@@ -591,20 +692,34 @@ mod tests {
             set_missing_path_configuration: false,
             oxidized_importer: true,
             filesystem_importer: true,
+            appended_zip_importer: true,
             packed_resources: vec![
                 PyembedPackedResourcesSource::MemoryIncludeBytes(PathBuf::from("packed-resources")),
                 PyembedPackedResourcesSource::MemoryMappedPath(PathBuf::from(
                     "$ORIGIN/packed-resources",
                 )),
+                PyembedPackedResourcesSource::Path(PathBuf::from("$ORIGIN/extra-resources")),
+                PyembedPackedResourcesSource::CurrentExecutable,
             ],
             argvb: true,
             sys_frozen: false,
             sys_meipass: true,
+            traceback_excepthook: true,
             terminfo_resolution: TerminfoResolution::Dynamic,
             tcl_library: Some("path".into()),
             write_modules_directory_env: Some("env".into()),
             multiprocessing_auto_dispatch: false,
             multiprocessing_start_method: MultiprocessingStartMethod::Spawn,
+            synthesize_dunder_file: true,
+            package_precedence: [
+                ("foo".to_string(), ImportPrecedence::FilesystemFirst),
+                ("bar".to_string(), ImportPrecedence::MemoryOnly),
+            ]
+            .into_iter()
+            .collect(),
+            reload_from_filesystem: true,
+            verify_resources: true,
+            packed_resources_public_key: Some([42; 32]),
         };
 
         let builder = dist.as_python_executable_builder(
@@ -624,6 +739,7 @@ mod tests {
             default_target_triple(),
             "0",
             false,
+            None,
         )?;
 
         Ok(())
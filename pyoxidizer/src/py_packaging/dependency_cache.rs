@@ -0,0 +1,128 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Local directories caching Python package dependencies.
+
+A dependency cache is a flat directory of package files, as accepted by pip's
+`--find-links`. `pyoxidizer download-deps` populates it by evaluating a
+configuration file with packaging operations downloading everything they
+fetch into the cache. Builds can then run offline, with packaging operations
+only obtaining packages from the cache.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    std::path::{Path, PathBuf},
+};
+
+/// How packaging operations use a dependency cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DependencyCacheMode {
+    /// Packages are downloaded into the cache before being used from it.
+    Populate,
+
+    /// Packages are only obtained from the cache. The network isn't accessed.
+    Offline,
+}
+
+/// A directory caching Python package dependencies.
+#[derive(Clone, Debug)]
+pub struct DependencyCache {
+    path: PathBuf,
+    mode: DependencyCacheMode,
+}
+
+impl DependencyCache {
+    /// Construct an instance.
+    ///
+    /// Relative paths are resolved against the current directory. The
+    /// directory is created when populating the cache and must exist when
+    /// offline.
+    pub fn new(path: impl AsRef<Path>, mode: DependencyCacheMode) -> Result<Self> {
+        let path = std::env::current_dir()?.join(path.as_ref());
+
+        match mode {
+            DependencyCacheMode::Populate => std::fs::create_dir_all(&path)
+                .with_context(|| format!("creating {}", path.display()))?,
+            DependencyCacheMode::Offline => {
+                if !path.is_dir() {
+                    return Err(anyhow!(
+                        "dependency cache {} does not exist; run `pyoxidizer download-deps` to populate it",
+                        path.display()
+                    ));
+                }
+            }
+        }
+
+        Ok(Self { path, mode })
+    }
+
+    /// The directory holding cached files.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How packaging operations use the cache.
+    pub fn mode(&self) -> DependencyCacheMode {
+        self.mode
+    }
+
+    /// Whether the network must not be accessed.
+    pub fn is_offline(&self) -> bool {
+        self.mode == DependencyCacheMode::Offline
+    }
+
+    /// Resolve pip arguments making pip only obtain packages from the cache.
+    ///
+    /// When populating, these are used after downloading packages into the
+    /// cache. So missing packages are discovered before going offline.
+    pub fn pip_args(&self) -> Vec<String> {
+        vec![
+            "--no-index".to_string(),
+            format!("--find-links={}", self.path.display()),
+        ]
+    }
+
+    /// Store a package file in the cache.
+    pub fn store(&self, filename: &str, data: &[u8]) -> Result<()> {
+        let path = self.path.join(filename);
+
+        std::fs::write(&path, data).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modes() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let path = temp_dir.path().join("deps");
+
+        assert!(DependencyCache::new(&path, DependencyCacheMode::Offline).is_err());
+
+        let cache = DependencyCache::new(&path, DependencyCacheMode::Populate)?;
+        assert!(path.is_dir());
+        assert!(!cache.is_offline());
+
+        cache.store("foo-1.0-py3-none-any.whl", b"foo")?;
+
+        let cache = DependencyCache::new(&path, DependencyCacheMode::Offline)?;
+        assert!(cache.is_offline());
+        assert!(cache.path().join("foo-1.0-py3-none-any.whl").exists());
+        assert_eq!(
+            cache.pip_args(),
+            vec![
+                "--no-index".to_string(),
+                format!("--find-links={}", path.display())
+            ]
+        );
+
+        Ok(())
+    }
+}
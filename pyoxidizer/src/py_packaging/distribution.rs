@@ -10,9 +10,13 @@ use {
     super::{
         binary::{LibpythonLinkMode, PythonBinaryBuilder},
         config::PyembedPythonInterpreterConfig,
+        pypy_distribution::PyPyDistribution,
         standalone_distribution::StandaloneDistribution,
     },
-    crate::{environment::Environment, python_distributions::PYTHON_DISTRIBUTIONS},
+    crate::{
+        environment::Environment,
+        python_distributions::{DEFAULT_PYTHON_VERSION, PYTHON_DISTRIBUTIONS},
+    },
     anyhow::{anyhow, Context, Result},
     fs2::FileExt,
     log::info,
@@ -81,6 +85,9 @@ pub struct PythonDistributionRecord {
 
     /// Whether the distribution can load prebuilt extension modules.
     pub supports_prebuilt_extension_modules: bool,
+
+    /// Whether the distribution is a free-threaded build without a GIL.
+    pub free_threaded: bool,
 }
 
 /// Describes Apple SDK build/targeting.
@@ -219,6 +226,14 @@ pub trait PythonDistribution {
     /// executable and load it without having to materialize it on a filesystem.
     fn supports_in_memory_shared_library_loading(&self) -> bool;
 
+    /// Whether this distribution is a free-threaded build without a global interpreter lock.
+    ///
+    /// Free-threaded builds of Python 3.13+ define `Py_GIL_DISABLED` and have a
+    /// distinct ABI, denoted by a `t` suffix on the ABI tag. e.g. `cp313t`.
+    fn is_free_threaded(&self) -> bool {
+        self.python_config_vars().get("Py_GIL_DISABLED") == Some(&"1".to_string())
+    }
+
     /// Determine whether a named module is in a known standard library test package.
     fn is_stdlib_test_package(&self, name: &str) -> bool {
         for package in self.stdlib_test_packages() {
@@ -293,11 +308,8 @@ fn sha256_path(path: &Path) -> Vec<u8> {
     hasher.finalize().to_vec()
 }
 
-/// Ensure a Python distribution at a URL is available in a local directory.
-///
-/// The path to the downloaded and validated file is returned.
-pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Result<PathBuf> {
-    let expected_hash = hex::decode(sha256)?;
+/// Resolve the path of the cached copy of a remote distribution.
+fn cached_distribution_path(url: &str, cache_dir: &Path) -> Result<PathBuf> {
     let u = Url::parse(url)?;
 
     let basename = u
@@ -307,7 +319,24 @@ pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Resul
         .unwrap()
         .to_string();
 
-    let cache_path = cache_dir.join(basename);
+    Ok(cache_dir.join(basename))
+}
+
+/// Whether a valid copy of a remote distribution is cached.
+fn is_distribution_cached(url: &str, sha256: &str, cache_dir: &Path) -> Result<bool> {
+    let cache_path = cached_distribution_path(url, cache_dir)?;
+
+    Ok(cache_path.exists() && sha256_path(&cache_path) == hex::decode(sha256)?)
+}
+
+/// Ensure a Python distribution at a URL is available in a local directory.
+///
+/// The path to the downloaded and validated file is returned.
+pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Result<PathBuf> {
+    let expected_hash = hex::decode(sha256)?;
+    let u = Url::parse(url)?;
+
+    let cache_path = cached_distribution_path(url, cache_dir)?;
 
     if cache_path.exists() {
         let file_hash = sha256_path(&cache_path);
@@ -435,7 +464,7 @@ pub fn resolve_python_distribution_from_location(
 
 /// Describes the flavor of a distribution.
 #[allow(clippy::enum_variant_names)]
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum DistributionFlavor {
     /// Distributions coming from the `python-build-standalone` project.
     Standalone,
@@ -445,6 +474,12 @@ pub enum DistributionFlavor {
 
     /// Dynamically linked distributions coming from the `python-build-standalone` project.
     StandaloneDynamic,
+
+    /// Official PyPy release archives.
+    ///
+    /// PyPy can't be embedded in an executable. These distributions are
+    /// installed as a filesystem layout run by a launcher instead.
+    PyPy,
 }
 
 impl Default for DistributionFlavor {
@@ -459,6 +494,7 @@ impl Display for DistributionFlavor {
             Self::Standalone => "standalone",
             Self::StandaloneStatic => "standalone-static",
             Self::StandaloneDynamic => "standalone-dynamic",
+            Self::PyPy => "pypy",
         })
     }
 }
@@ -471,6 +507,7 @@ impl TryFrom<&str> for DistributionFlavor {
             "standalone" => Ok(Self::Standalone),
             "standalone_static" | "standalone-static" => Ok(Self::StandaloneStatic),
             "standalone_dynamic" | "standalone-dynamic" => Ok(Self::StandaloneDynamic),
+            "pypy" => Ok(Self::PyPy),
             _ => Err(format!("distribution flavor {} not recognized", value)),
         }
     }
@@ -484,6 +521,7 @@ type DistributionCacheValue = Arc<Mutex<Option<Arc<StandaloneDistribution>>>>;
 pub struct DistributionCache {
     cache: Mutex<HashMap<DistributionCacheKey, DistributionCacheValue>>,
     default_dest_dir: Option<PathBuf>,
+    offline: bool,
 }
 
 impl DistributionCache {
@@ -491,9 +529,16 @@ impl DistributionCache {
         Self {
             cache: Mutex::new(HashMap::new()),
             default_dest_dir: default_dest_dir.map(|x| x.to_path_buf()),
+            offline: false,
         }
     }
 
+    /// Set whether resolving remote distributions that aren't cached fails instead of downloading them.
+    pub fn offline(mut self, value: bool) -> Self {
+        self.offline = value;
+        self
+    }
+
     /// Resolve a `PythonDistribution` given its source and storage locations.
     pub fn resolve_distribution(
         &self,
@@ -567,6 +612,8 @@ impl DistributionCache {
         if let Some(dist) = value {
             Ok(dist.clone())
         } else {
+            self.ensure_available(location, dest_dir)?;
+
             let dist = Arc::new(StandaloneDistribution::from_location(location, dest_dir)?);
 
             lock.replace(dist.clone());
@@ -575,6 +622,49 @@ impl DistributionCache {
         }
     }
 
+    /// Resolve a PyPy distribution given its source and storage locations.
+    ///
+    /// PyPy distributions are introspected by running them, which is cheap. So
+    /// resolved instances aren't cached.
+    pub fn resolve_pypy_distribution(
+        &self,
+        location: &PythonDistributionLocation,
+        dest_dir: Option<&Path>,
+    ) -> Result<Arc<PyPyDistribution>> {
+        let dest_dir = if let Some(p) = dest_dir {
+            p
+        } else if let Some(p) = &self.default_dest_dir {
+            p
+        } else {
+            return Err(anyhow!("no destination directory available"));
+        };
+
+        self.ensure_available(location, dest_dir)?;
+
+        Ok(Arc::new(PyPyDistribution::from_location(
+            location, dest_dir,
+        )?))
+    }
+
+    /// Ensure a distribution can be resolved without downloading it in offline mode.
+    fn ensure_available(
+        &self,
+        location: &PythonDistributionLocation,
+        dest_dir: &Path,
+    ) -> Result<()> {
+        if let PythonDistributionLocation::Url { url, sha256 } = location {
+            if self.offline && !is_distribution_cached(url, sha256, dest_dir)? {
+                return Err(anyhow!(
+                    "Python distribution {} is not cached in {} and downloading is disabled in offline mode; run `pyoxidizer download-deps` to cache it",
+                    url,
+                    dest_dir.display()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Resolve a Python distribution that runs on the current machine.
     pub fn host_distribution(
         &self,
@@ -613,9 +703,36 @@ pub fn default_distribution_location(
     target: &str,
     python_major_minor_version: Option<&str>,
 ) -> Result<PythonDistributionLocation> {
+    if flavor == &DistributionFlavor::PyPy {
+        return Err(anyhow!(
+            "there are no default PyPy distributions; define one with PythonDistribution(sha256, url=..., flavor=\"pypy\")"
+        ));
+    }
+
     let dist = PYTHON_DISTRIBUTIONS
         .find_distribution(target, flavor, python_major_minor_version)
-        .ok_or_else(|| anyhow!("could not find default Python distribution for {}", target))?;
+        .ok_or_else(|| {
+            let versions = PYTHON_DISTRIBUTIONS.python_versions(target, flavor);
+
+            if versions.is_empty() {
+                if flavor == &DistributionFlavor::StandaloneDynamic
+                    && target.contains("-linux-musl")
+                {
+                    anyhow!(
+                        "musl libc targets only have statically linked Python distributions; use the standalone or standalone_static flavor"
+                    )
+                } else {
+                    anyhow!("could not find default Python distribution for {}", target)
+                }
+            } else {
+                anyhow!(
+                    "could not find default Python {} distribution for {}; available versions: {}",
+                    python_major_minor_version.unwrap_or(DEFAULT_PYTHON_VERSION),
+                    target,
+                    versions.join(", ")
+                )
+            }
+        })?;
 
     Ok(dist.location)
 }
@@ -630,4 +747,31 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_default_distribution_location_musl() -> Result<()> {
+        let location = default_distribution_location(
+            &DistributionFlavor::Standalone,
+            "x86_64-unknown-linux-musl",
+            None,
+        )?;
+        assert_eq!(
+            location,
+            default_distribution_location(
+                &DistributionFlavor::StandaloneStatic,
+                "x86_64-unknown-linux-musl",
+                None,
+            )?
+        );
+
+        let err = default_distribution_location(
+            &DistributionFlavor::StandaloneDynamic,
+            "x86_64-unknown-linux-musl",
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("musl libc targets only have"));
+
+        Ok(())
+    }
 }
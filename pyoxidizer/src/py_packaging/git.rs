@@ -0,0 +1,282 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Obtaining Python packages from git repositories.
+
+Packages are installed from a checkout of an exact commit. The commit must
+be given as a full hash so the installed sources can't change behind the
+user's back, unlike with branches, tags, or abbreviated hashes. The checkout
+is verified to be at that commit before anything is installed from it.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    serde::Deserialize,
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Resource metadata key holding the URL of the git repository.
+pub const METADATA_URL: &str = "vcs.url";
+
+/// Resource metadata key holding the commit installed from.
+pub const METADATA_COMMIT: &str = "vcs.commit";
+
+/// Resource metadata key holding the directory of the package in the repository.
+pub const METADATA_SUBDIRECTORY: &str = "vcs.subdirectory";
+
+/// Validate and normalize a full git commit hash.
+///
+/// SHA-1 and SHA-256 hashes are accepted.
+pub fn normalize_commit(commit: &str) -> Result<String> {
+    if matches!(commit.len(), 40 | 64) && commit.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(commit.to_ascii_lowercase())
+    } else {
+        Err(anyhow!(
+            "{} is not a full git commit hash; pinning requires all 40 (or 64) hex digits",
+            commit
+        ))
+    }
+}
+
+/// Run git with arguments, returning its trimmed stdout.
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<String> {
+    let mut command = cmd("git", args)
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked();
+    if let Some(dir) = dir {
+        command = command.dir(dir);
+    }
+
+    let output = command
+        .run()
+        .with_context(|| format!("running git {}", args.join(" ")))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// A checkout of a git repository at an exact commit.
+#[derive(Clone, Debug)]
+pub struct GitCheckout {
+    url: String,
+    commit: String,
+    path: PathBuf,
+}
+
+impl GitCheckout {
+    /// Clone a repository into `dest` and check out a commit.
+    ///
+    /// If the commit isn't reachable from the refs obtained by cloning, it
+    /// is fetched explicitly. Submodules are checked out as well.
+    pub fn clone_commit(url: &str, commit: &str, dest: &Path) -> Result<Self> {
+        let commit = normalize_commit(commit)?;
+        let dest_str = dest.display().to_string();
+
+        git(
+            None,
+            &["clone", "--quiet", "--no-checkout", "--", url, &dest_str],
+        )
+        .with_context(|| format!("cloning {}", url))?;
+
+        let object = format!("{}^{{commit}}", commit);
+        if git(Some(dest), &["cat-file", "-e", &object]).is_err() {
+            git(Some(dest), &["fetch", "--quiet", "origin", &commit])
+                .with_context(|| format!("fetching commit {} from {}", commit, url))?;
+        }
+
+        git(Some(dest), &["checkout", "--quiet", "--detach", &commit])
+            .with_context(|| format!("checking out commit {}", commit))?;
+        git(
+            Some(dest),
+            &["submodule", "update", "--quiet", "--init", "--recursive"],
+        )
+        .context("checking out submodules")?;
+
+        let checkout = Self {
+            url: url.to_string(),
+            commit,
+            path: dest.to_path_buf(),
+        };
+        checkout.verify()?;
+
+        Ok(checkout)
+    }
+
+    /// The directory holding the checkout.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Verify the checkout is exactly at the pinned commit.
+    ///
+    /// Errors if `HEAD` is at a different commit or if the working directory
+    /// has modifications.
+    pub fn verify(&self) -> Result<()> {
+        let head = git(Some(&self.path), &["rev-parse", "HEAD"])?;
+        if head != self.commit {
+            return Err(anyhow!(
+                "checkout of {} is at commit {}; expected {}",
+                self.url,
+                head,
+                self.commit
+            ));
+        }
+
+        let status = git(Some(&self.path), &["status", "--porcelain"])?;
+        if !status.is_empty() {
+            return Err(anyhow!(
+                "checkout of {} at commit {} has modifications:\n{}",
+                self.url,
+                self.commit,
+                status
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Resource metadata recording where packages were installed from.
+    pub fn provenance_metadata(&self, subdirectory: Option<&str>) -> BTreeMap<String, String> {
+        let mut metadata = BTreeMap::new();
+        metadata.insert(METADATA_URL.to_string(), self.url.clone());
+        metadata.insert(METADATA_COMMIT.to_string(), self.commit.clone());
+        if let Some(subdirectory) = subdirectory {
+            metadata.insert(METADATA_SUBDIRECTORY.to_string(), subdirectory.to_string());
+        }
+
+        metadata
+    }
+}
+
+#[derive(Deserialize)]
+struct DirectUrl {
+    url: String,
+}
+
+/// Whether a `direct_url.json` file records an install from a local directory.
+///
+/// pip writes this file (PEP 610) into the `.dist-info` directory of packages
+/// installed from something other than a package index. It is how we tell the
+/// package installed from a checkout apart from its dependencies.
+pub fn direct_url_is_directory(data: &[u8], dir: &Path) -> bool {
+    let direct_url = match serde_json::from_slice::<DirectUrl>(data) {
+        Ok(direct_url) => direct_url,
+        Err(_) => return false,
+    };
+
+    let path = match url::Url::parse(&direct_url.url).map(|url| url.to_file_path()) {
+        Ok(Ok(path)) => path,
+        _ => return false,
+    };
+
+    match (path.canonicalize(), dir.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => path == dir,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_repo(path: &Path) -> Result<String> {
+        std::fs::create_dir_all(path)?;
+        git(Some(path), &["init", "--quiet"])?;
+        std::fs::write(path.join("setup.py"), "")?;
+        git(Some(path), &["add", "setup.py"])?;
+        git(
+            Some(path),
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                "initial",
+            ],
+        )?;
+
+        git(Some(path), &["rev-parse", "HEAD"])
+    }
+
+    #[test]
+    fn test_normalize_commit() -> Result<()> {
+        assert_eq!(
+            normalize_commit("0123456789ABCDEF0123456789abcdef01234567")?,
+            "0123456789abcdef0123456789abcdef01234567"
+        );
+        assert!(normalize_commit("0123456").is_err());
+        assert!(normalize_commit("main").is_err());
+        assert!(normalize_commit("z123456789abcdef0123456789abcdef01234567").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clone_commit() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let repo_path = temp_dir.path().join("repo");
+        let commit = create_repo(&repo_path)?;
+        let url = repo_path.display().to_string();
+
+        let checkout = GitCheckout::clone_commit(&url, &commit, &temp_dir.path().join("a"))?;
+        assert_eq!(checkout.commit, commit);
+        assert!(checkout.path().join("setup.py").exists());
+
+        let metadata = checkout.provenance_metadata(Some("sub"));
+        assert_eq!(metadata.get(METADATA_URL), Some(&url));
+        assert_eq!(metadata.get(METADATA_COMMIT), Some(&commit));
+        assert_eq!(
+            metadata.get(METADATA_SUBDIRECTORY),
+            Some(&"sub".to_string())
+        );
+
+        std::fs::write(checkout.path().join("setup.py"), "modified")?;
+        assert!(checkout.verify().is_err());
+
+        let missing = "0".repeat(40);
+        assert!(GitCheckout::clone_commit(&url, &missing, &temp_dir.path().join("b")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_direct_url_is_directory() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let url = url::Url::from_directory_path(temp_dir.path()).unwrap();
+
+        let data = format!("{{\"url\": \"{}\", \"dir_info\": {{}}}}", url);
+        assert!(direct_url_is_directory(data.as_bytes(), temp_dir.path()));
+        assert!(!direct_url_is_directory(
+            data.as_bytes(),
+            &temp_dir.path().join("other")
+        ));
+        assert!(!direct_url_is_directory(
+            b"{\"url\": \"https://example.com/foo.whl\"}",
+            temp_dir.path()
+        ));
+        assert!(!direct_url_is_directory(b"invalid", temp_dir.path()));
+
+        Ok(())
+    }
+}
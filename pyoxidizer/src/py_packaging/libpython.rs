@@ -164,7 +164,7 @@ fn assemble_archive_bsd(objects: &[PathBuf], temp_dir: &Path) -> Result<Vec<u8>>
 }
 
 /// Represents a built libpython.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LibpythonInfo {
     /// Raw data constituting static libpython library.
     pub libpython_data: Vec<u8>,
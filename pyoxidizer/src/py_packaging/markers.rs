@@ -0,0 +1,385 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Evaluation of PEP 508 environment markers.
+
+Lock files record which packages are only needed in some environments via
+environment markers like `sys_platform == "win32"`. We evaluate these against
+the environment of the binary being built rather than the environment running
+the build, so cross-compiled builds install what the target needs.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    std::collections::BTreeMap,
+};
+
+/// Marker variables compared as versions.
+const VERSION_VARIABLES: &[&str] = &[
+    "python_version",
+    "python_full_version",
+    "implementation_version",
+];
+
+/// Values of PEP 508 marker variables for an environment.
+#[derive(Clone, Debug)]
+pub struct MarkerEnvironment {
+    values: BTreeMap<&'static str, String>,
+}
+
+impl MarkerEnvironment {
+    /// Construct an instance describing CPython running on a Rust target triple.
+    ///
+    /// `python_version` is the full version of the Python distribution, e.g. `3.10.8`.
+    pub fn from_target(target_triple: &str, python_version: &str) -> Self {
+        let (sys_platform, platform_system, os_name) = if target_triple.contains("-windows-") {
+            ("win32", "Windows", "nt")
+        } else if target_triple.contains("-apple-") {
+            ("darwin", "Darwin", "posix")
+        } else {
+            ("linux", "Linux", "posix")
+        };
+
+        let arch = target_triple.split('-').next().unwrap_or_default();
+        let platform_machine = match (arch, sys_platform) {
+            ("x86_64", "win32") => "AMD64",
+            ("i686", "win32") => "x86",
+            ("aarch64", "win32") => "ARM64",
+            ("aarch64", "darwin") => "arm64",
+            (arch, _) => arch,
+        };
+
+        let major_minor = python_version
+            .split('.')
+            .take(2)
+            .collect::<Vec<_>>()
+            .join(".");
+
+        let mut values = BTreeMap::new();
+        values.insert("extra", "".to_string());
+        values.insert("implementation_name", "cpython".to_string());
+        values.insert("implementation_version", python_version.to_string());
+        values.insert("os_name", os_name.to_string());
+        values.insert("platform_machine", platform_machine.to_string());
+        values.insert("platform_python_implementation", "CPython".to_string());
+        values.insert("platform_release", "".to_string());
+        values.insert("platform_system", platform_system.to_string());
+        values.insert("platform_version", "".to_string());
+        values.insert("python_full_version", python_version.to_string());
+        values.insert("python_version", major_minor);
+        values.insert("sys_platform", sys_platform.to_string());
+
+        Self { values }
+    }
+
+    /// Obtain a copy of this environment for evaluating markers of an extra.
+    ///
+    /// Requirements of a package's extras have markers like `extra == "socks"`.
+    pub fn with_extra(&self, extra: &str) -> Self {
+        let mut values = self.values.clone();
+        values.insert("extra", extra.to_string());
+
+        Self { values }
+    }
+
+    /// Evaluate a marker expression.
+    pub fn evaluate(&self, marker: &str) -> Result<bool> {
+        let tokens = tokenize(marker)?;
+        let mut parser = Parser {
+            env: self,
+            tokens: &tokens,
+            position: 0,
+        };
+
+        let res = parser.parse_or()?;
+
+        if parser.position != tokens.len() {
+            return Err(anyhow!("invalid marker: {}", marker));
+        }
+
+        Ok(res)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Variable(String),
+    String(String),
+    Operator(String),
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+}
+
+fn tokenize(marker: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = marker.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::LeftParen),
+            ')' => tokens.push(Token::RightParen),
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some(other) => value.push(other),
+                        None => return Err(anyhow!("unterminated string in marker: {}", marker)),
+                    }
+                }
+                tokens.push(Token::String(value));
+            }
+            '<' | '>' | '=' | '!' | '~' => {
+                let mut op = c.to_string();
+                while let Some(next) = chars.peek().filter(|next| "<>=!~".contains(**next)) {
+                    op.push(*next);
+                    chars.next();
+                }
+                tokens.push(Token::Operator(op));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(next) = chars
+                    .peek()
+                    .filter(|next| next.is_ascii_alphanumeric() || **next == '_' || **next == '.')
+                {
+                    word.push(*next);
+                    chars.next();
+                }
+
+                tokens.push(match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "in" => Token::Operator("in".to_string()),
+                    "not" => Token::Operator("not".to_string()),
+                    _ => Token::Variable(word),
+                });
+            }
+            _ => return Err(anyhow!("invalid character in marker: {}", marker)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    env: &'a MarkerEnvironment,
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn parse_or(&mut self) -> Result<bool> {
+        let mut res = self.parse_and()?;
+
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            // Both sides are evaluated so invalid markers are rejected.
+            res = self.parse_and()? || res;
+        }
+
+        Ok(res)
+    }
+
+    fn parse_and(&mut self) -> Result<bool> {
+        let mut res = self.parse_atom()?;
+
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            res = self.parse_atom()? && res;
+        }
+
+        Ok(res)
+    }
+
+    fn parse_atom(&mut self) -> Result<bool> {
+        if self.peek() == Some(&Token::LeftParen) {
+            self.position += 1;
+            let res = self.parse_or()?;
+
+            return match self.next() {
+                Some(Token::RightParen) => Ok(res),
+                _ => Err(anyhow!("unbalanced parentheses in marker")),
+            };
+        }
+
+        let (left, left_variable) = self.parse_value()?;
+
+        let op = match self.next() {
+            Some(Token::Operator(op)) if op == "not" => match self.next() {
+                Some(Token::Operator(op)) if op == "in" => "not in".to_string(),
+                _ => return Err(anyhow!("expected `in` after `not` in marker")),
+            },
+            Some(Token::Operator(op)) => op.clone(),
+            _ => return Err(anyhow!("expected operator in marker")),
+        };
+
+        let (right, right_variable) = self.parse_value()?;
+
+        let is_version = [left_variable, right_variable]
+            .iter()
+            .flatten()
+            .any(|variable| VERSION_VARIABLES.contains(&variable.as_str()));
+
+        compare(&left, &op, &right, is_version)
+    }
+
+    /// Parse a variable or string, returning its value and the variable name.
+    fn parse_value(&mut self) -> Result<(String, Option<&'a String>)> {
+        match self.next() {
+            Some(Token::String(value)) => Ok((value.clone(), None)),
+            Some(Token::Variable(name)) => {
+                let value = self
+                    .env
+                    .values
+                    .get(name.as_str())
+                    .ok_or_else(|| anyhow!("unknown marker variable: {}", name))?;
+
+                Ok((value.clone(), Some(name)))
+            }
+            _ => Err(anyhow!("expected variable or string in marker")),
+        }
+    }
+}
+
+/// Parse the release segment of a version, e.g. `3.10.8` -> `[3, 10, 8]`.
+fn release(version: &str) -> Option<Vec<u64>> {
+    version
+        .split('.')
+        .map(|part| {
+            let digits = part
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>();
+            digits.parse::<u64>().ok()
+        })
+        .collect()
+}
+
+/// Compare 2 release segments, padding the shorter with zeros.
+fn compare_releases(a: &[u64], b: &[u64]) -> std::cmp::Ordering {
+    let length = a.len().max(b.len());
+    let pad = |v: &[u64]| {
+        let mut v = v.to_vec();
+        v.resize(length, 0);
+        v
+    };
+
+    pad(a).cmp(&pad(b))
+}
+
+fn compare(left: &str, op: &str, right: &str, is_version: bool) -> Result<bool> {
+    match op {
+        "in" => return Ok(right.contains(left)),
+        "not in" => return Ok(!right.contains(left)),
+        "===" => return Ok(left == right),
+        _ => {}
+    }
+
+    if is_version {
+        if let (Some(left_release), Some(spec)) = (release(left), right.strip_suffix(".*")) {
+            let spec = release(spec).ok_or_else(|| anyhow!("invalid version: {}", right))?;
+            let matches = left_release.len() >= spec.len() && left_release[..spec.len()] == spec;
+
+            return match op {
+                "==" => Ok(matches),
+                "!=" => Ok(!matches),
+                _ => Err(anyhow!("invalid use of wildcard version: {} {}", op, right)),
+            };
+        }
+
+        if let (Some(a), Some(b)) = (release(left), release(right)) {
+            let ordering = compare_releases(&a, &b);
+
+            return match op {
+                "==" => Ok(ordering.is_eq()),
+                "!=" => Ok(ordering.is_ne()),
+                "<" => Ok(ordering.is_lt()),
+                "<=" => Ok(ordering.is_le()),
+                ">" => Ok(ordering.is_gt()),
+                ">=" => Ok(ordering.is_ge()),
+                "~=" if b.len() >= 2 => {
+                    let prefix = &b[..b.len() - 1];
+                    Ok(ordering.is_ge() && a.len() >= prefix.len() && &a[..prefix.len()] == prefix)
+                }
+                _ => Err(anyhow!("invalid marker operator: {}", op)),
+            };
+        }
+    }
+
+    match op {
+        "==" => Ok(left == right),
+        "!=" => Ok(left != right),
+        "<" => Ok(left < right),
+        "<=" => Ok(left <= right),
+        ">" => Ok(left > right),
+        ">=" => Ok(left >= right),
+        _ => Err(anyhow!("invalid marker operator: {}", op)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_target() -> Result<()> {
+        let linux = MarkerEnvironment::from_target("x86_64-unknown-linux-gnu", "3.10.8");
+        let windows = MarkerEnvironment::from_target("x86_64-pc-windows-msvc", "3.9.15");
+        let macos = MarkerEnvironment::from_target("aarch64-apple-darwin", "3.8.15");
+
+        assert!(linux.evaluate("sys_platform == 'linux' and platform_machine == 'x86_64'")?);
+        assert!(windows.evaluate("os_name == \"nt\" and platform_machine == 'AMD64'")?);
+        assert!(macos.evaluate("platform_system == 'Darwin' and platform_machine == 'arm64'")?);
+        assert!(!macos.evaluate("sys_platform == 'win32'")?);
+
+        assert!(linux.evaluate("python_version >= '3.10'")?);
+        assert!(!windows.evaluate("python_version >= '3.10'")?);
+        assert!(linux.evaluate("python_full_version == '3.10.*'")?);
+        assert!(linux.evaluate("python_full_version ~= '3.10.2'")?);
+        assert!(!linux.evaluate("python_full_version ~= '3.9.2'")?);
+        assert!(macos.evaluate("python_version < \"3.9\"")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expressions() -> Result<()> {
+        let env = MarkerEnvironment::from_target("x86_64-unknown-linux-gnu", "3.10.8");
+
+        assert!(env.evaluate(
+            "(sys_platform == 'win32' or sys_platform == 'linux') and python_version > '3.7'"
+        )?);
+        assert!(!env.evaluate("sys_platform == 'win32' or sys_platform == 'darwin'")?);
+        assert!(env.evaluate(
+            "sys_platform == 'darwin' or python_version >= '3.8' and os_name == 'posix'"
+        )?);
+        assert!(env.evaluate("'linux' in sys_platform")?);
+        assert!(env.evaluate("sys_platform not in 'win32 cygwin'")?);
+        assert!(env.evaluate("extra != 'socks'")?);
+        assert!(env.with_extra("socks").evaluate("extra == 'socks'")?);
+        assert!(env.evaluate("'3.7' < python_version")?);
+
+        assert!(env.evaluate("unknown == 'foo'").is_err());
+        assert!(env.evaluate("sys_platform ==").is_err());
+        assert!(env.evaluate("(sys_platform == 'linux'").is_err());
+        assert!(env.evaluate("sys_platform == 'linux' foo").is_err());
+
+        Ok(())
+    }
+}
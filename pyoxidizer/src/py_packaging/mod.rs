@@ -8,14 +8,26 @@ Functionality for interacting with Python.
 This module tree holds functionality that is centered around Python.
 */
 
+pub mod artifact_cache;
 pub mod binary;
+pub mod conda;
 pub mod config;
+pub mod dependency_cache;
 pub mod distribution;
 pub mod distutils;
 pub mod embedding;
 pub mod filtering;
+pub mod git;
 pub mod libpython;
+pub mod markers;
 pub mod packaging_tool;
+pub mod pdm;
+pub mod pep440;
+pub mod pipenv;
+pub mod poetry;
+pub mod pypy_distribution;
+pub mod resolver;
 pub mod resource;
 pub mod standalone_builder;
 pub mod standalone_distribution;
+pub mod uv;
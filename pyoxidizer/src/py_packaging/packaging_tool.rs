@@ -19,14 +19,29 @@ use {
         filesystem_scanning::find_python_resources, policy::PythonPackagingPolicy,
         resource::PythonResource, wheel::WheelArchive,
     },
+    sha2::Digest,
     std::{
-        collections::{hash_map::RandomState, HashMap},
+        collections::{hash_map::RandomState, BTreeSet, HashMap},
         hash::BuildHasher,
         io::{BufRead, BufReader},
         path::{Path, PathBuf},
     },
 };
 
+/// Hash algorithms accepted by pip's `--hash` option.
+const PIP_HASH_ALGORITHMS: &[&str] = &["sha256", "sha384", "sha512"];
+
+/// pip arguments disabling PEP 517 isolated builds.
+const PIP_NO_BUILD_ISOLATION_ARGS: &[&str] = &["--no-build-isolation", "--no-use-pep517"];
+
+/// Environment variables through which pip configuration can change how
+/// source distributions are built.
+const PIP_BUILD_ISOLATION_ENV_VARS: &[&str] = &[
+    "PIP_NO_BUILD_ISOLATION",
+    "PIP_USE_PEP517",
+    "PIP_NO_USE_PEP517",
+];
+
 fn log_command_output(handle: &ReaderHandle) {
     let reader = BufReader::new(handle);
     for line in reader.lines() {
@@ -41,6 +56,220 @@ fn log_command_output(handle: &ReaderHandle) {
     }
 }
 
+/// Normalize the value of a pip `--hash` option to `<algorithm>:<digest>`.
+fn parse_pinned_hash(value: &str) -> Result<String> {
+    match value.split_once(':') {
+        Some((algorithm, digest))
+            if PIP_HASH_ALGORITHMS.contains(&algorithm) && hex::decode(digest).is_ok() =>
+        {
+            Ok(format!("{}:{}", algorithm, digest.to_lowercase()))
+        }
+        _ => Err(anyhow!("invalid --hash value: {}", value)),
+    }
+}
+
+/// Collect hashes pinned by pip arguments.
+///
+/// Hashes are pinned by `--hash` options, including in requirements files
+/// referenced by `-r` / `--requirement` options. Relative requirements file
+/// paths are resolved against `cwd`.
+fn collect_pinned_hashes(args: &[String], cwd: &Path, hashes: &mut BTreeSet<String>) -> Result<()> {
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let (option, value) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => (option, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+
+        let mut value = || {
+            value
+                .or_else(|| args.next().map(|arg| arg.as_str()))
+                .ok_or_else(|| anyhow!("{} requires a value", option))
+        };
+
+        match option {
+            "--hash" => {
+                hashes.insert(parse_pinned_hash(value()?)?);
+            }
+            "-r" | "--requirement" => {
+                read_requirements_file_hashes(&cwd.join(value()?), hashes)?;
+            }
+            _ => {
+                if let Some(path) = option.strip_prefix("-r") {
+                    read_requirements_file_hashes(&cwd.join(path), hashes)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect hashes pinned by a requirements file and the files it references.
+fn read_requirements_file_hashes(path: &Path, hashes: &mut BTreeSet<String>) -> Result<()> {
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("reading requirements file {}", path.display()))?;
+
+    let mut args = vec![];
+    for line in data.replace("\\\r\n", " ").replace("\\\n", " ").lines() {
+        args.extend(
+            line.split_whitespace()
+                .take_while(|token| !token.starts_with('#'))
+                .map(|token| token.to_string()),
+        );
+    }
+
+    collect_pinned_hashes(
+        &args,
+        path.parent().unwrap_or_else(|| Path::new("")),
+        hashes,
+    )
+    .with_context(|| format!("parsing requirements file {}", path.display()))
+}
+
+/// Resolve the hashes pinned by pip arguments.
+///
+/// Errors if no hashes are pinned.
+fn pinned_hashes(args: &[String]) -> Result<BTreeSet<String>> {
+    let mut hashes = BTreeSet::new();
+    collect_pinned_hashes(args, &std::env::current_dir()?, &mut hashes)?;
+
+    if hashes.is_empty() {
+        return Err(anyhow!(
+            "pip hashes are required but no hashes are pinned via --hash options"
+        ));
+    }
+
+    Ok(hashes)
+}
+
+/// Whether any digest of `data` is in a set of pinned hashes.
+fn is_hash_pinned(data: &[u8], pinned: &BTreeSet<String>) -> bool {
+    PIP_HASH_ALGORITHMS
+        .iter()
+        .filter(|algorithm| {
+            pinned
+                .iter()
+                .any(|hash| hash.split_once(':').map(|(a, _)| a) == Some(algorithm))
+        })
+        .any(|algorithm| {
+            let digest = match *algorithm {
+                "sha256" => hex::encode(sha2::Sha256::digest(data)),
+                "sha384" => hex::encode(sha2::Sha384::digest(data)),
+                _ => hex::encode(sha2::Sha512::digest(data)),
+            };
+
+            pinned.contains(&format!("{}:{}", algorithm, digest))
+        })
+}
+
+/// Verify that every artifact recorded in a `pip install --report` file is hash-pinned.
+fn verify_pip_report_hashes(report: &serde_json::Value, pinned: &BTreeSet<String>) -> Result<()> {
+    let installs = report
+        .get("install")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("pip installation report does not list installs"))?;
+
+    for install in installs {
+        let metadata = &install["metadata"];
+        let name = format!(
+            "{} {}",
+            metadata["name"].as_str().unwrap_or("<unknown>"),
+            metadata["version"].as_str().unwrap_or("<unknown>")
+        );
+
+        let archive_info = install["download_info"]
+            .get("archive_info")
+            .ok_or_else(|| anyhow!("{} was not installed from a hash-pinned archive", name))?;
+
+        let mut hashes = vec![];
+        if let Some(hash) = archive_info.get("hash").and_then(|v| v.as_str()) {
+            hashes.push(hash.replacen('=', ":", 1));
+        }
+        if let Some(map) = archive_info.get("hashes").and_then(|v| v.as_object()) {
+            for (algorithm, digest) in map {
+                if let Some(digest) = digest.as_str() {
+                    hashes.push(format!("{}:{}", algorithm, digest));
+                }
+            }
+        }
+
+        if !hashes
+            .iter()
+            .any(|hash| pinned.contains(&hash.to_lowercase()))
+        {
+            return Err(anyhow!("hash of installed artifact {} is not pinned", name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve pip arguments configuring how pip accesses the network.
+///
+/// Arguments appearing later in a pip command line take precedence. So
+/// arguments for a single operation can follow these to override them.
+pub fn pip_network_args(
+    index_url: Option<&str>,
+    extra_index_urls: &[String],
+    trusted_hosts: &[String],
+    proxy: Option<&str>,
+    retries: Option<u32>,
+) -> Vec<String> {
+    let mut res = vec![];
+
+    if let Some(url) = index_url {
+        res.push(format!("--index-url={}", url));
+    }
+    for url in extra_index_urls {
+        res.push(format!("--extra-index-url={}", url));
+    }
+    for host in trusted_hosts {
+        res.push(format!("--trusted-host={}", host));
+    }
+    if let Some(proxy) = proxy {
+        res.push(format!("--proxy={}", proxy));
+    }
+    if let Some(retries) = retries {
+        res.push(format!("--retries={}", retries));
+    }
+
+    res
+}
+
+/// Resolve pip network arguments from the settings of a packaging policy.
+fn policy_pip_network_args(policy: &PythonPackagingPolicy) -> Vec<String> {
+    pip_network_args(
+        policy.pip_index_url(),
+        policy.pip_extra_index_urls(),
+        policy.pip_trusted_hosts(),
+        policy.pip_proxy(),
+        policy.pip_retries(),
+    )
+}
+
+/// Resolve the pip arguments building source distributions in PEP 517 isolated environments.
+///
+/// `--use-pep517` makes pip build every source distribution with its declared
+/// build backend, falling back to the `setuptools` backend for projects
+/// without a `pyproject.toml`, instead of running `setup.py` directly. The
+/// build backend and build requirements are installed into an isolated
+/// environment of the Python distribution running pip.
+fn pip_build_isolation_args(args: &[String]) -> Result<Vec<String>> {
+    if let Some(arg) = args
+        .iter()
+        .find(|arg| PIP_NO_BUILD_ISOLATION_ARGS.contains(&arg.as_str()))
+    {
+        return Err(anyhow!(
+            "{} conflicts with the pip_build_isolation packaging policy setting",
+            arg
+        ));
+    }
+
+    Ok(vec!["--use-pep517".to_string()])
+}
+
 /// Find resources installed as part of a packaging operation.
 pub fn find_resources<'a>(
     dist: &dyn PythonDistribution,
@@ -86,32 +315,24 @@ pub fn find_resources<'a>(
     Ok(res)
 }
 
-/// Run `pip download` and collect resources found from downloaded packages.
+/// Run `pip download` to download packages into a directory.
 ///
 /// `host_dist` is the Python distribution to use to run `pip`.
 ///
-/// `build_dist` is the Python distribution that packages are being downloaded
-/// for.
-///
-/// The distributions are often the same. But passing a different
-/// distribution targeting a different platform allows this command to
-/// resolve resources for a non-native platform, which enables it to be used
-/// when cross-compiling.
-pub fn pip_download<'a>(
-    env: &Environment,
+/// If `target_dist` is defined, only wheels compatible with that distribution
+/// are downloaded. Otherwise pip downloads what it would install into
+/// `host_dist`, which can include source distributions.
+pub fn pip_download_files(
     host_dist: &dyn PythonDistribution,
-    taget_dist: &dyn PythonDistribution,
+    target_dist: Option<&dyn PythonDistribution>,
     policy: &PythonPackagingPolicy,
     verbose: bool,
     args: &[String],
-) -> Result<Vec<PythonResource<'a>>> {
-    let temp_dir = env.temporary_directory("pyoxidizer-pip-download")?;
-
+    dest_dir: &Path,
+) -> Result<()> {
     host_dist.ensure_pip()?;
 
-    let target_dir = temp_dir.path();
-
-    warn!("pip downloading to {}", target_dir.display());
+    warn!("pip downloading to {}", dest_dir.display());
 
     let mut pip_args = vec![
         "-m".to_string(),
@@ -125,27 +346,36 @@ pub fn pip_download<'a>(
 
     pip_args.extend(vec![
         "download".to_string(),
-        // Download packages to our temporary directory.
         "--dest".to_string(),
-        format!("{}", target_dir.display()),
-        // Only download wheels.
-        "--only-binary=:all:".to_string(),
-        // We download files compatible with the distribution we're targeting.
-        format!(
-            "--platform={}",
-            taget_dist.python_platform_compatibility_tag()
-        ),
-        format!("--python-version={}", taget_dist.python_version()),
-        format!(
-            "--implementation={}",
-            taget_dist.python_implementation_short()
-        ),
+        format!("{}", dest_dir.display()),
     ]);
 
-    if let Some(abi) = taget_dist.python_abi_tag() {
-        pip_args.push(format!("--abi={}", abi));
+    if let Some(target_dist) = target_dist {
+        pip_args.extend(vec![
+            // Only download wheels.
+            "--only-binary=:all:".to_string(),
+            // We download files compatible with the distribution we're targeting.
+            format!(
+                "--platform={}",
+                target_dist.python_platform_compatibility_tag()
+            ),
+            format!("--python-version={}", target_dist.python_version()),
+            format!(
+                "--implementation={}",
+                target_dist.python_implementation_short()
+            ),
+        ]);
+
+        if let Some(abi) = target_dist.python_abi_tag() {
+            pip_args.push(format!("--abi={}", abi));
+        }
     }
 
+    if policy.pip_require_hashes() {
+        pip_args.push("--require-hashes".to_string());
+    }
+
+    pip_args.extend(policy_pip_network_args(policy));
     pip_args.extend(args.iter().cloned());
 
     warn!("running python {:?}", pip_args);
@@ -164,6 +394,41 @@ pub fn pip_download<'a>(
         return Err(anyhow!("error running pip"));
     }
 
+    Ok(())
+}
+
+/// Run `pip download` and collect resources found from downloaded packages.
+///
+/// `host_dist` is the Python distribution to use to run `pip`.
+///
+/// `build_dist` is the Python distribution that packages are being downloaded
+/// for.
+///
+/// The distributions are often the same. But passing a different
+/// distribution targeting a different platform allows this command to
+/// resolve resources for a non-native platform, which enables it to be used
+/// when cross-compiling.
+pub fn pip_download<'a>(
+    env: &Environment,
+    host_dist: &dyn PythonDistribution,
+    taget_dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    verbose: bool,
+    args: &[String],
+) -> Result<Vec<PythonResource<'a>>> {
+    let temp_dir = env.temporary_directory("pyoxidizer-pip-download")?;
+
+    let target_dir = temp_dir.path();
+
+    pip_download_files(
+        host_dist,
+        Some(taget_dist),
+        policy,
+        verbose,
+        args,
+        target_dir,
+    )?;
+
     // Since we used --only-binary=:all: above, we should only have .whl files
     // in the destination directory. Iterate over them and collect resources
     // from each.
@@ -173,6 +438,19 @@ pub fn pip_download<'a>(
         .collect::<Result<Vec<_>>>()?;
     files.sort();
 
+    if policy.pip_require_hashes() {
+        let pinned = pinned_hashes(args)?;
+
+        for path in &files {
+            if !is_hash_pinned(&std::fs::read(path)?, &pinned) {
+                return Err(anyhow!(
+                    "hash of downloaded artifact {} is not pinned",
+                    path.display()
+                ));
+            }
+        }
+    }
+
     // TODO there's probably a way to do this using iterators.
     let mut res = Vec::new();
 
@@ -192,22 +470,26 @@ pub fn pip_download<'a>(
     Ok(res)
 }
 
-/// Run `pip install` and return found resources.
-pub fn pip_install<'a, S: BuildHasher>(
-    env: &Environment,
+/// Run `pip install --target` to install packages into a directory.
+///
+/// `work_dir` holds state used by the installation, such as a modified
+/// `distutils`. The returned path is the directory holding state recorded by
+/// that `distutils`, if any.
+#[allow(clippy::too_many_arguments)]
+pub fn pip_install_into_directory<S: BuildHasher>(
     dist: &dyn PythonDistribution,
     policy: &PythonPackagingPolicy,
     libpython_link_mode: LibpythonLinkMode,
     verbose: bool,
     install_args: &[String],
     extra_envs: &HashMap<String, String, S>,
-) -> Result<Vec<PythonResource<'a>>> {
-    let temp_dir = env.temporary_directory("pyoxidizer-pip-install")?;
-
+    work_dir: &Path,
+    target_dir: &Path,
+) -> Result<Option<PathBuf>> {
     dist.ensure_pip()?;
 
     let mut env: HashMap<String, String, RandomState> = std::env::vars().collect();
-    for (k, v) in dist.resolve_distutils(libpython_link_mode, temp_dir.path(), &[])? {
+    for (k, v) in dist.resolve_distutils(libpython_link_mode, work_dir, &[])? {
         env.insert(k, v);
     }
 
@@ -215,7 +497,12 @@ pub fn pip_install<'a, S: BuildHasher>(
         env.insert(key.clone(), value.clone());
     }
 
-    let target_dir = temp_dir.path().join("install");
+    // Builds shouldn't depend on pip configuration of the environment.
+    if policy.pip_build_isolation() {
+        for key in PIP_BUILD_ISOLATION_ENV_VARS {
+            env.remove(*key);
+        }
+    }
 
     warn!("pip installing to {}", target_dir.display());
 
@@ -235,6 +522,22 @@ pub fn pip_install<'a, S: BuildHasher>(
         format!("{}", target_dir.display()),
     ]);
 
+    // The installation report records the hashes of installed artifacts. It
+    // requires pip 22.2.
+    let report_path = work_dir.join("pip-report.json");
+    if policy.pip_require_hashes() {
+        pip_args.extend(vec![
+            "--require-hashes".to_string(),
+            "--report".to_string(),
+            format!("{}", report_path.display()),
+        ]);
+    }
+
+    if policy.pip_build_isolation() {
+        pip_args.extend(pip_build_isolation_args(install_args)?);
+    }
+
+    pip_args.extend(policy_pip_network_args(policy));
     pip_args.extend(install_args.iter().cloned());
 
     let command = cmd(dist.python_exe_path(), &pip_args)
@@ -252,7 +555,42 @@ pub fn pip_install<'a, S: BuildHasher>(
         return Err(anyhow!("error running pip"));
     }
 
-    let state_dir = env.get("PYOXIDIZER_DISTUTILS_STATE_DIR").map(PathBuf::from);
+    if policy.pip_require_hashes() {
+        let report = serde_json::from_slice(
+            &std::fs::read(&report_path).context("reading pip installation report")?,
+        )
+        .context("parsing pip installation report")?;
+
+        verify_pip_report_hashes(&report, &pinned_hashes(install_args)?)?;
+    }
+
+    Ok(env.get("PYOXIDIZER_DISTUTILS_STATE_DIR").map(PathBuf::from))
+}
+
+/// Run `pip install` and return found resources.
+pub fn pip_install<'a, S: BuildHasher>(
+    env: &Environment,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    libpython_link_mode: LibpythonLinkMode,
+    verbose: bool,
+    install_args: &[String],
+    extra_envs: &HashMap<String, String, S>,
+) -> Result<Vec<PythonResource<'a>>> {
+    let temp_dir = env.temporary_directory("pyoxidizer-pip-install")?;
+
+    let target_dir = temp_dir.path().join("install");
+
+    let state_dir = pip_install_into_directory(
+        dist,
+        policy,
+        libpython_link_mode,
+        verbose,
+        install_args,
+        extra_envs,
+        temp_dir.path(),
+        &target_dir,
+    )?;
 
     let resources =
         find_resources(dist, policy, &target_dir, state_dir).context("scanning for resources")?;
@@ -577,4 +915,148 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_collect_pinned_hashes() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let nested = temp_dir.path().join("nested");
+        std::fs::create_dir(&nested)?;
+
+        std::fs::write(
+            temp_dir.path().join("requirements.txt"),
+            "# Comment --hash=sha256:00\n\
+             foo==1.0 \\\n    --hash=sha256:AABB \\\n    --hash=sha512:ccdd\n\
+             -r nested/other.txt\n",
+        )?;
+        std::fs::write(
+            nested.join("other.txt"),
+            "bar==2.0 --hash=sha384:eeff # --hash=sha256:11\n",
+        )?;
+
+        let mut hashes = BTreeSet::new();
+        collect_pinned_hashes(
+            &[
+                "--require-hashes".to_string(),
+                "-r".to_string(),
+                "requirements.txt".to_string(),
+                "baz==3.0".to_string(),
+                "--hash".to_string(),
+                "sha256:0102".to_string(),
+            ],
+            temp_dir.path(),
+            &mut hashes,
+        )?;
+
+        assert_eq!(
+            hashes.into_iter().collect::<Vec<_>>(),
+            vec!["sha256:0102", "sha256:aabb", "sha384:eeff", "sha512:ccdd",]
+        );
+
+        let mut hashes = BTreeSet::new();
+        assert!(collect_pinned_hashes(
+            &["--hash=md5:0102".to_string()],
+            temp_dir.path(),
+            &mut hashes
+        )
+        .is_err());
+        assert!(collect_pinned_hashes(
+            &["--requirement=missing.txt".to_string()],
+            temp_dir.path(),
+            &mut hashes
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_build_isolation_args() -> Result<()> {
+        assert_eq!(
+            pip_build_isolation_args(&["foo==1.0".to_string()])?,
+            vec!["--use-pep517".to_string()]
+        );
+        assert!(
+            pip_build_isolation_args(&["--no-build-isolation".to_string(), "foo".to_string()])
+                .is_err()
+        );
+        assert!(pip_build_isolation_args(&["--no-use-pep517".to_string()]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_network_args() {
+        assert!(pip_network_args(None, &[], &[], None, None).is_empty());
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_pip_index_url(Some("https://mirror.example.com/simple".to_string()));
+        policy.set_pip_extra_index_urls(vec!["https://extra.example.com/simple".to_string()]);
+        policy.set_pip_trusted_hosts(vec!["mirror.example.com".to_string()]);
+        policy.set_pip_proxy(Some("proxy.example.com:3128".to_string()));
+        policy.set_pip_retries(Some(10));
+
+        assert_eq!(
+            policy_pip_network_args(&policy),
+            vec![
+                "--index-url=https://mirror.example.com/simple",
+                "--extra-index-url=https://extra.example.com/simple",
+                "--trusted-host=mirror.example.com",
+                "--proxy=proxy.example.com:3128",
+                "--retries=10",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_hashes() -> Result<()> {
+        let pinned = [
+            "sha256:2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae".to_string(),
+        ]
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+
+        assert!(is_hash_pinned(b"foo", &pinned));
+        assert!(!is_hash_pinned(b"bar", &pinned));
+
+        let report = serde_json::json!({
+            "install": [
+                {
+                    "metadata": {"name": "foo", "version": "1.0"},
+                    "download_info": {
+                        "url": "https://example.com/foo-1.0.tar.gz",
+                        "archive_info": {"hash": "sha256=2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae"},
+                    },
+                },
+            ],
+        });
+        verify_pip_report_hashes(&report, &pinned)?;
+
+        let report = serde_json::json!({
+            "install": [
+                {
+                    "metadata": {"name": "foo", "version": "1.0"},
+                    "download_info": {
+                        "url": "https://example.com/foo-1.0.tar.gz",
+                        "archive_info": {"hashes": {"sha256": "00"}},
+                    },
+                },
+            ],
+        });
+        assert!(verify_pip_report_hashes(&report, &pinned).is_err());
+
+        let report = serde_json::json!({
+            "install": [
+                {
+                    "metadata": {"name": "foo", "version": "1.0"},
+                    "download_info": {
+                        "url": "file:///foo",
+                        "dir_info": {},
+                    },
+                },
+            ],
+        });
+        assert!(verify_pip_report_hashes(&report, &pinned).is_err());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,257 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Interaction with PDM lock files.
+
+A `pdm.lock` file pins the exact version and file hashes of every package of
+a project, along with the dependency groups each package belongs to and the
+environment markers it is needed in. We convert the packages of a lock file
+to pip requirements for a target environment so they can be installed as is.
+*/
+
+use {
+    super::markers::MarkerEnvironment,
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    std::collections::{BTreeMap, BTreeSet},
+};
+
+#[derive(Deserialize)]
+struct PdmLock {
+    #[serde(default)]
+    package: Vec<PdmPackage>,
+    #[serde(default)]
+    metadata: PdmMetadata,
+}
+
+#[derive(Default, Deserialize)]
+struct PdmMetadata {
+    /// Files of packages, keyed by `<name> <version>`.
+    ///
+    /// Lock files written by PDM before lock format 4.1 store files here
+    /// instead of in each package.
+    #[serde(default)]
+    files: BTreeMap<String, Vec<PdmFile>>,
+}
+
+#[derive(Deserialize)]
+struct PdmPackage {
+    name: String,
+    version: Option<String>,
+    /// Dependency groups the package belongs to.
+    ///
+    /// Lock files written by PDM before lock format 4.4 don't record groups.
+    groups: Option<Vec<String>>,
+    marker: Option<String>,
+    #[serde(default)]
+    files: Vec<PdmFile>,
+    git: Option<String>,
+    path: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PdmFile {
+    hash: String,
+}
+
+/// Produce pip requirements for the packages of a PDM lock file.
+///
+/// Only packages belonging to at least one of `groups` and needed in the
+/// environment `env` are included. If the lock file doesn't record groups,
+/// packages of all locked groups are included. Every requirement pins the
+/// exact version of the package and the hashes of its files. So the
+/// requirements should be installed with `--no-deps`.
+///
+/// Packages from a VCS, path, or URL are rejected because their content
+/// can't be pinned by a hash.
+pub fn pdm_lock_requirements(
+    data: &str,
+    groups: &[String],
+    env: &MarkerEnvironment,
+) -> Result<String> {
+    let lock: PdmLock = toml::from_str(data).context("parsing pdm lock file")?;
+
+    let mut requirements = BTreeSet::new();
+
+    for package in &lock.package {
+        if let Some(package_groups) = &package.groups {
+            if !package_groups.iter().any(|group| groups.contains(group)) {
+                continue;
+            }
+        }
+
+        if let Some(marker) = &package.marker {
+            if !env
+                .evaluate(marker)
+                .with_context(|| format!("evaluating marker of package {}", package.name))?
+            {
+                continue;
+            }
+        }
+
+        if package.git.is_some() || package.path.is_some() || package.url.is_some() {
+            return Err(anyhow!(
+                "package {} is not from a package index; only packages from an index are supported",
+                package.name
+            ));
+        }
+
+        let version = package
+            .version
+            .as_ref()
+            .ok_or_else(|| anyhow!("package {} has no version", package.name))?;
+
+        let files = if package.files.is_empty() {
+            lock.metadata
+                .files
+                .get(&format!("{} {}", package.name, version))
+                .map(|files| files.as_slice())
+                .unwrap_or_default()
+        } else {
+            package.files.as_slice()
+        };
+
+        if files.is_empty() {
+            return Err(anyhow!(
+                "package {} has no pinned file hashes",
+                package.name
+            ));
+        }
+
+        // Packages with extras are recorded as additional entries having the
+        // same files. The dependencies of extras are recorded as packages.
+        let mut requirement = format!("{}=={}", package.name, version);
+        for file in files {
+            requirement.push_str(&format!(" --hash={}", file.hash));
+        }
+
+        requirements.insert(requirement);
+    }
+
+    let mut res = String::new();
+    for requirement in requirements {
+        res.push_str(&requirement);
+        res.push('\n');
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirements() -> Result<()> {
+        let data = r#"
+[metadata]
+groups = ["default", "test"]
+strategy = ["cross_platform", "inherit_metadata"]
+lock_version = "4.4.1"
+content_hash = "sha256:00"
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+requires_python = ">=2.7"
+summary = "Cross-platform colored terminal text."
+groups = ["default", "test"]
+marker = "sys_platform == \"win32\""
+files = [
+    {file = "colorama-0.4.6-py2.py3-none-any.whl", hash = "sha256:aa"},
+]
+
+[[package]]
+name = "requests"
+version = "2.31.0"
+requires_python = ">=3.7"
+groups = ["default"]
+dependencies = ["certifi>=2017.4.17"]
+files = [
+    {file = "requests-2.31.0-py3-none-any.whl", hash = "sha256:bb"},
+    {file = "requests-2.31.0.tar.gz", hash = "sha256:cc"},
+]
+
+[[package]]
+name = "requests"
+version = "2.31.0"
+extras = ["socks"]
+requires_python = ">=3.7"
+groups = ["default"]
+dependencies = ["PySocks!=1.5.7,>=1.5.6", "requests==2.31.0"]
+files = [
+    {file = "requests-2.31.0-py3-none-any.whl", hash = "sha256:bb"},
+    {file = "requests-2.31.0.tar.gz", hash = "sha256:cc"},
+]
+
+[[package]]
+name = "pytest"
+version = "7.4.3"
+requires_python = ">=3.7"
+groups = ["test"]
+files = [
+    {file = "pytest-7.4.3-py3-none-any.whl", hash = "sha256:dd"},
+]
+"#;
+
+        let linux = MarkerEnvironment::from_target("x86_64-unknown-linux-gnu", "3.10.8");
+        let windows = MarkerEnvironment::from_target("x86_64-pc-windows-msvc", "3.10.8");
+
+        assert_eq!(
+            pdm_lock_requirements(data, &["default".to_string()], &linux)?,
+            "requests==2.31.0 --hash=sha256:bb --hash=sha256:cc\n"
+        );
+        assert_eq!(
+            pdm_lock_requirements(data, &["default".to_string(), "test".to_string()], &windows)?,
+            "colorama==0.4.6 --hash=sha256:aa\n\
+             pytest==7.4.3 --hash=sha256:dd\n\
+             requests==2.31.0 --hash=sha256:bb --hash=sha256:cc\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_lock() -> Result<()> {
+        let data = r#"
+[[package]]
+name = "requests"
+version = "2.28.1"
+requires_python = ">=3.7, <4"
+
+[metadata]
+lock_version = "4.0"
+content_hash = "sha256:00"
+
+[metadata.files]
+"requests 2.28.1" = [
+    {url = "https://files.example.com/requests-2.28.1-py3-none-any.whl", hash = "sha256:aa"},
+]
+"#;
+
+        let env = MarkerEnvironment::from_target("x86_64-unknown-linux-gnu", "3.10.8");
+        assert_eq!(
+            pdm_lock_requirements(data, &["default".to_string()], &env)?,
+            "requests==2.28.1 --hash=sha256:aa\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_source() {
+        let data = r#"
+[[package]]
+name = "foo"
+version = "1.0"
+git = "https://github.com/example/foo.git"
+revision = "0123456789"
+"#;
+
+        let env = MarkerEnvironment::from_target("x86_64-unknown-linux-gnu", "3.10.8");
+        assert!(pdm_lock_requirements(data, &["default".to_string()], &env).is_err());
+    }
+}
@@ -0,0 +1,640 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+PEP 440 versions and version specifiers.
+
+Resolving dependencies requires comparing versions of packages and matching
+them against the version specifiers of requirements, e.g. `>=2.0,<3`. This
+module implements the ordering and matching rules of PEP 440.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    std::{cmp::Ordering, fmt::Display, str::FromStr},
+};
+
+/// A segment of the local version label of a version.
+///
+/// Numeric segments sort after alphanumeric segments.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum LocalSegment {
+    String(String),
+    Number(u64),
+}
+
+/// Phase of a pre-release.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum PreReleasePhase {
+    Alpha,
+    Beta,
+    ReleaseCandidate,
+}
+
+/// A PEP 440 version.
+#[derive(Clone, Debug)]
+pub struct Version {
+    epoch: u64,
+    release: Vec<u64>,
+    pre: Option<(PreReleasePhase, u64)>,
+    post: Option<u64>,
+    dev: Option<u64>,
+    local: Vec<LocalSegment>,
+}
+
+/// Parses versions in the normalized and alternative forms PEP 440 allows.
+struct VersionParser<'a> {
+    value: &'a str,
+    position: usize,
+}
+
+impl<'a> VersionParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.value[self.position..]
+    }
+
+    fn number(&mut self) -> Option<u64> {
+        let digits = self
+            .rest()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+
+        if digits == 0 {
+            return None;
+        }
+
+        let value = self.rest()[..digits].parse().ok()?;
+        self.position += digits;
+
+        Some(value)
+    }
+
+    /// Consume an optional `.`, `-`, or `_` separator.
+    fn separator(&mut self) -> bool {
+        if self.rest().starts_with(['.', '-', '_']) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consume one of `words` optionally preceded by a separator.
+    fn word(&mut self, words: &[&'a str]) -> Option<&'a str> {
+        let start = self.position;
+        self.separator();
+
+        // Longer words are listed first, so `preview` isn't parsed as `pre`.
+        for word in words {
+            if self.rest().starts_with(word) {
+                self.position += word.len();
+                return Some(word);
+            }
+        }
+
+        self.position = start;
+        None
+    }
+
+    /// Consume a number optionally preceded by a separator.
+    fn separated_number(&mut self) -> Option<u64> {
+        let start = self.position;
+        self.separator();
+
+        match self.number() {
+            Some(value) => Some(value),
+            None => {
+                self.position = start;
+                None
+            }
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let normalized = s.trim().to_lowercase();
+        let value = normalized.strip_prefix('v').unwrap_or(&normalized);
+        let invalid = || anyhow!("invalid version: {}", s);
+
+        let mut parser = VersionParser { value, position: 0 };
+
+        let mut epoch = 0;
+        let mut release = vec![parser.number().ok_or_else(invalid)?];
+
+        if parser.rest().starts_with('!') {
+            parser.position += 1;
+            epoch = release[0];
+            release = vec![parser.number().ok_or_else(invalid)?];
+        }
+
+        while parser.rest().starts_with('.')
+            && parser.rest()[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            parser.position += 1;
+            release.push(parser.number().ok_or_else(invalid)?);
+        }
+
+        let pre = parser
+            .word(&["alpha", "a", "beta", "b", "preview", "pre", "rc", "c"])
+            .map(|word| {
+                let phase = match word {
+                    "alpha" | "a" => PreReleasePhase::Alpha,
+                    "beta" | "b" => PreReleasePhase::Beta,
+                    _ => PreReleasePhase::ReleaseCandidate,
+                };
+
+                (phase, parser.separated_number().unwrap_or(0))
+            });
+
+        let post = if parser.word(&["post", "rev", "r"]).is_some() {
+            Some(parser.separated_number().unwrap_or(0))
+        } else if parser.rest().starts_with('-')
+            && parser.rest()[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            parser.position += 1;
+            parser.number()
+        } else {
+            None
+        };
+
+        let dev = parser
+            .word(&["dev"])
+            .map(|_| parser.separated_number().unwrap_or(0));
+
+        let local = if let Some(label) = parser.rest().strip_prefix('+') {
+            parser.position = value.len();
+
+            label
+                .split(['.', '-', '_'])
+                .map(|segment| {
+                    if segment.is_empty() || !segment.chars().all(|c| c.is_ascii_alphanumeric()) {
+                        Err(invalid())
+                    } else if let Ok(number) = segment.parse() {
+                        Ok(LocalSegment::Number(number))
+                    } else {
+                        Ok(LocalSegment::String(segment.to_string()))
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            vec![]
+        };
+
+        if !parser.rest().is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            epoch,
+            release,
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+
+        let release = self
+            .release
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", release.join("."))?;
+
+        if let Some((phase, number)) = &self.pre {
+            let phase = match phase {
+                PreReleasePhase::Alpha => "a",
+                PreReleasePhase::Beta => "b",
+                PreReleasePhase::ReleaseCandidate => "rc",
+            };
+            write!(f, "{}{}", phase, number)?;
+        }
+        if let Some(post) = self.post {
+            write!(f, ".post{}", post)?;
+        }
+        if let Some(dev) = self.dev {
+            write!(f, ".dev{}", dev)?;
+        }
+
+        if !self.local.is_empty() {
+            let local = self
+                .local
+                .iter()
+                .map(|segment| match segment {
+                    LocalSegment::String(s) => s.clone(),
+                    LocalSegment::Number(n) => n.to_string(),
+                })
+                .collect::<Vec<_>>();
+            write!(f, "+{}", local.join("."))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Version {
+    /// Whether this is a pre-release or development release.
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    /// Whether this is a post-release.
+    pub fn is_postrelease(&self) -> bool {
+        self.post.is_some()
+    }
+
+    /// Obtain the version without its local version label.
+    pub fn public(&self) -> Self {
+        Self {
+            local: vec![],
+            ..self.clone()
+        }
+    }
+
+    /// Obtain the version having only the epoch and release segment.
+    fn base(&self) -> Self {
+        Self {
+            epoch: self.epoch,
+            release: self.release.clone(),
+            pre: None,
+            post: None,
+            dev: None,
+            local: vec![],
+        }
+    }
+
+    /// Key sorting versions as PEP 440 specifies.
+    #[allow(clippy::type_complexity)]
+    fn key(
+        &self,
+    ) -> (
+        u64,
+        Vec<u64>,
+        (u8, u64),
+        Option<u64>,
+        (bool, u64),
+        &[LocalSegment],
+    ) {
+        let mut release = self.release.clone();
+        while release.len() > 1 && release.last() == Some(&0) {
+            release.pop();
+        }
+
+        // Development releases without a pre or post segment sort before
+        // pre-releases. Final releases sort after them.
+        let pre = match (&self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => (0, 0),
+            (Some((phase, number)), _, _) => (1 + *phase as u8, *number),
+            (None, _, _) => (u8::MAX, 0),
+        };
+
+        let dev = match self.dev {
+            Some(dev) => (false, dev),
+            None => (true, 0),
+        };
+
+        (self.epoch, release, pre, self.post, dev, &self.local)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Operator {
+    Compatible,
+    Equal,
+    EqualPrefix,
+    NotEqual,
+    NotEqualPrefix,
+    LessThanEqual,
+    GreaterThanEqual,
+    LessThan,
+    GreaterThan,
+    Arbitrary,
+}
+
+/// A single version specifier, e.g. `>=1.0`.
+#[derive(Clone, Debug)]
+pub struct Specifier {
+    operator: Operator,
+    version: Version,
+    /// The version string as specified, for `===` comparisons.
+    value: String,
+}
+
+impl FromStr for Specifier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+
+        let (operator, value) = ["===", "~=", "==", "!=", "<=", ">=", "<", ">"]
+            .iter()
+            .find_map(|op| s.strip_prefix(op).map(|value| (*op, value.trim())))
+            .ok_or_else(|| anyhow!("invalid version specifier: {}", s))?;
+
+        let (operator, version_value) = match (operator, value.strip_suffix(".*")) {
+            ("==", Some(prefix)) => (Operator::EqualPrefix, prefix),
+            ("!=", Some(prefix)) => (Operator::NotEqualPrefix, prefix),
+            (_, Some(_)) => return Err(anyhow!("invalid use of wildcard in specifier: {}", s)),
+            ("===", None) => (Operator::Arbitrary, value),
+            ("~=", None) => (Operator::Compatible, value),
+            ("==", None) => (Operator::Equal, value),
+            ("!=", None) => (Operator::NotEqual, value),
+            ("<=", None) => (Operator::LessThanEqual, value),
+            (">=", None) => (Operator::GreaterThanEqual, value),
+            ("<", None) => (Operator::LessThan, value),
+            (">", None) => (Operator::GreaterThan, value),
+            _ => unreachable!(),
+        };
+
+        // Arbitrary equality compares strings, so the value needn't be a
+        // valid version.
+        let version = match version_value.parse::<Version>() {
+            Ok(version) => version,
+            Err(_) if operator == Operator::Arbitrary => Version::from_str("0")?,
+            Err(e) => return Err(e),
+        };
+
+        if operator == Operator::Compatible && version.release.len() < 2 {
+            return Err(anyhow!(
+                "~= specifier requires at least 2 release segments: {}",
+                s
+            ));
+        }
+
+        Ok(Self {
+            operator,
+            version,
+            value: value.to_string(),
+        })
+    }
+}
+
+/// Whether the release segment of `version` starts with `prefix`.
+///
+/// The release segment is padded with zeros, so `1` has prefix `1.0`.
+fn release_has_prefix(version: &Version, prefix: &Version) -> bool {
+    if version.epoch != prefix.epoch {
+        return false;
+    }
+
+    prefix
+        .release
+        .iter()
+        .enumerate()
+        .all(|(i, segment)| version.release.get(i).unwrap_or(&0) == segment)
+}
+
+impl Specifier {
+    /// Whether a version satisfies the specifier.
+    pub fn contains(&self, version: &Version) -> bool {
+        let spec = &self.version;
+
+        match self.operator {
+            Operator::Arbitrary => version.to_string() == self.value.to_lowercase(),
+            Operator::Equal => {
+                if spec.local.is_empty() {
+                    version.public() == *spec
+                } else {
+                    version == spec
+                }
+            }
+            Operator::NotEqual => !Self {
+                operator: Operator::Equal,
+                ..self.clone()
+            }
+            .contains(version),
+            // Prefix matching also matches pre and post releases of the prefix.
+            Operator::EqualPrefix => release_has_prefix(version, spec),
+            Operator::NotEqualPrefix => !release_has_prefix(version, spec),
+            Operator::Compatible => {
+                let prefix = Version {
+                    release: spec.release[..spec.release.len() - 1].to_vec(),
+                    ..spec.base()
+                };
+
+                version.public() >= *spec && release_has_prefix(version, &prefix)
+            }
+            Operator::LessThanEqual => version.public() <= *spec,
+            Operator::GreaterThanEqual => version.public() >= *spec,
+            // `<V` excludes pre-releases of V unless V is a pre-release.
+            Operator::LessThan => {
+                version.public() < *spec
+                    && (spec.is_prerelease()
+                        || !version.is_prerelease()
+                        || version.base() != spec.base())
+            }
+            // `>V` excludes post-releases of V unless V is a post-release.
+            Operator::GreaterThan => {
+                version.public() > *spec
+                    && (spec.is_postrelease()
+                        || !version.is_postrelease()
+                        || version.base() != spec.base())
+            }
+        }
+    }
+}
+
+/// A set of version specifiers, e.g. `>=1.0,<2`.
+#[derive(Clone, Debug, Default)]
+pub struct Specifiers(Vec<Specifier>);
+
+impl FromStr for Specifiers {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self(
+            s.split(',')
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(Specifier::from_str)
+                .collect::<Result<Vec<_>>>()?,
+        ))
+    }
+}
+
+impl Specifiers {
+    /// Whether a version satisfies all specifiers.
+    pub fn contains(&self, version: &Version) -> bool {
+        self.0.iter().all(|spec| spec.contains(version))
+    }
+
+    /// Whether the specifiers explicitly accept pre-releases.
+    ///
+    /// Pre-releases are only eligible if a specifier mentions one, e.g.
+    /// `>=2.0b1`.
+    pub fn allows_prereleases(&self) -> bool {
+        self.0.iter().any(|spec| spec.version.is_prerelease())
+    }
+
+    /// Add the specifiers of another set to this one.
+    pub fn extend(&mut self, other: &Self) {
+        self.0.extend(other.0.iter().cloned());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Display for Specifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let specs = self
+            .0
+            .iter()
+            .map(|spec| {
+                let op = match spec.operator {
+                    Operator::Compatible => "~=",
+                    Operator::Equal | Operator::EqualPrefix => "==",
+                    Operator::NotEqual | Operator::NotEqualPrefix => "!=",
+                    Operator::LessThanEqual => "<=",
+                    Operator::GreaterThanEqual => ">=",
+                    Operator::LessThan => "<",
+                    Operator::GreaterThan => ">",
+                    Operator::Arbitrary => "===",
+                };
+                let suffix = match spec.operator {
+                    Operator::EqualPrefix | Operator::NotEqualPrefix => ".*",
+                    _ => "",
+                };
+
+                format!("{}{}{}", op, spec.value.trim_end_matches(".*"), suffix)
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", specs.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        Version::from_str(s).unwrap()
+    }
+
+    fn contains(specs: &str, version: &str) -> bool {
+        Specifiers::from_str(specs).unwrap().contains(&v(version))
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        assert_eq!(v("1.0").to_string(), "1.0");
+        assert_eq!(v("v1.0-RC.1").to_string(), "1.0rc1");
+        assert_eq!(
+            v("1!2.0.alpha2.post3.dev4+ubuntu-1").to_string(),
+            "1!2.0a2.post3.dev4+ubuntu.1"
+        );
+        assert_eq!(v("1.0-1").to_string(), "1.0.post1");
+        assert_eq!(v("1.0preview").to_string(), "1.0rc0");
+        assert_eq!(v("2.0.dev").to_string(), "2.0.dev0");
+
+        assert!(Version::from_str("1.0foo").is_err());
+        assert!(Version::from_str("").is_err());
+        assert!(Version::from_str("1.0+").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ordering() {
+        let ordered = [
+            "1.0.dev456",
+            "1.0a1",
+            "1.0a2.dev456",
+            "1.0a12.dev456",
+            "1.0a12",
+            "1.0b1.dev456",
+            "1.0b2",
+            "1.0b2.post345.dev456",
+            "1.0b2.post345",
+            "1.0rc1.dev456",
+            "1.0rc1",
+            "1.0",
+            "1.0+abc.5",
+            "1.0+abc.7",
+            "1.0+5",
+            "1.0.post456.dev34",
+            "1.0.post456",
+            "1.1.dev1",
+            "1!0.1",
+        ];
+
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+
+        assert_eq!(v("1.0"), v("1.0.0"));
+    }
+
+    #[test]
+    fn test_specifiers() {
+        assert!(contains(">=1.0,<2", "1.5"));
+        assert!(!contains(">=1.0,<2", "2.0"));
+        assert!(!contains("<2", "2.0rc1"));
+        assert!(contains("<2rc2", "2.0rc1"));
+        assert!(!contains(">1.0", "1.0.post1"));
+        assert!(contains(">1.0.post1", "1.0.post2"));
+        assert!(contains("==1.0", "1.0+local"));
+        assert!(!contains("==1.0+local", "1.0"));
+        assert!(contains("==1.1.*", "1.1.5"));
+        assert!(contains("==1.1.*", "1.1"));
+        assert!(!contains("==1.1.*", "1.10"));
+        assert!(contains("!=1.1.*", "1.2"));
+        assert!(contains("~=1.4.2", "1.4.9"));
+        assert!(!contains("~=1.4.2", "1.5"));
+        assert!(contains("~=2.2", "2.9"));
+        assert!(!contains("~=2.2", "3.0"));
+        assert!(contains("===1.0", "1.0"));
+        assert!(!contains("===1.0", "1.0.0"));
+        assert!(contains("", "0.1"));
+
+        assert!(Specifiers::from_str("~=1").is_err());
+        assert!(Specifiers::from_str(">=1.*").is_err());
+        assert!(Specifiers::from_str("1.0").is_err());
+
+        assert!(Specifiers::from_str(">=2.0b1")
+            .unwrap()
+            .allows_prereleases());
+        assert!(!Specifiers::from_str(">=2.0").unwrap().allows_prereleases());
+
+        assert_eq!(
+            Specifiers::from_str(">= 1.0, != 1.5.*")
+                .unwrap()
+                .to_string(),
+            ">=1.0,!=1.5.*"
+        );
+    }
+}
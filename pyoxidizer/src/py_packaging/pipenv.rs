@@ -0,0 +1,235 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Interaction with Pipenv lock files.
+
+A `Pipfile.lock` file pins the exact version and file hashes of every
+package of a project in a `default` section and in a `develop` section
+holding development packages. We convert the packages of a lock file to pip
+requirements so they can be installed as is.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    std::collections::{BTreeMap, BTreeSet},
+};
+
+#[derive(Deserialize)]
+struct PipfileLock {
+    #[serde(rename = "_meta", default)]
+    meta: PipfileMeta,
+    #[serde(default)]
+    default: BTreeMap<String, PipfilePackage>,
+    #[serde(default)]
+    develop: BTreeMap<String, PipfilePackage>,
+}
+
+#[derive(Default, Deserialize)]
+struct PipfileMeta {
+    #[serde(default)]
+    sources: Vec<PipfileSource>,
+}
+
+#[derive(Deserialize)]
+struct PipfileSource {
+    name: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct PipfilePackage {
+    /// Version specifier, e.g. `==1.0`.
+    version: Option<String>,
+    #[serde(default)]
+    hashes: Vec<String>,
+    #[serde(default)]
+    extras: Vec<String>,
+    markers: Option<String>,
+    /// Name of the source the package is installed from.
+    index: Option<String>,
+}
+
+/// Produce pip requirements for the packages of a Pipenv lock file.
+///
+/// `sections` are the names of the sections whose packages are included:
+/// `default` and/or `develop`. Every requirement pins the exact version of
+/// the package and the hashes of its files. So the requirements should be
+/// installed with `--no-deps`.
+///
+/// Packages from a VCS, path, or file, which don't have a pinned version,
+/// are rejected because their content can't be pinned by a hash.
+pub fn pipfile_lock_requirements(data: &str, sections: &[String]) -> Result<String> {
+    let lock: PipfileLock = serde_json::from_str(data).context("parsing Pipfile.lock")?;
+
+    let mut packages = BTreeMap::new();
+
+    for section in sections {
+        let section_packages = match section.as_str() {
+            "default" => &lock.default,
+            "develop" => &lock.develop,
+            _ => return Err(anyhow!("unknown Pipfile.lock section: {}", section)),
+        };
+
+        for (name, package) in section_packages {
+            packages.entry(name.as_str()).or_insert(package);
+        }
+    }
+
+    let mut indices = BTreeSet::new();
+    let mut requirements = vec![];
+
+    for (name, package) in packages {
+        let version = match &package.version {
+            Some(version) if version.starts_with("==") => version,
+            _ => {
+                return Err(anyhow!(
+                    "package {} doesn't have a pinned version; only packages from an index are supported",
+                    name
+                ))
+            }
+        };
+
+        if package.hashes.is_empty() {
+            return Err(anyhow!("package {} has no pinned file hashes", name));
+        }
+
+        if let Some(index) = &package.index {
+            if !lock.meta.sources.iter().any(|source| &source.name == index) {
+                return Err(anyhow!(
+                    "package {} references unknown source {}",
+                    name,
+                    index
+                ));
+            }
+
+            indices.insert(index.as_str());
+        }
+
+        let mut requirement = name.to_string();
+
+        if !package.extras.is_empty() {
+            requirement.push_str(&format!("[{}]", package.extras.join(",")));
+        }
+
+        requirement.push_str(version);
+
+        if let Some(markers) = &package.markers {
+            requirement.push_str(&format!(" ; {}", markers));
+        }
+
+        for hash in &package.hashes {
+            requirement.push_str(&format!(" --hash={}", hash));
+        }
+
+        requirements.push(requirement);
+    }
+
+    // The first source of the lock file is the primary index.
+    let mut res = String::new();
+    for (i, source) in lock
+        .meta
+        .sources
+        .iter()
+        .filter(|source| indices.contains(source.name.as_str()))
+        .enumerate()
+    {
+        if i == 0 {
+            res.push_str(&format!("--index-url {}\n", source.url));
+        } else {
+            res.push_str(&format!("--extra-index-url {}\n", source.url));
+        }
+    }
+    for requirement in requirements {
+        res.push_str(&requirement);
+        res.push('\n');
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"{
+    "_meta": {
+        "hash": {"sha256": "00"},
+        "pipfile-spec": 6,
+        "requires": {"python_version": "3.10"},
+        "sources": [
+            {"name": "pypi", "url": "https://pypi.org/simple", "verify_ssl": true},
+            {"name": "example", "url": "https://example.com/simple", "verify_ssl": true}
+        ]
+    },
+    "default": {
+        "requests": {
+            "extras": ["socks"],
+            "hashes": ["sha256:aa", "sha256:bb"],
+            "index": "pypi",
+            "markers": "python_version >= '3.7' and python_version < '4'",
+            "version": "==2.28.1"
+        },
+        "certifi": {
+            "hashes": ["sha256:cc"],
+            "index": "example",
+            "version": "==2022.9.24"
+        }
+    },
+    "develop": {
+        "pytest": {
+            "hashes": ["sha256:dd"],
+            "index": "pypi",
+            "version": "==7.2.0"
+        },
+        "certifi": {
+            "hashes": ["sha256:cc"],
+            "index": "example",
+            "version": "==2022.9.24"
+        }
+    }
+}"#;
+
+    #[test]
+    fn test_requirements() -> Result<()> {
+        assert_eq!(
+            pipfile_lock_requirements(LOCK, &["default".to_string()])?,
+            "--index-url https://pypi.org/simple\n\
+             --extra-index-url https://example.com/simple\n\
+             certifi==2022.9.24 --hash=sha256:cc\n\
+             requests[socks]==2.28.1 ; python_version >= '3.7' and python_version < '4' --hash=sha256:aa --hash=sha256:bb\n"
+        );
+        assert_eq!(
+            pipfile_lock_requirements(LOCK, &["develop".to_string()])?,
+            "--index-url https://pypi.org/simple\n\
+             --extra-index-url https://example.com/simple\n\
+             certifi==2022.9.24 --hash=sha256:cc\n\
+             pytest==7.2.0 --hash=sha256:dd\n"
+        );
+        assert_eq!(
+            pipfile_lock_requirements(LOCK, &["default".to_string(), "develop".to_string()])?
+                .lines()
+                .count(),
+            5
+        );
+        assert_eq!(pipfile_lock_requirements(LOCK, &[])?, "");
+        assert!(pipfile_lock_requirements(LOCK, &["dev".to_string()]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_package() {
+        let lock = r#"{
+    "_meta": {"sources": []},
+    "default": {
+        "foo": {"git": "https://github.com/example/foo.git", "ref": "0123456789"}
+    },
+    "develop": {}
+}"#;
+
+        assert!(pipfile_lock_requirements(lock, &["default".to_string()]).is_err());
+    }
+}
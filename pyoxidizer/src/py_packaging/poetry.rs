@@ -0,0 +1,344 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Interaction with Poetry lock files.
+
+A `poetry.lock` file pins the exact version of every package of a project
+along with the hashes of the files of each package. We convert the packages
+of a lock file to pip requirements so they can be installed as is.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    std::collections::BTreeMap,
+};
+
+#[derive(Deserialize)]
+struct PoetryLock {
+    #[serde(default)]
+    package: Vec<PoetryPackage>,
+    #[serde(default)]
+    metadata: PoetryMetadata,
+}
+
+#[derive(Default, Deserialize)]
+struct PoetryMetadata {
+    /// Files of packages, keyed by package name.
+    ///
+    /// Lock files written by Poetry before 1.2 store files here instead of
+    /// in each package.
+    #[serde(default)]
+    files: BTreeMap<String, Vec<PoetryFile>>,
+}
+
+#[derive(Deserialize)]
+struct PoetryPackage {
+    name: String,
+    version: String,
+    /// Dependency groups the package belongs to.
+    groups: Option<Vec<String>>,
+    /// `main` or `dev`. Written by Poetry before 2.0 instead of `groups`.
+    category: Option<String>,
+    #[serde(default)]
+    optional: bool,
+    markers: Option<PoetryMarkers>,
+    #[serde(default)]
+    files: Vec<PoetryFile>,
+    source: Option<PoetrySource>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PoetryMarkers {
+    /// Markers applying to all groups.
+    All(String),
+    /// Markers keyed by group name.
+    Groups(BTreeMap<String, String>),
+}
+
+#[derive(Deserialize)]
+struct PoetryFile {
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct PoetrySource {
+    #[serde(rename = "type")]
+    source_type: String,
+    url: String,
+}
+
+impl PoetryPackage {
+    fn groups(&self) -> Vec<&str> {
+        match (&self.groups, &self.category) {
+            (Some(groups), _) => groups.iter().map(|group| group.as_str()).collect(),
+            (None, Some(category)) => vec![category.as_str()],
+            (None, None) => vec!["main"],
+        }
+    }
+
+    /// Resolve the environment markers of the package for selected groups.
+    ///
+    /// `None` means the package is installed in all environments.
+    fn markers(&self, groups: &[&str]) -> Option<String> {
+        match &self.markers {
+            None => None,
+            Some(PoetryMarkers::All(markers)) => Some(markers.clone()),
+            Some(PoetryMarkers::Groups(by_group)) => {
+                let mut markers = vec![];
+
+                for group in groups {
+                    match by_group.get(*group) {
+                        Some(marker) => markers.push(marker.as_str()),
+                        None => return None,
+                    }
+                }
+
+                if markers.len() == 1 {
+                    Some(markers[0].to_string())
+                } else {
+                    Some(
+                        markers
+                            .iter()
+                            .map(|marker| format!("({})", marker))
+                            .collect::<Vec<_>>()
+                            .join(" or "),
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Produce pip requirements for the packages of a Poetry lock file.
+///
+/// Only packages belonging to at least one of `groups` are included. Optional
+/// packages, which are only installed for extras, are excluded. Every
+/// requirement pins the exact version of the package and the hashes of its
+/// files. So the requirements should be installed with `--no-deps`.
+///
+/// Packages from a VCS, directory, or URL source are rejected because their
+/// content can't be pinned by a hash.
+pub fn poetry_lock_requirements(data: &str, groups: &[String]) -> Result<String> {
+    let lock: PoetryLock = toml::from_str(data).context("parsing poetry lock file")?;
+
+    let mut index_urls = vec![];
+    let mut requirements = vec![];
+
+    for package in &lock.package {
+        if package.optional {
+            continue;
+        }
+
+        let package_groups = package
+            .groups()
+            .into_iter()
+            .filter(|group| groups.iter().any(|g| g == group))
+            .collect::<Vec<_>>();
+
+        if package_groups.is_empty() {
+            continue;
+        }
+
+        if let Some(source) = &package.source {
+            if source.source_type != "legacy" {
+                return Err(anyhow!(
+                    "package {} has unsupported source type {}",
+                    package.name,
+                    source.source_type
+                ));
+            }
+
+            if !index_urls.contains(&source.url) {
+                index_urls.push(source.url.clone());
+            }
+        }
+
+        let mut requirement = format!("{}=={}", package.name, package.version);
+
+        if let Some(markers) = package.markers(&package_groups) {
+            requirement.push_str(&format!(" ; {}", markers));
+        }
+
+        let files = if package.files.is_empty() {
+            lock.metadata
+                .files
+                .get(&package.name)
+                .map(|files| files.as_slice())
+                .unwrap_or_default()
+        } else {
+            package.files.as_slice()
+        };
+
+        if files.is_empty() {
+            return Err(anyhow!(
+                "package {} has no pinned file hashes",
+                package.name
+            ));
+        }
+
+        for file in files {
+            requirement.push_str(&format!(" --hash={}", file.hash));
+        }
+
+        requirements.push(requirement);
+    }
+
+    let mut res = String::new();
+    for url in index_urls {
+        res.push_str(&format!("--extra-index-url {}\n", url));
+    }
+    for requirement in requirements {
+        res.push_str(&requirement);
+        res.push('\n');
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirements() -> Result<()> {
+        let data = r#"
+[[package]]
+name = "certifi"
+version = "2022.9.24"
+description = "Python package for providing Mozilla's CA Bundle."
+optional = false
+python-versions = ">=3.6"
+groups = ["main"]
+files = [
+    {file = "certifi-2022.9.24-py3-none-any.whl", hash = "sha256:aa"},
+    {file = "certifi-2022.9.24.tar.gz", hash = "sha256:bb"},
+]
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+optional = false
+python-versions = "*"
+groups = ["main", "dev"]
+markers = {main = "sys_platform == \"win32\"", dev = "platform_system == \"Windows\""}
+files = [
+    {file = "colorama-0.4.6-py2.py3-none-any.whl", hash = "sha256:cc"},
+]
+
+[[package]]
+name = "pytest"
+version = "7.2.0"
+optional = false
+python-versions = ">=3.7"
+groups = ["dev"]
+markers = "python_version >= \"3.7\""
+files = [
+    {file = "pytest-7.2.0-py3-none-any.whl", hash = "sha256:dd"},
+]
+
+[[package]]
+name = "pysocks"
+version = "1.7.1"
+optional = true
+python-versions = "*"
+groups = ["main"]
+files = [
+    {file = "PySocks-1.7.1-py3-none-any.whl", hash = "sha256:ee"},
+]
+
+[package.source]
+type = "legacy"
+url = "https://example.com/simple"
+reference = "example"
+
+[metadata]
+lock-version = "2.1"
+python-versions = "^3.8"
+content-hash = "00"
+"#;
+
+        assert_eq!(
+            poetry_lock_requirements(data, &["main".to_string()])?,
+            "certifi==2022.9.24 --hash=sha256:aa --hash=sha256:bb\n\
+             colorama==0.4.6 ; sys_platform == \"win32\" --hash=sha256:cc\n"
+        );
+        assert_eq!(
+            poetry_lock_requirements(data, &["main".to_string(), "dev".to_string()])?,
+            "certifi==2022.9.24 --hash=sha256:aa --hash=sha256:bb\n\
+             colorama==0.4.6 ; (sys_platform == \"win32\") or (platform_system == \"Windows\") --hash=sha256:cc\n\
+             pytest==7.2.0 ; python_version >= \"3.7\" --hash=sha256:dd\n"
+        );
+        assert_eq!(poetry_lock_requirements(data, &["docs".to_string()])?, "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_lock() -> Result<()> {
+        let data = r#"
+[[package]]
+name = "requests"
+version = "2.28.1"
+category = "main"
+optional = false
+python-versions = ">=3.7, <4"
+
+[package.source]
+type = "legacy"
+url = "https://example.com/simple"
+reference = "example"
+
+[[package]]
+name = "black"
+version = "22.10.0"
+category = "dev"
+optional = false
+python-versions = ">=3.7"
+
+[metadata]
+lock-version = "1.1"
+python-versions = "^3.8"
+content-hash = "00"
+
+[metadata.files]
+requests = [
+    {file = "requests-2.28.1-py3-none-any.whl", hash = "sha256:aa"},
+]
+black = [
+    {file = "black-22.10.0-py3-none-any.whl", hash = "sha256:bb"},
+]
+"#;
+
+        assert_eq!(
+            poetry_lock_requirements(data, &["main".to_string()])?,
+            "--extra-index-url https://example.com/simple\n\
+             requests==2.28.1 --hash=sha256:aa\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_source() {
+        let data = r#"
+[[package]]
+name = "foo"
+version = "1.0"
+optional = false
+python-versions = "*"
+files = []
+
+[package.source]
+type = "git"
+url = "https://github.com/example/foo.git"
+reference = "main"
+resolved_reference = "0123456789"
+"#;
+
+        assert!(poetry_lock_requirements(data, &["main".to_string()]).is_err());
+    }
+}
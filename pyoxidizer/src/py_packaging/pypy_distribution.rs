@@ -0,0 +1,729 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Functionality for PyPy distributions.
+
+PyPy release archives contain a self-contained interpreter. Unlike CPython,
+PyPy doesn't expose the initialization APIs required to embed it in another
+executable. So PyPy distributions can't be turned into a `PythonBinaryBuilder`.
+Instead, applications are materialized as a copy of the distribution with
+packages installed into its `site-packages` and a launcher script running a
+module with the copied interpreter.
+*/
+
+use {
+    super::{
+        binary::{LibpythonLinkMode, PythonBinaryBuilder},
+        config::PyembedPythonInterpreterConfig,
+        dependency_cache::{DependencyCache, DependencyCacheMode},
+        distribution::{
+            resolve_python_distribution_from_location, AppleSdkInfo, BinaryLibpythonLinkMode,
+            DistributionExtractLock, PythonDistribution, PythonDistributionLocation,
+        },
+        packaging_tool::{pip_download_files, pip_install_into_directory},
+        standalone_distribution::parse_python_major_minor_version,
+    },
+    crate::environment::{default_target_triple, Environment},
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    log::warn,
+    python_packaging::{
+        bytecode::{BytecodeCompiler, PythonBytecodeCompiler},
+        filesystem_scanning::find_python_resources,
+        location::ConcreteResourceLocation,
+        module_util::PythonModuleSuffixes,
+        policy::PythonPackagingPolicy,
+        resource::PythonResource,
+    },
+    serde::Deserialize,
+    simple_file_manifest::{FileEntry, FileManifest},
+    std::{
+        collections::HashMap,
+        io::BufReader,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+/// File written once a distribution archive is fully extracted.
+const EXTRACTED_MARKER: &str = ".pyoxidizer-extracted";
+
+/// Directory holding the distribution in application layouts.
+const LAYOUT_DISTRIBUTION_DIR: &str = "pypy";
+
+/// Python packages in the standard library providing tests.
+const STDLIB_TEST_PACKAGES: &[&str] = &[
+    "ctypes.test",
+    "distutils.tests",
+    "idlelib.idle_test",
+    "lib2to3.tests",
+    "sqlite3.test",
+    "test",
+    "tkinter.test",
+    "unittest.test",
+];
+
+/// Python script printing metadata about the running interpreter as JSON.
+const INTROSPECTION_SCRIPT: &str = r#"
+import importlib.machinery
+import json
+import sys
+import sysconfig
+
+print(json.dumps({
+    "version": "%d.%d.%d" % sys.version_info[0:3],
+    "pypy_version": "%d.%d.%d" % sys.pypy_version_info[0:3],
+    "cache_tag": sys.implementation.cache_tag,
+    "platform": sysconfig.get_platform(),
+    "soabi": sysconfig.get_config_var("SOABI"),
+    "stdlib": sysconfig.get_path("stdlib"),
+    "site_packages": sysconfig.get_path("purelib"),
+    "config_vars": {
+        k: str(v) for k, v in sysconfig.get_config_vars().items() if v is not None
+    },
+    "source_suffixes": importlib.machinery.SOURCE_SUFFIXES,
+    "bytecode_suffixes": importlib.machinery.BYTECODE_SUFFIXES,
+    "debug_bytecode_suffixes": importlib.machinery.DEBUG_BYTECODE_SUFFIXES,
+    "optimized_bytecode_suffixes": importlib.machinery.OPTIMIZED_BYTECODE_SUFFIXES,
+    "extension_suffixes": importlib.machinery.EXTENSION_SUFFIXES,
+}))
+"#;
+
+/// Metadata reported by a PyPy interpreter.
+#[derive(Debug, Deserialize)]
+struct PyPyIntrospection {
+    version: String,
+    pypy_version: String,
+    cache_tag: String,
+    platform: String,
+    soabi: Option<String>,
+    stdlib: PathBuf,
+    site_packages: PathBuf,
+    config_vars: HashMap<String, String>,
+    source_suffixes: Vec<String>,
+    bytecode_suffixes: Vec<String>,
+    debug_bytecode_suffixes: Vec<String>,
+    optimized_bytecode_suffixes: Vec<String>,
+    extension_suffixes: Vec<String>,
+}
+
+/// The error returned when attempting to embed a PyPy distribution.
+fn embedding_unsupported() -> anyhow::Error {
+    anyhow!("PyPy distributions cannot be embedded in an executable; use PythonDistribution.to_file_manifest() to produce a filesystem layout instead")
+}
+
+/// Resolve the pip platform tag for a `sysconfig` platform.
+fn platform_compatibility_tag(platform: &str) -> String {
+    match platform {
+        "linux-aarch64" => "manylinux2014_aarch64".to_string(),
+        "linux-x86_64" => "manylinux2014_x86_64".to_string(),
+        "linux-i686" => "manylinux2014_i686".to_string(),
+        "win-amd64" => "win_amd64".to_string(),
+        p => p.replace(['-', '.'], "_"),
+    }
+}
+
+/// Ensure a value is a dotted Python module name.
+///
+/// Module names are written into launcher scripts. So this also guards
+/// against values being interpreted by a shell.
+fn validate_module_name(name: &str) -> Result<()> {
+    let valid = name.split('.').all(|part| {
+        part.chars()
+            .next()
+            .map(|c| c.is_ascii_alphabetic() || c == '_')
+            .unwrap_or(false)
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("{} is not a valid Python module name", name))
+    }
+}
+
+/// Produce the content of a launcher script running a module.
+///
+/// `exe_path` is the path of the interpreter relative to the launcher.
+fn launcher_script(target_triple: &str, exe_path: &Path, run_module: &str) -> String {
+    if target_triple.contains("-windows-") {
+        let exe_path = exe_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("\\");
+
+        format!(
+            "@echo off\r\n\"%~dp0{}\" -I -m {} %*\r\n",
+            exe_path, run_module
+        )
+    } else {
+        let exe_path = exe_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        format!(
+            "#!/bin/sh\ndir=\"$(cd \"$(dirname \"$0\")\" && pwd)\"\nexec \"$dir/{}\" -I -m {} \"$@\"\n",
+            exe_path, run_module
+        )
+    }
+}
+
+/// Add files in a directory tree to a `FileManifest` under a prefix.
+///
+/// Paths under `exclude` aren't added.
+fn add_tree_to_manifest(
+    manifest: &mut FileManifest,
+    root: &Path,
+    prefix: &Path,
+    exclude: Option<&Path>,
+) -> Result<()> {
+    for entry in walkdir::WalkDir::new(root).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry?;
+        let path = entry.path();
+
+        if let Some(exclude) = exclude {
+            if path.starts_with(exclude) {
+                continue;
+            }
+        }
+
+        // Symlinks to directories aren't followed. Their targets are in the tree.
+        if path.is_dir() {
+            continue;
+        }
+
+        let rel_path = path.strip_prefix(root)?;
+
+        manifest.add_file_entry(
+            prefix.join(rel_path),
+            FileEntry::try_from(path).with_context(|| format!("reading {}", path.display()))?,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// A PyPy distribution.
+#[derive(Clone, Debug)]
+pub struct PyPyDistribution {
+    /// Directory the distribution is installed in.
+    base_dir: PathBuf,
+
+    /// Rust target triple the distribution runs on.
+    target_triple: String,
+
+    /// Path to the `pypy` executable.
+    python_exe: PathBuf,
+
+    /// Full Python language version implemented by the distribution.
+    version: String,
+
+    /// Version of PyPy itself.
+    pypy_version: String,
+
+    /// PEP 425 Python tag.
+    python_tag: String,
+
+    /// PEP 425 Python ABI tag.
+    python_abi_tag: Option<String>,
+
+    /// Platform tag reported by `sysconfig`.
+    python_platform_tag: String,
+
+    /// Platform tag used for compatibility by `pip`.
+    python_platform_compatibility_tag: String,
+
+    /// Bytecode cache tag.
+    cache_tag: String,
+
+    /// Suffixes of Python module files.
+    module_suffixes: PythonModuleSuffixes,
+
+    /// Configuration variables reported by `sysconfig`.
+    config_vars: HashMap<String, String>,
+
+    /// Directory holding the standard library.
+    stdlib_path: PathBuf,
+
+    /// Directory holding installed packages.
+    site_packages_path: PathBuf,
+}
+
+impl PyPyDistribution {
+    /// Resolve a PyPy distribution from a location.
+    ///
+    /// The distribution archive is extracted in `distributions_dir`.
+    pub fn from_location(
+        location: &PythonDistributionLocation,
+        distributions_dir: &Path,
+    ) -> Result<Self> {
+        let (archive_path, extract_path) =
+            resolve_python_distribution_from_location(location, distributions_dir)?;
+
+        Self::from_archive(&archive_path, &extract_path)
+    }
+
+    /// Create an instance from a `.tar.bz2` or `.zip` release archive.
+    ///
+    /// The archive will be extracted to `extract_dir` if necessary.
+    pub fn from_archive(path: &Path, extract_dir: &Path) -> Result<Self> {
+        let basename = path
+            .file_name()
+            .ok_or_else(|| anyhow!("unable to determine filename"))?
+            .to_string_lossy()
+            .to_string();
+
+        {
+            let _lock = DistributionExtractLock::new(extract_dir)?;
+
+            if !extract_dir.join(EXTRACTED_MARKER).exists() {
+                std::fs::create_dir_all(extract_dir)?;
+
+                let fh = std::fs::File::open(path)
+                    .with_context(|| format!("unable to open {}", path.display()))?;
+                let reader = BufReader::new(fh);
+
+                if basename.ends_with(".tar.bz2") {
+                    let mut archive = tar::Archive::new(bzip2::read::BzDecoder::new(reader));
+                    // See StandaloneDistribution::from_tar() for why mtimes aren't preserved.
+                    archive.set_preserve_mtime(false);
+                    archive
+                        .unpack(extract_dir)
+                        .context("extracting tar.bz2 distribution")?;
+                } else if basename.ends_with(".zip") {
+                    zip::ZipArchive::new(reader)?
+                        .extract(extract_dir)
+                        .context("extracting zip distribution")?;
+                } else {
+                    return Err(anyhow!(
+                        "unhandled PyPy distribution format: {}; expected a .tar.bz2 or .zip file",
+                        path.display()
+                    ));
+                }
+
+                std::fs::write(extract_dir.join(EXTRACTED_MARKER), b"")?;
+            }
+        }
+
+        // Release archives have a single top-level directory.
+        let base_dir = std::fs::read_dir(extract_dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| path.is_dir())
+            .ok_or_else(|| anyhow!("no distribution directory in {}", extract_dir.display()))?;
+
+        Self::from_directory(&base_dir)
+    }
+
+    /// Obtain an instance from a directory containing an extracted distribution.
+    pub fn from_directory(base_dir: &Path) -> Result<Self> {
+        let python_exe = [
+            base_dir.join("bin").join("pypy3"),
+            base_dir.join("pypy3.exe"),
+        ]
+        .into_iter()
+        .find(|p| p.exists())
+        .ok_or_else(|| anyhow!("unable to find pypy3 executable in {}", base_dir.display()))?;
+
+        let output = cmd(&python_exe, &["-c", INTROSPECTION_SCRIPT])
+            .stdout_capture()
+            .run()
+            .with_context(|| format!("running {}", python_exe.display()))?;
+
+        let info: PyPyIntrospection =
+            serde_json::from_slice(&output.stdout).context("parsing PyPy metadata")?;
+
+        let major_minor = info.version.split('.').take(2).collect::<Vec<_>>().join("");
+
+        Ok(Self {
+            base_dir: base_dir.to_path_buf(),
+            target_triple: default_target_triple().to_string(),
+            python_exe,
+            version: info.version,
+            pypy_version: info.pypy_version,
+            python_tag: format!("pp{}", major_minor),
+            python_abi_tag: info.soabi.map(|soabi| soabi.replace(['-', '.'], "_")),
+            python_platform_compatibility_tag: platform_compatibility_tag(&info.platform),
+            python_platform_tag: info.platform,
+            cache_tag: info.cache_tag,
+            module_suffixes: PythonModuleSuffixes {
+                source: info.source_suffixes,
+                bytecode: info.bytecode_suffixes,
+                debug_bytecode: info.debug_bytecode_suffixes,
+                optimized_bytecode: info.optimized_bytecode_suffixes,
+                extension: info.extension_suffixes,
+            },
+            config_vars: info.config_vars,
+            stdlib_path: info.stdlib,
+            site_packages_path: info.site_packages,
+        })
+    }
+
+    /// The version of PyPy.
+    pub fn pypy_version(&self) -> &str {
+        &self.pypy_version
+    }
+
+    /// Materialize an application running a Python module with this distribution.
+    ///
+    /// The distribution is copied into a `pypy` directory, with packages
+    /// installed by `pip install <pip_install_args>` added to its
+    /// `site-packages`. A launcher script named after `name` runs
+    /// `run_module` with the copied interpreter.
+    ///
+    /// Returns the manifest and the path of the launcher within it.
+    pub fn to_application_manifest(
+        &self,
+        env: &Environment,
+        name: &str,
+        run_module: &str,
+        pip_install_args: &[String],
+        dependency_cache: Option<&DependencyCache>,
+        verbose: bool,
+    ) -> Result<(FileManifest, PathBuf)> {
+        validate_module_name(run_module)?;
+
+        let mut manifest = FileManifest::default();
+        let prefix = Path::new(LAYOUT_DISTRIBUTION_DIR);
+
+        // Packages in the distribution's site-packages, such as pip, aren't
+        // part of the application.
+        add_tree_to_manifest(
+            &mut manifest,
+            &self.base_dir,
+            prefix,
+            Some(&self.site_packages_path),
+        )
+        .context("adding PyPy distribution files")?;
+
+        if !pip_install_args.is_empty() {
+            let policy = self.create_packaging_policy()?;
+
+            let install_args = if let Some(cache) = dependency_cache {
+                if cache.mode() == DependencyCacheMode::Populate {
+                    pip_download_files(
+                        self,
+                        None,
+                        &policy,
+                        verbose,
+                        pip_install_args,
+                        cache.path(),
+                    )
+                    .context("downloading into dependency cache")?;
+                }
+
+                cache
+                    .pip_args()
+                    .into_iter()
+                    .chain(pip_install_args.iter().cloned())
+                    .collect()
+            } else {
+                pip_install_args.to_vec()
+            };
+
+            let temp_dir = env.temporary_directory("pyoxidizer-pypy-pip-install")?;
+            let target_dir = temp_dir.path().join("install");
+
+            pip_install_into_directory(
+                self,
+                &policy,
+                LibpythonLinkMode::Dynamic,
+                verbose,
+                &install_args,
+                &HashMap::<String, String>::new(),
+                temp_dir.path(),
+                &target_dir,
+            )
+            .context("calling pip install")?;
+
+            let site_packages = self
+                .site_packages_path
+                .strip_prefix(&self.base_dir)
+                .context("resolving site-packages directory")?;
+
+            add_tree_to_manifest(
+                &mut manifest,
+                &target_dir,
+                &prefix.join(site_packages),
+                None,
+            )
+            .context("adding installed packages")?;
+
+            temp_dir.close().context("closing temporary directory")?;
+        }
+
+        let exe_path = prefix.join(self.python_exe.strip_prefix(&self.base_dir)?);
+
+        let launcher_path = if self.target_triple.contains("-windows-") {
+            PathBuf::from(format!("{}.cmd", name))
+        } else {
+            PathBuf::from(name)
+        };
+
+        manifest.add_file_entry(
+            &launcher_path,
+            FileEntry::new_from_data(
+                launcher_script(&self.target_triple, &exe_path, run_module).into_bytes(),
+                true,
+            ),
+        )?;
+
+        Ok((manifest, launcher_path))
+    }
+}
+
+impl PythonDistribution for PyPyDistribution {
+    fn clone_trait(&self) -> Arc<dyn PythonDistribution> {
+        Arc::new(self.clone())
+    }
+
+    fn target_triple(&self) -> &str {
+        &self.target_triple
+    }
+
+    fn compatible_host_triples(&self) -> Vec<String> {
+        vec![self.target_triple.clone()]
+    }
+
+    fn python_exe_path(&self) -> &Path {
+        &self.python_exe
+    }
+
+    fn python_version(&self) -> &str {
+        &self.version
+    }
+
+    fn python_major_minor_version(&self) -> String {
+        parse_python_major_minor_version(&self.version)
+    }
+
+    fn python_implementation(&self) -> &str {
+        "pypy"
+    }
+
+    fn python_implementation_short(&self) -> &str {
+        "pp"
+    }
+
+    fn python_tag(&self) -> &str {
+        &self.python_tag
+    }
+
+    fn python_abi_tag(&self) -> Option<&str> {
+        self.python_abi_tag.as_deref()
+    }
+
+    fn python_platform_tag(&self) -> &str {
+        &self.python_platform_tag
+    }
+
+    fn python_platform_compatibility_tag(&self) -> &str {
+        &self.python_platform_compatibility_tag
+    }
+
+    fn cache_tag(&self) -> &str {
+        &self.cache_tag
+    }
+
+    fn python_module_suffixes(&self) -> Result<PythonModuleSuffixes> {
+        Ok(self.module_suffixes.clone())
+    }
+
+    fn python_config_vars(&self) -> &HashMap<String, String> {
+        &self.config_vars
+    }
+
+    fn stdlib_test_packages(&self) -> Vec<String> {
+        STDLIB_TEST_PACKAGES.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn apple_sdk_info(&self) -> Option<&AppleSdkInfo> {
+        None
+    }
+
+    fn create_bytecode_compiler(
+        &self,
+        env: &Environment,
+    ) -> Result<Box<dyn PythonBytecodeCompiler>> {
+        let temp_dir = env.temporary_directory("pyoxidizer-bytecode-compiler")?;
+
+        Ok(Box::new(BytecodeCompiler::new(
+            &self.python_exe,
+            temp_dir.path(),
+        )?))
+    }
+
+    fn create_packaging_policy(&self) -> Result<PythonPackagingPolicy> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        // Resources can only be loaded from the filesystem.
+        policy.set_resources_location(ConcreteResourceLocation::RelativePath("lib".to_string()));
+
+        Ok(policy)
+    }
+
+    fn create_python_interpreter_config(&self) -> Result<PyembedPythonInterpreterConfig> {
+        Err(embedding_unsupported())
+    }
+
+    fn as_python_executable_builder(
+        &self,
+        _host_triple: &str,
+        _target_triple: &str,
+        _name: &str,
+        _libpython_link_mode: BinaryLibpythonLinkMode,
+        _policy: &PythonPackagingPolicy,
+        _config: &PyembedPythonInterpreterConfig,
+        _host_distribution: Option<Arc<dyn PythonDistribution>>,
+    ) -> Result<Box<dyn PythonBinaryBuilder>> {
+        Err(embedding_unsupported())
+    }
+
+    fn python_resources<'a>(&self) -> Vec<PythonResource<'a>> {
+        let resources = match find_python_resources(
+            &self.stdlib_path,
+            &self.cache_tag,
+            &self.module_suffixes,
+            false,
+            true,
+        ) {
+            Ok(resources) => resources,
+            Err(e) => {
+                warn!("error scanning PyPy standard library: {:?}", e);
+                return vec![];
+            }
+        };
+
+        resources
+            .filter_map(|resource| match resource {
+                Ok(resource) => Some(resource),
+                Err(e) => {
+                    warn!("error scanning PyPy standard library: {:?}", e);
+                    None
+                }
+            })
+            // Installed packages aren't part of the standard library.
+            .filter(|resource| {
+                !resource
+                    .full_name()
+                    .split('.')
+                    .next()
+                    .unwrap_or_default()
+                    .contains('-')
+            })
+            .filter_map(|resource| match resource {
+                PythonResource::ModuleSource(m) => {
+                    let mut m = m.into_owned();
+                    m.is_stdlib = true;
+                    m.is_test = self.is_stdlib_test_package(&m.name);
+                    Some(m.into())
+                }
+                PythonResource::PackageResource(r) => {
+                    let mut r = r.into_owned();
+                    r.is_stdlib = true;
+                    r.is_test = self.is_stdlib_test_package(&r.leaf_package);
+                    Some(r.into())
+                }
+                PythonResource::ExtensionModule(e) => {
+                    let mut e = e.into_owned();
+                    e.is_stdlib = true;
+                    Some(e.into())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    }
+
+    fn ensure_pip(&self) -> Result<PathBuf> {
+        let pip_path = if self.target_triple.contains("-windows-") {
+            self.base_dir.join("Scripts").join("pip3.exe")
+        } else {
+            self.base_dir.join("bin").join("pip3")
+        };
+
+        if !pip_path.exists() {
+            warn!("{} doesnt exist", pip_path.display());
+            cmd(&self.python_exe, &["-m", "ensurepip"])
+                .stdout_to_stderr()
+                .run()
+                .context("running ensurepip")?;
+        }
+
+        Ok(pip_path)
+    }
+
+    fn resolve_distutils(
+        &self,
+        _libpython_link_mode: LibpythonLinkMode,
+        _dest_dir: &Path,
+        _extra_python_paths: &[&Path],
+    ) -> Result<HashMap<String, String>> {
+        // Extension modules link against the distribution's libpypy. So the
+        // stock distutils works.
+        Ok(HashMap::new())
+    }
+
+    fn supports_in_memory_shared_library_loading(&self) -> bool {
+        false
+    }
+
+    fn tcl_files(&self) -> Result<Vec<(PathBuf, FileEntry)>> {
+        Ok(vec![])
+    }
+
+    fn tcl_library_path_directory(&self) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform_compatibility_tag() {
+        assert_eq!(
+            platform_compatibility_tag("linux-x86_64"),
+            "manylinux2014_x86_64"
+        );
+        assert_eq!(platform_compatibility_tag("win-amd64"), "win_amd64");
+        assert_eq!(platform_compatibility_tag("win-arm64"), "win_arm64");
+        assert_eq!(
+            platform_compatibility_tag("macosx-10.15-x86_64"),
+            "macosx_10_15_x86_64"
+        );
+    }
+
+    #[test]
+    fn test_validate_module_name() {
+        assert!(validate_module_name("pytest").is_ok());
+        assert!(validate_module_name("my_app.__main__").is_ok());
+        assert!(validate_module_name("").is_err());
+        assert!(validate_module_name("app.").is_err());
+        assert!(validate_module_name("1app").is_err());
+        assert!(validate_module_name("app; rm -rf /").is_err());
+    }
+
+    #[test]
+    fn test_launcher_script() {
+        let exe_path = Path::new("pypy").join("bin").join("pypy3");
+
+        assert_eq!(
+            launcher_script("x86_64-unknown-linux-gnu", &exe_path, "app"),
+            "#!/bin/sh\ndir=\"$(cd \"$(dirname \"$0\")\" && pwd)\"\nexec \"$dir/pypy/bin/pypy3\" -I -m app \"$@\"\n"
+        );
+
+        let exe_path = Path::new("pypy").join("pypy3.exe");
+
+        assert_eq!(
+            launcher_script("x86_64-pc-windows-msvc", &exe_path, "app"),
+            "@echo off\r\n\"%~dp0pypy\\pypy3.exe\" -I -m app %*\r\n"
+        );
+    }
+}
@@ -0,0 +1,1046 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Resolution of Python package dependencies for a target environment.
+
+`pip` resolves dependencies for the environment it runs in: environment
+markers and wheel compatibility tags are evaluated against the machine
+running the build. When cross-compiling, that is the wrong environment.
+
+The resolver in this module instead evaluates markers and wheel tags for the
+Python distribution being embedded. It finds wheels in a PEP 691 package index
+and/or in local directories, reads their core metadata to discover
+dependencies, and pins a version and wheel of every package. Only wheels are
+considered because building source distributions runs code on the host.
+*/
+
+use {
+    super::{
+        dependency_cache::{DependencyCache, DependencyCacheMode},
+        distribution::PythonDistribution,
+        markers::MarkerEnvironment,
+        pep440::{Specifiers, Version},
+    },
+    anyhow::{anyhow, Context, Result},
+    log::warn,
+    python_packaging::{
+        package_metadata::PythonPackageMetadata, policy::PythonPackagingPolicy,
+        resource::PythonResource, wheel::WheelArchive,
+    },
+    serde::Deserialize,
+    sha2::Digest,
+    std::{
+        collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+        io::{Cursor, Read},
+        path::PathBuf,
+        str::FromStr,
+    },
+    tugger_common::http::get_http_client,
+    url::Url,
+};
+
+/// URL of the Python Package Index.
+pub const PYPI_SIMPLE_URL: &str = "https://pypi.org/simple/";
+
+/// Maximum number of times resolution restarts after discovering a conflict.
+const MAX_RESOLUTION_ROUNDS: usize = 100;
+
+/// Normalize a package name as PEP 503 specifies.
+pub fn normalize_name(name: &str) -> String {
+    let mut res = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !res.ends_with('-') {
+                res.push('-');
+            }
+        } else {
+            res.push(c.to_ascii_lowercase());
+        }
+    }
+
+    res
+}
+
+/// A PEP 508 dependency specification.
+#[derive(Clone, Debug)]
+pub struct Requirement {
+    /// Normalized name of the package.
+    pub name: String,
+    pub extras: Vec<String>,
+    pub specifiers: Specifiers,
+    pub marker: Option<String>,
+}
+
+impl FromStr for Requirement {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (value, marker) = match s.split_once(';') {
+            Some((value, marker)) => (value.trim(), Some(marker.trim().to_string())),
+            None => (s.trim(), None),
+        };
+
+        let name_length = value
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(value.len());
+        if name_length == 0 {
+            return Err(anyhow!("invalid requirement: {}", s));
+        }
+
+        let name = normalize_name(&value[..name_length]);
+        let mut rest = value[name_length..].trim();
+
+        let mut extras = vec![];
+        if let Some(extras_value) = rest.strip_prefix('[') {
+            let (extras_value, after) = extras_value
+                .split_once(']')
+                .ok_or_else(|| anyhow!("invalid extras in requirement: {}", s))?;
+
+            extras = extras_value
+                .split(',')
+                .map(|extra| normalize_name(extra.trim()))
+                .filter(|extra| !extra.is_empty())
+                .collect();
+            rest = after.trim();
+        }
+
+        if rest.starts_with('@') {
+            return Err(anyhow!(
+                "URL requirements aren't supported by the resolver: {}",
+                s
+            ));
+        }
+
+        let rest = rest
+            .strip_prefix('(')
+            .and_then(|rest| rest.strip_suffix(')'))
+            .unwrap_or(rest);
+
+        Ok(Self {
+            name,
+            extras,
+            specifiers: Specifiers::from_str(rest)
+                .with_context(|| format!("parsing requirement {}", s))?,
+            marker,
+        })
+    }
+}
+
+/// Parsed components of a wheel filename.
+#[derive(Clone, Debug)]
+pub struct WheelFilename {
+    /// Normalized name of the package.
+    pub name: String,
+    pub version: Version,
+    /// Compatibility tags as `(python, abi, platform)` tuples.
+    pub tags: Vec<(String, String, String)>,
+}
+
+impl WheelFilename {
+    /// Parse a wheel filename, e.g. `foo-1.0-py3-none-any.whl`.
+    pub fn parse(filename: &str) -> Option<Self> {
+        let stem = filename.strip_suffix(".whl")?;
+        let parts = stem.split('-').collect::<Vec<_>>();
+
+        // The build tag is optional.
+        let (name, version, python, abi, platform) = match parts.as_slice() {
+            [name, version, python, abi, platform] => (name, version, python, abi, platform),
+            [name, version, _, python, abi, platform] => (name, version, python, abi, platform),
+            _ => return None,
+        };
+
+        let mut tags = vec![];
+        for python in python.split('.') {
+            for abi in abi.split('.') {
+                for platform in platform.split('.') {
+                    tags.push((python.to_string(), abi.to_string(), platform.to_string()));
+                }
+            }
+        }
+
+        Some(Self {
+            name: normalize_name(name),
+            version: Version::from_str(version).ok()?,
+            tags,
+        })
+    }
+}
+
+/// Expand a platform compatibility tag to all the tags it is compatible with.
+///
+/// e.g. `manylinux2014_x86_64` is compatible with older `manylinux` tags.
+/// Tags are ordered from most to least preferred.
+fn compatible_platforms(platform: &str) -> Vec<String> {
+    let legacy_manylinux = |minor: u32| match minor {
+        17 => Some("manylinux2014"),
+        12 => Some("manylinux2010"),
+        5 => Some("manylinux1"),
+        _ => None,
+    };
+
+    let manylinux = ["manylinux2014", "manylinux2010", "manylinux1"]
+        .iter()
+        .zip([17, 12, 5])
+        .find_map(|(prefix, minor)| {
+            platform
+                .strip_prefix(prefix)
+                .and_then(|arch| arch.strip_prefix('_'))
+                .map(|arch| (minor, arch))
+        })
+        .or_else(|| {
+            let rest = platform.strip_prefix("manylinux_2_")?;
+            let (minor, arch) = rest.split_once('_')?;
+            Some((minor.parse().ok()?, arch))
+        });
+
+    if let Some((glibc_minor, arch)) = manylinux {
+        let mut res = vec![];
+        for minor in (5..=glibc_minor).rev() {
+            res.push(format!("manylinux_2_{}_{}", minor, arch));
+            if let Some(legacy) = legacy_manylinux(minor) {
+                res.push(format!("{}_{}", legacy, arch));
+            }
+        }
+
+        return res;
+    }
+
+    if let Some(rest) = platform.strip_prefix("macosx_") {
+        let parts = rest.splitn(3, '_').collect::<Vec<_>>();
+        if let [major, minor, arch] = parts.as_slice() {
+            if let (Ok(major), Ok(minor)) = (major.parse::<u32>(), minor.parse::<u32>()) {
+                let arches: &[&str] = match *arch {
+                    "x86_64" => &[
+                        "x86_64",
+                        "intel",
+                        "fat64",
+                        "fat32",
+                        "universal2",
+                        "universal",
+                    ],
+                    "arm64" => &["arm64", "universal2"],
+                    arch => return vec![format!("macosx_{}_{}_{}", major, minor, arch)],
+                };
+
+                let mut versions = vec![];
+                if major >= 11 {
+                    versions.extend((11..=major).rev().map(|major| (major, 0)));
+                    versions.extend((0..=16).rev().map(|minor| (10, minor)));
+                } else {
+                    versions.extend((0..=minor).rev().map(|minor| (10, minor)));
+                }
+
+                let mut res = vec![];
+                for (major, minor) in versions {
+                    for candidate in arches {
+                        // macOS 10 only ran arm64 code in universal2 binaries.
+                        if *arch == "arm64" && major == 10 && *candidate != "universal2" {
+                            continue;
+                        }
+                        res.push(format!("macosx_{}_{}_{}", major, minor, candidate));
+                    }
+                }
+
+                return res;
+            }
+        }
+    }
+
+    vec![platform.to_string()]
+}
+
+/// Wheel compatibility tags supported by a Python interpreter.
+#[derive(Clone, Debug)]
+pub struct CompatibilityTags {
+    /// Supported tags ordered from most to least preferred.
+    tags: Vec<(String, String, String)>,
+}
+
+impl CompatibilityTags {
+    /// Construct an instance for an interpreter.
+    ///
+    /// `python_tag` is the PEP 425 Python tag of the interpreter, e.g. `cp310`.
+    /// `platform` is a platform tag like `manylinux2014_x86_64`, or `none`
+    /// if only pure Python wheels can be used.
+    pub fn new(python_tag: &str, abi_tag: Option<&str>, platform: &str) -> Result<Self> {
+        let (implementation, version) = python_tag.split_at(python_tag.len().min(2));
+        let minor = version
+            .strip_prefix('3')
+            .and_then(|minor| minor.parse::<u32>().ok())
+            .ok_or_else(|| anyhow!("unsupported Python tag: {}", python_tag))?;
+
+        let platforms = if platform == "none" {
+            vec![]
+        } else {
+            compatible_platforms(platform)
+        };
+
+        let mut abis = abi_tag.into_iter().map(String::from).collect::<Vec<_>>();
+        if implementation == "cp" {
+            abis.push("abi3".to_string());
+        }
+        abis.push("none".to_string());
+
+        let mut tags = vec![];
+
+        for abi in &abis {
+            for platform in &platforms {
+                tags.push((python_tag.to_string(), abi.clone(), platform.clone()));
+            }
+        }
+
+        // The stable ABI of older versions is supported by newer ones.
+        if implementation == "cp" {
+            for older in (2..minor).rev() {
+                for platform in &platforms {
+                    tags.push((
+                        format!("cp3{}", older),
+                        "abi3".to_string(),
+                        platform.clone(),
+                    ));
+                }
+            }
+        }
+
+        let generic_pythons = std::iter::once(format!("py3{}", minor))
+            .chain(std::iter::once("py3".to_string()))
+            .chain((0..minor).rev().map(|older| format!("py3{}", older)))
+            .collect::<Vec<_>>();
+
+        for python in &generic_pythons {
+            for platform in &platforms {
+                tags.push((python.clone(), "none".to_string(), platform.clone()));
+            }
+        }
+
+        tags.push((
+            python_tag.to_string(),
+            "none".to_string(),
+            "any".to_string(),
+        ));
+        for python in &generic_pythons {
+            tags.push((python.clone(), "none".to_string(), "any".to_string()));
+        }
+
+        Ok(Self { tags })
+    }
+
+    /// Construct an instance for a Python distribution.
+    pub fn from_distribution(dist: &dyn PythonDistribution) -> Result<Self> {
+        Self::new(
+            dist.python_tag(),
+            dist.python_abi_tag(),
+            dist.python_platform_compatibility_tag(),
+        )
+    }
+
+    /// Obtain the priority of a wheel, lower being preferred.
+    ///
+    /// Returns `None` if the wheel isn't compatible.
+    pub fn priority(&self, wheel: &WheelFilename) -> Option<usize> {
+        wheel
+            .tags
+            .iter()
+            .filter_map(|tag| self.tags.iter().position(|supported| supported == tag))
+            .min()
+    }
+}
+
+/// Location of a package file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileLocation {
+    Path(PathBuf),
+    Url(Url),
+}
+
+/// A wheel file of a package available to the resolver.
+#[derive(Clone, Debug)]
+pub struct PackageFile {
+    pub filename: String,
+    pub location: FileLocation,
+    /// SHA-256 of the file, if the package source publishes it.
+    pub sha256: Option<String>,
+    /// The `Requires-Python` of the file, if the package source publishes it.
+    pub requires_python: Option<String>,
+    /// URL of the file's core metadata, if the package source serves it.
+    metadata_url: Option<Url>,
+}
+
+#[derive(Deserialize)]
+struct IndexProject {
+    files: Vec<IndexFile>,
+}
+
+#[derive(Deserialize)]
+struct IndexFile {
+    filename: String,
+    url: String,
+    #[serde(default)]
+    hashes: HashMap<String, String>,
+    #[serde(rename = "requires-python")]
+    requires_python: Option<String>,
+    #[serde(rename = "core-metadata")]
+    core_metadata: Option<serde_json::Value>,
+    #[serde(rename = "dist-info-metadata")]
+    dist_info_metadata: Option<serde_json::Value>,
+    yanked: Option<serde_json::Value>,
+}
+
+/// Whether a PEP 691 boolean-or-object field is set.
+fn json_flag(value: &Option<serde_json::Value>) -> bool {
+    match value {
+        None | Some(serde_json::Value::Null) => false,
+        Some(serde_json::Value::Bool(value)) => *value,
+        Some(_) => true,
+    }
+}
+
+fn http_get(url: &Url, accept: Option<&str>) -> Result<Vec<u8>> {
+    let client = get_http_client()?;
+    let mut request = client.get(url.as_str());
+    if let Some(accept) = accept {
+        request = request.header("Accept", accept);
+    }
+
+    let mut response = request
+        .send()
+        .with_context(|| format!("requesting {}", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("HTTP {} requesting {}", response.status(), url));
+    }
+
+    let mut data = vec![];
+    response.read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
+/// Finds package files in a package index and/or local directories.
+///
+/// Indexes must implement the JSON API of PEP 691, which PyPI does.
+/// Responses and files are cached for the lifetime of the instance.
+#[derive(Default)]
+pub struct PackageFinder {
+    index_url: Option<Url>,
+    find_links: Vec<PathBuf>,
+    files: HashMap<String, Vec<PackageFile>>,
+    data: HashMap<String, Vec<u8>>,
+    metadata: HashMap<String, Vec<u8>>,
+}
+
+impl PackageFinder {
+    /// Construct an instance.
+    ///
+    /// `find_links` are directories containing wheel files.
+    pub fn new(index_url: Option<&str>, find_links: Vec<PathBuf>) -> Result<Self> {
+        let index_url = index_url
+            .map(|url| Url::parse(&format!("{}/", url.trim_end_matches('/'))))
+            .transpose()
+            .context("parsing index URL")?;
+
+        Ok(Self {
+            index_url,
+            find_links,
+            ..Default::default()
+        })
+    }
+
+    fn find_index_files(&self, index_url: &Url, name: &str) -> Result<Vec<PackageFile>> {
+        let url = index_url.join(&format!("{}/", name))?;
+
+        let data = match http_get(&url, Some("application/vnd.pypi.simple.v1+json")) {
+            Ok(data) => data,
+            // Packages absent from the index may be in local directories.
+            Err(e) if !self.find_links.is_empty() => {
+                warn!("unable to find {} in index: {:?}", name, e);
+                return Ok(vec![]);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let project: IndexProject = serde_json::from_slice(&data).with_context(|| {
+            format!(
+                "parsing {}; the package index must support the PEP 691 JSON API",
+                url
+            )
+        })?;
+
+        project
+            .files
+            .into_iter()
+            .filter(|file| file.filename.ends_with(".whl") && !json_flag(&file.yanked))
+            .map(|file| {
+                let file_url = url.join(&file.url)?;
+                let metadata_url =
+                    if json_flag(&file.core_metadata) || json_flag(&file.dist_info_metadata) {
+                        let mut metadata_url = file_url.clone();
+                        metadata_url.set_fragment(None);
+                        Some(Url::parse(&format!("{}.metadata", metadata_url))?)
+                    } else {
+                        None
+                    };
+
+                Ok(PackageFile {
+                    filename: file.filename,
+                    location: FileLocation::Url(file_url),
+                    sha256: file.hashes.get("sha256").map(|x| x.to_lowercase()),
+                    requires_python: file.requires_python,
+                    metadata_url,
+                })
+            })
+            .collect()
+    }
+
+    fn find_local_files(&self, name: &str) -> Result<Vec<PackageFile>> {
+        let mut res = vec![];
+
+        for dir in &self.find_links {
+            for entry in
+                std::fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))?
+            {
+                let path = entry?.path();
+                let filename = match path.file_name() {
+                    Some(filename) => filename.to_string_lossy().to_string(),
+                    None => continue,
+                };
+
+                if WheelFilename::parse(&filename)
+                    .map(|wheel| wheel.name)
+                    .as_deref()
+                    == Some(name)
+                {
+                    res.push(PackageFile {
+                        filename,
+                        location: FileLocation::Path(path),
+                        sha256: None,
+                        requires_python: None,
+                        metadata_url: None,
+                    });
+                }
+            }
+        }
+
+        res.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+        Ok(res)
+    }
+
+    /// Find the wheel files of a package.
+    pub fn find_files(&mut self, name: &str) -> Result<&[PackageFile]> {
+        let name = normalize_name(name);
+
+        if !self.files.contains_key(&name) {
+            let mut files = self.find_local_files(&name)?;
+            if let Some(index_url) = &self.index_url {
+                files.extend(self.find_index_files(index_url, &name)?);
+            }
+
+            self.files.insert(name.clone(), files);
+        }
+
+        Ok(&self.files[&name])
+    }
+
+    /// Read the content of a package file, verifying its SHA-256 if known.
+    pub fn read_file(&mut self, file: &PackageFile) -> Result<Vec<u8>> {
+        if let Some(data) = self.data.get(&file.filename) {
+            return Ok(data.clone());
+        }
+
+        let data = match &file.location {
+            FileLocation::Path(path) => {
+                std::fs::read(path).with_context(|| format!("reading {}", path.display()))?
+            }
+            FileLocation::Url(url) => {
+                warn!("downloading {}", url);
+                http_get(url, None)?
+            }
+        };
+
+        if let Some(expected) = &file.sha256 {
+            let actual = hex::encode(sha2::Sha256::digest(&data));
+            if &actual != expected {
+                return Err(anyhow!(
+                    "SHA-256 of {} is {}; expected {}",
+                    file.filename,
+                    actual,
+                    expected
+                ));
+            }
+        }
+
+        self.data.insert(file.filename.clone(), data.clone());
+
+        Ok(data)
+    }
+
+    /// Read the core metadata of a wheel file.
+    ///
+    /// Metadata served separately by the index is used if available, to avoid
+    /// downloading wheels that aren't selected.
+    pub fn read_metadata(&mut self, file: &PackageFile) -> Result<PythonPackageMetadata> {
+        if let Some(url) = &file.metadata_url {
+            if !self.metadata.contains_key(&file.filename) {
+                self.metadata
+                    .insert(file.filename.clone(), http_get(url, None)?);
+            }
+
+            PythonPackageMetadata::from_metadata(&self.metadata[&file.filename])
+        } else {
+            let data = self.read_file(file)?;
+            WheelArchive::from_reader(Cursor::new(data), &file.filename)?.metadata()
+        }
+    }
+}
+
+/// A package pinned by the resolver.
+#[derive(Clone, Debug)]
+pub struct ResolvedPackage {
+    /// Normalized name of the package.
+    pub name: String,
+    pub version: Version,
+    /// The wheel to install.
+    pub file: PackageFile,
+}
+
+/// A package selected during a round of resolution.
+struct Selection {
+    version: Version,
+    file: PackageFile,
+    requirements: Vec<Requirement>,
+    /// Extras whose requirements have been queued.
+    extras: BTreeSet<String>,
+}
+
+/// Resolves requirements for a target environment.
+pub struct Resolver<'a> {
+    finder: &'a mut PackageFinder,
+    env: MarkerEnvironment,
+    tags: CompatibilityTags,
+    python_version: Version,
+}
+
+impl<'a> Resolver<'a> {
+    /// Construct an instance.
+    ///
+    /// `python_version` is the full version of the target interpreter, which
+    /// is compared against the `Requires-Python` of packages.
+    pub fn new(
+        finder: &'a mut PackageFinder,
+        env: MarkerEnvironment,
+        tags: CompatibilityTags,
+        python_version: &str,
+    ) -> Result<Self> {
+        Ok(Self {
+            finder,
+            env,
+            tags,
+            python_version: Version::from_str(python_version)?,
+        })
+    }
+
+    fn requires_python_matches(&self, requires_python: Option<&str>) -> Result<bool> {
+        match requires_python {
+            Some(value) if !value.trim().is_empty() => {
+                Ok(Specifiers::from_str(value)?.contains(&self.python_version))
+            }
+            _ => Ok(true),
+        }
+    }
+
+    /// Select the best wheel of a package satisfying specifiers.
+    fn select(
+        &mut self,
+        name: &str,
+        specifiers: &Specifiers,
+    ) -> Result<Option<(Version, PackageFile, PythonPackageMetadata)>> {
+        let mut candidates = BTreeMap::new();
+
+        for file in self.finder.find_files(name)?.to_vec() {
+            let wheel = match WheelFilename::parse(&file.filename) {
+                Some(wheel) => wheel,
+                None => continue,
+            };
+
+            let priority = match self.tags.priority(&wheel) {
+                Some(priority) => priority,
+                None => continue,
+            };
+
+            if !specifiers.contains(&wheel.version)
+                || !self
+                    .requires_python_matches(file.requires_python.as_deref())
+                    .unwrap_or(true)
+            {
+                continue;
+            }
+
+            let entry = candidates
+                .entry(wheel.version.clone())
+                .or_insert_with(|| (priority, file.clone()));
+            if priority < entry.0 {
+                *entry = (priority, file);
+            }
+        }
+
+        // Pre-releases are only considered if requested or if there are no
+        // final releases.
+        let allow_prereleases = specifiers.allows_prereleases()
+            || candidates.keys().all(|version| version.is_prerelease());
+
+        for (version, (_, file)) in candidates.into_iter().rev() {
+            if version.is_prerelease() && !allow_prereleases {
+                continue;
+            }
+
+            let metadata = self
+                .finder
+                .read_metadata(&file)
+                .with_context(|| format!("reading metadata of {}", file.filename))?;
+
+            if !self.requires_python_matches(metadata.find_first_header("Requires-Python"))? {
+                continue;
+            }
+
+            return Ok(Some((version, file, metadata)));
+        }
+
+        Ok(None)
+    }
+
+    /// Resolve requirements to a set of packages.
+    ///
+    /// The newest compatible version of each package is selected. When a
+    /// selected version turns out to conflict with a requirement found later,
+    /// the requirement is remembered and resolution starts over.
+    pub fn resolve(&mut self, requirements: &[Requirement]) -> Result<Vec<ResolvedPackage>> {
+        let mut learned: BTreeMap<String, Specifiers> = BTreeMap::new();
+
+        'rounds: for _ in 0..MAX_RESOLUTION_ROUNDS {
+            let mut constraints = learned.clone();
+            let mut selections: BTreeMap<String, Selection> = BTreeMap::new();
+            let mut queue = requirements
+                .iter()
+                .map(|requirement| (requirement.clone(), None))
+                .collect::<VecDeque<(Requirement, Option<String>)>>();
+
+            while let Some((requirement, extra)) = queue.pop_front() {
+                if let Some(marker) = &requirement.marker {
+                    let env = match &extra {
+                        Some(extra) => self.env.with_extra(extra),
+                        None => self.env.clone(),
+                    };
+
+                    if !env.evaluate(marker).with_context(|| {
+                        format!("evaluating marker of requirement {}", requirement.name)
+                    })? {
+                        continue;
+                    }
+                }
+
+                let name = requirement.name.clone();
+                constraints
+                    .entry(name.clone())
+                    .or_default()
+                    .extend(&requirement.specifiers);
+
+                if let Some(selection) = selections.get(&name) {
+                    if !requirement.specifiers.contains(&selection.version) {
+                        learned
+                            .entry(name)
+                            .or_default()
+                            .extend(&requirement.specifiers);
+                        continue 'rounds;
+                    }
+                } else {
+                    let (version, file, metadata) =
+                        self.select(&name, &constraints[&name])?.ok_or_else(|| {
+                            anyhow!(
+                                "no wheel of {} matching {} is compatible with the target",
+                                name,
+                                if constraints[&name].is_empty() {
+                                    "any version".to_string()
+                                } else {
+                                    constraints[&name].to_string()
+                                }
+                            )
+                        })?;
+
+                    let requirements = metadata
+                        .find_all_headers("Requires-Dist")
+                        .into_iter()
+                        .map(Requirement::from_str)
+                        .collect::<Result<Vec<_>>>()
+                        .with_context(|| format!("parsing requirements of {}", file.filename))?;
+
+                    // Requirements of extras are queued as extras are requested.
+                    queue.extend(
+                        requirements
+                            .iter()
+                            .map(|requirement| (requirement.clone(), None)),
+                    );
+
+                    selections.insert(
+                        name.clone(),
+                        Selection {
+                            version,
+                            file,
+                            requirements,
+                            extras: BTreeSet::new(),
+                        },
+                    );
+                }
+
+                let selection = selections.get_mut(&name).unwrap();
+                for extra in &requirement.extras {
+                    if selection.extras.insert(extra.clone()) {
+                        queue.extend(
+                            selection
+                                .requirements
+                                .iter()
+                                .filter(|requirement| requirement.marker.is_some())
+                                .map(|requirement| (requirement.clone(), Some(extra.clone()))),
+                        );
+                    }
+                }
+            }
+
+            return Ok(selections
+                .into_iter()
+                .map(|(name, selection)| ResolvedPackage {
+                    name,
+                    version: selection.version,
+                    file: selection.file,
+                })
+                .collect());
+        }
+
+        Err(anyhow!(
+            "unable to resolve requirements after {} attempts",
+            MAX_RESOLUTION_ROUNDS
+        ))
+    }
+}
+
+/// Resolve requirements for a Python distribution and collect resources of the resolved wheels.
+///
+/// If a dependency cache being populated is given, resolved wheels are stored
+/// in it.
+pub fn resolve_python_resources<'a>(
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    finder: &mut PackageFinder,
+    requirements: &[String],
+    cache: Option<&DependencyCache>,
+) -> Result<Vec<PythonResource<'a>>> {
+    let requirements = requirements
+        .iter()
+        .map(|requirement| Requirement::from_str(requirement))
+        .collect::<Result<Vec<_>>>()?;
+
+    let packages = Resolver::new(
+        finder,
+        MarkerEnvironment::from_target(dist.target_triple(), dist.python_version()),
+        CompatibilityTags::from_distribution(dist)?,
+        dist.python_version(),
+    )?
+    .resolve(&requirements)?;
+
+    let mut res = vec![];
+
+    for package in packages {
+        warn!(
+            "resolved {} {} ({})",
+            package.name, package.version, package.file.filename
+        );
+
+        let data = finder.read_file(&package.file)?;
+
+        if let Some(cache) = cache {
+            if cache.mode() == DependencyCacheMode::Populate {
+                cache.store(&package.file.filename, &data)?;
+            }
+        }
+        let wheel = WheelArchive::from_reader(Cursor::new(data), &package.file.filename)?;
+
+        res.extend(wheel.python_resources(
+            dist.cache_tag(),
+            &dist.python_module_suffixes()?,
+            policy.file_scanner_emit_files(),
+            policy.file_scanner_classify_files(),
+        )?);
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, python_packaging::wheel_builder::WheelBuilder};
+
+    #[test]
+    fn test_parse_requirement() -> Result<()> {
+        let requirement = Requirement::from_str(
+            "Requests[Socks, security] (>=2.8.1,<3) ; python_version < '3.11'",
+        )?;
+        assert_eq!(requirement.name, "requests");
+        assert_eq!(requirement.extras, vec!["socks", "security"]);
+        assert_eq!(requirement.specifiers.to_string(), ">=2.8.1,<3");
+        assert_eq!(
+            requirement.marker.as_deref(),
+            Some("python_version < '3.11'")
+        );
+
+        let requirement = Requirement::from_str("zope.interface")?;
+        assert_eq!(requirement.name, "zope-interface");
+        assert!(requirement.specifiers.is_empty());
+
+        assert!(Requirement::from_str("foo @ https://example.com/foo.whl").is_err());
+        assert!(Requirement::from_str(">=1.0").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compatibility_tags() -> Result<()> {
+        let linux = CompatibilityTags::new("cp310", Some("cp310"), "manylinux2014_x86_64")?;
+        let musl = CompatibilityTags::new("cp310", Some("cp310"), "none")?;
+        let macos = CompatibilityTags::new("cp310", Some("cp310"), "macosx_11_0_arm64")?;
+
+        let wheel = |filename: &str| WheelFilename::parse(filename).unwrap();
+
+        let native = wheel("foo-1.0-cp310-cp310-manylinux_2_17_x86_64.manylinux2014_x86_64.whl");
+        let abi3 = wheel("foo-1.0-cp37-abi3-manylinux1_x86_64.whl");
+        let pure = wheel("foo-1.0-py2.py3-none-any.whl");
+
+        assert!(linux.priority(&native).unwrap() < linux.priority(&abi3).unwrap());
+        assert!(linux.priority(&abi3).unwrap() < linux.priority(&pure).unwrap());
+        assert!(linux
+            .priority(&wheel("foo-1.0-cp311-cp311-manylinux2014_x86_64.whl"))
+            .is_none());
+        assert!(linux
+            .priority(&wheel("foo-1.0-cp310-cp310-manylinux_2_28_x86_64.whl"))
+            .is_none());
+        assert!(linux
+            .priority(&wheel("foo-1.0-cp310-cp310-win_amd64.whl"))
+            .is_none());
+
+        assert!(musl.priority(&native).is_none());
+        assert!(musl.priority(&pure).is_some());
+
+        assert!(macos
+            .priority(&wheel("foo-1.0-cp310-cp310-macosx_10_9_universal2.whl"))
+            .is_some());
+        assert!(macos
+            .priority(&wheel("foo-1.0-cp310-cp310-macosx_10_9_x86_64.whl"))
+            .is_none());
+
+        Ok(())
+    }
+
+    fn write_wheel(
+        dir: &std::path::Path,
+        name: &str,
+        version: &str,
+        tag: &str,
+        requires: &[&str],
+    ) -> Result<()> {
+        let mut builder = WheelBuilder::new(name, version);
+        builder.set_tag(tag)?;
+        builder.add_file_dist_info(
+            "METADATA",
+            format!(
+                "Metadata-Version: 2.1\nName: {}\nVersion: {}\n{}",
+                name,
+                version,
+                requires
+                    .iter()
+                    .map(|requirement| format!("Requires-Dist: {}\n", requirement))
+                    .collect::<String>()
+            )
+            .as_bytes(),
+        )?;
+        builder.write_wheel_into_directory(dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let dir = temp_dir.path();
+
+        write_wheel(
+            dir,
+            "app",
+            "1.0",
+            "py3-none-any",
+            &[
+                "lib>=1.0",
+                "winonly; sys_platform == 'win32'",
+                "other",
+                "extra-dep; extra == 'fancy'",
+            ],
+        )?;
+        write_wheel(dir, "lib", "1.0", "py3-none-any", &[])?;
+        write_wheel(dir, "lib", "2.0", "py3-none-any", &["helper>=1.0"])?;
+        write_wheel(dir, "lib", "3.0b1", "py3-none-any", &[])?;
+        write_wheel(dir, "lib", "4.0", "cp310-cp310-win_amd64", &[])?;
+        write_wheel(dir, "other", "1.0", "py3-none-any", &["helper<2"])?;
+        write_wheel(dir, "helper", "1.0", "py3-none-any", &[])?;
+        write_wheel(dir, "helper", "2.0", "py3-none-any", &[])?;
+        write_wheel(dir, "extra_dep", "1.0", "py3-none-any", &[])?;
+
+        let mut finder = PackageFinder::new(None, vec![dir.to_path_buf()])?;
+        let mut resolver = Resolver::new(
+            &mut finder,
+            MarkerEnvironment::from_target("x86_64-unknown-linux-gnu", "3.10.8"),
+            CompatibilityTags::new("cp310", Some("cp310"), "manylinux2014_x86_64")?,
+            "3.10.8",
+        )?;
+
+        let resolved = |packages: Vec<ResolvedPackage>| {
+            packages
+                .iter()
+                .map(|package| format!("{}=={}", package.name, package.version))
+                .collect::<Vec<_>>()
+        };
+
+        // helper 2.0 is selected for lib before the `helper<2` of other is
+        // seen. So resolution has to start over.
+        assert_eq!(
+            resolved(resolver.resolve(&[Requirement::from_str("app")?])?),
+            vec!["app==1.0", "helper==1.0", "lib==2.0", "other==1.0"]
+        );
+        assert_eq!(
+            resolved(resolver.resolve(&[Requirement::from_str("app[fancy]")?])?),
+            vec![
+                "app==1.0",
+                "extra-dep==1.0",
+                "helper==1.0",
+                "lib==2.0",
+                "other==1.0"
+            ]
+        );
+        assert_eq!(
+            resolved(resolver.resolve(&[Requirement::from_str("lib>=3.0b1")?])?),
+            vec!["lib==3.0b1"]
+        );
+        assert!(resolver
+            .resolve(&[Requirement::from_str("lib>=4")?])
+            .is_err());
+        assert!(resolver
+            .resolve(&[
+                Requirement::from_str("lib==1.0")?,
+                Requirement::from_str("lib==2.0")?
+            ])
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -5,10 +5,12 @@
 use {
     super::{
         binary::{
-            LibpythonLinkMode, PackedResourcesLoadMode, PythonBinaryBuilder,
+            CargoBuildSettings, LibpythonLinkMode, PackedResourcesLoadMode, PythonBinaryBuilder,
             ResourceAddCollectionContextCallback, WindowsRuntimeDllsMode,
         },
+        conda::{read_conda_explicit_lock, read_conda_package},
         config::{PyembedPackedResourcesSource, PyembedPythonInterpreterConfig},
+        dependency_cache::{DependencyCache, DependencyCacheMode},
         distribution::{AppleSdkInfo, BinaryLibpythonLinkMode, PythonDistribution},
         embedding::{
             EmbeddedPythonContext, LibpythonLinkSettings, LinkSharedLibraryPath,
@@ -17,8 +19,10 @@ use {
         filtering::{filter_btreemap, resolve_resource_names_from_files},
         libpython::link_libpython,
         packaging_tool::{
-            find_resources, pip_download, pip_install, read_virtualenv, setup_py_install,
+            find_resources, pip_download, pip_download_files, pip_install, read_virtualenv,
+            setup_py_install,
         },
+        resolver::{resolve_python_resources, PackageFinder},
         standalone_distribution::StandaloneDistribution,
     },
     crate::environment::Environment,
@@ -60,6 +64,29 @@ static LINUX_IGNORE_LIBRARIES: Lazy<Vec<&'static str>> = Lazy::new(|| vec!["dl",
 /// Libraries that we should not link against on macOS.
 static MACOS_IGNORE_LIBRARIES: Lazy<Vec<&'static str>> = Lazy::new(|| vec!["dl", "m"]);
 
+/// Read a hex encoded 32 byte key for signing or encrypting packed resources.
+fn read_key_file(path: &Path, purpose: &str) -> Result<[u8; 32]> {
+    let data = std::fs::read_to_string(path).with_context(|| {
+        format!(
+            "reading packed resources {} key {}",
+            purpose,
+            path.display()
+        )
+    })?;
+
+    let mut key = [0; 32];
+    hex::decode_to_slice(data.trim(), &mut key).map_err(|e| {
+        anyhow!(
+            "packed resources {} key {} is not a hex encoded 32 byte key: {}",
+            purpose,
+            path.display(),
+            e
+        )
+    })?;
+
+    Ok(key)
+}
+
 /// Obtain a list of ignored libraries for a given target triple.
 fn ignored_libraries_for_target(target_triple: &str) -> Vec<&'static str> {
     if crate::environment::LINUX_TARGET_TRIPLES.contains(&target_triple) {
@@ -105,6 +132,9 @@ pub struct StandalonePythonExecutableBuilder {
     /// How packed resources will be loaded at run-time.
     resources_load_mode: PackedResourcesLoadMode,
 
+    /// Whether to load packed resources injected into the built binary.
+    resources_load_injected: bool,
+
     /// Holds state necessary to link libpython.
     core_build_context: LibPythonBuildContext,
 
@@ -131,6 +161,18 @@ pub struct StandalonePythonExecutableBuilder {
 
     /// Describes how Windows runtime DLLs should be handled during builds.
     windows_runtime_dlls_mode: WindowsRuntimeDllsMode,
+
+    /// Whether to build in development mode.
+    dev_mode: bool,
+
+    /// Directories and the packages to import from them in development mode.
+    dev_overlays: Vec<(PathBuf, Vec<String>)>,
+
+    /// Cache of package dependencies that packaging operations use.
+    dependency_cache: Option<DependencyCache>,
+
+    /// Settings for the cargo build of the generated Rust project.
+    cargo_build_settings: CargoBuildSettings,
 }
 
 impl StandalonePythonExecutableBuilder {
@@ -222,6 +264,7 @@ impl StandalonePythonExecutableBuilder {
             resources_load_mode: PackedResourcesLoadMode::EmbeddedInBinary(
                 "packed-resources".to_string(),
             ),
+            resources_load_injected: false,
             core_build_context: LibPythonBuildContext::default(),
             extension_build_contexts: BTreeMap::new(),
             config,
@@ -230,6 +273,10 @@ impl StandalonePythonExecutableBuilder {
             windows_subsystem: "console".to_string(),
             tcl_files_path: None,
             windows_runtime_dlls_mode: WindowsRuntimeDllsMode::WhenPresent,
+            dev_mode: false,
+            dev_overlays: vec![],
+            dependency_cache: None,
+            cargo_build_settings: CargoBuildSettings::default(),
         });
 
         builder.add_distribution_core_state()?;
@@ -237,6 +284,14 @@ impl StandalonePythonExecutableBuilder {
         Ok(builder)
     }
 
+    /// Whether a resource belongs to a package of a development overlay.
+    fn is_dev_overlay_resource(&self, name: &str) -> bool {
+        self.dev_overlays
+            .iter()
+            .flat_map(|(_, packages)| packages.iter())
+            .any(|package| name == package || name.starts_with(&format!("{}.", package)))
+    }
+
     fn add_distribution_core_state(&mut self) -> Result<()> {
         self.core_build_context.inittab_cflags =
             Some(self.target_distribution.inittab_cflags.clone());
@@ -317,13 +372,24 @@ impl StandalonePythonExecutableBuilder {
                     link_contexts.push(c);
                 }
 
-                let library_info = link_libpython(
-                    env,
-                    &LibPythonBuildContext::merge(&link_contexts),
+                let context = LibPythonBuildContext::merge(&link_contexts);
+
+                let library_info = env.artifact_cache().libpython(
+                    &context,
                     &self.host_triple,
                     &self.target_triple,
                     opt_level,
                     self.apple_sdk_info(),
+                    || {
+                        link_libpython(
+                            env,
+                            &context,
+                            &self.host_triple,
+                            &self.target_triple,
+                            opt_level,
+                            self.apple_sdk_info(),
+                        )
+                    },
                 )?;
 
                 Ok(LinkStaticLibraryData {
@@ -371,6 +437,40 @@ impl StandalonePythonExecutableBuilder {
         }
     }
 
+    /// Ensure the distribution doesn't use the free-threaded ABI.
+    fn validate_not_free_threaded(&self) -> Result<()> {
+        // pyembed's PyO3 version doesn't support the free-threaded ABI. Embedding
+        // such a distribution would produce a binary corrupting memory.
+        if self.target_distribution.is_free_threaded() {
+            Err(anyhow!(
+                "free-threaded Python distributions can't be embedded: the PyO3 version used by pyembed doesn't support their ABI"
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn validate_injection_settings(&self) -> Result<()> {
+        if !self.resources_load_injected {
+            return Ok(());
+        }
+
+        // Resources are injected by appending them to the binary. codesign
+        // rejects appended data and an appended zip archive must also be at the
+        // end of the binary.
+        if crate::environment::MACOS_TARGET_TRIPLES.contains(&self.target_triple.as_str()) {
+            Err(anyhow!(
+                "packed_resources_load_injected isn't supported for macOS targets: code signing doesn't support data appended to Mach-O binaries"
+            ))
+        } else if self.config.appended_zip_importer {
+            Err(anyhow!(
+                "packed_resources_load_injected and appended_zip_importer can't both be enabled"
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Resolves Windows runtime DLLs file needed for this binary given current settings.
     fn resolve_windows_runtime_dll_files(&self) -> Result<FileManifest> {
         let mut manifest = FileManifest::default();
@@ -429,6 +529,20 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         &self.target_triple
     }
 
+    fn set_target_triple(&mut self, value: &str) -> Result<()> {
+        if value != self.target_distribution.target_triple() {
+            return Err(anyhow!(
+                "the Python distribution targets {}; use a distribution for {} to target it",
+                self.target_distribution.target_triple(),
+                value
+            ));
+        }
+
+        self.target_triple = value.to_string();
+
+        Ok(())
+    }
+
     fn vc_runtime_requirements(&self) -> Option<(String, VcRedistributablePlatform)> {
         let platform = if self.target_triple.starts_with("i686-") {
             VcRedistributablePlatform::X86
@@ -451,6 +565,10 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.target_distribution.cache_tag()
     }
 
+    fn python_version(&self) -> &str {
+        self.target_distribution.python_version()
+    }
+
     fn python_packaging_policy(&self) -> &PythonPackagingPolicy {
         &self.packaging_policy
     }
@@ -521,6 +639,58 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.resources_load_mode = load_mode;
     }
 
+    fn packed_resources_load_injected(&self) -> bool {
+        self.resources_load_injected
+    }
+
+    fn set_packed_resources_load_injected(&mut self, value: bool) {
+        self.resources_load_injected = value;
+    }
+
+    fn dev_mode(&self) -> bool {
+        self.dev_mode
+    }
+
+    fn set_dev_mode(&mut self, value: bool) {
+        self.dev_mode = value;
+    }
+
+    fn cargo_build_settings(&self) -> &CargoBuildSettings {
+        &self.cargo_build_settings
+    }
+
+    fn set_cargo_build_settings(&mut self, value: CargoBuildSettings) {
+        self.cargo_build_settings = value;
+    }
+
+    fn set_run_module(&mut self, module: &str) {
+        self.config.config.run_command = None;
+        self.config.config.run_filename = None;
+        self.config.config.run_module = Some(module.to_string());
+    }
+
+    fn add_dev_overlay(&mut self, path: &Path, packages: &[String]) -> Result<()> {
+        if !path.is_absolute() {
+            return Err(anyhow!(
+                "development overlay path must be absolute: {}",
+                path.display()
+            ));
+        }
+
+        self.dev_overlays
+            .push((path.to_path_buf(), packages.to_vec()));
+
+        Ok(())
+    }
+
+    fn dependency_cache(&self) -> Option<&DependencyCache> {
+        self.dependency_cache.as_ref()
+    }
+
+    fn set_dependency_cache(&mut self, cache: Option<DependencyCache>) {
+        self.dependency_cache = cache;
+    }
+
     fn iter_resources<'a>(
         &'a self,
     ) -> Box<dyn Iterator<Item = (&'a String, &'a PrePackagedResource)> + 'a> {
@@ -545,13 +715,35 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         verbose: bool,
         args: &[String],
     ) -> Result<Vec<PythonResource>> {
+        let args = if let Some(cache) = &self.dependency_cache {
+            if cache.mode() == DependencyCacheMode::Populate {
+                pip_download_files(
+                    &*self.host_distribution,
+                    Some(&*self.target_distribution),
+                    self.python_packaging_policy(),
+                    verbose,
+                    args,
+                    cache.path(),
+                )
+                .context("downloading into dependency cache")?;
+            }
+
+            cache
+                .pip_args()
+                .into_iter()
+                .chain(args.iter().cloned())
+                .collect()
+        } else {
+            args.to_vec()
+        };
+
         let resources = pip_download(
             env,
             &*self.host_distribution,
             &*self.target_distribution,
             self.python_packaging_policy(),
             verbose,
-            args,
+            &args,
         )
         .context("calling pip download")?;
 
@@ -568,13 +760,35 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         install_args: &[String],
         extra_envs: &HashMap<String, String>,
     ) -> Result<Vec<PythonResource>> {
+        let install_args = if let Some(cache) = &self.dependency_cache {
+            if cache.mode() == DependencyCacheMode::Populate {
+                pip_download_files(
+                    &*self.target_distribution,
+                    None,
+                    self.python_packaging_policy(),
+                    verbose,
+                    install_args,
+                    cache.path(),
+                )
+                .context("downloading into dependency cache")?;
+            }
+
+            cache
+                .pip_args()
+                .into_iter()
+                .chain(install_args.iter().cloned())
+                .collect()
+        } else {
+            install_args.to_vec()
+        };
+
         let resources = pip_install(
             env,
             &*self.target_distribution,
             self.python_packaging_policy(),
             self.link_mode,
             verbose,
-            install_args,
+            &install_args,
             extra_envs,
         )
         .context("calling pip install")?;
@@ -627,6 +841,68 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(resources)
     }
 
+    fn read_conda_package(&mut self, path: &Path) -> Result<Vec<PythonResource>> {
+        let resources = read_conda_package(
+            &*self.target_distribution,
+            self.python_packaging_policy(),
+            path,
+        )
+        .context("reading conda package")?;
+
+        self.index_package_license_info_from_resources(&resources)
+            .context("indexing package license metadata")?;
+
+        Ok(resources)
+    }
+
+    fn read_conda_lock(&mut self, path: &Path) -> Result<Vec<PythonResource>> {
+        let resources = read_conda_explicit_lock(
+            &*self.target_distribution,
+            self.python_packaging_policy(),
+            path,
+        )
+        .context("reading conda lock file")?;
+
+        self.index_package_license_info_from_resources(&resources)
+            .context("indexing package license metadata")?;
+
+        Ok(resources)
+    }
+
+    fn resolve_requirements(
+        &mut self,
+        requirements: &[String],
+        index_url: Option<&str>,
+        find_links: &[PathBuf],
+    ) -> Result<Vec<PythonResource>> {
+        let mut find_links = find_links.to_vec();
+        let mut index_url = index_url;
+
+        if let Some(cache) = &self.dependency_cache {
+            find_links.push(cache.path().to_path_buf());
+
+            if cache.is_offline() {
+                index_url = None;
+            }
+        }
+
+        let mut finder = PackageFinder::new(index_url, find_links)?;
+
+        let resources = resolve_python_resources(
+            &*self.target_distribution,
+            self.python_packaging_policy(),
+            &mut finder,
+            requirements,
+            self.dependency_cache.as_ref(),
+        )
+        .context("resolving requirements")?;
+
+        self.index_package_license_info_from_resources(&resources)
+            .context("indexing package license metadata")?;
+
+        Ok(resources)
+    }
+
     fn setup_py_install(
         &mut self,
         env: &Environment,
@@ -835,6 +1111,10 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             .add_file_data_with_context(file, &add_context)
     }
 
+    fn add_data_file(&mut self, name: &str, entry: &FileEntry) -> Result<Vec<AddResourceAction>> {
+        self.resources_collector.add_data_file(name, entry)
+    }
+
     fn filter_resources_from_files(
         &mut self,
         files: &[&Path],
@@ -871,6 +1151,37 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.config.allocator_backend == MemoryAllocatorBackend::Snmalloc
     }
 
+    fn pyembed_resources_features(&self) -> Vec<&'static str> {
+        let mut res = vec![];
+
+        if self
+            .packaging_policy
+            .packed_resources_zstd_level()
+            .is_some()
+        {
+            res.push("pyembed/resources-zstd");
+        }
+        if self
+            .packaging_policy
+            .packed_resources_encryption_key_path()
+            .is_some()
+        {
+            res.push("pyembed/resources-encryption");
+        }
+        if self.config.verify_resources {
+            res.push("pyembed/resources-sha256");
+        }
+        if self
+            .packaging_policy
+            .packed_resources_signing_key_path()
+            .is_some()
+        {
+            res.push("pyembed/resources-signatures");
+        }
+
+        res
+    }
+
     fn licensed_components(&self) -> Result<LicensedComponents> {
         Ok(self.resources_collector.normalized_licensed_components())
     }
@@ -884,6 +1195,9 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         env: &Environment,
         opt_level: &str,
     ) -> Result<EmbeddedPythonContext> {
+        self.validate_not_free_threaded()?;
+        self.validate_injection_settings()?;
+
         let mut file_seen = false;
         for module in self.resources_collector.find_dunder_file()? {
             file_seen = true;
@@ -899,10 +1213,37 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         let compiled_resources = {
             let temp_dir = env.temporary_directory("pyoxidizer-bytecode-compile")?;
             let mut compiler = BytecodeCompiler::new(self.host_python_exe_path(), temp_dir.path())?;
-            let resources = self.resources_collector.compile_resources(&mut compiler)?;
+            let mut compiler = env
+                .artifact_cache()
+                .bytecode_compiler(&mut compiler, self.host_python_exe_path());
+            let mut resources = self.resources_collector.compile_resources(&mut compiler)?;
+
+            if self.dev_mode {
+                resources
+                    .resources
+                    .retain(|name, _| !self.is_dev_overlay_resource(name));
+            }
 
             temp_dir.close().context("closing temporary directory")?;
 
+            if let Some(level) = self.packaging_policy.packed_resources_zstd_level() {
+                resources
+                    .zstd_compress(level)
+                    .context("compressing packed resources")?;
+            }
+
+            if let Some(path) = self.packaging_policy.packed_resources_encryption_key_path() {
+                resources
+                    .encrypt(&read_key_file(path, "encryption")?)
+                    .context("encrypting packed resources")?;
+            }
+
+            resources.add_sha256_digests();
+
+            if let Some(path) = self.packaging_policy.packed_resources_signing_key_path() {
+                resources.signing_key = Some(read_key_file(path, "signing")?);
+            }
+
             resources
         };
 
@@ -912,6 +1253,11 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
 
         let mut config = self.config.clone();
 
+        if let Some(key) = &compiled_resources.signing_key {
+            config.packed_resources_public_key =
+                Some(python_packed_resources::ed25519_public_key(key)?);
+        }
+
         match &self.resources_load_mode {
             PackedResourcesLoadMode::None => {}
             PackedResourcesLoadMode::EmbeddedInBinary(filename) => {
@@ -938,6 +1284,36 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             }
         }
 
+        // Injected resources are loaded last so they override built resources.
+        if self.resources_load_injected {
+            config
+                .packed_resources
+                .push(PyembedPackedResourcesSource::CurrentExecutable);
+        }
+
+        // Development binaries import overlay packages from their source
+        // directories, which requires the standard filesystem importer.
+        if self.dev_mode && !self.dev_overlays.is_empty() {
+            for (path, packages) in &self.dev_overlays {
+                warn!(
+                    "development mode: importing {} from {}",
+                    packages.join(", "),
+                    path.display()
+                );
+            }
+            warn!("development binaries depend on these paths; do not distribute them");
+
+            let mut paths = self
+                .dev_overlays
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>();
+            paths.extend(config.config.module_search_paths.take().unwrap_or_default());
+
+            config.config.module_search_paths = Some(paths);
+            config.filesystem_importer = true;
+        }
+
         let link_settings = self.resolve_python_link_settings(env, opt_level)?;
 
         if self.link_mode == LibpythonLinkMode::Dynamic {
@@ -1332,6 +1708,28 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_load_injected_resources() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.resources_load_injected = true;
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+
+        assert_eq!(
+            &embedded.config.packed_resources,
+            &vec![
+                PyembedPackedResourcesSource::MemoryIncludeBytes("packed-resources".into()),
+                PyembedPackedResourcesSource::CurrentExecutable,
+            ]
+        );
+
+        exe.config.appended_zip_importer = true;
+        assert!(exe.to_embedded_python_context(&get_env()?, "0").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_memory_mapped_file_resources() -> Result<()> {
         let options = StandalonePythonExecutableBuilderOptions::default();
@@ -1357,6 +1755,72 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_dev_overlay() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        let overlay_path = std::env::current_dir()?.join("src");
+        exe.add_dev_overlay(&overlay_path, &["email".to_string()])?;
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+        let resources = &embedded.pending_resources[0].0.resources;
+        assert!(resources.contains_key("email.parser"));
+        assert!(!embedded.config.filesystem_importer);
+
+        exe.set_dev_mode(true);
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+        let resources = &embedded.pending_resources[0].0.resources;
+        assert!(!resources.contains_key("email"));
+        assert!(!resources.contains_key("email.parser"));
+        assert!(resources.contains_key("json"));
+        assert_eq!(
+            embedded.config.config.module_search_paths,
+            Some(vec![overlay_path])
+        );
+        assert!(embedded.config.filesystem_importer);
+
+        assert!(exe
+            .add_dev_overlay(Path::new("relative"), &["foo".to_string()])
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_run_module() -> Result<()> {
+        let mut options = StandalonePythonExecutableBuilderOptions::default();
+        options.config.config.run_command = Some("print('hello')".to_string());
+        let mut exe = options.new_builder()?;
+
+        exe.set_run_module("pytest");
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+        assert_eq!(embedded.config.config.run_command, None);
+        assert_eq!(
+            embedded.config.config.run_module,
+            Some("pytest".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_target_triple() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        let distribution_triple = exe.target_distribution.target_triple().to_string();
+        exe.set_target_triple(&distribution_triple)?;
+        assert_eq!(exe.target_triple(), distribution_triple);
+
+        assert!(exe.set_target_triple("riscv64gc-unknown-none-elf").is_err());
+        assert_eq!(exe.target_triple(), distribution_triple);
+
+        Ok(())
+    }
+
     #[test]
     fn test_minimal_extensions_present() -> Result<()> {
         let options = StandalonePythonExecutableBuilderOptions::default();
@@ -2589,6 +3053,7 @@ pub mod tests {
             );
 
             let lib_suffix = match *target {
+                "aarch64-pc-windows-msvc" => "-arm64",
                 "i686-pc-windows-msvc" => "",
                 "x86_64-pc-windows-msvc" => "-x64",
                 _ => panic!("unexpected target: {}", target),
@@ -3039,6 +3504,7 @@ pub mod tests {
 
             if dist.target_triple().contains("windows") && dist.libpython_shared_library.is_some() {
                 let platform = match dist.target_triple() {
+                    "aarch64-pc-windows-msvc" => VcRedistributablePlatform::Arm64,
                     "i686-pc-windows-msvc" => VcRedistributablePlatform::X86,
                     "x86_64-pc-windows-msvc" => VcRedistributablePlatform::X64,
                     triple => {
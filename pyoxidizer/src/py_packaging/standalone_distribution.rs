@@ -256,7 +256,7 @@ fn parse_python_json_from_distribution(dist_dir: &Path) -> Result<PythonJsonMain
     parse_python_json(&python_json_path)
 }
 
-fn parse_python_major_minor_version(version: &str) -> String {
+pub fn parse_python_major_minor_version(version: &str) -> String {
     let mut at_least_minor_version = String::from(version);
     if !version.contains(".") {
         at_least_minor_version.push_str(".0");
@@ -1154,6 +1154,7 @@ impl PythonDistribution for StandaloneDistribution {
             "macosx-10.9-x86_64" => "macosx_10_9_x86_64",
             "macosx-11.0-arm64" => "macosx_11_0_arm64",
             "win-amd64" => "win_amd64",
+            "win-arm64" => "win_arm64",
             "win32" => "win32",
             p => panic!("unsupported Python platform: {}", p),
         }
@@ -1196,7 +1197,12 @@ impl PythonDistribution for StandaloneDistribution {
 
         // In-memory shared library loading is brittle. Disable this configuration
         // even if supported because it leads to pain.
-        if self.supports_in_memory_shared_library_loading() {
+        //
+        // The filesystem fallback predates in-memory loading on Linux. Keep it
+        // Windows only so Linux defaults don't change.
+        if self.supports_in_memory_shared_library_loading()
+            && self.target_triple.contains("pc-windows")
+        {
             policy.set_resources_location(ConcreteResourceLocation::InMemory);
             policy.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
                 "lib".to_string(),
@@ -1355,11 +1361,12 @@ impl PythonDistribution for StandaloneDistribution {
 
     /// Determines whether dynamically linked extension modules can be loaded from memory.
     fn supports_in_memory_shared_library_loading(&self) -> bool {
-        // Loading from memory is only supported on Windows where symbols are
-        // declspec(dllexport) and the distribution is capable of loading
-        // shared library extensions.
-        self.target_triple.contains("pc-windows")
-            && self.python_symbol_visibility == "dllexport"
+        // Loading from memory is supported on Windows where symbols are
+        // declspec(dllexport) and on Linux via memfd_create(). In both cases,
+        // the distribution must be capable of loading shared library extensions.
+        ((self.target_triple.contains("pc-windows")
+            && self.python_symbol_visibility == "dllexport")
+            || self.target_triple.contains("-linux-"))
             && self
                 .extension_module_loading
                 .contains(&"shared-library".to_string())
@@ -0,0 +1,343 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Interaction with uv lock files.
+
+A `uv.lock` file records a dependency graph covering all platforms: every
+package with its exact version and the hashes of its files, plus the
+dependencies of each package with the environment markers they apply in.
+We walk this graph from the projects of the lock file for a target
+environment to find the packages to install.
+*/
+
+use {
+    super::markers::MarkerEnvironment,
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    std::collections::{BTreeMap, BTreeSet, VecDeque},
+};
+
+/// URL of the Python Package Index, which pip uses by default.
+const PYPI_URL: &str = "https://pypi.org/simple";
+
+#[derive(Deserialize)]
+struct UvLock {
+    #[serde(default)]
+    package: Vec<UvPackage>,
+}
+
+#[derive(Deserialize)]
+struct UvPackage {
+    name: String,
+    version: Option<String>,
+    source: UvSource,
+    #[serde(default)]
+    dependencies: Vec<UvDependency>,
+    #[serde(rename = "optional-dependencies", default)]
+    optional_dependencies: BTreeMap<String, Vec<UvDependency>>,
+    #[serde(rename = "dev-dependencies", default)]
+    dev_dependencies: BTreeMap<String, Vec<UvDependency>>,
+    sdist: Option<UvArtifact>,
+    #[serde(default)]
+    wheels: Vec<UvArtifact>,
+}
+
+#[derive(Deserialize)]
+struct UvSource {
+    registry: Option<String>,
+    editable: Option<String>,
+    #[serde(rename = "virtual")]
+    virtual_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UvDependency {
+    name: String,
+    /// Version of the dependency if the lock file has multiple versions of it.
+    version: Option<String>,
+    marker: Option<String>,
+    #[serde(default)]
+    extra: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct UvArtifact {
+    hash: Option<String>,
+}
+
+impl UvPackage {
+    /// Whether the package is a project of the lock file rather than a dependency.
+    fn is_project(&self) -> bool {
+        self.source.editable.is_some() || self.source.virtual_path.is_some()
+    }
+}
+
+/// Produce pip requirements for the packages of a uv lock file.
+///
+/// Packages are the dependencies, direct or indirect, of the projects of the
+/// lock file in the environment `env`, including the dependencies in the
+/// `groups` development dependency groups of the projects. Projects
+/// themselves aren't included. Every requirement pins the exact version of
+/// the package and the hashes of its files. So the requirements should be
+/// installed with `--no-deps`.
+///
+/// Packages not from a package index are rejected because their content
+/// can't be pinned by a hash.
+pub fn uv_lock_requirements(
+    data: &str,
+    groups: &[String],
+    env: &MarkerEnvironment,
+) -> Result<String> {
+    let lock: UvLock = toml::from_str(data).context("parsing uv lock file")?;
+
+    let find_package = |dependency: &UvDependency| -> Result<usize> {
+        let mut candidates = lock.package.iter().enumerate().filter(|(_, package)| {
+            package.name == dependency.name
+                && (dependency.version.is_none() || package.version == dependency.version)
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some((index, _)), None) => Ok(index),
+            (None, _) => Err(anyhow!("lock file has no package {}", dependency.name)),
+            (Some(_), Some(_)) => Err(anyhow!(
+                "lock file has multiple versions of package {}",
+                dependency.name
+            )),
+        }
+    };
+
+    let mut queue = VecDeque::new();
+    let mut unused_groups = groups.iter().collect::<BTreeSet<_>>();
+
+    for package in lock.package.iter().filter(|package| package.is_project()) {
+        queue.extend(package.dependencies.iter());
+
+        for group in groups {
+            if let Some(dependencies) = package.dev_dependencies.get(group) {
+                unused_groups.remove(group);
+                queue.extend(dependencies.iter());
+            }
+        }
+    }
+
+    if let Some(group) = unused_groups.into_iter().next() {
+        return Err(anyhow!("lock file has no dependency group {}", group));
+    }
+
+    // Packages are visited once for their dependencies and once for the
+    // dependencies of each extra.
+    let mut installed = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+
+    while let Some(dependency) = queue.pop_front() {
+        if let Some(marker) = &dependency.marker {
+            if !env
+                .evaluate(marker)
+                .with_context(|| format!("evaluating marker of dependency {}", dependency.name))?
+            {
+                continue;
+            }
+        }
+
+        let index = find_package(dependency)?;
+        let package = &lock.package[index];
+
+        if visited.insert((index, None)) {
+            installed.insert(index);
+            queue.extend(package.dependencies.iter());
+        }
+
+        for extra in &dependency.extra {
+            if visited.insert((index, Some(extra.as_str()))) {
+                queue.extend(
+                    package
+                        .optional_dependencies
+                        .get(extra)
+                        .into_iter()
+                        .flatten(),
+                );
+            }
+        }
+    }
+
+    let mut index_urls = BTreeSet::new();
+    let mut requirements = vec![];
+
+    for package in installed.into_iter().map(|index| &lock.package[index]) {
+        if package.is_project() {
+            continue;
+        }
+
+        let registry = package.source.registry.as_ref().ok_or_else(|| {
+            anyhow!(
+                "package {} is not from a package index; only packages from an index are supported",
+                package.name
+            )
+        })?;
+
+        if registry.trim_end_matches('/') != PYPI_URL {
+            index_urls.insert(registry.as_str());
+        }
+
+        let version = package
+            .version
+            .as_ref()
+            .ok_or_else(|| anyhow!("package {} has no version", package.name))?;
+
+        let hashes = package
+            .sdist
+            .iter()
+            .chain(package.wheels.iter())
+            .filter_map(|artifact| artifact.hash.as_ref())
+            .collect::<Vec<_>>();
+
+        if hashes.is_empty() {
+            return Err(anyhow!(
+                "package {} has no pinned file hashes",
+                package.name
+            ));
+        }
+
+        let mut requirement = format!("{}=={}", package.name, version);
+        for hash in hashes {
+            requirement.push_str(&format!(" --hash={}", hash));
+        }
+
+        requirements.push(requirement);
+    }
+
+    requirements.sort();
+
+    let mut res = String::new();
+    for url in index_urls {
+        res.push_str(&format!("--extra-index-url {}\n", url));
+    }
+    for requirement in requirements {
+        res.push_str(&requirement);
+        res.push('\n');
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"
+version = 1
+requires-python = ">=3.8"
+
+[[package]]
+name = "app"
+version = "0.1.0"
+source = { editable = "." }
+dependencies = [
+    { name = "click" },
+    { name = "requests", extra = ["socks"] },
+]
+
+[package.dev-dependencies]
+dev = [
+    { name = "pytest" },
+]
+
+[[package]]
+name = "click"
+version = "8.1.7"
+source = { registry = "https://pypi.org/simple" }
+dependencies = [
+    { name = "colorama", marker = "platform_system == 'Windows'" },
+]
+sdist = { url = "https://files.example.com/click-8.1.7.tar.gz", hash = "sha256:aa", size = 1 }
+wheels = [
+    { url = "https://files.example.com/click-8.1.7-py3-none-any.whl", hash = "sha256:bb", size = 1 },
+]
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+source = { registry = "https://pypi.org/simple" }
+wheels = [
+    { url = "https://files.example.com/colorama-0.4.6-py2.py3-none-any.whl", hash = "sha256:cc", size = 1 },
+]
+
+[[package]]
+name = "pysocks"
+version = "1.7.1"
+source = { registry = "https://example.com/simple" }
+wheels = [
+    { url = "https://files.example.com/PySocks-1.7.1-py3-none-any.whl", hash = "sha256:dd", size = 1 },
+]
+
+[[package]]
+name = "pytest"
+version = "8.3.3"
+source = { registry = "https://pypi.org/simple" }
+wheels = [
+    { url = "https://files.example.com/pytest-8.3.3-py3-none-any.whl", hash = "sha256:ee", size = 1 },
+]
+
+[[package]]
+name = "requests"
+version = "2.32.3"
+source = { registry = "https://pypi.org/simple" }
+wheels = [
+    { url = "https://files.example.com/requests-2.32.3-py3-none-any.whl", hash = "sha256:ff", size = 1 },
+]
+
+[package.optional-dependencies]
+socks = [
+    { name = "pysocks" },
+]
+"#;
+
+    #[test]
+    fn test_requirements() -> Result<()> {
+        let linux = MarkerEnvironment::from_target("x86_64-unknown-linux-gnu", "3.10.8");
+        let windows = MarkerEnvironment::from_target("x86_64-pc-windows-msvc", "3.10.8");
+
+        assert_eq!(
+            uv_lock_requirements(LOCK, &[], &linux)?,
+            "--extra-index-url https://example.com/simple\n\
+             click==8.1.7 --hash=sha256:aa --hash=sha256:bb\n\
+             pysocks==1.7.1 --hash=sha256:dd\n\
+             requests==2.32.3 --hash=sha256:ff\n"
+        );
+        assert_eq!(
+            uv_lock_requirements(LOCK, &["dev".to_string()], &windows)?,
+            "--extra-index-url https://example.com/simple\n\
+             click==8.1.7 --hash=sha256:aa --hash=sha256:bb\n\
+             colorama==0.4.6 --hash=sha256:cc\n\
+             pysocks==1.7.1 --hash=sha256:dd\n\
+             pytest==8.3.3 --hash=sha256:ee\n\
+             requests==2.32.3 --hash=sha256:ff\n"
+        );
+        assert!(uv_lock_requirements(LOCK, &["docs".to_string()], &linux).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_source() {
+        let lock = r#"
+version = 1
+
+[[package]]
+name = "app"
+version = "0.1.0"
+source = { virtual = "." }
+dependencies = [{ name = "foo" }]
+
+[[package]]
+name = "foo"
+version = "1.0"
+source = { git = "https://github.com/example/foo.git?rev=main#0123456789" }
+"#;
+
+        let env = MarkerEnvironment::from_target("x86_64-unknown-linux-gnu", "3.10.8");
+        assert!(uv_lock_requirements(lock, &[], &env).is_err());
+    }
+}
@@ -14,6 +14,20 @@ pub use crate::default_python_distributions::PYTHON_DISTRIBUTIONS;
 /// Default Python X.Y version to use.
 pub const DEFAULT_PYTHON_VERSION: &str = "3.10";
 
+/// Whether a distribution is of a flavor.
+fn is_flavor(dist: &PythonDistributionRecord, flavor: &DistributionFlavor) -> bool {
+    match flavor {
+        DistributionFlavor::Standalone => !dist.free_threaded,
+        DistributionFlavor::StandaloneStatic => {
+            !dist.free_threaded && !dist.supports_prebuilt_extension_modules
+        }
+        DistributionFlavor::StandaloneDynamic => {
+            !dist.free_threaded && dist.supports_prebuilt_extension_modules
+        }
+        DistributionFlavor::PyPy => false,
+    }
+}
+
 /// A collection of available Python distributions.
 pub struct PythonDistributionCollection {
     pub(crate) dists: Vec<PythonDistributionRecord>,
@@ -39,15 +53,28 @@ impl PythonDistributionCollection {
             .iter()
             .filter(|dist| dist.python_major_minor_version == python_major_minor_version)
             .filter(|dist| dist.target_triple == target_triple)
-            .filter(|dist| match flavor {
-                DistributionFlavor::Standalone => true,
-                DistributionFlavor::StandaloneStatic => !dist.supports_prebuilt_extension_modules,
-                DistributionFlavor::StandaloneDynamic => dist.supports_prebuilt_extension_modules,
-            })
+            .filter(|dist| is_flavor(dist, flavor))
             .cloned()
             .next()
     }
 
+    /// Python `X.Y` versions of distributions for a target triple and flavor.
+    pub fn python_versions(&self, target_triple: &str, flavor: &DistributionFlavor) -> Vec<&str> {
+        self.dists
+            .iter()
+            .filter(|dist| dist.target_triple == target_triple)
+            .filter(|dist| is_flavor(dist, flavor))
+            .map(|dist| dist.python_major_minor_version.as_str())
+            .sorted_by_key(|version| {
+                version
+                    .split('.')
+                    .map(|x| x.parse::<u32>().unwrap_or(0))
+                    .collect::<Vec<_>>()
+            })
+            .dedup()
+            .collect()
+    }
+
     /// Obtain records for all registered distributions.
     #[allow(unused)]
     pub fn iter(&self) -> impl Iterator<Item = &PythonDistributionRecord> {
@@ -67,7 +94,46 @@ impl PythonDistributionCollection {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, crate::py_packaging::distribution::PythonDistributionLocation};
+
+    #[test]
+    fn test_python_versions() {
+        assert_eq!(
+            PYTHON_DISTRIBUTIONS
+                .python_versions("x86_64-unknown-linux-gnu", &DistributionFlavor::Standalone),
+            vec!["3.8", "3.9", "3.10"]
+        );
+        assert!(PYTHON_DISTRIBUTIONS
+            .python_versions(
+                "x86_64-unknown-linux-musl",
+                &DistributionFlavor::StandaloneDynamic
+            )
+            .is_empty());
+        assert!(PYTHON_DISTRIBUTIONS
+            .python_versions(
+                "riscv64gc-unknown-linux-gnu",
+                &DistributionFlavor::Standalone
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn test_free_threaded_flavor() {
+        let record = PythonDistributionRecord {
+            python_major_minor_version: "3.13".to_string(),
+            location: PythonDistributionLocation::Url {
+                url: "https://example.com/cpython-3.13.0-freethreaded.tar.zst".to_string(),
+                sha256: "sha256".to_string(),
+            },
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            supports_prebuilt_extension_modules: true,
+            free_threaded: true,
+        };
+
+        assert!(!is_flavor(&record, &DistributionFlavor::Standalone));
+        assert!(!is_flavor(&record, &DistributionFlavor::StandaloneStatic));
+        assert!(!is_flavor(&record, &DistributionFlavor::StandaloneDynamic));
+    }
 
     #[test]
     fn test_all_target_triples() {
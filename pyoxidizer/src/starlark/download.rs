@@ -0,0 +1,270 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Starlark functionality for fetching remote files into builds.
+*/
+
+use {
+    super::env::{get_context, PyOxidizerEnvironmentContext},
+    anyhow::{anyhow, Context, Result},
+    log::warn,
+    sha2::{Digest, Sha256},
+    simple_file_manifest::{FileData, FileEntry},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            Value, ValueResult,
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{optional_bool_arg, optional_str_arg},
+    std::path::{Path, PathBuf},
+    tugger::starlark::file_content::FileContentWrapper,
+    tugger_common::http::{download_to_path, RemoteContent},
+    url::Url,
+};
+
+fn error_context<F, T>(label: &str, f: F) -> Result<T, ValueError>
+where
+    F: FnOnce() -> anyhow::Result<T>,
+{
+    f().map_err(|e| {
+        ValueError::Runtime(RuntimeError {
+            code: "PYOXIDIZER_DOWNLOAD",
+            message: format!("{:?}", e),
+            label: label.to_string(),
+        })
+    })
+}
+
+/// Resolve the file name of downloaded content from its URL.
+fn url_filename(url: &Url) -> Result<String> {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|filename| !filename.is_empty())
+        .map(|filename| filename.to_string())
+        .ok_or_else(|| anyhow!("unable to resolve file name from URL {}", url))
+}
+
+/// Whether a file exists and has the expected SHA-256.
+fn is_cached(path: &Path, sha256: &str) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+    Ok(Sha256::digest(&data).to_vec() == hex::decode(sha256)?)
+}
+
+/// Ensure the content of a URL is present in a cache directory.
+///
+/// Files are stored under a directory named after their SHA-256, so
+/// different files having the same name don't collide. The path to the
+/// verified file is returned.
+///
+/// When `offline`, the network isn't accessed and the file must already
+/// be cached.
+pub fn download_to_cache(
+    url: &Url,
+    sha256: &str,
+    cache_dir: &Path,
+    offline: bool,
+) -> Result<PathBuf> {
+    let path = cache_dir.join(sha256).join(url_filename(url)?);
+
+    if offline {
+        return if is_cached(&path, sha256)? {
+            Ok(path)
+        } else {
+            Err(anyhow!(
+                "{} is not cached; run `pyoxidizer download-deps` to populate the dependency cache",
+                url
+            ))
+        };
+    }
+
+    download_to_path(
+        &RemoteContent {
+            name: format!("PYOXIDIZER_DOWNLOAD_{}", sha256.to_uppercase()),
+            url: url.to_string(),
+            sha256: sha256.to_string(),
+        },
+        &path,
+    )
+    .with_context(|| format!("downloading {}", url))?;
+
+    Ok(path)
+}
+
+/// download_file(url, sha256, filename=None, executable=None)
+fn starlark_download_file(
+    type_values: &TypeValues,
+    url: String,
+    sha256: String,
+    filename: &Value,
+    executable: &Value,
+) -> ValueResult {
+    const LABEL: &str = "download_file()";
+
+    let filename = optional_str_arg("filename", filename)?;
+    let executable = optional_bool_arg("executable", executable)?;
+
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let wrapper = error_context(LABEL, || {
+        let sha256 = sha256.to_lowercase();
+        if sha256.len() != 64 || hex::decode(&sha256).is_err() {
+            return Err(anyhow!("sha256 must be 64 hex characters; got {}", sha256));
+        }
+
+        let parsed_url = Url::parse(&url).with_context(|| format!("parsing URL {}", url))?;
+
+        let filename = if let Some(filename) = filename {
+            filename
+        } else {
+            url_filename(&parsed_url)?
+        };
+
+        if filename.contains('/') || filename.contains('\\') {
+            return Err(anyhow!(
+                "directory separators aren't allowed in file names: {}",
+                filename
+            ));
+        }
+
+        // Checking must not access the network. Content is empty in this mode.
+        let data = if pyoxidizer_context.check_mode {
+            warn!("skipping download of {} when checking configuration", url);
+            FileData::Memory(vec![])
+        } else {
+            let (cache_dir, offline) = match &pyoxidizer_context.dependency_cache {
+                Some(cache) => (cache.path().join("downloads"), cache.is_offline()),
+                None => (pyoxidizer_context.env().downloads_dir(), false),
+            };
+
+            FileData::Path(download_to_cache(
+                &parsed_url,
+                &sha256,
+                &cache_dir,
+                offline,
+            )?)
+        };
+
+        Ok(FileContentWrapper {
+            content: FileEntry::new_from_data(data, executable.unwrap_or(false)),
+            filename,
+        })
+    })?;
+
+    Ok(wrapper.into())
+}
+
+starlark_module! { download_module =>
+    download_file(
+        env env,
+        url: String,
+        sha256: String,
+        filename = NoneType::None,
+        executable = NoneType::None
+    ) {
+        starlark_download_file(env, url, sha256, &filename, &executable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{starlark::testutil::*, testutil::get_env},
+        tugger::starlark::file_content::FileContentValue,
+    };
+
+    #[test]
+    fn test_download_file() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+
+        let source_path = temp_dir.path().join("tool.bin");
+        std::fs::write(&source_path, b"tool")?;
+        let sha256 = hex::encode(Sha256::digest(b"tool"));
+        let url = Url::from_file_path(&source_path).unwrap();
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        let value = env.eval(&format!(
+            "download_file('{}', '{}', executable = True)",
+            url, sha256
+        ))?;
+        assert_eq!(value.get_type(), "FileContent");
+        {
+            let value = value.downcast_ref::<FileContentValue>().unwrap();
+            let inner = value.inner("ignored").unwrap();
+            assert_eq!(inner.filename, "tool.bin");
+            assert_eq!(inner.content.resolve_content()?, b"tool".to_vec());
+            assert!(inner.content.is_executable());
+        }
+
+        // Cached content is used once the source is gone.
+        std::fs::remove_file(&source_path)?;
+        let value = env.eval(&format!(
+            "download_file('{}', '{}', filename = 'ffmpeg')",
+            url, sha256
+        ))?;
+        {
+            let value = value.downcast_ref::<FileContentValue>().unwrap();
+            let inner = value.inner("ignored").unwrap();
+            assert_eq!(inner.filename, "ffmpeg");
+            assert!(!inner.content.is_executable());
+        }
+
+        assert!(env
+            .eval(&format!("download_file('{}', 'abc')", url))
+            .is_err());
+        assert!(env
+            .eval(&format!(
+                "download_file('{}', '{}', filename = 'a/b')",
+                url, sha256
+            ))
+            .is_err());
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_download_to_cache() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+        let cache_dir = temp_dir.path().join("cache");
+
+        let source_path = temp_dir.path().join("data.txt");
+        std::fs::write(&source_path, b"data")?;
+        let url = Url::from_file_path(&source_path).unwrap();
+        let sha256 = hex::encode(Sha256::digest(b"data"));
+
+        assert!(download_to_cache(&url, &sha256, &cache_dir, true).is_err());
+
+        let path = download_to_cache(&url, &sha256, &cache_dir, false)?;
+        assert_eq!(path, cache_dir.join(&sha256).join("data.txt"));
+        assert_eq!(std::fs::read(&path)?, b"data".to_vec());
+        assert_eq!(download_to_cache(&url, &sha256, &cache_dir, true)?, path);
+
+        // Content not matching the digest is rejected.
+        let bad_sha256 = hex::encode(Sha256::digest(b"other"));
+        assert!(download_to_cache(&url, &bad_sha256, &cache_dir, false).is_err());
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+}
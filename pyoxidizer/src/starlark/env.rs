@@ -3,7 +3,11 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::py_packaging::distribution::DistributionCache,
+    crate::py_packaging::{
+        binary::{LibpythonLinkMode, PythonBinaryBuilder},
+        dependency_cache::DependencyCache,
+        distribution::DistributionCache,
+    },
     anyhow::{Context, Result},
     starlark::{
         environment::{Environment, EnvironmentError, TypeValues},
@@ -12,6 +16,10 @@ use {
             none::NoneType,
             {Mutable, TypedValue, Value, ValueResult},
         },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
     },
     starlark_dialect_build_targets::{get_context_value, EnvironmentContext},
     std::{
@@ -48,6 +56,9 @@ pub struct PyOxidizerEnvironmentContext {
     /// Whether we are building a debug or release binary.
     pub build_release: bool,
 
+    /// Whether we are building development binaries.
+    pub build_dev: bool,
+
     /// Optimization level when building binaries.
     pub build_opt_level: String,
 
@@ -57,6 +68,21 @@ pub struct PyOxidizerEnvironmentContext {
     /// few seconds in debug builds. And this adds up, especially in tests!
     pub distribution_cache: Arc<DistributionCache>,
 
+    /// Cache of package dependencies that packaging operations use.
+    pub dependency_cache: Option<DependencyCache>,
+
+    /// Whether the configuration is being checked instead of built.
+    ///
+    /// Checking doesn't download Python distributions, fetch or build Python
+    /// packages, or compile executables.
+    pub check_mode: bool,
+
+    /// Executables built ahead of their targets' build(), keyed by target name.
+    ///
+    /// Values are the file name and content of the executable. This allows
+    /// building the executables of multiple targets in parallel.
+    pub prebuilt_executables: HashMap<String, (String, Vec<u8>)>,
+
     /// Extra variables to inject into Starlark environment.
     extra_vars: HashMap<String, Option<String>>,
 }
@@ -70,8 +96,11 @@ impl PyOxidizerEnvironmentContext {
         build_host_triple: &str,
         build_target_triple: &str,
         build_release: bool,
+        build_dev: bool,
         build_opt_level: &str,
         distribution_cache: Option<Arc<DistributionCache>>,
+        dependency_cache: Option<DependencyCache>,
+        check_mode: bool,
         extra_vars: HashMap<String, Option<String>>,
     ) -> Result<PyOxidizerEnvironmentContext> {
         let parent = config_path
@@ -84,10 +113,10 @@ impl PyOxidizerEnvironmentContext {
             parent.to_path_buf()
         };
 
+        let offline = check_mode || matches!(&dependency_cache, Some(cache) if cache.is_offline());
+
         let distribution_cache = distribution_cache.unwrap_or_else(|| {
-            Arc::new(DistributionCache::new(Some(
-                &env.python_distributions_dir(),
-            )))
+            Arc::new(DistributionCache::new(Some(&env.python_distributions_dir())).offline(offline))
         });
 
         Ok(PyOxidizerEnvironmentContext {
@@ -98,8 +127,12 @@ impl PyOxidizerEnvironmentContext {
             build_host_triple: build_host_triple.to_string(),
             build_target_triple: build_target_triple.to_string(),
             build_release,
+            build_dev,
             build_opt_level: build_opt_level.to_string(),
             distribution_cache,
+            dependency_cache,
+            check_mode,
+            prebuilt_executables: HashMap::new(),
             extra_vars,
         })
     }
@@ -117,6 +150,28 @@ impl PyOxidizerEnvironmentContext {
         Ok(context.build_path().to_path_buf())
     }
 
+    /// Directory holding Rust build state shared by built executables.
+    ///
+    /// Executables embedding the same Python version with the same link mode
+    /// share a directory. Executables embedding other Pythons use other
+    /// directories so building them doesn't invalidate the shared state.
+    pub fn shared_rust_build_path(
+        &self,
+        type_values: &TypeValues,
+        exe: &dyn PythonBinaryBuilder,
+    ) -> Result<PathBuf, ValueError> {
+        let link_mode = match exe.libpython_link_mode() {
+            LibpythonLinkMode::Static => "static",
+            LibpythonLinkMode::Dynamic => "dynamic",
+        };
+
+        Ok(self.build_path(type_values)?.join("rust").join(format!(
+            "{}-{}",
+            exe.cache_tag(),
+            link_mode
+        )))
+    }
+
     pub fn python_distributions_path(&self) -> Result<PathBuf, ValueError> {
         Ok(self.env.python_distributions_dir())
     }
@@ -170,6 +225,25 @@ pub fn get_context(type_values: &TypeValues) -> ValueResult {
         })
 }
 
+/// getenv(name, default=None)
+fn starlark_getenv(name: String, default: Value) -> ValueResult {
+    match std::env::var(&name) {
+        Ok(value) => Ok(Value::from(value)),
+        Err(std::env::VarError::NotPresent) => Ok(default),
+        Err(std::env::VarError::NotUnicode(_)) => Err(ValueError::from(RuntimeError {
+            code: "PYOXIDIZER",
+            message: format!("environment variable {} is not valid UTF-8", name),
+            label: "getenv()".to_string(),
+        })),
+    }
+}
+
+starlark_module! { env_module =>
+    getenv(name: String, default = NoneType::None) {
+        starlark_getenv(name, default)
+    }
+}
+
 /// Obtain a Starlark environment for evaluating PyOxidizer configurations.
 pub fn register_starlark_dialect(
     env: &mut Environment,
@@ -177,11 +251,15 @@ pub fn register_starlark_dialect(
 ) -> Result<(), EnvironmentError> {
     starlark_dialect_build_targets::register_starlark_dialect(env, type_values)?;
     tugger::starlark::register_starlark_dialect(env, type_values)?;
+    env_module(env, type_values);
+    super::download::download_module(env, type_values);
     super::file_resource::file_resource_env(env, type_values);
+    super::python_c_embedding_library::python_c_embedding_library_module(env, type_values);
     super::python_distribution::python_distribution_module(env, type_values);
     super::python_embedded_resources::python_embedded_resources_module(env, type_values);
     super::python_executable::python_executable_env(env, type_values);
     super::python_packaging_policy::python_packaging_policy_module(env, type_values);
+    super::python_test::python_test_module(env, type_values);
 
     Ok(())
 }
@@ -237,6 +315,7 @@ pub fn populate_environment(
         "BUILD_TARGET_TRIPLE",
         Value::from(context.build_target_triple.clone()),
     )?;
+    env.set("BUILD_DEV_MODE", Value::from(context.build_dev))?;
 
     env.set("CONTEXT", Value::new(context))?;
 
@@ -244,7 +323,13 @@ pub fn populate_environment(
     // available via the type object API. This is a bit hacky. But it allows
     // Rust code with only access to the TypeValues dictionary to retrieve
     // these globals.
-    for f in &["CONTEXT", "CWD", "CONFIG_PATH", "BUILD_TARGET_TRIPLE"] {
+    for f in &[
+        "CONTEXT",
+        "CWD",
+        "CONFIG_PATH",
+        "BUILD_TARGET_TRIPLE",
+        "BUILD_DEV_MODE",
+    ] {
         type_values.add_type_value(PyOxidizerContext::TYPE, f, env.get(f)?);
     }
 
@@ -268,6 +353,25 @@ pub mod tests {
         assert_eq!(target.to_str(), default_target_triple());
     }
 
+    #[test]
+    fn test_build_dev_mode() {
+        let dev = starlark_ok("BUILD_DEV_MODE");
+        assert!(!dev.to_bool());
+    }
+
+    #[test]
+    fn test_getenv() {
+        let value = starlark_ok("getenv('PYOXIDIZER_TEST_GETENV_DOES_NOT_EXIST')");
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = starlark_ok("getenv('PYOXIDIZER_TEST_GETENV_DOES_NOT_EXIST', 'default')");
+        assert_eq!(value.to_str(), "default");
+
+        std::env::set_var("PYOXIDIZER_TEST_GETENV", "value");
+        let value = starlark_ok("getenv('PYOXIDIZER_TEST_GETENV', 'default')");
+        assert_eq!(value.to_str(), "value");
+    }
+
     #[test]
     fn test_print() {
         starlark_ok("print('hello, world')");
@@ -5,27 +5,36 @@
 use {
     crate::{
         environment::default_target_triple,
-        py_packaging::distribution::DistributionCache,
-        starlark::env::{
-            populate_environment, register_starlark_dialect, PyOxidizerContext,
-            PyOxidizerEnvironmentContext,
+        project_building::{prepare_python_executable, run_cargo_builds},
+        py_packaging::{
+            binary::PythonBinaryBuilder, dependency_cache::DependencyCache,
+            distribution::DistributionCache,
+        },
+        starlark::{
+            env::{
+                populate_environment, register_starlark_dialect, PyOxidizerContext,
+                PyOxidizerEnvironmentContext,
+            },
+            file_loader::{resolve_load_root, ConfigFileLoader},
+            python_executable::PythonExecutableValue,
         },
     },
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, Context, Result},
     codemap::CodeMap,
     codemap_diagnostic::{Diagnostic, Emitter},
-    log::error,
+    log::{error, warn},
     starlark::{
         environment::{Environment, EnvironmentError, TypeValues},
         eval::call_stack::CallStack,
         syntax::dialect::Dialect,
         values::{
+            dict::Dictionary,
             error::{RuntimeError, ValueError},
             Value, ValueResult,
         },
     },
     starlark_dialect_build_targets::{
-        build_target, run_target, EnvironmentContext, ResolvedTarget,
+        build_target, resolve_registered_target, run_target, EnvironmentContext, ResolvedTarget,
     },
     std::{
         collections::HashMap,
@@ -40,11 +49,14 @@ pub struct EvaluationContextBuilder {
     config_path: PathBuf,
     build_target_triple: String,
     release: bool,
+    dev: bool,
     verbose: bool,
     resolve_targets: Option<Vec<String>>,
     build_script_mode: bool,
     build_opt_level: String,
     distribution_cache: Option<Arc<DistributionCache>>,
+    dependency_cache: Option<DependencyCache>,
+    check_mode: bool,
     extra_vars: HashMap<String, Option<String>>,
 }
 
@@ -59,11 +71,14 @@ impl EvaluationContextBuilder {
             config_path: config_path.as_ref().to_path_buf(),
             build_target_triple: build_target_triple.to_string(),
             release: false,
+            dev: false,
             verbose: false,
             resolve_targets: None,
             build_script_mode: false,
             build_opt_level: "0".to_string(),
             distribution_cache: None,
+            dependency_cache: None,
+            check_mode: false,
             extra_vars: HashMap::new(),
         }
     }
@@ -91,6 +106,12 @@ impl EvaluationContextBuilder {
         self
     }
 
+    #[must_use]
+    pub fn dev(mut self, value: bool) -> Self {
+        self.dev = value;
+        self
+    }
+
     #[must_use]
     pub fn verbose(mut self, value: bool) -> Self {
         self.verbose = value;
@@ -134,6 +155,18 @@ impl EvaluationContextBuilder {
         self
     }
 
+    #[must_use]
+    pub fn dependency_cache(mut self, cache: Option<DependencyCache>) -> Self {
+        self.dependency_cache = cache;
+        self
+    }
+
+    #[must_use]
+    pub fn check_mode(mut self, value: bool) -> Self {
+        self.check_mode = value;
+        self
+    }
+
     #[must_use]
     pub fn extra_vars(mut self, extra_vars: HashMap<String, Option<String>>) -> Self {
         self.extra_vars = extra_vars;
@@ -141,6 +174,40 @@ impl EvaluationContextBuilder {
     }
 }
 
+/// Copy global variables into the environment files are loaded from.
+fn populate_load_environment(
+    env: &Environment,
+    load_env: &Environment,
+) -> Result<(), EnvironmentError> {
+    for name in &[
+        "CWD",
+        "CONFIG_PATH",
+        "BUILD_TARGET_TRIPLE",
+        "BUILD_DEV_MODE",
+    ] {
+        load_env.set(name, env.get(name)?)?;
+    }
+
+    // VARS is mutable. Freezing the loader environment would freeze it.
+    let vars = env.get("VARS")?;
+    let mut vars_copy = Dictionary::default();
+    if let Ok(keys) = vars.iter() {
+        for key in keys.iter() {
+            if let Ok(value) = vars.at(key.clone()) {
+                vars_copy
+                    .insert(key, value)
+                    .expect("error inserting variable; this should not happen");
+            }
+        }
+    }
+    load_env.set(
+        "VARS",
+        Value::try_from(vars_copy.get_content().clone()).unwrap(),
+    )?;
+
+    Ok(())
+}
+
 /// Interface to evaluate Starlark configuration files.
 ///
 /// This type provides the primary interface for evaluating Starlark
@@ -149,8 +216,10 @@ impl EvaluationContextBuilder {
 /// Instances should be constructed from `EvaluationContextBuilder` instances, as
 /// the number of parameters to construct an evaluation context is significant.
 pub struct EvaluationContext {
-    parent_env: Environment,
     child_env: Environment,
+    load_env: Environment,
+    load_root: PathBuf,
+    config_dir: PathBuf,
     type_values: TypeValues,
 }
 
@@ -164,6 +233,13 @@ impl TryFrom<EvaluationContextBuilder> for EvaluationContext {
 
 impl EvaluationContext {
     pub fn from_builder(builder: EvaluationContextBuilder) -> Result<Self> {
+        let config_dir = builder
+            .config_path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default();
+        let load_root = resolve_load_root(&config_dir);
+
         let context = PyOxidizerEnvironmentContext::new(
             &builder.env,
             builder.verbose,
@@ -171,8 +247,11 @@ impl EvaluationContext {
             default_target_triple(),
             &builder.build_target_triple,
             builder.release,
+            builder.dev,
             &builder.build_opt_level,
             builder.distribution_cache,
+            builder.dependency_cache,
+            builder.check_mode,
             builder.extra_vars,
         )?;
 
@@ -194,9 +273,18 @@ impl EvaluationContext {
         )
         .map_err(|e| anyhow!("error populating Starlark environment: {:?}", e))?;
 
+        // Files evaluated by load() get copies of the global variables. They can't
+        // share the child environment because loading freezes the parent of the
+        // environment a file is evaluated in.
+        let load_env = parent_env.child("pyoxidizer-load");
+        populate_load_environment(&child_env, &load_env)
+            .map_err(|e| anyhow!("error populating Starlark environment: {:?}", e))?;
+
         Ok(Self {
-            parent_env,
             child_env,
+            load_env,
+            load_root,
+            config_dir,
             type_values,
         })
     }
@@ -211,18 +299,28 @@ impl EvaluationContext {
         self.child_env.set(name, value)
     }
 
+    /// Obtain the `FileLoader` handling `load()` calls.
+    fn file_loader(&self, map: &Arc<Mutex<CodeMap>>) -> ConfigFileLoader {
+        ConfigFileLoader::new(
+            map,
+            self.load_env.clone(),
+            &self.load_root,
+            &self.config_dir,
+        )
+    }
+
     /// Evaluate a Starlark configuration file, returning a Diagnostic on error.
     pub fn evaluate_file_diagnostic(&mut self, config_path: &Path) -> Result<(), Diagnostic> {
         let map = Arc::new(Mutex::new(CodeMap::new()));
-        let file_loader_env = self.parent_env.clone();
+        let file_loader = self.file_loader(&map);
 
-        starlark::eval::simple::eval_file(
+        starlark::eval::eval_file(
             &map,
             &config_path.display().to_string(),
             Dialect::Bzl,
             &mut self.child_env,
             &self.type_values,
-            file_loader_env,
+            &file_loader,
         )
         .map_err(|e| {
             let mut msg = Vec::new();
@@ -253,16 +351,16 @@ impl EvaluationContext {
         path: &str,
         code: &str,
     ) -> Result<Value, Diagnostic> {
-        let file_loader_env = self.child_env.clone();
+        let file_loader = self.file_loader(map);
 
-        starlark::eval::simple::eval(
+        starlark::eval::eval(
             map,
             path,
             code,
             Dialect::Bzl,
             &mut self.child_env,
             &self.type_values,
-            file_loader_env,
+            &file_loader,
         )
     }
 
@@ -348,6 +446,16 @@ impl EvaluationContext {
             .collect::<Vec<_>>())
     }
 
+    /// Obtain the names of test targets, in registration order.
+    pub fn test_target_names(&self) -> Result<Vec<String>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context.test_targets())
+    }
+
     /// Obtain targets that should be resolved.
     pub fn targets_to_resolve(&self) -> Result<Vec<String>> {
         let raw_context = self.build_targets_context_value()?;
@@ -358,6 +466,25 @@ impl EvaluationContext {
         Ok(context.targets_to_resolve())
     }
 
+    /// Obtain target dependencies referring to targets that don't exist.
+    ///
+    /// Returns pairs of the depending target and the missing dependency.
+    pub fn unknown_target_dependencies(&self) -> Result<Vec<(String, String)>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context.unknown_dependencies())
+    }
+
+    /// Resolve a target, calling its function and the functions of its dependencies.
+    pub fn resolve_target(&mut self, target: &str) -> Result<Value> {
+        let mut call_stack = CallStack::default();
+
+        resolve_registered_target(&self.type_values, &mut call_stack, target)
+    }
+
     pub fn build_resolved_target(&mut self, target: &str) -> Result<ResolvedTarget> {
         let mut call_stack = CallStack::default();
 
@@ -369,6 +496,124 @@ impl EvaluationContext {
         )
     }
 
+    /// Build multiple resolved targets.
+    ///
+    /// The executables of targets resolving to a `PythonExecutable` are
+    /// compiled in parallel before targets are built in order. See
+    /// [Self::prebuild_executables()].
+    pub fn build_resolved_targets(&mut self, targets: &[String]) -> Result<()> {
+        self.prebuild_executables(targets)?;
+
+        let res = targets
+            .iter()
+            .try_for_each(|target| self.build_resolved_target(target).map(|_| ()));
+
+        // Release the memory held by the built executables.
+        self.pyoxidizer_context_value()
+            .map_err(|e| anyhow!("{:?}", e))?
+            .downcast_mut::<PyOxidizerEnvironmentContext>()
+            .map_err(|_| anyhow!("unable to obtain mutable context"))?
+            .ok_or_else(|| anyhow!("context has incorrect type"))?
+            .prebuilt_executables
+            .clear();
+
+        res
+    }
+
+    /// Compile the executables of targets resolving to a `PythonExecutable`.
+    ///
+    /// The Rust project of each executable is prepared one at a time, as this
+    /// needs the Starlark values. Cargo then builds the projects in parallel,
+    /// except for projects sharing a Rust build directory: those are built one
+    /// after the other. Built executables are used by the targets' `build()`.
+    fn prebuild_executables(&mut self, targets: &[String]) -> Result<()> {
+        let mut exes: Vec<(String, Arc<dyn PythonBinaryBuilder>)> = vec![];
+
+        {
+            let raw_context = self.build_targets_context_value()?;
+            let context = raw_context
+                .downcast_ref::<EnvironmentContext>()
+                .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+            for target in targets {
+                let value = match context.get_target(target) {
+                    Some(t) if t.built_target.is_none() => t.resolved_value.as_ref(),
+                    _ => None,
+                };
+
+                if let Some(exe) = value.and_then(|v| v.downcast_ref::<PythonExecutableValue>()) {
+                    if !exes.iter().any(|(name, _)| name == target) {
+                        let exe = exe
+                            .inner("PythonExecutable.build()")
+                            .map_err(|e| anyhow!("{:?}", e))?
+                            .clone_trait();
+                        exes.push((target.clone(), exe));
+                    }
+                }
+            }
+        }
+
+        // Building a single executable ahead of time wouldn't gain anything.
+        if exes.len() < 2 {
+            return Ok(());
+        }
+
+        let raw_context = self
+            .pyoxidizer_context_value()
+            .map_err(|e| anyhow!("{:?}", e))?;
+
+        let prebuilt = {
+            let context = raw_context
+                .downcast_ref::<PyOxidizerEnvironmentContext>()
+                .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+            if context.check_mode {
+                return Ok(());
+            }
+
+            let prepared = exes
+                .iter()
+                .map(|(target, exe)| {
+                    let shared_build_path = context
+                        .shared_rust_build_path(&self.type_values, &**exe)
+                        .map_err(|_| anyhow!("unable to resolve build path"))?;
+
+                    prepare_python_executable(
+                        context.env(),
+                        &exe.name(),
+                        &**exe,
+                        exe.target_triple(),
+                        &context.build_opt_level,
+                        context.build_release,
+                        Some(&shared_build_path),
+                    )
+                    .with_context(|| format!("preparing executable of target {}", target))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            warn!("building {} executables in parallel", prepared.len());
+            run_cargo_builds(&prepared.iter().map(|p| &p.cargo).collect::<Vec<_>>())?;
+
+            exes.iter()
+                .zip(prepared)
+                .map(|((target, _), prepared)| {
+                    let built = prepared.finish()?;
+
+                    Ok((target.clone(), (built.exe_name, built.exe_data)))
+                })
+                .collect::<Result<HashMap<_, _>>>()?
+        };
+
+        raw_context
+            .downcast_mut::<PyOxidizerEnvironmentContext>()
+            .map_err(|_| anyhow!("unable to obtain mutable context"))?
+            .ok_or_else(|| anyhow!("context has incorrect type"))?
+            .prebuilt_executables
+            .extend(prebuilt);
+
+        Ok(())
+    }
+
     pub fn run_target(&mut self, target: Option<&str>) -> Result<()> {
         let mut call_stack = CallStack::default();
 
@@ -383,7 +628,7 @@ impl EvaluationContext {
 
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::testutil::*, starlark::values::dict::Dictionary};
+    use {super::*, crate::testutil::*, starlark_dialect_build_targets::RunMode};
 
     #[test]
     fn test_load() -> Result<()> {
@@ -420,6 +665,74 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_load_relative() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+        let root = temp_dir.path();
+
+        std::fs::create_dir_all(root.join("packaging"))?;
+        std::fs::create_dir_all(root.join("app"))?;
+        // Marks the root of a checkout, which // paths are relative to.
+        std::fs::create_dir_all(root.join(".git"))?;
+        std::fs::write(
+            root.join("packaging/common.bzl"),
+            "load('helpers.bzl', 'suffix')\ndef make_name(name):\n    return name + suffix + VARS.get('extra', '')\n",
+        )?;
+        std::fs::write(root.join("packaging/helpers.bzl"), "suffix = '-app'\n")?;
+
+        let config_path = root.join("app/pyoxidizer.bzl");
+        std::fs::write(
+            &config_path,
+            "load('//packaging/common.bzl', 'make_name')\nload('../packaging/helpers.bzl', 'suffix')\nname = make_name('foo')\n",
+        )?;
+
+        let mut extra_vars = HashMap::new();
+        extra_vars.insert("extra".to_string(), Some("!".to_string()));
+
+        let mut context = EvaluationContextBuilder::new(
+            &env,
+            config_path.clone(),
+            default_target_triple().to_string(),
+        )
+        .extra_vars(extra_vars)
+        .into_context()?;
+
+        context.evaluate_file(&config_path)?;
+        assert_eq!(context.get_var("name").unwrap().to_string(), "foo-app!");
+        assert_eq!(context.get_var("suffix").unwrap().to_string(), "-app");
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_cycle() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+
+        std::fs::write(temp_dir.path().join("a.bzl"), "load('b.bzl', 'b')\na = 1\n")?;
+        std::fs::write(temp_dir.path().join("b.bzl"), "load('a.bzl', 'a')\nb = 1\n")?;
+
+        let config_path = temp_dir.path().join("pyoxidizer.bzl");
+        std::fs::write(&config_path, "load('a.bzl', 'a')\n")?;
+
+        let mut context = EvaluationContextBuilder::new(
+            &env,
+            config_path.clone(),
+            default_target_triple().to_string(),
+        )
+        .into_context()?;
+
+        let err = context.evaluate_file(&config_path).unwrap_err().to_string();
+        assert!(err.as_str().contains("load cycle"), "{}", err);
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_register_target() -> Result<()> {
         let env = get_env()?;
@@ -442,6 +755,57 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_build_resolved_targets() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+
+        let config_path = temp_dir.path().join("pyoxidizer.bzl");
+        std::fs::write(
+            &config_path,
+            format!(
+                "set_build_path('{}')\n\
+                 def make_exe1():\n    \
+                     return default_python_distribution().to_python_executable('exe1')\n\
+                 def make_exe2():\n    \
+                     return default_python_distribution().to_python_executable('exe2')\n\
+                 register_target('exe1', make_exe1)\n\
+                 register_target('exe2', make_exe2)\n\
+                 resolve_targets()\n",
+                temp_dir
+                    .path()
+                    .join("build")
+                    .display()
+                    .to_string()
+                    .escape_default()
+            ),
+        )?;
+
+        let targets = vec!["exe1".to_string(), "exe2".to_string()];
+
+        let mut context: EvaluationContext = EvaluationContextBuilder::new(
+            &env,
+            config_path.clone(),
+            default_target_triple().to_string(),
+        )
+        .resolve_targets(targets.clone())
+        .into_context()?;
+        context.evaluate_file(&config_path)?;
+        context.build_resolved_targets(&targets)?;
+
+        for target in &targets {
+            let resolved = context.build_resolved_target(target)?;
+            match resolved.run_mode {
+                RunMode::Path { path } => assert!(path.exists()),
+                mode => panic!("unexpected run mode {:?}", mode),
+            }
+        }
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
     #[test]
     fn extra_vars() -> Result<()> {
         let env = get_env()?;
@@ -0,0 +1,209 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Resolving and evaluating files referenced by `load()`.
+
+`load()` paths starting with `//` are relative to a root directory. This
+is the directory named by `PYOXIDIZER_LOAD_ROOT` if set, else the nearest
+ancestor of the configuration file that is the root of a version control
+checkout, else the directory of the configuration file. Other relative
+paths are relative to the directory of the file calling `load()`.
+*/
+
+use {
+    codemap::CodeMap,
+    codemap_diagnostic::{Diagnostic, Level},
+    starlark::{
+        environment::{Environment, TypeValues},
+        eval::{EvalException, FileLoader},
+        syntax::dialect::Dialect,
+    },
+    std::{
+        cell::RefCell,
+        collections::HashMap,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Environment variable overriding the directory `//` paths are relative to.
+pub const LOAD_ROOT_ENV: &str = "PYOXIDIZER_LOAD_ROOT";
+
+/// Files and directories denoting the root of a version control checkout.
+const VCS_MARKERS: &[&str] = &[".git", ".hg", ".jj", ".svn"];
+
+/// Resolve the directory `//` paths passed to `load()` are relative to.
+pub fn resolve_load_root(config_dir: &Path) -> PathBuf {
+    if let Ok(root) = std::env::var(LOAD_ROOT_ENV) {
+        return config_dir.join(root);
+    }
+
+    config_dir
+        .ancestors()
+        .find(|dir| VCS_MARKERS.iter().any(|marker| dir.join(marker).exists()))
+        .unwrap_or(config_dir)
+        .to_path_buf()
+}
+
+/// Resolve a path passed to `load()` to a filesystem path.
+///
+/// `current_dir` is the directory of the file calling `load()`.
+pub fn resolve_load_path(path: &str, root: &Path, current_dir: &Path) -> PathBuf {
+    if let Some(relative) = path.strip_prefix("//") {
+        root.join(relative)
+    } else {
+        current_dir.join(path)
+    }
+}
+
+fn load_error(message: String) -> EvalException {
+    EvalException::DiagnosedError(Diagnostic {
+        level: Level::Error,
+        message,
+        code: Some("PYOXIDIZER_LOAD".to_string()),
+        spans: vec![],
+    })
+}
+
+/// A `FileLoader` for files loaded by PyOxidizer configuration files.
+///
+/// Loaded files are evaluated in a child of `globals_env`, so they see the
+/// same global variables as the configuration file. Each file is evaluated
+/// at most once and subsequent loads reuse its symbols.
+pub struct ConfigFileLoader {
+    codemap: Arc<Mutex<CodeMap>>,
+    globals_env: Environment,
+    root: PathBuf,
+    config_dir: PathBuf,
+    modules: RefCell<HashMap<PathBuf, Environment>>,
+    loading: RefCell<Vec<PathBuf>>,
+}
+
+impl ConfigFileLoader {
+    pub fn new(
+        codemap: &Arc<Mutex<CodeMap>>,
+        globals_env: Environment,
+        root: impl AsRef<Path>,
+        config_dir: impl AsRef<Path>,
+    ) -> Self {
+        Self {
+            codemap: codemap.clone(),
+            globals_env,
+            root: root.as_ref().to_path_buf(),
+            config_dir: config_dir.as_ref().to_path_buf(),
+            modules: RefCell::new(HashMap::new()),
+            loading: RefCell::new(vec![]),
+        }
+    }
+
+    /// The directory of the file currently calling `load()`.
+    fn current_dir(&self) -> PathBuf {
+        self.loading
+            .borrow()
+            .last()
+            .and_then(|path| path.parent())
+            .unwrap_or(&self.config_dir)
+            .to_path_buf()
+    }
+}
+
+impl FileLoader for ConfigFileLoader {
+    fn load(&self, path: &str, type_values: &TypeValues) -> Result<Environment, EvalException> {
+        let resolved = resolve_load_path(path, &self.root, &self.current_dir());
+        let resolved = resolved.canonicalize().map_err(|e| {
+            load_error(format!(
+                "unable to load {} ({}): {}",
+                path,
+                resolved.display(),
+                e
+            ))
+        })?;
+
+        if let Some(env) = self.modules.borrow().get(&resolved) {
+            return Ok(env.clone());
+        }
+
+        if let Some(pos) = self.loading.borrow().iter().position(|p| p == &resolved) {
+            let cycle = self.loading.borrow()[pos..]
+                .iter()
+                .chain(std::iter::once(&resolved))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>();
+
+            return Err(load_error(format!("load cycle: {}", cycle.join(" -> "))));
+        }
+
+        let resolved_str = resolved.display().to_string();
+        let mut env = self.globals_env.child(&resolved_str);
+
+        self.loading.borrow_mut().push(resolved.clone());
+        let res = starlark::eval::eval_file(
+            &self.codemap,
+            &resolved_str,
+            Dialect::Bzl,
+            &mut env,
+            type_values,
+            self,
+        );
+        self.loading.borrow_mut().pop();
+        res?;
+
+        env.freeze();
+        self.modules.borrow_mut().insert(resolved, env.clone());
+
+        Ok(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_load_path() {
+        let root = Path::new("/repo");
+        let current = Path::new("/repo/app");
+
+        assert_eq!(
+            resolve_load_path("//packaging/common.bzl", root, current),
+            PathBuf::from("/repo/packaging/common.bzl")
+        );
+        assert_eq!(
+            resolve_load_path("common.bzl", root, current),
+            PathBuf::from("/repo/app/common.bzl")
+        );
+        assert_eq!(
+            resolve_load_path("../common.bzl", root, current),
+            PathBuf::from("/repo/app/../common.bzl")
+        );
+        assert_eq!(
+            resolve_load_path("/other/common.bzl", root, current),
+            PathBuf::from("/other/common.bzl")
+        );
+    }
+
+    #[test]
+    fn test_resolve_load_root() -> anyhow::Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let app_dir = temp_dir.path().join("app");
+        std::fs::create_dir_all(&app_dir)?;
+
+        // Without a checkout, the configuration directory is the root.
+        if !temp_dir
+            .path()
+            .ancestors()
+            .any(|dir| VCS_MARKERS.iter().any(|marker| dir.join(marker).exists()))
+        {
+            assert_eq!(resolve_load_root(&app_dir), app_dir);
+        }
+
+        std::fs::create_dir(temp_dir.path().join(".git"))?;
+        assert_eq!(resolve_load_root(&app_dir), temp_dir.path());
+
+        Ok(())
+    }
+}
@@ -29,8 +29,13 @@ use {
             starlark_signature_extraction, starlark_signatures,
         },
     },
-    std::{ops::DerefMut, path::Path},
+    std::{
+        collections::BTreeMap,
+        ops::DerefMut,
+        path::{Path, PathBuf},
+    },
     tugger::starlark::file_manifest::FileManifestValue,
+    tugger_apple::{is_macho, UniversalBinaryBuilder},
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -42,11 +47,20 @@ pub fn file_manifest_add_python_executable(
     target: &str,
     release: bool,
     opt_level: &str,
+    shared_build_path: Option<&Path>,
 ) -> Result<()> {
     const LABEL: &str = "FileManifest.add_python_executable()";
 
-    let build = build_python_executable(env, &exe.name(), exe, target, opt_level, release)
-        .context("building Python executable")?;
+    let build = build_python_executable(
+        env,
+        &exe.name(),
+        exe,
+        target,
+        opt_level,
+        release,
+        shared_build_path,
+    )
+    .context("building Python executable")?;
 
     let content = FileEntry::new_from_data(build.exe_data.clone(), true);
 
@@ -75,6 +89,101 @@ pub fn file_manifest_add_python_executable(
     Ok(())
 }
 
+/// Add an empty file standing in for a Python executable to a manifest.
+///
+/// This is used when checking a configuration, which doesn't compile
+/// executables.
+pub fn file_manifest_add_python_executable_placeholder(
+    manifest: &mut FileManifestValue,
+    prefix: &str,
+    exe: &dyn PythonBinaryBuilder,
+    target: &str,
+) -> Result<()> {
+    const LABEL: &str = "FileManifest.add_python_executable()";
+
+    warn!("not building {} when checking configuration", exe.name());
+
+    let exe_name = if target.contains("pc-windows") {
+        format!("{}.exe", exe.name())
+    } else {
+        exe.name()
+    };
+
+    let use_prefix = if prefix == "." { "" } else { prefix };
+
+    let path = Path::new(use_prefix).join(exe_name);
+    manifest
+        .inner(LABEL)
+        .map_err(|e| anyhow!("{:?}", e))?
+        .add_file_entry(&path, FileEntry::new_from_data(vec![], true))
+        .context("adding exe placeholder to manifest")?;
+
+    manifest.run_path = Some(path);
+
+    Ok(())
+}
+
+/// Merge manifests holding the same application built for different Apple architectures.
+///
+/// Mach-O files differing between manifests, such as executables and
+/// extension modules, are combined into universal binaries. Other files
+/// differing between manifests take the content of the first manifest.
+/// Files missing from some manifests are copied as is.
+pub fn merge_apple_universal_file_manifests(manifests: &[FileManifest]) -> Result<FileManifest> {
+    let mut entries = BTreeMap::<PathBuf, Vec<&FileEntry>>::new();
+
+    for manifest in manifests {
+        for (path, entry) in manifest.iter_entries() {
+            entries.entry(path.clone()).or_default().push(entry);
+        }
+    }
+
+    let mut res = FileManifest::default();
+
+    for (path, path_entries) in entries {
+        if path_entries.len() != manifests.len() {
+            warn!(
+                "{} is not present for all architectures; copying it as is",
+                path.display()
+            );
+        }
+
+        let data = path_entries
+            .iter()
+            .map(|entry| Ok(entry.resolve_content()?))
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("resolving content of {}", path.display()))?;
+
+        let entry = if data.iter().all(|x| x == &data[0]) {
+            path_entries[0].clone()
+        } else if data.iter().all(|x| is_macho(x)) {
+            let mut builder = UniversalBinaryBuilder::default();
+            for x in &data {
+                builder
+                    .add_binary(x)
+                    .with_context(|| format!("adding {} to universal binary", path.display()))?;
+            }
+
+            let mut universal = vec![];
+            builder
+                .write(&mut universal)
+                .with_context(|| format!("writing universal binary {}", path.display()))?;
+
+            FileEntry::new_from_data(universal, path_entries[0].is_executable())
+        } else {
+            warn!(
+                "{} differs between architectures; using content of the first",
+                path.display()
+            );
+            path_entries[0].clone()
+        };
+
+        res.add_file_entry(&path, entry)?;
+    }
+
+    Ok(res)
+}
+
 /// FileManifest.add_python_resource(prefix, resource)
 pub fn file_manifest_add_python_resource(
     manifest: &mut FileManifestValue,
@@ -351,6 +460,70 @@ mod tests {
         Ok(())
     }
 
+    /// A minimal thin 64-bit Mach-O executable for a CPU type.
+    fn thin_macho(cputype: u32) -> Vec<u8> {
+        [0xfeedfacf, cputype, 0, 2, 0, 0, 0, 0]
+            .iter()
+            .flat_map(|x: &u32| x.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_apple_universal_file_manifests() -> Result<()> {
+        let mut arm64 = FileManifest::default();
+        arm64.add_file_entry(
+            "myapp",
+            FileEntry::new_from_data(thin_macho(0x0100000c), true),
+        )?;
+        arm64.add_file_entry("lib/foo.so", thin_macho(0x0100000c))?;
+        arm64.add_file_entry("lib/foo.py", b"foo".to_vec())?;
+        arm64.add_file_entry("lib/WHEEL", b"arm64".to_vec())?;
+
+        let mut x86_64 = FileManifest::default();
+        x86_64.add_file_entry(
+            "myapp",
+            FileEntry::new_from_data(thin_macho(0x01000007), true),
+        )?;
+        x86_64.add_file_entry("lib/foo.so", thin_macho(0x01000007))?;
+        x86_64.add_file_entry("lib/foo.py", b"foo".to_vec())?;
+        x86_64.add_file_entry("lib/WHEEL", b"x86_64".to_vec())?;
+        x86_64.add_file_entry("lib/bar.so", thin_macho(0x01000007))?;
+
+        let merged = merge_apple_universal_file_manifests(&[arm64, x86_64.clone()])?;
+        assert_eq!(merged.iter_entries().count(), 5);
+
+        let entries = merged.iter_entries().collect::<BTreeMap<_, _>>();
+
+        let exe = entries[&PathBuf::from("myapp")];
+        assert!(exe.is_executable());
+        let data = exe.resolve_content()?;
+        assert_eq!(&data[0..4], &[0xca, 0xfe, 0xba, 0xbe]);
+        assert!(is_macho(&data));
+        assert_eq!(
+            UniversalBinaryBuilder::default().add_binary(&data)?,
+            2,
+            "universal binary has both architectures"
+        );
+
+        let data = entries[&PathBuf::from("lib/foo.so")].resolve_content()?;
+        assert_eq!(&data[0..4], &[0xca, 0xfe, 0xba, 0xbe]);
+
+        assert_eq!(
+            entries[&PathBuf::from("lib/foo.py")].resolve_content()?,
+            b"foo".to_vec()
+        );
+        assert_eq!(
+            entries[&PathBuf::from("lib/WHEEL")].resolve_content()?,
+            b"arm64".to_vec()
+        );
+        assert_eq!(
+            entries[&PathBuf::from("lib/bar.so")].resolve_content()?,
+            thin_macho(0x01000007)
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_install() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -8,10 +8,13 @@ The `starlark` module and related sub-modules define the
 define Oxidized Python binaries.
 */
 
+pub mod download;
 pub mod env;
 pub mod eval;
 pub mod file;
+pub mod file_loader;
 pub mod file_resource;
+pub mod python_c_embedding_library;
 pub mod python_distribution;
 pub mod python_embedded_resources;
 pub mod python_executable;
@@ -22,6 +25,7 @@ pub mod python_package_distribution_resource;
 pub mod python_package_resource;
 pub mod python_packaging_policy;
 pub mod python_resource;
+pub mod python_test;
 #[cfg(test)]
 mod testutil;
 pub mod util;
@@ -0,0 +1,130 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        project_building::build_c_embedding_library,
+        py_packaging::binary::PythonBinaryBuilder,
+        starlark::env::{get_context, PyOxidizerEnvironmentContext},
+    },
+    anyhow::{anyhow, Context, Result},
+    log::warn,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{ResolvedTarget, ResolvedTargetValue, RunMode},
+    std::sync::Arc,
+};
+
+fn error_context<F, T>(label: &str, f: F) -> Result<T, ValueError>
+where
+    F: FnOnce() -> anyhow::Result<T>,
+{
+    f().map_err(|e| {
+        ValueError::Runtime(RuntimeError {
+            code: "PYOXIDIZER_PYTHON_C_EMBEDDING_LIBRARY",
+            message: format!("{:?}", e),
+            label: label.to_string(),
+        })
+    })
+}
+
+/// A static library and C header exposing an embedded Python interpreter.
+pub struct PythonCEmbeddingLibraryValue {
+    pub exe: Arc<dyn PythonBinaryBuilder>,
+}
+
+impl TypedValue for PythonCEmbeddingLibraryValue {
+    type Holder = Mutable<PythonCEmbeddingLibraryValue>;
+    const TYPE: &'static str = "PythonCEmbeddingLibrary";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl PythonCEmbeddingLibraryValue {
+    fn build(
+        &self,
+        type_values: &TypeValues,
+        target: &str,
+        context: &PyOxidizerEnvironmentContext,
+    ) -> Result<ResolvedTarget> {
+        let shared_build_path = context
+            .shared_rust_build_path(type_values, &*self.exe)
+            .map_err(|_| anyhow!("unable to resolve build path"))?;
+
+        let build = build_c_embedding_library(
+            context.env(),
+            &self.exe.name(),
+            &*self.exe,
+            self.exe.target_triple(),
+            &context.build_opt_level,
+            context.build_release,
+            Some(&shared_build_path),
+        )
+        .context("building C embedding library")?;
+
+        let output_path = context
+            .get_output_path(type_values, target)
+            .map_err(|_| anyhow!("unable to resolve output path"))?;
+        std::fs::create_dir_all(&output_path)
+            .with_context(|| format!("creating output directory {}", output_path.display()))?;
+
+        for (name, data) in [
+            (&build.library_name, &build.library_data),
+            (&build.header_name, &build.header_data),
+        ] {
+            let dest_path = output_path.join(name);
+            warn!("writing {}", dest_path.display());
+            std::fs::write(&dest_path, data)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
+        }
+
+        for (path, entry) in build.binary_data.extra_files.iter_entries() {
+            let dest_path = output_path.join(path);
+            warn!("installing {}", dest_path.display());
+            entry
+                .write_to_path(&dest_path)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
+        }
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path,
+        })
+    }
+
+    fn build_starlark(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let inner = error_context("PythonCEmbeddingLibrary.build()", || {
+            self.build(type_values, &target, &pyoxidizer_context)
+        })?;
+
+        Ok(Value::new(ResolvedTargetValue { inner }))
+    }
+}
+
+starlark_module! { python_c_embedding_library_module =>
+    PythonCEmbeddingLibrary.build(
+        env env,
+        this,
+        target: String
+    ) {
+        let this = this.downcast_ref::<PythonCEmbeddingLibraryValue>().unwrap();
+        this.build_starlark(env, target)
+    }
+}
@@ -8,7 +8,9 @@ use {
         python_executable::PythonExecutableValue,
         python_interpreter_config::PythonInterpreterConfigValue,
         python_packaging_policy::PythonPackagingPolicyValue,
-        python_resource::{add_context_for_value, python_resource_to_value},
+        python_resource::{
+            add_context_for_value, python_resource_to_value, python_resources_to_values,
+        },
     },
     crate::py_packaging::{
         distribution::BinaryLibpythonLinkMode,
@@ -16,12 +18,13 @@ use {
             default_distribution_location, DistributionFlavor, PythonDistribution,
             PythonDistributionLocation,
         },
+        pypy_distribution::PyPyDistribution,
     },
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, Context, Result},
     log::{info, warn},
     python_packaging::{
         policy::PythonPackagingPolicy, resource::PythonResource,
-        resource_collection::PythonResourceAddCollectionContext,
+        resource_collection::PythonResourceAddCollectionContext, wheel::WheelArchive,
     },
     starlark::{
         environment::TypeValues,
@@ -36,26 +39,56 @@ use {
             starlark_signature_extraction, starlark_signatures,
         },
     },
-    starlark_dialect_build_targets::{optional_str_arg, optional_type_arg},
-    std::{ops::Deref, sync::Arc},
+    starlark_dialect_build_targets::{optional_list_arg, optional_str_arg, optional_type_arg},
+    std::{ops::Deref, path::Path, sync::Arc},
+    tugger::starlark::file_manifest::FileManifestValue,
 };
 
+/// Obtain resources in a wheel file as classified by a distribution's default policy.
+fn read_wheel_resources<'a>(
+    dist: &dyn PythonDistribution,
+    path: &Path,
+) -> Result<(Vec<PythonResource<'a>>, PythonPackagingPolicy)> {
+    let policy = dist.create_packaging_policy()?;
+    let wheel = WheelArchive::from_path(path)?;
+
+    let resources = wheel.python_resources(
+        dist.cache_tag(),
+        &dist.python_module_suffixes()?,
+        policy.file_scanner_emit_files(),
+        policy.file_scanner_classify_files(),
+    )?;
+
+    Ok((resources, policy))
+}
+
 /// A Starlark Value wrapper for `PythonDistribution` traits.
 pub struct PythonDistributionValue {
     /// Where the distribution should be obtained from.
     pub source: PythonDistributionLocation,
 
+    /// The flavor of the distribution.
+    pub flavor: DistributionFlavor,
+
     /// The actual distribution.
     ///
     /// Populated on first read.
     pub distribution: Option<Arc<dyn PythonDistribution>>,
+
+    /// The resolved distribution if it is a PyPy distribution.
+    pypy_distribution: Option<Arc<PyPyDistribution>>,
 }
 
 impl PythonDistributionValue {
-    fn from_location(location: PythonDistributionLocation) -> PythonDistributionValue {
+    fn from_location(
+        location: PythonDistributionLocation,
+        flavor: DistributionFlavor,
+    ) -> PythonDistributionValue {
         PythonDistributionValue {
             source: location,
+            flavor,
             distribution: None,
+            pypy_distribution: None,
         }
     }
 
@@ -72,19 +105,31 @@ impl PythonDistributionValue {
 
             let dest_dir = pyoxidizer_context.python_distributions_path()?;
 
-            self.distribution = Some(
-                pyoxidizer_context
+            let to_value_error = |e: anyhow::Error| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:?}", e),
+                    label: label.to_string(),
+                })
+            };
+
+            if self.flavor == DistributionFlavor::PyPy {
+                let dist = pyoxidizer_context
                     .distribution_cache
-                    .resolve_distribution(&self.source, Some(&dest_dir))
-                    .map_err(|e| {
-                        ValueError::from(RuntimeError {
-                            code: "PYOXIDIZER_BUILD",
-                            message: format!("{:?}", e),
-                            label: label.to_string(),
-                        })
-                    })?
-                    .clone_trait(),
-            );
+                    .resolve_pypy_distribution(&self.source, Some(&dest_dir))
+                    .map_err(to_value_error)?;
+
+                self.distribution = Some(dist.clone());
+                self.pypy_distribution = Some(dist);
+            } else {
+                self.distribution = Some(
+                    pyoxidizer_context
+                        .distribution_cache
+                        .resolve_distribution(&self.source, Some(&dest_dir))
+                        .map_err(to_value_error)?
+                        .clone_trait(),
+                );
+            }
         }
 
         Ok(self.distribution.as_ref().unwrap().clone())
@@ -150,7 +195,9 @@ impl PythonDistributionValue {
             build_target, location
         );
 
-        Ok(Value::new(PythonDistributionValue::from_location(location)))
+        Ok(Value::new(PythonDistributionValue::from_location(
+            location, flavor,
+        )))
     }
 
     /// PythonDistribution()
@@ -178,8 +225,9 @@ impl PythonDistributionValue {
             }
         };
 
-        match flavor.as_ref() {
-            "standalone" => (),
+        let flavor = match flavor.as_ref() {
+            "standalone" => DistributionFlavor::Standalone,
+            "pypy" => DistributionFlavor::PyPy,
             v => {
                 return Err(ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
@@ -187,10 +235,11 @@ impl PythonDistributionValue {
                     label: "PythonDistribution()".to_string(),
                 }))
             }
-        }
+        };
 
         Ok(Value::new(PythonDistributionValue::from_location(
             distribution,
+            flavor,
         )))
     }
 
@@ -383,9 +432,84 @@ impl PythonDistributionValue {
             info!("{}", action.to_string());
         }
 
+        builder.set_dev_mode(pyoxidizer_context.build_dev);
+        builder.set_dependency_cache(pyoxidizer_context.dependency_cache.clone());
+
         Ok(Value::new(PythonExecutableValue::new(builder, policy)))
     }
 
+    /// PythonDistribution.to_file_manifest(name, run_module, pip_install=None)
+    #[allow(clippy::wrong_self_convention)]
+    fn to_file_manifest_starlark(
+        &mut self,
+        type_values: &TypeValues,
+        name: String,
+        run_module: String,
+        pip_install: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonDistribution.to_file_manifest()";
+
+        optional_list_arg("pip_install", "string", pip_install)?;
+
+        let pip_install_args = if pip_install.get_type() == "NoneType" {
+            vec![]
+        } else {
+            pip_install
+                .iter()?
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        self.resolve_distribution(type_values, LABEL)?;
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let (manifest, run_path) = self
+            .pypy_distribution
+            .as_ref()
+            .ok_or_else(|| {
+                anyhow!("only PyPy distributions can be converted to a FileManifest; use to_python_executable() instead")
+            })
+            .and_then(|dist| {
+                // Checking must not access the network.
+                let pip_install_args = if pyoxidizer_context.check_mode {
+                    warn!("skipping pip install when checking configuration");
+                    vec![]
+                } else {
+                    pip_install_args
+                };
+
+                dist.to_application_manifest(
+                    pyoxidizer_context.env(),
+                    &name,
+                    &run_module,
+                    &pip_install_args,
+                    pyoxidizer_context.dependency_cache.as_ref(),
+                    pyoxidizer_context.verbose,
+                )
+                .context("materializing PyPy application")
+            })
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:?}", e),
+                    label: LABEL.to_string(),
+                })
+            })?;
+
+        let manifest_value = FileManifestValue::new_from_manifest(manifest)?;
+        manifest_value
+            .downcast_mut::<FileManifestValue>()?
+            .ok_or(ValueError::IncorrectParameterType)?
+            .run_path = Some(run_path);
+
+        Ok(manifest_value)
+    }
+
     pub fn python_resources_starlark(
         &mut self,
         type_values: &TypeValues,
@@ -413,6 +537,34 @@ impl PythonDistributionValue {
 
         Ok(Value::from(values))
     }
+
+    /// PythonDistribution.read_wheel(path)
+    pub fn read_wheel_starlark(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonDistribution.read_wheel()";
+
+        let dist = self.resolve_distribution(type_values, "resolve_distribution")?;
+
+        let (resources, policy) =
+            read_wheel_resources(dist.deref(), Path::new(&path)).map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYTHON_DISTRIBUTION",
+                    message: format!("{:?}", e),
+                    label: LABEL.to_string(),
+                })
+            })?;
+
+        let policy = PythonPackagingPolicyValue::new(policy);
+
+        let values =
+            python_resources_to_values(LABEL, type_values, call_stack, &resources, &policy)?;
+
+        Ok(Value::from(values))
+    }
 }
 
 starlark_module! { python_distribution_module =>
@@ -436,6 +588,11 @@ starlark_module! { python_distribution_module =>
         this.python_resources_starlark(env, cs)
     }
 
+    PythonDistribution.read_wheel(env env, call_stack cs, this, path: String) {
+        let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
+        this.read_wheel_starlark(env, cs, path)
+    }
+
     PythonDistribution.to_python_executable(
         env env,
         call_stack cs,
@@ -454,6 +611,17 @@ starlark_module! { python_distribution_module =>
         )
     }
 
+    PythonDistribution.to_file_manifest(
+        env env,
+        this,
+        name: String,
+        run_module: String,
+        pip_install=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
+        this.to_file_manifest_starlark(env, name, run_module, &pip_install)
+    }
+
     default_python_distribution(
         env env,
         flavor: String = "standalone".to_string(),
@@ -477,7 +645,9 @@ mod tests {
                 python_module_source::PythonModuleSourceValue,
                 python_package_resource::PythonPackageResourceValue, testutil::*,
             },
+            testutil::*,
         },
+        python_packaging::wheel_builder::WheelBuilder,
     };
 
     #[test]
@@ -562,6 +732,19 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_default_python_distribution_unknown_version() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        let err = env
+            .eval("default_python_distribution(python_version='2.7')")
+            .unwrap_err();
+        let message = format!("{:?}", err);
+        assert!(message.as_str().contains("available versions: "));
+
+        Ok(())
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_default_python_distribution_dynamic_windows() {
@@ -618,6 +801,32 @@ mod tests {
         assert_eq!(x.source, wanted);
     }
 
+    #[test]
+    fn test_python_distribution_pypy() {
+        let dist = starlark_ok("PythonDistribution('sha256', url='some_url', flavor='pypy')");
+
+        let x = dist.downcast_ref::<PythonDistributionValue>().unwrap();
+        assert_eq!(x.flavor, DistributionFlavor::PyPy);
+
+        let err = starlark_nok("PythonDistribution('sha256', url='some_url', flavor='other')");
+        assert_eq!(err.message, "invalid distribution flavor other");
+
+        let err = starlark_nok("default_python_distribution(flavor='pypy')");
+        assert!(err
+            .message
+            .as_str()
+            .contains("there are no default PyPy distributions"));
+    }
+
+    #[test]
+    fn test_to_file_manifest_standalone() {
+        let err = starlark_nok("default_python_distribution().to_file_manifest('app', 'app')");
+        assert!(err
+            .message
+            .as_str()
+            .contains("only PyPy distributions can be converted to a FileManifest"));
+    }
+
     #[test]
     fn test_make_python_packaging_policy() {
         let policy = starlark_ok("default_python_distribution().make_python_packaging_policy()");
@@ -662,4 +871,47 @@ mod tests {
             .filter(|v| v.get_type() == PythonPackageResourceValue::TYPE)
             .all(|v| v.get_attr("is_stdlib").unwrap().to_bool()));
     }
+
+    #[test]
+    fn test_read_wheel() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+
+        let mut builder = WheelBuilder::new("my-package", "0.1");
+        builder.add_file("my_package/__init__.py", vec![42])?;
+        builder.add_file("my_package/data.txt", vec![42])?;
+        builder.add_file_data("scripts", "my-script", vec![42])?;
+        let wheel_path = builder.write_wheel_into_directory(temp_dir.path())?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.set_var("wheel_path", Value::from(wheel_path.display().to_string()))
+            .unwrap();
+
+        let resources = env.eval("dist.read_wheel(wheel_path)")?;
+        assert_eq!(resources.get_type(), "list");
+
+        let values = resources.iter().unwrap().to_vec();
+
+        assert!(values
+            .iter()
+            .any(|v| v.get_type() == PythonModuleSourceValue::TYPE
+                && v.get_attr("name").unwrap().to_string() == "my_package"));
+        assert!(values
+            .iter()
+            .any(|v| v.get_type() == PythonPackageResourceValue::TYPE));
+        assert!(values
+            .iter()
+            .any(|v| v.get_type() == "PythonPackageDistributionResource"));
+        assert!(values
+            .iter()
+            .filter(|v| v.get_type() == PythonModuleSourceValue::TYPE)
+            .all(|v| !v.get_attr("is_stdlib").unwrap().to_bool()));
+
+        env.eval("exe = dist.to_python_executable('testapp')")?;
+        env.eval("exe.add_python_resources(dist.read_wheel(wheel_path))")?;
+
+        assert!(env.eval("dist.read_wheel('does-not-exist.whl')").is_err());
+
+        Ok(())
+    }
 }
@@ -6,14 +6,19 @@ use {
     super::{
         env::{get_context, PyOxidizerEnvironmentContext},
         file::FileValue,
-        file_resource::file_manifest_add_python_executable,
+        file_resource::{
+            file_manifest_add_python_executable, file_manifest_add_python_executable_placeholder,
+            merge_apple_universal_file_manifests,
+        },
+        python_c_embedding_library::PythonCEmbeddingLibraryValue,
         python_embedded_resources::PythonEmbeddedResourcesValue,
         python_extension_module::PythonExtensionModuleValue,
         python_module_source::PythonModuleSourceValue,
         python_package_distribution_resource::PythonPackageDistributionResourceValue,
         python_package_resource::PythonPackageResourceValue,
         python_packaging_policy::PythonPackagingPolicyValue,
-        python_resource::{is_resource_starlark_compatible, python_resource_to_value},
+        python_resource::python_resources_to_values,
+        python_test::PythonTestValue,
         util::ToValue,
     },
     crate::{
@@ -21,12 +26,20 @@ use {
         project_building::build_python_executable,
         py_packaging::binary::PythonBinaryBuilder,
         py_packaging::binary::{PackedResourcesLoadMode, WindowsRuntimeDllsMode},
+        py_packaging::git::{direct_url_is_directory, GitCheckout},
+        py_packaging::markers::MarkerEnvironment,
+        py_packaging::packaging_tool::pip_network_args,
+        py_packaging::pdm::pdm_lock_requirements,
+        py_packaging::pipenv::pipfile_lock_requirements,
+        py_packaging::poetry::poetry_lock_requirements,
+        py_packaging::resolver::PYPI_SIMPLE_URL,
+        py_packaging::uv::uv_lock_requirements,
     },
     anyhow::{anyhow, Context, Result},
     linked_hash_map::LinkedHashMap,
     log::{info, warn},
     python_packaging::resource::PythonModuleSource,
-    simple_file_manifest::FileData,
+    simple_file_manifest::{FileData, FileEntry},
     starlark::{
         environment::TypeValues,
         eval::call_stack::CallStack,
@@ -43,8 +56,9 @@ use {
         },
     },
     starlark_dialect_build_targets::{
-        optional_dict_arg, optional_list_arg, optional_str_arg, optional_type_arg,
-        required_list_arg, ResolvedTarget, ResolvedTargetValue, RunMode, ToOptional,
+        optional_dict_arg, optional_int_arg, optional_list_arg, optional_str_arg,
+        optional_type_arg, required_list_arg, ResolvedTarget, ResolvedTargetValue, RunMode,
+        ToOptional,
     },
     std::{
         collections::HashMap,
@@ -56,9 +70,11 @@ use {
     tugger::starlark::{
         code_signing::{handle_signable_event, SigningAction, SigningContext},
         file_manifest::FileManifestValue,
+        macos_application_bundle_builder::MacOsApplicationBundleBuilderValue,
         wix_bundle_builder::WiXBundleBuilderValue,
         wix_msi_builder::WiXMsiBuilderValue,
     },
+    tugger_apple::is_macho,
     tugger_code_signing::SigningDestination,
     tugger_wix::target_triple_to_wix_arch,
 };
@@ -76,23 +92,124 @@ where
     })
 }
 
+/// Resolve pip network arguments from optional Starlark function arguments.
+fn pip_network_args_from_values(
+    index_url: &Value,
+    extra_index_urls: &Value,
+    trusted_hosts: &Value,
+    proxy: &Value,
+    retries: &Value,
+) -> Result<Vec<String>, ValueError> {
+    let index_url = optional_str_arg("index_url", index_url)?;
+    optional_list_arg("extra_index_urls", "string", extra_index_urls)?;
+    optional_list_arg("trusted_hosts", "string", trusted_hosts)?;
+    let proxy = optional_str_arg("proxy", proxy)?;
+    optional_type_arg("retries", "int", retries)?;
+
+    let strings = |value: &Value| -> Result<Vec<String>, ValueError> {
+        match value.get_type() {
+            "list" => Ok(value.iter()?.iter().map(|x| x.to_string()).collect()),
+            _ => Ok(vec![]),
+        }
+    };
+
+    let retries = match retries.get_type() {
+        "int" => Some(u32::try_from(retries.to_int()?).map_err(|_| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_PYTHON_EXECUTABLE",
+                message: "retries must not be negative".to_string(),
+                label: "retries".to_string(),
+            })
+        })?),
+        _ => None,
+    };
+
+    Ok(pip_network_args(
+        index_url.as_deref(),
+        &strings(extra_index_urls)?,
+        &strings(trusted_hosts)?,
+        proxy.as_deref(),
+        retries,
+    ))
+}
+
+/// Resolve pip arguments applying constraints files.
+///
+/// Relative paths are relative to the directory of the configuration file.
+fn pip_constraint_args(
+    type_values: &TypeValues,
+    constraints: &Value,
+) -> Result<Vec<String>, ValueError> {
+    optional_list_arg("constraints", "string", constraints)?;
+
+    if constraints.get_type() != "list" {
+        return Ok(vec![]);
+    }
+
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    Ok(constraints
+        .iter()?
+        .iter()
+        .map(|x| {
+            format!(
+                "--constraint={}",
+                pyoxidizer_context.cwd.join(x.to_string()).display()
+            )
+        })
+        .collect())
+}
+
+/// Whether a packaging operation fetching or building packages is skipped.
+///
+/// Operations are skipped when checking a configuration, as checking must
+/// not access the network or compile code.
+fn skip_package_operation(type_values: &TypeValues, label: &str) -> Result<bool, ValueError> {
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    if pyoxidizer_context.check_mode {
+        warn!("skipping {} when checking configuration", label);
+    }
+
+    Ok(pyoxidizer_context.check_mode)
+}
+
 pub fn build_internal(
-    exe: MutexGuard<Box<dyn PythonBinaryBuilder>>,
+    exe: &dyn PythonBinaryBuilder,
     type_values: &TypeValues,
     target: &str,
     context: &PyOxidizerEnvironmentContext,
 ) -> Result<(ResolvedTarget, PathBuf)> {
-    // Build an executable by writing out a temporary Rust project
-    // and building it.
-    let build = build_python_executable(
-        context.env(),
-        &exe.name(),
-        &**exe,
-        &context.build_target_triple,
-        &context.build_opt_level,
-        context.build_release,
-    )
-    .context("building Python executable")?;
+    let shared_build_path = context
+        .shared_rust_build_path(type_values, exe)
+        .map_err(|_| anyhow!("unable to resolve build path"))?;
+
+    let build;
+    let (exe_name, exe_data) = match context.prebuilt_executables.get(target) {
+        Some((exe_name, exe_data)) => (exe_name, exe_data),
+        None => {
+            // Build an executable by writing out a temporary Rust project
+            // and building it.
+            build = build_python_executable(
+                context.env(),
+                &exe.name(),
+                exe,
+                exe.target_triple(),
+                &context.build_opt_level,
+                context.build_release,
+                Some(&shared_build_path),
+            )
+            .context("building Python executable")?;
+
+            (&build.exe_name, &build.exe_data)
+        }
+    };
 
     let output_path = context
         .get_output_path(type_values, target)
@@ -100,11 +217,11 @@ pub fn build_internal(
     std::fs::create_dir_all(&output_path)
         .with_context(|| format!("creating output directory {}", output_path.display()))?;
 
-    let dest_path = output_path.join(build.exe_name);
+    let dest_path = output_path.join(exe_name);
     warn!("writing executable to {}", dest_path.display());
     let mut fh =
         std::fs::File::create(&dest_path).context(format!("creating {}", dest_path.display()))?;
-    fh.write_all(&build.exe_data)
+    fh.write_all(exe_data)
         .context(format!("writing {}", dest_path.display()))?;
     simple_file_manifest::set_executable(&mut fh).context("making binary executable")?;
 
@@ -176,10 +293,23 @@ impl TypedValue for PythonExecutableValue {
         let exe = self.inner(&format!("PythonExecutable.{}", attribute))?;
 
         match attribute {
+            "cargo_codegen_units" => match exe.cargo_build_settings().codegen_units {
+                Some(value) => Ok(Value::from(value as i64)),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "cargo_features" => Ok(Value::from(exe.cargo_build_settings().features.clone())),
+            "cargo_lto" => Ok(exe.cargo_build_settings().lto.to_value()),
+            "cargo_panic" => Ok(exe.cargo_build_settings().panic.to_value()),
+            "cargo_rustflags" => Ok(Value::from(exe.cargo_build_settings().rust_flags.clone())),
+            "dev_mode" => Ok(Value::from(exe.dev_mode())),
             "licenses_filename" => Ok(exe.licenses_filename().to_value()),
+            "packed_resources_load_injected" => {
+                Ok(Value::from(exe.packed_resources_load_injected()))
+            }
             "packed_resources_load_mode" => {
                 Ok(Value::from(exe.packed_resources_load_mode().to_string()))
             }
+            "target_triple" => Ok(Value::from(exe.target_triple())),
             "tcl_files_path" => match exe.tcl_files_path() {
                 Some(value) => Ok(Value::from(value.to_string())),
                 None => Ok(Value::from(NoneType::None)),
@@ -199,8 +329,16 @@ impl TypedValue for PythonExecutableValue {
     fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
         Ok(matches!(
             attribute,
-            "licenses_filename"
+            "cargo_codegen_units"
+                | "cargo_features"
+                | "cargo_lto"
+                | "cargo_panic"
+                | "cargo_rustflags"
+                | "dev_mode"
+                | "licenses_filename"
+                | "packed_resources_load_injected"
                 | "packed_resources_load_mode"
+                | "target_triple"
                 | "tcl_files_path"
                 | "windows_runtime_dlls_mode"
                 | "windows_subsystem"
@@ -210,13 +348,98 @@ impl TypedValue for PythonExecutableValue {
     fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
         let mut exe = self.inner(&format!("PythonExecutable.{}", attribute))?;
 
+        let invalid_value = |message: String| {
+            ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message,
+                label: format!("{}.{}", Self::TYPE, attribute),
+            })
+        };
+
         match attribute {
+            "cargo_codegen_units" => {
+                let value = optional_int_arg("cargo_codegen_units", &value)?
+                    .map(|x| {
+                        u32::try_from(x).ok().filter(|x| *x > 0).ok_or_else(|| {
+                            invalid_value(format!("{} is not a positive integer", x))
+                        })
+                    })
+                    .transpose()?;
+
+                let mut settings = exe.cargo_build_settings().clone();
+                settings.codegen_units = value;
+                exe.set_cargo_build_settings(settings);
+
+                Ok(())
+            }
+            "cargo_features" | "cargo_rustflags" => {
+                required_list_arg(attribute, "string", &value)?;
+                let values = value
+                    .iter()?
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>();
+
+                let mut settings = exe.cargo_build_settings().clone();
+                if attribute == "cargo_features" {
+                    settings.features = values;
+                } else {
+                    settings.rust_flags = values;
+                }
+                exe.set_cargo_build_settings(settings);
+
+                Ok(())
+            }
+            "cargo_lto" => {
+                let value = optional_str_arg("cargo_lto", &value)?;
+                if let Some(lto) = &value {
+                    if !matches!(lto.as_str(), "false" | "true" | "thin" | "fat" | "off") {
+                        return Err(invalid_value(format!(
+                            "{} is not a valid LTO setting; must be 'false', 'true', 'thin', 'fat' or 'off'",
+                            lto
+                        )));
+                    }
+                }
+
+                let mut settings = exe.cargo_build_settings().clone();
+                settings.lto = value;
+                exe.set_cargo_build_settings(settings);
+
+                Ok(())
+            }
+            "cargo_panic" => {
+                let value = optional_str_arg("cargo_panic", &value)?;
+                if let Some(panic) = &value {
+                    if !matches!(panic.as_str(), "unwind" | "abort") {
+                        return Err(invalid_value(format!(
+                            "{} is not a valid panic strategy; must be 'unwind' or 'abort'",
+                            panic
+                        )));
+                    }
+                }
+
+                let mut settings = exe.cargo_build_settings().clone();
+                settings.panic = value;
+                exe.set_cargo_build_settings(settings);
+
+                Ok(())
+            }
+            "dev_mode" => {
+                exe.set_dev_mode(value.to_bool());
+
+                Ok(())
+            }
             "licenses_filename" => {
                 let value = optional_str_arg("licenses_filename", &value)?;
                 exe.set_licenses_filename(value);
 
                 Ok(())
             }
+            "packed_resources_load_injected" => {
+                exe.set_packed_resources_load_injected(value.to_bool());
+
+                Ok(())
+            }
             "packed_resources_load_mode" => {
                 exe.set_packed_resources_load_mode(
                     PackedResourcesLoadMode::try_from(value.to_string().as_str()).map_err(|e| {
@@ -230,6 +453,12 @@ impl TypedValue for PythonExecutableValue {
 
                 Ok(())
             }
+            "target_triple" => {
+                exe.set_target_triple(value.to_string().as_str())
+                    .map_err(|e| invalid_value(format!("{:?}", e)))?;
+
+                Ok(())
+            }
             "tcl_files_path" => {
                 exe.set_tcl_files_path(value.to_optional());
 
@@ -287,7 +516,7 @@ impl PythonExecutableValue {
         let exe = self.inner(LABEL)?;
 
         let (inner, exe_path) = error_context(LABEL, || {
-            build_internal(exe, type_values, &target, &pyoxidizer_context)
+            build_internal(&**exe, type_values, &target, &pyoxidizer_context)
         })?;
 
         let candidate = exe_path.clone().into();
@@ -343,18 +572,40 @@ impl PythonExecutableValue {
         Ok(Value::new(value))
     }
 
-    /// PythonExecutable.pip_download(args)
+    /// PythonExecutable.pip_download(args, index_url=None, extra_index_urls=None, trusted_hosts=None, proxy=None, retries=None, constraints=None)
+    #[allow(clippy::too_many_arguments)]
     pub fn pip_download(
         &mut self,
         type_values: &TypeValues,
         call_stack: &mut CallStack,
         args: &Value,
+        index_url: &Value,
+        extra_index_urls: &Value,
+        trusted_hosts: &Value,
+        proxy: &Value,
+        retries: &Value,
+        constraints: &Value,
     ) -> ValueResult {
         const LABEL: &str = "PythonExecutable.pip_download()";
 
         required_list_arg("args", "string", args)?;
 
-        let args: Vec<String> = args.iter()?.iter().map(|x| x.to_string()).collect();
+        if skip_package_operation(type_values, LABEL)? {
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
+        // Arguments of the call follow those of the packaging policy. So they
+        // take precedence.
+        let mut pip_args = pip_network_args_from_values(
+            index_url,
+            extra_index_urls,
+            trusted_hosts,
+            proxy,
+            retries,
+        )?;
+        pip_args.extend(pip_constraint_args(type_values, constraints)?);
+        pip_args.extend(args.iter()?.iter().map(|x| x.to_string()));
+        let args = pip_args;
 
         let pyoxidizer_context_value = get_context(type_values)?;
         let pyoxidizer_context = pyoxidizer_context_value
@@ -369,37 +620,51 @@ impl PythonExecutableValue {
             exe.pip_download(pyoxidizer_context.env(), pyoxidizer_context.verbose, &args)
         })?;
 
-        let resources = resources
-            .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    LABEL,
-                    type_values,
-                    call_stack,
-                    r,
-                    &python_packaging_policy,
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+        let resources = python_resources_to_values(
+            LABEL,
+            type_values,
+            call_stack,
+            &resources,
+            &python_packaging_policy,
+        )?;
 
         Ok(Value::from(resources))
     }
 
-    /// PythonExecutable.pip_install(args, extra_envs=None)
+    /// PythonExecutable.pip_install(args, extra_envs=None, index_url=None, extra_index_urls=None, trusted_hosts=None, proxy=None, retries=None, constraints=None)
+    #[allow(clippy::too_many_arguments)]
     pub fn pip_install(
         &mut self,
         type_values: &TypeValues,
         call_stack: &mut CallStack,
         args: &Value,
         extra_envs: &Value,
+        index_url: &Value,
+        extra_index_urls: &Value,
+        trusted_hosts: &Value,
+        proxy: &Value,
+        retries: &Value,
+        constraints: &Value,
     ) -> ValueResult {
         const LABEL: &str = "PythonExecutable.pip_install()";
 
         required_list_arg("args", "string", args)?;
         optional_dict_arg("extra_envs", "string", "string", extra_envs)?;
 
-        let args: Vec<String> = args.iter()?.iter().map(|x| x.to_string()).collect();
+        if skip_package_operation(type_values, LABEL)? {
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
+        let mut pip_args = pip_network_args_from_values(
+            index_url,
+            extra_index_urls,
+            trusted_hosts,
+            proxy,
+            retries,
+        )?;
+        pip_args.extend(pip_constraint_args(type_values, constraints)?);
+        pip_args.extend(args.iter()?.iter().map(|x| x.to_string()));
+        let args = pip_args;
 
         let extra_envs = match extra_envs.get_type() {
             "dict" => extra_envs
@@ -433,19 +698,391 @@ impl PythonExecutableValue {
             )
         })?;
 
-        let resources = resources
-            .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    LABEL,
-                    type_values,
-                    call_stack,
-                    r,
-                    &python_packaging_policy,
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+        let resources = python_resources_to_values(
+            LABEL,
+            type_values,
+            call_stack,
+            &resources,
+            &python_packaging_policy,
+        )?;
+
+        Ok(Value::from(resources))
+    }
+
+    /// Run `pip install` for requirements resolved from a lock file.
+    ///
+    /// `resolve_requirements` receives the content of the lock file at `path`
+    /// and returns the content of a requirements file.
+    #[allow(clippy::too_many_arguments)]
+    fn pip_install_lock_file(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        label: &str,
+        path: &str,
+        resolve_requirements: impl FnOnce(&str) -> Result<String>,
+        extra_envs: &Value,
+        constraints: &Value,
+    ) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let temp_dir = error_context(label, || {
+            let data =
+                std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?;
+            let requirements = resolve_requirements(&data)?;
+
+            if requirements.is_empty() {
+                return Ok(None);
+            }
+
+            let temp_dir = pyoxidizer_context
+                .env()
+                .temporary_directory("pyoxidizer-lock-file")?;
+            std::fs::write(temp_dir.path().join("requirements.txt"), requirements)?;
+
+            Ok(Some(temp_dir))
+        })?;
+
+        // pip refuses to run without requirements.
+        let temp_dir = match temp_dir {
+            Some(temp_dir) => temp_dir,
+            None => return Ok(Value::from(Vec::<Value>::new())),
+        };
+
+        // Lock files pin every dependency. So pip shouldn't resolve any.
+        let args = vec![
+            "--no-deps".to_string(),
+            "-r".to_string(),
+            temp_dir
+                .path()
+                .join("requirements.txt")
+                .display()
+                .to_string(),
+        ];
+
+        let res = self.pip_install(
+            type_values,
+            call_stack,
+            &Value::from(args.into_iter().map(Value::from).collect::<Vec<_>>()),
+            extra_envs,
+            &Value::from(NoneType::None),
+            &Value::from(NoneType::None),
+            &Value::from(NoneType::None),
+            &Value::from(NoneType::None),
+            &Value::from(NoneType::None),
+            constraints,
+        );
+
+        error_context(label, || {
+            temp_dir.close().context("closing temporary directory")
+        })?;
+
+        res
+    }
+
+    /// PythonExecutable.pip_install_poetry_lock(path, groups=None, extra_envs=None, constraints=None)
+    pub fn pip_install_poetry_lock(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+        groups: &Value,
+        extra_envs: &Value,
+        constraints: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.pip_install_poetry_lock()";
+
+        optional_list_arg("groups", "string", groups)?;
+
+        let groups = match groups.get_type() {
+            "list" => groups.iter()?.iter().map(|x| x.to_string()).collect(),
+            _ => vec!["main".to_string()],
+        };
+
+        self.pip_install_lock_file(
+            type_values,
+            call_stack,
+            LABEL,
+            &path,
+            |data| poetry_lock_requirements(data, &groups),
+            extra_envs,
+            constraints,
+        )
+    }
+
+    /// PythonExecutable.pip_install_pipfile_lock(path, sections=None, extra_envs=None, constraints=None)
+    pub fn pip_install_pipfile_lock(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+        sections: &Value,
+        extra_envs: &Value,
+        constraints: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.pip_install_pipfile_lock()";
+
+        optional_list_arg("sections", "string", sections)?;
+
+        let sections = match sections.get_type() {
+            "list" => sections.iter()?.iter().map(|x| x.to_string()).collect(),
+            _ => vec!["default".to_string()],
+        };
+
+        self.pip_install_lock_file(
+            type_values,
+            call_stack,
+            LABEL,
+            &path,
+            |data| pipfile_lock_requirements(data, &sections),
+            extra_envs,
+            constraints,
+        )
+    }
+
+    /// Obtain the marker environment of the binary being built.
+    fn marker_environment(&self, label: &str) -> Result<MarkerEnvironment, ValueError> {
+        let exe = self.inner(label)?;
+
+        Ok(MarkerEnvironment::from_target(
+            exe.target_triple(),
+            exe.python_version(),
+        ))
+    }
+
+    /// PythonExecutable.pip_install_uv_lock(path, groups=None, extra_envs=None, constraints=None)
+    pub fn pip_install_uv_lock(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+        groups: &Value,
+        extra_envs: &Value,
+        constraints: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.pip_install_uv_lock()";
+
+        optional_list_arg("groups", "string", groups)?;
+
+        let groups = match groups.get_type() {
+            "list" => groups.iter()?.iter().map(|x| x.to_string()).collect(),
+            _ => vec![],
+        };
+
+        let marker_env = self.marker_environment(LABEL)?;
+
+        self.pip_install_lock_file(
+            type_values,
+            call_stack,
+            LABEL,
+            &path,
+            |data| uv_lock_requirements(data, &groups, &marker_env),
+            extra_envs,
+            constraints,
+        )
+    }
+
+    /// PythonExecutable.pip_install_pdm_lock(path, groups=None, extra_envs=None, constraints=None)
+    pub fn pip_install_pdm_lock(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+        groups: &Value,
+        extra_envs: &Value,
+        constraints: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.pip_install_pdm_lock()";
+
+        optional_list_arg("groups", "string", groups)?;
+
+        let groups = match groups.get_type() {
+            "list" => groups.iter()?.iter().map(|x| x.to_string()).collect(),
+            _ => vec!["default".to_string()],
+        };
+
+        let marker_env = self.marker_environment(LABEL)?;
+
+        self.pip_install_lock_file(
+            type_values,
+            call_stack,
+            LABEL,
+            &path,
+            |data| pdm_lock_requirements(data, &groups, &marker_env),
+            extra_envs,
+            constraints,
+        )
+    }
+
+    /// PythonExecutable.pip_install_git(url, commit, subdirectory=None, extra_envs=None, constraints=None)
+    #[allow(clippy::too_many_arguments)]
+    pub fn pip_install_git(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        url: String,
+        commit: String,
+        subdirectory: &Value,
+        extra_envs: &Value,
+        constraints: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.pip_install_git()";
+
+        let subdirectory = optional_str_arg("subdirectory", subdirectory)?;
+
+        // A leading dash would be interpreted as an option by git.
+        if url.starts_with('-') {
+            return Err(ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_PYTHON_EXECUTABLE",
+                message: format!("invalid git URL {}: URLs must not begin with -", url),
+                label: "url".to_string(),
+            }));
+        }
+
+        if skip_package_operation(type_values, LABEL)? {
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let (temp_dir, checkout) = error_context(LABEL, || {
+            if matches!(&pyoxidizer_context.dependency_cache, Some(cache) if cache.is_offline()) {
+                return Err(anyhow!(
+                    "cannot clone {} when building offline; git dependencies aren't cached",
+                    url
+                ));
+            }
+
+            let temp_dir = pyoxidizer_context
+                .env()
+                .temporary_directory("pyoxidizer-git")?;
+            let checkout =
+                GitCheckout::clone_commit(&url, &commit, &temp_dir.path().join("checkout"))?;
+
+            Ok((temp_dir, checkout))
+        })?;
+
+        let project_dir = match &subdirectory {
+            Some(subdirectory) => checkout.path().join(subdirectory),
+            None => checkout.path().to_path_buf(),
+        };
+
+        let res = self.pip_install(
+            type_values,
+            call_stack,
+            &Value::from(vec![Value::from(project_dir.display().to_string())]),
+            extra_envs,
+            &Value::from(NoneType::None),
+            &Value::from(NoneType::None),
+            &Value::from(NoneType::None),
+            &Value::from(NoneType::None),
+            &Value::from(NoneType::None),
+            constraints,
+        );
+
+        let resources = res.and_then(|resources| {
+            let values = resources.iter()?.iter().collect::<Vec<_>>();
+
+            // The package installed from the checkout is the one whose
+            // direct_url.json points at it. Everything else is a dependency.
+            let mut package = None;
+            for value in &values {
+                if let Some(resource) = value.downcast_ref::<PythonPackageDistributionResourceValue>()
+                {
+                    let inner = resource.inner(LABEL)?;
+                    if inner.r.name == "direct_url.json"
+                        && matches!(inner.r.data.resolve_content(), Ok(data) if direct_url_is_directory(&data, &project_dir))
+                    {
+                        package = Some(inner.r.package.clone());
+                    }
+                }
+            }
+
+            let package = package.ok_or_else(|| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("unable to find the package installed from {}", url),
+                    label: LABEL.to_string(),
+                })
+            })?;
+
+            let metadata = checkout.provenance_metadata(subdirectory.as_deref());
+            for value in &values {
+                if let Some(resource) = value.downcast_ref::<PythonPackageDistributionResourceValue>()
+                {
+                    let mut inner = resource.inner(LABEL)?;
+                    if inner.r.package == package {
+                        if let Some(add_context) = inner.add_context.as_mut() {
+                            add_context.metadata.extend(metadata.clone());
+                        }
+                    }
+                }
+            }
+
+            Ok(resources)
+        });
+
+        error_context(LABEL, || {
+            temp_dir.close().context("closing temporary directory")
+        })?;
+
+        resources
+    }
+
+    /// PythonExecutable.read_conda_lock(path)
+    pub fn read_conda_lock(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.read_conda_lock()";
+
+        let python_packaging_policy = self.python_packaging_policy();
+
+        let mut exe = self.inner(LABEL)?;
+
+        let resources = error_context(LABEL, || exe.read_conda_lock(Path::new(&path)))?;
+
+        let resources = python_resources_to_values(
+            LABEL,
+            type_values,
+            call_stack,
+            &resources,
+            &python_packaging_policy,
+        )?;
+
+        Ok(Value::from(resources))
+    }
+
+    /// PythonExecutable.read_conda_package(path)
+    pub fn read_conda_package(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.read_conda_package()";
+
+        let python_packaging_policy = self.python_packaging_policy();
+
+        let mut exe = self.inner(LABEL)?;
+
+        let resources = error_context(LABEL, || exe.read_conda_package(Path::new(&path)))?;
+
+        let resources = python_resources_to_values(
+            LABEL,
+            type_values,
+            call_stack,
+            &resources,
+            &python_packaging_policy,
+        )?;
 
         Ok(Value::from(resources))
     }
@@ -475,19 +1112,13 @@ impl PythonExecutableValue {
         let resources =
             error_context(LABEL, || exe.read_package_root(Path::new(&path), &packages))?;
 
-        let resources = resources
-            .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    LABEL,
-                    type_values,
-                    call_stack,
-                    r,
-                    &python_packaging_policy,
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+        let resources = python_resources_to_values(
+            LABEL,
+            type_values,
+            call_stack,
+            &resources,
+            &python_packaging_policy,
+        )?;
 
         Ok(Value::from(resources))
     }
@@ -507,19 +1138,87 @@ impl PythonExecutableValue {
 
         let resources = error_context(LABEL, || exe.read_virtualenv(Path::new(&path)))?;
 
-        let resources = resources
+        let resources = python_resources_to_values(
+            LABEL,
+            type_values,
+            call_stack,
+            &resources,
+            &python_packaging_policy,
+        )?;
+
+        Ok(Value::from(resources))
+    }
+
+    /// PythonExecutable.resolve_requirements(requirements, index_url=None, find_links=None, no_index=False)
+    pub fn resolve_requirements(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        requirements: &Value,
+        index_url: &Value,
+        find_links: &Value,
+        no_index: bool,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.resolve_requirements()";
+
+        required_list_arg("requirements", "string", requirements)?;
+        let index_url = optional_str_arg("index_url", index_url)?;
+        optional_list_arg("find_links", "string", find_links)?;
+
+        if skip_package_operation(type_values, LABEL)? {
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
+        let requirements = requirements
+            .iter()?
             .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    LABEL,
-                    type_values,
-                    call_stack,
-                    r,
-                    &python_packaging_policy,
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>();
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let find_links = match find_links.get_type() {
+            "list" => find_links
+                .iter()?
+                .iter()
+                .map(|x| pyoxidizer_context.cwd.join(x.to_string()))
+                .collect(),
+            _ => vec![],
+        };
+
+        let python_packaging_policy = self.python_packaging_policy();
+
+        let policy_index_url = python_packaging_policy
+            .inner(LABEL)?
+            .pip_index_url()
+            .map(String::from);
+
+        let index_url = if no_index {
+            None
+        } else {
+            Some(
+                index_url
+                    .or(policy_index_url)
+                    .unwrap_or_else(|| PYPI_SIMPLE_URL.to_string()),
+            )
+        };
+
+        let mut exe = self.inner(LABEL)?;
+
+        let resources = error_context(LABEL, || {
+            exe.resolve_requirements(&requirements, index_url.as_deref(), &find_links)
+        })?;
+
+        let resources = python_resources_to_values(
+            LABEL,
+            type_values,
+            call_stack,
+            &resources,
+            &python_packaging_policy,
+        )?;
 
         Ok(Value::from(resources))
     }
@@ -538,6 +1237,10 @@ impl PythonExecutableValue {
         optional_dict_arg("extra_envs", "string", "string", extra_envs)?;
         optional_list_arg("extra_global_arguments", "string", extra_global_arguments)?;
 
+        if skip_package_operation(type_values, LABEL)? {
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
         let extra_envs = match extra_envs.get_type() {
             "dict" => extra_envs
                 .iter()?
@@ -588,19 +1291,13 @@ impl PythonExecutableValue {
             )
         })?;
 
-        let resources = resources
-            .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    LABEL,
-                    type_values,
-                    call_stack,
-                    r,
-                    &python_packaging_policy,
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+        let resources = python_resources_to_values(
+            LABEL,
+            type_values,
+            call_stack,
+            &resources,
+            &python_packaging_policy,
+        )?;
 
         warn!(
             "collected {} resources from setup.py install",
@@ -767,6 +1464,111 @@ impl PythonExecutableValue {
         Ok(Value::new(NoneType::None))
     }
 
+    /// PythonExecutable.add_dev_overlay(path, packages)
+    pub fn add_dev_overlay(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+        packages: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.add_dev_overlay()";
+
+        required_list_arg("packages", "string", packages)?;
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        // The path is baked into development binaries. So it must not depend
+        // on the directory they are run from.
+        let path = pyoxidizer_context.cwd.join(path);
+
+        // Resources are always added so toggling development mode after this
+        // call works. Development binaries exclude them when built.
+        let resources = self.read_package_root(
+            type_values,
+            call_stack,
+            path.display().to_string(),
+            packages,
+        )?;
+        self.add_python_resources(&resources)?;
+
+        let packages = packages
+            .iter()?
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>();
+
+        let mut exe = self.inner(LABEL)?;
+        error_context(LABEL, || exe.add_dev_overlay(&path, &packages))?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.add_data_files(namespace, path)
+    pub fn add_data_files(
+        &mut self,
+        type_values: &TypeValues,
+        namespace: String,
+        path: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.add_data_files()";
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = pyoxidizer_context.cwd.join(path);
+
+        let mut exe = self.inner(LABEL)?;
+
+        error_context(LABEL, || {
+            let mut files = vec![];
+
+            if path.is_dir() {
+                for entry in walkdir::WalkDir::new(&path).sort_by_file_name() {
+                    let entry = entry?;
+
+                    if entry.file_type().is_dir() {
+                        continue;
+                    }
+
+                    let relative = entry.path().strip_prefix(&path)?;
+                    let components = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy().to_string())
+                        .collect::<Vec<_>>();
+
+                    files.push((components.join("/"), entry.path().to_path_buf()));
+                }
+            } else {
+                let filename = path
+                    .file_name()
+                    .ok_or_else(|| anyhow!("unable to resolve file name of {}", path.display()))?
+                    .to_string_lossy()
+                    .to_string();
+
+                files.push((filename, path.clone()));
+            }
+
+            for (relative, file_path) in files {
+                let entry = FileEntry::try_from(file_path.as_path())
+                    .with_context(|| format!("reading {}", file_path.display()))?;
+
+                for action in exe.add_data_file(&format!("{}/{}", namespace, relative), &entry)? {
+                    info!("{}", action.to_string());
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
     /// Add licensing information from a `Cargo.toml` manifest.
     pub fn add_cargo_manifest_licensing(
         &mut self,
@@ -824,6 +1626,34 @@ impl PythonExecutableValue {
         }))
     }
 
+    /// PythonExecutable.to_c_embedding_library()
+    pub fn to_c_embedding_library(&self) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.to_c_embedding_library()";
+
+        Ok(Value::new(PythonCEmbeddingLibraryValue {
+            exe: self.inner(LABEL)?.clone_trait(),
+        }))
+    }
+
+    /// PythonExecutable.to_test(module="pytest", args=None)
+    pub fn to_test(&self, module: String, args: &Value) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.to_test()";
+
+        optional_list_arg("args", "string", args)?;
+
+        let args = match args.get_type() {
+            "list" => args.iter()?.iter().map(|x| x.to_string()).collect(),
+            _ => vec![],
+        };
+
+        let mut exe = self.inner(LABEL)?.clone_trait();
+        Arc::get_mut(&mut exe)
+            .expect("cloned builder should not be shared")
+            .set_run_module(&module);
+
+        Ok(Value::new(PythonTestValue { exe, args }))
+    }
+
     /// PythonExecutable.to_file_manifest(prefix)
     pub fn to_file_manifest(&self, type_values: &TypeValues, prefix: String) -> ValueResult {
         const LABEL: &str = "PythonExecutable.to_file_manifest()";
@@ -840,16 +1670,27 @@ impl PythonExecutableValue {
             .unwrap();
 
         let exe = self.inner(LABEL)?;
+        let shared_build_path = pyoxidizer_context.shared_rust_build_path(type_values, &**exe)?;
 
         error_context(LABEL, || {
+            if pyoxidizer_context.check_mode {
+                return file_manifest_add_python_executable_placeholder(
+                    &mut manifest,
+                    &prefix,
+                    &**exe,
+                    exe.target_triple(),
+                );
+            }
+
             file_manifest_add_python_executable(
                 &mut manifest,
                 pyoxidizer_context.env(),
                 &prefix,
                 &**exe,
-                &pyoxidizer_context.build_target_triple,
+                exe.target_triple(),
                 pyoxidizer_context.build_release,
                 &pyoxidizer_context.build_opt_level,
+                Some(&shared_build_path),
             )
             .context("adding PythonExecutable to FileManifest")
         })?;
@@ -857,6 +1698,162 @@ impl PythonExecutableValue {
         Ok(manifest_value.clone())
     }
 
+    /// PythonExecutable.to_universal_file_manifest(other, prefix)
+    pub fn to_universal_file_manifest(
+        &self,
+        type_values: &TypeValues,
+        other: &Value,
+        prefix: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.to_universal_file_manifest()";
+
+        let other = other
+            .downcast_ref::<PythonExecutableValue>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let (name, target_triple) = {
+            let exe = self.inner(LABEL)?;
+            (exe.name(), exe.target_triple().to_string())
+        };
+        let (other_name, other_target_triple) = {
+            let exe = other.inner(LABEL)?;
+            (exe.name(), exe.target_triple().to_string())
+        };
+
+        error_context(LABEL, || {
+            let mut triples = [target_triple.as_str(), other_target_triple.as_str()];
+            triples.sort_unstable();
+
+            if triples != ["aarch64-apple-darwin", "x86_64-apple-darwin"] {
+                return Err(anyhow!(
+                    "universal binaries require executables targeting aarch64-apple-darwin and x86_64-apple-darwin; got {} and {}",
+                    target_triple,
+                    other_target_triple
+                ));
+            }
+
+            if name != other_name {
+                return Err(anyhow!(
+                    "executables must have the same name; got {} and {}",
+                    name,
+                    other_name
+                ));
+            }
+
+            Ok(())
+        })?;
+
+        let mut manifests = vec![];
+        let mut run_path = None;
+
+        for exe in [self, &*other] {
+            let manifest_value = exe.to_file_manifest(type_values, prefix.clone())?;
+            let manifest_value = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+
+            manifests.push(manifest_value.inner(LABEL)?.clone());
+            run_path = manifest_value.run_path.clone();
+        }
+
+        let manifest = error_context(LABEL, || {
+            merge_apple_universal_file_manifests(&manifests)
+                .context("merging architecture-specific files")
+        })?;
+
+        let manifest_value = FileManifestValue::new_from_args()?;
+        let mut value = manifest_value
+            .downcast_mut::<FileManifestValue>()
+            .unwrap()
+            .unwrap();
+
+        error_context(LABEL, || {
+            value
+                .inner(LABEL)
+                .map_err(|e| anyhow!("{:?}", e))?
+                .add_manifest(&manifest)
+                .context("adding merged files")
+        })?;
+        value.run_path = run_path;
+        drop(value);
+
+        Ok(manifest_value)
+    }
+
+    /// PythonExecutable.to_macos_application_bundle_builder(bundle_name, identifier, version, display_name=None)
+    pub fn to_macos_application_bundle_builder(
+        &self,
+        type_values: &TypeValues,
+        bundle_name: String,
+        identifier: String,
+        version: String,
+        display_name: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.to_macos_application_bundle_builder()";
+
+        let display_name =
+            optional_str_arg("display_name", display_name)?.unwrap_or_else(|| bundle_name.clone());
+
+        let manifest_value = self.to_file_manifest(type_values, ".".to_string())?;
+        let manifest_value = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+        let manifest = manifest_value.inner(LABEL)?;
+
+        let builder_value = MacOsApplicationBundleBuilderValue::new_from_args(bundle_name)?;
+        let mut builder = builder_value
+            .downcast_mut::<MacOsApplicationBundleBuilderValue>()
+            .unwrap()
+            .unwrap();
+
+        error_context(LABEL, || {
+            let exe_path = manifest_value
+                .run_path
+                .clone()
+                .ok_or_else(|| anyhow!("unable to resolve path of executable"))?;
+
+            // The executable locates files relative to itself, so everything
+            // goes in Contents/MacOS.
+            for (path, entry) in manifest.iter_entries() {
+                if *path != exe_path && !is_macho(&entry.resolve_content()?) {
+                    warn!(
+                        "{} is not code; code signing rejects files in Contents/MacOS that aren't code; \
+                        consider loading resources from memory",
+                        path.display()
+                    );
+                }
+
+                builder
+                    .inner
+                    .add_file_macos(path, entry.clone())
+                    .with_context(|| format!("adding {}", path.display()))?;
+            }
+
+            builder
+                .inner
+                .set_info_plist_required_keys(
+                    display_name,
+                    identifier,
+                    version.clone(),
+                    "????",
+                    exe_path.display(),
+                )
+                .context("setting Info.plist required keys")?;
+            builder
+                .inner
+                .set_info_plist_key("CFBundleShortVersionString", version)
+                .context("setting CFBundleShortVersionString")?;
+            builder
+                .inner
+                .set_info_plist_key("CFBundleInfoDictionaryVersion", "6.0")
+                .context("setting CFBundleInfoDictionaryVersion")?;
+            builder
+                .inner
+                .set_info_plist_key("NSHighResolutionCapable", true)
+                .context("setting NSHighResolutionCapable")?;
+
+            Ok(())
+        })?;
+
+        Ok(builder_value.clone())
+    }
+
     /// PythonExecutable.to_wix_bundle_builder(id_prefix, name, version, manufacturer, msi_builder_callback)
     #[allow(clippy::too_many_arguments)]
     pub fn to_wix_bundle_builder(
@@ -919,6 +1916,9 @@ impl PythonExecutableValue {
             "x86_64-pc-windows-msvc" => {
                 bundle_builder.add_vc_redistributable(type_values, "x64".to_string())?;
             }
+            "aarch64-pc-windows-msvc" => {
+                bundle_builder.add_vc_redistributable(type_values, "arm64".to_string())?;
+            }
             _ => {}
         }
 
@@ -1003,45 +2003,164 @@ impl PythonExecutableValue {
 
         Ok(Value::new(NoneType::None))
     }
-}
+}
+
+starlark_module! { python_executable_env =>
+    PythonExecutable.build(env env, call_stack cs, this, target: String) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.build(env, cs, target)
+    }
+
+    PythonExecutable.make_python_module_source(
+        env env,
+        call_stack cs,
+        this,
+        name: String,
+        source: String,
+        is_package: bool = false
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.make_python_module_source(env, cs, name, source, is_package)
+    }
+
+    PythonExecutable.pip_download(
+        env env,
+        call_stack cs,
+        this,
+        args,
+        index_url=NoneType::None,
+        extra_index_urls=NoneType::None,
+        trusted_hosts=NoneType::None,
+        proxy=NoneType::None,
+        retries=NoneType::None,
+        constraints=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_download(
+            env,
+            cs,
+            &args,
+            &index_url,
+            &extra_index_urls,
+            &trusted_hosts,
+            &proxy,
+            &retries,
+            &constraints,
+        )
+    }
+
+    PythonExecutable.pip_install(
+        env env,
+        call_stack cs,
+        this,
+        args,
+        extra_envs=NoneType::None,
+        index_url=NoneType::None,
+        extra_index_urls=NoneType::None,
+        trusted_hosts=NoneType::None,
+        proxy=NoneType::None,
+        retries=NoneType::None,
+        constraints=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_install(
+            env,
+            cs,
+            &args,
+            &extra_envs,
+            &index_url,
+            &extra_index_urls,
+            &trusted_hosts,
+            &proxy,
+            &retries,
+            &constraints,
+        )
+    }
+
+    PythonExecutable.pip_install_poetry_lock(
+        env env,
+        call_stack cs,
+        this,
+        path: String,
+        groups=NoneType::None,
+        extra_envs=NoneType::None,
+        constraints=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_install_poetry_lock(env, cs, path, &groups, &extra_envs, &constraints)
+    }
+
+    PythonExecutable.pip_install_pipfile_lock(
+        env env,
+        call_stack cs,
+        this,
+        path: String,
+        sections=NoneType::None,
+        extra_envs=NoneType::None,
+        constraints=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_install_pipfile_lock(env, cs, path, &sections, &extra_envs, &constraints)
+    }
+
+    PythonExecutable.pip_install_uv_lock(
+        env env,
+        call_stack cs,
+        this,
+        path: String,
+        groups=NoneType::None,
+        extra_envs=NoneType::None,
+        constraints=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_install_uv_lock(env, cs, path, &groups, &extra_envs, &constraints)
+    }
 
-starlark_module! { python_executable_env =>
-    PythonExecutable.build(env env, call_stack cs, this, target: String) {
-        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
-        this.build(env, cs, target)
+    PythonExecutable.pip_install_pdm_lock(
+        env env,
+        call_stack cs,
+        this,
+        path: String,
+        groups=NoneType::None,
+        extra_envs=NoneType::None,
+        constraints=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_install_pdm_lock(env, cs, path, &groups, &extra_envs, &constraints)
     }
 
-    PythonExecutable.make_python_module_source(
+    PythonExecutable.pip_install_git(
         env env,
         call_stack cs,
         this,
-        name: String,
-        source: String,
-        is_package: bool = false
+        url: String,
+        commit: String,
+        subdirectory=NoneType::None,
+        extra_envs=NoneType::None,
+        constraints=NoneType::None
     ) {
-        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
-        this.make_python_module_source(env, cs, name, source, is_package)
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_install_git(env, cs, url, commit, &subdirectory, &extra_envs, &constraints)
     }
 
-    PythonExecutable.pip_download(
+    PythonExecutable.read_conda_lock(
         env env,
         call_stack cs,
         this,
-        args
+        path: String
     ) {
         let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.pip_download(env, cs, &args)
+        this.read_conda_lock(env, cs, path)
     }
 
-    PythonExecutable.pip_install(
+    PythonExecutable.read_conda_package(
         env env,
         call_stack cs,
         this,
-        args,
-        extra_envs=NoneType::None
+        path: String
     ) {
         let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.pip_install(env, cs, &args, &extra_envs)
+        this.read_conda_package(env, cs, path)
     }
 
     PythonExecutable.read_package_root(
@@ -1065,6 +2184,19 @@ starlark_module! { python_executable_env =>
         this.read_virtualenv(env, cs, path)
     }
 
+    PythonExecutable.resolve_requirements(
+        env env,
+        call_stack cs,
+        this,
+        requirements,
+        index_url=NoneType::None,
+        find_links=NoneType::None,
+        no_index: bool = false
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.resolve_requirements(env, cs, &requirements, &index_url, &find_links, no_index)
+    }
+
     PythonExecutable.setup_py_install(
         env env,
         call_stack cs,
@@ -1098,6 +2230,22 @@ starlark_module! { python_executable_env =>
         )
     }
 
+    PythonExecutable.add_dev_overlay(
+        env env,
+        call_stack cs,
+        this,
+        path: String,
+        packages
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_dev_overlay(env, cs, path, &packages)
+    }
+
+    PythonExecutable.add_data_files(env env, this, namespace: String, path: String) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_data_files(env, namespace, path)
+    }
+
     PythonExecutable.add_cargo_manifest_licensing(
         env env,
         this,
@@ -1118,16 +2266,52 @@ starlark_module! { python_executable_env =>
         this.filter_resources_from_files(&files, &glob_files)
     }
 
+    PythonExecutable.to_c_embedding_library(this) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_c_embedding_library()
+    }
+
     PythonExecutable.to_embedded_resources(this) {
         let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
         this.to_embedded_resources()
     }
 
+    PythonExecutable.to_test(
+        this,
+        module: String = "pytest".to_string(),
+        args = NoneType::None
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_test(module, &args)
+    }
+
     PythonExecutable.to_file_manifest(env env, this, prefix: String) {
         let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
         this.to_file_manifest(env, prefix)
     }
 
+    PythonExecutable.to_universal_file_manifest(
+        env env,
+        this,
+        other,
+        prefix: String = ".".to_string()
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_universal_file_manifest(env, &other, prefix)
+    }
+
+    PythonExecutable.to_macos_application_bundle_builder(
+        env env,
+        this,
+        bundle_name: String,
+        identifier: String,
+        version: String,
+        display_name = NoneType::None
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_macos_application_bundle_builder(env, bundle_name, identifier, version, &display_name)
+    }
+
     PythonExecutable.to_wix_bundle_builder(
         env env,
         call_stack cs,
@@ -1169,7 +2353,10 @@ mod tests {
     use {
         super::super::testutil::*,
         super::*,
-        crate::{python_distributions::PYTHON_DISTRIBUTIONS, testutil::*},
+        crate::{
+            environment::default_target_triple, python_distributions::PYTHON_DISTRIBUTIONS,
+            testutil::*,
+        },
     };
 
     #[test]
@@ -1305,6 +2492,228 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_check_mode() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?
+            .check_mode(true)
+            .into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("exe = dist.to_python_executable('testapp')")?;
+
+        for code in [
+            "exe.pip_install(['pyflakes==2.1.1'])",
+            "exe.pip_download(['pyflakes==2.1.1'])",
+            "exe.setup_py_install('/does/not/exist')",
+        ] {
+            let resources = env.eval(code)?;
+            assert_eq!(resources.get_type(), "list");
+            assert_eq!(resources.length().unwrap(), 0);
+        }
+
+        let manifest = env.eval("exe.to_file_manifest('.')")?;
+        let manifest = manifest.downcast_ref::<FileManifestValue>().unwrap();
+        let inner = manifest.inner("ignored").unwrap();
+        let (path, entry) = inner.iter_entries().next().unwrap();
+        assert!(path.starts_with("testapp"));
+        assert!(entry.resolve_content()?.is_empty());
+        assert_eq!(inner.iter_entries().count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_install_constraints() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+        let constraints_path = temp_dir.path().join("constraints.txt");
+        std::fs::write(&constraints_path, "pyflakes==2.1.1\n")?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("exe = dist.to_python_executable('testapp')")?;
+        env.set_var(
+            "constraints_path",
+            Value::from(constraints_path.display().to_string()),
+        )
+        .unwrap();
+
+        let resources =
+            env.eval("exe.pip_install(['pyflakes'], constraints = [constraints_path])")?;
+        assert_eq!(resources.get_type(), "list");
+        assert!(resources.length().unwrap() > 0);
+
+        // Requirements conflicting with constraints fail.
+        assert!(env
+            .eval("exe.pip_install(['pyflakes==2.2.0'], constraints = [constraints_path])")
+            .is_err());
+        assert!(env
+            .eval("exe.pip_download(['pyflakes==2.2.0'], constraints = [constraints_path])")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_install_poetry_lock() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+        let lock_path = temp_dir.path().join("poetry.lock");
+
+        std::fs::write(
+            &lock_path,
+            "[[package]]\n\
+             name = \"foo\"\n\
+             version = \"1.0\"\n\
+             optional = false\n\
+             python-versions = \"*\"\n\
+             groups = [\"dev\"]\n\
+             files = []\n\
+             \n\
+             [package.source]\n\
+             type = \"git\"\n\
+             url = \"https://github.com/example/foo.git\"\n\
+             reference = \"main\"\n\
+             resolved_reference = \"0123456789\"\n",
+        )?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("exe = dist.to_python_executable('testapp')")?;
+        env.set_var("lock_path", Value::from(lock_path.display().to_string()))
+            .unwrap();
+
+        // Packages outside the requested groups aren't installed.
+        let resources = env.eval("exe.pip_install_poetry_lock(lock_path)")?;
+        assert_eq!(resources.get_type(), "list");
+        assert_eq!(resources.length().unwrap(), 0);
+
+        assert!(env
+            .eval("exe.pip_install_poetry_lock(lock_path, groups = ['dev'])")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_install_pipfile_lock() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+        let lock_path = temp_dir.path().join("Pipfile.lock");
+
+        std::fs::write(
+            &lock_path,
+            r#"{"_meta": {"sources": []}, "default": {"foo": {"git": "https://github.com/example/foo.git"}}, "develop": {}}"#,
+        )?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("exe = dist.to_python_executable('testapp')")?;
+        env.set_var("lock_path", Value::from(lock_path.display().to_string()))
+            .unwrap();
+
+        let resources =
+            env.eval("exe.pip_install_pipfile_lock(lock_path, sections = ['develop'])")?;
+        assert_eq!(resources.get_type(), "list");
+        assert_eq!(resources.length().unwrap(), 0);
+
+        assert!(env.eval("exe.pip_install_pipfile_lock(lock_path)").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_install_uv_lock() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+        let lock_path = temp_dir.path().join("uv.lock");
+
+        // The dependency only applies to a platform we aren't building for.
+        std::fs::write(
+            &lock_path,
+            "version = 1\n\
+             \n\
+             [[package]]\n\
+             name = \"app\"\n\
+             version = \"0.1.0\"\n\
+             source = { editable = \".\" }\n\
+             dependencies = [{ name = \"foo\", marker = \"sys_platform == 'emscripten'\" }]\n\
+             \n\
+             [package.dev-dependencies]\n\
+             dev = [{ name = \"foo\" }]\n\
+             \n\
+             [[package]]\n\
+             name = \"foo\"\n\
+             version = \"1.0\"\n\
+             source = { git = \"https://github.com/example/foo.git\" }\n",
+        )?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("exe = dist.to_python_executable('testapp')")?;
+        env.set_var("lock_path", Value::from(lock_path.display().to_string()))
+            .unwrap();
+
+        let resources = env.eval("exe.pip_install_uv_lock(lock_path)")?;
+        assert_eq!(resources.get_type(), "list");
+        assert_eq!(resources.length().unwrap(), 0);
+
+        assert!(env
+            .eval("exe.pip_install_uv_lock(lock_path, groups = ['dev'])")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_install_git() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("exe = dist.to_python_executable('testapp')")?;
+
+        // Only full commit hashes are accepted.
+        assert!(env
+            .eval("exe.pip_install_git('https://github.com/example/foo.git', 'abcdef0')")
+            .is_err());
+
+        // URLs that git would parse as options are rejected.
+        assert!(env
+            .eval(
+                "exe.pip_install_git('--upload-pack=touch /tmp/pwned', \
+                 '0123456789abcdef0123456789abcdef01234567')"
+            )
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_install_pdm_lock() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+        let lock_path = temp_dir.path().join("pdm.lock");
+
+        std::fs::write(
+            &lock_path,
+            "[[package]]\n\
+             name = \"foo\"\n\
+             version = \"1.0\"\n\
+             groups = [\"dev\"]\n\
+             git = \"https://github.com/example/foo.git\"\n",
+        )?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("exe = dist.to_python_executable('testapp')")?;
+        env.set_var("lock_path", Value::from(lock_path.display().to_string()))
+            .unwrap();
+
+        let resources = env.eval("exe.pip_install_pdm_lock(lock_path)")?;
+        assert_eq!(resources.get_type(), "list");
+        assert_eq!(resources.length().unwrap(), 0);
+
+        assert!(env
+            .eval("exe.pip_install_pdm_lock(lock_path, groups = ['dev'])")
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_read_package_root_simple() -> Result<()> {
         let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
@@ -1364,6 +2773,95 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_data_files() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("models").join("v1"))?;
+        std::fs::write(root.join("models").join("v1").join("model.bin"), b"model")?;
+        std::fs::write(root.join("models").join("labels.txt"), b"labels")?;
+        std::fs::write(root.join("config.toml"), b"config")?;
+
+        let root_normalized = root.display().to_string().replace('\\', "/");
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+        env.eval(&format!(
+            "exe.add_data_files('models', '{}/models')",
+            root_normalized
+        ))?;
+        env.eval(&format!(
+            "exe.add_data_files('app', '{}/config.toml')",
+            root_normalized
+        ))?;
+        assert!(env
+            .eval(&format!(
+                "exe.add_data_files('', '{}/config.toml')",
+                root_normalized
+            ))
+            .is_err());
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        let inner = exe.inner("ignored").unwrap();
+
+        let data = inner
+            .iter_resources()
+            .filter(|(_, r)| r.is_utf8_filename_data)
+            .map(|(name, r)| {
+                (
+                    name.clone(),
+                    r.file_data_embedded
+                        .as_ref()
+                        .unwrap()
+                        .resolve_content()
+                        .unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for (name, content) in [
+            ("models/labels.txt", b"labels".to_vec()),
+            ("models/v1/model.bin", b"model".to_vec()),
+            ("app/config.toml", b"config".to_vec()),
+        ] {
+            assert!(data.contains(&(name.to_string(), content)));
+        }
+        drop(inner);
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_test() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.to_test(args = ['-x', 'tests'])")?;
+        assert_eq!(value.get_type(), "PythonTest");
+
+        let test = value.downcast_ref::<PythonTestValue>().unwrap();
+        assert_eq!(test.args, vec!["-x".to_string(), "tests".to_string()]);
+
+        assert!(env.eval("exe.to_test(args = [1])").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_c_embedding_library() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.to_c_embedding_library()")?;
+        assert_eq!(value.get_type(), "PythonCEmbeddingLibrary");
+
+        Ok(())
+    }
+
     #[test]
     fn licenses_filename() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -1429,6 +2927,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_packed_resources_load_injected() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.packed_resources_load_injected")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval(
+            "exe.packed_resources_load_injected = True; exe.packed_resources_load_injected",
+        )?;
+        assert!(value.to_bool());
+
+        Ok(())
+    }
+
     #[test]
     fn test_windows_subsystem() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -1463,6 +2978,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_cargo_build_settings() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        eval_assert(&mut env, "exe.cargo_features == []")?;
+        eval_assert(&mut env, "exe.cargo_rustflags == []")?;
+        eval_assert(&mut env, "exe.cargo_lto == None")?;
+        eval_assert(&mut env, "exe.cargo_codegen_units == None")?;
+        eval_assert(&mut env, "exe.cargo_panic == None")?;
+
+        env.eval("exe.cargo_features = ['pyembed/serialization']")?;
+        env.eval("exe.cargo_rustflags = ['-C', 'target-cpu=native']")?;
+        env.eval("exe.cargo_lto = 'fat'")?;
+        env.eval("exe.cargo_codegen_units = 1")?;
+        env.eval("exe.cargo_panic = 'abort'")?;
+
+        eval_assert(&mut env, "exe.cargo_features == ['pyembed/serialization']")?;
+        eval_assert(
+            &mut env,
+            "exe.cargo_rustflags == ['-C', 'target-cpu=native']",
+        )?;
+        eval_assert(&mut env, "exe.cargo_lto == 'fat'")?;
+        eval_assert(&mut env, "exe.cargo_codegen_units == 1")?;
+        eval_assert(&mut env, "exe.cargo_panic == 'abort'")?;
+
+        assert!(env.eval("exe.cargo_lto = 'medium'").is_err());
+        assert!(env.eval("exe.cargo_codegen_units = 0").is_err());
+        assert!(env.eval("exe.cargo_panic = 'crash'").is_err());
+        assert!(env.eval("exe.cargo_features = [1]").is_err());
+
+        env.eval("exe.cargo_lto = None")?;
+        eval_assert(&mut env, "exe.cargo_lto == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_triple() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.target_triple")?;
+        assert_eq!(value.to_string(), default_target_triple());
+
+        env.eval("exe.target_triple = exe.target_triple")?;
+        assert!(env
+            .eval("exe.target_triple = 'riscv64gc-unknown-none-elf'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_universal_file_manifest() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?
+            .build_target_triple("aarch64-apple-darwin")
+            .check_mode(true)
+            .into_context()?;
+
+        env.eval("arm64 = default_python_distribution().to_python_executable('testapp')")?;
+        env.eval("x86_64 = default_python_distribution(build_target = 'x86_64-apple-darwin').to_python_executable('testapp')")?;
+        env.eval("x86_64.target_triple = 'x86_64-apple-darwin'")?;
+
+        let manifest = env.eval("arm64.to_universal_file_manifest(x86_64)")?;
+        let manifest = manifest.downcast_ref::<FileManifestValue>().unwrap();
+        assert_eq!(manifest.run_path, Some(PathBuf::from("testapp")));
+        let inner = manifest.inner("ignored").unwrap();
+        assert_eq!(inner.iter_entries().count(), 1);
+
+        assert!(env.eval("arm64.to_universal_file_manifest(arm64)").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_to_wix_bundle_builder_callback() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -1487,6 +3077,43 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_to_macos_application_bundle_builder() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let builder_value = env.eval(
+            "exe.to_macos_application_bundle_builder('myapp', 'com.example.myapp', '0.1', display_name = 'My App')",
+        )?;
+        let builder = builder_value
+            .downcast_ref::<MacOsApplicationBundleBuilderValue>()
+            .unwrap();
+
+        let exe_name = builder
+            .inner
+            .get_info_plist_key("CFBundleExecutable")?
+            .unwrap()
+            .into_string()
+            .unwrap();
+        assert!(builder
+            .inner
+            .files()
+            .get(format!("Contents/MacOS/{}", exe_name))
+            .is_some());
+        assert_eq!(
+            builder.inner.get_info_plist_key("CFBundleDisplayName")?,
+            Some("My App".into())
+        );
+        assert_eq!(
+            builder
+                .inner
+                .get_info_plist_key("CFBundleShortVersionString")?,
+            Some("0.1".into())
+        );
+
+        Ok(())
+    }
+
     #[cfg(windows)]
     #[test]
     fn test_to_wix_bundle_builder() -> Result<()> {
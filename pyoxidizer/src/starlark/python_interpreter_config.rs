@@ -7,8 +7,9 @@ use {
     crate::py_packaging::config::PyembedPythonInterpreterConfig,
     python_packaging::{
         interpreter::{
-            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-            MultiprocessingStartMethod, PythonInterpreterProfile, TerminfoResolution,
+            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, ImportPrecedence,
+            MemoryAllocatorBackend, MultiprocessingStartMethod, PythonInterpreterProfile,
+            TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
@@ -19,8 +20,9 @@ use {
         none::NoneType,
         {Mutable, TypedValue, Value, ValueResult},
     },
-    starlark_dialect_build_targets::{ToOptional, TryToOptional},
+    starlark_dialect_build_targets::{required_dict_arg, ToOptional, TryToOptional},
     std::{
+        collections::{BTreeMap, HashMap},
         str::FromStr,
         sync::{Arc, Mutex, MutexGuard},
     },
@@ -108,6 +110,20 @@ fn bytecode_optimization_level_try_to_optional(
     }
 }
 
+fn tracemalloc_frames_try_to_optional(v: Value) -> Result<Option<u32>, ValueError> {
+    if v.get_type() == "NoneType" {
+        Ok(None)
+    } else {
+        Ok(Some(u32::try_from(v.to_int()?).map_err(|_| {
+            ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "number of frames must be a non-negative integer".to_string(),
+                label: "PythonInterpreterConfig.tracemalloc_frames".to_string(),
+            })
+        })?))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PythonInterpreterConfigValue {
     pub inner: Arc<Mutex<PyembedPythonInterpreterConfig>>,
@@ -190,6 +206,7 @@ impl TypedValue for PythonInterpreterConfigValue {
             "optimization_level" => inner.config.optimization_level.to_value(),
             "parser_debug" => inner.config.parser_debug.to_value(),
             "pathconfig_warnings" => inner.config.pathconfig_warnings.to_value(),
+            "platlibdir" => inner.config.platlibdir.to_value(),
             "prefix" => inner.config.prefix.to_value(),
             "program_name" => inner.config.program_name.to_value(),
             "pycache_prefix" => inner.config.pycache_prefix.to_value(),
@@ -204,8 +221,13 @@ impl TypedValue for PythonInterpreterConfigValue {
             "stdio_encoding" => inner.config.stdio_encoding.to_value(),
             "stdio_errors" => inner.config.stdio_errors.to_value(),
             "tracemalloc" => inner.config.tracemalloc.to_value(),
+            "tracemalloc_frames" => match inner.config.tracemalloc_frames {
+                Some(value) => Value::from(value as i64),
+                None => Value::from(NoneType::None),
+            },
             "user_site_directory" => inner.config.user_site_directory.to_value(),
             "verbose" => inner.config.verbose.to_value(),
+            "warn_default_encoding" => inner.config.warn_default_encoding.to_value(),
             "warn_options" => inner.config.warn_options.to_value(),
             "write_bytecode" => inner.config.write_bytecode.to_value(),
             "x_options" => inner.config.x_options.to_value(),
@@ -217,13 +239,25 @@ impl TypedValue for PythonInterpreterConfigValue {
             "allocator_debug" => Value::from(inner.allocator_debug),
             "oxidized_importer" => Value::from(inner.oxidized_importer),
             "filesystem_importer" => Value::from(inner.filesystem_importer),
+            "appended_zip_importer" => Value::from(inner.appended_zip_importer),
             "argvb" => Value::from(inner.argvb),
             "multiprocessing_auto_dispatch" => Value::from(inner.multiprocessing_auto_dispatch),
             "multiprocessing_start_method" => {
                 Value::from(inner.multiprocessing_start_method.to_string())
             }
+            "synthesize_dunder_file" => Value::from(inner.synthesize_dunder_file),
+            "package_precedence" => Value::try_from(
+                inner
+                    .package_precedence
+                    .iter()
+                    .map(|(package, precedence)| (package.clone(), precedence.to_string()))
+                    .collect::<HashMap<_, _>>(),
+            )?,
+            "reload_from_filesystem" => Value::from(inner.reload_from_filesystem),
+            "verify_resources" => Value::from(inner.verify_resources),
             "sys_frozen" => Value::from(inner.sys_frozen),
             "sys_meipass" => Value::from(inner.sys_meipass),
+            "traceback_excepthook" => Value::from(inner.traceback_excepthook),
             "terminfo_resolution" => inner.terminfo_resolution.to_value(),
             "write_modules_directory_env" => inner.write_modules_directory_env.to_value(),
             attr => {
@@ -277,6 +311,7 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "optimization_level"
                 | "parser_debug"
                 | "pathconfig_warnings"
+                | "platlibdir"
                 | "prefix"
                 | "program_name"
                 | "pycache_prefix"
@@ -291,8 +326,10 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "stdio_encoding"
                 | "stdio_errors"
                 | "tracemalloc"
+                | "tracemalloc_frames"
                 | "user_site_directory"
                 | "verbose"
+                | "warn_default_encoding"
                 | "warn_options"
                 | "write_bytecode"
                 | "x_options"
@@ -304,11 +341,17 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "allocator_debug"
                 | "oxidized_importer"
                 | "filesystem_importer"
+                | "appended_zip_importer"
                 | "argvb"
                 | "multiprocessing_auto_dispatch"
                 | "multiprocessing_start_method"
+                | "synthesize_dunder_file"
+                | "package_precedence"
+                | "reload_from_filesystem"
+                | "verify_resources"
                 | "sys_frozen"
                 | "sys_meipass"
+                | "traceback_excepthook"
                 | "terminfo_resolution"
                 | "write_modules_directory_env"
         ))
@@ -492,6 +535,9 @@ impl TypedValue for PythonInterpreterConfigValue {
             "pathconfig_warnings" => {
                 inner.config.pathconfig_warnings = value.to_optional();
             }
+            "platlibdir" => {
+                inner.config.platlibdir = value.to_optional();
+            }
             "prefix" => {
                 inner.config.prefix = value.to_optional();
             }
@@ -534,12 +580,18 @@ impl TypedValue for PythonInterpreterConfigValue {
             "tracemalloc" => {
                 inner.config.tracemalloc = value.to_optional();
             }
+            "tracemalloc_frames" => {
+                inner.config.tracemalloc_frames = tracemalloc_frames_try_to_optional(value)?;
+            }
             "user_site_directory" => {
                 inner.config.user_site_directory = value.to_optional();
             }
             "verbose" => {
                 inner.config.configure_locale = value.to_optional();
             }
+            "warn_default_encoding" => {
+                inner.config.warn_default_encoding = value.to_optional();
+            }
             "warn_options" => {
                 inner.config.warn_options = value.try_to_optional()?;
             }
@@ -580,6 +632,9 @@ impl TypedValue for PythonInterpreterConfigValue {
             "filesystem_importer" => {
                 inner.filesystem_importer = value.to_bool();
             }
+            "appended_zip_importer" => {
+                inner.appended_zip_importer = value.to_bool();
+            }
             "argvb" => {
                 inner.argvb = value.to_bool();
             }
@@ -598,12 +653,45 @@ impl TypedValue for PythonInterpreterConfigValue {
                     })
                 })?;
             }
+            "synthesize_dunder_file" => {
+                inner.synthesize_dunder_file = value.to_bool();
+            }
+            "package_precedence" => {
+                required_dict_arg(attribute, "string", "string", &value)?;
+
+                inner.package_precedence = value
+                    .iter()?
+                    .iter()
+                    .map(|key| {
+                        let precedence =
+                            ImportPrecedence::from_str(&value.at(key.clone())?.to_string())
+                                .map_err(|e| {
+                                    ValueError::from(RuntimeError {
+                                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                                        message: e,
+                                        label: format!("{}.{}", Self::TYPE, attribute),
+                                    })
+                                })?;
+
+                        Ok((key.to_string(), precedence))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, ValueError>>()?;
+            }
+            "reload_from_filesystem" => {
+                inner.reload_from_filesystem = value.to_bool();
+            }
+            "verify_resources" => {
+                inner.verify_resources = value.to_bool();
+            }
             "sys_frozen" => {
                 inner.sys_frozen = value.to_bool();
             }
             "sys_meipass" => {
                 inner.sys_meipass = value.to_bool();
             }
+            "traceback_excepthook" => {
+                inner.traceback_excepthook = value.to_bool();
+            }
             "terminfo_resolution" => {
                 inner.terminfo_resolution =
                     TerminfoResolution::try_from(value.to_string().as_str()).map_err(|e| {
@@ -1017,6 +1105,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_platlibdir() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.platlibdir == None")?;
+        env.eval("config.platlibdir = 'lib64'")?;
+        eval_assert(&mut env, "config.platlibdir == 'lib64'")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_prefix() -> Result<()> {
         let mut env = get_env()?;
@@ -1143,6 +1242,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_tracemalloc_frames() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.tracemalloc_frames == None")?;
+        env.eval("config.tracemalloc_frames = 25")?;
+        eval_assert(&mut env, "config.tracemalloc_frames == 25")?;
+        assert!(env.eval("config.tracemalloc_frames = -1").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_user_site_directory() -> Result<()> {
         let mut env = get_env()?;
@@ -1167,6 +1278,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_warn_default_encoding() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.warn_default_encoding == None")?;
+        env.eval("config.warn_default_encoding = True")?;
+        eval_assert(&mut env, "config.warn_default_encoding == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_warn_options() -> Result<()> {
         let mut env = get_env()?;
@@ -1296,6 +1418,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_appended_zip_importer() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.appended_zip_importer == False")?;
+
+        env.eval("config.appended_zip_importer = True")?;
+        eval_assert(&mut env, "config.appended_zip_importer == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_argvb() -> Result<()> {
         let mut env = get_env()?;
@@ -1344,6 +1478,61 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_synthesize_dunder_file() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.synthesize_dunder_file == False")?;
+
+        env.eval("config.synthesize_dunder_file = True")?;
+        eval_assert(&mut env, "config.synthesize_dunder_file == True")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_precedence() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.package_precedence == {}")?;
+
+        env.eval("config.package_precedence = {'foo': 'filesystem-first', 'bar': 'memory-only'}")?;
+        eval_assert(
+            &mut env,
+            "config.package_precedence == {'foo': 'filesystem-first', 'bar': 'memory-only'}",
+        )?;
+
+        assert!(env
+            .eval("config.package_precedence = {'foo': 'invalid'}")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_from_filesystem() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.reload_from_filesystem == False")?;
+
+        env.eval("config.reload_from_filesystem = True")?;
+        eval_assert(&mut env, "config.reload_from_filesystem == True")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_resources() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.verify_resources == False")?;
+
+        env.eval("config.verify_resources = True")?;
+        eval_assert(&mut env, "config.verify_resources == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_sys_frozen() -> Result<()> {
         let mut env = get_env()?;
@@ -1362,6 +1551,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_traceback_excepthook() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.traceback_excepthook == False")?;
+        env.eval("config.traceback_excepthook = True")?;
+        eval_assert(&mut env, "config.traceback_excepthook == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_terminfo_resolution() -> Result<()> {
         let mut env = get_env()?;
@@ -8,6 +8,7 @@ use {
         resource::{PythonModuleSource, PythonResource},
         resource_collection::PythonResourceAddCollectionContext,
     },
+    simple_file_manifest::FileData,
     starlark::values::{
         error::{RuntimeError, UnsupportedOperation, ValueError},
         {Mutable, TypedValue, Value, ValueResult},
@@ -52,6 +53,17 @@ impl PythonModuleSourceValue {
     }
 }
 
+/// Whether a string is a valid, fully qualified Python module name.
+fn is_valid_module_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('.').all(|part| {
+            let mut chars = part.chars();
+
+            matches!(chars.next(), Some(c) if c == '_' || c.is_alphabetic())
+                && chars.all(|c| c == '_' || c.is_alphanumeric())
+        })
+}
+
 impl ResourceCollectionContext for PythonModuleSourceValue {
     fn add_collection_context(
         &self,
@@ -90,7 +102,13 @@ impl TypedValue for PythonModuleSourceValue {
     }
 
     fn to_str(&self) -> String {
-        format!("{}<name={}>", Self::TYPE, self.name)
+        // The name may have been changed via another reference to this value.
+        let name = match self.inner.try_lock() {
+            Ok(inner) => inner.m.name.clone(),
+            Err(_) => self.name.clone(),
+        };
+
+        format!("{}<name={}>", Self::TYPE, name)
     }
 
     fn to_repr(&self) -> String {
@@ -102,6 +120,7 @@ impl TypedValue for PythonModuleSourceValue {
 
         let v = match attribute {
             "is_stdlib" => Value::from(inner.m.is_stdlib),
+            "is_test" => Value::from(inner.m.is_test),
             "name" => Value::new(inner.m.name.clone()),
             "source" => {
                 let source = inner.m.source.resolve_content().map_err(|e| {
@@ -147,12 +166,41 @@ impl TypedValue for PythonModuleSourceValue {
             "source" => true,
             "is_package" => true,
             "is_stdlib" => true,
+            "is_test" => true,
             attr => self.add_collection_context_attrs().contains(&attr),
         })
     }
 
     fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
-        if self.add_collection_context_attrs().contains(&attribute) {
+        let label = format!("PythonModuleSource.{}", attribute);
+
+        if attribute == "name" {
+            let name = value.to_string();
+            if value.get_type() != "string" || !is_valid_module_name(&name) {
+                return Err(ValueError::from(RuntimeError {
+                    code: "PYTHON_MODULE_SOURCE",
+                    message: format!("{} is not a valid Python module name", value.to_repr()),
+                    label,
+                }));
+            }
+
+            self.inner(&label)?.m.name = name.clone();
+            self.name = name;
+
+            Ok(())
+        } else if attribute == "source" {
+            if value.get_type() != "string" {
+                return Err(ValueError::from(RuntimeError {
+                    code: "PYTHON_MODULE_SOURCE",
+                    message: format!("source must be a string; got {}", value.get_type()),
+                    label,
+                }));
+            }
+
+            self.inner(&label)?.m.source = FileData::Memory(value.to_string().into_bytes());
+
+            Ok(())
+        } else if self.add_collection_context_attrs().contains(&attribute) {
             self.set_attr_add_collection_context(attribute, value)
         } else {
             Err(ValueError::OperationNotSupported {
@@ -220,7 +268,9 @@ mod tests {
 
         assert_eq!(
             m.get_attr("add_location_fallback").unwrap().get_type(),
-            if dist.supports_in_memory_shared_library_loading() {
+            if dist.supports_in_memory_shared_library_loading()
+                && dist.target_triple().contains("pc-windows")
+            {
                 "string"
             } else {
                 "NoneType"
@@ -311,6 +361,22 @@ mod tests {
             .unwrap()
             .to_bool());
 
+        assert!(m.has_attr("add_metadata").unwrap());
+        assert_eq!(m.get_attr("add_metadata").unwrap().get_type(), "dict");
+        assert_eq!(m.get_attr("add_metadata").unwrap().length().unwrap(), 0);
+        let metadata = env.eval("{'license': 'MIT'}")?;
+        m.set_attr("add_metadata", metadata).unwrap();
+        let metadata = m.get_attr("add_metadata").unwrap();
+        assert_eq!(metadata.length().unwrap(), 1);
+        assert_eq!(
+            metadata.at(Value::from("license")).unwrap(),
+            Value::from("MIT")
+        );
+        assert!(m.set_attr("add_metadata", Value::from("MIT")).is_err());
+        m.set_attr("add_metadata", Value::from(NoneType::None))
+            .unwrap();
+        assert_eq!(m.get_attr("add_metadata").unwrap().length().unwrap(), 0);
+
         Ok(())
     }
 }
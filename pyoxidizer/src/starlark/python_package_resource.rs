@@ -96,11 +96,17 @@ impl TypedValue for PythonPackageResourceValue {
     }
 
     fn to_str(&self) -> String {
+        // The name may have been changed via another reference to this value.
+        let relative_name = match self.inner.try_lock() {
+            Ok(inner) => inner.r.relative_name.clone(),
+            Err(_) => self.relative_name.clone(),
+        };
+
         format!(
             "{}<package={}, name={}>",
             Self::TYPE,
             self.leaf_package,
-            self.relative_name
+            relative_name
         )
     }
 
@@ -145,7 +151,30 @@ impl TypedValue for PythonPackageResourceValue {
     }
 
     fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
-        if self.add_collection_context_attrs().contains(&attribute) {
+        if attribute == "name" {
+            let label = format!("PythonPackageResource.{}", attribute);
+            let name = value.to_string();
+
+            if value.get_type() != "string"
+                || name.is_empty()
+                || name.starts_with('/')
+                || name.split(['/', '\\']).any(|part| part == "..")
+            {
+                return Err(ValueError::from(RuntimeError {
+                    code: "PYTHON_PACKAGE_RESOURCE",
+                    message: format!(
+                        "{} is not a valid resource name relative to its package",
+                        value.to_repr()
+                    ),
+                    label,
+                }));
+            }
+
+            self.inner(&label)?.r.relative_name = name.clone();
+            self.relative_name = name;
+
+            Ok(())
+        } else if self.add_collection_context_attrs().contains(&attribute) {
             self.set_attr_add_collection_context(attribute, value)
         } else {
             Err(ValueError::OperationNotSupported {
@@ -7,7 +7,10 @@ use {
     linked_hash_map::LinkedHashMap,
     python_packaging::{
         location::ConcreteResourceLocation,
-        policy::{ExtensionModuleFilter, PythonPackagingPolicy, ResourceHandlingMode},
+        policy::{
+            ExtensionModuleFilter, PythonPackagingPolicy, ResourceHandlingMode,
+            ResourceLocationAction, ResourceLocationRule,
+        },
     },
     starlark::{
         environment::TypeValues,
@@ -20,9 +23,11 @@ use {
             Mutable, TypedValue, Value, ValueResult,
         },
     },
-    starlark_dialect_build_targets::required_type_arg,
+    starlark_dialect_build_targets::{optional_str_arg, required_list_arg, required_type_arg},
     std::{
+        collections::BTreeMap,
         ops::Deref,
+        path::PathBuf,
         sync::{Arc, Mutex, MutexGuard},
     },
 };
@@ -68,9 +73,29 @@ impl PythonPackagingPolicyValue {
     where
         T: TypedValue + ResourceCollectionContext + Clone,
     {
-        let new_context = self
-            .inner(label)?
-            .derive_add_collection_context(&value.as_python_resource()?);
+        self.apply_to_resource_relocating(label, type_values, call_stack, value, &BTreeMap::new())
+    }
+
+    /// Apply this policy to a resource, relocating packages that don't work in memory.
+    ///
+    /// This is like [Self::apply_to_resource()] except the add context is
+    /// derived taking packages detected as not working when imported from
+    /// memory into account.
+    pub fn apply_to_resource_relocating<T>(
+        &self,
+        label: &str,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        value: &mut T,
+        in_memory_incompatible: &BTreeMap<String, String>,
+    ) -> ValueResult
+    where
+        T: TypedValue + ResourceCollectionContext + Clone,
+    {
+        let new_context = self.inner(label)?.derive_add_collection_context_relocating(
+            &value.as_python_resource()?,
+            in_memory_incompatible,
+        );
         value.replace_add_collection_context(new_context)?;
 
         for func in &self.derive_context_callbacks {
@@ -82,6 +107,8 @@ impl PythonPackagingPolicyValue {
             // Our solution for this is to create a copy of the passed object and
             // construct a `Value` from it. After the call, we downcast it back to
             // our T, retrieve its add context, and replace that on the original value.
+            // Changes to the resource itself, like renames, don't need copying because
+            // the copy shares the resource with the original value.
             //
             // There might be a way to pass a `Value` into this method. But for now,
             // this solution works.
@@ -140,6 +167,57 @@ impl TypedValue for PythonPackagingPolicyValue {
             "preferred_extension_module_variants" => {
                 Value::try_from(inner.preferred_extension_module_variants().clone())?
             }
+            "packed_resources_encryption_key_path" => {
+                match inner.packed_resources_encryption_key_path() {
+                    Some(path) => Value::from(path.display().to_string()),
+                    None => Value::from(NoneType::None),
+                }
+            }
+            "pip_build_isolation" => Value::from(inner.pip_build_isolation()),
+            "pip_extra_index_urls" => Value::from(
+                inner
+                    .pip_extra_index_urls()
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            "pip_index_url" => match inner.pip_index_url() {
+                Some(url) => Value::from(url),
+                None => Value::from(NoneType::None),
+            },
+            "pip_proxy" => match inner.pip_proxy() {
+                Some(proxy) => Value::from(proxy),
+                None => Value::from(NoneType::None),
+            },
+            "pip_require_hashes" => Value::from(inner.pip_require_hashes()),
+            "pip_retries" => match inner.pip_retries() {
+                Some(retries) => Value::from(retries as i64),
+                None => Value::from(NoneType::None),
+            },
+            "pip_trusted_hosts" => Value::from(
+                inner
+                    .pip_trusted_hosts()
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            "packed_resources_signing_key_path" => {
+                match inner.packed_resources_signing_key_path() {
+                    Some(path) => Value::from(path.display().to_string()),
+                    None => Value::from(NoneType::None),
+                }
+            }
+            "packed_resources_zstd_level" => match inner.packed_resources_zstd_level() {
+                Some(level) => Value::from(level),
+                None => Value::from(NoneType::None),
+            },
+            "relocate_in_memory_incompatible" => {
+                Value::from(inner.relocate_in_memory_incompatible())
+            }
+            "resources_in_memory_size_limit" => match inner.resources_in_memory_size_limit() {
+                Some(limit) => Value::from(limit as i64),
+                None => Value::from(NoneType::None),
+            },
             "resources_location" => Value::from(inner.resources_location().to_string()),
             "resources_location_fallback" => match inner.resources_location_fallback() {
                 Some(location) => Value::from(location.to_string()),
@@ -175,6 +253,18 @@ impl TypedValue for PythonPackagingPolicyValue {
                 | "include_non_distribution_sources"
                 | "include_test"
                 | "preferred_extension_module_variants"
+                | "packed_resources_encryption_key_path"
+                | "pip_build_isolation"
+                | "pip_extra_index_urls"
+                | "pip_index_url"
+                | "pip_proxy"
+                | "pip_require_hashes"
+                | "pip_retries"
+                | "pip_trusted_hosts"
+                | "packed_resources_signing_key_path"
+                | "packed_resources_zstd_level"
+                | "relocate_in_memory_incompatible"
+                | "resources_in_memory_size_limit"
                 | "resources_location"
                 | "resources_location_fallback"
         ))
@@ -235,6 +325,102 @@ impl TypedValue for PythonPackagingPolicyValue {
             "include_test" => {
                 inner.set_include_test(value.to_bool());
             }
+            "packed_resources_encryption_key_path" => {
+                if value.get_type() == "NoneType" {
+                    inner.set_packed_resources_encryption_key_path(None);
+                } else {
+                    inner.set_packed_resources_encryption_key_path(Some(PathBuf::from(
+                        value.to_string(),
+                    )));
+                }
+            }
+            "pip_build_isolation" => {
+                inner.set_pip_build_isolation(value.to_bool());
+            }
+            "pip_extra_index_urls" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.set_pip_extra_index_urls(
+                    value.iter()?.iter().map(|x| x.to_string()).collect(),
+                );
+            }
+            "pip_index_url" => {
+                inner.set_pip_index_url(optional_str_arg(attribute, &value)?);
+            }
+            "pip_proxy" => {
+                inner.set_pip_proxy(optional_str_arg(attribute, &value)?);
+            }
+            "pip_require_hashes" => {
+                inner.set_pip_require_hashes(value.to_bool());
+            }
+            "pip_retries" => {
+                if value.get_type() == "NoneType" {
+                    inner.set_pip_retries(None);
+                } else {
+                    let retries = value.to_int()?;
+
+                    if retries < 0 {
+                        return Err(ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: "pip retries must not be negative".to_string(),
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        }));
+                    }
+
+                    inner.set_pip_retries(Some(retries as u32));
+                }
+            }
+            "pip_trusted_hosts" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.set_pip_trusted_hosts(value.iter()?.iter().map(|x| x.to_string()).collect());
+            }
+            "packed_resources_signing_key_path" => {
+                if value.get_type() == "NoneType" {
+                    inner.set_packed_resources_signing_key_path(None);
+                } else {
+                    inner.set_packed_resources_signing_key_path(Some(PathBuf::from(
+                        value.to_string(),
+                    )));
+                }
+            }
+            "packed_resources_zstd_level" => {
+                if value.get_type() == "NoneType" {
+                    inner.set_packed_resources_zstd_level(None);
+                } else {
+                    let level = value.to_int()?;
+
+                    if !(1..=22).contains(&level) {
+                        return Err(ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: "zstandard compression level must be between 1 and 22"
+                                .to_string(),
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        }));
+                    }
+
+                    inner.set_packed_resources_zstd_level(Some(level as i32));
+                }
+            }
+            "relocate_in_memory_incompatible" => {
+                inner.set_relocate_in_memory_incompatible(value.to_bool());
+            }
+            "resources_in_memory_size_limit" => {
+                if value.get_type() == "NoneType" {
+                    inner.set_resources_in_memory_size_limit(None);
+                } else {
+                    let limit = value.to_int()?;
+
+                    if limit < 0 {
+                        return Err(ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: "resources in memory size limit must not be negative"
+                                .to_string(),
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        }));
+                    }
+
+                    inner.set_resources_in_memory_size_limit(Some(limit as u64));
+                }
+            }
             "resources_location" => {
                 inner.set_resources_location(
                     ConcreteResourceLocation::try_from(value.to_string().as_str()).map_err(
@@ -280,6 +466,34 @@ impl TypedValue for PythonPackagingPolicyValue {
 
 // Starlark methods.
 impl PythonPackagingPolicyValue {
+    fn starlark_add_resource_location_rule(
+        &mut self,
+        pattern: String,
+        location: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonPackagingPolicy.add_resource_location_rule()";
+
+        let action = ResourceLocationAction::try_from(location.as_str()).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYTHON_PACKAGING_POLICY",
+                message: e,
+                label: LABEL.to_string(),
+            })
+        })?;
+
+        let rule = ResourceLocationRule::new(&pattern, action).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYTHON_PACKAGING_POLICY",
+                message: format!("{:?}", e),
+                label: LABEL.to_string(),
+            })
+        })?;
+
+        self.inner(LABEL)?.add_resource_location_rule(rule);
+
+        Ok(Value::from(NoneType::None))
+    }
+
     fn starlark_register_resource_callback(&mut self, func: &Value) -> ValueResult {
         required_type_arg("func", "function", func)?;
 
@@ -318,6 +532,11 @@ impl PythonPackagingPolicyValue {
 }
 
 starlark_module! { python_packaging_policy_module =>
+    PythonPackagingPolicy.add_resource_location_rule(this, pattern: String, location: String) {
+        let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
+        this.starlark_add_resource_location_rule(pattern, location)
+    }
+
     PythonPackagingPolicy.register_resource_callback(this, func) {
         let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
         this.starlark_register_resource_callback(&func)
@@ -477,6 +696,63 @@ mod tests {
         let value = env.eval("policy.include_test = True; policy.include_test")?;
         assert!(value.to_bool());
 
+        let value = env.eval("policy.pip_build_isolation")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval("policy.pip_build_isolation = True; policy.pip_build_isolation")?;
+        assert!(value.to_bool());
+
+        let value = env.eval("policy.pip_require_hashes")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval("policy.pip_require_hashes = True; policy.pip_require_hashes")?;
+        assert!(value.to_bool());
+
+        let value = env.eval("policy.relocate_in_memory_incompatible")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(value.to_bool());
+
+        let value = env.eval(
+            "policy.relocate_in_memory_incompatible = False; policy.relocate_in_memory_incompatible",
+        )?;
+        assert!(!value.to_bool());
+
+        let value = env.eval("policy.pip_index_url")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value =
+            env.eval("policy.pip_index_url = 'https://example.com/simple'; policy.pip_index_url")?;
+        assert_eq!(value.to_string(), "https://example.com/simple");
+
+        let value = env.eval("policy.pip_extra_index_urls")?;
+        assert_eq!(value.get_type(), "list");
+        assert_eq!(value.length().unwrap(), 0);
+
+        let value = env.eval(
+            "policy.pip_extra_index_urls = ['https://example.com/extra']; policy.pip_extra_index_urls",
+        )?;
+        assert_eq!(value.length().unwrap(), 1);
+
+        let value =
+            env.eval("policy.pip_trusted_hosts = ['example.com']; policy.pip_trusted_hosts")?;
+        assert_eq!(value.length().unwrap(), 1);
+
+        let value = env.eval("policy.pip_proxy")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("policy.pip_proxy = 'proxy.example.com:3128'; policy.pip_proxy")?;
+        assert_eq!(value.to_string(), "proxy.example.com:3128");
+
+        let value = env.eval("policy.pip_retries")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("policy.pip_retries = 10; policy.pip_retries")?;
+        assert_eq!(value.to_int().unwrap(), 10);
+
+        assert!(env.eval("policy.pip_retries = -1").is_err());
+
         let value = env.eval("policy.resources_location")?;
         assert_eq!(value.get_type(), "string");
         assert_eq!(value.to_string(), "in-memory");
@@ -487,7 +763,9 @@ mod tests {
         assert_eq!(value.to_string(), "filesystem-relative:lib");
 
         let value = env.eval("policy.resources_location_fallback")?;
-        if dist_ref.supports_in_memory_shared_library_loading() {
+        if dist_ref.supports_in_memory_shared_library_loading()
+            && dist_ref.target_triple().contains("pc-windows")
+        {
             assert_eq!(value.get_type(), "string");
             assert_eq!(value.to_string(), "filesystem-relative:lib");
         } else {
@@ -502,6 +780,67 @@ mod tests {
         )?;
         assert_eq!(value.get_type(), "NoneType");
 
+        let value = env.eval("policy.packed_resources_zstd_level")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env
+            .eval("policy.packed_resources_zstd_level = 10; policy.packed_resources_zstd_level")?;
+        assert_eq!(value.get_type(), "int");
+        assert_eq!(value.to_int().unwrap(), 10);
+
+        assert!(env.eval("policy.packed_resources_zstd_level = 23").is_err());
+
+        let value = env.eval(
+            "policy.packed_resources_zstd_level = None; policy.packed_resources_zstd_level",
+        )?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("policy.packed_resources_encryption_key_path")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval(
+            "policy.packed_resources_encryption_key_path = 'key'; policy.packed_resources_encryption_key_path",
+        )?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "key");
+
+        let value = env.eval(
+            "policy.packed_resources_encryption_key_path = None; policy.packed_resources_encryption_key_path",
+        )?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("policy.packed_resources_signing_key_path")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval(
+            "policy.packed_resources_signing_key_path = 'key'; policy.packed_resources_signing_key_path",
+        )?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "key");
+
+        let value = env.eval(
+            "policy.packed_resources_signing_key_path = None; policy.packed_resources_signing_key_path",
+        )?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("policy.resources_in_memory_size_limit")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval(
+            "policy.resources_in_memory_size_limit = 1048576; policy.resources_in_memory_size_limit",
+        )?;
+        assert_eq!(value.get_type(), "int");
+        assert_eq!(value.to_int().unwrap(), 1048576);
+
+        assert!(env
+            .eval("policy.resources_in_memory_size_limit = -1")
+            .is_err());
+
+        let value = env.eval(
+            "policy.resources_in_memory_size_limit = None; policy.resources_in_memory_size_limit",
+        )?;
+        assert_eq!(value.get_type(), "NoneType");
+
         let value = env.eval("policy.allow_files")?;
         assert_eq!(value.get_type(), "bool");
         assert!(!value.to_bool());
@@ -566,6 +905,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_resource_location_rule() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        env.eval("policy.add_resource_location_rule('numpy.*', 'filesystem-relative:lib')")?;
+        env.eval("policy.add_resource_location_rule('*.dist-info', 'exclude')")?;
+        assert!(env
+            .eval("policy.add_resource_location_rule('foo', 'invalid')")
+            .is_err());
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+        let inner = policy.inner("ignored").unwrap();
+        let rules = inner.resource_location_rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].pattern(), "numpy.*");
+        assert_eq!(
+            rules[0].action(),
+            &ResourceLocationAction::Location(ConcreteResourceLocation::RelativePath(
+                "lib".to_string()
+            ))
+        );
+        assert_eq!(rules[1].action(), &ResourceLocationAction::Exclude);
+
+        Ok(())
+    }
+
     #[test]
     fn test_preferred_extension_module_variants() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -610,6 +981,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_resource_callback_transform() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval(indoc! {r#"
+            dist = default_python_distribution()
+            policy = dist.make_python_packaging_policy()
+
+            def transform(policy, resource):
+                if type(resource) == "PythonModuleSource" and resource.name == "foo":
+                    resource.name = "bar"
+                    resource.source = "print('transformed')"
+                    resource.add_bytecode_optimization_level_two = True
+
+            policy.register_resource_callback(transform)
+            exe = dist.to_python_executable("testapp", packaging_policy = policy)
+            m = exe.make_python_module_source("foo", "print('hello')")
+        "#})?;
+
+        assert_eq!(env.eval("m.name")?.to_string(), "bar");
+        assert_eq!(env.eval("m.source")?.to_string(), "print('transformed')");
+        assert!(env.eval("m.add_bytecode_optimization_level_two")?.to_bool());
+        assert_eq!(
+            env.eval("repr(m)")?.to_string(),
+            "PythonModuleSource<name=bar>"
+        );
+
+        assert!(env.eval("m.name = 'not valid'").is_err());
+        assert!(env.eval("m.name = 'foo..bar'").is_err());
+        assert!(env.eval("m.source = 42").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_set_resource_handling_mode() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
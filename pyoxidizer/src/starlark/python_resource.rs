@@ -10,7 +10,9 @@ use {
         python_package_resource::PythonPackageResourceValue,
         python_packaging_policy::PythonPackagingPolicyValue,
     },
+    log::warn,
     python_packaging::{
+        in_memory_compatibility::find_in_memory_incompatible_packages,
         location::ConcreteResourceLocation, resource::PythonResource,
         resource_collection::PythonResourceAddCollectionContext,
     },
@@ -18,6 +20,7 @@ use {
         environment::TypeValues,
         eval::call_stack::CallStack,
         values::{
+            dict::Dictionary,
             error::{
                 RuntimeError, UnsupportedOperation, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE,
             },
@@ -25,6 +28,8 @@ use {
             {Value, ValueResult},
         },
     },
+    starlark_dialect_build_targets::optional_dict_arg,
+    std::collections::BTreeMap,
 };
 
 #[derive(Clone, Debug)]
@@ -125,6 +130,7 @@ pub trait ResourceCollectionContext {
             "add_bytecode_optimization_level_zero",
             "add_bytecode_optimization_level_one",
             "add_bytecode_optimization_level_two",
+            "add_metadata",
         ]
     }
 
@@ -153,6 +159,14 @@ pub trait ResourceCollectionContext {
                     Some(location) => Value::new::<String>(location.clone().into()),
                     None => Value::from(NoneType::None),
                 },
+                "add_metadata" => {
+                    let mut metadata = Dictionary::default();
+                    for (k, v) in &context.metadata {
+                        metadata.insert(Value::from(k.as_str()), Value::from(v.as_str()))?;
+                    }
+
+                    Value::try_from(metadata.get_content().clone())?
+                }
                 "add_source" => Value::new(context.store_source),
                 _ => panic!("this should not happen"),
             },
@@ -220,6 +234,24 @@ pub trait ResourceCollectionContext {
                             }
                         }
                     }
+                    "add_metadata" => {
+                        optional_dict_arg(attribute, "string", "string", &value)?;
+
+                        context.metadata = if value.get_type() == "NoneType" {
+                            BTreeMap::new()
+                        } else {
+                            value
+                                .iter()?
+                                .iter()
+                                .map(|key| {
+                                    let v = value.at(key.clone())?.to_string();
+                                    Ok((key.to_string(), v))
+                                })
+                                .collect::<Result<_, ValueError>>()?
+                        };
+
+                        Ok(())
+                    }
                     "add_source" => {
                         context.store_source = value.to_bool();
                         Ok(())
@@ -261,39 +293,91 @@ pub fn python_resource_to_value(
     call_stack: &mut CallStack,
     resource: &PythonResource,
     policy: &PythonPackagingPolicyValue,
+) -> ValueResult {
+    python_resource_to_value_relocating(
+        label,
+        type_values,
+        call_stack,
+        resource,
+        policy,
+        &BTreeMap::new(),
+    )
+}
+
+/// Like [python_resource_to_value()] but relocating packages not working in memory.
+///
+/// `in_memory_incompatible` maps top-level package names to why they
+/// don't work when imported from memory.
+fn python_resource_to_value_relocating(
+    label: &str,
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    resource: &PythonResource,
+    policy: &PythonPackagingPolicyValue,
+    in_memory_incompatible: &BTreeMap<String, String>,
 ) -> ValueResult {
     match resource {
         PythonResource::ModuleSource(sm) => {
             let mut m = PythonModuleSourceValue::new(sm.clone().into_owned());
-            policy.apply_to_resource(label, type_values, call_stack, &mut m)?;
+            policy.apply_to_resource_relocating(
+                label,
+                type_values,
+                call_stack,
+                &mut m,
+                in_memory_incompatible,
+            )?;
 
             Ok(Value::new(m))
         }
 
         PythonResource::PackageResource(data) => {
             let mut r = PythonPackageResourceValue::new(data.clone().into_owned());
-            policy.apply_to_resource(label, type_values, call_stack, &mut r)?;
+            policy.apply_to_resource_relocating(
+                label,
+                type_values,
+                call_stack,
+                &mut r,
+                in_memory_incompatible,
+            )?;
 
             Ok(Value::new(r))
         }
 
         PythonResource::PackageDistributionResource(resource) => {
             let mut r = PythonPackageDistributionResourceValue::new(resource.clone().into_owned());
-            policy.apply_to_resource(label, type_values, call_stack, &mut r)?;
+            policy.apply_to_resource_relocating(
+                label,
+                type_values,
+                call_stack,
+                &mut r,
+                in_memory_incompatible,
+            )?;
 
             Ok(Value::new(r))
         }
 
         PythonResource::ExtensionModule(em) => {
             let mut em = PythonExtensionModuleValue::new(em.clone().into_owned());
-            policy.apply_to_resource(label, type_values, call_stack, &mut em)?;
+            policy.apply_to_resource_relocating(
+                label,
+                type_values,
+                call_stack,
+                &mut em,
+                in_memory_incompatible,
+            )?;
 
             Ok(Value::new(em))
         }
 
         PythonResource::File(f) => {
             let mut value = FileValue::new(f.clone().into_owned());
-            policy.apply_to_resource(label, type_values, call_stack, &mut value)?;
+            policy.apply_to_resource_relocating(
+                label,
+                type_values,
+                call_stack,
+                &mut value,
+                in_memory_incompatible,
+            )?;
 
             Ok(Value::new(value))
         }
@@ -304,6 +388,76 @@ pub fn python_resource_to_value(
     }
 }
 
+/// Convert Python resources to Starlark values.
+///
+/// Resources not representable in Starlark are ignored. If the policy
+/// relocates packages not working when imported from memory, such packages
+/// are detected among all resources and their resources are relocated with
+/// a warning.
+pub fn python_resources_to_values(
+    label: &str,
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    resources: &[PythonResource],
+    policy: &PythonPackagingPolicyValue,
+) -> Result<Vec<Value>, ValueError> {
+    let resources = resources
+        .iter()
+        .filter(|r| is_resource_starlark_compatible(r))
+        .collect::<Vec<_>>();
+
+    let in_memory_incompatible = {
+        let inner = policy.inner(label)?;
+
+        if inner.relocate_in_memory_incompatible() {
+            let packages = find_in_memory_incompatible_packages(resources.iter().copied())
+                .map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER",
+                        message: format!("{:?}", e),
+                        label: label.to_string(),
+                    })
+                })?;
+
+            let location = inner.in_memory_incompatible_location();
+            for (package, reason) in &packages {
+                if let Some(location) = &location {
+                    warn!(
+                        "relocating {} to {} since it does not work when imported from memory: {}",
+                        package,
+                        location.to_string(),
+                        reason
+                    );
+                } else {
+                    warn!(
+                        "{} does not work when imported from memory but isn't relocated since the packaging policy has no filesystem fallback location: {}",
+                        package,
+                        reason
+                    );
+                }
+            }
+
+            packages
+        } else {
+            BTreeMap::new()
+        }
+    };
+
+    resources
+        .into_iter()
+        .map(|r| {
+            python_resource_to_value_relocating(
+                label,
+                type_values,
+                call_stack,
+                r,
+                policy,
+                &in_memory_incompatible,
+            )
+        })
+        .collect::<Result<Vec<Value>, ValueError>>()
+}
+
 /// Attempt to resolve the `PythonResourceAddCollectionContext` for a Value.
 pub fn add_context_for_value(
     value: &Value,
@@ -0,0 +1,101 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        py_packaging::binary::PythonBinaryBuilder,
+        starlark::{
+            env::{get_context, PyOxidizerEnvironmentContext},
+            python_executable::build_internal,
+        },
+    },
+    anyhow::Result,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{ResolvedTarget, ResolvedTargetValue, RunMode},
+    std::sync::Arc,
+};
+
+fn error_context<F, T>(label: &str, f: F) -> Result<T, ValueError>
+where
+    F: FnOnce() -> anyhow::Result<T>,
+{
+    f().map_err(|e| {
+        ValueError::Runtime(RuntimeError {
+            code: "PYOXIDIZER_PYTHON_TEST",
+            message: format!("{:?}", e),
+            label: label.to_string(),
+        })
+    })
+}
+
+/// A Python executable running a test entry point.
+pub struct PythonTestValue {
+    /// The executable, configured to run the test module.
+    pub exe: Arc<dyn PythonBinaryBuilder>,
+
+    /// Arguments passed to the test module.
+    pub args: Vec<String>,
+}
+
+impl TypedValue for PythonTestValue {
+    type Holder = Mutable<PythonTestValue>;
+    const TYPE: &'static str = "PythonTest";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl PythonTestValue {
+    fn build(
+        &self,
+        type_values: &TypeValues,
+        target: &str,
+        context: &PyOxidizerEnvironmentContext,
+    ) -> Result<ResolvedTarget> {
+        let (resolved, exe_path) = build_internal(&*self.exe, type_values, target, context)?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::Command {
+                path: exe_path,
+                args: self.args.clone(),
+            },
+            output_path: resolved.output_path,
+        })
+    }
+
+    fn build_starlark(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let inner = error_context("PythonTest.build()", || {
+            self.build(type_values, &target, &pyoxidizer_context)
+        })?;
+
+        Ok(Value::new(ResolvedTargetValue { inner }))
+    }
+}
+
+starlark_module! { python_test_module =>
+    PythonTest.build(
+        env env,
+        this,
+        target: String
+    ) {
+        let this = this.downcast_ref::<PythonTestValue>().unwrap();
+        this.build_starlark(env, target)
+    }
+}
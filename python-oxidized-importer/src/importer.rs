@@ -9,6 +9,8 @@ This module defines a Python meta path importer and associated functionality
 for importing Python modules from memory.
 */
 
+#[cfg(target_os = "linux")]
+use crate::memfd_library::{load_shared_library_dependencies, memory_file_path};
 #[cfg(windows)]
 use {
     crate::memory_dll::{free_library_memory, get_proc_address_memory, load_library_memory},
@@ -17,6 +19,7 @@ use {
 };
 use {
     crate::{
+        audit,
         conversion::pyobject_to_pathbuf,
         get_module_state,
         path_entry_finder::OxidizedPathEntryFinder,
@@ -28,16 +31,22 @@ use {
         OXIDIZED_IMPORTER_NAME_STR,
     },
     pyo3::{
-        exceptions::{PyImportError, PyValueError},
+        exceptions::{PyFileNotFoundError, PyImportError, PyModuleNotFoundError, PyValueError},
         ffi as pyffi,
         prelude::*,
         types::{PyBytes, PyDict, PyList, PyString, PyTuple},
         AsPyPointer, FromPyPointer, PyNativeType, PyTraverseError, PyVisit,
     },
-    python_packaging::resource::BytecodeOptimizationLevel,
-    std::sync::Arc,
+    python_packaging::{interpreter::ImportPrecedence, resource::BytecodeOptimizationLevel},
+    std::{collections::HashMap, str::FromStr, sync::Arc},
 };
 
+/// Virtual `sys.path` entry serviced by [OxidizedFinder::path_hook()].
+///
+/// Unlike paths derived from the current executable, this value is stable
+/// across machines and install locations.
+const PATH_HOOK_VIRTUAL_STR: &str = "oxidized://resources";
+
 #[cfg(windows)]
 #[allow(non_camel_case_types)]
 type py_init_fn = extern "C" fn() -> *mut pyffi::PyObject;
@@ -105,7 +114,46 @@ fn extension_module_shared_library_create_module(
     })
 }
 
-#[cfg(unix)]
+/// Implementation of `Loader.create_module()` for in-memory extension modules.
+///
+/// The library is written to an anonymous file created with `memfd_create()`.
+/// `imp.create_dynamic()` is then called with the spec's origin temporarily
+/// set to the file's `/proc/self/fd/` path. So CPython loads the library with
+/// `dlopen()` like any other extension module, but without it touching the
+/// filesystem.
+#[cfg(target_os = "linux")]
+fn extension_module_shared_library_create_module(
+    resources_state: &PythonResourcesState<u8>,
+    py: Python,
+    _sys_modules: &PyAny,
+    spec: &PyAny,
+    _name_py: &PyAny,
+    name: &str,
+    library_data: &[u8],
+) -> PyResult<Py<PyAny>> {
+    load_shared_library_dependencies(resources_state, name)?;
+
+    let (path, _) = memory_file_path(name, library_data).map_err(|e| {
+        PyImportError::new_err((
+            format!(
+                "unable to write extension module library to memory file: {}",
+                e
+            ),
+            name.to_owned(),
+        ))
+    })?;
+
+    let create_dynamic = py.import("_imp")?.getattr("create_dynamic")?;
+
+    let origin = spec.getattr("origin")?;
+    spec.setattr("origin", path)?;
+    let module = create_dynamic.call1((spec,));
+    spec.setattr("origin", origin)?;
+
+    Ok(module?.into_py(py))
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
 fn extension_module_shared_library_create_module(
     _resources_state: &PythonResourcesState<u8>,
     _py: Python,
@@ -115,7 +163,7 @@ fn extension_module_shared_library_create_module(
     _name: &str,
     _library_data: &[u8],
 ) -> PyResult<Py<PyAny>> {
-    panic!("should only be called on Windows");
+    panic!("should only be called on Windows or Linux");
 }
 
 /// Reimplementation of `_PyImport_LoadDynamicModuleWithSpec()`.
@@ -271,6 +319,14 @@ pub struct ImporterState {
     pub(crate) multiprocessing_set_start_method: Option<String>,
     /// Whether to automatically register ourself with `pkg_resources` when it is imported.
     pub(crate) pkg_resources_import_auto_register: bool,
+    /// Whether to set `__file__` on modules imported from memory.
+    pub(crate) synthesize_dunder_file: bool,
+    /// Where to look for modules of packages, keyed by package name.
+    ///
+    /// Packages not present use [ImportPrecedence::MemoryFirst].
+    pub(crate) package_precedence: HashMap<String, ImportPrecedence>,
+    /// Whether `importlib.reload()` prefers modules on the filesystem.
+    pub(crate) reload_from_filesystem: bool,
     /// Holds state about importable resources.
     ///
     /// This field is a PyCapsule and is a glorified wrapper around
@@ -389,6 +445,9 @@ impl ImporterState {
             multiprocessing_set_start_method: None,
             // TODO value should come from config.
             pkg_resources_import_auto_register: true,
+            synthesize_dunder_file: false,
+            package_precedence: HashMap::new(),
+            reload_from_filesystem: false,
             resources_state: capsule,
         })
     }
@@ -448,6 +507,39 @@ impl ImporterState {
     pub fn set_multiprocessing_set_start_method(&mut self, value: Option<String>) {
         self.multiprocessing_set_start_method = value;
     }
+
+    /// Set whether to set `__file__` on modules imported from memory.
+    pub fn set_synthesize_dunder_file(&mut self, value: bool) {
+        self.synthesize_dunder_file = value;
+    }
+
+    /// Set whether `importlib.reload()` prefers modules on the filesystem.
+    pub fn set_reload_from_filesystem(&mut self, value: bool) {
+        self.reload_from_filesystem = value;
+    }
+
+    /// Set where to look for modules of a package and its sub-packages.
+    pub fn set_package_precedence(&mut self, package: &str, value: ImportPrecedence) {
+        self.package_precedence.insert(package.to_string(), value);
+    }
+
+    /// Resolve where to look for a module.
+    ///
+    /// The precedence of the closest package defining one is used.
+    pub(crate) fn resolve_precedence(&self, fullname: &str) -> ImportPrecedence {
+        let mut name = fullname;
+
+        loop {
+            if let Some(precedence) = self.package_precedence.get(name) {
+                return *precedence;
+            }
+
+            match name.rfind('.') {
+                Some(idx) => name = &name[0..idx],
+                None => return ImportPrecedence::MemoryFirst,
+            }
+        }
+    }
 }
 
 impl Drop for ImporterState {
@@ -478,6 +570,11 @@ impl OxidizedFinder {
         self.state.clone()
     }
 
+    /// Obtain the `PythonResourcesState` indexed by this instance.
+    pub fn get_resources_state<'a>(&self) -> &PythonResourcesState<'a, u8> {
+        self.state.get_resources_state()
+    }
+
     /// Construct an instance from a module and resources state.
     pub fn new_from_module_and_resources<'a>(
         py: Python,
@@ -524,12 +621,35 @@ impl OxidizedFinder {
         let py = slf.py();
         let finder = slf.borrow();
 
+        let precedence = finder.state.resolve_precedence(&fullname);
+
+        // `importlib.reload()` passes the module being reloaded as `target`.
+        let reloading = finder.state.reload_from_filesystem
+            && precedence != ImportPrecedence::MemoryOnly
+            && target.is_some();
+
+        if precedence == ImportPrecedence::FilesystemFirst || reloading {
+            let spec = finder.find_filesystem_spec(py, &fullname, path, target)?;
+
+            if !spec.is_none() {
+                return Ok(spec);
+            }
+        }
+
         let module = match finder
             .state
             .get_resources_state()
             .resolve_importable_module(&fullname, finder.state.optimize_level)
+            .map_err(PyImportError::new_err)?
         {
             Some(module) => module,
+            None if precedence == ImportPrecedence::MemoryOnly => {
+                // Raising prevents subsequent meta path finders from being consulted.
+                let err = PyModuleNotFoundError::new_err(format!("No module named '{}'", fullname));
+                err.value(py).setattr("name", &fullname)?;
+
+                return Err(err);
+            }
             None => return Ok(py.None().into_ref(py)),
         };
 
@@ -539,6 +659,7 @@ impl OxidizedFinder {
                 finder.state.module_spec_type.clone_ref(py).into_ref(py),
                 slf,
                 finder.state.optimize_level,
+                finder.state.synthesize_dunder_file,
             ),
             ModuleFlavor::Builtin => {
                 // BuiltinImporter.find_spec() always returns None if `path` is defined.
@@ -554,6 +675,21 @@ impl OxidizedFinder {
                 .frozen_importer
                 .call_method(py, "find_spec", (fullname, path, target), None)?
                 .into_ref(py)),
+            ModuleFlavor::Namespace => {
+                // `path` is only defined for sub-packages. Top-level packages
+                // are searched for on `sys.path`.
+                let search_path = if path.is_none() {
+                    finder.state.sys_module.getattr(py, "path")?.into_ref(py)
+                } else {
+                    path
+                };
+
+                module.resolve_namespace_spec(
+                    py,
+                    finder.state.module_spec_type.clone_ref(py).into_ref(py),
+                    search_path,
+                )
+            }
         }
     }
 
@@ -591,6 +727,7 @@ impl OxidizedFinder {
         let module = match state
             .get_resources_state()
             .resolve_importable_module(&key, state.optimize_level)
+            .map_err(PyImportError::new_err)?
         {
             Some(module) => module,
             None => return Ok(py.None()),
@@ -607,6 +744,8 @@ impl OxidizedFinder {
             // potentially work around this and move all extension module
             // initialization into `exec_module()`.
             if let Some(library_data) = &module.in_memory_extension_module_shared_library() {
+                audit(py, "oxidized_importer.load_extension_module", (&key,))?;
+
                 let sys_modules = state.sys_module.getattr(py, "modules")?;
 
                 extension_module_shared_library_create_module(
@@ -642,6 +781,7 @@ impl OxidizedFinder {
         let mut entry = match state
             .get_resources_state()
             .resolve_importable_module(&key, state.optimize_level)
+            .map_err(PyImportError::new_err)?
         {
             Some(entry) => entry,
             None => {
@@ -651,6 +791,14 @@ impl OxidizedFinder {
             }
         };
 
+        if matches!(
+            entry.flavor,
+            ModuleFlavor::SourceBytecode | ModuleFlavor::Extension
+        ) {
+            let origin = module.getattr("__spec__")?.getattr("origin")?;
+            audit(py, "oxidized_importer.exec_module", (&key, origin))?;
+        }
+
         if let Some(bytecode) = entry.resolve_bytecode(
             py,
             state.optimize_level,
@@ -715,6 +863,8 @@ impl OxidizedFinder {
     /// found. The path is expected to be constructed using a module’s __file__
     /// attribute or an item from a package’s __path__.
     fn get_data<'p>(slf: &'p PyCell<Self>, path: &str) -> PyResult<&'p PyAny> {
+        audit(slf.py(), "oxidized_importer.get_data", (path,))?;
+
         slf.borrow()
             .state
             .get_resources_state()
@@ -735,6 +885,7 @@ impl OxidizedFinder {
         let mut module = match state
             .get_resources_state()
             .resolve_importable_module(&key, state.optimize_level)
+            .map_err(PyImportError::new_err)?
         {
             Some(module) => module,
             None => return Ok(py.None()),
@@ -765,6 +916,7 @@ impl OxidizedFinder {
         let module = match state
             .get_resources_state()
             .resolve_importable_module(&key, state.optimize_level)
+            .map_err(PyImportError::new_err)?
         {
             Some(module) => module,
             None => return Ok(py.None()),
@@ -802,10 +954,11 @@ impl OxidizedFinder {
         let module = state
             .get_resources_state()
             .resolve_importable_module(&key, state.optimize_level)
+            .map_err(PyImportError::new_err)?
             .ok_or_else(|| make_error("unknown module"))?;
 
         module
-            .resolve_origin(slf.py())
+            .resolve_origin(slf.py(), state.synthesize_dunder_file)
             .map_err(|_| make_error("unable to resolve origin"))?
             .ok_or_else(|| make_error("no origin"))
     }
@@ -824,6 +977,7 @@ impl OxidizedFinder {
         let entry = match state
             .get_resources_state()
             .resolve_importable_module(&key, state.optimize_level)
+            .map_err(PyImportError::new_err)?
         {
             Some(entry) => entry,
             None => return Ok(slf.py().None()),
@@ -904,8 +1058,19 @@ impl OxidizedFinder {
 
     /// OxidizedFinder.__new__(relative_path_origin=None))
     #[new]
-    #[args(relative_path_origin = "None")]
-    fn new(py: Python, relative_path_origin: Option<&PyAny>) -> PyResult<Self> {
+    #[args(
+        relative_path_origin = "None",
+        synthesize_dunder_file = false,
+        package_precedence = "None",
+        reload_from_filesystem = false
+    )]
+    fn new(
+        py: Python,
+        relative_path_origin: Option<&PyAny>,
+        synthesize_dunder_file: bool,
+        package_precedence: Option<&PyDict>,
+        reload_from_filesystem: bool,
+    ) -> PyResult<Self> {
         // We need to obtain an ImporterState instance. This requires handles on a
         // few items...
 
@@ -921,13 +1086,21 @@ impl OxidizedFinder {
             resources_state.set_origin(pyobject_to_pathbuf(py, py_origin)?);
         }
 
+        let mut state = ImporterState::new(py, m, bootstrap_module, resources_state)?;
+        state.set_synthesize_dunder_file(synthesize_dunder_file);
+        state.set_reload_from_filesystem(reload_from_filesystem);
+
+        if let Some(package_precedence) = package_precedence {
+            for (package, precedence) in package_precedence {
+                let precedence = ImportPrecedence::from_str(precedence.extract::<&str>()?)
+                    .map_err(PyValueError::new_err)?;
+
+                state.set_package_precedence(package.extract::<&str>()?, precedence);
+            }
+        }
+
         Ok(OxidizedFinder {
-            state: Arc::new(ImporterState::new(
-                py,
-                m,
-                bootstrap_module,
-                resources_state,
-            )?),
+            state: Arc::new(state),
         })
     }
 
@@ -958,11 +1131,37 @@ impl OxidizedFinder {
             .into_ref(py)
     }
 
+    #[getter]
+    fn path_hook_virtual_str(&self) -> &'static str {
+        PATH_HOOK_VIRTUAL_STR
+    }
+
     #[getter]
     fn pkg_resources_import_auto_register(&self) -> PyResult<bool> {
         Ok(self.state.pkg_resources_import_auto_register)
     }
 
+    #[getter]
+    fn synthesize_dunder_file(&self) -> bool {
+        self.state.synthesize_dunder_file
+    }
+
+    #[getter]
+    fn package_precedence<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let d = PyDict::new(py);
+
+        for (package, precedence) in &self.state.package_precedence {
+            d.set_item(package, precedence.to_string())?;
+        }
+
+        Ok(d)
+    }
+
+    #[getter]
+    fn reload_from_filesystem(&self) -> bool {
+        self.state.reload_from_filesystem
+    }
+
     fn path_hook(slf: &PyCell<Self>, path: &PyAny) -> PyResult<OxidizedPathEntryFinder> {
         Self::path_hook_inner(slf, path).map_err(|inner| {
             let err = PyImportError::new_err("error running OxidizedFinder.path_hook");
@@ -1026,6 +1225,24 @@ impl OxidizedFinder {
         Ok(())
     }
 
+    /// Open an embedded data file for binary reading.
+    ///
+    /// Raises FileNotFoundError if no data file with this name is indexed.
+    fn open_data<'p>(&self, py: Python<'p>, name: &str) -> PyResult<&'p PyAny> {
+        if let Some(file) = self.state.get_resources_state().get_data_file(
+            py,
+            self.state.resources_state.as_ref(py),
+            name,
+        )? {
+            Ok(file)
+        } else {
+            Err(PyFileNotFoundError::new_err(format!(
+                "data file not found: {}",
+                name
+            )))
+        }
+    }
+
     fn indexed_resources<'p>(&self, py: Python<'p>) -> PyResult<&'p PyList> {
         let resources_state = self.state.get_resources_state();
 
@@ -1057,6 +1274,33 @@ impl OxidizedFinder {
         Ok(())
     }
 
+    #[args(is_package = false)]
+    fn add_module_source(&self, name: &str, source: &PyAny, is_package: bool) -> PyResult<()> {
+        let source = if let Ok(source) = source.cast_as::<PyString>() {
+            source.to_str()?.as_bytes().to_vec()
+        } else {
+            source.extract::<&[u8]>()?.to_vec()
+        };
+
+        self.state
+            .get_resources_state_mut()
+            .add_module_source(name, source, is_package)
+            .map_err(PyValueError::new_err)
+    }
+
+    #[args(is_package = false)]
+    fn add_module_bytecode(&self, name: &str, bytecode: &[u8], is_package: bool) -> PyResult<()> {
+        self.state
+            .get_resources_state_mut()
+            .add_module_bytecode(
+                name,
+                bytecode.to_vec(),
+                self.state.optimize_level,
+                is_package,
+            )
+            .map_err(PyValueError::new_err)
+    }
+
     #[args(ignore_builtin = true, ignore_frozen = true)]
     fn serialize_indexed_resources<'p>(
         &self,
@@ -1075,6 +1319,61 @@ impl OxidizedFinder {
 }
 
 impl OxidizedFinder {
+    /// Find a module spec using `PathFinder`.
+    ///
+    /// Search path entries serviced by our path hook are ignored, so the search
+    /// doesn't recurse into us.
+    fn find_filesystem_spec<'p>(
+        &self,
+        py: Python<'p>,
+        fullname: &str,
+        path: &PyAny,
+        target: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
+        let search_path = if path.is_none() {
+            self.state.sys_module.getattr(py, "path")?.into_ref(py)
+        } else {
+            path
+        };
+
+        // Like path_hook_inner(), accept both directory separators as prefix match.
+        let path_hook_base = self.path_hook_base_str(py);
+        let path_hook_prefix = PyTuple::new(
+            py,
+            [
+                path_hook_base.call_method1("__add__", ("/",))?,
+                path_hook_base.call_method1("__add__", ("\\",))?,
+            ],
+        );
+
+        let mut filesystem_path = vec![];
+        for entry in search_path.iter()? {
+            let entry = entry?;
+
+            if let Ok(entry_str) = entry.cast_as::<PyString>() {
+                if entry_str.eq(path_hook_base)?
+                    || entry_str
+                        .call_method1("startswith", (path_hook_prefix,))?
+                        .extract::<bool>()?
+                    || entry_str
+                        .call_method1("startswith", (PATH_HOOK_VIRTUAL_STR,))?
+                        .extract::<bool>()?
+                {
+                    continue;
+                }
+            }
+
+            filesystem_path.push(entry);
+        }
+
+        py.import("_frozen_importlib_external")?
+            .getattr("PathFinder")?
+            .call_method1(
+                "find_spec",
+                (fullname, PyList::new(py, filesystem_path), target),
+            )
+    }
+
     fn path_hook_inner(
         slf: &PyCell<Self>,
         path_original: &PyAny,
@@ -1086,6 +1385,7 @@ impl OxidizedFinder {
         //
         // * self.path_hook_base_str
         // * virtual sub-directories under self.path_hook_base_str
+        // * PATH_HOOK_VIRTUAL_STR and virtual sub-directories under it
         //
         // There is a mismatch between the ways that Rust and Python store paths.
         // self.current_exe is a Rust PathBuf and came from Rust. We can get the raw
@@ -1101,7 +1401,17 @@ impl OxidizedFinder {
         // Only accept str.
         let path = path_original.cast_as::<PyString>()?;
 
-        let path_hook_base = finder.path_hook_base_str(py).cast_as::<PyString>()?;
+        // The virtual path entry is a stand-in for the current executable that
+        // doesn't depend on where the executable lives.
+        let path_hook_virtual = PyString::new(py, PATH_HOOK_VIRTUAL_STR);
+        let path_hook_base = if path
+            .call_method1("startswith", (path_hook_virtual,))?
+            .extract::<bool>()?
+        {
+            path_hook_virtual
+        } else {
+            finder.path_hook_base_str(py).cast_as::<PyString>()?
+        };
 
         let target_package = if path.compare(path_hook_base)? == std::cmp::Ordering::Equal {
             None
@@ -1198,80 +1508,6 @@ impl OxidizedFinder {
     }
 }
 
-/// Path-like object facilitating Python resource access.
-///
-/// This implements importlib.abc.Traversable.
-#[pyclass(module = "oxidized_importer")]
-pub(crate) struct PyOxidizerTraversable {
-    state: Arc<ImporterState>,
-    path: String,
-}
-
-#[pymethods]
-impl PyOxidizerTraversable {
-    /// Yield Traversable objects in self.
-    fn iterdir(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Read contents of self as bytes.
-    fn read_bytes(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Read contents of self as text.
-    fn read_text(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Return True if self is a dir.
-    fn is_dir(&self) -> PyResult<bool> {
-        // We are a directory if the current path is a known package.
-        // TODO We may need to expand this definition in the future to cover
-        // virtual subdirectories in addressable resources. But this will require
-        // changes to the resources data format to capture said annotations.
-        if let Some(entry) = self
-            .state
-            .get_resources_state()
-            .resolve_importable_module(&self.path, self.state.optimize_level)
-        {
-            if entry.is_package {
-                return Ok(true);
-            }
-        }
-
-        Ok(false)
-    }
-
-    /// Return True if self is a file.
-    fn is_file(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Return Traversable child in self.
-    #[allow(unused)]
-    fn joinpath(&self, child: &PyAny) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Return Traversable child in self.
-    #[allow(unused)]
-    fn __truediv__(&self, child: &PyAny) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// mode may be 'r' or 'rb' to open as text or binary. Return a handle
-    /// suitable for reading (same as pathlib.Path.open).
-    ///
-    /// When opening as text, accepts encoding parameters such as those
-    /// accepted by io.TextIOWrapper.
-    #[allow(unused)]
-    #[args(py_args = "*", py_kwargs = "**")]
-    fn open(&self, py_args: &PyTuple, py_kwargs: Option<&PyDict>) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-}
-
 /// Replace all meta path importers with an OxidizedFinder instance and return it.
 ///
 /// This is called after PyInit_* to finish the initialization of the
@@ -7,6 +7,8 @@
 mod conversion;
 #[allow(clippy::needless_option_as_deref)]
 mod importer;
+#[cfg(target_os = "linux")]
+mod memfd_library;
 #[cfg(windows)]
 mod memory_dll;
 mod package_metadata;
@@ -40,12 +42,13 @@ use {
         path_entry_finder::OxidizedPathEntryFinder,
         pkg_resources::{register_pkg_resources_with_module, OxidizedPkgResourcesProvider},
         python_resources::OxidizedResource,
-        resource_reader::OxidizedResourceReader,
+        resource_reader::{OxidizedResourceFile, OxidizedResourceReader, OxidizedTraversable},
     },
     pyo3::{
-        exceptions::{PyImportError, PyValueError},
+        exceptions::{PyFileNotFoundError, PyImportError, PyValueError},
         ffi as pyffi,
         prelude::*,
+        types::PyTuple,
         AsPyPointer, FromPyPointer,
     },
 };
@@ -158,6 +161,45 @@ pub(crate) fn decode_source<'p>(
     newline_decoder.call_method("decode", (data,), None)
 }
 
+/// Raise a PEP 578 audit event via `sys.audit()`.
+///
+/// An error is returned if an audit hook raises, which should abort the
+/// operation being audited.
+pub(crate) fn audit(py: Python, event: &str, args: impl IntoPy<Py<PyTuple>>) -> PyResult<()> {
+    let mut call_args = vec![event.into_py(py)];
+    call_args.extend(
+        args.into_py(py)
+            .as_ref(py)
+            .iter()
+            .map(|arg| arg.into_py(py)),
+    );
+
+    py.import("sys")?
+        .getattr("audit")?
+        .call1(PyTuple::new(py, call_args))?;
+
+    Ok(())
+}
+
+/// Open an embedded data file for binary reading.
+///
+/// The first [OxidizedFinder] on `sys.meta_path` services the request.
+#[pyfunction]
+fn open_data<'p>(py: Python<'p>, name: &str) -> PyResult<&'p PyAny> {
+    for finder in py.import("sys")?.getattr("meta_path")?.iter()? {
+        let finder = finder?;
+
+        if let Ok(finder) = finder.cast_as::<PyCell<OxidizedFinder>>() {
+            return finder.call_method1("open_data", (name,));
+        }
+    }
+
+    Err(PyFileNotFoundError::new_err(format!(
+        "data file not found: {}",
+        name
+    )))
+}
+
 #[pyfunction]
 fn register_pkg_resources(py: Python) -> PyResult<()> {
     register_pkg_resources_with_module(py, py.import("pkg_resources")?)
@@ -189,12 +231,15 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
 
     m.add_function(wrap_pyfunction!(decode_source, m)?)?;
     m.add_function(wrap_pyfunction!(register_pkg_resources, m)?)?;
+    m.add_function(wrap_pyfunction!(open_data, m)?)?;
 
     m.add_class::<crate::package_metadata::OxidizedDistribution>()?;
     m.add_class::<OxidizedFinder>()?;
     m.add_class::<OxidizedResource>()?;
     m.add_class::<crate::python_resource_collector::OxidizedResourceCollector>()?;
     m.add_class::<OxidizedResourceReader>()?;
+    m.add_class::<OxidizedTraversable>()?;
+    m.add_class::<OxidizedResourceFile>()?;
     m.add_class::<OxidizedPathEntryFinder>()?;
     m.add_class::<OxidizedPkgResourcesProvider>()?;
     m.add_class::<crate::python_resource_types::PythonModuleSource>()?;
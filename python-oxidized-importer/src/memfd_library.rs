@@ -0,0 +1,158 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Functionality for loading Linux shared libraries from memory.
+
+Library data is written to an anonymous file created with `memfd_create()`.
+The dynamic linker then opens the file via its `/proc/self/fd/` path. So
+library data is never written to a real filesystem, except on kernels
+without `memfd_create()`, where an unlinked temporary file is used.
+*/
+
+use {
+    crate::python_resources::PythonResourcesState,
+    once_cell::sync::Lazy,
+    pyo3::{exceptions::PyImportError, prelude::*},
+    std::{
+        collections::HashMap,
+        ffi::{CStr, CString},
+        fs::File,
+        io::Write,
+        os::unix::io::{AsRawFd, FromRawFd, RawFd},
+        sync::Mutex,
+    },
+};
+
+/// Anonymous files holding library data, keyed by resource name.
+///
+/// Files are kept open for the lifetime of the process. So loading a library
+/// multiple times resolves to the same path and the dynamic linker reuses the
+/// already loaded library.
+static MEMORY_FILES: Lazy<Mutex<HashMap<String, File>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn fd_path(f: &File) -> String {
+    format!("/proc/self/fd/{}", f.as_raw_fd())
+}
+
+/// Create an anonymous file.
+///
+/// `memfd_create()` is invoked as a raw syscall because glibc only exposes a
+/// wrapper since 2.27 and binaries must run on glibc 2.19. Kernels older than
+/// 3.17 don't have the syscall. There, a file in the temporary directory is
+/// created and unlinked right away. Its data stays reachable via the open
+/// descriptor.
+fn create_memory_file(name: &str) -> std::io::Result<File> {
+    let c_name = CString::new(name)?;
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_memfd_create,
+            c_name.as_ptr(),
+            libc::MFD_CLOEXEC as libc::c_uint,
+        )
+    };
+
+    if fd >= 0 {
+        return Ok(unsafe { File::from_raw_fd(fd as RawFd) });
+    }
+
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() != Some(libc::ENOSYS) {
+        return Err(err);
+    }
+
+    let path = std::env::temp_dir().join(format!(
+        "oxidized-importer-{}-{}",
+        std::process::id(),
+        name.replace('/', "_")
+    ));
+    let f = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    std::fs::remove_file(&path)?;
+
+    Ok(f)
+}
+
+/// Obtain a path to an anonymous file holding the data of a named library.
+///
+/// The file is created the first time a name is seen. Returns whether the file
+/// was created by this call.
+pub(crate) fn memory_file_path(name: &str, data: &[u8]) -> std::io::Result<(String, bool)> {
+    let mut files = MEMORY_FILES.lock().unwrap();
+
+    if let Some(f) = files.get(name) {
+        return Ok((fd_path(f), false));
+    }
+
+    let mut f = create_memory_file(name)?;
+    f.write_all(data)?;
+
+    let path = fd_path(&f);
+    files.insert(name.to_string(), f);
+
+    Ok((path, true))
+}
+
+/// Load the in-memory shared libraries a named resource depends on.
+///
+/// Libraries are loaded with `RTLD_GLOBAL` before the resource itself. So the
+/// dynamic linker resolves the resource's dependencies to them by soname.
+/// Dependencies without in-memory data are left for the dynamic linker to find.
+pub(crate) fn load_shared_library_dependencies(
+    resources_state: &PythonResourcesState<u8>,
+    name: &str,
+) -> PyResult<()> {
+    for dependency in resources_state.resolve_shared_library_dependency_names(name) {
+        let data = match resources_state
+            .resolve_in_memory_shared_library_data(dependency)
+            .map_err(PyImportError::new_err)?
+        {
+            Some(data) => data,
+            None => continue,
+        };
+
+        let (path, created) = memory_file_path(dependency, data).map_err(|e| {
+            PyImportError::new_err((
+                format!(
+                    "unable to write library {} to memory file: {}",
+                    dependency, e
+                ),
+                name.to_owned(),
+            ))
+        })?;
+
+        // The library was loaded by an earlier import.
+        if !created {
+            continue;
+        }
+
+        load_shared_library_dependencies(resources_state, dependency)?;
+
+        let c_path = CString::new(path).unwrap();
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL) };
+
+        if handle.is_null() {
+            let error = unsafe { libc::dlerror() };
+            let error = if error.is_null() {
+                "unknown error".to_string()
+            } else {
+                unsafe { CStr::from_ptr(error) }
+                    .to_string_lossy()
+                    .to_string()
+            };
+
+            return Err(PyImportError::new_err((
+                format!(
+                    "unable to load library {} from memory: {}",
+                    dependency, error
+                ),
+                name.to_owned(),
+            )));
+        }
+    }
+
+    Ok(())
+}
@@ -9,7 +9,7 @@ use {
         python_resources::{name_at_package_hierarchy, name_within_package_hierarchy},
     },
     pyo3::{
-        exceptions::{PyIOError, PyNotImplementedError, PyValueError},
+        exceptions::{PyIOError, PyValueError},
         prelude::*,
         types::{PyBytes, PyDict, PyList, PyString, PyTuple, PyType},
     },
@@ -227,9 +227,71 @@ impl OxidizedDistribution {
         }
     }
 
+    /// Files in this distribution.
+    ///
+    /// Parsed from the `RECORD` file, falling back to `SOURCES.txt`. Returns
+    /// `None` if neither exists.
     #[getter]
-    fn files(&self) -> PyResult<()> {
-        Err(PyNotImplementedError::new_err(()))
+    fn files<'p>(self_: PyRef<Self>, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let text = self_.read_text(py, "RECORD".into())?;
+
+        let lines = if !text.is_none() {
+            text.call_method0("splitlines")?
+        } else {
+            let text = self_.read_text(py, "SOURCES.txt".into())?;
+
+            if text.is_none() {
+                return Ok(py.None().into_ref(py));
+            }
+
+            // SOURCES.txt only has paths. Quote them so they are treated as
+            // a single CSV field.
+            let lines = text
+                .call_method0("splitlines")?
+                .iter()?
+                .map(|line| Ok(format!("\"{}\"", line?.extract::<&str>()?)))
+                .collect::<PyResult<Vec<_>>>()?;
+
+            PyList::new(py, lines).into()
+        };
+
+        let importlib_metadata = py.import("importlib.metadata")?;
+        let package_path_type = importlib_metadata.getattr("PackagePath")?;
+        let file_hash_type = importlib_metadata.getattr("FileHash")?;
+
+        let dist = self_.into_py(py);
+        let files = PyList::empty(py);
+
+        for row in py.import("csv")?.call_method1("reader", (lines,))?.iter()? {
+            let row = row?.extract::<Vec<String>>()?;
+
+            let name = match row.first() {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let path = package_path_type.call1((name,))?;
+
+            match row.get(1).filter(|hash| !hash.is_empty()) {
+                Some(hash) => path.setattr("hash", file_hash_type.call1((hash,))?)?,
+                None => path.setattr("hash", py.None())?,
+            }
+
+            match row.get(2).filter(|size| !size.is_empty()) {
+                Some(size) => path.setattr(
+                    "size",
+                    size.parse::<u64>()
+                        .map_err(|e| PyValueError::new_err(format!("invalid size: {}", e)))?,
+                )?,
+                None => path.setattr("size", py.None())?,
+            }
+
+            path.setattr("dist", &dist)?;
+
+            files.append(path)?;
+        }
+
+        Ok(files.into())
     }
 
     #[getter]
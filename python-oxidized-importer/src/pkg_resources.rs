@@ -124,7 +124,12 @@ impl OxidizedPkgResourcesProvider {
     ) -> PyResult<&'p PyAny> {
         self.state
             .get_resources_state()
-            .get_package_resource_file(py, &self.package, resource_name)?
+            .get_package_resource_file(
+                py,
+                self.state.resources_state.as_ref(py),
+                &self.package,
+                resource_name,
+            )?
             .ok_or_else(|| PyIOError::new_err("resource does not exist"))
     }
 
@@ -143,7 +148,12 @@ impl OxidizedPkgResourcesProvider {
         Ok(self
             .state
             .get_resources_state()
-            .get_package_resource_file(py, &self.package, resource_name)
+            .get_package_resource_file(
+                py,
+                self.state.resources_state.as_ref(py),
+                &self.package,
+                resource_name,
+            )
             .unwrap_or(None)
             .is_some())
     }
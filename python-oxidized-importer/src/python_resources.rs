@@ -7,12 +7,17 @@ Management of Python resources.
 */
 
 use {
-    crate::conversion::{
-        path_to_pathlib_path, pyobject_optional_resources_map_to_owned_bytes,
-        pyobject_optional_resources_map_to_pathbuf, pyobject_to_owned_bytes_optional,
-        pyobject_to_pathbuf_optional,
+    crate::{
+        audit,
+        conversion::{
+            path_to_pathlib_path, pyobject_optional_resources_map_to_owned_bytes,
+            pyobject_optional_resources_map_to_pathbuf, pyobject_to_owned_bytes_optional,
+            pyobject_to_pathbuf_optional,
+        },
+        resource_reader::OxidizedResourceFile,
     },
     anyhow::Result,
+    once_cell::sync::OnceCell,
     pyo3::{
         buffer::PyBuffer,
         exceptions::{PyImportError, PyOSError, PyValueError},
@@ -22,19 +27,101 @@ use {
         PyTypeInfo,
     },
     python_packaging::resource::BytecodeOptimizationLevel,
-    python_packed_resources::Resource,
+    python_packed_resources::{Resource, ResourceNameIndex},
     std::{
         borrow::Cow,
         cell::RefCell,
-        collections::{hash_map::Entry, BTreeSet, HashMap},
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
         ffi::CStr,
         os::raw::c_int,
         path::{Path, PathBuf},
+        sync::Mutex,
     },
 };
 
 const ENOENT: c_int = 2;
 
+/// Whether in-memory data of a resource must be decoded before use.
+fn is_encoded<X>(resource: &Resource<X>) -> bool
+where
+    [X]: ToOwned<Owned = Vec<X>>,
+{
+    resource.in_memory_zstd_compressed || resource.in_memory_encrypted
+}
+
+/// Decrypt and decompress in-memory data of a resource.
+fn decode_resource<'a>(
+    resource: Resource<'a, u8>,
+    decryption_key: Option<&[u8; 32]>,
+) -> Result<Resource<'a, u8>, &'static str> {
+    let resource = if resource.in_memory_encrypted {
+        let key =
+            decryption_key.ok_or("resource data is encrypted and no decryption key is set")?;
+
+        decrypt_resource(resource, key)?
+    } else {
+        resource
+    };
+
+    if resource.in_memory_zstd_compressed {
+        zstd_decompress_resource(resource)
+    } else {
+        Ok(resource)
+    }
+}
+
+#[cfg(feature = "resources-encryption")]
+fn decrypt_resource<'a>(
+    resource: Resource<'a, u8>,
+    key: &[u8; 32],
+) -> Result<Resource<'a, u8>, &'static str> {
+    resource
+        .decrypt(key)
+        .map_err(|_| "error decrypting resource data")
+}
+
+#[cfg(not(feature = "resources-encryption"))]
+fn decrypt_resource<'a>(
+    _resource: Resource<'a, u8>,
+    _key: &[u8; 32],
+) -> Result<Resource<'a, u8>, &'static str> {
+    Err("resource data is encrypted but support for decrypting it isn't compiled in (resources-encryption feature)")
+}
+
+#[cfg(feature = "resources-zstd")]
+fn zstd_decompress_resource(resource: Resource<'_, u8>) -> Result<Resource<'_, u8>, &'static str> {
+    resource
+        .zstd_decompress()
+        .map_err(|_| "error decompressing zstandard resource data")
+}
+
+#[cfg(not(feature = "resources-zstd"))]
+fn zstd_decompress_resource(_resource: Resource<'_, u8>) -> Result<Resource<'_, u8>, &'static str> {
+    Err("resource data is zstandard compressed but support for decompressing it isn't compiled in (resources-zstd feature)")
+}
+
+#[cfg(feature = "resources-signatures")]
+fn verify_signature<'a>(data: &'a [u8], public_key: &[u8; 32]) -> Result<&'a [u8], &'static str> {
+    python_packed_resources::verify_packed_resources_signature(data, public_key)
+}
+
+#[cfg(not(feature = "resources-signatures"))]
+fn verify_signature<'a>(_data: &'a [u8], _public_key: &[u8; 32]) -> Result<&'a [u8], &'static str> {
+    Err("packed resources signature verification isn't compiled in (resources-signatures feature)")
+}
+
+#[cfg(feature = "resources-sha256")]
+fn verify_sha256(resource: &Resource<'_, u8>) -> Result<(), &'static str> {
+    resource.verify_in_memory_sha256().map_err(|_| {
+        "packed resources data is corrupt: resource data does not match its SHA-256 digest"
+    })
+}
+
+#[cfg(not(feature = "resources-sha256"))]
+fn verify_sha256(_resource: &Resource<'_, u8>) -> Result<(), &'static str> {
+    Err("resource digest verification isn't compiled in (resources-sha256 feature)")
+}
+
 /// Determines whether an entry represents an importable Python module.
 ///
 /// Should only be called on module flavors.
@@ -94,6 +181,32 @@ pub(crate) fn name_within_package_hierarchy(fullname: &str, package_target: Opti
     }
 }
 
+/// Normalize a package resource name to the `/` delimited form it is indexed by.
+///
+/// Callers may construct names with `os.path.join()`, which uses `\\` on
+/// Windows.
+fn normalize_resource_name(name: &str) -> Cow<'_, str> {
+    if name.contains('\\') {
+        Cow::Owned(name.replace('\\', "/"))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Whether a filesystem directory contains an `__init__` module.
+fn is_regular_package_directory(path: &Path) -> bool {
+    match std::fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).any(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .map(|name| name.starts_with("__init__."))
+                .unwrap_or(false)
+        }),
+        Err(_) => false,
+    }
+}
+
 /// Describes the type of an importable Python module.
 #[derive(Debug, PartialEq)]
 pub enum ModuleFlavor {
@@ -101,6 +214,7 @@ pub enum ModuleFlavor {
     Frozen,
     Extension,
     SourceBytecode,
+    Namespace,
 }
 
 /// Holds state for an importable Python module.
@@ -138,8 +252,17 @@ impl<'a> ImportablePythonModule<'a, u8> {
         decode_source: &'p PyAny,
         io_module: &PyAny,
     ) -> PyResult<Option<&'p PyAny>> {
-        let bytes = if let Some(data) = &self.resource.in_memory_source {
-            Some(PyBytes::new(py, data))
+        if let Some(bytes) = self.resolve_source_bytes(py)? {
+            Ok(Some(decode_source.call((io_module, bytes), None)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Attempt to resolve the raw, undecoded source code behind this module.
+    fn resolve_source_bytes<'p>(&self, py: Python<'p>) -> PyResult<Option<&'p PyBytes>> {
+        if let Some(data) = &self.resource.in_memory_source {
+            Ok(Some(PyBytes::new(py, data)))
         } else if let Some(relative_path) = &self.resource.relative_path_module_source {
             let path = self.origin.join(relative_path);
 
@@ -153,16 +276,54 @@ impl<'a> ImportablePythonModule<'a, u8> {
                 )
             })?;
 
-            Some(PyBytes::new(py, &source))
+            Ok(Some(PyBytes::new(py, &source)))
         } else {
-            None
+            Ok(None)
+        }
+    }
+
+    /// Whether the source hash in a hash-based `.pyc` header is stale.
+    ///
+    /// This mirrors the validation CPython's `SourceLoader` performs for
+    /// hash-based `.pyc` files (PEP 552). Checked `.pyc` files are validated
+    /// unless `_imp.check_hash_based_pycs` is `never`. Unchecked `.pyc` files
+    /// are only validated if it is `always`. Timestamp-based `.pyc` files and
+    /// modules without source are never considered stale.
+    fn bytecode_source_hash_mismatch(&self, py: Python, header: &[u8]) -> PyResult<bool> {
+        let flags = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+
+        if flags & 0b1 == 0 {
+            return Ok(false);
+        }
+
+        let imp = py.import("_imp")?;
+        let check_mode = imp.getattr("check_hash_based_pycs")?.extract::<String>()?;
+
+        let check_source = match check_mode.as_str() {
+            "always" => true,
+            "never" => false,
+            _ => flags & 0b10 != 0,
         };
 
-        if let Some(bytes) = bytes {
-            Ok(Some(decode_source.call((io_module, bytes), None)?))
-        } else {
-            Ok(None)
+        if !check_source {
+            return Ok(false);
         }
+
+        let source = if let Some(source) = self.resolve_source_bytes(py)? {
+            source
+        } else {
+            return Ok(false);
+        };
+
+        let magic = py
+            .import("_frozen_importlib_external")?
+            .getattr("_RAW_MAGIC_NUMBER")?;
+        let source_hash = imp
+            .getattr("source_hash")?
+            .call1((magic, source))?
+            .extract::<Vec<u8>>()?;
+
+        Ok(source_hash != header[8..16])
     }
 
     /// Attempt to resolve bytecode for this module.
@@ -193,11 +354,11 @@ impl<'a> ImportablePythonModule<'a, u8> {
                 )
             };
 
-            if ptr.is_null() {
+            return if ptr.is_null() {
                 Ok(None)
             } else {
                 Ok(Some(unsafe { PyObject::from_owned_ptr(py, ptr) }))
-            }
+            };
         } else if let Some(path) = self.bytecode_path(optimize_level) {
             // TODO we could potentially avoid the double allocation for bytecode
             // by reading directly into a buffer transferred to Python.
@@ -218,9 +379,14 @@ impl<'a> ImportablePythonModule<'a, u8> {
                 ));
             }
 
-            // First 16 bytes of .pyc files are a header.
-            Ok(Some(PyBytes::new(py, &bytecode[16..]).into_py(py)))
-        } else if let Some(source) = self.resolve_source(py, decode_source, io_module)? {
+            // First 16 bytes of .pyc files are a header. If the header says the
+            // bytecode is stale, fall back to compiling source below.
+            if !self.bytecode_source_hash_mismatch(py, &bytecode[0..16])? {
+                return Ok(Some(PyBytes::new(py, &bytecode[16..]).into_py(py)));
+            }
+        }
+
+        if let Some(source) = self.resolve_source(py, decode_source, io_module)? {
             let builtins = py.import("builtins")?;
             let marshal = py.import("marshal")?;
 
@@ -236,12 +402,17 @@ impl<'a> ImportablePythonModule<'a, u8> {
     }
 
     /// Resolve the `importlib.machinery.ModuleSpec` for this module.
+    ///
+    /// `synthesize_dunder_file` controls whether modules imported from memory
+    /// get a `ModuleSpec.origin` (and therefore `__file__`). See
+    /// [Self::resolve_origin()].
     pub fn resolve_module_spec<'p>(
         &self,
         py: Python,
         module_spec_type: &'p PyAny,
         loader: &PyAny,
         optimize_level: BytecodeOptimizationLevel,
+        synthesize_dunder_file: bool,
     ) -> PyResult<&'p PyAny> {
         let name = PyString::new(py, &self.resource.name);
 
@@ -252,7 +423,7 @@ impl<'a> ImportablePythonModule<'a, u8> {
         // will be set on the module. This is appropriate for modules backed by
         // the filesystem.
 
-        let origin = self.resolve_origin(py)?;
+        let origin = self.resolve_origin(py, synthesize_dunder_file)?;
         if let Some(origin) = &origin {
             kwargs.set_item("origin", origin)?;
         }
@@ -319,15 +490,102 @@ impl<'a> ImportablePythonModule<'a, u8> {
         Ok(spec)
     }
 
+    /// Resolve the `importlib.machinery.ModuleSpec` for this namespace package.
+    ///
+    /// Per PEP 420, the spec has no loader. `__path__` is the package's path
+    /// under the current executable followed by directories for the package
+    /// in each `search_path` entry that aren't regular packages. This allows
+    /// portions of the namespace package outside our resources, such as on the
+    /// filesystem, to be imported.
+    pub fn resolve_namespace_spec<'p>(
+        &self,
+        py: Python,
+        module_spec_type: &'p PyAny,
+        search_path: &PyAny,
+    ) -> PyResult<&'p PyAny> {
+        let name = PyString::new(py, &self.resource.name);
+
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("is_package", true)?;
+
+        let spec = module_spec_type.call((name, py.None()), Some(kwargs))?;
+
+        let mut path = self.current_exe.to_path_buf();
+        path.extend(self.resource.name.split('.'));
+
+        let mut locations = vec![path];
+
+        let leaf = self
+            .resource
+            .name
+            .rsplit('.')
+            .next()
+            .unwrap_or(&self.resource.name);
+
+        for entry in search_path.iter()? {
+            let entry = match entry?.extract::<PathBuf>() {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+
+            let candidate = entry.join(leaf);
+
+            if candidate.is_dir()
+                && !is_regular_package_directory(&candidate)
+                && !locations.contains(&candidate)
+            {
+                locations.push(candidate);
+            }
+        }
+
+        let locations = locations
+            .into_iter()
+            .map(|path| path.into_py(py))
+            .collect::<Vec<_>>();
+
+        spec.setattr("submodule_search_locations", locations)?;
+
+        Ok(spec)
+    }
+
     /// Resolve the value of a `ModuleSpec` origin.
     ///
     /// The value gets turned into `__file__`
-    pub fn resolve_origin<'p>(&self, py: Python<'p>) -> PyResult<Option<&'p PyAny>> {
-        Ok(if let Some(path) = self.origin_path() {
-            Some(path.into_py(py).into_ref(py))
+    ///
+    /// Modules imported from memory don't have an origin unless
+    /// `synthesize_dunder_file` is set. In that case, a path under the current
+    /// executable is used, like for `__path__`. e.g. `/usr/bin/myapp/foo/bar.py`
+    /// for `foo.bar`. This path does not exist on the filesystem.
+    pub fn resolve_origin<'p>(
+        &self,
+        py: Python<'p>,
+        synthesize_dunder_file: bool,
+    ) -> PyResult<Option<&'p PyAny>> {
+        let path = match self.origin_path() {
+            Some(path) => Some(path),
+            None if synthesize_dunder_file => self.synthesized_origin_path(),
+            None => None,
+        };
+
+        Ok(path.map(|path| path.into_py(py).into_ref(py)))
+    }
+
+    /// Obtain a virtual path to this module under the current executable.
+    fn synthesized_origin_path(&self) -> Option<PathBuf> {
+        if self.flavor != ModuleFlavor::SourceBytecode {
+            return None;
+        }
+
+        let mut path = self.current_exe.to_path_buf();
+        path.extend(self.resource.name.split('.'));
+
+        if self.is_package {
+            path.push("__init__.py");
         } else {
-            None
-        })
+            path.set_extension("py");
+        }
+
+        Some(path)
     }
 
     /// Resolve the value of a `ModuleSpec` `cached` attribute.
@@ -394,6 +652,14 @@ pub enum PackedResourcesSource<'a> {
     /// Load resources data from a filesystem path using memory mapped I/O.
     #[allow(unused)]
     MemoryMappedPath(PathBuf),
+
+    /// Load resources data from a filesystem path by reading it into memory.
+    Path(PathBuf),
+
+    /// Load resources data injected into the current executable.
+    ///
+    /// Nothing is loaded if no resources data is injected.
+    CurrentExecutable,
 }
 
 impl<'a> From<&'a [u8]> for PackedResourcesSource<'a> {
@@ -402,6 +668,113 @@ impl<'a> From<&'a [u8]> for PackedResourcesSource<'a> {
     }
 }
 
+/// Storage of named resources.
+///
+/// Resources of packed resources data having a name index are kept in the
+/// order of that data and found through its index. So indexing such data
+/// doesn't hash every resource name. Other resources are kept in a hash map.
+/// A name is in at most one of these.
+#[derive(Debug)]
+struct ResourcesStore<'a, X>
+where
+    [X]: ToOwned<Owned = Vec<X>>,
+{
+    /// Resources of packed resources data and the name index of that data.
+    indexed: Vec<(ResourceNameIndex<'a>, Vec<Resource<'a, X>>)>,
+
+    /// Resources not found through a name index.
+    other: HashMap<Cow<'a, str>, Resource<'a, X>>,
+}
+
+impl<'a, X> ResourcesStore<'a, X>
+where
+    [X]: ToOwned<Owned = Vec<X>>,
+{
+    fn new() -> Self {
+        Self {
+            indexed: vec![],
+            other: HashMap::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.indexed.is_empty() && self.other.is_empty()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.other.reserve(additional);
+    }
+
+    /// Add resources of packed resources data having a name index.
+    ///
+    /// Names of `resources` must not be known to this instance.
+    fn add_indexed(&mut self, index: ResourceNameIndex<'a>, resources: Vec<Resource<'a, X>>) {
+        self.indexed.push((index, resources));
+    }
+
+    /// Resolve the location of a resource found through a name index.
+    fn indexed_position(&self, name: &str) -> Option<(usize, usize)> {
+        self.indexed
+            .iter()
+            .enumerate()
+            .find_map(|(table, (index, resources))| {
+                let position = index.lookup(name)?;
+
+                match resources.get(position) {
+                    Some(resource) if resource.name == name => Some((table, position)),
+                    _ => None,
+                }
+            })
+    }
+
+    fn get(&self, name: &str) -> Option<&Resource<'a, X>> {
+        match self.indexed_position(name) {
+            Some((table, position)) => Some(&self.indexed[table].1[position]),
+            None => self.other.get(name),
+        }
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Resource<'a, X>> {
+        match self.indexed_position(name) {
+            Some((table, position)) => Some(&mut self.indexed[table].1[position]),
+            None => self.other.get_mut(name),
+        }
+    }
+
+    fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Obtain a named resource, inserting the resource returned by `f` if it doesn't exist.
+    fn get_or_insert_with(
+        &mut self,
+        name: Cow<'a, str>,
+        f: impl FnOnce() -> Resource<'a, X>,
+    ) -> &mut Resource<'a, X> {
+        match self.indexed_position(&name) {
+            Some((table, position)) => &mut self.indexed[table].1[position],
+            None => self.other.entry(name).or_insert_with(f),
+        }
+    }
+
+    /// Insert a resource, replacing an existing resource having the same name.
+    fn insert(&mut self, resource: Resource<'a, X>) {
+        match self.get_mut(&resource.name) {
+            Some(existing) => *existing = resource,
+            None => {
+                self.other.insert(resource.name.clone(), resource);
+            }
+        }
+    }
+
+    fn values(&self) -> impl Iterator<Item = &Resource<'a, X>> {
+        self.indexed
+            .iter()
+            .flat_map(|(_, resources)| resources.iter())
+            .chain(self.other.values())
+    }
+}
+
 /// Defines Python resources available for import.
 #[derive(Debug)]
 pub struct PythonResourcesState<'a, X>
@@ -417,7 +790,25 @@ where
     origin: PathBuf,
 
     /// Named resources available for loading.
-    resources: HashMap<Cow<'a, str>, Resource<'a, X>>,
+    resources: ResourcesStore<'a, X>,
+
+    /// Decoded forms of resources having encrypted or zstandard compressed data.
+    ///
+    /// Every encrypted or compressed resource in `resources` has an entry,
+    /// which is populated the first time the resource's data is accessed.
+    decoded_resources: HashMap<Cow<'a, str>, OnceCell<Resource<'a, X>>>,
+
+    /// Whether to verify digests of in-memory data when resources are first accessed.
+    verify_resources: bool,
+
+    /// Names of resources whose in-memory data was verified against its digest.
+    verified_resources: Mutex<HashSet<Cow<'a, str>>>,
+
+    /// ed25519 public key that indexed packed resources data must be signed with.
+    packed_resources_public_key: Option<[u8; 32]>,
+
+    /// Key to decrypt encrypted in-memory data with.
+    decryption_key: Option<[u8; 32]>,
 
     /// List of `PyObject` that back indexed data.
     ///
@@ -427,6 +818,12 @@ where
 
     /// Holds memory mapped file instances that resources data came from.
     backing_mmaps: Vec<memmap2::Mmap>,
+
+    /// Holds file content read into memory that resources data came from.
+    backing_buffers: Vec<Vec<u8>>,
+
+    /// Whether `OxidizedResourceFile` is registered with `io.BufferedIOBase`.
+    resource_file_type_registered: OnceCell<()>,
 }
 
 impl<'a> Default for PythonResourcesState<'a, u8> {
@@ -434,9 +831,16 @@ impl<'a> Default for PythonResourcesState<'a, u8> {
         Self {
             current_exe: PathBuf::new(),
             origin: PathBuf::new(),
-            resources: HashMap::new(),
+            resources: ResourcesStore::new(),
+            decoded_resources: HashMap::new(),
+            verify_resources: false,
+            verified_resources: Mutex::new(HashSet::new()),
+            packed_resources_public_key: None,
+            decryption_key: None,
             backing_py_objects: vec![],
             backing_mmaps: vec![],
+            backing_buffers: vec![],
+            resource_file_type_registered: OnceCell::new(),
         }
     }
 }
@@ -477,28 +881,115 @@ impl<'a> PythonResourcesState<'a, u8> {
         self.origin = path;
     }
 
+    /// Whether digests of in-memory data are verified on first access.
+    pub fn verify_resources(&self) -> bool {
+        self.verify_resources
+    }
+
+    /// Set whether digests of in-memory data are verified on first access.
+    ///
+    /// When enabled, the in-memory data of resources having a SHA-256 digest
+    /// is verified against it the first time the resource is accessed.
+    /// Accessing data that doesn't match its digest is an error.
+    pub fn set_verify_resources(&mut self, value: bool) {
+        self.verify_resources = value;
+    }
+
+    /// The ed25519 public key that indexed packed resources data must be signed with.
+    pub fn packed_resources_public_key(&self) -> Option<&[u8; 32]> {
+        self.packed_resources_public_key.as_ref()
+    }
+
+    /// Set the ed25519 public key that indexed packed resources data must be signed with.
+    ///
+    /// When set, the signature of packed resources data is verified before
+    /// it is indexed and data without a valid signature is rejected.
+    pub fn set_packed_resources_public_key(&mut self, key: Option<[u8; 32]>) {
+        self.packed_resources_public_key = key;
+    }
+
+    /// Set the key to decrypt encrypted in-memory data of resources with.
+    ///
+    /// Encrypted data is decrypted the first time a resource is accessed.
+    /// Accessing encrypted data without a key, or with the wrong key, is an error.
+    pub fn set_decryption_key(&mut self, key: Option<[u8; 32]>) {
+        self.decryption_key = key;
+    }
+
     /// Load resources by parsing a blob.
     ///
     /// If an existing entry exists, the new entry will be merged into it. Set fields
     /// on the incoming entry will overwrite fields on the existing entry.
     ///
     /// If an entry doesn't exist, the resource will be inserted as-is.
+    ///
+    /// If a packed resources public key is set, the signature of the data is
+    /// verified before anything is indexed.
     pub fn index_data(&mut self, data: &'a [u8]) -> Result<(), &'static str> {
+        let data = if let Some(public_key) = &self.packed_resources_public_key {
+            verify_signature(data, public_key)?
+        } else {
+            data
+        };
+
         let resources = python_packed_resources::load_resources(data)?;
 
+        // Resources of data having a name index are found through that index.
+        // Names are only known to be unique within the data. So this is only
+        // possible if nothing was indexed yet.
+        if let (true, Some(index)) = (self.resources.is_empty(), resources.name_index()) {
+            let resources = resources.collect::<Result<Vec<_>, &'static str>>()?;
+
+            for resource in resources.iter().filter(|resource| is_encoded(resource)) {
+                self.decoded_resources
+                    .insert(resource.name.clone(), OnceCell::new());
+            }
+
+            self.resources.add_indexed(index, resources);
+
+            return Ok(());
+        }
+
         // Reserve space for expected number of incoming items so we can avoid extra
         // allocations.
         self.resources.reserve(resources.expected_resources_count());
 
         for resource in resources {
-            let resource = resource?;
+            let mut resource = resource?;
+
+            match self.resources.get_mut(&resource.name) {
+                Some(existing) => {
+                    // Compression and encryption apply to all in-memory fields. So
+                    // resources with mismatched encodings are decoded to merge them.
+                    if existing.in_memory_zstd_compressed != resource.in_memory_zstd_compressed
+                        || existing.in_memory_encrypted != resource.in_memory_encrypted
+                    {
+                        let key = self.decryption_key.as_ref();
+                        *existing = decode_resource(std::mem::take(existing), key)?;
+                        resource = decode_resource(resource, key)?;
+                    }
+
+                    existing.merge_from(resource)?;
+
+                    self.verified_resources
+                        .get_mut()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .remove(&existing.name);
 
-            match self.resources.entry(resource.name.clone()) {
-                Entry::Occupied(existing) => {
-                    existing.into_mut().merge_from(resource)?;
+                    if is_encoded(existing) {
+                        self.decoded_resources
+                            .insert(existing.name.clone(), OnceCell::new());
+                    } else {
+                        self.decoded_resources.remove(&existing.name);
+                    }
                 }
-                Entry::Vacant(vacant) => {
-                    vacant.insert(resource);
+                None => {
+                    if is_encoded(&resource) {
+                        self.decoded_resources
+                            .insert(resource.name.clone(), OnceCell::new());
+                    }
+
+                    self.resources.insert(resource);
                 }
             }
         }
@@ -521,6 +1012,40 @@ impl<'a> PythonResourcesState<'a, u8> {
         Ok(())
     }
 
+    /// Load resources data injected into a binary using memory mapped I/O.
+    ///
+    /// Nothing is loaded if the binary doesn't have injected resources data.
+    pub fn index_path_injected(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let f = std::fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mapped = unsafe { memmap2::Mmap::map(&f) }.map_err(|e| e.to_string())?;
+
+        let data = unsafe { std::slice::from_raw_parts::<u8>(mapped.as_ptr(), mapped.len()) };
+
+        if let Some(data) = python_packed_resources::find_injected_packed_resources(data)? {
+            self.index_data(data)?;
+            self.backing_mmaps.push(mapped);
+        }
+
+        Ok(())
+    }
+
+    /// Load resources data from a filesystem path by reading it into memory.
+    pub fn index_path(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let buffer = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        // Moving the buffer into `backing_buffers` doesn't move its heap
+        // allocation. So the data remains valid for the lifetime of this instance.
+        let data = unsafe { std::slice::from_raw_parts::<u8>(buffer.as_ptr(), buffer.len()) };
+
+        self.index_data(data)?;
+        self.backing_buffers.push(buffer);
+
+        Ok(())
+    }
+
     /// Load resources from packed data stored in a PyObject.
     ///
     /// The `PyObject` must conform to the buffer protocol.
@@ -555,15 +1080,11 @@ impl<'a> PythonResourcesState<'a, u8> {
             };
 
             self.resources
-                .entry(name_str.into())
-                .and_modify(|r| {
-                    r.is_python_builtin_extension_module = true;
-                })
-                .or_insert_with(|| Resource {
-                    is_python_builtin_extension_module: true,
+                .get_or_insert_with(name_str.into(), || Resource {
                     name: Cow::Owned(name_str.to_string()),
                     ..Resource::default()
-                });
+                })
+                .is_python_builtin_extension_module = true;
         }
 
         Ok(())
@@ -587,15 +1108,11 @@ impl<'a> PythonResourcesState<'a, u8> {
             };
 
             self.resources
-                .entry(name_str.into())
-                .and_modify(|r| {
-                    r.is_python_frozen_module = true;
-                })
-                .or_insert_with(|| Resource {
-                    is_python_frozen_module: true,
+                .get_or_insert_with(name_str.into(), || Resource {
                     name: Cow::Owned(name_str.to_string()),
                     ..Resource::default()
-                });
+                })
+                .is_python_frozen_module = true;
         }
 
         Ok(())
@@ -626,17 +1143,172 @@ impl<'a> PythonResourcesState<'a, u8> {
         &mut self,
         resource: Resource<'resource, u8>,
     ) -> Result<(), &'static str> {
-        self.resources.insert(resource.name.clone(), resource);
+        if is_encoded(&resource) {
+            self.decoded_resources
+                .insert(resource.name.clone(), OnceCell::new());
+        } else {
+            self.decoded_resources.remove(&resource.name);
+        }
+
+        self.verified_resources
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&resource.name);
+
+        self.resources.insert(resource);
+
+        Ok(())
+    }
+
+    /// Obtain a named resource with in-memory data in decoded form.
+    ///
+    /// Encrypted data is decrypted and zstandard compressed data is
+    /// decompressed the first time a resource is accessed. The decoded
+    /// resource is retained for subsequent calls.
+    ///
+    /// If resource verification is enabled, in-memory data is verified against
+    /// its digest the first time a resource is accessed.
+    pub fn get_resource(&self, name: &str) -> Result<Option<&Resource<'a, u8>>, &'static str> {
+        let resource = match self.resources.get(name) {
+            Some(resource) => resource,
+            None => return Ok(None),
+        };
+
+        if self.verify_resources && resource.in_memory_sha256.is_some() {
+            self.verify_resource(resource)?;
+        }
+
+        if !is_encoded(resource) {
+            return Ok(Some(resource));
+        }
+
+        self.decoded_resources
+            .get(name)
+            .ok_or("decoded resource state not found")?
+            .get_or_try_init(|| decode_resource(resource.clone(), self.decryption_key.as_ref()))
+            .map(Some)
+    }
+
+    /// Verify in-memory data of a resource against its digest.
+    ///
+    /// Resources are only verified once.
+    fn verify_resource(&self, resource: &Resource<'a, u8>) -> Result<(), &'static str> {
+        if self
+            .verified_resources
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(resource.name.as_ref())
+        {
+            return Ok(());
+        }
+
+        verify_sha256(resource)?;
+
+        self.verified_resources
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(resource.name.clone());
+
+        Ok(())
+    }
+
+    /// Index source code for a Python module.
+    ///
+    /// Code already indexed for the module, including bytecode, is replaced. So
+    /// the module is compiled from this source when imported. Other data indexed
+    /// for the module, such as package resources, is kept.
+    pub fn add_module_source(
+        &mut self,
+        name: &str,
+        source: Vec<u8>,
+        is_package: bool,
+    ) -> Result<(), &'static str> {
+        let resource = self.module_resource_mut(name, is_package)?;
+
+        resource.in_memory_source = Some(Cow::Owned(source));
+        resource.relative_path_module_source = None;
+        resource.in_memory_bytecode = None;
+        resource.in_memory_bytecode_opt1 = None;
+        resource.in_memory_bytecode_opt2 = None;
+        resource.relative_path_module_bytecode = None;
+        resource.relative_path_module_bytecode_opt1 = None;
+        resource.relative_path_module_bytecode_opt2 = None;
+
+        Ok(())
+    }
+
+    /// Index bytecode for a Python module.
+    ///
+    /// `bytecode` is a marshalled code object, without a `.pyc` header. Bytecode
+    /// already indexed for the module at any optimization level is replaced.
+    /// Indexed source is kept, so it can still be used by `get_source()`.
+    pub fn add_module_bytecode(
+        &mut self,
+        name: &str,
+        bytecode: Vec<u8>,
+        optimize_level: BytecodeOptimizationLevel,
+        is_package: bool,
+    ) -> Result<(), &'static str> {
+        let resource = self.module_resource_mut(name, is_package)?;
+
+        resource.in_memory_bytecode = None;
+        resource.in_memory_bytecode_opt1 = None;
+        resource.in_memory_bytecode_opt2 = None;
+        resource.relative_path_module_bytecode = None;
+        resource.relative_path_module_bytecode_opt1 = None;
+        resource.relative_path_module_bytecode_opt2 = None;
+
+        let bytecode = Some(Cow::Owned(bytecode));
+
+        match optimize_level {
+            BytecodeOptimizationLevel::Zero => resource.in_memory_bytecode = bytecode,
+            BytecodeOptimizationLevel::One => resource.in_memory_bytecode_opt1 = bytecode,
+            BytecodeOptimizationLevel::Two => resource.in_memory_bytecode_opt2 = bytecode,
+        }
 
         Ok(())
     }
 
+    /// Obtain the resource for a Python module, creating it if necessary.
+    ///
+    /// Encoded resources are decoded so plain data can be added.
+    fn module_resource_mut(
+        &mut self,
+        name: &str,
+        is_package: bool,
+    ) -> Result<&mut Resource<'a, u8>, &'static str> {
+        let resource = self
+            .resources
+            .get_or_insert_with(Cow::Owned(name.to_string()), || Resource {
+                name: Cow::Owned(name.to_string()),
+                ..Default::default()
+            });
+
+        resource.is_python_module = true;
+        resource.is_python_package = is_package;
+        resource.is_python_namespace_package = false;
+
+        // The digest won't match once data is added.
+        resource.in_memory_sha256 = None;
+        self.verified_resources
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name);
+
+        if is_encoded(resource) {
+            *resource = decode_resource(std::mem::take(resource), self.decryption_key.as_ref())?;
+            self.decoded_resources.remove(name);
+        }
+
+        Ok(resource)
+    }
+
     /// Attempt to resolve an importable Python module.
     pub fn resolve_importable_module(
         &self,
         name: &str,
         optimize_level: BytecodeOptimizationLevel,
-    ) -> Option<ImportablePythonModule<u8>> {
+    ) -> Result<Option<ImportablePythonModule<u8>>, &'static str> {
         // Python's filesystem based importer accepts `foo.__init__` as a valid
         // module name. When these names are encountered, it fails to recognize
         // that `__init__` is special and happily searches for and uses/imports a
@@ -662,9 +1334,9 @@ impl<'a> PythonResourcesState<'a, u8> {
         // for recognizing `__init__` because Python code in the wild relies on it.
         let name = name.strip_suffix(".__init__").unwrap_or(name);
 
-        let resource = match self.resources.get(name) {
+        let resource = match self.get_resource(name)? {
             Some(entry) => entry,
-            None => return None,
+            None => return Ok(None),
         };
 
         // Since resources can exist as multiple types and it is possible
@@ -691,9 +1363,14 @@ impl<'a> PythonResourcesState<'a, u8> {
         // 1. built-in extension modules
         // 2. frozen modules
         // 3. extension modules
-        // 4. module (covers both source and bytecode)
+        // 4. namespace packages
+        // 5. module (covers both source and bytecode)
+        //
+        // Namespace packages may have module data so importers unaware of
+        // them can import them as regular packages. So they take precedence
+        // over modules.
 
-        if resource.is_python_builtin_extension_module {
+        Ok(if resource.is_python_builtin_extension_module {
             Some(ImportablePythonModule {
                 resource,
                 current_exe: &self.current_exe,
@@ -717,6 +1394,14 @@ impl<'a> PythonResourcesState<'a, u8> {
                 flavor: ModuleFlavor::Extension,
                 is_package: resource.is_python_package,
             })
+        } else if resource.is_python_namespace_package {
+            Some(ImportablePythonModule {
+                resource,
+                current_exe: &self.current_exe,
+                origin: &self.origin,
+                flavor: ModuleFlavor::Namespace,
+                is_package: true,
+            })
         } else if resource.is_python_module {
             if is_module_importable(resource, optimize_level) {
                 Some(ImportablePythonModule {
@@ -731,7 +1416,26 @@ impl<'a> PythonResourcesState<'a, u8> {
             }
         } else {
             None
-        }
+        })
+    }
+
+    /// Register `OxidizedResourceFile` as a virtual subclass of `io.BufferedIOBase`.
+    ///
+    /// So its instances pass isinstance() checks for binary file objects. The
+    /// io module isn't importable yet when the extension module is initialized
+    /// during interpreter initialization. So this is done once, when the first
+    /// file is opened.
+    fn register_resource_file_type(&self, py: Python) -> PyResult<()> {
+        self.resource_file_type_registered
+            .get_or_try_init(|| -> PyResult<()> {
+                py.import("io")?
+                    .getattr("BufferedIOBase")?
+                    .call_method1("register", (py.get_type::<OxidizedResourceFile>(),))?;
+
+                Ok(())
+            })?;
+
+        Ok(())
     }
 
     /// Obtain a single named resource in a package.
@@ -739,24 +1443,38 @@ impl<'a> PythonResourcesState<'a, u8> {
     /// Err occurs if loading the resource data fails. `Ok(None)` is returned
     /// if the resource does not exist. Otherwise the returned `PyObject`
     /// is a file-like object to read the resource data.
+    ///
+    /// In-memory resource data is not copied. `owner` must keep the memory
+    /// backing this instance alive and is held by the returned object.
     pub fn get_package_resource_file<'p>(
         &self,
         py: Python<'p>,
+        owner: &PyAny,
         package: &str,
         resource_name: &str,
     ) -> PyResult<Option<&'p PyAny>> {
-        let entry = match self.resources.get(package) {
+        let entry = match self.get_resource(package).map_err(PyValueError::new_err)? {
             Some(entry) => entry,
             None => return Ok(None),
         };
 
+        let resource_name = normalize_resource_name(resource_name);
+        let resource_name = resource_name.as_ref();
+
+        if self.is_package_resource(package, resource_name) {
+            audit(
+                py,
+                "oxidized_importer.resource_read",
+                (package, resource_name),
+            )?;
+        }
+
         if let Some(resources) = &entry.in_memory_package_resources {
             if let Some(data) = resources.get(resource_name) {
-                let io_module = py.import("io")?;
-                let bytes_io = io_module.getattr("BytesIO")?;
+                self.register_resource_file_type(py)?;
+                let file = PyCell::new(py, OxidizedResourceFile::new(data, owner))?;
 
-                let data = PyBytes::new(py, data);
-                return Ok(Some(bytes_io.call((data,), None)?));
+                return Ok(Some(file.as_ref()));
             }
         }
 
@@ -776,8 +1494,53 @@ impl<'a> PythonResourcesState<'a, u8> {
         Ok(None)
     }
 
+    /// Obtain a file-like object for reading an embedded data file.
+    ///
+    /// `name` is a namespace and a relative path separated by `/`, as
+    /// registered at build time. `Ok(None)` is returned if no data file
+    /// with this name exists.
+    ///
+    /// In-memory data is not copied. `owner` must keep the memory backing
+    /// this instance alive and is held by the returned object.
+    pub fn get_data_file<'p>(
+        &self,
+        py: Python<'p>,
+        owner: &PyAny,
+        name: &str,
+    ) -> PyResult<Option<&'p PyAny>> {
+        let entry = match self.get_resource(name).map_err(PyValueError::new_err)? {
+            Some(entry) if entry.is_utf8_filename_data => entry,
+            _ => return Ok(None),
+        };
+
+        audit(py, "oxidized_importer.open_data", (name,))?;
+
+        if let Some(data) = &entry.file_data_embedded {
+            self.register_resource_file_type(py)?;
+            let file = PyCell::new(py, OxidizedResourceFile::new(data, owner))?;
+
+            return Ok(Some(file.as_ref()));
+        }
+
+        if let Some(path) = &entry.file_data_utf8_relative_path {
+            let path = self.origin.join(&**path);
+            let io_module = py.import("io")?;
+
+            return Ok(Some(
+                io_module
+                    .getattr("FileIO")?
+                    .call((path.into_py(py), "r"), None)?,
+            ));
+        }
+
+        Ok(None)
+    }
+
     /// Determines whether a specific package + name pair is a known Python package resource.
     pub fn is_package_resource(&self, package: &str, resource_name: &str) -> bool {
+        let resource_name = normalize_resource_name(resource_name);
+        let resource_name = resource_name.as_ref();
+
         if let Some(entry) = self.resources.get(package) {
             if let Some(resources) = &entry.in_memory_package_resources {
                 if resources.contains_key(resource_name) {
@@ -804,15 +1567,20 @@ impl<'a> PythonResourcesState<'a, u8> {
             None => return Ok(PyList::empty(py).into()),
         };
 
-        let mut names = if let Some(resources) = &entry.in_memory_package_resources {
-            resources.keys().collect()
-        } else if let Some(resources) = &entry.relative_path_package_resources {
-            resources.keys().collect()
-        } else {
-            vec![]
-        };
+        let mut names = entry
+            .in_memory_package_resources
+            .iter()
+            .flat_map(|resources| resources.keys())
+            .chain(
+                entry
+                    .relative_path_package_resources
+                    .iter()
+                    .flat_map(|resources| resources.keys()),
+            )
+            .collect::<Vec<_>>();
 
         names.sort();
+        names.dedup();
 
         let names = names
             .iter()
@@ -852,55 +1620,89 @@ impl<'a> PythonResourcesState<'a, u8> {
         }
     }
 
-    /// Resolve package resources in a directory.
-    pub fn package_resources_list_directory(&self, package: &str, name: &str) -> Vec<String> {
+    /// Resolve the immediate children of a package resource directory.
+    ///
+    /// Keys are file or directory names. Values are whether the entry is a
+    /// directory. An empty `name` resolves the root directory of the package.
+    /// Sub-packages are not included.
+    pub fn package_resource_directory_entries(
+        &self,
+        package: &str,
+        name: &str,
+    ) -> BTreeMap<String, bool> {
         let name = name.replace('\\', "/");
-
-        let prefix = if name.ends_with('/') {
-            Some(name)
-        } else if name.is_empty() {
-            None
+        let prefix = if name.is_empty() || name.ends_with('/') {
+            name
         } else {
-            Some(format!("{}/", name))
+            format!("{}/", name)
         };
 
-        let filter_map_resource = |path: &'_ Cow<'_, str>| -> Option<String> {
-            match &prefix {
-                Some(prefix) => {
-                    if let Some(name) = path.strip_prefix(prefix) {
-                        if name.contains('/') {
-                            None
-                        } else {
-                            Some(name.to_string())
+        let mut entries = BTreeMap::new();
+
+        if let Some(entry) = self.resources.get(package) {
+            let resources = entry
+                .in_memory_package_resources
+                .iter()
+                .flat_map(|resources| resources.keys())
+                .chain(
+                    entry
+                        .relative_path_package_resources
+                        .iter()
+                        .flat_map(|resources| resources.keys()),
+                );
+
+            for path in resources {
+                if let Some(remaining) = path.strip_prefix(&prefix) {
+                    match remaining.split_once('/') {
+                        Some((dir, _)) => {
+                            entries.insert(dir.to_string(), true);
+                        }
+                        None => {
+                            entries.entry(remaining.to_string()).or_insert(false);
                         }
-                    } else {
-                        None
-                    }
-                }
-                None => {
-                    // Empty string input matches root directory.
-                    if path.contains('/') {
-                        None
-                    } else {
-                        Some(path.to_string())
                     }
                 }
             }
-        };
+        }
 
-        let mut entries = BTreeSet::new();
+        entries
+    }
 
-        if let Some(entry) = self.resources.get(package) {
-            if let Some(resources) = &entry.in_memory_package_resources {
-                entries.extend(resources.keys().filter_map(filter_map_resource));
-            }
+    /// Whether a resource is a Python package.
+    pub fn is_package(&self, name: &str) -> bool {
+        self.resources
+            .get(name)
+            .map(|resource| resource.is_python_package)
+            .unwrap_or(false)
+    }
 
-            if let Some(resources) = &entry.relative_path_package_resources {
-                entries.extend(resources.keys().filter_map(filter_map_resource));
-            }
-        }
+    /// Resolve the names of packages that are direct children of a package.
+    ///
+    /// Names are relative to the parent package and returned in sorted order.
+    pub fn child_package_names(&self, package: &str) -> Vec<&str> {
+        let prefix = format!("{}.", package);
 
-        entries.into_iter().collect::<Vec<_>>()
+        let mut names = self
+            .resources
+            .values()
+            .filter(|resource| resource.is_python_package)
+            .filter_map(|resource| resource.name.strip_prefix(&prefix))
+            .filter(|name| !name.contains('.'))
+            .collect::<Vec<_>>();
+
+        names.sort_unstable();
+
+        names
+    }
+
+    /// Resolve the names of files and directories in a package resource directory.
+    ///
+    /// An empty `name` lists the root directory of the package. Names are
+    /// returned in sorted order.
+    pub fn package_resources_list_directory(&self, package: &str, name: &str) -> Vec<String> {
+        self.package_resource_directory_entries(package, name)
+            .into_keys()
+            .collect::<Vec<_>>()
     }
 
     /// Attempt to resolve a PyBytes for resource data given a relative path.
@@ -1008,7 +1810,10 @@ impl<'a> PythonResourcesState<'a, u8> {
             let resource_name = name_parts.join("/");
             let resource_name_ref: &str = &resource_name;
 
-            if let Some(entry) = self.resources.get(package_name_ref) {
+            if let Some(entry) = self
+                .get_resource(package_name_ref)
+                .map_err(PyValueError::new_err)?
+            {
                 if check_in_memory {
                     if let Some(resources) = &entry.in_memory_package_resources {
                         if let Some(data) = resources.get(resource_name_ref) {
@@ -1112,7 +1917,7 @@ impl<'a> PythonResourcesState<'a, u8> {
         package: &str,
         name: &str,
     ) -> Result<Option<Cow<'_, [u8]>>> {
-        if let Some(entry) = self.resources.get(package) {
+        if let Some(entry) = self.get_resource(package).map_err(anyhow::Error::msg)? {
             if let Some(resources) = &entry.in_memory_distribution_resources {
                 if let Some(data) = resources.get(name) {
                     return Ok(Some(Cow::Borrowed(data.as_ref())));
@@ -1223,21 +2028,39 @@ impl<'a> PythonResourcesState<'a, u8> {
     }
 
     /// Resolve content of a shared library to load from memory.
-    pub fn resolve_in_memory_shared_library_data(&self, name: &str) -> Option<&[u8]> {
-        if let Some(entry) = &self.resources.get(name) {
-            if let Some(library_data) = &entry.in_memory_shared_library {
-                Some(library_data.as_ref())
-            } else {
-                None
-            }
+    pub fn resolve_in_memory_shared_library_data(
+        &self,
+        name: &str,
+    ) -> Result<Option<&[u8]>, &'static str> {
+        Ok(if let Some(entry) = self.get_resource(name)? {
+            entry
+                .in_memory_shared_library
+                .as_ref()
+                .map(|library_data| library_data.as_ref())
         } else {
             None
+        })
+    }
+
+    /// Obtain the names of shared libraries that a named resource depends on.
+    pub fn resolve_shared_library_dependency_names(&self, name: &str) -> Vec<&str> {
+        if let Some(entry) = &self.resources.get(name) {
+            if let Some(names) = &entry.shared_library_dependency_names {
+                return names.iter().map(|name| name.as_ref()).collect();
+            }
         }
+
+        vec![]
     }
 
     /// Convert indexed resources to a [PyList].
     pub fn resources_as_py_list<'p>(&self, py: Python<'p>) -> PyResult<&'p PyList> {
-        let mut resources = self.resources.values().collect::<Vec<_>>();
+        let mut resources = self
+            .resources
+            .values()
+            .filter_map(|resource| self.get_resource(&resource.name).transpose())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(PyValueError::new_err)?;
         resources.sort_by_key(|r| &r.name);
 
         let objects = resources
@@ -1755,6 +2578,26 @@ impl OxidizedResource {
 
         Ok(())
     }
+
+    #[getter]
+    fn get_metadata(&self) -> Option<HashMap<String, String>> {
+        self.resource.borrow().metadata.as_ref().map(|x| {
+            x.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        })
+    }
+
+    #[setter]
+    fn set_metadata(&self, value: Option<HashMap<String, String>>) -> PyResult<()> {
+        self.resource.borrow_mut().metadata = value.map(|x| {
+            x.into_iter()
+                .map(|(k, v)| (Cow::Owned(k), Cow::Owned(v)))
+                .collect()
+        });
+
+        Ok(())
+    }
 }
 
 /// Convert a Resource to an OxidizedResource.
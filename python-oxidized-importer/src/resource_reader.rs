@@ -4,10 +4,334 @@
 
 use {
     crate::importer::ImporterState,
-    pyo3::{exceptions::PyFileNotFoundError, prelude::*},
-    std::sync::Arc,
+    pyo3::{
+        buffer::PyBuffer,
+        exceptions::{
+            PyBufferError, PyFileNotFoundError, PyNotADirectoryError, PyTypeError, PyValueError,
+        },
+        ffi as pyffi,
+        prelude::*,
+        types::{PyBytes, PyDict, PyList, PyTuple},
+        AsPyPointer,
+    },
+    std::{borrow::Cow, os::raw::c_int, sync::Arc},
 };
 
+/// A read-only, binary file-like object over resource data held in memory.
+///
+/// Behaves like `io.BytesIO` except the data isn't copied when the file is
+/// opened. Data borrowed from packed resources is referenced in place.
+/// `getbuffer()` and `memoryview()` expose the data without any copies.
+#[pyclass(module = "oxidized_importer")]
+pub(crate) struct OxidizedResourceFile {
+    /// The resource data.
+    ///
+    /// Borrowed data isn't actually `'static`. It lives as long as `owner`.
+    data: Cow<'static, [u8]>,
+    /// Python object keeping borrowed data alive.
+    owner: Option<Py<PyAny>>,
+    position: usize,
+    closed: bool,
+    /// Number of buffers exported via the buffer protocol.
+    exports: usize,
+}
+
+impl OxidizedResourceFile {
+    /// Construct an instance over resource data.
+    ///
+    /// Borrowed data is referenced in place and must remain valid for as
+    /// long as `owner` is alive. Owned data is copied, as it may be
+    /// replaced while the file is open.
+    // Whether the data is borrowed matters, so a plain slice won't do.
+    #[allow(clippy::ptr_arg)]
+    pub(crate) fn new(data: &Cow<[u8]>, owner: &PyAny) -> Self {
+        let (data, owner) = match data {
+            Cow::Borrowed(data) => {
+                let data = unsafe { std::slice::from_raw_parts(data.as_ptr(), data.len()) };
+                (Cow::Borrowed(data), Some(owner.into()))
+            }
+            Cow::Owned(data) => (Cow::Owned(data.clone()), None),
+        };
+
+        Self {
+            data,
+            owner,
+            position: 0,
+            closed: false,
+            exports: 0,
+        }
+    }
+
+    fn check_closed(&self) -> PyResult<()> {
+        if self.closed {
+            Err(PyValueError::new_err("I/O operation on closed file."))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Data from the current position to the end of the file.
+    fn remaining(&self) -> &[u8] {
+        &self.data[self.position.min(self.data.len())..]
+    }
+
+    /// Advance the position by up to `size` bytes, returning the data passed over.
+    ///
+    /// A negative `size` means the rest of the data.
+    fn consume(&mut self, size: Option<isize>) -> PyResult<&[u8]> {
+        self.check_closed()?;
+
+        let start = self.position.min(self.data.len());
+        let end = match size {
+            Some(size) if size >= 0 => self.data.len().min(start + size as usize),
+            _ => self.data.len(),
+        };
+
+        self.position = end;
+
+        Ok(&self.data[start..end])
+    }
+}
+
+#[pymethods]
+impl OxidizedResourceFile {
+    fn __enter__(slf: PyRef<Self>) -> PyResult<PyRef<Self>> {
+        slf.check_closed()?;
+
+        Ok(slf)
+    }
+
+    #[allow(unused)]
+    fn __exit__(&mut self, exc_type: &PyAny, exc_value: &PyAny, exc_tb: &PyAny) -> PyResult<()> {
+        self.close()
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> PyResult<PyRef<Self>> {
+        slf.check_closed()?;
+
+        Ok(slf)
+    }
+
+    fn __next__<'p>(&mut self, py: Python<'p>) -> PyResult<Option<&'p PyBytes>> {
+        let line = self.readline(py, None)?;
+
+        Ok(if line.as_bytes().is_empty() {
+            None
+        } else {
+            Some(line)
+        })
+    }
+
+    unsafe fn __getbuffer__(
+        mut slf: PyRefMut<Self>,
+        view: *mut pyffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null"));
+        }
+
+        slf.check_closed()?;
+
+        if pyffi::PyBuffer_FillInfo(
+            view,
+            slf.as_ptr(),
+            slf.data.as_ptr() as *mut _,
+            slf.data.len() as _,
+            1,
+            flags,
+        ) != 0
+        {
+            return Err(PyErr::fetch(slf.py()));
+        }
+
+        slf.exports += 1;
+
+        Ok(())
+    }
+
+    unsafe fn __releasebuffer__(mut slf: PyRefMut<Self>, _view: *mut pyffi::Py_buffer) {
+        slf.exports -= 1;
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Close the file, releasing the reference to the resource data.
+    fn close(&mut self) -> PyResult<()> {
+        if self.exports > 0 {
+            return Err(PyBufferError::new_err(
+                "Existing exports of data: object cannot be closed",
+            ));
+        }
+
+        self.closed = true;
+        self.data = Cow::Borrowed(&[]);
+        self.owner = None;
+
+        Ok(())
+    }
+
+    fn flush(&self) -> PyResult<()> {
+        self.check_closed()
+    }
+
+    /// Obtain a read-only `memoryview` of the resource data.
+    fn getbuffer<'p>(slf: PyRef<Self>, py: Python<'p>) -> PyResult<&'p PyAny> {
+        slf.check_closed()?;
+
+        py.import("builtins")?.getattr("memoryview")?.call1((slf,))
+    }
+
+    /// Obtain the entire resource data as `bytes`.
+    ///
+    /// Unlike `getbuffer()`, this copies the data.
+    fn getvalue<'p>(&self, py: Python<'p>) -> PyResult<&'p PyBytes> {
+        self.check_closed()?;
+
+        Ok(PyBytes::new(py, &self.data))
+    }
+
+    fn isatty(&self) -> PyResult<bool> {
+        self.check_closed()?;
+
+        Ok(false)
+    }
+
+    #[args(size = "None")]
+    fn read<'p>(&mut self, py: Python<'p>, size: Option<isize>) -> PyResult<&'p PyBytes> {
+        Ok(PyBytes::new(py, self.consume(size)?))
+    }
+
+    #[args(size = "None")]
+    fn read1<'p>(&mut self, py: Python<'p>, size: Option<isize>) -> PyResult<&'p PyBytes> {
+        self.read(py, size)
+    }
+
+    fn readable(&self) -> PyResult<bool> {
+        self.check_closed()?;
+
+        Ok(true)
+    }
+
+    fn readinto(&mut self, buffer: &PyAny) -> PyResult<usize> {
+        self.check_closed()?;
+
+        let buffer = PyBuffer::<u8>::get(buffer)?;
+
+        if buffer.readonly() || !buffer.is_c_contiguous() {
+            return Err(PyTypeError::new_err(
+                "readinto() argument must be a writable, contiguous buffer",
+            ));
+        }
+
+        let data = self.remaining();
+        let size = data.len().min(buffer.len_bytes());
+
+        // The buffer is writable and contiguous, so it can be written to
+        // directly.
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), buffer.buf_ptr() as *mut u8, size) };
+
+        self.position += size;
+
+        Ok(size)
+    }
+
+    #[args(size = "None")]
+    fn readline<'p>(&mut self, py: Python<'p>, size: Option<isize>) -> PyResult<&'p PyBytes> {
+        self.check_closed()?;
+
+        let data = self.remaining();
+        let mut end = data
+            .iter()
+            .position(|c| *c == b'\n')
+            .map(|i| i + 1)
+            .unwrap_or_else(|| data.len());
+
+        if let Some(size) = size {
+            if size >= 0 {
+                end = end.min(size as usize);
+            }
+        }
+
+        Ok(PyBytes::new(py, self.consume(Some(end as isize))?))
+    }
+
+    #[args(hint = "None")]
+    fn readlines<'p>(&mut self, py: Python<'p>, hint: Option<isize>) -> PyResult<&'p PyList> {
+        let lines = PyList::empty(py);
+        let mut total = 0;
+
+        loop {
+            let line = self.readline(py, None)?;
+            let len = line.as_bytes().len();
+
+            if len == 0 {
+                break;
+            }
+
+            lines.append(line)?;
+            total += len;
+
+            if matches!(hint, Some(hint) if hint > 0 && total >= hint as usize) {
+                break;
+            }
+        }
+
+        Ok(lines)
+    }
+
+    #[args(whence = "0")]
+    fn seek(&mut self, offset: isize, whence: i32) -> PyResult<usize> {
+        self.check_closed()?;
+
+        let base = match whence {
+            0 => {
+                if offset < 0 {
+                    return Err(PyValueError::new_err(format!(
+                        "negative seek value {}",
+                        offset
+                    )));
+                }
+
+                0
+            }
+            1 => self.position as isize,
+            2 => self.data.len() as isize,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "invalid whence ({}, should be 0, 1 or 2)",
+                    whence
+                )))
+            }
+        };
+
+        self.position = (base + offset).max(0) as usize;
+
+        Ok(self.position)
+    }
+
+    fn seekable(&self) -> PyResult<bool> {
+        self.check_closed()?;
+
+        Ok(true)
+    }
+
+    fn tell(&self) -> PyResult<usize> {
+        self.check_closed()?;
+
+        Ok(self.position)
+    }
+
+    fn writable(&self) -> PyResult<bool> {
+        self.check_closed()?;
+
+        Ok(false)
+    }
+}
+
 /// Implements in-memory reading of resource data.
 ///
 /// Implements importlib.abc.ResourceReader.
@@ -31,6 +355,7 @@ impl OxidizedResourceReader {
     fn open_resource<'p>(&self, py: Python<'p>, resource: &str) -> PyResult<&'p PyAny> {
         if let Some(file) = self.state.get_resources_state().get_package_resource_file(
             py,
+            self.state.resources_state.as_ref(py),
             &self.package,
             resource,
         )? {
@@ -80,4 +405,233 @@ impl OxidizedResourceReader {
             .get_resources_state()
             .package_resource_names(py, &self.package)
     }
+
+    /// Returns an importlib.abc.Traversable for the root of the package.
+    fn files(&self) -> OxidizedTraversable {
+        OxidizedTraversable::new(self.state.clone(), self.package.clone(), String::new())
+    }
+}
+
+/// Path-like object facilitating Python resource access.
+///
+/// Implements importlib.abc.Traversable.
+///
+/// Instances reference a package and a `/` delimited path to a resource or
+/// resource directory within it. An empty path refers to the package itself.
+#[pyclass(module = "oxidized_importer")]
+pub(crate) struct OxidizedTraversable {
+    state: Arc<ImporterState>,
+    package: String,
+    path: String,
+}
+
+impl OxidizedTraversable {
+    fn new(state: Arc<ImporterState>, package: String, path: String) -> Self {
+        Self {
+            state,
+            package,
+            path,
+        }
+    }
+
+    /// Resolve the Traversable for a child.
+    ///
+    /// Children of a package's root directory may be sub-packages.
+    fn child(&self, name: &str) -> Self {
+        if self.path.is_empty()
+            && self
+                .state
+                .get_resources_state()
+                .child_package_names(&self.package)
+                .contains(&name)
+        {
+            Self::new(
+                self.state.clone(),
+                format!("{}.{}", self.package, name),
+                String::new(),
+            )
+        } else if self.path.is_empty() {
+            Self::new(self.state.clone(), self.package.clone(), name.to_string())
+        } else {
+            Self::new(
+                self.state.clone(),
+                self.package.clone(),
+                format!("{}/{}", self.path, name),
+            )
+        }
+    }
+
+    /// Resolve a `/` delimited path relative to this instance.
+    fn joinpath_str(&self, path: &str) -> OxidizedTraversable {
+        let mut traversable =
+            Self::new(self.state.clone(), self.package.clone(), self.path.clone());
+
+        for name in path
+            .split(&['/', '\\'][..])
+            .filter(|name| !name.is_empty() && *name != ".")
+        {
+            traversable = traversable.child(name);
+        }
+
+        traversable
+    }
+
+    /// Obtain a file-like object for binary reading of the resource.
+    fn open_binary<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        if self.path.is_empty() {
+            return Err(PyFileNotFoundError::new_err("resource not found"));
+        }
+
+        if let Some(file) = self.state.get_resources_state().get_package_resource_file(
+            py,
+            self.state.resources_state.as_ref(py),
+            &self.package,
+            &self.path,
+        )? {
+            Ok(file)
+        } else {
+            Err(PyFileNotFoundError::new_err("resource not found"))
+        }
+    }
+}
+
+#[pymethods]
+impl OxidizedTraversable {
+    fn __repr__(&self) -> String {
+        format!(
+            "<OxidizedTraversable package={:?} path={:?}>",
+            self.package, self.path
+        )
+    }
+
+    fn __truediv__(&self, child: &str) -> OxidizedTraversable {
+        self.joinpath_str(child)
+    }
+
+    /// The base name of this object without any parent references.
+    #[getter]
+    fn name(&self) -> String {
+        if self.path.is_empty() {
+            self.package
+                .rsplit('.')
+                .next()
+                .unwrap_or(&self.package)
+                .to_string()
+        } else {
+            self.path
+                .rsplit('/')
+                .next()
+                .unwrap_or(&self.path)
+                .to_string()
+        }
+    }
+
+    /// Yield Traversable objects in self.
+    fn iterdir<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        if !self.is_dir() {
+            return Err(PyNotADirectoryError::new_err("not a resource directory"));
+        }
+
+        let resources = self.state.get_resources_state();
+
+        let mut children = resources
+            .package_resource_directory_entries(&self.package, &self.path)
+            .into_keys()
+            .collect::<Vec<_>>();
+
+        if self.path.is_empty() {
+            children.extend(
+                resources
+                    .child_package_names(&self.package)
+                    .into_iter()
+                    .map(|name| name.to_string()),
+            );
+        }
+
+        let children = children
+            .iter()
+            .map(|name| Py::new(py, self.child(name)))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        PyList::new(py, children).call_method0("__iter__")
+    }
+
+    /// Read contents of self as bytes.
+    fn read_bytes<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        self.open_binary(py)?.call_method0("read")
+    }
+
+    /// Read contents of self as text.
+    #[args(encoding = "None")]
+    fn read_text<'p>(&self, py: Python<'p>, encoding: Option<&str>) -> PyResult<&'p PyAny> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("encoding", encoding)?;
+
+        py.import("io")?
+            .getattr("TextIOWrapper")?
+            .call((self.open_binary(py)?,), Some(kwargs))?
+            .call_method0("read")
+    }
+
+    /// Return True if self is a directory.
+    fn is_dir(&self) -> bool {
+        let resources = self.state.get_resources_state();
+
+        if self.path.is_empty() {
+            resources.is_package(&self.package)
+        } else {
+            resources.is_package_resource_directory(&self.package, &self.path)
+        }
+    }
+
+    /// Return True if self is a file.
+    fn is_file(&self) -> bool {
+        !self.path.is_empty()
+            && self
+                .state
+                .get_resources_state()
+                .is_package_resource(&self.package, &self.path)
+    }
+
+    /// Return Traversable child in self.
+    #[args(descendants = "*")]
+    fn joinpath(&self, descendants: &PyTuple) -> PyResult<OxidizedTraversable> {
+        let mut path = Vec::with_capacity(descendants.len());
+
+        for descendant in descendants {
+            path.push(descendant.extract::<String>()?);
+        }
+
+        Ok(self.joinpath_str(&path.join("/")))
+    }
+
+    /// mode may be 'r' or 'rb' to open as text or binary. Return a handle
+    /// suitable for reading (same as pathlib.Path.open).
+    ///
+    /// When opening as text, accepts encoding parameters such as those
+    /// accepted by io.TextIOWrapper.
+    #[args(mode = "\"r\"", py_args = "*", py_kwargs = "**")]
+    fn open<'p>(
+        &self,
+        py: Python<'p>,
+        mode: &str,
+        py_args: &PyTuple,
+        py_kwargs: Option<&PyDict>,
+    ) -> PyResult<&'p PyAny> {
+        match mode {
+            "r" => {
+                let mut args = vec![self.open_binary(py)?];
+                args.extend(py_args.iter());
+
+                py.import("io")?
+                    .getattr("TextIOWrapper")?
+                    .call(PyTuple::new(py, args), py_kwargs)
+            }
+            "rb" => self.open_binary(py),
+            _ => Err(PyValueError::new_err(format!(
+                "invalid mode: {}; must be 'r' or 'rb'",
+                mode
+            ))),
+        }
+    }
 }
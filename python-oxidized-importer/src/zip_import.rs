@@ -20,7 +20,7 @@ use {
         io::{BufReader, Cursor, Read, Seek},
         path::{Path, PathBuf},
     },
-    zip::read::ZipArchive,
+    zip::{read::ZipArchive, result::ZipError},
 };
 
 /// Represents a handle on a Python module within a [ZipImporter].
@@ -262,6 +262,41 @@ impl OxidizedZipFinder {
         Self::new_internal(py, index, zip_path, None)
     }
 
+    /// Construct a new instance from a zip archive appended to a file.
+    ///
+    /// The offset of the archive within the file is discovered from the zip
+    /// end of central directory record. So arbitrary data, such as an
+    /// executable, can precede it.
+    ///
+    /// Returns [None] if the file doesn't end with a zip archive.
+    pub fn new_from_appended_path(py: Python, path: &Path) -> PyResult<Option<Self>> {
+        let f = std::fs::File::open(path).map_err(|e| {
+            PyValueError::new_err(format!("failed to open path {}: {}", path.display(), e))
+        })?;
+        let reader: Box<dyn SeekableReader> = Box::new(BufReader::new(f));
+
+        let index = match ZipIndex::new(reader, None) {
+            Ok(index) => index,
+            Err(e) if matches!(e.downcast_ref(), Some(ZipError::InvalidArchive(_))) => {
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(PyValueError::new_err(format!(
+                    "error indexing zip data in {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+
+        Ok(Some(Self::new_internal(
+            py,
+            index,
+            path.to_path_buf(),
+            None,
+        )?))
+    }
+
     fn new_internal(
         py: Python,
         index: ZipIndex<Box<dyn SeekableReader>>,
@@ -0,0 +1,227 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+Detection of Python packages that don't work when imported from memory.
+
+Modules imported from memory don't have files on the filesystem. Packages
+that locate files relative to their modules, such as data files or shared
+libraries loaded with `ctypes`, break when imported from memory.
+
+Some packages are known to do this. Others are detected by looking for
+code patterns in their sources. Detection is heuristic: it can flag packages
+that work fine or miss packages that don't.
+*/
+
+use {
+    crate::{python_source::decode_python_source, resource::PythonResource},
+    anyhow::Result,
+    once_cell::sync::Lazy,
+    regex::Regex,
+    std::collections::BTreeMap,
+};
+
+/// Top-level packages known to not work when imported from memory.
+///
+/// Values describe why.
+pub const KNOWN_INCOMPATIBLE_PACKAGES: &[(&str, &str)] = &[
+    ("babel", "locates locale data relative to __file__"),
+    ("botocore", "locates its data files relative to __file__"),
+    (
+        "django",
+        "locates templates, static files, and locale data relative to __file__",
+    ),
+    ("matplotlib", "locates mpl-data relative to __file__"),
+    (
+        "numpy",
+        "locates bundled shared libraries and data relative to __file__",
+    ),
+    ("PyQt5", "Qt loads plugins from the package directory"),
+    ("PyQt6", "Qt loads plugins from the package directory"),
+    ("pyproj", "locates PROJ data relative to __file__"),
+    ("PySide2", "Qt loads plugins from the package directory"),
+    ("PySide6", "Qt loads plugins from the package directory"),
+    ("pytz", "opens zoneinfo files relative to __file__"),
+    (
+        "scipy",
+        "locates bundled shared libraries and data relative to __file__",
+    ),
+];
+
+static RE_CTYPES_LOAD: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(?:CDLL|PyDLL|WinDLL|OleDLL|LoadLibrary)\(").unwrap());
+
+static RE_MODULE_PATH: Lazy<Regex> = Lazy::new(|| Regex::new(r"__file__|__path__").unwrap());
+
+static RE_FILE_RELATIVE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:dirname|abspath|realpath|split|Path)\(\s*__file__\s*\)").unwrap()
+});
+
+static RE_PATH_INDEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"__path__\s*\[\s*0\s*\]").unwrap());
+
+static RE_RESOURCE_FILENAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\bresource_filename\(").unwrap());
+
+/// Look up why a package is known to not work when imported from memory.
+///
+/// `name` can be the name of any module in the package.
+pub fn known_incompatibility(name: &str) -> Option<&'static str> {
+    let package = name.split('.').next().unwrap_or(name);
+
+    KNOWN_INCOMPATIBLE_PACKAGES
+        .iter()
+        .find(|(candidate, _)| *candidate == package)
+        .map(|(_, reason)| *reason)
+}
+
+/// Look for code patterns in Python source that break when imported from memory.
+///
+/// Returns a description of the first pattern found.
+pub fn source_incompatibility(source: &[u8]) -> Option<&'static str> {
+    let source = decode_python_source(source);
+
+    if RE_CTYPES_LOAD.is_match(&source) && RE_MODULE_PATH.is_match(&source) {
+        Some("loads shared libraries relative to the module with ctypes")
+    } else if RE_FILE_RELATIVE.is_match(&source) {
+        Some("locates files relative to __file__")
+    } else if RE_PATH_INDEX.is_match(&source) {
+        Some("locates files relative to __path__")
+    } else if RE_RESOURCE_FILENAME.is_match(&source) {
+        Some("requires resources to be files with resource_filename()")
+    } else {
+        None
+    }
+}
+
+/// Find packages among resources that don't work when imported from memory.
+///
+/// Returns a mapping of top-level package name to a description of why.
+/// Resources of the Python standard library and tests are ignored.
+pub fn find_in_memory_incompatible_packages<'a>(
+    resources: impl Iterator<Item = &'a PythonResource<'a>>,
+) -> Result<BTreeMap<String, String>> {
+    let mut res = BTreeMap::new();
+
+    for resource in resources {
+        let (name, reason) = match resource {
+            PythonResource::ModuleSource(module) if !module.is_stdlib && !module.is_test => {
+                let reason = match known_incompatibility(&module.name) {
+                    Some(reason) => Some(reason.to_string()),
+                    None => source_incompatibility(&module.source.resolve_content()?)
+                        .map(|reason| format!("{} {}", module.name, reason)),
+                };
+
+                (&module.name, reason)
+            }
+            PythonResource::PackageResource(resource) if !resource.is_stdlib => (
+                &resource.leaf_package,
+                known_incompatibility(&resource.leaf_package).map(|x| x.to_string()),
+            ),
+            PythonResource::ExtensionModule(module) if !module.is_stdlib => (
+                &module.name,
+                known_incompatibility(&module.name).map(|x| x.to_string()),
+            ),
+            _ => continue,
+        };
+
+        if let Some(reason) = reason {
+            let package = name.split('.').next().unwrap_or(name);
+            res.entry(package.to_string()).or_insert(reason);
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, crate::resource::PythonModuleSource, simple_file_manifest::FileData,
+        std::borrow::Cow,
+    };
+
+    fn module(name: &str, source: &str, is_stdlib: bool) -> PythonResource<'static> {
+        PythonResource::ModuleSource(Cow::Owned(PythonModuleSource {
+            name: name.to_string(),
+            source: FileData::Memory(source.as_bytes().to_vec()),
+            is_package: false,
+            cache_tag: "cpython-39".to_string(),
+            is_stdlib,
+            is_test: false,
+        }))
+    }
+
+    #[test]
+    fn test_known_incompatibility() {
+        assert!(known_incompatibility("numpy").is_some());
+        assert!(known_incompatibility("numpy.core.multiarray").is_some());
+        assert!(known_incompatibility("numpyx").is_none());
+        assert!(known_incompatibility("foo").is_none());
+    }
+
+    #[test]
+    fn test_source_incompatibility() {
+        assert_eq!(source_incompatibility(b"import os\nprint('hello')\n"), None);
+        assert_eq!(
+            source_incompatibility(b"if hasattr(m, '__file__'): pass\n"),
+            None
+        );
+        assert_eq!(
+            source_incompatibility(b"HERE = os.path.dirname(__file__)\n"),
+            Some("locates files relative to __file__")
+        );
+        assert_eq!(
+            source_incompatibility(b"DATA = Path(__file__).parent / 'data'\n"),
+            Some("locates files relative to __file__")
+        );
+        assert_eq!(
+            source_incompatibility(b"d = __path__[0]\n"),
+            Some("locates files relative to __path__")
+        );
+        assert_eq!(
+            source_incompatibility(b"f = pkg_resources.resource_filename('foo', 'bar')\n"),
+            Some("requires resources to be files with resource_filename()")
+        );
+        assert_eq!(
+            source_incompatibility(
+                b"lib = ctypes.CDLL(os.path.join(os.path.dirname(__file__), 'libfoo.so'))\n"
+            ),
+            Some("loads shared libraries relative to the module with ctypes")
+        );
+        // Loading system libraries is fine.
+        assert_eq!(
+            source_incompatibility(b"libc = ctypes.CDLL('libc.so.6')\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_in_memory_incompatible_packages() -> Result<()> {
+        let resources = [
+            module("numpy", "", false),
+            module("numpy.core", "", false),
+            module("foo", "", false),
+            module("foo.data", "HERE = os.path.dirname(__file__)", false),
+            module("bar", "import os", false),
+            module("json", "HERE = os.path.dirname(__file__)", true),
+        ];
+
+        let packages = find_in_memory_incompatible_packages(resources.iter())?;
+        assert_eq!(packages.len(), 2);
+        assert_eq!(
+            packages.get("numpy").map(|x| x.as_str()),
+            known_incompatibility("numpy")
+        );
+        assert_eq!(
+            packages.get("foo"),
+            Some(&"foo.data locates files relative to __file__".to_string())
+        );
+
+        Ok(())
+    }
+}
@@ -666,6 +666,85 @@ impl TryFrom<String> for MultiprocessingStartMethod {
     }
 }
 
+/// Defines where `oxidized_importer.OxidizedFinder` looks for a package's modules.
+///
+/// A precedence applies to a package and all its sub-modules, unless a more
+/// specific precedence is defined for a sub-package.
+///
+/// Serialization type: `string`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
+pub enum ImportPrecedence {
+    /// Import indexed modules in preference to modules on the filesystem.
+    ///
+    /// This is the default behavior.
+    ///
+    /// Serialized value: `memory-first`
+    #[default]
+    MemoryFirst,
+
+    /// Import modules from the filesystem via `sys.path` if present.
+    ///
+    /// Indexed modules are only imported if the filesystem doesn't have them.
+    ///
+    /// Serialized value: `filesystem-first`
+    FilesystemFirst,
+
+    /// Only import indexed modules.
+    ///
+    /// Other meta path finders are prevented from importing modules not
+    /// indexed by `OxidizedFinder`.
+    ///
+    /// Serialized value: `memory-only`
+    MemoryOnly,
+}
+
+impl std::fmt::Display for ImportPrecedence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::MemoryFirst => "memory-first",
+            Self::FilesystemFirst => "filesystem-first",
+            Self::MemoryOnly => "memory-only",
+        })
+    }
+}
+
+impl From<ImportPrecedence> for String {
+    fn from(v: ImportPrecedence) -> Self {
+        v.to_string()
+    }
+}
+
+impl FromStr for ImportPrecedence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memory-first" => Ok(Self::MemoryFirst),
+            "filesystem-first" => Ok(Self::FilesystemFirst),
+            "memory-only" => Ok(Self::MemoryOnly),
+            _ => Err(format!("{} is not a valid import precedence", s)),
+        }
+    }
+}
+
+impl TryFrom<&str> for ImportPrecedence {
+    type Error = String;
+
+    fn try_from(v: &str) -> Result<Self, Self::Error> {
+        Self::from_str(v)
+    }
+}
+
+impl TryFrom<String> for ImportPrecedence {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
 /// Holds configuration of a Python interpreter.
 ///
 /// This struct holds fields that are exposed by `PyPreConfig` and
@@ -874,6 +953,13 @@ pub struct PythonInterpreterConfig {
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.pathconfig_warnings>.
     pub pathconfig_warnings: Option<bool>,
 
+    /// Name of the platform-specific library directory (`sys.platlibdir`).
+    ///
+    /// Only supported on Python 3.9+.
+    ///
+    /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.platlibdir>.
+    pub platlibdir: Option<String>,
+
     /// Defines `sys.prefix`.
     ///
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.prefix>.
@@ -951,6 +1037,15 @@ pub struct PythonInterpreterConfig {
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.tracemalloc>.
     pub tracemalloc: Option<bool>,
 
+    /// Number of frames to store in `tracemalloc` tracebacks.
+    ///
+    /// This is equivalent to `PYTHONTRACEMALLOC=N` and `-X tracemalloc=N`. A value
+    /// of `0` disables `tracemalloc`. If set, this takes precedence over
+    /// [Self::tracemalloc], which is equivalent to storing 1 frame.
+    ///
+    /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.tracemalloc>.
+    pub tracemalloc_frames: Option<u32>,
+
     /// Whether to add the user site directory to `sys.path`.
     ///
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.user_site_directory>.
@@ -961,6 +1056,13 @@ pub struct PythonInterpreterConfig {
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.verbose>.
     pub verbose: Option<bool>,
 
+    /// Whether to emit an `EncodingWarning` when a default encoding is used.
+    ///
+    /// Only supported on Python 3.10+.
+    ///
+    /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.warn_default_encoding>.
+    pub warn_default_encoding: Option<bool>,
+
     /// Options of the `warning` module to control behavior.
     ///
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.warnoptions>.
@@ -14,6 +14,7 @@ and packaging facilities.
 
 pub mod bytecode;
 pub mod filesystem_scanning;
+pub mod in_memory_compatibility;
 pub mod interpreter;
 pub mod libpython;
 pub mod licensing;
@@ -9,10 +9,14 @@
 /*! Working with Python package metadata (i.e. .pkg-info directories) */
 
 use {
-    anyhow::{Context, Result},
+    anyhow::{anyhow, Context, Result},
     mailparse::parse_mail,
+    std::collections::BTreeMap,
 };
 
+/// Prefix of resource metadata keys recording parsed `.dist-info` files.
+pub const DIST_INFO_METADATA_PREFIX: &str = "dist-info.";
+
 /// Represents a Python METADATA file.
 pub struct PythonPackageMetadata {
     headers: Vec<(String, String)>,
@@ -64,6 +68,177 @@ impl PythonPackageMetadata {
     pub fn license(&self) -> Option<&str> {
         self.find_first_header("License")
     }
+
+    pub fn summary(&self) -> Option<&str> {
+        self.find_first_header("Summary")
+    }
+}
+
+/// An entry in a `.dist-info/RECORD` file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordEntry {
+    /// Path of the installed file, relative to the installation root.
+    pub path: String,
+    /// Hash of the file content, as `<algorithm>=<urlsafe base64 digest>`.
+    pub hash: Option<String>,
+    /// Size of the file in bytes.
+    pub size: Option<u64>,
+}
+
+/// Split a CSV line into fields, honoring double quoted fields.
+fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(anyhow!("unterminated quoted field: {}", line));
+    }
+
+    fields.push(field);
+
+    Ok(fields)
+}
+
+/// Parse the content of a `RECORD` file.
+pub fn parse_record(data: &[u8]) -> Result<Vec<RecordEntry>> {
+    let data = std::str::from_utf8(data).context("RECORD is not UTF-8")?;
+
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields = split_csv_line(line)?;
+
+            if fields.len() != 3 {
+                return Err(anyhow!("malformed RECORD line: {}", line));
+            }
+
+            Ok(RecordEntry {
+                path: fields[0].clone(),
+                hash: Some(fields[1].clone()).filter(|hash| !hash.is_empty()),
+                size: if fields[2].is_empty() {
+                    None
+                } else {
+                    Some(
+                        fields[2]
+                            .parse()
+                            .with_context(|| format!("parsing size in RECORD line: {}", line))?,
+                    )
+                },
+            })
+        })
+        .collect()
+}
+
+/// Parse the content of an `entry_points.txt` file.
+///
+/// Returns a mapping of entry point group to `(name, object reference)` tuples.
+pub fn parse_entry_points(data: &[u8]) -> Result<BTreeMap<String, Vec<(String, String)>>> {
+    let data = std::str::from_utf8(data).context("entry_points.txt is not UTF-8")?;
+
+    let mut res = BTreeMap::new();
+    let mut group = None;
+
+    for line in data.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            group = Some(res.entry(name.trim().to_string()).or_insert_with(Vec::new));
+        } else if let Some((name, value)) = line.split_once('=') {
+            group
+                .as_mut()
+                .ok_or_else(|| anyhow!("entry point outside of a group: {}", line))?
+                .push((name.trim().to_string(), value.trim().to_string()));
+        } else {
+            return Err(anyhow!("malformed entry_points.txt line: {}", line));
+        }
+    }
+
+    Ok(res)
+}
+
+/// Derive resource metadata from a file in a `.dist-info` directory.
+///
+/// `METADATA`, `RECORD`, and `entry_points.txt` files are parsed into
+/// entries whose keys begin with [DIST_INFO_METADATA_PREFIX]. Values holding
+/// multiple items have one item per line. Returns `None` for other files.
+pub fn dist_info_resource_metadata(
+    name: &str,
+    data: &[u8],
+) -> Result<Option<BTreeMap<String, String>>> {
+    let key = |suffix: &str| format!("{}{}", DIST_INFO_METADATA_PREFIX, suffix);
+    let mut res = BTreeMap::new();
+
+    match name {
+        "METADATA" => {
+            let metadata = PythonPackageMetadata::from_metadata(data)?;
+
+            for (header, suffix) in [
+                ("Name", "name"),
+                ("Version", "version"),
+                ("Summary", "summary"),
+                ("License", "license"),
+                ("License-Expression", "license-expression"),
+                ("Requires-Python", "requires-python"),
+            ] {
+                if let Some(value) = metadata.find_first_header(header) {
+                    res.insert(key(suffix), value.to_string());
+                }
+            }
+
+            let license_classifiers = metadata
+                .find_all_headers("Classifier")
+                .into_iter()
+                .filter(|classifier| classifier.starts_with("License ::"))
+                .collect::<Vec<_>>();
+            if !license_classifiers.is_empty() {
+                res.insert(key("license-classifiers"), license_classifiers.join("\n"));
+            }
+
+            let requires_dist = metadata.find_all_headers("Requires-Dist");
+            if !requires_dist.is_empty() {
+                res.insert(key("requires-dist"), requires_dist.join("\n"));
+            }
+        }
+        "RECORD" => {
+            let paths = parse_record(data)?
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect::<Vec<_>>();
+
+            res.insert(key("files"), paths.join("\n"));
+        }
+        "entry_points.txt" => {
+            for (group, entry_points) in parse_entry_points(data)? {
+                res.insert(
+                    key(&format!("entry-points.{}", group)),
+                    entry_points
+                        .into_iter()
+                        .map(|(name, value)| format!("{} = {}", name, value))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+        }
+        _ => return Ok(None),
+    }
+
+    Ok(Some(res))
 }
 
 #[cfg(test)]
@@ -103,4 +278,130 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_record() -> Result<()> {
+        let data = concat!(
+            "black.py,sha256=aaaa,1024\n",
+            "\"dir/with,comma.txt\",sha256=bbbb,12\n",
+            "black-19.10b0.dist-info/RECORD,,\n",
+        )
+        .as_bytes();
+
+        assert_eq!(
+            parse_record(data)?,
+            vec![
+                RecordEntry {
+                    path: "black.py".to_string(),
+                    hash: Some("sha256=aaaa".to_string()),
+                    size: Some(1024),
+                },
+                RecordEntry {
+                    path: "dir/with,comma.txt".to_string(),
+                    hash: Some("sha256=bbbb".to_string()),
+                    size: Some(12),
+                },
+                RecordEntry {
+                    path: "black-19.10b0.dist-info/RECORD".to_string(),
+                    hash: None,
+                    size: None,
+                },
+            ]
+        );
+
+        assert!(parse_record(b"foo.py,sha256=aaaa\n").is_err());
+        assert!(parse_record(b"\"foo.py,sha256=aaaa,1\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_entry_points() -> Result<()> {
+        let data = concat!(
+            "# comment\n",
+            "[console_scripts]\n",
+            "black = black:patched_main\n",
+            "blackd = blackd:patched_main [d]\n",
+            "\n",
+            "[pygments.lexers]\n",
+            "foo=foo.lexer:FooLexer\n",
+        )
+        .as_bytes();
+
+        let entry_points = parse_entry_points(data)?;
+        assert_eq!(entry_points.len(), 2);
+        assert_eq!(
+            entry_points["console_scripts"],
+            vec![
+                ("black".to_string(), "black:patched_main".to_string()),
+                ("blackd".to_string(), "blackd:patched_main [d]".to_string()),
+            ]
+        );
+        assert_eq!(
+            entry_points["pygments.lexers"],
+            vec![("foo".to_string(), "foo.lexer:FooLexer".to_string())]
+        );
+
+        assert!(parse_entry_points(b"foo = bar\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dist_info_resource_metadata() -> Result<()> {
+        let metadata = dist_info_resource_metadata(
+            "METADATA",
+            concat!(
+                "Metadata-Version: 2.1\n",
+                "Name: black\n",
+                "Version: 19.10b0\n",
+                "License: MIT\n",
+                "Classifier: Environment :: Console\n",
+                "Classifier: License :: OSI Approved :: MIT License\n",
+                "Requires-Dist: click (>=6.5)\n",
+                "Requires-Dist: appdirs\n",
+            )
+            .as_bytes(),
+        )?
+        .unwrap();
+
+        assert_eq!(
+            metadata.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("dist-info.license".to_string(), "MIT".to_string()),
+                (
+                    "dist-info.license-classifiers".to_string(),
+                    "License :: OSI Approved :: MIT License".to_string()
+                ),
+                ("dist-info.name".to_string(), "black".to_string()),
+                (
+                    "dist-info.requires-dist".to_string(),
+                    "click (>=6.5)\nappdirs".to_string()
+                ),
+                ("dist-info.version".to_string(), "19.10b0".to_string()),
+            ]
+        );
+
+        let metadata = dist_info_resource_metadata(
+            "entry_points.txt",
+            b"[console_scripts]\nblack = black:main\nblackd = blackd:main\n",
+        )?
+        .unwrap();
+        assert_eq!(
+            metadata.get("dist-info.entry-points.console_scripts"),
+            Some(&"black = black:main\nblackd = blackd:main".to_string())
+        );
+
+        let metadata =
+            dist_info_resource_metadata("RECORD", b"black.py,sha256=aaaa,1\nblack.pyi,,\n")?
+                .unwrap();
+        assert_eq!(
+            metadata.get("dist-info.files"),
+            Some(&"black.py\nblack.pyi".to_string())
+        );
+
+        assert!(dist_info_resource_metadata("WHEEL", b"Wheel-Version: 1.0\n")?.is_none());
+
+        Ok(())
+    }
 }
@@ -17,8 +17,13 @@ use {
         resource::{PythonExtensionModule, PythonExtensionModuleVariants, PythonResource},
         resource_collection::PythonResourceAddCollectionContext,
     },
-    anyhow::Result,
-    std::collections::{HashMap, HashSet},
+    anyhow::{anyhow, Result},
+    regex::Regex,
+    simple_file_manifest::FileData,
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        path::{Path, PathBuf},
+    },
 };
 
 /// Denotes methods to filter extension modules.
@@ -92,6 +97,125 @@ impl AsRef<str> for ResourceHandlingMode {
     }
 }
 
+/// What happens to resources matching a [ResourceLocationRule].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResourceLocationAction {
+    /// Resources are placed in a location.
+    Location(ConcreteResourceLocation),
+
+    /// Resources are excluded.
+    Exclude,
+}
+
+impl TryFrom<&str> for ResourceLocationAction {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if value == "exclude" {
+            Ok(Self::Exclude)
+        } else {
+            Ok(Self::Location(ConcreteResourceLocation::try_from(value)?))
+        }
+    }
+}
+
+impl std::fmt::Display for ResourceLocationAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Location(location) => f.write_str(&location.to_string()),
+            Self::Exclude => f.write_str("exclude"),
+        }
+    }
+}
+
+/// A rule defining the location of resources whose name matches a glob pattern.
+///
+/// In patterns, `*` matches any sequence of characters and `?` matches a
+/// single character. A pattern ending in `.*` also matches the name before
+/// it. So `foo.*` matches the `foo` package and everything in it.
+///
+/// Modules and extension modules are matched by their name, package
+/// resources by the name of their package, distribution resources by the
+/// name of their `.dist-info` or `.egg-info` directory, and files by their
+/// path.
+#[derive(Clone, Debug)]
+pub struct ResourceLocationRule {
+    pattern: String,
+    regex: Regex,
+    action: ResourceLocationAction,
+}
+
+impl PartialEq for ResourceLocationRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern && self.action == other.action
+    }
+}
+
+impl ResourceLocationRule {
+    /// Construct an instance from a glob pattern.
+    pub fn new(pattern: &str, action: ResourceLocationAction) -> Result<Self> {
+        if pattern.is_empty() {
+            return Err(anyhow!("resource location rule pattern cannot be empty"));
+        }
+
+        let (stem, package) = match pattern.strip_suffix(".*") {
+            Some(stem) => (stem, true),
+            None => (pattern, false),
+        };
+
+        let mut expression = "^".to_string();
+        for c in stem.chars() {
+            match c {
+                '*' => expression.push_str(".*"),
+                '?' => expression.push('.'),
+                c => expression.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        if package {
+            expression.push_str(r"(\..*)?");
+        }
+        expression.push('$');
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&expression)?,
+            action,
+        })
+    }
+
+    /// The glob pattern resource names are matched against.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// What happens to matching resources.
+    pub fn action(&self) -> &ResourceLocationAction {
+        &self.action
+    }
+
+    /// Whether a resource matches this rule.
+    pub fn matches(&self, resource: &PythonResource) -> bool {
+        let name = match resource {
+            PythonResource::ModuleSource(module) => module.name.clone(),
+            PythonResource::ModuleBytecodeRequest(module) => module.name.clone(),
+            PythonResource::ModuleBytecode(module) => module.name.clone(),
+            PythonResource::PackageResource(resource) => resource.leaf_package.clone(),
+            PythonResource::PackageDistributionResource(resource) => {
+                match resource.resolve_path("").parent() {
+                    Some(path) => path.display().to_string(),
+                    None => return false,
+                }
+            }
+            PythonResource::ExtensionModule(module) => module.name.clone(),
+            PythonResource::EggFile(_) => return false,
+            PythonResource::PathExtension(_) => return false,
+            PythonResource::File(file) => file.path_string(),
+        };
+
+        self.regex.is_match(&name)
+    }
+}
+
 /// Defines how Python resources should be packaged.
 #[derive(Clone, Debug, PartialEq)]
 pub struct PythonPackagingPolicy {
@@ -107,6 +231,25 @@ pub struct PythonPackagingPolicy {
     /// Optional fallback location for resources should `resources_location` fail.
     resources_location_fallback: Option<ConcreteResourceLocation>,
 
+    /// Rules overriding `resources_location` for resources matching patterns.
+    ///
+    /// The first matching rule applies.
+    resource_location_rules: Vec<ResourceLocationRule>,
+
+    /// Whether to relocate packages that don't work when imported from memory.
+    ///
+    /// If true, resources of packages detected as not working when imported
+    /// from memory are placed on the filesystem instead of in memory. This
+    /// requires a filesystem `resources_location_fallback`.
+    relocate_in_memory_incompatible: bool,
+
+    /// Maximum size in bytes of resource data to store in memory.
+    ///
+    /// Package resources, distribution resources, and files larger than this
+    /// are placed in `resources_location_fallback` instead of an in-memory
+    /// `resources_location`.
+    resources_in_memory_size_limit: Option<u64>,
+
     /// Whether to allow in-memory shared library loading.
     ///
     /// If true, we will attempt to load Python extension modules
@@ -178,6 +321,49 @@ pub struct PythonPackagingPolicy {
 
     /// Python modules for which bytecode should not be generated by default.
     no_bytecode_modules: HashSet<String>,
+
+    /// zstandard compression level for in-memory data in packed resources.
+    ///
+    /// If `None`, in-memory data is not compressed.
+    packed_resources_zstd_level: Option<i32>,
+
+    /// Path to a file holding an ed25519 secret key to sign packed resources with.
+    ///
+    /// If `None`, packed resources are not signed.
+    packed_resources_signing_key_path: Option<PathBuf>,
+
+    /// Path to a file holding a 32 byte key to encrypt packed resources with.
+    ///
+    /// If `None`, packed resources are not encrypted.
+    packed_resources_encryption_key_path: Option<PathBuf>,
+
+    /// Whether pip operations must only install hash-pinned artifacts.
+    ///
+    /// If true, pip runs in `--require-hashes` mode and every artifact it
+    /// downloads or installs must have a hash pinned via a `--hash` option.
+    pip_require_hashes: bool,
+
+    /// Whether pip builds source distributions in PEP 517 isolated environments.
+    ///
+    /// If true, pip always builds with the build backend and build requirements
+    /// declared by a source distribution, installed into an isolated environment
+    /// of the packaging Python distribution.
+    pip_build_isolation: bool,
+
+    /// URL of the package index pip operations use instead of PyPI.
+    pip_index_url: Option<String>,
+
+    /// URLs of package indexes pip operations use in addition to the main index.
+    pip_extra_index_urls: Vec<String>,
+
+    /// Hosts pip operations trust even if they don't have valid HTTPS.
+    pip_trusted_hosts: Vec<String>,
+
+    /// Proxy pip operations connect through, in the form `[user:passwd@]proxy.server:port`.
+    pip_proxy: Option<String>,
+
+    /// Maximum number of times pip retries each connection.
+    pip_retries: Option<u32>,
 }
 
 impl Default for PythonPackagingPolicy {
@@ -187,6 +373,9 @@ impl Default for PythonPackagingPolicy {
             preferred_extension_module_variants: HashMap::new(),
             resources_location: ConcreteResourceLocation::InMemory,
             resources_location_fallback: None,
+            resource_location_rules: vec![],
+            relocate_in_memory_incompatible: true,
+            resources_in_memory_size_limit: None,
             allow_in_memory_shared_library_loading: false,
             allow_files: false,
             file_scanner_emit_files: false,
@@ -202,6 +391,16 @@ impl Default for PythonPackagingPolicy {
             bytecode_optimize_level_one: false,
             bytecode_optimize_level_two: false,
             no_bytecode_modules: HashSet::new(),
+            packed_resources_zstd_level: None,
+            packed_resources_signing_key_path: None,
+            packed_resources_encryption_key_path: None,
+            pip_require_hashes: false,
+            pip_build_isolation: false,
+            pip_index_url: None,
+            pip_extra_index_urls: vec![],
+            pip_trusted_hosts: vec![],
+            pip_proxy: None,
+            pip_retries: None,
         }
     }
 }
@@ -253,6 +452,50 @@ impl PythonPackagingPolicy {
         self.resources_location_fallback = location;
     }
 
+    /// Obtain rules defining the location of resources matching patterns.
+    pub fn resource_location_rules(&self) -> &[ResourceLocationRule] {
+        &self.resource_location_rules
+    }
+
+    /// Add a rule defining the location of resources matching a pattern.
+    ///
+    /// Rules are consulted in the order they are added.
+    pub fn add_resource_location_rule(&mut self, rule: ResourceLocationRule) {
+        self.resource_location_rules.push(rule);
+    }
+
+    /// Whether packages that don't work when imported from memory are relocated.
+    pub fn relocate_in_memory_incompatible(&self) -> bool {
+        self.relocate_in_memory_incompatible
+    }
+
+    /// Set whether packages that don't work when imported from memory are relocated.
+    pub fn set_relocate_in_memory_incompatible(&mut self, value: bool) {
+        self.relocate_in_memory_incompatible = value;
+    }
+
+    /// Obtain the location packages that don't work when imported from memory are relocated to.
+    ///
+    /// This is `resources_location_fallback` if it is on the filesystem. Without
+    /// a filesystem fallback, packages aren't relocated, so policies only
+    /// allowing in-memory resources stay that way.
+    pub fn in_memory_incompatible_location(&self) -> Option<ConcreteResourceLocation> {
+        match &self.resources_location_fallback {
+            Some(location @ ConcreteResourceLocation::RelativePath(_)) => Some(location.clone()),
+            _ => None,
+        }
+    }
+
+    /// Obtain the maximum size of resource data to store in memory.
+    pub fn resources_in_memory_size_limit(&self) -> Option<u64> {
+        self.resources_in_memory_size_limit
+    }
+
+    /// Set the maximum size of resource data to store in memory.
+    pub fn set_resources_in_memory_size_limit(&mut self, limit: Option<u64>) {
+        self.resources_in_memory_size_limit = limit;
+    }
+
     /// Whether to allow untyped `File` resources.
     pub fn allow_files(&self) -> bool {
         self.allow_files
@@ -383,6 +626,114 @@ impl PythonPackagingPolicy {
         self.bytecode_optimize_level_two = value;
     }
 
+    /// zstandard compression level for in-memory data in packed resources.
+    pub fn packed_resources_zstd_level(&self) -> Option<i32> {
+        self.packed_resources_zstd_level
+    }
+
+    /// Set the zstandard compression level for in-memory data in packed resources.
+    ///
+    /// `None` disables compression.
+    pub fn set_packed_resources_zstd_level(&mut self, level: Option<i32>) {
+        self.packed_resources_zstd_level = level;
+    }
+
+    /// Path to a file holding an ed25519 secret key to sign packed resources with.
+    pub fn packed_resources_signing_key_path(&self) -> Option<&Path> {
+        self.packed_resources_signing_key_path.as_deref()
+    }
+
+    /// Set the path to a file holding an ed25519 secret key to sign packed resources with.
+    ///
+    /// `None` disables signing.
+    pub fn set_packed_resources_signing_key_path(&mut self, path: Option<PathBuf>) {
+        self.packed_resources_signing_key_path = path;
+    }
+
+    /// Path to a file holding a 32 byte key to encrypt packed resources with.
+    pub fn packed_resources_encryption_key_path(&self) -> Option<&Path> {
+        self.packed_resources_encryption_key_path.as_deref()
+    }
+
+    /// Set the path to a file holding a 32 byte key to encrypt packed resources with.
+    ///
+    /// `None` disables encryption.
+    pub fn set_packed_resources_encryption_key_path(&mut self, path: Option<PathBuf>) {
+        self.packed_resources_encryption_key_path = path;
+    }
+
+    /// Whether pip operations must only install hash-pinned artifacts.
+    pub fn pip_require_hashes(&self) -> bool {
+        self.pip_require_hashes
+    }
+
+    /// Set whether pip operations must only install hash-pinned artifacts.
+    pub fn set_pip_require_hashes(&mut self, value: bool) {
+        self.pip_require_hashes = value;
+    }
+
+    /// Whether pip builds source distributions in PEP 517 isolated environments.
+    pub fn pip_build_isolation(&self) -> bool {
+        self.pip_build_isolation
+    }
+
+    /// Set whether pip builds source distributions in PEP 517 isolated environments.
+    pub fn set_pip_build_isolation(&mut self, value: bool) {
+        self.pip_build_isolation = value;
+    }
+
+    /// URL of the package index pip operations use instead of PyPI.
+    pub fn pip_index_url(&self) -> Option<&str> {
+        self.pip_index_url.as_deref()
+    }
+
+    /// Set the URL of the package index pip operations use instead of PyPI.
+    pub fn set_pip_index_url(&mut self, value: Option<String>) {
+        self.pip_index_url = value;
+    }
+
+    /// URLs of package indexes pip operations use in addition to the main index.
+    pub fn pip_extra_index_urls(&self) -> &[String] {
+        &self.pip_extra_index_urls
+    }
+
+    /// Set the URLs of package indexes pip operations use in addition to the main index.
+    pub fn set_pip_extra_index_urls(&mut self, value: Vec<String>) {
+        self.pip_extra_index_urls = value;
+    }
+
+    /// Hosts pip operations trust even if they don't have valid HTTPS.
+    pub fn pip_trusted_hosts(&self) -> &[String] {
+        &self.pip_trusted_hosts
+    }
+
+    /// Set the hosts pip operations trust even if they don't have valid HTTPS.
+    pub fn set_pip_trusted_hosts(&mut self, value: Vec<String>) {
+        self.pip_trusted_hosts = value;
+    }
+
+    /// Proxy pip operations connect through.
+    pub fn pip_proxy(&self) -> Option<&str> {
+        self.pip_proxy.as_deref()
+    }
+
+    /// Set the proxy pip operations connect through.
+    pub fn set_pip_proxy(&mut self, value: Option<String>) {
+        self.pip_proxy = value;
+    }
+
+    /// Maximum number of times pip retries each connection.
+    pub fn pip_retries(&self) -> Option<u32> {
+        self.pip_retries
+    }
+
+    /// Set the maximum number of times pip retries each connection.
+    ///
+    /// `None` uses pip's default.
+    pub fn set_pip_retries(&mut self, value: Option<u32>) {
+        self.pip_retries = value;
+    }
+
     /// Set the resource handling mode of the policy.
     ///
     /// This is a convenience function for mapping a `ResourceHandlingMode`
@@ -443,7 +794,31 @@ impl PythonPackagingPolicy {
         &self,
         resource: &PythonResource,
     ) -> PythonResourceAddCollectionContext {
-        let include = self.filter_python_resource(resource);
+        self.derive_add_collection_context_relocating(resource, &BTreeMap::new())
+    }
+
+    /// Derive a `PythonResourceAddCollectionContext` for a resource, relocating incompatible packages.
+    ///
+    /// `in_memory_incompatible` is a mapping of top-level package names to
+    /// reasons they don't work when imported from memory, as obtained by
+    /// [crate::in_memory_compatibility::find_in_memory_incompatible_packages].
+    /// Modules, package resources, and extension modules of these packages
+    /// that would be placed in memory are placed in
+    /// [Self::in_memory_incompatible_location] instead, unless
+    /// relocation is disabled or a resource location rule applies to them.
+    pub fn derive_add_collection_context_relocating(
+        &self,
+        resource: &PythonResource,
+        in_memory_incompatible: &BTreeMap<String, String>,
+    ) -> PythonResourceAddCollectionContext {
+        let rule_action = self
+            .resource_location_rules
+            .iter()
+            .find(|rule| rule.matches(resource))
+            .map(|rule| rule.action());
+
+        let include = self.filter_python_resource(resource)
+            && !matches!(rule_action, Some(ResourceLocationAction::Exclude));
 
         let store_source = match resource {
             PythonResource::ModuleSource(ref module) => {
@@ -456,8 +831,51 @@ impl PythonPackagingPolicy {
             _ => false,
         };
 
-        let location = self.resources_location.clone();
-        let location_fallback = self.resources_location_fallback.clone();
+        let oversized = match self.resources_in_memory_size_limit {
+            Some(limit) => matches!(resource_data_size(resource), Some(size) if size > limit),
+            None => false,
+        };
+
+        let resources_location = match rule_action {
+            Some(ResourceLocationAction::Location(location)) => location,
+            _ => &self.resources_location,
+        };
+
+        let (location, location_fallback) =
+            match (resources_location, &self.resources_location_fallback) {
+                (ConcreteResourceLocation::InMemory, Some(fallback)) if oversized => {
+                    (fallback.clone(), None)
+                }
+                _ => (
+                    resources_location.clone(),
+                    self.resources_location_fallback.clone(),
+                ),
+            };
+
+        let package = match resource {
+            PythonResource::ModuleSource(module) => Some(&module.name),
+            PythonResource::PackageResource(resource) => Some(&resource.leaf_package),
+            PythonResource::ExtensionModule(module) => Some(&module.name),
+            _ => None,
+        }
+        .and_then(|name| name.split('.').next());
+
+        let relocated = match (package, self.in_memory_incompatible_location()) {
+            (Some(package), Some(relocated))
+                if self.relocate_in_memory_incompatible
+                    && rule_action.is_none()
+                    && location == ConcreteResourceLocation::InMemory
+                    && in_memory_incompatible.contains_key(package) =>
+            {
+                Some(relocated)
+            }
+            _ => None,
+        };
+
+        let (location, location_fallback) = match relocated {
+            Some(relocated) => (relocated, None),
+            None => (location, location_fallback),
+        };
 
         let optimize_level_zero = match resource {
             PythonResource::ModuleSource(module) => {
@@ -498,6 +916,7 @@ impl PythonPackagingPolicy {
             optimize_level_zero,
             optimize_level_one,
             optimize_level_two,
+            metadata: BTreeMap::new(),
         }
     }
 
@@ -681,6 +1100,21 @@ impl PythonPackagingPolicy {
     }
 }
 
+/// Obtain the size of the data backing a resource that is subject to size limits.
+fn resource_data_size(resource: &PythonResource) -> Option<u64> {
+    let data = match resource {
+        PythonResource::PackageResource(resource) => &resource.data,
+        PythonResource::PackageDistributionResource(resource) => &resource.data,
+        PythonResource::File(file) => file.entry().file_data(),
+        _ => return None,
+    };
+
+    match data {
+        FileData::Memory(data) => Some(data.len() as u64),
+        FileData::Path(path) => std::fs::metadata(path).ok().map(|m| m.len()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {super::*, simple_file_manifest::File};
@@ -703,4 +1137,179 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_add_collection_context_size_limit() -> Result<()> {
+        let mut policy = PythonPackagingPolicy {
+            resources_location_fallback: Some(ConcreteResourceLocation::RelativePath(
+                "lib".to_string(),
+            )),
+            resources_in_memory_size_limit: Some(2),
+            ..Default::default()
+        };
+
+        let small = File::new("foo.dat", vec![42, 42]);
+        let large = File::new("bar.dat", vec![42, 42, 42]);
+
+        let add_context = policy.derive_add_collection_context(&small.clone().into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        let add_context = policy.derive_add_collection_context(&large.clone().into());
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+        assert_eq!(add_context.location_fallback, None);
+
+        // Modules aren't subject to the limit.
+        let module = PythonResource::ModuleSource(std::borrow::Cow::Owned(
+            crate::resource::PythonModuleSource {
+                name: "foo".to_string(),
+                source: FileData::Memory(vec![42; 16]),
+                is_package: false,
+                cache_tag: "cpython-39".to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+        ));
+        let add_context = policy.derive_add_collection_context(&module);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        // The limit only applies to an in-memory primary location.
+        policy.resources_location = ConcreteResourceLocation::RelativePath("prefix".to_string());
+        let add_context = policy.derive_add_collection_context(&large.into());
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("prefix".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_collection_context_relocating() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        let module = |name: &str| {
+            PythonResource::ModuleSource(std::borrow::Cow::Owned(
+                crate::resource::PythonModuleSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(vec![]),
+                    is_package: false,
+                    cache_tag: "cpython-39".to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+            ))
+        };
+
+        let mut incompatible = BTreeMap::new();
+        incompatible.insert("foo".to_string(), "reason".to_string());
+
+        // Nothing is relocated without a filesystem fallback location.
+        let add_context =
+            policy.derive_add_collection_context_relocating(&module("foo.bar"), &incompatible);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        policy.resources_location_fallback = Some(ConcreteResourceLocation::InMemory);
+        let add_context =
+            policy.derive_add_collection_context_relocating(&module("foo.bar"), &incompatible);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        policy.resources_location_fallback =
+            Some(ConcreteResourceLocation::RelativePath("prefix".to_string()));
+        let add_context =
+            policy.derive_add_collection_context_relocating(&module("foo.bar"), &incompatible);
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("prefix".to_string())
+        );
+        assert_eq!(add_context.location_fallback, None);
+
+        let add_context =
+            policy.derive_add_collection_context_relocating(&module("bar"), &incompatible);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        // Resource location rules take precedence.
+        policy.add_resource_location_rule(ResourceLocationRule::new(
+            "foo.*",
+            ResourceLocationAction::Location(ConcreteResourceLocation::InMemory),
+        )?);
+        let add_context =
+            policy.derive_add_collection_context_relocating(&module("foo"), &incompatible);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        policy.resource_location_rules.clear();
+        policy.set_relocate_in_memory_incompatible(false);
+        let add_context =
+            policy.derive_add_collection_context_relocating(&module("foo"), &incompatible);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_collection_context_location_rules() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.add_resource_location_rule(ResourceLocationRule::new(
+            "numpy.*",
+            ResourceLocationAction::try_from("filesystem-relative:lib").unwrap(),
+        )?);
+        policy.add_resource_location_rule(ResourceLocationRule::new(
+            "*.dist-info",
+            ResourceLocationAction::Exclude,
+        )?);
+        policy.add_resource_location_rule(ResourceLocationRule::new(
+            "*",
+            ResourceLocationAction::Location(ConcreteResourceLocation::InMemory),
+        )?);
+
+        let module = |name: &str| {
+            PythonResource::ModuleSource(std::borrow::Cow::Owned(
+                crate::resource::PythonModuleSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(vec![]),
+                    is_package: false,
+                    cache_tag: "cpython-39".to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+            ))
+        };
+
+        for name in ["numpy", "numpy.core", "numpy.core.multiarray"] {
+            let add_context = policy.derive_add_collection_context(&module(name));
+            assert!(add_context.include);
+            assert_eq!(
+                add_context.location,
+                ConcreteResourceLocation::RelativePath("lib".to_string())
+            );
+        }
+
+        for name in ["numpyx", "myapp.numpy"] {
+            let add_context = policy.derive_add_collection_context(&module(name));
+            assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+        }
+
+        let distribution_resource = PythonResource::PackageDistributionResource(
+            std::borrow::Cow::Owned(crate::resource::PythonPackageDistributionResource {
+                location: crate::resource::PythonPackageDistributionResourceFlavor::DistInfo,
+                package: "numpy".to_string(),
+                version: "1.0".to_string(),
+                name: "METADATA".to_string(),
+                data: FileData::Memory(vec![]),
+            }),
+        );
+        assert!(!policy.resource_location_rules()[0].matches(&distribution_resource));
+        assert!(
+            !policy
+                .derive_add_collection_context(&distribution_resource)
+                .include
+        );
+
+        assert!(ResourceLocationRule::new("", ResourceLocationAction::Exclude).is_err());
+        assert!(ResourceLocationAction::try_from("invalid").is_err());
+
+        Ok(())
+    }
 }
@@ -34,11 +34,8 @@ pub fn python_source_encoding(source: &[u8]) -> Vec<u8> {
     b"utf-8".to_vec()
 }
 
-/// Whether __file__ occurs in Python source code.
-pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
-    // We can't just look for b"__file__ because the source file may be in
-    // encodings like UTF-16. So we need to decode to Unicode first then look for
-    // the code points.
+/// Decode Python source code to Unicode using its source encoding.
+pub fn decode_python_source(source: &[u8]) -> String {
     let encoding = python_source_encoding(source);
 
     let encoder = match encoding_rs::Encoding::for_label(&encoding) {
@@ -48,5 +45,13 @@ pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
 
     let (source, ..) = encoder.decode(source);
 
-    Ok(source.contains("__file__"))
+    source.into_owned()
+}
+
+/// Whether __file__ occurs in Python source code.
+pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
+    // We can't just look for b"__file__ because the source file may be in
+    // encodings like UTF-16. So we need to decode to Unicode first then look for
+    // the code points.
+    Ok(decode_python_source(source).contains("__file__"))
 }
@@ -17,11 +17,13 @@ use {
         licensing::{LicensedComponent, LicensedComponents},
         location::{AbstractResourceLocation, ConcreteResourceLocation},
         module_util::{packages_from_module_name, resolve_path_for_module},
+        package_metadata::dist_info_resource_metadata,
         python_source::has_dunder_file,
         resource::{
             BytecodeOptimizationLevel, PythonExtensionModule, PythonModuleBytecode,
             PythonModuleBytecodeFromSource, PythonModuleSource, PythonPackageDistributionResource,
-            PythonPackageResource, PythonResource, SharedLibrary,
+            PythonPackageDistributionResourceFlavor, PythonPackageResource, PythonResource,
+            SharedLibrary,
         },
     },
     anyhow::{anyhow, Context, Result},
@@ -89,6 +91,7 @@ pub struct PrePackagedResource {
     pub file_executable: bool,
     pub file_data_embedded: Option<FileData>,
     pub file_data_utf8_relative_path: Option<(PathBuf, FileData)>,
+    pub metadata: Option<BTreeMap<String, String>>,
 }
 
 impl PrePackagedResource {
@@ -402,6 +405,15 @@ impl PrePackagedResource {
             } else {
                 None
             },
+            in_memory_zstd_compressed: false,
+            in_memory_encrypted: false,
+            in_memory_sha256: None,
+            metadata: self.metadata.as_ref().map(|metadata| {
+                metadata
+                    .iter()
+                    .map(|(k, v)| (Cow::Owned(k.clone()), Cow::Owned(v.clone())))
+                    .collect()
+            }),
         };
 
         if let Some((prefix, filename, location)) = &self.relative_path_shared_library {
@@ -425,6 +437,11 @@ impl PrePackagedResource {
 /// a particular field, we populate that field in all its parent
 /// packages. If a corresponding fields is already populated, we
 /// copy its data as well.
+///
+/// Parents that aren't modules themselves (e.g. directories without an
+/// `__init__.py`) are marked as PEP 420 namespace packages. They still
+/// receive empty module data so importers not aware of namespace packages
+/// can import them as regular packages.
 pub fn populate_parent_packages(
     resources: &mut BTreeMap<String, PrePackagedResource>,
 ) -> Result<()> {
@@ -448,6 +465,10 @@ pub fn populate_parent_packages(
                     ..PrePackagedResource::default()
                 });
 
+            if !entry.is_module {
+                entry.is_namespace_package = true;
+            }
+
             // Parents must be modules + packages by definition.
             entry.is_module = true;
             entry.is_package = true;
@@ -584,6 +605,12 @@ pub struct PythonResourceAddCollectionContext {
 
     /// Whether to store Python bytecode for optimization level 2.
     pub optimize_level_two: bool,
+
+    /// Arbitrary key-value metadata to attach to the resource.
+    ///
+    /// Metadata is stored in packed resources data and is available to
+    /// the application at run-time.
+    pub metadata: BTreeMap<String, String>,
 }
 
 impl PythonResourceAddCollectionContext {
@@ -596,6 +623,7 @@ impl PythonResourceAddCollectionContext {
         self.optimize_level_zero = other.optimize_level_zero;
         self.optimize_level_one = other.optimize_level_one;
         self.optimize_level_two = other.optimize_level_two;
+        self.metadata = other.metadata.clone();
     }
 }
 
@@ -642,20 +670,84 @@ pub struct CompiledResourcesCollection<'a> {
 
     /// Extra file installs that must be performed so referenced files are available.
     pub extra_files: Vec<FileInstall>,
+
+    /// ed25519 secret key to sign written packed resources data with.
+    pub signing_key: Option<[u8; 32]>,
 }
 
 impl<'a> CompiledResourcesCollection<'a> {
     /// Write resources to packed resources data, version 1.
+    ///
+    /// If a signing key is set, the data is followed by its signature.
+    ///
+    /// The data has a name index of resources, allowing the importer to find
+    /// resources without hashing all their names at startup.
     pub fn write_packed_resources<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
-        python_packed_resources::write_packed_resources_v3(
-            &self
-                .resources
-                .values()
-                .cloned()
-                .collect::<Vec<Resource<'a, u8>>>(),
-            writer,
-            None,
-        )
+        let resources = self
+            .resources
+            .values()
+            .cloned()
+            .collect::<Vec<Resource<'a, u8>>>();
+
+        let options = python_packed_resources::WriteOptions {
+            name_index: true,
+            ..Default::default()
+        };
+
+        if let Some(key) = &self.signing_key {
+            let mut data = vec![];
+            python_packed_resources::write_packed_resources_with_options(
+                &resources, &mut data, &options,
+            )?;
+            writer.write_all(&python_packed_resources::sign_packed_resources(&data, key)?)?;
+
+            Ok(())
+        } else {
+            python_packed_resources::write_packed_resources_with_options(
+                &resources, writer, &options,
+            )
+        }
+    }
+
+    /// Compress in-memory data of all resources with zstandard.
+    ///
+    /// Compressed data is decompressed by the importer when it is first
+    /// accessed.
+    pub fn zstd_compress(&mut self, level: i32) -> Result<()> {
+        for (name, resource) in std::mem::take(&mut self.resources) {
+            let resource = resource
+                .zstd_compress(level)
+                .with_context(|| format!("compressing {}", name))?;
+            self.resources.insert(name, resource);
+        }
+
+        Ok(())
+    }
+
+    /// Encrypt in-memory data of all resources with a 32 byte key.
+    ///
+    /// Encrypted data is decrypted by the importer when it is first accessed,
+    /// using a key supplied at run-time. This should be called after
+    /// [Self::zstd_compress()], as encrypted data doesn't compress.
+    pub fn encrypt(&mut self, key: &[u8; 32]) -> Result<()> {
+        for (name, resource) in std::mem::take(&mut self.resources) {
+            let resource = resource
+                .encrypt(key)
+                .with_context(|| format!("encrypting {}", name))?;
+            self.resources.insert(name, resource);
+        }
+
+        Ok(())
+    }
+
+    /// Record SHA-256 digests of in-memory data of all resources.
+    ///
+    /// Digests cover data as stored. So this should be called after
+    /// [Self::zstd_compress()] and [Self::encrypt()].
+    pub fn add_sha256_digests(&mut self) {
+        for resource in self.resources.values_mut() {
+            *resource = std::mem::take(resource).with_in_memory_sha256();
+        }
     }
 
     /// Convert the file installs to a [FileManifest].
@@ -778,6 +870,33 @@ impl PythonResourceCollector {
         }
     }
 
+    /// Attach metadata from an add context to a resource that was added.
+    fn add_metadata_from_context(
+        &mut self,
+        name: &str,
+        add_context: &PythonResourceAddCollectionContext,
+        actions: &[AddResourceAction],
+    ) {
+        if add_context.metadata.is_empty()
+            || !actions.iter().any(|action| {
+                matches!(
+                    action,
+                    AddResourceAction::Added(..)
+                        | AddResourceAction::AddedBuiltinExtensionModule(_)
+                )
+            })
+        {
+            return;
+        }
+
+        if let Some(entry) = self.resources.get_mut(name) {
+            entry
+                .metadata
+                .get_or_insert_with(BTreeMap::new)
+                .extend(add_context.metadata.clone());
+        }
+    }
+
     /// Apply a filter function on resources in this collection and mutate in place.
     ///
     /// If the filter function returns true, the item will be preserved.
@@ -921,6 +1040,8 @@ impl PythonResourceCollector {
             );
         }
 
+        self.add_metadata_from_context(&module.name, add_context, &actions);
+
         Ok(actions)
     }
 
@@ -997,7 +1118,7 @@ impl PythonResourceCollector {
             return Ok(vec![AddResourceAction::NoInclude(module.description())]);
         }
 
-        match module.optimize_level {
+        let actions = match module.optimize_level {
             BytecodeOptimizationLevel::Zero => {
                 if add_context.optimize_level_zero {
                     self.add_python_resource_with_locations(
@@ -1037,7 +1158,11 @@ impl PythonResourceCollector {
                     )])
                 }
             }
-        }
+        }?;
+
+        self.add_metadata_from_context(&module.name, add_context, &actions);
+
+        Ok(actions)
     }
 
     /// Add Python module bytecode derived from source code to the collection.
@@ -1112,7 +1237,7 @@ impl PythonResourceCollector {
             return Ok(vec![AddResourceAction::NoInclude(module.description())]);
         }
 
-        match module.optimize_level {
+        let actions = match module.optimize_level {
             BytecodeOptimizationLevel::Zero => {
                 if add_context.optimize_level_zero {
                     self.add_python_resource_with_locations(
@@ -1152,7 +1277,11 @@ impl PythonResourceCollector {
                     )])
                 }
             }
-        }
+        }?;
+
+        self.add_metadata_from_context(&module.name, add_context, &actions);
+
+        Ok(actions)
     }
 
     /// Add resource data to a given location.
@@ -1223,11 +1352,15 @@ impl PythonResourceCollector {
             return Ok(vec![AddResourceAction::NoInclude(resource.description())]);
         }
 
-        self.add_python_resource_with_locations(
+        let actions = self.add_python_resource_with_locations(
             &resource.into(),
             &add_context.location,
             &add_context.location_fallback,
-        )
+        )?;
+
+        self.add_metadata_from_context(&resource.leaf_package, add_context, &actions);
+
+        Ok(actions)
     }
 
     /// Add a Python package distribution resource to a given location.
@@ -1278,6 +1411,24 @@ impl PythonResourceCollector {
             }
         }
 
+        // Recognized metadata files are also recorded as resource metadata so
+        // they are available without parsing distribution resources. Files
+        // that fail to parse are only stored as distribution resources.
+        if resource.location == PythonPackageDistributionResourceFlavor::DistInfo {
+            if let Some(metadata) = resource
+                .data
+                .resolve_content()
+                .ok()
+                .and_then(|data| dist_info_resource_metadata(&resource.name, &data).ok())
+                .flatten()
+            {
+                entry
+                    .metadata
+                    .get_or_insert_with(BTreeMap::new)
+                    .extend(metadata);
+            }
+        }
+
         Ok(vec![AddResourceAction::Added(
             resource.description(),
             location.clone(),
@@ -1297,11 +1448,15 @@ impl PythonResourceCollector {
             return Ok(vec![AddResourceAction::NoInclude(resource.description())]);
         }
 
-        self.add_python_resource_with_locations(
+        let actions = self.add_python_resource_with_locations(
             &resource.into(),
             &add_context.location,
             &add_context.location_fallback,
-        )
+        )?;
+
+        self.add_metadata_from_context(&resource.package, add_context, &actions);
+
+        Ok(actions)
     }
 
     /// Add a Python extension module using an add context.
@@ -1445,6 +1600,7 @@ impl PythonResourceCollector {
             }
 
             let actions = self.add_builtin_python_extension_module(extension_module)?;
+            self.add_metadata_from_context(&extension_module.name, add_context, &actions);
 
             Ok((actions, Some(build_context)))
         } else {
@@ -1462,6 +1618,7 @@ impl PythonResourceCollector {
             };
 
             let actions = self.add_python_extension_module(extension_module, &location)?;
+            self.add_metadata_from_context(&extension_module.name, add_context, &actions);
 
             Ok((actions, None))
         }
@@ -1665,6 +1822,50 @@ impl PythonResourceCollector {
         )])
     }
 
+    /// Add a data file embedded in memory.
+    ///
+    /// `name` is the name the file is accessed by at run-time. It is a
+    /// namespace and the file's path within it, separated by `/`. e.g.
+    /// `models/classifier.bin`.
+    ///
+    /// Data files are explicitly requested. So they are added regardless of
+    /// whether the collector allows files and of the allowed locations.
+    pub fn add_data_file(
+        &mut self,
+        name: &str,
+        entry: &FileEntry,
+    ) -> Result<Vec<AddResourceAction>> {
+        let parts = name.split('/').collect::<Vec<_>>();
+
+        if parts.len() < 2
+            || parts
+                .iter()
+                .any(|part| part.is_empty() || *part == "." || *part == ".." || part.contains('\\'))
+        {
+            return Err(anyhow!(
+                "invalid data file name {}; names must be a namespace and a relative path separated by /",
+                name
+            ));
+        }
+
+        let entry_resource =
+            self.resources
+                .entry(name.to_string())
+                .or_insert_with(|| PrePackagedResource {
+                    name: name.to_string(),
+                    ..PrePackagedResource::default()
+                });
+
+        entry_resource.is_utf8_filename_data = true;
+        entry_resource.file_executable = entry.is_executable();
+        entry_resource.file_data_embedded = Some(entry.file_data().clone());
+
+        Ok(vec![AddResourceAction::Added(
+            format!("data file {}", name),
+            ConcreteResourceLocation::InMemory,
+        )])
+    }
+
     pub fn add_file_data_with_context(
         &mut self,
         file: &File,
@@ -1677,11 +1878,15 @@ impl PythonResourceCollector {
             ))]);
         }
 
-        self.add_python_resource_with_locations(
+        let actions = self.add_python_resource_with_locations(
             &file.into(),
             &add_context.location,
             &add_context.location_fallback,
-        )
+        )?;
+
+        self.add_metadata_from_context(&file.path_string(), add_context, &actions);
+
+        Ok(actions)
     }
 
     fn add_python_resource_with_locations(
@@ -1865,6 +2070,7 @@ impl PythonResourceCollector {
         Ok(CompiledResourcesCollection {
             resources,
             extra_files,
+            signing_key: None,
         })
     }
 }
@@ -1873,10 +2079,7 @@ impl PythonResourceCollector {
 mod tests {
     use {
         super::*,
-        crate::{
-            resource::{LibraryDependency, PythonPackageDistributionResourceFlavor},
-            testutil::FakeBytecodeCompiler,
-        },
+        crate::{resource::LibraryDependency, testutil::FakeBytecodeCompiler},
         simple_file_manifest::FileEntry,
     };
 
@@ -2754,6 +2957,7 @@ mod tests {
                 is_module: true,
                 name: "root.parent".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 in_memory_source: Some(FileData::Memory(vec![])),
                 ..PrePackagedResource::default()
             })
@@ -2764,6 +2968,7 @@ mod tests {
                 is_module: true,
                 name: "root".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 in_memory_source: Some(FileData::Memory(vec![])),
                 ..PrePackagedResource::default()
             })
@@ -2798,6 +3003,7 @@ mod tests {
                 is_module: true,
                 name: "root.parent".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 relative_path_module_source: Some(("prefix".to_string(), FileData::Memory(vec![]))),
                 ..PrePackagedResource::default()
             })
@@ -2808,6 +3014,7 @@ mod tests {
                 is_module: true,
                 name: "root".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 relative_path_module_source: Some(("prefix".to_string(), FileData::Memory(vec![]))),
                 ..PrePackagedResource::default()
             })
@@ -2841,6 +3048,7 @@ mod tests {
                 is_module: true,
                 name: "root.parent".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 in_memory_bytecode: Some(PythonModuleBytecodeProvider::FromSource(
                     FileData::Memory(vec![])
                 )),
@@ -2853,6 +3061,7 @@ mod tests {
                 is_module: true,
                 name: "root".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 in_memory_bytecode: Some(PythonModuleBytecodeProvider::FromSource(
                     FileData::Memory(vec![])
                 )),
@@ -2887,6 +3096,7 @@ mod tests {
                 is_module: true,
                 name: "foo".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2920,6 +3130,58 @@ mod tests {
                 is_module: true,
                 name: "foo".to_string(),
                 is_package: true,
+                is_namespace_package: true,
+                ..PrePackagedResource::default()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_populate_parent_packages_existing_parent() -> Result<()> {
+        let mut h = BTreeMap::new();
+        h.insert(
+            "root".to_string(),
+            PrePackagedResource {
+                is_module: true,
+                name: "root".to_string(),
+                in_memory_source: Some(FileData::Memory(vec![42])),
+                is_package: true,
+                ..PrePackagedResource::default()
+            },
+        );
+        h.insert(
+            "root.parent.child".to_string(),
+            PrePackagedResource {
+                is_module: true,
+                name: "root.parent.child".to_string(),
+                in_memory_source: Some(FileData::Memory(vec![42])),
+                ..PrePackagedResource::default()
+            },
+        );
+
+        populate_parent_packages(&mut h)?;
+
+        assert_eq!(h.len(), 3);
+        assert_eq!(
+            h.get("root"),
+            Some(&PrePackagedResource {
+                is_module: true,
+                name: "root".to_string(),
+                is_package: true,
+                in_memory_source: Some(FileData::Memory(vec![42])),
+                ..PrePackagedResource::default()
+            })
+        );
+        assert_eq!(
+            h.get("root.parent"),
+            Some(&PrePackagedResource {
+                is_module: true,
+                name: "root.parent".to_string(),
+                is_package: true,
+                is_namespace_package: true,
+                in_memory_source: Some(FileData::Memory(vec![])),
                 ..PrePackagedResource::default()
             })
         );
@@ -3021,6 +3283,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("root".to_string()),
                 is_python_package: true,
+                is_python_namespace_package: true,
                 in_memory_source: Some(Cow::Owned(vec![])),
                 ..Resource::default()
             })
@@ -3031,6 +3294,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("root.parent".to_string()),
                 is_python_package: true,
+                is_python_namespace_package: true,
                 in_memory_source: Some(Cow::Owned(vec![])),
                 ..Resource::default()
             })
@@ -3051,6 +3315,157 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_compiled_resources_zstd_compress() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: FileData::Memory(vec![42; 1024]),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let mut resources = r.compile_resources(&mut compiler)?;
+        resources.zstd_compress(3)?;
+
+        let resource = resources.resources.get("foo").unwrap();
+        assert!(resource.in_memory_zstd_compressed);
+        assert!(resource.in_memory_source.as_ref().unwrap().len() < 1024);
+        assert_eq!(
+            resource.clone().zstd_decompress()?.in_memory_source,
+            Some(Cow::Owned(vec![42; 1024]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compiled_resources_add_sha256_digests() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: FileData::Memory(vec![42; 1024]),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let mut resources = r.compile_resources(&mut compiler)?;
+        resources.zstd_compress(3)?;
+        resources.add_sha256_digests();
+
+        let resource = resources.resources.get("foo").unwrap();
+        assert_eq!(
+            resource.in_memory_sha256,
+            Some(resource.compute_in_memory_sha256())
+        );
+        assert!(resource.verify_in_memory_sha256().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compiled_resources_encrypt() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: FileData::Memory(vec![42; 1024]),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let mut resources = r.compile_resources(&mut compiler)?;
+        resources.zstd_compress(3)?;
+        resources.encrypt(&[42; 32])?;
+
+        let resource = resources.resources.get("foo").unwrap();
+        assert!(resource.in_memory_encrypted);
+        assert_eq!(
+            resource
+                .clone()
+                .decrypt(&[42; 32])?
+                .zstd_decompress()?
+                .in_memory_source,
+            Some(Cow::Owned(vec![42; 1024]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compiled_resources_signing_key() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: FileData::Memory(vec![42]),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let mut resources = r.compile_resources(&mut compiler)?;
+        let mut unsigned = vec![];
+        resources.write_packed_resources(&mut unsigned)?;
+
+        resources.signing_key = Some([42; 32]);
+        let mut signed = vec![];
+        resources.write_packed_resources(&mut signed)?;
+
+        let public_key = python_packed_resources::ed25519_public_key(&[42; 32])?;
+        assert_eq!(
+            python_packed_resources::verify_packed_resources_signature(&signed, &public_key),
+            Ok(unsigned.as_slice())
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_relative_path_source_module() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -3097,6 +3512,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("foo".to_string()),
                 is_python_package: true,
+                is_python_namespace_package: true,
                 relative_path_module_source: Some(Cow::Owned(PathBuf::from(
                     "prefix/foo/__init__.py"
                 ))),
@@ -3157,6 +3573,7 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            metadata: BTreeMap::new(),
         };
 
         // include=false is a noop.
@@ -3251,6 +3668,69 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_module_source_with_context_metadata() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+
+        let module = PythonModuleSource {
+            name: "foo".to_string(),
+            source: FileData::Memory(vec![42]),
+            is_package: false,
+            cache_tag: DEFAULT_CACHE_TAG.to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+
+        let mut add_context = PythonResourceAddCollectionContext {
+            include: false,
+            location: ConcreteResourceLocation::InMemory,
+            location_fallback: None,
+            store_source: true,
+            optimize_level_zero: false,
+            optimize_level_one: false,
+            optimize_level_two: false,
+            metadata: [("license".to_string(), "MIT".to_string())]
+                .into_iter()
+                .collect(),
+        };
+
+        // Metadata isn't recorded when the resource isn't added.
+        r.add_python_module_source_with_context(&module, &add_context)?;
+        assert!(r.resources.is_empty());
+
+        add_context.include = true;
+        r.add_python_module_source_with_context(&module, &add_context)?;
+        assert_eq!(
+            r.resources.get(&module.name).unwrap().metadata,
+            Some(add_context.metadata.clone())
+        );
+
+        // Metadata from subsequent adds is merged.
+        add_context.metadata = [("wheel".to_string(), "foo.whl".to_string())]
+            .into_iter()
+            .collect();
+        r.add_python_module_source_with_context(&module, &add_context)?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let resources = r.compile_resources(&mut compiler)?;
+
+        let mut metadata = HashMap::new();
+        metadata.insert(Cow::from("license"), Cow::from("MIT"));
+        metadata.insert(Cow::from("wheel"), Cow::from("foo.whl"));
+
+        assert_eq!(
+            resources.resources.get("foo").unwrap().metadata,
+            Some(metadata)
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_in_memory_bytecode_module() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -3382,6 +3862,7 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            metadata: BTreeMap::new(),
         };
 
         // include=false is a noop.
@@ -3522,6 +4003,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("root".to_string()),
                 is_python_package: true,
+                is_python_namespace_package: true,
                 in_memory_bytecode_opt1: Some(Cow::Owned(b"bc1".to_vec())),
                 ..Resource::default()
             })
@@ -3532,6 +4014,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("root.parent".to_string()),
                 is_python_package: true,
+                is_python_namespace_package: true,
                 in_memory_bytecode_opt1: Some(Cow::Owned(b"bc1".to_vec())),
                 ..Resource::default()
             })
@@ -3578,6 +4061,7 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            metadata: BTreeMap::new(),
         };
 
         // include=false is a noop.
@@ -3835,6 +4319,7 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            metadata: BTreeMap::new(),
         };
 
         // include=false is a noop.
@@ -4035,6 +4520,70 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_package_distribution_resource_metadata() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+
+        for (name, data) in [
+            (
+                "METADATA",
+                "Metadata-Version: 2.1\nName: mypackage\nVersion: 1.0\nLicense: MIT\n",
+            ),
+            (
+                "entry_points.txt",
+                "[console_scripts]\nmypackage = mypackage:main\n",
+            ),
+            ("RECORD", "mypackage/__init__.py,sha256=aaaa,1\n"),
+            ("WHEEL", "Wheel-Version: 1.0\n"),
+        ] {
+            r.add_python_package_distribution_resource(
+                &PythonPackageDistributionResource {
+                    location: PythonPackageDistributionResourceFlavor::DistInfo,
+                    package: "mypackage".to_string(),
+                    version: "1.0".to_string(),
+                    name: name.to_string(),
+                    data: FileData::Memory(data.as_bytes().to_vec()),
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let resources = r.compile_resources(&mut compiler)?;
+
+        let resource = resources.resources.get("mypackage").unwrap();
+        assert_eq!(
+            resource
+                .in_memory_distribution_resources
+                .as_ref()
+                .unwrap()
+                .len(),
+            4
+        );
+
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            Cow::from("dist-info.entry-points.console_scripts"),
+            Cow::from("mypackage = mypackage:main"),
+        );
+        metadata.insert(
+            Cow::from("dist-info.files"),
+            Cow::from("mypackage/__init__.py"),
+        );
+        metadata.insert(Cow::from("dist-info.license"), Cow::from("MIT"));
+        metadata.insert(Cow::from("dist-info.name"), Cow::from("mypackage"));
+        metadata.insert(Cow::from("dist-info.version"), Cow::from("1.0"));
+
+        assert_eq!(resource.metadata, Some(metadata));
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_package_distribution_resource_with_context() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -4060,6 +4609,7 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            metadata: BTreeMap::new(),
         };
 
         // include=false is a noop.
@@ -4354,6 +4904,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("foo".to_string()),
                 is_python_package: true,
+                is_python_namespace_package: true,
                 ..Resource::default()
             })
         );
@@ -4514,6 +5065,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_data_file() -> Result<()> {
+        // Data files are added even if files and in-memory resources aren't allowed.
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::RelativePath],
+            vec![],
+            false,
+            false,
+        );
+
+        r.add_data_file(
+            "models/v1/model.bin",
+            &FileEntry::new_from_data(vec![42], false),
+        )?;
+
+        assert_eq!(
+            r.resources.get("models/v1/model.bin"),
+            Some(&PrePackagedResource {
+                is_utf8_filename_data: true,
+                name: "models/v1/model.bin".to_string(),
+                file_data_embedded: Some(FileData::Memory(vec![42])),
+                ..PrePackagedResource::default()
+            })
+        );
+
+        for name in [
+            "model.bin",
+            "models/",
+            "/model.bin",
+            "models//model.bin",
+            "models/../x",
+            "models/a\\b",
+        ] {
+            assert!(r
+                .add_data_file(name, &FileEntry::new_from_data(vec![42], false))
+                .is_err());
+        }
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let resources = r.compile_resources(&mut compiler)?;
+        assert_eq!(
+            resources.resources.get("models/v1/model.bin"),
+            Some(&Resource {
+                is_utf8_filename_data: true,
+                name: Cow::Owned("models/v1/model.bin".to_string()),
+                file_data_embedded: Some(Cow::Owned(vec![42])),
+                ..Resource::default()
+            })
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_relative_path_file_data() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -4586,6 +5190,7 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            metadata: BTreeMap::new(),
         };
 
         // include=false is a noop.
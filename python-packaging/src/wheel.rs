@@ -291,16 +291,100 @@ impl WheelArchive {
         // Get resources from data, remapping them to the root.
         inputs.extend(self.data_files());
 
-        // Other data keys are `headers` and `scripts`, which we don't yet
-        // support as resource types.
+        // The other data key is `headers`, which we don't yet support as a
+        // resource type.
 
-        PythonResourceIterator::from_data_locations(
+        let mut res = PythonResourceIterator::from_data_locations(
             &inputs,
             cache_tag,
             suffixes,
             emit_files,
             classify_files,
         )?
-        .collect::<Result<Vec<_>>>()
+        .collect::<Result<Vec<_>>>()?;
+
+        // Scripts aren't Python resources. So they are only emitted as files.
+        // They are installed to a `bin/` directory, like `pip install --target`
+        // does.
+        if emit_files {
+            res.extend(
+                self.scripts_files()
+                    .into_iter()
+                    .map(|f| File::new(Path::new("bin").join(f.path()), f.entry()).into()),
+            );
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::wheel_builder::WheelBuilder};
+
+    #[test]
+    fn test_python_resources() -> Result<()> {
+        let mut builder = WheelBuilder::new("my-package", "0.1");
+        builder.add_file("my_package/__init__.py", vec![42])?;
+        builder.add_file_data("purelib", "other.py", vec![42])?;
+        builder.add_file_data(
+            "scripts",
+            "my-script",
+            FileEntry::new_from_data(vec![42], true),
+        )?;
+        builder.add_file_data("headers", "my_package.h", vec![42])?;
+
+        let mut data = std::io::Cursor::new(vec![]);
+        builder.write_wheel_data(&mut data)?;
+        data.set_position(0);
+
+        let wheel = WheelArchive::from_reader(data, &builder.wheel_file_name())?;
+
+        let suffixes = PythonModuleSuffixes {
+            source: vec![".py".to_string()],
+            bytecode: vec![".pyc".to_string()],
+            debug_bytecode: vec![],
+            optimized_bytecode: vec![],
+            extension: vec![],
+        };
+
+        let resources = wheel.python_resources("cpython-39", &suffixes, true, true)?;
+
+        let mut modules = resources
+            .iter()
+            .filter_map(|r| match r {
+                PythonResource::ModuleSource(m) => Some(m.name.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        modules.sort_unstable();
+        assert_eq!(modules, vec!["my_package", "other"]);
+
+        assert!(resources.iter().any(|r| matches!(
+            r,
+            PythonResource::PackageDistributionResource(r) if r.name == "METADATA"
+        )));
+
+        let files = resources
+            .iter()
+            .filter_map(|r| match r {
+                PythonResource::File(f) => Some(f),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let script = files
+            .iter()
+            .find(|f| f.path() == Path::new("bin/my-script"))
+            .unwrap();
+        assert!(script.entry().is_executable());
+        // Headers aren't supported.
+        assert!(!files.iter().any(|f| f.path().ends_with("my_package.h")));
+
+        let resources = wheel.python_resources("cpython-39", &suffixes, false, true)?;
+        assert!(!resources
+            .iter()
+            .any(|r| matches!(r, PythonResource::File(_))));
+
+        Ok(())
     }
 }
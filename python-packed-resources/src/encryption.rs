@@ -0,0 +1,154 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Encryption of in-memory resource data.
+
+Each encrypted value is a 16 byte nonce, the data encrypted with AES-256 in
+counter mode, and a 32 byte HMAC-SHA256 of the nonce and encrypted data.
+
+Encryption, authentication, and nonce keys are derived from a single 32 byte
+key. The nonce is derived from the data being encrypted, so encrypting the
+same data with the same key produces the same output and builds remain
+reproducible.
+*/
+
+use {
+    aes::{cipher::generic_array::GenericArray, Aes256, Block, BlockEncrypt, NewBlockCipher},
+    hmac::{Hmac, Mac},
+    sha2::Sha256,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const NONCE_LENGTH: usize = 16;
+const TAG_LENGTH: usize = 32;
+
+struct Keys {
+    cipher: Aes256,
+    authentication: [u8; 32],
+    nonce: [u8; 32],
+}
+
+impl Keys {
+    fn derive(key: &[u8; 32]) -> Self {
+        let cipher_key = hmac(key, &[b"packed resources encryption"]);
+
+        Self {
+            cipher: Aes256::new(GenericArray::from_slice(&cipher_key)),
+            authentication: hmac(key, &[b"packed resources authentication"]),
+            nonce: hmac(key, &[b"packed resources nonce"]),
+        }
+    }
+
+    /// XOR data with the AES-CTR key stream starting at a nonce.
+    fn apply_key_stream(&self, nonce: &[u8], data: &mut [u8]) {
+        let mut counter = u128::from_be_bytes(nonce.try_into().expect("nonce is 16 bytes"));
+
+        for chunk in data.chunks_mut(16) {
+            let mut block = Block::from(counter.to_be_bytes());
+            self.cipher.encrypt_block(&mut block);
+
+            for (byte, key) in chunk.iter_mut().zip(block.iter()) {
+                *byte ^= key;
+            }
+
+            counter = counter.wrapping_add(1);
+        }
+    }
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+
+    mac.finalize().into_bytes().into()
+}
+
+/// Encrypt data with a 32 byte key.
+pub(crate) fn encrypt(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+    let keys = Keys::derive(key);
+    let nonce = &hmac(&keys.nonce, &[data])[0..NONCE_LENGTH];
+
+    let mut res = Vec::with_capacity(NONCE_LENGTH + data.len() + TAG_LENGTH);
+    res.extend_from_slice(nonce);
+    res.extend_from_slice(data);
+    keys.apply_key_stream(nonce, &mut res[NONCE_LENGTH..]);
+
+    let tag = hmac(&keys.authentication, &[&res]);
+    res.extend_from_slice(&tag);
+
+    res
+}
+
+/// Decrypt data encrypted by [encrypt()].
+///
+/// Errors if the data wasn't encrypted with the key or was modified.
+pub(crate) fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if data.len() < NONCE_LENGTH + TAG_LENGTH {
+        return Err("encrypted resource data is truncated");
+    }
+
+    let keys = Keys::derive(key);
+    let (payload, tag) = data.split_at(data.len() - TAG_LENGTH);
+
+    let mut mac =
+        HmacSha256::new_from_slice(&keys.authentication).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac.verify_slice(tag)
+        .map_err(|_| "encrypted resource data failed authentication")?;
+
+    let (nonce, ciphertext) = payload.split_at(NONCE_LENGTH);
+    let mut res = ciphertext.to_vec();
+    keys.apply_key_stream(nonce, &mut res);
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [42; 32];
+
+    #[test]
+    fn test_round_trip() {
+        for data in [&b""[..], b"foo", &[42; 100]] {
+            let encrypted = encrypt(&KEY, data);
+            assert_eq!(encrypted.len(), data.len() + NONCE_LENGTH + TAG_LENGTH);
+            assert_eq!(decrypt(&KEY, &encrypted), Ok(data.to_vec()));
+
+            // Encryption is deterministic.
+            assert_eq!(encrypt(&KEY, data), encrypted);
+        }
+
+        assert_ne!(&encrypt(&KEY, b"foobar")[NONCE_LENGTH..22], b"foobar");
+        assert_ne!(encrypt(&KEY, b"foo"), encrypt(&[1; 32], b"foo"));
+    }
+
+    #[test]
+    fn test_decrypt_failures() {
+        let mut encrypted = encrypt(&KEY, b"foo");
+
+        assert_eq!(
+            decrypt(&[1; 32], &encrypted),
+            Err("encrypted resource data failed authentication")
+        );
+        assert_eq!(
+            decrypt(&KEY, &encrypted[0..40]),
+            Err("encrypted resource data is truncated")
+        );
+
+        encrypted[NONCE_LENGTH] ^= 0xff;
+        assert_eq!(
+            decrypt(&KEY, &encrypted),
+            Err("encrypted resource data failed authentication")
+        );
+    }
+}
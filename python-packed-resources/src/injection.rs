@@ -0,0 +1,351 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Packed resources data injected into already linked binaries.
+
+Injected data is appended to the end of a binary: the packed resources data,
+a little endian `u64` holding the length of that data, and the 8 byte
+[INJECTION_TRAILER_V1] magic. Operating system loaders ignore data after the
+content they map, so the binary still runs. And injecting new data replaces
+previously injected data, so a single built binary can be stamped with
+different resources.
+
+Authenticode signatures of PE binaries are stored at the end of the file.
+So injected data is found before the certificate table of a signed PE binary
+and resources must be injected before the binary is signed.
+
+Mach-O binaries are rejected. `codesign` refuses to sign binaries having
+data after their `__LINKEDIT` segment and binaries must be signed to run on
+Apple silicon. So appended data can't be used with Mach-O binaries.
+
+Binaries ending with a zip archive, such as one read by
+`OxidizedZipFinder::new_from_appended_path()`, are also rejected. Both
+mechanisms require their data to be at the end of the binary, so only one
+of them can be used.
+*/
+
+use {
+    crate::parser::packed_resources_format_version,
+    anyhow::{anyhow, Result},
+    std::convert::TryInto,
+};
+
+/// Trailing magic value identifying packed resources data injected into a binary.
+pub const INJECTION_TRAILER_V1: &[u8] = b"pyinjct\x01";
+
+/// Length in bytes of the injection trailer, including the data length.
+const TRAILER_LENGTH: usize = 8 + 8;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Whether data is a Mach-O binary, including universal binaries.
+fn is_macho(data: &[u8]) -> bool {
+    matches!(
+        data.get(0..4),
+        Some(
+            [0xfe, 0xed, 0xfa, 0xce]
+                | [0xce, 0xfa, 0xed, 0xfe]
+                | [0xfe, 0xed, 0xfa, 0xcf]
+                | [0xcf, 0xfa, 0xed, 0xfe]
+                | [0xca, 0xfe, 0xba, 0xbe]
+                | [0xbe, 0xba, 0xfe, 0xca]
+        )
+    )
+}
+
+/// Whether data ends with a zip archive.
+///
+/// Looks for a zip end of central directory record whose comment extends to
+/// the end of the data.
+fn ends_with_zip_archive(data: &[u8]) -> bool {
+    const EOCD_LENGTH: usize = 22;
+
+    if data.len() < EOCD_LENGTH {
+        return false;
+    }
+
+    let search_start = data.len().saturating_sub(EOCD_LENGTH + u16::MAX as usize);
+
+    (search_start..=data.len() - EOCD_LENGTH)
+        .rev()
+        .any(|offset| {
+            data[offset..].starts_with(b"PK\x05\x06")
+                && read_u16(data, offset + 20)
+                    .map(|comment_length| offset + EOCD_LENGTH + comment_length as usize)
+                    == Some(data.len())
+        })
+}
+
+/// Resolve the offset of the certificate table of a signed PE binary.
+///
+/// Returns `None` if the data isn't a PE binary or if it has no certificate
+/// table at the end of the file.
+fn pe_certificate_table_offset(data: &[u8]) -> Option<usize> {
+    if !data.starts_with(b"MZ") {
+        return None;
+    }
+
+    let pe_offset = read_u32(data, 0x3c)? as usize;
+    if data.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+        return None;
+    }
+
+    let optional_header = pe_offset + 24;
+    let directories = match read_u16(data, optional_header)? {
+        0x10b => optional_header + 96,
+        0x20b => optional_header + 112,
+        _ => return None,
+    };
+    let directories_count = read_u32(data, directories - 4)?;
+
+    // The certificate table is data directory entry 4. Its address is a file
+    // offset rather than a virtual address.
+    if directories_count < 5 {
+        return None;
+    }
+    let offset = read_u32(data, directories + 4 * 8)? as usize;
+    let size = read_u32(data, directories + 4 * 8 + 4)? as usize;
+
+    if size > 0 && offset.checked_add(size)? == data.len() {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+/// Resolve the range of injected packed resources data.
+///
+/// Returns the offset of the start of injected data and its length. The
+/// injection trailer follows the data.
+fn injected_range(binary: &[u8]) -> Result<Option<(usize, usize)>, &'static str> {
+    let end = pe_certificate_table_offset(binary).unwrap_or(binary.len());
+    let binary = &binary[0..end];
+
+    if binary.len() < TRAILER_LENGTH || !binary.ends_with(INJECTION_TRAILER_V1) {
+        return Ok(None);
+    }
+
+    let length_offset = binary.len() - TRAILER_LENGTH;
+    let length = u64::from_le_bytes(
+        binary[length_offset..length_offset + 8]
+            .try_into()
+            .expect("slice is 8 bytes"),
+    );
+    let length = usize::try_from(length).map_err(|_| "injected packed resources are truncated")?;
+
+    if length > length_offset {
+        return Err("injected packed resources are truncated");
+    }
+
+    Ok(Some((length_offset - length, length)))
+}
+
+/// Find packed resources data injected into a binary.
+///
+/// Returns `None` if no data is injected.
+pub fn find_injected_packed_resources(binary: &[u8]) -> Result<Option<&[u8]>, &'static str> {
+    Ok(injected_range(binary)?.map(|(start, length)| &binary[start..start + length]))
+}
+
+/// Remove injected packed resources data from a binary.
+///
+/// Returns the binary unmodified if no data is injected.
+pub fn strip_injected_packed_resources(binary: &[u8]) -> Result<Vec<u8>> {
+    let end = pe_certificate_table_offset(binary).unwrap_or(binary.len());
+
+    match injected_range(binary).map_err(|e| anyhow!("{}", e))? {
+        Some((start, _)) => {
+            let mut res = binary[0..start].to_vec();
+            res.extend_from_slice(&binary[end..]);
+
+            Ok(res)
+        }
+        None => Ok(binary.to_vec()),
+    }
+}
+
+/// Inject packed resources data into a binary.
+///
+/// Previously injected data is replaced. `resources` can be signed packed
+/// resources data.
+///
+/// Signed PE binaries are rejected because injecting data would invalidate
+/// their signature. Mach-O binaries and binaries ending with a zip archive
+/// are rejected because appended data would break code signing or the
+/// appended archive.
+pub fn inject_packed_resources(binary: &[u8], resources: &[u8]) -> Result<Vec<u8>> {
+    if is_macho(binary) {
+        return Err(anyhow!(
+            "cannot inject packed resources into a Mach-O binary; code signing doesn't support appended data"
+        ));
+    }
+
+    if pe_certificate_table_offset(binary).is_some() {
+        return Err(anyhow!(
+            "cannot inject packed resources into a signed binary; inject resources before signing"
+        ));
+    }
+
+    if ends_with_zip_archive(binary) {
+        return Err(anyhow!(
+            "cannot inject packed resources into a binary having an appended zip archive"
+        ));
+    }
+
+    packed_resources_format_version(resources)
+        .map_err(|e| anyhow!("resources to inject are not packed resources data: {}", e))?;
+
+    let mut res = strip_injected_packed_resources(binary)?;
+    res.reserve(resources.len() + TRAILER_LENGTH);
+    res.extend_from_slice(resources);
+    res.extend_from_slice(&(resources.len() as u64).to_le_bytes());
+    res.extend_from_slice(INJECTION_TRAILER_V1);
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::serialization::HEADER_V3};
+
+    fn packed_resources(content: &[u8]) -> Vec<u8> {
+        let mut data = HEADER_V3.to_vec();
+        data.extend_from_slice(content);
+        data
+    }
+
+    /// Produce a minimal PE32+ binary with a certificate table of a given size.
+    fn pe_binary(content: &[u8], certificate_size: usize) -> Vec<u8> {
+        let mut data = vec![0; 0x40];
+        data[0..2].copy_from_slice(b"MZ");
+        data[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        data.extend_from_slice(b"PE\0\0");
+        data.extend_from_slice(&[0; 20]);
+        let optional_header = data.len();
+        data.resize(optional_header + 112 + 16 * 8, 0);
+        data[optional_header..optional_header + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+        data[optional_header + 108..optional_header + 112].copy_from_slice(&16u32.to_le_bytes());
+        data.extend_from_slice(content);
+
+        if certificate_size > 0 {
+            let entry = optional_header + 112 + 4 * 8;
+            let offset = data.len() as u32;
+            data[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
+            data[entry + 4..entry + 8].copy_from_slice(&(certificate_size as u32).to_le_bytes());
+            data.resize(data.len() + certificate_size, 0xff);
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_inject() -> Result<()> {
+        let binary = b"binary content".to_vec();
+        assert_eq!(find_injected_packed_resources(&binary), Ok(None));
+
+        let resources = packed_resources(b"foo");
+        let injected = inject_packed_resources(&binary, &resources)?;
+        assert!(injected.starts_with(&binary));
+        assert!(injected.ends_with(INJECTION_TRAILER_V1));
+        assert_eq!(
+            find_injected_packed_resources(&injected),
+            Ok(Some(resources.as_slice()))
+        );
+
+        // Injecting again replaces existing data.
+        let resources = packed_resources(b"foobar");
+        let replaced = inject_packed_resources(&injected, &resources)?;
+        assert_eq!(replaced.len(), injected.len() + 3);
+        assert_eq!(
+            find_injected_packed_resources(&replaced),
+            Ok(Some(resources.as_slice()))
+        );
+
+        assert_eq!(strip_injected_packed_resources(&replaced)?, binary);
+        assert_eq!(strip_injected_packed_resources(&binary)?, binary);
+
+        assert!(inject_packed_resources(&binary, b"not packed resources").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut data = b"foo".to_vec();
+        data.extend_from_slice(&100u64.to_le_bytes());
+        data.extend_from_slice(INJECTION_TRAILER_V1);
+
+        assert_eq!(
+            find_injected_packed_resources(&data),
+            Err("injected packed resources are truncated")
+        );
+    }
+
+    #[test]
+    fn test_macho() {
+        let resources = packed_resources(b"foo");
+
+        for magic in [[0xcfu8, 0xfa, 0xed, 0xfe], [0xca, 0xfe, 0xba, 0xbe]] {
+            let mut binary = magic.to_vec();
+            binary.extend_from_slice(b"content");
+
+            assert!(inject_packed_resources(&binary, &resources).is_err());
+        }
+    }
+
+    #[test]
+    fn test_appended_zip() -> Result<()> {
+        let resources = packed_resources(b"foo");
+
+        let mut binary = b"binary content".to_vec();
+        binary.extend_from_slice(b"PK\x05\x06");
+        binary.extend_from_slice(&[0; 16]);
+        binary.extend_from_slice(&3u16.to_le_bytes());
+        binary.extend_from_slice(b"abc");
+        assert!(inject_packed_resources(&binary, &resources).is_err());
+
+        // Data after the archive means it isn't at the end of the binary.
+        binary.extend_from_slice(b"trailing");
+        inject_packed_resources(&binary, &resources)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pe() -> Result<()> {
+        let resources = packed_resources(b"foo");
+
+        let unsigned = pe_binary(b"content", 0);
+        let injected = inject_packed_resources(&unsigned, &resources)?;
+        assert_eq!(
+            find_injected_packed_resources(&injected),
+            Ok(Some(resources.as_slice()))
+        );
+
+        // Data injected before signing is found before the certificate table.
+        let signed = pe_binary(&injected[unsigned.len()..], 32);
+        assert_eq!(
+            find_injected_packed_resources(&signed),
+            Ok(Some(resources.as_slice()))
+        );
+
+        assert!(inject_packed_resources(&signed, &resources).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Inspection of existing packed resources data.
+
+These APIs allow tooling to examine the resources in packed resources data,
+or in a binary embedding such data, without loading them into an interpreter.
+*/
+
+use {
+    crate::{
+        parser::{load_resources, packed_resources_format_version},
+        resource::Resource,
+        serialization::HEADER_PREFIX,
+    },
+    std::path::Path,
+};
+
+/// Describes a resource in packed resources data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceSummary {
+    /// The name of the resource.
+    pub name: String,
+
+    /// Names of the type and storage flags set on the resource.
+    ///
+    /// e.g. `python-module`, `in-memory`, or `zstd-compressed`.
+    pub flags: Vec<&'static str>,
+
+    /// Total size in bytes of in-memory data as stored.
+    ///
+    /// This is the size of data after compression and encryption, if any.
+    pub in_memory_size: usize,
+
+    /// Paths, relative to the origin, of files holding data of the resource.
+    pub relative_paths: Vec<String>,
+
+    /// SHA-256 digest of in-memory data recorded in the packed resources data.
+    pub in_memory_sha256: Option<[u8; 32]>,
+}
+
+impl<'a> From<&Resource<'a, u8>> for ResourceSummary {
+    fn from(resource: &Resource<'a, u8>) -> Self {
+        let mut in_memory_size = 0;
+        for data in [
+            &resource.in_memory_source,
+            &resource.in_memory_bytecode,
+            &resource.in_memory_bytecode_opt1,
+            &resource.in_memory_bytecode_opt2,
+            &resource.in_memory_extension_module_shared_library,
+            &resource.in_memory_shared_library,
+            &resource.file_data_embedded,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            in_memory_size += data.len();
+        }
+        for resources in [
+            &resource.in_memory_package_resources,
+            &resource.in_memory_distribution_resources,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            in_memory_size += resources.values().map(|data| data.len()).sum::<usize>();
+        }
+
+        let mut relative_paths = [
+            &resource.relative_path_module_source,
+            &resource.relative_path_module_bytecode,
+            &resource.relative_path_module_bytecode_opt1,
+            &resource.relative_path_module_bytecode_opt2,
+            &resource.relative_path_extension_module_shared_library,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|path| path_string(path))
+        .collect::<Vec<_>>();
+        for resources in [
+            &resource.relative_path_package_resources,
+            &resource.relative_path_distribution_resources,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let mut paths = resources
+                .values()
+                .map(|path| path_string(path))
+                .collect::<Vec<_>>();
+            paths.sort();
+            relative_paths.extend(paths);
+        }
+        if let Some(path) = &resource.file_data_utf8_relative_path {
+            relative_paths.push(path.to_string());
+        }
+
+        let mut flags = vec![];
+        for (value, flag) in [
+            (resource.is_python_module, "python-module"),
+            (resource.is_python_package, "python-package"),
+            (
+                resource.is_python_namespace_package,
+                "python-namespace-package",
+            ),
+            (
+                resource.is_python_builtin_extension_module,
+                "python-builtin-extension-module",
+            ),
+            (resource.is_python_frozen_module, "python-frozen-module"),
+            (
+                resource.is_python_extension_module,
+                "python-extension-module",
+            ),
+            (resource.is_shared_library, "shared-library"),
+            (resource.is_utf8_filename_data, "file"),
+            (resource.file_executable, "executable"),
+            (in_memory_size > 0, "in-memory"),
+            (!relative_paths.is_empty(), "filesystem-relative"),
+            (resource.in_memory_zstd_compressed, "zstd-compressed"),
+            (resource.in_memory_encrypted, "encrypted"),
+        ] {
+            if value {
+                flags.push(flag);
+            }
+        }
+
+        Self {
+            name: resource.name.to_string(),
+            flags,
+            in_memory_size,
+            relative_paths,
+            in_memory_sha256: resource.in_memory_sha256,
+        }
+    }
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Packed resources data found in a larger piece of data.
+#[derive(Clone, Debug)]
+pub struct FoundPackedResources<'a> {
+    /// Offset of the packed resources data from the start of the searched data.
+    pub offset: usize,
+
+    /// The format version of the packed resources data.
+    pub format_version: u8,
+
+    /// Resources in the packed resources data.
+    pub resources: Vec<Resource<'a, u8>>,
+}
+
+/// Find packed resources data in arbitrary data, such as an executable.
+///
+/// Every occurrence of a packed resources header is examined, so this finds
+/// data embedded in a binary as well as standalone packed resources data
+/// (found at offset 0). Occurrences that don't parse as valid packed
+/// resources data are ignored.
+pub fn find_packed_resources(data: &[u8]) -> Vec<FoundPackedResources<'_>> {
+    let mut res = vec![];
+
+    let mut offset = 0;
+    while let Some(position) = data[offset..]
+        .windows(HEADER_PREFIX.len())
+        .position(|window| window == HEADER_PREFIX)
+    {
+        let start = offset + position;
+        offset = start + 1;
+
+        let candidate = &data[start..];
+        let format_version = match packed_resources_format_version(candidate) {
+            Ok(version) => version,
+            Err(_) => continue,
+        };
+
+        let resources = match load_resources(candidate) {
+            Ok(resources) => resources,
+            Err(_) => continue,
+        };
+
+        if let Ok(resources) = resources.collect::<Result<Vec<_>, &'static str>>() {
+            res.push(FoundPackedResources {
+                offset: start,
+                format_version,
+                resources,
+            });
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::writer::write_packed_resources_v3,
+        std::{borrow::Cow, collections::HashMap},
+    };
+
+    #[test]
+    fn test_resource_summary() {
+        let mut package_resources = HashMap::new();
+        package_resources.insert(Cow::from("data.txt"), Cow::from(b"data".to_vec()));
+
+        let resource = Resource {
+            name: Cow::from("foo"),
+            is_python_module: true,
+            is_python_package: true,
+            in_memory_source: Some(Cow::from(b"import io".to_vec())),
+            in_memory_package_resources: Some(package_resources),
+            relative_path_module_bytecode: Some(Cow::from(Path::new("foo.pyc"))),
+            in_memory_sha256: Some([42; 32]),
+            ..Resource::default()
+        };
+
+        assert_eq!(
+            ResourceSummary::from(&resource),
+            ResourceSummary {
+                name: "foo".to_string(),
+                flags: vec![
+                    "python-module",
+                    "python-package",
+                    "in-memory",
+                    "filesystem-relative"
+                ],
+                in_memory_size: 13,
+                relative_paths: vec!["foo.pyc".to_string()],
+                in_memory_sha256: Some([42; 32]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_packed_resources() {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"import io".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut packed = Vec::new();
+        write_packed_resources_v3(std::slice::from_ref(&resource), &mut packed, None).unwrap();
+
+        let found = find_packed_resources(&packed);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].offset, 0);
+        assert_eq!(found[0].format_version, 3);
+        assert_eq!(found[0].resources, vec![resource.clone()]);
+
+        // Data embedded in other data is found. Stray headers are ignored.
+        let mut data = b"garbagepyembed\x03garbage".to_vec();
+        data.extend_from_slice(&packed);
+        data.extend_from_slice(b"pyembed\x04");
+
+        let found = find_packed_resources(&data);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].offset, 22);
+        assert_eq!(found[0].resources, vec![resource]);
+
+        assert!(find_packed_resources(b"pyembe").is_empty());
+    }
+}
@@ -29,14 +29,42 @@ it can be used outside the PyOxidizer project. See the aforementioned docs
 for the canonical specification of this format.
 */
 
+#[cfg(feature = "encryption")]
+mod encryption;
+mod injection;
+mod inspect;
+mod name_index;
 mod parser;
+#[cfg(all(feature = "ed25519-dalek", feature = "sha2"))]
+mod patch;
 mod resource;
 mod serialization;
+#[cfg(feature = "ed25519-dalek")]
+mod signature;
 mod writer;
 
 pub use crate::{
-    parser::{load_resources, ResourceParserIterator},
+    injection::{
+        find_injected_packed_resources, inject_packed_resources, strip_injected_packed_resources,
+        INJECTION_TRAILER_V1,
+    },
+    inspect::{find_packed_resources, FoundPackedResources, ResourceSummary},
+    name_index::ResourceNameIndex,
+    parser::{load_resources, packed_resources_format_version, ResourceParserIterator},
     resource::Resource,
-    serialization::HEADER_V3,
-    writer::write_packed_resources_v3,
+    serialization::{
+        BlobInteriorPadding, HEADER_V3, HEADER_V4, MAX_FORMAT_VERSION, MIN_FORMAT_VERSION,
+    },
+    writer::{write_packed_resources_v3, write_packed_resources_with_options, WriteOptions},
+};
+
+#[cfg(all(feature = "ed25519-dalek", feature = "sha2"))]
+pub use crate::patch::{
+    apply_packed_resources_patch, create_packed_resources_patch, PATCH_HEADER_V1,
+};
+
+#[cfg(feature = "ed25519-dalek")]
+pub use crate::signature::{
+    ed25519_public_key, is_signed_packed_resources, sign_packed_resources,
+    verify_packed_resources_signature, SIGNATURE_TRAILER_V1,
 };
@@ -0,0 +1,235 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Perfect hash index of resource names.
+
+The index maps each resource name in packed resources data to the position
+of the resource in that data using a *hash and displace* perfect hash built
+at write time. Readers can look up resources by name without hashing every
+name into a hash table first.
+
+Names are grouped into buckets by a first hash. Each bucket stores a seed
+for a second hash that maps its names to distinct slots. Each slot holds
+the position of a resource. A lookup is 2 hashes of the name and 2 array
+reads.
+
+Names not in the index map to arbitrary positions. So callers must compare
+the name of the resource at the returned position with the name looked up.
+*/
+
+use std::{collections::HashSet, convert::TryInto};
+
+/// Average number of names per bucket.
+const NAMES_PER_BUCKET: usize = 4;
+
+/// Number of seeds tried for a bucket before building the index fails.
+const MAX_SEED: u32 = 1 << 20;
+
+/// Length in bytes of the header holding the bucket and slot counts.
+const HEADER_LENGTH: usize = 8;
+
+/// Hash a name with a seed.
+///
+/// This is 64-bit FNV-1a with the seed mixed into the offset basis.
+fn hash(name: &[u8], seed: u32) -> u64 {
+    let mut h = 0xcbf2_9ce4_8422_2325 ^ (seed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+
+    for b in name {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    h ^ (h >> 32)
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    u32::from_le_bytes(
+        data[index * 4..index * 4 + 4]
+            .try_into()
+            .expect("slice is 4 bytes"),
+    )
+}
+
+/// Build a perfect hash index of resource names.
+///
+/// Names are the names of resources in the order they are written. Returns
+/// `None` if names aren't unique or if a perfect hash couldn't be found.
+pub(crate) fn build_name_index(names: &[&str]) -> Option<Vec<u8>> {
+    if names.iter().collect::<HashSet<_>>().len() != names.len() {
+        return None;
+    }
+
+    let slot_count = names.len();
+    let bucket_count = slot_count / NAMES_PER_BUCKET + 1;
+
+    let mut buckets = vec![vec![]; bucket_count];
+    for (position, name) in names.iter().enumerate() {
+        buckets[(hash(name.as_bytes(), 0) % bucket_count as u64) as usize].push(position);
+    }
+
+    // Buckets with the most names are the hardest to place. So place them
+    // while the most slots are free.
+    let mut order = (0..bucket_count).collect::<Vec<_>>();
+    order.sort_by_key(|bucket| std::cmp::Reverse(buckets[*bucket].len()));
+
+    let mut seeds = vec![0u32; bucket_count];
+    let mut slots = vec![None; slot_count];
+    let mut bucket_slots = vec![];
+
+    for bucket in order {
+        let positions = &buckets[bucket];
+        if positions.is_empty() {
+            break;
+        }
+
+        let seed = (1..MAX_SEED).find(|seed| {
+            bucket_slots.clear();
+
+            positions.iter().all(|position| {
+                let slot = (hash(names[*position].as_bytes(), *seed) % slot_count as u64) as usize;
+
+                if slots[slot].is_some() || bucket_slots.contains(&slot) {
+                    false
+                } else {
+                    bucket_slots.push(slot);
+                    true
+                }
+            })
+        })?;
+
+        seeds[bucket] = seed;
+        for (position, slot) in positions.iter().zip(&bucket_slots) {
+            slots[*slot] = Some(*position);
+        }
+    }
+
+    let mut res = Vec::with_capacity(HEADER_LENGTH + 4 * (bucket_count + slot_count));
+    res.extend_from_slice(&(bucket_count as u32).to_le_bytes());
+    res.extend_from_slice(&(slot_count as u32).to_le_bytes());
+    for seed in seeds {
+        res.extend_from_slice(&seed.to_le_bytes());
+    }
+    for position in slots {
+        res.extend_from_slice(&(position.expect("every slot is filled") as u32).to_le_bytes());
+    }
+
+    Some(res)
+}
+
+/// A perfect hash index of the resource names in packed resources data.
+#[derive(Clone, Copy, Debug)]
+pub struct ResourceNameIndex<'a> {
+    seeds: &'a [u8],
+    positions: &'a [u8],
+}
+
+impl<'a> ResourceNameIndex<'a> {
+    /// Construct an instance from the data of a name index blob section.
+    pub fn from_data(data: &'a [u8]) -> Result<Self, &'static str> {
+        if data.len() < HEADER_LENGTH {
+            return Err("name index is truncated");
+        }
+
+        let bucket_count = read_u32(data, 0) as usize;
+        let slot_count = read_u32(data, 1) as usize;
+
+        if bucket_count == 0 {
+            return Err("name index has no buckets");
+        }
+
+        let expected_length = bucket_count
+            .checked_add(slot_count)
+            .and_then(|count| count.checked_mul(4))
+            .and_then(|length| length.checked_add(HEADER_LENGTH));
+
+        if expected_length != Some(data.len()) {
+            return Err("name index is truncated");
+        }
+
+        let (seeds, positions) = data[HEADER_LENGTH..].split_at(4 * bucket_count);
+
+        Ok(Self { seeds, positions })
+    }
+
+    /// The number of resources in the index.
+    pub fn len(&self) -> usize {
+        self.positions.len() / 4
+    }
+
+    /// Whether the index has no resources.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Resolve the position of a named resource.
+    ///
+    /// Returns `None` if the index has no resources. Otherwise returns a
+    /// position that holds the named resource if any resource does. The name
+    /// of the resource at that position must be compared with `name`.
+    pub fn lookup(&self, name: &str) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let bucket_count = self.seeds.len() / 4;
+        let bucket = (hash(name.as_bytes(), 0) % bucket_count as u64) as usize;
+        let seed = read_u32(self.seeds, bucket);
+        let slot = (hash(name.as_bytes(), seed) % self.len() as u64) as usize;
+
+        Some(read_u32(self.positions, slot) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() -> Result<(), &'static str> {
+        let names = (0..1000)
+            .map(|i| format!("package{}.module{}", i % 10, i))
+            .collect::<Vec<_>>();
+        let names = names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+
+        let data = build_name_index(&names).unwrap();
+        let index = ResourceNameIndex::from_data(&data)?;
+        assert_eq!(index.len(), names.len());
+
+        for (position, name) in names.iter().enumerate() {
+            assert_eq!(index.lookup(name), Some(position));
+        }
+
+        assert!(index.lookup("missing").unwrap() < names.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty() -> Result<(), &'static str> {
+        let data = build_name_index(&[]).unwrap();
+        let index = ResourceNameIndex::from_data(&data)?;
+        assert!(index.is_empty());
+        assert_eq!(index.lookup("foo"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        assert!(build_name_index(&["foo", "bar", "foo"]).is_none());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = build_name_index(&["foo", "bar"]).unwrap();
+
+        assert!(ResourceNameIndex::from_data(&data[0..4]).is_err());
+        assert!(ResourceNameIndex::from_data(&data[0..data.len() - 1]).is_err());
+        assert!(ResourceNameIndex::from_data(&[0; 8]).is_err());
+    }
+}
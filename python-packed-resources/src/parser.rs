@@ -10,11 +10,20 @@
 
 use {
     crate::{
+        name_index::ResourceNameIndex,
         resource::Resource,
-        serialization::{BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3},
+        serialization::{
+            BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_PREFIX, HEADER_V3,
+            MAX_FORMAT_VERSION, MIN_FORMAT_VERSION,
+        },
     },
     byteorder::{LittleEndian, ReadBytesExt},
-    std::{borrow::Cow, collections::HashMap, io::Cursor, path::Path},
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        io::{Cursor, Read},
+        path::Path,
+    },
 };
 
 #[cfg(unix)]
@@ -28,6 +37,7 @@ struct BlobSection {
     resource_field: u8,
     raw_payload_length: usize,
     interior_padding: Option<BlobInteriorPadding>,
+    alignment: Option<usize>,
 }
 
 /// Holds state used to read an individual blob section.
@@ -35,6 +45,7 @@ struct BlobSection {
 struct BlobSectionReadState {
     offset: usize,
     interior_padding: BlobInteriorPadding,
+    alignment: usize,
 }
 
 /// An iterator over an actively parsed packed resources data structure.
@@ -43,11 +54,14 @@ struct BlobSectionReadState {
 /// not read or validated until the iterator attempts to deserialize it.
 pub struct ResourceParserIterator<'a> {
     done: bool,
+    format_version: u8,
     data: &'a [u8],
     reader: Cursor<&'a [u8]>,
     blob_sections: [Option<BlobSectionReadState>; 256],
+    blob_start_offset: usize,
     claimed_resources_count: usize,
     read_resources_count: usize,
+    name_index: Option<ResourceNameIndex<'a>>,
 }
 
 impl<'a> ResourceParserIterator<'a> {
@@ -56,16 +70,53 @@ impl<'a> ResourceParserIterator<'a> {
         self.claimed_resources_count
     }
 
+    /// The format version of the data being parsed.
+    pub fn format_version(&self) -> u8 {
+        self.format_version
+    }
+
+    /// The perfect hash index of resource names, if the data has one.
+    ///
+    /// Positions in the index are the order in which resources are emitted.
+    pub fn name_index(&self) -> Option<ResourceNameIndex<'a>> {
+        self.name_index
+    }
+
+    /// Resolve a field type, validating it is supported by the format version.
+    fn resolve_field_type(&self, field_type: u8) -> Result<ResourceField, &'static str> {
+        let field_type = ResourceField::try_from(field_type)?;
+
+        if field_type.format_version() > self.format_version {
+            Err("field type not supported by format version of packed resources data")
+        } else {
+            Ok(field_type)
+        }
+    }
+
     /// Resolve a slice to an individual blob's data.
     ///
     /// This accepts a reference to the original blobs payload, an array of
     /// current blob section offsets, the resource field being accessed, and the
     /// length of the blob and returns a slice to that blob.
     fn resolve_blob_data(&mut self, resource_field: ResourceField, length: usize) -> &'a [u8] {
+        // Writers don't emit blob sections for fields whose blobs are all empty.
+        if length == 0 && self.blob_sections[resource_field as usize].is_none() {
+            return &[];
+        }
+
         let mut state = self.blob_sections[resource_field as usize]
             .as_mut()
             .expect("blob state not found");
 
+        // Non-empty blobs in aligned sections start at an aligned offset from
+        // the start of blob data.
+        if state.alignment > 1 && length > 0 {
+            let remainder = (state.offset - self.blob_start_offset) % state.alignment;
+            if remainder != 0 {
+                state.offset += state.alignment - remainder;
+            }
+        }
+
         let blob = &self.data[state.offset..state.offset + length];
 
         let increment = match &state.interior_padding {
@@ -106,7 +157,7 @@ impl<'a> ResourceParserIterator<'a> {
                 .read_u8()
                 .map_err(|_| "failed reading field type")?;
 
-            let field_type = ResourceField::try_from(field_type)?;
+            let field_type = self.resolve_field_type(field_type)?;
 
             match field_type {
                 ResourceField::EndOfIndex => {
@@ -118,6 +169,9 @@ impl<'a> ResourceParserIterator<'a> {
 
                     return Ok(None);
                 }
+                ResourceField::NameIndex => {
+                    return Err("name index field is not valid in a resource entry");
+                }
                 ResourceField::StartOfEntry => {
                     self.read_resources_count += 1;
                     current_resource = Resource::default();
@@ -485,6 +539,145 @@ impl<'a> ResourceParserIterator<'a> {
                         std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
                     }));
                 }
+
+                ResourceField::InMemoryZstdCompressed => {
+                    current_resource.in_memory_zstd_compressed = true;
+                }
+
+                ResourceField::InMemoryEncrypted => {
+                    current_resource.in_memory_encrypted = true;
+                }
+
+                ResourceField::Metadata => {
+                    let entries_count = self
+                        .reader
+                        .read_u32::<LittleEndian>()
+                        .map_err(|_| "failed reading metadata entries count")?
+                        as usize;
+
+                    let mut metadata = HashMap::with_capacity(entries_count);
+
+                    for _ in 0..entries_count {
+                        let key_length = self
+                            .reader
+                            .read_u16::<LittleEndian>()
+                            .map_err(|_| "failed reading metadata key length")?
+                            as usize;
+
+                        let key = unsafe {
+                            std::str::from_utf8_unchecked(
+                                self.resolve_blob_data(field_type, key_length),
+                            )
+                        };
+
+                        let value_length = self
+                            .reader
+                            .read_u32::<LittleEndian>()
+                            .map_err(|_| "failed reading metadata value length")?
+                            as usize;
+
+                        let value = unsafe {
+                            std::str::from_utf8_unchecked(
+                                self.resolve_blob_data(field_type, value_length),
+                            )
+                        };
+
+                        metadata.insert(Cow::Borrowed(key), Cow::Borrowed(value));
+                    }
+
+                    current_resource.metadata = Some(metadata);
+                }
+
+                ResourceField::InMemorySha256 => {
+                    let mut digest = [0; 32];
+                    self.reader
+                        .read_exact(&mut digest)
+                        .map_err(|_| "failed reading in-memory SHA-256 digest")?;
+
+                    current_resource.in_memory_sha256 = Some(digest);
+                }
+
+                ResourceField::SharedBlobReferences => {
+                    let references_count = self
+                        .reader
+                        .read_u16::<LittleEndian>()
+                        .map_err(|_| "failed reading shared blob references count")?;
+
+                    for _ in 0..references_count {
+                        let field = self
+                            .reader
+                            .read_u8()
+                            .map_err(|_| "failed reading shared blob reference field")?;
+                        let field = self.resolve_field_type(field)?;
+
+                        let key_length = self
+                            .reader
+                            .read_u16::<LittleEndian>()
+                            .map_err(|_| "failed reading shared blob reference key length")?
+                            as usize;
+                        let offset = self
+                            .reader
+                            .read_u64::<LittleEndian>()
+                            .map_err(|_| "failed reading shared blob reference offset")?
+                            as usize;
+                        let length = self
+                            .reader
+                            .read_u64::<LittleEndian>()
+                            .map_err(|_| "failed reading shared blob reference length")?
+                            as usize;
+
+                        let key = unsafe {
+                            std::str::from_utf8_unchecked(
+                                self.resolve_blob_data(field_type, key_length),
+                            )
+                        };
+
+                        let start = self.blob_start_offset + offset;
+                        let data = start
+                            .checked_add(length)
+                            .and_then(|end| self.data.get(start..end))
+                            .ok_or("shared blob reference out of bounds")?;
+                        let data = Cow::Borrowed(data);
+
+                        match field {
+                            ResourceField::InMemorySource => {
+                                current_resource.in_memory_source = Some(data);
+                            }
+                            ResourceField::InMemoryBytecode => {
+                                current_resource.in_memory_bytecode = Some(data);
+                            }
+                            ResourceField::InMemoryBytecodeOpt1 => {
+                                current_resource.in_memory_bytecode_opt1 = Some(data);
+                            }
+                            ResourceField::InMemoryBytecodeOpt2 => {
+                                current_resource.in_memory_bytecode_opt2 = Some(data);
+                            }
+                            ResourceField::InMemoryExtensionModuleSharedLibrary => {
+                                current_resource.in_memory_extension_module_shared_library =
+                                    Some(data);
+                            }
+                            ResourceField::InMemorySharedLibrary => {
+                                current_resource.in_memory_shared_library = Some(data);
+                            }
+                            ResourceField::FileDataEmbedded => {
+                                current_resource.file_data_embedded = Some(data);
+                            }
+                            ResourceField::InMemoryResourcesData => {
+                                current_resource
+                                    .in_memory_package_resources
+                                    .get_or_insert_with(HashMap::new)
+                                    .insert(Cow::Borrowed(key), data);
+                            }
+                            ResourceField::InMemoryDistributionResource => {
+                                current_resource
+                                    .in_memory_distribution_resources
+                                    .get_or_insert_with(HashMap::new)
+                                    .insert(Cow::Borrowed(key), data);
+                            }
+                            _ => return Err("invalid field type for shared blob reference"),
+                        }
+                    }
+                }
             }
         }
     }
@@ -513,21 +706,44 @@ impl<'a> Iterator for ResourceParserIterator<'a> {
 /// Performance note: we once attempted to switch to anyhow for error handling and
 /// this decreased performance by ~15%. Given the performance sensitivity of this
 /// code, we need to keep error handling primitive.
+///
+/// Data of any format version between [MIN_FORMAT_VERSION] and [MAX_FORMAT_VERSION]
+/// can be parsed. Data of other versions is rejected.
 pub fn load_resources<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+    let format_version = packed_resources_format_version(data)?;
+
+    load_resources_v3(&data[HEADER_V3.len()..], format_version)
+}
+
+/// Resolve the format version of a packed resources data structure.
+///
+/// Errors if the data isn't packed resources data or if its format version
+/// can't be parsed.
+pub fn packed_resources_format_version(data: &[u8]) -> Result<u8, &'static str> {
     if data.len() < HEADER_V3.len() {
         return Err("error reading 8 byte header");
     }
 
-    let header = &data[0..8];
+    if &data[0..HEADER_PREFIX.len()] != HEADER_PREFIX {
+        return Err("unrecognized file format");
+    }
 
-    if header == HEADER_V3 {
-        load_resources_v3(&data[8..])
-    } else {
-        Err("unrecognized file format")
+    match data[HEADER_PREFIX.len()] {
+        0 => Err("unrecognized file format"),
+        version if version < MIN_FORMAT_VERSION => {
+            Err("packed resources data format version is too old to be read")
+        }
+        version if version > MAX_FORMAT_VERSION => {
+            Err("packed resources data format version is newer than this reader supports")
+        }
+        version => Ok(version),
     }
 }
 
-fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+fn load_resources_v3<'a>(
+    data: &'a [u8],
+    format_version: u8,
+) -> Result<ResourceParserIterator<'a>, &'static str> {
     let mut reader = Cursor::new(data);
 
     let blob_section_count = reader
@@ -547,6 +763,7 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
     let mut current_blob_field = None;
     let mut current_blob_raw_payload_length = None;
     let mut current_blob_interior_padding = None;
+    let mut current_blob_alignment = None;
     let mut blob_entry_count = 0;
     let mut blob_sections = Vec::with_capacity(blob_section_count as usize);
 
@@ -565,6 +782,7 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                     current_blob_field = None;
                     current_blob_raw_payload_length = None;
                     current_blob_interior_padding = None;
+                    current_blob_alignment = None;
                 }
                 BlobSectionField::EndOfEntry => {
                     if current_blob_field.is_none() {
@@ -578,11 +796,13 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                         resource_field: current_blob_field.unwrap(),
                         raw_payload_length: current_blob_raw_payload_length.unwrap(),
                         interior_padding: current_blob_interior_padding,
+                        alignment: current_blob_alignment,
                     });
 
                     current_blob_field = None;
                     current_blob_raw_payload_length = None;
                     current_blob_interior_padding = None;
+                    current_blob_alignment = None;
                 }
                 BlobSectionField::ResourceFieldType => {
                     let field = reader
@@ -607,6 +827,23 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                         _ => return Err("invalid value for interior padding field"),
                     });
                 }
+                BlobSectionField::Alignment => {
+                    if format_version < 4 {
+                        return Err(
+                            "blob index field type not supported by format version of packed resources data",
+                        );
+                    }
+
+                    let alignment = reader
+                        .read_u32::<LittleEndian>()
+                        .map_err(|_| "failed reading alignment field value")?;
+
+                    if !alignment.is_power_of_two() {
+                        return Err("invalid value for alignment field");
+                    }
+
+                    current_blob_alignment = Some(alignment as usize);
+                }
             }
         }
     }
@@ -627,26 +864,49 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
         ;
     // Current offset from start of blobs data.
     let mut current_blob_offset = 0;
+    let mut name_index = None;
 
     for section in &blob_sections {
         let section_start_offset = blob_start_offset + current_blob_offset;
+
+        if section.resource_field == u8::from(ResourceField::NameIndex) {
+            if format_version < 4 {
+                return Err("name index not supported by format version of packed resources data");
+            }
+
+            let index_data = data
+                .get(section_start_offset..section_start_offset + section.raw_payload_length)
+                .ok_or("name index is truncated")?;
+            let index = ResourceNameIndex::from_data(index_data)?;
+
+            if index.len() != resources_count {
+                return Err("mismatch between name index and resources count");
+            }
+
+            name_index = Some(index);
+        }
+
         blob_offsets[section.resource_field as usize] = Some(BlobSectionReadState {
             offset: section_start_offset,
             interior_padding: match section.interior_padding {
                 Some(padding) => padding,
                 None => BlobInteriorPadding::None,
             },
+            alignment: section.alignment.unwrap_or(1),
         });
         current_blob_offset += section.raw_payload_length;
     }
 
     Ok(ResourceParserIterator {
         done: resources_index_length == 0 || resources_count == 0,
+        format_version,
         data,
         reader,
         blob_sections: blob_offsets,
+        blob_start_offset,
         claimed_resources_count: resources_count,
         read_resources_count: 0,
+        name_index,
     })
 }
 
@@ -655,8 +915,11 @@ mod tests {
     use {
         super::*,
         crate::{
-            resource::Resource, serialization::BlobInteriorPadding,
-            writer::write_packed_resources_v3,
+            resource::Resource,
+            serialization::BlobInteriorPadding,
+            writer::{
+                write_packed_resources_v3, write_packed_resources_with_options, WriteOptions,
+            },
         },
     };
 
@@ -674,11 +937,48 @@ mod tests {
         let res = load_resources(data);
         assert_eq!(res.err(), Some("unrecognized file format"));
 
-        let data = b"pyembed\x04";
+        let data = b"foobarba";
         let res = load_resources(data);
         assert_eq!(res.err(), Some("unrecognized file format"));
     }
 
+    #[test]
+    fn test_format_versions() {
+        let data = b"pyembed\x02";
+        assert_eq!(
+            load_resources(data).err(),
+            Some("packed resources data format version is too old to be read")
+        );
+
+        let data = b"pyembed\x05";
+        assert_eq!(
+            load_resources(data).err(),
+            Some("packed resources data format version is newer than this reader supports")
+        );
+
+        let data = b"pyembed\x04\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+        assert_eq!(packed_resources_format_version(data), Ok(4));
+        assert_eq!(load_resources(data).unwrap().format_version(), 4);
+
+        // Fields introduced in version 4 are rejected in version 3 data.
+        for field in 0x1f..=0x23 {
+            let mut data =
+                b"pyembed\x03\x00\x01\x00\x00\x00\x01\x00\x00\x00\x03\x00\x00\x00\x00\x01\x00\x00"
+                    .to_vec();
+            data[23] = field;
+            assert_eq!(ResourceField::try_from(field).unwrap().format_version(), 4);
+
+            let mut res = load_resources(&data).unwrap();
+            assert_eq!(res.format_version(), 3);
+            assert_eq!(
+                res.next(),
+                Some(Err(
+                    "field type not supported by format version of packed resources data"
+                ))
+            );
+        }
+    }
+
     #[test]
     fn test_no_indices() {
         let data = b"pyembed\x03\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
@@ -750,6 +1050,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_empty_in_memory_source() {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 1);
+        assert_eq!(
+            resources[0].in_memory_source,
+            Some(Cow::Borrowed(b"".as_ref()))
+        );
+    }
+
     #[test]
     fn test_multiple_resources_just_names() {
         let resource1 = Resource {
@@ -1101,6 +1424,289 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_in_memory_encrypted() {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"encrypted".to_vec())),
+            in_memory_encrypted: true,
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+        assert_eq!(packed_resources_format_version(&data), Ok(4));
+
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 1);
+        assert!(resources[0].in_memory_encrypted);
+        assert_eq!(
+            resources[0].in_memory_source.as_ref().unwrap().as_ref(),
+            b"encrypted"
+        );
+    }
+
+    #[test]
+    fn test_in_memory_zstd_compressed() {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"compressed".to_vec())),
+            in_memory_zstd_compressed: true,
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 1);
+
+        let entry = &resources[0];
+
+        assert!(entry.in_memory_zstd_compressed);
+        assert_eq!(
+            entry.in_memory_source.as_ref().unwrap().as_ref(),
+            b"compressed"
+        );
+    }
+
+    #[test]
+    fn test_in_memory_sha256() {
+        let mut digest = [0; 32];
+        for (i, b) in digest.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            in_memory_sha256: Some(digest),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 1);
+
+        let entry = &resources[0];
+
+        assert_eq!(entry.in_memory_sha256, Some(digest));
+        assert_eq!(entry.in_memory_source.as_ref().unwrap().as_ref(), b"source");
+    }
+
+    #[test]
+    fn test_shared_blobs() {
+        let payload = b"duplicated payload long enough to be shared".to_vec();
+
+        let resource1 = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(payload.clone())),
+            ..Resource::default()
+        };
+        let resource2 = Resource {
+            name: Cow::from("bar"),
+            in_memory_source: Some(Cow::from(payload.clone())),
+            file_data_embedded: Some(Cow::from(payload.clone())),
+            ..Resource::default()
+        };
+        let resource3 = Resource {
+            name: Cow::from("baz"),
+            in_memory_source: Some(Cow::from(b"short".to_vec())),
+            in_memory_bytecode: Some(Cow::from(b"short".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[&resource1, &resource2, &resource3], &mut data, None).unwrap();
+
+        // The payload is only stored once.
+        assert_eq!(
+            data.windows(payload.len())
+                .filter(|window| *window == payload.as_slice())
+                .count(),
+            1
+        );
+
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 3);
+        assert_eq!(resources[0], resource1);
+        assert_eq!(resources[1], resource2);
+        assert_eq!(resources[2], resource3);
+
+        let first = resources[0].in_memory_source.as_ref().unwrap();
+        assert_eq!(
+            resources[1].in_memory_source.as_ref().unwrap().as_ptr(),
+            first.as_ptr()
+        );
+        assert_eq!(
+            resources[1].file_data_embedded.as_ref().unwrap().as_ptr(),
+            first.as_ptr()
+        );
+    }
+
+    #[test]
+    fn test_shared_blobs_package_resources() {
+        let payload = b"duplicated package resource data for sharing".to_vec();
+
+        let mut resources1 = HashMap::new();
+        resources1.insert(Cow::from("data.txt"), Cow::from(payload.clone()));
+        resources1.insert(Cow::from("copy.txt"), Cow::from(payload.clone()));
+        resources1.insert(Cow::from("other.txt"), Cow::from(b"other".to_vec()));
+
+        let mut resources2 = HashMap::new();
+        resources2.insert(Cow::from("LICENSE"), Cow::from(payload.clone()));
+
+        let resource1 = Resource {
+            name: Cow::from("foo"),
+            in_memory_package_resources: Some(resources1),
+            ..Resource::default()
+        };
+        let resource2 = Resource {
+            name: Cow::from("bar"),
+            in_memory_distribution_resources: Some(resources2),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[&resource1, &resource2], &mut data, None).unwrap();
+
+        assert_eq!(
+            data.windows(payload.len())
+                .filter(|window| *window == payload.as_slice())
+                .count(),
+            1
+        );
+
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0], resource1);
+        assert_eq!(resources[1], resource2);
+    }
+
+    #[test]
+    fn test_shared_blobs_null_padding() {
+        let payload = b"duplicated payload long enough to be shared".to_vec();
+
+        let mut resources = HashMap::new();
+        resources.insert(Cow::from("data.txt"), Cow::from(payload.clone()));
+
+        let resource1 = Resource {
+            name: Cow::from("foo"),
+            in_memory_bytecode: Some(Cow::from(payload.clone())),
+            ..Resource::default()
+        };
+        let resource2 = Resource {
+            name: Cow::from("bar"),
+            in_memory_shared_library: Some(Cow::from(payload.clone())),
+            in_memory_package_resources: Some(resources),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(
+            &[&resource1, &resource2],
+            &mut data,
+            Some(BlobInteriorPadding::Null),
+        )
+        .unwrap();
+
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0], resource1);
+        assert_eq!(resources[1], resource2);
+    }
+
+    #[test]
+    fn test_blob_alignment() {
+        let payload = b"duplicated payload long enough to be shared".to_vec();
+
+        let resource1 = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            in_memory_bytecode: Some(Cow::from(b"bytecode".to_vec())),
+            in_memory_bytecode_opt1: Some(Cow::from(payload.clone())),
+            ..Resource::default()
+        };
+        let resource2 = Resource {
+            name: Cow::from("bar"),
+            in_memory_bytecode: Some(Cow::from(b"more bytecode".to_vec())),
+            in_memory_bytecode_opt1: Some(Cow::from(Vec::new())),
+            in_memory_shared_library: Some(Cow::from(payload.clone())),
+            ..Resource::default()
+        };
+
+        for interior_padding in [None, Some(BlobInteriorPadding::Null)] {
+            let mut data = Vec::new();
+            write_packed_resources_with_options(
+                &[&resource1, &resource2],
+                &mut data,
+                &WriteOptions {
+                    interior_padding,
+                    blob_alignment: Some(64),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+            assert_eq!(packed_resources_format_version(&data), Ok(4));
+
+            let resources = load_resources(&data)
+                .unwrap()
+                .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                .unwrap();
+
+            assert_eq!(resources.len(), 2);
+            assert_eq!(resources[0], resource1);
+            assert_eq!(resources[1], resource2);
+
+            for blob in [
+                resources[0].in_memory_bytecode.as_ref().unwrap(),
+                resources[0].in_memory_bytecode_opt1.as_ref().unwrap(),
+                resources[1].in_memory_bytecode.as_ref().unwrap(),
+                resources[1].in_memory_shared_library.as_ref().unwrap(),
+            ] {
+                let offset = blob.as_ptr() as usize - data.as_ptr() as usize;
+                assert_eq!(offset % 64, 0);
+            }
+        }
+
+        // Alignment must be a power of 2.
+        let mut data = Vec::new();
+        assert!(write_packed_resources_with_options(
+            &[&resource1],
+            &mut data,
+            &WriteOptions {
+                blob_alignment: Some(48),
+                ..Default::default()
+            },
+        )
+        .is_err());
+    }
+
     #[test]
     fn test_shared_library_dependency_names() {
         let names = vec![Cow::from("depends"), Cow::from("libfoo")];
@@ -1376,6 +1982,10 @@ mod tests {
             Cow::from(Path::new("package/resource.txt")),
         );
 
+        let mut metadata = HashMap::new();
+        metadata.insert(Cow::from("license"), Cow::from("MIT"));
+        metadata.insert(Cow::from("wheel"), Cow::from("foo-1.0-py3-none-any.whl"));
+
         let resource = Resource {
             name: Cow::from("module"),
             is_python_package: true,
@@ -1405,6 +2015,10 @@ mod tests {
             file_executable: true,
             file_data_embedded: Some(Cow::from(b"file_data_embedded".to_vec())),
             file_data_utf8_relative_path: Some(Cow::from("file_data_utf8_relative_path")),
+            in_memory_zstd_compressed: true,
+            in_memory_encrypted: true,
+            in_memory_sha256: Some([42; 32]),
+            metadata: Some(metadata),
         };
 
         let mut data = Vec::new();
@@ -1519,6 +2133,121 @@ mod tests {
             entry.file_data_utf8_relative_path.as_ref().unwrap(),
             "file_data_utf8_relative_path"
         );
+        assert!(entry.in_memory_zstd_compressed);
+        assert!(entry.in_memory_encrypted);
+        assert_eq!(entry.in_memory_sha256, Some([42; 32]));
+
+        let metadata = entry.metadata.as_ref().unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata.get("license").unwrap(), "MIT");
+        assert_eq!(metadata.get("wheel").unwrap(), "foo-1.0-py3-none-any.whl");
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut metadata = HashMap::new();
+        metadata.insert(Cow::from("license"), Cow::from("Apache-2.0"));
+        metadata.insert(Cow::from("empty"), Cow::from(""));
+
+        let resources = vec![
+            Resource {
+                name: Cow::from("foo"),
+                is_python_module: true,
+                metadata: Some(metadata),
+                ..Resource::default()
+            },
+            Resource {
+                name: Cow::from("bar"),
+                is_python_module: true,
+                metadata: Some(HashMap::new()),
+                ..Resource::default()
+            },
+            Resource {
+                name: Cow::from("baz"),
+                is_python_module: true,
+                ..Resource::default()
+            },
+        ];
+
+        for interior_padding in [None, Some(BlobInteriorPadding::Null)] {
+            let mut data = Vec::new();
+            write_packed_resources_with_options(
+                &resources,
+                &mut data,
+                &WriteOptions {
+                    interior_padding,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert_eq!(packed_resources_format_version(&data), Ok(4));
+
+            let loaded = load_resources(&data)
+                .unwrap()
+                .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                .unwrap();
+            assert_eq!(loaded, resources);
+        }
+    }
+
+    #[test]
+    fn test_name_index() {
+        let resources = (0..100)
+            .map(|i| Resource {
+                name: Cow::from(format!("module{}", i)),
+                is_python_module: true,
+                in_memory_source: Some(Cow::from(format!("source{}", i).into_bytes())),
+                ..Resource::default()
+            })
+            .collect::<Vec<_>>();
+
+        for interior_padding in [None, Some(BlobInteriorPadding::Null)] {
+            let mut data = Vec::new();
+            write_packed_resources_with_options(
+                &resources,
+                &mut data,
+                &WriteOptions {
+                    interior_padding,
+                    name_index: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert_eq!(packed_resources_format_version(&data), Ok(4));
+
+            let parser = load_resources(&data).unwrap();
+            let index = parser.name_index().unwrap();
+            let loaded = parser
+                .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                .unwrap();
+            assert_eq!(loaded, resources);
+
+            for resource in &resources {
+                let position = index.lookup(&resource.name).unwrap();
+                assert_eq!(loaded[position].name, resource.name);
+            }
+
+            let position = index.lookup("missing").unwrap();
+            assert_ne!(loaded[position].name, "missing");
+        }
+
+        // No index is written when names aren't unique.
+        let mut data = Vec::new();
+        write_packed_resources_with_options(
+            &[&resources[0], &resources[0]],
+            &mut data,
+            &WriteOptions {
+                name_index: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(packed_resources_format_version(&data), Ok(3));
+        assert!(load_resources(&data).unwrap().name_index().is_none());
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&resources, &mut data, None).unwrap();
+        assert!(load_resources(&data).unwrap().name_index().is_none());
     }
 
     #[test]
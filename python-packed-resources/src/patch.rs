@@ -0,0 +1,460 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Delta updates of packed resources data.
+
+A patch transforms one packed resources blob into another by adding,
+replacing, and deleting individual resources. It starts with the
+[PATCH_HEADER_V1] magic, followed by:
+
+* The SHA-256 of the packed resources data the patch applies to.
+* The SHA-256 of the packed resources data produced by applying the patch.
+* A `u8` holding the [BlobInteriorPadding] of the produced data, or `0`.
+* A little endian `u32` holding the blob alignment of the produced data, or `0`.
+* A `u8` that is `1` if the produced data is signed, followed by the 64 byte
+  ed25519 signature of the produced data, or `0`.
+* A little endian `u32` holding the number of operations.
+* For each operation, a `u8` operation type (`0x01` add, `0x02` replace,
+  `0x03` delete), a little endian `u16` holding the length of the resource
+  name, and the UTF-8 resource name.
+* Packed resources data holding the resources of add and replace operations.
+
+Patched data holds the resources of the original data in their original
+order, with added resources appended. Patched data doesn't have a name
+index, even if the data the patch was created from did. Since the digests of both the original
+and the produced data are verified, a patch either produces exactly the data
+its creator intended or fails.
+
+Signed patches carry the signature of the produced data. Applying them
+produces signed data (see [crate::sign_packed_resources()]). So a signed
+packed resources file stays signed when patched.
+*/
+
+use {
+    crate::{
+        parser::load_resources,
+        resource::Resource,
+        serialization::BlobInteriorPadding,
+        signature::{append_signature, ed25519_signature, SIGNATURE_LENGTH},
+        writer::{write_packed_resources_with_options, WriteOptions},
+    },
+    anyhow::{anyhow, Context, Result},
+    byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt},
+    sha2::Digest,
+    std::{
+        collections::HashMap,
+        io::{Cursor, Read, Write},
+    },
+};
+
+/// Header magic value identifying a packed resources patch.
+pub const PATCH_HEADER_V1: &[u8] = b"pypatch\x01";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operation {
+    Add = 0x01,
+    Replace = 0x02,
+    Delete = 0x03,
+}
+
+impl TryFrom<u8> for Operation {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0x01 => Ok(Self::Add),
+            0x02 => Ok(Self::Replace),
+            0x03 => Ok(Self::Delete),
+            _ => Err(anyhow!("invalid packed resources patch operation")),
+        }
+    }
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    sha2::Sha256::digest(data).into()
+}
+
+fn parse_resources(data: &[u8]) -> Result<Vec<Resource<'_, u8>>> {
+    load_resources(data)
+        .map_err(|e| anyhow!("error parsing packed resources: {}", e))?
+        .collect::<Result<Vec<_>, &'static str>>()
+        .map_err(|e| anyhow!("error parsing packed resources: {}", e))
+}
+
+/// Produce patched packed resources data.
+fn apply_operations<'a>(
+    base: Vec<Resource<'a, u8>>,
+    operations: &[(Operation, String)],
+    mut resources: HashMap<String, Resource<'a, u8>>,
+    options: &WriteOptions,
+) -> Result<Vec<u8>> {
+    let mut positions = base
+        .iter()
+        .enumerate()
+        .map(|(i, resource)| (resource.name.to_string(), i))
+        .collect::<HashMap<_, _>>();
+    let mut result = base.into_iter().map(Some).collect::<Vec<_>>();
+
+    for (operation, name) in operations {
+        match operation {
+            Operation::Add => {
+                if positions.contains_key(name) {
+                    return Err(anyhow!("patch adds resource that already exists: {}", name));
+                }
+
+                let resource = resources
+                    .remove(name)
+                    .ok_or_else(|| anyhow!("patch is missing data for resource {}", name))?;
+                positions.insert(name.clone(), result.len());
+                result.push(Some(resource));
+            }
+            Operation::Replace => {
+                let position = *positions
+                    .get(name)
+                    .ok_or_else(|| anyhow!("patch replaces unknown resource: {}", name))?;
+                let resource = resources
+                    .remove(name)
+                    .ok_or_else(|| anyhow!("patch is missing data for resource {}", name))?;
+                result[position] = Some(resource);
+            }
+            Operation::Delete => {
+                let position = positions
+                    .remove(name)
+                    .ok_or_else(|| anyhow!("patch deletes unknown resource: {}", name))?;
+                result[position] = None;
+            }
+        }
+    }
+
+    if !resources.is_empty() {
+        return Err(anyhow!("patch contains data for resources it doesn't use"));
+    }
+
+    let result = result.into_iter().flatten().collect::<Vec<_>>();
+
+    let mut data = Vec::new();
+    write_packed_resources_with_options(&result, &mut data, options)?;
+
+    Ok(data)
+}
+
+/// Create a patch transforming packed resources data `old` into `new`.
+///
+/// `options` controls the layout of the data produced by the patch. Its
+/// `name_index` is ignored.
+///
+/// If `secret_key` is set, the data produced by the patch is signed with it.
+/// The patch itself isn't signed. Sign it with [crate::sign_packed_resources()].
+pub fn create_packed_resources_patch(
+    old: &[u8],
+    new: &[u8],
+    options: &WriteOptions,
+    secret_key: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let options = &WriteOptions {
+        name_index: false,
+        ..*options
+    };
+    let old_resources = parse_resources(old)?;
+    let new_resources = parse_resources(new)?;
+
+    let old_by_name = old_resources
+        .iter()
+        .map(|resource| (resource.name.as_ref(), resource))
+        .collect::<HashMap<_, _>>();
+    let new_by_name = new_resources
+        .iter()
+        .map(|resource| (resource.name.as_ref(), resource))
+        .collect::<HashMap<_, _>>();
+
+    let mut operations = vec![];
+    let mut patch_resources = vec![];
+
+    for resource in &old_resources {
+        if !new_by_name.contains_key(resource.name.as_ref()) {
+            operations.push((Operation::Delete, resource.name.to_string()));
+        }
+    }
+
+    for resource in &new_resources {
+        match old_by_name.get(resource.name.as_ref()) {
+            Some(existing) if *existing == resource => {}
+            Some(_) => {
+                operations.push((Operation::Replace, resource.name.to_string()));
+                patch_resources.push(resource.clone());
+            }
+            None => {
+                operations.push((Operation::Add, resource.name.to_string()));
+                patch_resources.push(resource.clone());
+            }
+        }
+    }
+
+    let mut resources_data = Vec::new();
+    write_packed_resources_with_options(
+        &patch_resources,
+        &mut resources_data,
+        &WriteOptions::default(),
+    )?;
+
+    let result = apply_operations(
+        old_resources,
+        &operations,
+        patch_resources
+            .into_iter()
+            .map(|resource| (resource.name.to_string(), resource))
+            .collect(),
+        options,
+    )?;
+
+    let mut patch = Vec::new();
+    patch.write_all(PATCH_HEADER_V1)?;
+    patch.write_all(&sha256(old))?;
+    patch.write_all(&sha256(&result))?;
+    patch.write_u8(options.interior_padding.as_ref().map_or(0, u8::from))?;
+    patch.write_u32::<LittleEndian>(options.blob_alignment.unwrap_or(0))?;
+    if let Some(key) = secret_key {
+        patch.write_u8(1)?;
+        patch.write_all(&ed25519_signature(&result, key)?)?;
+    } else {
+        patch.write_u8(0)?;
+    }
+    patch.write_u32::<LittleEndian>(operations.len() as u32)?;
+
+    for (operation, name) in &operations {
+        let name_length =
+            u16::try_from(name.len()).map_err(|_| anyhow!("resource name too long: {}", name))?;
+
+        patch.write_u8(*operation as u8)?;
+        patch.write_u16::<LittleEndian>(name_length)?;
+        patch.write_all(name.as_bytes())?;
+    }
+
+    patch.write_all(&resources_data)?;
+
+    Ok(patch)
+}
+
+/// Apply a patch to packed resources data.
+///
+/// Returns the patched packed resources data, with a signature trailer if the
+/// patch carries a signature. Errors if the patch doesn't apply to `data` or
+/// doesn't produce the data it was created to produce. The signature isn't
+/// verified.
+pub fn apply_packed_resources_patch(data: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    if !patch.starts_with(PATCH_HEADER_V1) {
+        return Err(anyhow!("data is not a packed resources patch"));
+    }
+
+    let mut reader = Cursor::new(&patch[PATCH_HEADER_V1.len()..]);
+
+    let mut base_digest = [0; 32];
+    let mut result_digest = [0; 32];
+    reader
+        .read_exact(&mut base_digest)
+        .context("reading base digest")?;
+    reader
+        .read_exact(&mut result_digest)
+        .context("reading result digest")?;
+
+    if sha256(data) != base_digest {
+        return Err(anyhow!(
+            "packed resources patch does not apply to this data"
+        ));
+    }
+
+    let interior_padding = match reader.read_u8().context("reading interior padding")? {
+        0 => None,
+        0x01 => Some(BlobInteriorPadding::None),
+        0x02 => Some(BlobInteriorPadding::Null),
+        _ => {
+            return Err(anyhow!(
+                "invalid interior padding in packed resources patch"
+            ))
+        }
+    };
+    let blob_alignment = match reader
+        .read_u32::<LittleEndian>()
+        .context("reading blob alignment")?
+    {
+        0 => None,
+        alignment => Some(alignment),
+    };
+    let signature = match reader.read_u8().context("reading signature flag")? {
+        0 => None,
+        1 => {
+            let mut signature = [0; SIGNATURE_LENGTH];
+            reader
+                .read_exact(&mut signature)
+                .context("reading signature")?;
+            Some(signature)
+        }
+        _ => return Err(anyhow!("invalid signature flag in packed resources patch")),
+    };
+
+    let operations_count = reader
+        .read_u32::<LittleEndian>()
+        .context("reading operations count")?;
+
+    let mut operations = vec![];
+    for _ in 0..operations_count {
+        let operation = Operation::try_from(reader.read_u8().context("reading operation")?)?;
+        let name_length = reader
+            .read_u16::<LittleEndian>()
+            .context("reading resource name length")?;
+        let mut name = vec![0; name_length as usize];
+        reader
+            .read_exact(&mut name)
+            .context("reading resource name")?;
+        let name = String::from_utf8(name)
+            .map_err(|_| anyhow!("resource name in packed resources patch is not UTF-8"))?;
+
+        operations.push((operation, name));
+    }
+
+    let resources_data = &reader.get_ref()[reader.position() as usize..];
+
+    let resources = parse_resources(resources_data)?
+        .into_iter()
+        .map(|resource| (resource.name.to_string(), resource))
+        .collect::<HashMap<_, _>>();
+
+    let mut result = apply_operations(
+        parse_resources(data)?,
+        &operations,
+        resources,
+        &WriteOptions {
+            interior_padding,
+            blob_alignment,
+            name_index: false,
+        },
+    )?;
+
+    if sha256(&result) != result_digest {
+        return Err(anyhow!("packed resources patch produced unexpected data"));
+    }
+
+    if let Some(signature) = signature {
+        append_signature(&mut result, &signature);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::borrow::Cow};
+
+    fn resource(name: &str, source: &[u8]) -> Resource<'static, u8> {
+        Resource {
+            name: Cow::Owned(name.to_string()),
+            is_python_module: true,
+            in_memory_source: Some(Cow::Owned(source.to_vec())),
+            ..Default::default()
+        }
+    }
+
+    fn packed_resources(resources: &[Resource<'_, u8>]) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_packed_resources_with_options(resources, &mut data, &WriteOptions::default())
+            .unwrap();
+
+        data
+    }
+
+    #[test]
+    fn test_create_apply() -> Result<()> {
+        let old = packed_resources(&[
+            resource("foo", b"foo"),
+            resource("bar", b"bar"),
+            resource("baz", b"baz"),
+        ]);
+        let new = packed_resources(&[
+            resource("foo", b"foo"),
+            resource("bar", b"bar2"),
+            resource("qux", b"qux"),
+        ]);
+
+        let patch = create_packed_resources_patch(&old, &new, &WriteOptions::default(), None)?;
+        assert!(patch.starts_with(PATCH_HEADER_V1));
+
+        let patched = apply_packed_resources_patch(&old, &patch)?;
+        let resources = parse_resources(&patched)?;
+        assert_eq!(
+            resources,
+            vec![
+                resource("foo", b"foo"),
+                resource("bar", b"bar2"),
+                resource("qux", b"qux"),
+            ]
+        );
+
+        // Unchanged resources aren't in the patch.
+        assert!(!patch.windows(3).any(|window| window == b"foo"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_result() -> Result<()> {
+        let secret_key = [42; 32];
+        let public_key = crate::ed25519_public_key(&secret_key)?;
+
+        let old = crate::sign_packed_resources(
+            &packed_resources(&[resource("foo", b"foo")]),
+            &secret_key,
+        )?;
+        let new = packed_resources(&[resource("foo", b"foo2")]);
+
+        let patch =
+            create_packed_resources_patch(&old, &new, &WriteOptions::default(), Some(&secret_key))?;
+        let patched = apply_packed_resources_patch(&old, &patch)?;
+
+        let verified = crate::verify_packed_resources_signature(&patched, &public_key)
+            .map_err(|e| anyhow!(e))?;
+        assert_eq!(verified, new.as_slice());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_failures() -> Result<()> {
+        let old = packed_resources(&[resource("foo", b"foo")]);
+        let new = packed_resources(&[resource("foo", b"foo2")]);
+
+        let patch = create_packed_resources_patch(&old, &new, &WriteOptions::default(), None)?;
+
+        assert_eq!(
+            apply_packed_resources_patch(&old, &old)
+                .unwrap_err()
+                .to_string(),
+            "data is not a packed resources patch"
+        );
+        assert_eq!(
+            apply_packed_resources_patch(&new, &patch)
+                .unwrap_err()
+                .to_string(),
+            "packed resources patch does not apply to this data"
+        );
+
+        let mut corrupted = patch.clone();
+        let offset = corrupted
+            .windows(4)
+            .position(|window| window == b"foo2")
+            .unwrap();
+        corrupted[offset] = b'x';
+        assert_eq!(
+            apply_packed_resources_patch(&old, &corrupted)
+                .unwrap_err()
+                .to_string(),
+            "packed resources patch produced unexpected data"
+        );
+
+        Ok(())
+    }
+}
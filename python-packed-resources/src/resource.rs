@@ -8,6 +8,9 @@
 
 use std::{borrow::Cow, collections::HashMap, path::Path};
 
+#[cfg(feature = "sha2")]
+use crate::serialization::ResourceField;
+
 /// Represents an indexed resource.
 ///
 /// The resource has a name and type affinity via various `is_*` fields.
@@ -115,6 +118,35 @@ where
 
     /// Holds arbitrary file data in a relative path encoded in UTF-8.
     pub file_data_utf8_relative_path: Option<Cow<'a, str>>,
+
+    /// Whether in-memory data fields hold zstandard compressed data.
+    ///
+    /// If set, every `in_memory_*` data value and `file_data_embedded` is a
+    /// zstandard frame which must be decompressed before use. Names and paths
+    /// are never compressed.
+    pub in_memory_zstd_compressed: bool,
+
+    /// Whether in-memory data fields hold encrypted data.
+    ///
+    /// If set, every `in_memory_*` data value and `file_data_embedded` is
+    /// encrypted and must be decrypted with the key it was encrypted with
+    /// before use. Encryption is applied after compression. Names and paths
+    /// are never encrypted.
+    pub in_memory_encrypted: bool,
+
+    /// SHA-256 digest of in-memory data.
+    ///
+    /// The digest covers every `in_memory_*` data value and `file_data_embedded`
+    /// as stored, i.e. after compression and encryption. It can be used to detect corruption of
+    /// the stored data.
+    pub in_memory_sha256: Option<[u8; 32]>,
+
+    /// Arbitrary key-value metadata attached to the resource.
+    ///
+    /// Metadata doesn't influence how the resource is loaded. It allows
+    /// resources to be annotated with e.g. their license or the wheel they
+    /// came from for consumption by applications.
+    pub metadata: Option<HashMap<Cow<'a, str>, Cow<'a, str>>>,
 }
 
 impl<'a, X> Default for Resource<'a, X>
@@ -151,6 +183,10 @@ where
             file_executable: false,
             file_data_embedded: None,
             file_data_utf8_relative_path: None,
+            in_memory_zstd_compressed: false,
+            in_memory_encrypted: false,
+            in_memory_sha256: None,
+            metadata: None,
         }
     }
 }
@@ -171,11 +207,31 @@ where
     /// Merge another resource into this one.
     ///
     /// Fields from other will overwrite fields from self.
+    ///
+    /// Both resources must agree on whether in-memory data is zstandard
+    /// compressed and encrypted, as these flags apply to all in-memory fields.
+    ///
+    /// The digest of in-memory data is only retained if in-memory data
+    /// comes from a single resource.
     pub fn merge_from(&mut self, other: Resource<'a, X>) -> Result<(), &'static str> {
         if self.name != other.name {
             return Err("resource names must be identical to perform a merge");
         }
 
+        if self.in_memory_zstd_compressed != other.in_memory_zstd_compressed {
+            return Err("resources must have identical compression to perform a merge");
+        }
+
+        if self.in_memory_encrypted != other.in_memory_encrypted {
+            return Err("resources must have identical encryption to perform a merge");
+        }
+
+        self.in_memory_sha256 = match (self.has_in_memory_data(), other.has_in_memory_data()) {
+            (_, false) => self.in_memory_sha256,
+            (false, true) => other.in_memory_sha256,
+            (true, true) => None,
+        };
+
         self.is_python_module |= other.is_python_module;
         self.is_python_builtin_extension_module |= other.is_python_builtin_extension_module;
         self.is_python_frozen_module |= other.is_python_frozen_module;
@@ -243,10 +299,101 @@ where
         if let Some(value) = other.file_data_utf8_relative_path {
             self.file_data_utf8_relative_path.replace(value);
         }
+        if let Some(value) = other.metadata {
+            self.metadata.replace(value);
+        }
 
         Ok(())
     }
 
+    /// Whether the resource has in-memory data.
+    fn has_in_memory_data(&self) -> bool {
+        self.in_memory_source.is_some()
+            || self.in_memory_bytecode.is_some()
+            || self.in_memory_bytecode_opt1.is_some()
+            || self.in_memory_bytecode_opt2.is_some()
+            || self.in_memory_extension_module_shared_library.is_some()
+            || self.in_memory_package_resources.is_some()
+            || self.in_memory_distribution_resources.is_some()
+            || self.in_memory_shared_library.is_some()
+            || self.file_data_embedded.is_some()
+    }
+
+    /// Obtain a copy of this resource that borrows all data from it.
+    pub fn to_borrowed(&self) -> Resource<'_, X> {
+        fn borrow_map<'r, V: ?Sized + ToOwned>(
+            map: &'r Option<HashMap<Cow<str>, Cow<V>>>,
+        ) -> Option<HashMap<Cow<'r, str>, Cow<'r, V>>> {
+            map.as_ref().map(|value| {
+                value
+                    .iter()
+                    .map(|(k, v)| (Cow::Borrowed(k.as_ref()), Cow::Borrowed(v.as_ref())))
+                    .collect()
+            })
+        }
+
+        Resource {
+            name: Cow::Borrowed(self.name.as_ref()),
+            is_python_module: self.is_python_module,
+            is_python_builtin_extension_module: self.is_python_builtin_extension_module,
+            is_python_frozen_module: self.is_python_frozen_module,
+            is_python_extension_module: self.is_python_extension_module,
+            is_shared_library: self.is_shared_library,
+            is_utf8_filename_data: self.is_utf8_filename_data,
+            is_python_package: self.is_python_package,
+            is_python_namespace_package: self.is_python_namespace_package,
+            in_memory_source: self.in_memory_source.as_deref().map(Cow::Borrowed),
+            in_memory_bytecode: self.in_memory_bytecode.as_deref().map(Cow::Borrowed),
+            in_memory_bytecode_opt1: self.in_memory_bytecode_opt1.as_deref().map(Cow::Borrowed),
+            in_memory_bytecode_opt2: self.in_memory_bytecode_opt2.as_deref().map(Cow::Borrowed),
+            in_memory_extension_module_shared_library: self
+                .in_memory_extension_module_shared_library
+                .as_deref()
+                .map(Cow::Borrowed),
+            in_memory_package_resources: borrow_map(&self.in_memory_package_resources),
+            in_memory_distribution_resources: borrow_map(&self.in_memory_distribution_resources),
+            in_memory_shared_library: self.in_memory_shared_library.as_deref().map(Cow::Borrowed),
+            shared_library_dependency_names: self
+                .shared_library_dependency_names
+                .as_ref()
+                .map(|value| value.iter().map(|x| Cow::Borrowed(x.as_ref())).collect()),
+            relative_path_module_source: self
+                .relative_path_module_source
+                .as_deref()
+                .map(Cow::Borrowed),
+            relative_path_module_bytecode: self
+                .relative_path_module_bytecode
+                .as_deref()
+                .map(Cow::Borrowed),
+            relative_path_module_bytecode_opt1: self
+                .relative_path_module_bytecode_opt1
+                .as_deref()
+                .map(Cow::Borrowed),
+            relative_path_module_bytecode_opt2: self
+                .relative_path_module_bytecode_opt2
+                .as_deref()
+                .map(Cow::Borrowed),
+            relative_path_extension_module_shared_library: self
+                .relative_path_extension_module_shared_library
+                .as_deref()
+                .map(Cow::Borrowed),
+            relative_path_package_resources: borrow_map(&self.relative_path_package_resources),
+            relative_path_distribution_resources: borrow_map(
+                &self.relative_path_distribution_resources,
+            ),
+            file_executable: self.file_executable,
+            file_data_embedded: self.file_data_embedded.as_deref().map(Cow::Borrowed),
+            file_data_utf8_relative_path: self
+                .file_data_utf8_relative_path
+                .as_deref()
+                .map(Cow::Borrowed),
+            in_memory_zstd_compressed: self.in_memory_zstd_compressed,
+            in_memory_encrypted: self.in_memory_encrypted,
+            in_memory_sha256: self.in_memory_sha256,
+            metadata: borrow_map(&self.metadata),
+        }
+    }
+
     pub fn to_owned(&self) -> Resource<'static, X> {
         Resource {
             name: Cow::Owned(self.name.clone().into_owned()),
@@ -370,6 +517,371 @@ where
                 .file_data_utf8_relative_path
                 .as_ref()
                 .map(|value| Cow::Owned(value.clone().into_owned())),
+            in_memory_zstd_compressed: self.in_memory_zstd_compressed,
+            in_memory_encrypted: self.in_memory_encrypted,
+            in_memory_sha256: self.in_memory_sha256,
+            metadata: self.metadata.as_ref().map(|value| {
+                value
+                    .iter()
+                    .map(|(k, v)| {
+                        (
+                            Cow::Owned(k.clone().into_owned()),
+                            Cow::Owned(v.clone().into_owned()),
+                        )
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<'a> Resource<'a, u8> {
+    /// Compress in-memory data with zstandard at the given compression level.
+    ///
+    /// Resources without in-memory data or whose data is already compressed
+    /// are returned unmodified.
+    pub fn zstd_compress(self, level: i32) -> anyhow::Result<Self> {
+        if self.in_memory_zstd_compressed || !self.has_in_memory_data() {
+            return Ok(self);
         }
+
+        let mut resource =
+            self.map_in_memory_data(|data| Ok(zstd::bulk::compress(data, level)?))?;
+        resource.in_memory_zstd_compressed = true;
+
+        Ok(resource)
+    }
+
+    /// Decompress zstandard compressed in-memory data.
+    ///
+    /// Resources whose data isn't compressed are returned unmodified.
+    pub fn zstd_decompress(self) -> anyhow::Result<Self> {
+        if !self.in_memory_zstd_compressed {
+            return Ok(self);
+        }
+
+        let mut resource = self.map_in_memory_data(|data| Ok(zstd::stream::decode_all(data)?))?;
+        resource.in_memory_zstd_compressed = false;
+
+        Ok(resource)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<'a> Resource<'a, u8> {
+    /// Encrypt in-memory data with a 32 byte key.
+    ///
+    /// Resources without in-memory data or whose data is already encrypted
+    /// are returned unmodified.
+    pub fn encrypt(self, key: &[u8; 32]) -> anyhow::Result<Self> {
+        if self.in_memory_encrypted || !self.has_in_memory_data() {
+            return Ok(self);
+        }
+
+        let mut resource =
+            self.map_in_memory_data(|data| Ok(crate::encryption::encrypt(key, data)))?;
+        resource.in_memory_encrypted = true;
+
+        Ok(resource)
+    }
+
+    /// Decrypt encrypted in-memory data with the key it was encrypted with.
+    ///
+    /// Resources whose data isn't encrypted are returned unmodified.
+    pub fn decrypt(self, key: &[u8; 32]) -> anyhow::Result<Self> {
+        if !self.in_memory_encrypted {
+            return Ok(self);
+        }
+
+        let mut resource = self.map_in_memory_data(|data| {
+            crate::encryption::decrypt(key, data).map_err(|e| anyhow::anyhow!(e))
+        })?;
+        resource.in_memory_encrypted = false;
+
+        Ok(resource)
+    }
+}
+
+#[cfg(any(feature = "encryption", feature = "zstd"))]
+impl<'a> Resource<'a, u8> {
+    fn map_in_memory_data(
+        self,
+        f: impl Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
+    ) -> anyhow::Result<Self> {
+        let map_data = |data: Option<Cow<'a, [u8]>>| -> anyhow::Result<Option<Cow<'a, [u8]>>> {
+            data.map(|data| f(&data).map(Cow::Owned)).transpose()
+        };
+        let map_resources = |resources: Option<HashMap<Cow<'a, str>, Cow<'a, [u8]>>>| {
+            resources
+                .map(|resources| {
+                    resources
+                        .into_iter()
+                        .map(|(name, data)| Ok((name, Cow::Owned(f(&data)?))))
+                        .collect::<anyhow::Result<HashMap<_, _>>>()
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            in_memory_source: map_data(self.in_memory_source)?,
+            in_memory_bytecode: map_data(self.in_memory_bytecode)?,
+            in_memory_bytecode_opt1: map_data(self.in_memory_bytecode_opt1)?,
+            in_memory_bytecode_opt2: map_data(self.in_memory_bytecode_opt2)?,
+            in_memory_extension_module_shared_library: map_data(
+                self.in_memory_extension_module_shared_library,
+            )?,
+            in_memory_package_resources: map_resources(self.in_memory_package_resources)?,
+            in_memory_distribution_resources: map_resources(self.in_memory_distribution_resources)?,
+            in_memory_shared_library: map_data(self.in_memory_shared_library)?,
+            file_data_embedded: map_data(self.file_data_embedded)?,
+            in_memory_sha256: None,
+            ..self
+        })
+    }
+}
+
+#[cfg(feature = "sha2")]
+impl<'a> Resource<'a, u8> {
+    /// Compute the SHA-256 digest of in-memory data.
+    ///
+    /// Each value is hashed with its field type and length. Entries of
+    /// resource mappings are hashed in name order.
+    pub fn compute_in_memory_sha256(&self) -> [u8; 32] {
+        use sha2::Digest;
+
+        let mut hasher = sha2::Sha256::new();
+
+        let mut update = |field: ResourceField, key: Option<&str>, data: &[u8]| {
+            hasher.update([u8::from(field)]);
+            if let Some(key) = key {
+                hasher.update((key.len() as u64).to_le_bytes());
+                hasher.update(key.as_bytes());
+            }
+            hasher.update((data.len() as u64).to_le_bytes());
+            hasher.update(data);
+        };
+
+        for (field, data) in [
+            (ResourceField::InMemorySource, &self.in_memory_source),
+            (ResourceField::InMemoryBytecode, &self.in_memory_bytecode),
+            (
+                ResourceField::InMemoryBytecodeOpt1,
+                &self.in_memory_bytecode_opt1,
+            ),
+            (
+                ResourceField::InMemoryBytecodeOpt2,
+                &self.in_memory_bytecode_opt2,
+            ),
+            (
+                ResourceField::InMemoryExtensionModuleSharedLibrary,
+                &self.in_memory_extension_module_shared_library,
+            ),
+            (
+                ResourceField::InMemorySharedLibrary,
+                &self.in_memory_shared_library,
+            ),
+            (ResourceField::FileDataEmbedded, &self.file_data_embedded),
+        ] {
+            if let Some(data) = data {
+                update(field, None, data);
+            }
+        }
+
+        for (field, resources) in [
+            (
+                ResourceField::InMemoryResourcesData,
+                &self.in_memory_package_resources,
+            ),
+            (
+                ResourceField::InMemoryDistributionResource,
+                &self.in_memory_distribution_resources,
+            ),
+        ] {
+            if let Some(resources) = resources {
+                let mut entries = resources.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+
+                for (key, data) in entries {
+                    update(field, Some(key), data);
+                }
+            }
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// Record the SHA-256 digest of in-memory data.
+    ///
+    /// Resources without in-memory data are returned unmodified.
+    pub fn with_in_memory_sha256(mut self) -> Self {
+        if self.has_in_memory_data() {
+            self.in_memory_sha256 = Some(self.compute_in_memory_sha256());
+        }
+
+        self
+    }
+
+    /// Verify in-memory data against its recorded SHA-256 digest.
+    ///
+    /// Resources without a recorded digest always verify.
+    pub fn verify_in_memory_sha256(&self) -> Result<(), &'static str> {
+        match &self.in_memory_sha256 {
+            Some(digest) if digest != &self.compute_in_memory_sha256() => {
+                Err("resource data does not match its SHA-256 digest")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_sha256() {
+        let mut resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            in_memory_sha256: Some([1; 32]),
+            ..Resource::<u8>::default()
+        };
+
+        // Merging a resource without in-memory data retains the digest.
+        resource
+            .merge_from(Resource {
+                name: Cow::from("foo"),
+                is_python_package: true,
+                ..Resource::default()
+            })
+            .unwrap();
+        assert_eq!(resource.in_memory_sha256, Some([1; 32]));
+
+        // Merging in-memory data from both resources drops the digest.
+        resource
+            .merge_from(Resource {
+                name: Cow::from("foo"),
+                in_memory_bytecode: Some(Cow::from(b"bytecode".to_vec())),
+                in_memory_sha256: Some([2; 32]),
+                ..Resource::default()
+            })
+            .unwrap();
+        assert_eq!(resource.in_memory_sha256, None);
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_sha256() {
+        let mut package_resources = HashMap::new();
+        package_resources.insert(Cow::from("data.txt"), Cow::from(b"data".to_vec()));
+
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"import io\n".to_vec())),
+            in_memory_package_resources: Some(package_resources),
+            ..Resource::default()
+        }
+        .with_in_memory_sha256();
+
+        assert!(resource.in_memory_sha256.is_some());
+        assert!(resource.verify_in_memory_sha256().is_ok());
+
+        let mut corrupted = resource.clone();
+        corrupted
+            .in_memory_package_resources
+            .as_mut()
+            .unwrap()
+            .insert(Cow::from("data.txt"), Cow::from(b"dat4".to_vec()));
+        assert_eq!(
+            corrupted.verify_in_memory_sha256(),
+            Err("resource data does not match its SHA-256 digest")
+        );
+
+        let no_data = Resource {
+            name: Cow::from("foo"),
+            is_python_namespace_package: true,
+            ..Resource::<u8>::default()
+        }
+        .with_in_memory_sha256();
+        assert_eq!(no_data.in_memory_sha256, None);
+        assert!(no_data.verify_in_memory_sha256().is_ok());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() -> anyhow::Result<()> {
+        let mut package_resources = HashMap::new();
+        package_resources.insert(Cow::from("data.txt"), Cow::from(vec![b'a'; 4096]));
+
+        let resource = Resource {
+            name: Cow::from("foo"),
+            is_python_package: true,
+            in_memory_source: Some(Cow::from(b"import io\n".repeat(100))),
+            in_memory_package_resources: Some(package_resources),
+            relative_path_module_source: Some(Cow::from(Path::new("foo.py"))),
+            ..Resource::default()
+        };
+
+        let compressed = resource.clone().zstd_compress(3)?;
+        assert!(compressed.in_memory_zstd_compressed);
+        assert!(compressed.is_python_package);
+        assert_eq!(
+            compressed.relative_path_module_source,
+            resource.relative_path_module_source
+        );
+        assert!(compressed.in_memory_source.as_ref().unwrap().len() < 1000);
+        assert!(compressed.in_memory_package_resources.as_ref().unwrap()["data.txt"].len() < 4096);
+
+        // Compressing again is a no-op.
+        assert_eq!(compressed.clone().zstd_compress(3)?, compressed);
+
+        assert_eq!(compressed.zstd_decompress()?, resource);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_compress_no_data() -> anyhow::Result<()> {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            is_python_namespace_package: true,
+            ..Resource::<u8>::default()
+        };
+
+        assert_eq!(resource.clone().zstd_compress(3)?, resource);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encryption_round_trip() -> anyhow::Result<()> {
+        let key = [42; 32];
+
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"import io\n".to_vec())),
+            in_memory_bytecode: Some(Cow::from(b"bytecode".to_vec())),
+            relative_path_module_source: Some(Cow::from(Path::new("foo.py"))),
+            ..Resource::default()
+        };
+
+        let encrypted = resource.clone().encrypt(&key)?;
+        assert!(encrypted.in_memory_encrypted);
+        assert_ne!(encrypted.in_memory_source, resource.in_memory_source);
+        assert_eq!(
+            encrypted.relative_path_module_source,
+            resource.relative_path_module_source
+        );
+
+        // Encrypting again is a no-op.
+        assert_eq!(encrypted.clone().encrypt(&key)?, encrypted);
+
+        assert!(encrypted.clone().decrypt(&[1; 32]).is_err());
+        assert_eq!(encrypted.decrypt(&key)?, resource);
+
+        Ok(())
     }
 }
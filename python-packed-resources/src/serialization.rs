@@ -8,9 +8,28 @@
 
 /*! Declares the foundational data primitives inside packed resources data. */
 
-/// Header value for version 2 of resources payload.
+/// Prefix of the header value of all versions of resources payload.
+///
+/// The header is this prefix followed by a `u8` format version.
+pub const HEADER_PREFIX: &[u8] = b"pyembed";
+
+/// Header value for version 3 of resources payload.
 pub const HEADER_V3: &[u8] = b"pyembed\x03";
 
+/// Header value for version 4 of resources payload.
+///
+/// Version 4 adds resource field types `0x1f` to `0x23`, the name index blob
+/// section (`0x24`), and the blob index alignment field. Fields can't be added
+/// to a format version once it is released: readers of that version would
+/// fail on them. New fields require a new format version.
+pub const HEADER_V4: &[u8] = b"pyembed\x04";
+
+/// Oldest format version of resources payload the parser can read.
+pub const MIN_FORMAT_VERSION: u8 = 3;
+
+/// Newest format version of resources payload the parser can read.
+pub const MAX_FORMAT_VERSION: u8 = 4;
+
 /// Defines interior padding mechanism between entries in blob sections.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BlobInteriorPadding {
@@ -43,6 +62,7 @@ pub enum BlobSectionField {
     ResourceFieldType = 0x03,
     RawPayloadLength = 0x04,
     InteriorPadding = 0x05,
+    Alignment = 0x06,
 }
 
 impl From<BlobSectionField> for u8 {
@@ -53,6 +73,7 @@ impl From<BlobSectionField> for u8 {
             BlobSectionField::ResourceFieldType => 0x02,
             BlobSectionField::RawPayloadLength => 0x03,
             BlobSectionField::InteriorPadding => 0x04,
+            BlobSectionField::Alignment => 0x05,
             BlobSectionField::EndOfEntry => 0xff,
         }
     }
@@ -68,6 +89,7 @@ impl TryFrom<u8> for BlobSectionField {
             0x02 => Ok(BlobSectionField::ResourceFieldType),
             0x03 => Ok(BlobSectionField::RawPayloadLength),
             0x04 => Ok(BlobSectionField::InteriorPadding),
+            0x05 => Ok(BlobSectionField::Alignment),
             0xff => Ok(BlobSectionField::EndOfEntry),
             _ => Err("invalid blob index field type"),
         }
@@ -109,6 +131,12 @@ pub enum ResourceField {
     FileExecutable = 0x1c,
     FileDataEmbedded = 0x1d,
     FileDataUtf8RelativePath = 0x1e,
+    InMemoryZstdCompressed = 0x1f,
+    SharedBlobReferences = 0x20,
+    InMemorySha256 = 0x21,
+    InMemoryEncrypted = 0x22,
+    Metadata = 0x23,
+    NameIndex = 0x24,
 }
 
 impl From<ResourceField> for u8 {
@@ -144,11 +172,35 @@ impl From<ResourceField> for u8 {
             ResourceField::FileExecutable => 0x1c,
             ResourceField::FileDataEmbedded => 0x1d,
             ResourceField::FileDataUtf8RelativePath => 0x1e,
+            ResourceField::InMemoryZstdCompressed => 0x1f,
+            ResourceField::SharedBlobReferences => 0x20,
+            ResourceField::InMemorySha256 => 0x21,
+            ResourceField::InMemoryEncrypted => 0x22,
+            ResourceField::Metadata => 0x23,
+            ResourceField::NameIndex => 0x24,
             ResourceField::EndOfEntry => 0xff,
         }
     }
 }
 
+impl ResourceField {
+    /// The format version that introduced this field.
+    ///
+    /// Writers must write data using this field with at least this version
+    /// and parsers reject the field in data of older versions.
+    pub fn format_version(&self) -> u8 {
+        match self {
+            ResourceField::InMemoryZstdCompressed
+            | ResourceField::SharedBlobReferences
+            | ResourceField::InMemorySha256
+            | ResourceField::InMemoryEncrypted
+            | ResourceField::Metadata
+            | ResourceField::NameIndex => 4,
+            _ => 3,
+        }
+    }
+}
+
 impl TryFrom<u8> for ResourceField {
     type Error = &'static str;
 
@@ -184,6 +236,12 @@ impl TryFrom<u8> for ResourceField {
             0x1c => Ok(ResourceField::FileExecutable),
             0x1d => Ok(ResourceField::FileDataEmbedded),
             0x1e => Ok(ResourceField::FileDataUtf8RelativePath),
+            0x1f => Ok(ResourceField::InMemoryZstdCompressed),
+            0x20 => Ok(ResourceField::SharedBlobReferences),
+            0x21 => Ok(ResourceField::InMemorySha256),
+            0x22 => Ok(ResourceField::InMemoryEncrypted),
+            0x23 => Ok(ResourceField::Metadata),
+            0x24 => Ok(ResourceField::NameIndex),
             0xff => Ok(ResourceField::EndOfEntry),
             _ => Err("invalid field type"),
         }
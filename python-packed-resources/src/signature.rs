@@ -0,0 +1,169 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! ed25519 signatures of packed resources data.
+
+A signed packed resources blob is the original blob followed by a
+64 byte ed25519 signature of that blob and the 8 byte
+[SIGNATURE_TRAILER_V1] magic. Since the parser ignores data after the
+last blob section, signed data can still be loaded without verifying
+the signature.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    ed25519_dalek::{ExpandedSecretKey, PublicKey, SecretKey, Signature},
+    std::convert::TryFrom,
+};
+
+/// Trailing magic value identifying packed resources data signed with ed25519.
+pub const SIGNATURE_TRAILER_V1: &[u8] = b"pyed255\x01";
+
+/// Length in bytes of an ed25519 signature.
+pub(crate) const SIGNATURE_LENGTH: usize = 64;
+
+/// Derive the ed25519 public key from a 32 byte secret key.
+pub fn ed25519_public_key(secret_key: &[u8]) -> Result<[u8; 32]> {
+    let secret = SecretKey::from_bytes(secret_key)
+        .map_err(|e| anyhow!("invalid ed25519 secret key: {}", e))?;
+
+    Ok(PublicKey::from(&secret).to_bytes())
+}
+
+/// Compute the ed25519 signature of data.
+pub(crate) fn ed25519_signature(data: &[u8], secret_key: &[u8]) -> Result<[u8; SIGNATURE_LENGTH]> {
+    let secret = SecretKey::from_bytes(secret_key)
+        .map_err(|e| anyhow!("invalid ed25519 secret key: {}", e))?;
+    let public = PublicKey::from(&secret);
+
+    Ok(ExpandedSecretKey::from(&secret)
+        .sign(data, &public)
+        .to_bytes())
+}
+
+/// Append a signature and the signature trailer to data.
+pub(crate) fn append_signature(data: &mut Vec<u8>, signature: &[u8; SIGNATURE_LENGTH]) {
+    data.extend_from_slice(signature);
+    data.extend_from_slice(SIGNATURE_TRAILER_V1);
+}
+
+/// Sign packed resources data with an ed25519 secret key.
+///
+/// Returns the data with the signature trailer appended.
+pub fn sign_packed_resources(data: &[u8], secret_key: &[u8]) -> Result<Vec<u8>> {
+    let signature = ed25519_signature(data, secret_key)?;
+
+    let mut res = Vec::with_capacity(data.len() + SIGNATURE_LENGTH + SIGNATURE_TRAILER_V1.len());
+    res.extend_from_slice(data);
+    append_signature(&mut res, &signature);
+
+    Ok(res)
+}
+
+/// Whether packed resources data ends with a signature trailer.
+pub fn is_signed_packed_resources(data: &[u8]) -> bool {
+    data.len() >= SIGNATURE_LENGTH + SIGNATURE_TRAILER_V1.len()
+        && data.ends_with(SIGNATURE_TRAILER_V1)
+}
+
+/// Verify the ed25519 signature of signed packed resources data.
+///
+/// Returns the packed resources data without the signature trailer.
+pub fn verify_packed_resources_signature<'a>(
+    data: &'a [u8],
+    public_key: &[u8; 32],
+) -> Result<&'a [u8], &'static str> {
+    if !is_signed_packed_resources(data) {
+        return Err("packed resources data is not signed");
+    }
+
+    let public = PublicKey::from_bytes(public_key).map_err(|_| "invalid ed25519 public key")?;
+
+    let signature_offset = data.len() - SIGNATURE_TRAILER_V1.len() - SIGNATURE_LENGTH;
+    let (payload, trailer) = data.split_at(signature_offset);
+    let signature = Signature::try_from(&trailer[0..SIGNATURE_LENGTH])
+        .map_err(|_| "invalid packed resources signature")?;
+
+    public
+        .verify_strict(payload, &signature)
+        .map_err(|_| "packed resources signature verification failed")?;
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::parser::load_resources, crate::writer::write_packed_resources_v3};
+
+    const SECRET_KEY: [u8; 32] = [42; 32];
+
+    fn packed_resources() -> Vec<u8> {
+        let resource = crate::resource::Resource {
+            name: "foo".into(),
+            in_memory_source: Some(b"import io".to_vec().into()),
+            ..Default::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+
+        data
+    }
+
+    #[test]
+    fn test_sign_verify() -> Result<()> {
+        let data = packed_resources();
+        let signed = sign_packed_resources(&data, &SECRET_KEY)?;
+        assert!(is_signed_packed_resources(&signed));
+        assert!(!is_signed_packed_resources(&data));
+
+        let public_key = ed25519_public_key(&SECRET_KEY)?;
+        assert_eq!(
+            verify_packed_resources_signature(&signed, &public_key),
+            Ok(data.as_slice())
+        );
+
+        // Signed data remains loadable without verification.
+        let resources = load_resources(&signed)
+            .unwrap()
+            .collect::<Result<Vec<_>, &'static str>>()
+            .unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].name, "foo");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_failures() -> Result<()> {
+        let data = packed_resources();
+        let public_key = ed25519_public_key(&SECRET_KEY)?;
+
+        assert_eq!(
+            verify_packed_resources_signature(&data, &public_key),
+            Err("packed resources data is not signed")
+        );
+
+        let mut signed = sign_packed_resources(&data, &SECRET_KEY)?;
+        let other_key = ed25519_public_key(&[1; 32])?;
+        assert_eq!(
+            verify_packed_resources_signature(&signed, &other_key),
+            Err("packed resources signature verification failed")
+        );
+
+        signed[data.len() - 1] ^= 0xff;
+        assert_eq!(
+            verify_packed_resources_signature(&signed, &public_key),
+            Err("packed resources signature verification failed")
+        );
+
+        assert!(sign_packed_resources(&data, &[0; 16]).is_err());
+
+        Ok(())
+    }
+}
@@ -10,12 +10,19 @@
 
 use {
     crate::{
+        name_index::build_name_index,
         resource::Resource,
-        serialization::{BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3},
+        serialization::{
+            BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3, HEADER_V4,
+        },
     },
     anyhow::{anyhow, Context, Result},
     byteorder::{LittleEndian, WriteBytesExt},
-    std::{collections::BTreeMap, io::Write, path::Path},
+    std::{
+        collections::{BTreeMap, HashMap},
+        io::Write,
+        path::Path,
+    },
 };
 
 #[cfg(unix)]
@@ -51,11 +58,113 @@ fn path_to_bytes(p: &Path) -> Vec<u8> {
     }
 }
 
+/// Obtain the entries of a map sorted by key.
+///
+/// Map entries are serialized in key order so output is deterministic.
+fn sorted_entries<K: Ord, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    entries
+}
+
+/// Number of bytes of interior padding following each blob.
+fn padding_overhead(padding: BlobInteriorPadding) -> usize {
+    match padding {
+        BlobInteriorPadding::None => 0,
+        BlobInteriorPadding::Null => 1,
+    }
+}
+
+/// Resource fields whose blobs are aligned when writing with an alignment.
+const ALIGNED_FIELDS: [ResourceField; 5] = [
+    ResourceField::InMemoryBytecode,
+    ResourceField::InMemoryBytecodeOpt1,
+    ResourceField::InMemoryBytecodeOpt2,
+    ResourceField::InMemoryExtensionModuleSharedLibrary,
+    ResourceField::InMemorySharedLibrary,
+];
+
+/// Number of bytes needed to advance `offset` to a multiple of `alignment`.
+fn alignment_padding(offset: usize, alignment: usize) -> usize {
+    (alignment - offset % alignment) % alignment
+}
+
+/// Options controlling the layout of written packed resources data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WriteOptions {
+    /// Interior padding between blobs in blob sections.
+    pub interior_padding: Option<BlobInteriorPadding>,
+
+    /// Alignment in bytes of in-memory bytecode and shared library blobs.
+    ///
+    /// When set, each such blob starts at an offset from the start of the
+    /// data that is a multiple of this value. A page size (e.g. 4096) allows
+    /// blobs of data loaded at a page aligned address (e.g. memory mapped) to
+    /// be mapped or executed in place. Must be a power of 2.
+    pub blob_alignment: Option<u32>,
+
+    /// Whether to write a perfect hash index of resource names.
+    ///
+    /// The index allows readers to look up resources by name without
+    /// building a hash table of all resources. It isn't written if resource
+    /// names aren't unique.
+    pub name_index: bool,
+}
+
+/// A writer counting the number of bytes written through it.
+struct CountingWriter<'w, W: Write> {
+    inner: &'w mut W,
+    count: usize,
+}
+
+impl<'w, W: Write> Write for CountingWriter<'w, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Minimum length of a blob for it to be considered for deduplication.
+///
+/// Shorter blobs cost less to store than the index entry referencing them.
+const MIN_SHARED_BLOB_LENGTH: usize = 32;
+
+/// Resource fields having blob sections, in the order they are written.
+const BLOB_FIELDS: [ResourceField; 19] = [
+    ResourceField::Name,
+    ResourceField::InMemorySource,
+    ResourceField::InMemoryBytecode,
+    ResourceField::InMemoryBytecodeOpt1,
+    ResourceField::InMemoryBytecodeOpt2,
+    ResourceField::InMemoryExtensionModuleSharedLibrary,
+    ResourceField::InMemoryResourcesData,
+    ResourceField::InMemoryDistributionResource,
+    ResourceField::InMemorySharedLibrary,
+    ResourceField::SharedLibraryDependencyNames,
+    ResourceField::RelativeFilesystemModuleSource,
+    ResourceField::RelativeFilesystemModuleBytecode,
+    ResourceField::RelativeFilesystemModuleBytecodeOpt1,
+    ResourceField::RelativeFilesystemModuleBytecodeOpt2,
+    ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
+    ResourceField::RelativeFilesystemPackageResources,
+    ResourceField::RelativeFilesystemDistributionResource,
+    ResourceField::FileDataEmbedded,
+    ResourceField::FileDataUtf8RelativePath,
+];
+
 #[derive(Debug)]
 struct BlobSection {
     resource_field: ResourceField,
     raw_payload_length: usize,
     interior_padding: Option<BlobInteriorPadding>,
+    alignment: Option<u32>,
 }
 
 impl BlobSection {
@@ -75,6 +184,11 @@ impl BlobSection {
             index += 2;
         }
 
+        if self.alignment.is_some() {
+            // Field + value.
+            index += 5;
+        }
+
         // End of index entry.
         index += 1;
 
@@ -102,6 +216,13 @@ impl BlobSection {
                 .context("writing interior padding value")?;
         }
 
+        if let Some(alignment) = self.alignment {
+            dest.write_u8(BlobSectionField::Alignment.into())
+                .context("writing alignment field")?;
+            dest.write_u32::<LittleEndian>(alignment)
+                .context("writing alignment value")?;
+        }
+
         dest.write_u8(BlobSectionField::EndOfEntry.into())
             .context("writing end of index entry")?;
 
@@ -109,6 +230,210 @@ impl BlobSection {
     }
 }
 
+/// A reference from a resource field to blob data written for another field.
+#[derive(Clone, Debug)]
+struct SharedBlobReference<'a> {
+    /// The resource field the blob data is for.
+    resource_field: ResourceField,
+    /// Key of the entry in the resource field, for fields holding maps.
+    key: Option<&'a str>,
+    /// Offset of the blob data from the start of blob data.
+    offset: u64,
+    /// Length of the blob data.
+    length: u64,
+}
+
+impl<'a> SharedBlobReference<'a> {
+    fn key_bytes(&self) -> &'a [u8] {
+        self.key.map(|key| key.as_bytes()).unwrap_or_default()
+    }
+
+    /// Compute length of the index entry for a set of references.
+    fn index_v1_length(references: &[Self]) -> usize {
+        if references.is_empty() {
+            0
+        } else {
+            // Field + count + field, key length, offset, and length of each reference.
+            3 + 19 * references.len()
+        }
+    }
+
+    /// Write the index entry for a set of references.
+    fn write_index_v1<W: Write>(references: &[Self], dest: &mut W) -> Result<()> {
+        if references.is_empty() {
+            return Ok(());
+        }
+
+        let l = u16::try_from(references.len())
+            .context("converting shared blob references count to u16")?;
+        dest.write_u8(ResourceField::SharedBlobReferences.into())
+            .context("writing shared blob references field")?;
+        dest.write_u16::<LittleEndian>(l)
+            .context("writing shared blob references count")?;
+
+        for reference in references {
+            let key_length = u16::try_from(reference.key_bytes().len())
+                .context("converting shared blob reference key length to u16")?;
+            dest.write_u8(reference.resource_field.into())
+                .context("writing shared blob reference field")?;
+            dest.write_u16::<LittleEndian>(key_length)
+                .context("writing shared blob reference key length")?;
+            dest.write_u64::<LittleEndian>(reference.offset)
+                .context("writing shared blob reference offset")?;
+            dest.write_u64::<LittleEndian>(reference.length)
+                .context("writing shared blob reference length")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Obtain the in-memory data for a resource field holding a single blob.
+fn in_memory_data<'r>(resource: &'r Resource<u8>, field: ResourceField) -> Option<&'r [u8]> {
+    match field {
+        ResourceField::InMemorySource => resource.in_memory_source.as_deref(),
+        ResourceField::InMemoryBytecode => resource.in_memory_bytecode.as_deref(),
+        ResourceField::InMemoryBytecodeOpt1 => resource.in_memory_bytecode_opt1.as_deref(),
+        ResourceField::InMemoryBytecodeOpt2 => resource.in_memory_bytecode_opt2.as_deref(),
+        ResourceField::InMemoryExtensionModuleSharedLibrary => resource
+            .in_memory_extension_module_shared_library
+            .as_deref(),
+        ResourceField::InMemorySharedLibrary => resource.in_memory_shared_library.as_deref(),
+        ResourceField::FileDataEmbedded => resource.file_data_embedded.as_deref(),
+        _ => None,
+    }
+}
+
+/// Remove the in-memory data for a resource field holding a single blob.
+fn clear_in_memory_data(resource: &mut Resource<u8>, field: ResourceField) {
+    match field {
+        ResourceField::InMemorySource => resource.in_memory_source = None,
+        ResourceField::InMemoryBytecode => resource.in_memory_bytecode = None,
+        ResourceField::InMemoryBytecodeOpt1 => resource.in_memory_bytecode_opt1 = None,
+        ResourceField::InMemoryBytecodeOpt2 => resource.in_memory_bytecode_opt2 = None,
+        ResourceField::InMemoryExtensionModuleSharedLibrary => {
+            resource.in_memory_extension_module_shared_library = None
+        }
+        ResourceField::InMemorySharedLibrary => resource.in_memory_shared_library = None,
+        ResourceField::FileDataEmbedded => resource.file_data_embedded = None,
+        _ => {}
+    }
+}
+
+/// Deduplicate identical in-memory blobs across resources.
+///
+/// Blobs are visited in the order they will be written. Blobs whose content
+/// was already seen are removed from the returned resources and replaced by
+/// references to the offset of the first occurrence.
+#[allow(clippy::type_complexity)]
+fn deduplicate_blobs<'r, 'a: 'r, T: AsRef<Resource<'a, u8>>>(
+    resources: &'r [T],
+    padding: BlobInteriorPadding,
+    alignment: Option<usize>,
+) -> (Vec<Resource<'r, u8>>, Vec<Vec<SharedBlobReference<'r>>>) {
+    let overhead = padding_overhead(padding);
+
+    let mut stripped = resources
+        .iter()
+        .map(|resource| resource.as_ref().to_borrowed())
+        .collect::<Vec<_>>();
+    let mut references = vec![vec![]; resources.len()];
+
+    // Content of blobs seen so far to their offset.
+    let mut seen: HashMap<&'r [u8], u64> = HashMap::new();
+    // Offset of the next blob from start of blob data.
+    let mut offset = 0u64;
+
+    for field in BLOB_FIELDS {
+        let alignment = alignment.filter(|_| ALIGNED_FIELDS.contains(&field));
+
+        for (i, resource) in resources.iter().enumerate() {
+            let resource = resource.as_ref();
+
+            let map = match field {
+                ResourceField::InMemoryResourcesData => Some(&resource.in_memory_package_resources),
+                ResourceField::InMemoryDistributionResource => {
+                    Some(&resource.in_memory_distribution_resources)
+                }
+                _ => None,
+            };
+
+            if let Some(map) = map {
+                for (key, value) in map.iter().flat_map(sorted_entries) {
+                    let value: &'r [u8] = value;
+
+                    if value.len() >= MIN_SHARED_BLOB_LENGTH {
+                        if let Some(existing) = seen.get(value) {
+                            references[i].push(SharedBlobReference {
+                                resource_field: field,
+                                key: Some(key),
+                                offset: *existing,
+                                length: value.len() as u64,
+                            });
+
+                            let stripped_map = match field {
+                                ResourceField::InMemoryResourcesData => {
+                                    &mut stripped[i].in_memory_package_resources
+                                }
+                                _ => &mut stripped[i].in_memory_distribution_resources,
+                            };
+                            if let Some(m) = stripped_map {
+                                m.remove(key.as_ref());
+                            }
+
+                            continue;
+                        }
+                    }
+
+                    offset += (key.len() + overhead) as u64;
+                    if value.len() >= MIN_SHARED_BLOB_LENGTH {
+                        seen.insert(value, offset);
+                    }
+                    offset += (value.len() + overhead) as u64;
+                }
+            } else if let Some(data) = in_memory_data(resource, field) {
+                if data.len() >= MIN_SHARED_BLOB_LENGTH {
+                    // Blobs of aligned fields only reference aligned blobs.
+                    let existing = seen.get(data).filter(|existing| match alignment {
+                        Some(alignment) => alignment_padding(**existing as usize, alignment) == 0,
+                        None => true,
+                    });
+
+                    if let Some(existing) = existing {
+                        references[i].push(SharedBlobReference {
+                            resource_field: field,
+                            key: None,
+                            offset: *existing,
+                            length: data.len() as u64,
+                        });
+                        clear_in_memory_data(&mut stripped[i], field);
+
+                        continue;
+                    }
+                }
+
+                if let Some(alignment) = alignment {
+                    if !data.is_empty() {
+                        offset += alignment_padding(offset as usize, alignment) as u64;
+                    }
+                }
+
+                if data.len() >= MIN_SHARED_BLOB_LENGTH {
+                    seen.insert(data, offset);
+                }
+
+                offset += (data.len() + overhead) as u64;
+            } else {
+                offset += (stripped[i].field_blob_length(field)
+                    + stripped[i].field_blob_interior_padding_length(field, padding))
+                    as u64;
+            }
+        }
+    }
+
+    (stripped, references)
+}
+
 impl<'a, X: Clone + 'a> Resource<'a, X>
 where
     [X]: ToOwned<Owned = Vec<X>>,
@@ -264,6 +589,25 @@ where
             index += 5;
         }
 
+        if self.in_memory_zstd_compressed {
+            index += 1;
+        }
+
+        if self.in_memory_encrypted {
+            index += 1;
+        }
+
+        if self.in_memory_sha256.is_some() {
+            index += 33;
+        }
+
+        if let Some(metadata) = &self.metadata {
+            index += 5;
+
+            // u16 + u32 for key + value length.
+            index += 6 * metadata.len();
+        }
+
         // End of index entry.
         index += 1;
 
@@ -426,6 +770,21 @@ where
                     0
                 }
             }
+            ResourceField::InMemoryZstdCompressed => 0,
+            ResourceField::SharedBlobReferences => 0,
+            ResourceField::InMemorySha256 => 0,
+            ResourceField::InMemoryEncrypted => 0,
+            ResourceField::Metadata => {
+                if let Some(metadata) = &self.metadata {
+                    metadata
+                        .iter()
+                        .map(|(key, value)| key.len() + value.len())
+                        .sum()
+                } else {
+                    0
+                }
+            }
+            ResourceField::NameIndex => 0,
         }
     }
 
@@ -575,6 +934,18 @@ where
                     0
                 }
             }
+            ResourceField::InMemoryZstdCompressed => 0,
+            ResourceField::SharedBlobReferences => 0,
+            ResourceField::InMemorySha256 => 0,
+            ResourceField::InMemoryEncrypted => 0,
+            ResourceField::Metadata => {
+                if let Some(metadata) = &self.metadata {
+                    metadata.len() * 2
+                } else {
+                    0
+                }
+            }
+            ResourceField::NameIndex => 0,
         };
 
         let overhead = match padding {
@@ -587,6 +958,15 @@ where
 
     /// Write the version 1 index entry for a resource instance.
     pub fn write_index_v1<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.write_index_entry_v1(dest, &[])
+    }
+
+    /// Write the version 1 index entry for a resource instance and its shared blobs.
+    fn write_index_entry_v1<W: Write>(
+        &self,
+        dest: &mut W,
+        references: &[SharedBlobReference],
+    ) -> Result<()> {
         let name_len =
             u16::try_from(self.name.as_bytes().len()).context("converting name to u16")?;
 
@@ -662,7 +1042,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory resources data length")?;
 
-            for (name, value) in resources.iter() {
+            for (name, value) in sorted_entries(resources) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
@@ -680,7 +1060,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory package distribution length")?;
 
-            for (name, value) in metadata {
+            for (name, value) in sorted_entries(metadata) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting distribution name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
@@ -768,7 +1148,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path resources resources data length")?;
 
-            for (name, path) in resources.iter() {
+            for (name, path) in sorted_entries(resources) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 let path_length = u32::try_from(path_bytes_length(path))
@@ -788,7 +1168,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path distribution data length")?;
 
-            for (name, path) in metadata.iter() {
+            for (name, path) in sorted_entries(metadata) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 let path_length = u32::try_from(path_bytes_length(path))
@@ -853,6 +1233,44 @@ where
                 .context("writing file_data_utf_relative_path field")?;
         }
 
+        if self.in_memory_zstd_compressed {
+            dest.write_u8(ResourceField::InMemoryZstdCompressed.into())
+                .context("writing in_memory_zstd_compressed field")?;
+        }
+
+        if self.in_memory_encrypted {
+            dest.write_u8(ResourceField::InMemoryEncrypted.into())
+                .context("writing in_memory_encrypted field")?;
+        }
+
+        if let Some(digest) = &self.in_memory_sha256 {
+            dest.write_u8(ResourceField::InMemorySha256.into())
+                .context("writing in-memory SHA-256 field")?;
+            dest.write_all(digest)
+                .context("writing in-memory SHA-256 digest")?;
+        }
+
+        if let Some(metadata) = &self.metadata {
+            let l = u32::try_from(metadata.len()).context("converting metadata length to u32")?;
+            dest.write_u8(ResourceField::Metadata.into())
+                .context("writing metadata field")?;
+            dest.write_u32::<LittleEndian>(l)
+                .context("writing metadata length")?;
+
+            for (key, value) in sorted_entries(metadata) {
+                let key_length =
+                    u16::try_from(key.len()).context("converting metadata key length to u16")?;
+                let value_length = u32::try_from(value.len())
+                    .context("converting metadata value length to u32")?;
+                dest.write_u16::<LittleEndian>(key_length)
+                    .context("writing metadata key length")?;
+                dest.write_u32::<LittleEndian>(value_length)
+                    .context("writing metadata value length")?;
+            }
+        }
+
+        SharedBlobReference::write_index_v1(references, dest)?;
+
         dest.write_u8(ResourceField::EndOfEntry.into())
             .map_err(|_| anyhow!("error writing end of index entry"))?;
 
@@ -860,13 +1278,59 @@ where
     }
 }
 
+/// Whether a resource needs fields introduced in format version 4.
+///
+/// This must cover every field whose [ResourceField::format_version()] is 4.
+/// The name index and blob alignment are data-wide and checked by the caller.
+fn requires_format_v4(resource: &Resource<u8>, references: &[SharedBlobReference]) -> bool {
+    resource.in_memory_zstd_compressed
+        || resource.in_memory_encrypted
+        || resource.in_memory_sha256.is_some()
+        || resource.metadata.is_some()
+        || !references.is_empty()
+}
+
 /// Write packed resources data, version 3.
-#[allow(clippy::cognitive_complexity)]
+///
+/// Version 4 data is written if resources use fields introduced in that
+/// version. Otherwise the data is readable by version 3 parsers.
 pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     resources: &[T],
     dest: &mut W,
     interior_padding: Option<BlobInteriorPadding>,
 ) -> Result<()> {
+    write_packed_resources_with_options(
+        resources,
+        dest,
+        &WriteOptions {
+            interior_padding,
+            ..Default::default()
+        },
+    )
+}
+
+/// Write packed resources data with options controlling its layout.
+///
+/// Version 4 data is written if resources use fields or layout options
+/// introduced in that version. Otherwise the data is readable by version 3 parsers.
+#[allow(clippy::cognitive_complexity)]
+pub fn write_packed_resources_with_options<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
+    resources: &[T],
+    dest: &mut W,
+    options: &WriteOptions,
+) -> Result<()> {
+    let interior_padding = options.interior_padding;
+
+    if let Some(alignment) = options.blob_alignment {
+        if !alignment.is_power_of_two() {
+            return Err(anyhow!(
+                "blob alignment must be a power of 2: {}",
+                alignment
+            ));
+        }
+    }
+    let alignment = options.blob_alignment.map(|alignment| alignment as usize);
+
     let mut blob_sections = BTreeMap::new();
 
     let mut blob_section_count = 0;
@@ -876,16 +1340,14 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     // 1 for end of index field.
     let mut resource_index_length = 1;
 
-    let process_field = |blob_sections: &mut BTreeMap<ResourceField, BlobSection>,
-                         resource: &Resource<u8>,
-                         field: ResourceField| {
-        let padding = match &interior_padding {
-            Some(padding) => *padding,
-            None => BlobInteriorPadding::None,
-        };
+    let padding = match &interior_padding {
+        Some(padding) => *padding,
+        None => BlobInteriorPadding::None,
+    };
+
+    let (resources, references) = deduplicate_blobs(resources, padding, alignment);
 
-        let l = resource.field_blob_length(field)
-            + resource.field_blob_interior_padding_length(field, padding);
+    let mut add_to_section = |field: ResourceField, l: usize, alignment: Option<usize>| {
         if l > 0 {
             blob_sections
                 .entry(field)
@@ -893,12 +1355,13 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
                     resource_field: field,
                     raw_payload_length: 0,
                     interior_padding,
+                    alignment: alignment.map(|alignment| alignment as u32),
                 })
                 .raw_payload_length += l;
         }
     };
 
-    let add_interior_padding = |dest: &mut W| -> Result<()> {
+    let add_interior_padding = |dest: &mut CountingWriter<W>| -> Result<()> {
         if interior_padding == Some(BlobInteriorPadding::Null) {
             dest.write_all(b"\0")?;
         }
@@ -906,97 +1369,70 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         Ok(())
     };
 
-    for resource in resources {
-        let resource = resource.as_ref();
-        resource_index_length += resource.index_v1_length();
-
-        process_field(&mut blob_sections, resource, ResourceField::Name);
-        process_field(&mut blob_sections, resource, ResourceField::InMemorySource);
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::InMemoryBytecode,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::InMemoryBytecodeOpt1,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::InMemoryBytecodeOpt2,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::InMemoryExtensionModuleSharedLibrary,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::InMemoryResourcesData,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::InMemoryDistributionResource,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::InMemorySharedLibrary,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::SharedLibraryDependencyNames,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::RelativeFilesystemModuleSource,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::RelativeFilesystemModuleBytecode,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::RelativeFilesystemModuleBytecodeOpt1,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::RelativeFilesystemModuleBytecodeOpt2,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::RelativeFilesystemPackageResources,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::RelativeFilesystemDistributionResource,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::FileDataEmbedded,
-        );
-        process_field(
-            &mut blob_sections,
-            resource,
-            ResourceField::FileDataUtf8RelativePath,
-        );
+    for (resource, references) in resources.iter().zip(&references) {
+        resource_index_length +=
+            resource.index_v1_length() + SharedBlobReference::index_v1_length(references);
+    }
+
+    // Sections are laid out in field order. So blobs are visited in the order
+    // they are written to determine where alignment padding is needed.
+    let mut blob_offset = 0;
+    for field in BLOB_FIELDS {
+        let alignment = alignment.filter(|_| ALIGNED_FIELDS.contains(&field));
+
+        for resource in &resources {
+            let length = resource.field_blob_length(field);
+            let mut l = length + resource.field_blob_interior_padding_length(field, padding);
+
+            if let Some(alignment) = alignment {
+                if length > 0 {
+                    l += alignment_padding(blob_offset, alignment);
+                }
+            }
+
+            add_to_section(field, l, alignment);
+            blob_offset += l;
+        }
+    }
+
+    add_to_section(
+        ResourceField::SharedBlobReferences,
+        references
+            .iter()
+            .flatten()
+            .map(|reference| reference.key_bytes().len() + padding_overhead(padding))
+            .sum(),
+        None,
+    );
+
+    // Metadata follows shared blob reference keys since sections are laid out
+    // in field order.
+    add_to_section(
+        ResourceField::Metadata,
+        resources
+            .iter()
+            .map(|resource| {
+                resource.field_blob_length(ResourceField::Metadata)
+                    + resource.field_blob_interior_padding_length(ResourceField::Metadata, padding)
+            })
+            .sum(),
+        None,
+    );
+
+    let name_index = if options.name_index {
+        build_name_index(
+            &resources
+                .iter()
+                .map(|resource| resource.name.as_ref())
+                .collect::<Vec<_>>(),
+        )
+    } else {
+        None
+    };
+
+    // The name index is a single blob without interior padding.
+    if let Some(index) = &name_index {
+        add_to_section(ResourceField::NameIndex, index.len(), None);
     }
 
     for section in blob_sections.values() {
@@ -1004,7 +1440,33 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         blob_index_length += section.index_v1_length();
     }
 
-    dest.write_all(HEADER_V3)?;
+    let aligned = blob_sections
+        .values()
+        .any(|section| section.alignment.is_some());
+
+    // Blob offsets are aligned relative to the start of blob data. Pad the
+    // resources index so blob data starts at an aligned offset of the data.
+    let index_padding = if let (true, Some(alignment)) = (aligned, alignment) {
+        alignment_padding(
+            HEADER_V4.len() + 1 + 4 + 4 + 4 + blob_index_length + resource_index_length,
+            alignment,
+        )
+    } else {
+        0
+    };
+    resource_index_length += index_padding;
+
+    if aligned
+        || name_index.is_some()
+        || resources
+            .iter()
+            .zip(&references)
+            .any(|(resource, references)| requires_format_v4(resource, references))
+    {
+        dest.write_all(HEADER_V4)?;
+    } else {
+        dest.write_all(HEADER_V3)?;
+    }
 
     dest.write_u8(blob_section_count)?;
     dest.write_u32::<LittleEndian>(blob_index_length as u32)?;
@@ -1018,55 +1480,73 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     dest.write_u8(ResourceField::EndOfIndex.into())?;
 
     // Write the resources index.
-    for resource in resources {
-        resource.as_ref().write_index_v1(dest)?;
+    for (resource, references) in resources.iter().zip(&references) {
+        resource.write_index_entry_v1(dest, references)?;
     }
     dest.write_u8(ResourceField::EndOfIndex.into())?;
+    dest.write_all(&vec![0; index_padding])?;
+
+    let dest = &mut CountingWriter {
+        inner: dest,
+        count: 0,
+    };
+
+    let write_aligned = |dest: &mut CountingWriter<W>, data: &[u8]| -> Result<()> {
+        if let Some(alignment) = alignment {
+            if !data.is_empty() {
+                dest.write_all(&vec![0; alignment_padding(dest.count, alignment)])?;
+            }
+        }
+
+        dest.write_all(data)?;
+
+        Ok(())
+    };
 
     // Write blob data, one field at a time.
-    for resource in resources {
-        dest.write_all(resource.as_ref().name.as_bytes())?;
+    for resource in &resources {
+        dest.write_all(resource.name.as_bytes())?;
         add_interior_padding(dest)?;
     }
 
-    for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_source {
+    for resource in &resources {
+        if let Some(data) = &resource.in_memory_source {
             dest.write_all(data)?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_bytecode {
-            dest.write_all(data)?;
+    for resource in &resources {
+        if let Some(data) = &resource.in_memory_bytecode {
+            write_aligned(dest, data)?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_bytecode_opt1 {
-            dest.write_all(data)?;
+    for resource in &resources {
+        if let Some(data) = &resource.in_memory_bytecode_opt1 {
+            write_aligned(dest, data)?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_bytecode_opt2 {
-            dest.write_all(data)?;
+    for resource in &resources {
+        if let Some(data) = &resource.in_memory_bytecode_opt2 {
+            write_aligned(dest, data)?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_extension_module_shared_library {
-            dest.write_all(data)?;
+    for resource in &resources {
+        if let Some(data) = &resource.in_memory_extension_module_shared_library {
+            write_aligned(dest, data)?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(resources) = &resource.as_ref().in_memory_package_resources {
-            for (key, value) in resources.iter() {
+    for resource in &resources {
+        if let Some(resources) = &resource.in_memory_package_resources {
+            for (key, value) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(value)?;
@@ -1075,9 +1555,9 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         }
     }
 
-    for resource in resources {
-        if let Some(resources) = &resource.as_ref().in_memory_distribution_resources {
-            for (key, value) in resources {
+    for resource in &resources {
+        if let Some(resources) = &resource.in_memory_distribution_resources {
+            for (key, value) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(value)?;
@@ -1086,15 +1566,15 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         }
     }
 
-    for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_shared_library {
-            dest.write_all(data)?;
+    for resource in &resources {
+        if let Some(data) = &resource.in_memory_shared_library {
+            write_aligned(dest, data)?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(names) = &resource.as_ref().shared_library_dependency_names {
+    for resource in &resources {
+        if let Some(names) = &resource.shared_library_dependency_names {
             for name in names {
                 dest.write_all(name.as_bytes())?;
                 add_interior_padding(dest)?;
@@ -1102,47 +1582,44 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         }
     }
 
-    for resource in resources {
-        if let Some(path) = &resource.as_ref().relative_path_module_source {
+    for resource in &resources {
+        if let Some(path) = &resource.relative_path_module_source {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(path) = &resource.as_ref().relative_path_module_bytecode {
+    for resource in &resources {
+        if let Some(path) = &resource.relative_path_module_bytecode {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(path) = &resource.as_ref().relative_path_module_bytecode_opt1 {
+    for resource in &resources {
+        if let Some(path) = &resource.relative_path_module_bytecode_opt1 {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(path) = &resource.as_ref().relative_path_module_bytecode_opt2 {
+    for resource in &resources {
+        if let Some(path) = &resource.relative_path_module_bytecode_opt2 {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(path) = &resource
-            .as_ref()
-            .relative_path_extension_module_shared_library
-        {
+    for resource in &resources {
+        if let Some(path) = &resource.relative_path_extension_module_shared_library {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(resources) = &resource.as_ref().relative_path_package_resources {
-            for (key, path) in resources.iter() {
+    for resource in &resources {
+        if let Some(resources) = &resource.relative_path_package_resources {
+            for (key, path) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(&path_to_bytes(path))?;
@@ -1151,9 +1628,9 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         }
     }
 
-    for resource in resources {
-        if let Some(resources) = &resource.as_ref().relative_path_distribution_resources {
-            for (key, path) in resources {
+    for resource in &resources {
+        if let Some(resources) = &resource.relative_path_distribution_resources {
+            for (key, path) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(&path_to_bytes(path))?;
@@ -1162,20 +1639,40 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         }
     }
 
-    for resource in resources {
-        if let Some(data) = &resource.as_ref().file_data_embedded {
+    for resource in &resources {
+        if let Some(data) = &resource.file_data_embedded {
             dest.write_all(data)?;
             add_interior_padding(dest)?;
         }
     }
 
-    for resource in resources {
-        if let Some(path) = &resource.as_ref().file_data_utf8_relative_path {
+    for resource in &resources {
+        if let Some(path) = &resource.file_data_utf8_relative_path {
             dest.write_all(path.as_bytes())?;
             add_interior_padding(dest)?;
         }
     }
 
+    for reference in references.iter().flatten() {
+        dest.write_all(reference.key_bytes())?;
+        add_interior_padding(dest)?;
+    }
+
+    for resource in &resources {
+        if let Some(metadata) = &resource.metadata {
+            for (key, value) in sorted_entries(metadata) {
+                dest.write_all(key.as_bytes())?;
+                add_interior_padding(dest)?;
+                dest.write_all(value.as_bytes())?;
+                add_interior_padding(dest)?;
+            }
+        }
+    }
+
+    if let Some(index) = &name_index {
+        dest.write_all(index)?;
+    }
+
     Ok(())
 }
 
@@ -1247,4 +1744,45 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_write_format_version() -> Result<()> {
+        let resource = Resource {
+            name: Cow::Owned("foo".to_string()),
+            in_memory_source: Some(Cow::Owned(b"import io".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(std::slice::from_ref(&resource), &mut data, None)?;
+        assert_eq!(&data[0..8], HEADER_V3);
+
+        // Every field introduced in version 4 results in version 4 data.
+        let v4_resources = [
+            Resource {
+                in_memory_zstd_compressed: true,
+                ..resource.clone()
+            },
+            Resource {
+                in_memory_sha256: Some([0; 32]),
+                ..resource.clone()
+            },
+            Resource {
+                in_memory_encrypted: true,
+                ..resource.clone()
+            },
+            Resource {
+                metadata: Some([("license".into(), "MIT".into())].into()),
+                ..resource
+            },
+        ];
+
+        for resource in v4_resources {
+            let mut data = Vec::new();
+            write_packed_resources_v3(&[resource], &mut data, None)?;
+            assert_eq!(&data[0..8], HEADER_V4);
+        }
+
+        Ok(())
+    }
 }
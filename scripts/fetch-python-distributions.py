@@ -21,6 +21,7 @@ PythonDistributionRecord {{
     }},
     target_triple: "{target_triple}".to_string(),
     supports_prebuilt_extension_modules: {supports_prebuilt_extension_modules},
+    free_threaded: {free_threaded},
 }},
 """.strip()
 
@@ -91,16 +92,21 @@ def main():
             target_triple != "x86_64-unknown-linux-musl" and flavor != "static-noopt"
         )
 
+        # cpython-3.13.0+20241016-x86_64-unknown-linux-gnu-freethreaded+pgo+lto-full.tar.zst
+        free_threaded = "freethreaded" in flavor
+
         key = "%s-%s-%s" % (major_minor, target_triple, flavor)
 
         records[key] = {
             "name": name,
+            "flavor": flavor,
             "url": url,
             "major_minor": major_minor,
             "target_triple": target_triple,
             "supports_prebuilt_extension_modules": "true"
             if supports_prebuilt_extension_modules
             else "false",
+            "free_threaded": "true" if free_threaded else "false",
         }
 
     print("// This Source Code Form is subject to the terms of the Mozilla Public")
@@ -122,6 +128,16 @@ def main():
     )
     print("    let dists = vec![")
 
+    # Windows ARM64 distributions only exist in newer releases. So we
+    # include whatever the release provides.
+    windows_arm64 = [
+        format_record(records[key])
+        for key in sorted(records)
+        if records[key]["target_triple"] == "aarch64-pc-windows-msvc"
+        and records[key]["flavor"] in ("pgo", "shared-pgo")
+        and records[key]["major_minor"] in ("3.8", "3.9", "3.10")
+    ]
+
     lines = [
         "// Linux glibc linked.",
         format_record(records["3.8-x86_64-unknown-linux-gnu-pgo"]),
@@ -157,6 +173,7 @@ def main():
         format_record(records["3.8-x86_64-pc-windows-msvc-shared-pgo"]),
         format_record(records["3.9-x86_64-pc-windows-msvc-shared-pgo"]),
         format_record(records["3.10-x86_64-pc-windows-msvc-shared-pgo"]),
+        *windows_arm64,
         "",
         "// Windows static.",
         format_record(records["3.8-i686-pc-windows-msvc-static-noopt"]),
@@ -37,6 +37,8 @@ pub enum RunMode {
     None,
     /// Target is run by executing a path.
     Path { path: PathBuf },
+    /// Target is run by executing a path with arguments.
+    Command { path: PathBuf, args: Vec<String> },
 }
 
 /// Represents a resolved target.
@@ -49,21 +51,30 @@ pub struct ResolvedTarget {
     pub output_path: PathBuf,
 }
 
+/// Run an executable at `path` with `args`, from the directory containing it.
+fn run_executable(path: &Path, args: &[String]) -> Result<()> {
+    let mut command = std::process::Command::new(path);
+    command.args(args);
+
+    if let Some(parent) = path.parent() {
+        command.current_dir(parent);
+    }
+
+    let status = command.status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} failed: {}", path.display(), status))
+    }
+}
+
 impl ResolvedTarget {
     pub fn run(&self) -> Result<()> {
         match &self.run_mode {
             RunMode::None => Ok(()),
-            RunMode::Path { path } => {
-                let status = std::process::Command::new(&path)
-                    .current_dir(&path.parent().unwrap())
-                    .status()?;
-
-                if status.success() {
-                    Ok(())
-                } else {
-                    Err(anyhow!("cargo run failed"))
-                }
-            }
+            RunMode::Path { path } => run_executable(path, &[]),
+            RunMode::Command { path, args } => run_executable(path, args),
         }
     }
 }
@@ -96,6 +107,9 @@ pub struct Target {
     /// Other targets this one depends on.
     pub depends: Vec<String>,
 
+    /// Whether this target is a test, registered via `register_test_target()`.
+    pub test: bool,
+
     /// What calling callable returned, if it has been called.
     pub resolved_value: Option<Value>,
 
@@ -131,6 +145,11 @@ pub struct EnvironmentContext {
     /// List of targets to resolve.
     resolve_targets: Option<Vec<String>>,
 
+    /// Targets currently being resolved, outermost first.
+    ///
+    /// Used to detect dependency cycles.
+    resolving: Vec<String>,
+
     // TODO figure out a generic way to express build script mode.
     /// Name of default target to resolve in build script mode.
     pub default_build_script_target: Option<String>,
@@ -153,6 +172,7 @@ impl EnvironmentContext {
             targets_order: vec![],
             default_target: None,
             resolve_targets: None,
+            resolving: vec![],
             default_build_script_target: None,
             build_script_mode: false,
         }
@@ -262,6 +282,7 @@ impl EnvironmentContext {
             Target {
                 callable,
                 depends,
+                test: false,
                 resolved_value: None,
                 built_target: None,
             },
@@ -276,6 +297,53 @@ impl EnvironmentContext {
         }
     }
 
+    /// Register a named test target.
+    ///
+    /// Test targets are never default targets.
+    pub fn register_test_target(&mut self, target: String, callable: Value, depends: Vec<String>) {
+        if !self.targets.contains_key(&target) {
+            self.targets_order.push(target.clone());
+        }
+
+        self.targets.insert(
+            target,
+            Target {
+                callable,
+                depends,
+                test: true,
+                resolved_value: None,
+                built_target: None,
+            },
+        );
+    }
+
+    /// Obtain the names of test targets, in registration order.
+    pub fn test_targets(&self) -> Vec<String> {
+        self.targets_order
+            .iter()
+            .filter(|name| self.targets.get(*name).map(|t| t.test).unwrap_or(false))
+            .cloned()
+            .collect()
+    }
+
+    /// Obtain dependencies referring to targets that aren't registered.
+    ///
+    /// Returns pairs of the depending target and the missing dependency, in
+    /// target registration order.
+    pub fn unknown_dependencies(&self) -> Vec<(String, String)> {
+        self.targets_order
+            .iter()
+            .filter_map(|name| self.targets.get(name).map(|t| (name, t)))
+            .flat_map(|(name, target)| {
+                target
+                    .depends
+                    .iter()
+                    .filter(|depend| !self.targets.contains_key(*depend))
+                    .map(move |depend| (name.clone(), depend.clone()))
+            })
+            .collect()
+    }
+
     /// Determine what targets should be resolved.
     ///
     /// This isn't the full list of targets that will be resolved, only the main
@@ -745,19 +813,43 @@ pub fn get_context_value(type_values: &TypeValues) -> ValueResult {
         })
 }
 
+/// Format arguments to `print()` and the `log` methods as a message.
+fn format_message(args: &[Value]) -> String {
+    args.iter()
+        .map(|arg| arg.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// print(*args)
 fn starlark_print(args: &[Value]) -> ValueResult {
-    let mut parts = Vec::new();
-    let mut first = true;
-    for arg in args {
-        if !first {
-            parts.push(" ".to_string());
-        }
-        first = false;
-        parts.push(arg.to_string());
+    warn!("{}", format_message(args));
+
+    Ok(Value::new(NoneType::None))
+}
+
+/// The log target of messages logged by the `log` global.
+pub const STARLARK_LOG_TARGET: &str = "starlark";
+
+/// The type of the `log` global, emitting log messages from Starlark.
+///
+/// Messages go through the `log` crate with the [STARLARK_LOG_TARGET]
+/// target, so whether they are shown depends on the configured log level.
+#[derive(Default)]
+pub struct StarlarkLogger {}
+
+impl TypedValue for StarlarkLogger {
+    type Holder = Mutable<StarlarkLogger>;
+    const TYPE: &'static str = "Logger";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
     }
+}
 
-    warn!("{}", parts.join(""));
+/// Logger.debug(*args), Logger.info(*args), etc.
+fn starlark_log(level: log::Level, args: &[Value]) -> ValueResult {
+    log::log!(target: STARLARK_LOG_TARGET, level, "{}", format_message(args));
 
     Ok(Value::new(NoneType::None))
 }
@@ -789,6 +881,31 @@ fn starlark_register_target(
     Ok(Value::new(NoneType::None))
 }
 
+/// register_test_target(target, callable, depends=None)
+fn starlark_register_test_target(
+    type_values: &TypeValues,
+    target: String,
+    callable: Value,
+    depends: Value,
+) -> ValueResult {
+    required_type_arg("callable", "function", &callable)?;
+    optional_list_arg("depends", "string", &depends)?;
+
+    let depends = match depends.get_type() {
+        "list" => depends.iter()?.iter().map(|x| x.to_string()).collect(),
+        _ => Vec::new(),
+    };
+
+    let raw_context = get_context_value(type_values)?;
+    let mut context = raw_context
+        .downcast_mut::<EnvironmentContext>()?
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    context.register_test_target(target, callable, depends);
+
+    Ok(Value::new(NoneType::None))
+}
+
 /// resolve_target(target)
 ///
 /// This will return a Value returned from the called function.
@@ -808,8 +925,8 @@ fn starlark_resolve_target(
     // could get a double borrow.
     let target_entry = {
         let raw_context = get_context_value(type_values)?;
-        let context = raw_context
-            .downcast_ref::<EnvironmentContext>()
+        let mut context = raw_context
+            .downcast_mut::<EnvironmentContext>()?
             .ok_or(ValueError::IncorrectParameterType)?;
 
         // If we have a resolved value for this target, return it.
@@ -821,37 +938,54 @@ fn starlark_resolve_target(
             return Ok(v);
         }
 
+        if context.resolving.contains(&target) {
+            let mut cycle = context.resolving.clone();
+            cycle.push(target.clone());
+
+            return Err(ValueError::from(RuntimeError {
+                code: "BUILD_TARGETS",
+                message: format!("target dependency cycle: {}", cycle.join(" -> ")),
+                label: "resolve_target()".to_string(),
+            }));
+        }
+
         warn!("resolving target {}", target);
 
-        match context.get_target(&target) {
+        let entry = match context.get_target(&target) {
             Some(v) => Ok((*v).clone()),
             None => Err(ValueError::from(RuntimeError {
                 code: "BUILD_TARGETS",
                 message: format!("target {} does not exist", target),
                 label: "resolve_target()".to_string(),
             })),
-        }?
+        }?;
+
+        context.resolving.push(target.clone());
+
+        entry
     };
 
-    // Resolve target dependencies.
-    let mut args = Vec::new();
+    // Resolve target dependencies, then call the target's function.
+    let res = (|| {
+        let mut args = Vec::new();
 
-    for depend_target in target_entry.depends {
-        args.push(starlark_resolve_target(
-            type_values,
-            call_stack,
-            depend_target,
-        )?);
-    }
+        for depend_target in target_entry.depends {
+            args.push(starlark_resolve_target(
+                type_values,
+                call_stack,
+                depend_target,
+            )?);
+        }
 
-    let res = target_entry.callable.call(
-        call_stack,
-        type_values,
-        args,
-        LinkedHashMap::new(),
-        None,
-        None,
-    )?;
+        target_entry.callable.call(
+            call_stack,
+            type_values,
+            args,
+            LinkedHashMap::new(),
+            None,
+            None,
+        )
+    })();
 
     // TODO consider replacing the target's callable with a new function that returns the
     // resolved value. This will ensure a target function is only ever called once.
@@ -863,6 +997,9 @@ fn starlark_resolve_target(
         .downcast_mut::<EnvironmentContext>()?
         .ok_or(ValueError::IncorrectParameterType)?;
 
+    context.resolving.pop();
+    let res = res?;
+
     if let Some(target_entry) = context.get_target_mut(&target) {
         target_entry.resolved_value = Some(res.clone());
     }
@@ -945,6 +1082,15 @@ starlark_module! { build_targets_module =>
         starlark_register_target(env, target, callable, depends, default, default_build_script)
     }
 
+    register_test_target(
+        env env,
+        target: String,
+        callable,
+        depends = NoneType::None
+    ) {
+        starlark_register_test_target(env, target, callable, depends)
+    }
+
     resolve_target(env env, call_stack cs, target: String) {
         starlark_resolve_target(env, cs, target)
     }
@@ -956,6 +1102,22 @@ starlark_module! { build_targets_module =>
     set_build_path(env env, path: String) {
         starlark_set_build_path(env, path)
     }
+
+    Logger.debug(this, *args) {
+        starlark_log(log::Level::Debug, &args)
+    }
+
+    Logger.info(this, *args) {
+        starlark_log(log::Level::Info, &args)
+    }
+
+    Logger.warn(this, *args) {
+        starlark_log(log::Level::Warn, &args)
+    }
+
+    Logger.error(this, *args) {
+        starlark_log(log::Level::Error, &args)
+    }
 }
 
 /// Register our Starlark dialect with an environment and type values.
@@ -965,6 +1127,8 @@ pub fn register_starlark_dialect(
 ) -> Result<(), EnvironmentError> {
     build_targets_module(env, type_values);
 
+    env.set("log", Value::new(StarlarkLogger::default()))?;
+
     Ok(())
 }
 
@@ -985,6 +1149,7 @@ pub fn populate_environment(
     // these symbols.
     for f in &[
         "register_target",
+        "register_test_target",
         "resolve_target",
         "resolve_targets",
         "set_build_path",
@@ -996,6 +1161,19 @@ pub fn populate_environment(
     Ok(())
 }
 
+/// Resolve a registered target in a Starlark environment.
+///
+/// Targets the target depends on are resolved first. Returns the value
+/// returned by the target's function.
+pub fn resolve_registered_target(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    target: &str,
+) -> Result<Value> {
+    starlark_resolve_target(type_values, call_stack, target.to_string())
+        .map_err(|e| anyhow!("error resolving target {}: {:?}", target, e))
+}
+
 /// Build a registered target in a Starlark environment.
 pub fn build_target(
     _env: &mut Environment,
@@ -1139,4 +1317,107 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_register_test_target() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def foo(): pass")?;
+        env.eval("def bar(): pass")?;
+        env.eval("register_test_target('tests', bar, depends=['foo'])")?;
+        env.eval("register_target('foo', foo)")?;
+
+        let context_value = get_context_value(&env.type_values).unwrap();
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)
+            .unwrap();
+
+        assert_eq!(context.targets().len(), 2);
+        assert!(context.get_target("tests").unwrap().test);
+        assert!(!context.get_target("foo").unwrap().test);
+        assert_eq!(context.test_targets(), vec!["tests".to_string()]);
+        assert_eq!(context.default_target(), Some("foo"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_target_depends() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def foo(): return 'foo'")?;
+        env.eval("def bar(foo): return foo + 'bar'")?;
+        env.eval("register_target('foo', foo)")?;
+        env.eval("register_target('bar', bar, depends=['foo'])")?;
+
+        assert_eq!(env.eval("resolve_target('bar')")?.to_string(), "foobar");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_target_cycle() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def foo(bar): pass")?;
+        env.eval("def bar(foo): pass")?;
+        env.eval("register_target('foo', foo, depends=['bar'])")?;
+        env.eval("register_target('bar', bar, depends=['foo'])")?;
+
+        let err = env.eval("resolve_target('foo')").unwrap_err();
+        let message = format!("{:?}", err);
+        assert!(message.as_str().contains("foo -> bar -> foo"));
+
+        // State is cleaned up after failures.
+        env.eval("def baz(): return 'baz'")?;
+        env.eval("register_target('baz', baz)")?;
+        assert_eq!(env.eval("resolve_target('baz')")?.to_string(), "baz");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        assert_eq!(env.eval("log")?.get_type(), "Logger");
+
+        for level in ["debug", "info", "warn", "error"] {
+            let res = env.eval(&format!("log.{}('message', 42, ['list'])", level))?;
+            assert_eq!(res.get_type(), "NoneType");
+        }
+
+        assert!(env.eval("log.critical('message')").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_dependencies() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def foo(): pass")?;
+        env.eval("def bar(foo, baz): pass")?;
+        env.eval("register_target('foo', foo)")?;
+        env.eval("register_target('bar', bar, depends=['foo', 'baz'])")?;
+
+        {
+            let context_value = get_context_value(&env.type_values).unwrap();
+            let context = context_value
+                .downcast_ref::<EnvironmentContext>()
+                .ok_or(ValueError::IncorrectParameterType)
+                .unwrap();
+
+            assert_eq!(
+                context.unknown_dependencies(),
+                vec![("bar".to_string(), "baz".to_string())]
+            );
+        }
+
+        let mut call_stack = CallStack::default();
+        assert!(resolve_registered_target(&env.type_values, &mut call_stack, "bar").is_err());
+        assert_eq!(
+            resolve_registered_target(&env.type_values, &mut call_stack, "foo")?.get_type(),
+            "NoneType"
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,161 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Apple icon image (`.icns`) files.
+
+An `.icns` file is a header followed by elements holding images. Modern
+element types hold PNG data as-is, so PNG images can be converted without
+decoding them.
+*/
+
+use anyhow::{anyhow, Result};
+
+const ICNS_MAGIC: &[u8; 4] = b"icns";
+const PNG_MAGIC: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Element types holding PNG images, by image dimensions.
+///
+/// Where Apple defines both a regular and a `@2x` type for a size, the
+/// regular type is used.
+const PNG_ELEMENT_TYPES: &[(u32, &[u8; 4])] = &[
+    (16, b"icp4"),
+    (32, b"icp5"),
+    (64, b"icp6"),
+    (128, b"ic07"),
+    (256, b"ic08"),
+    (512, b"ic09"),
+    (1024, b"ic10"),
+];
+
+/// Whether data is an `.icns` file.
+pub fn is_icns(data: &[u8]) -> bool {
+    data.starts_with(ICNS_MAGIC)
+}
+
+/// Whether data is a PNG image.
+pub fn is_png(data: &[u8]) -> bool {
+    data.starts_with(PNG_MAGIC)
+}
+
+/// Obtain the width and height of a PNG image.
+pub fn png_dimensions(data: &[u8]) -> Result<(u32, u32)> {
+    // The IHDR chunk must come first: length, type, width, height.
+    if !is_png(data) || data.len() < 24 || &data[12..16] != b"IHDR" {
+        return Err(anyhow!("data is not a PNG image"));
+    }
+
+    let width = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+    let height = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+
+    Ok((width, height))
+}
+
+/// Convert PNG images to an `.icns` file.
+///
+/// Images must be square and have a size supported by `.icns` files:
+/// 16, 32, 64, 128, 256, 512, or 1024 pixels. Each size can only be given
+/// once.
+pub fn png_to_icns<'a>(images: impl IntoIterator<Item = &'a [u8]>) -> Result<Vec<u8>> {
+    let mut elements: Vec<(&[u8; 4], &[u8])> = vec![];
+
+    for data in images {
+        let (width, height) = png_dimensions(data)?;
+
+        if width != height {
+            return Err(anyhow!(
+                "icon images must be square; got {}x{}",
+                width,
+                height
+            ));
+        }
+
+        let element_type = PNG_ELEMENT_TYPES
+            .iter()
+            .find(|(size, _)| *size == width)
+            .map(|(_, element_type)| *element_type)
+            .ok_or_else(|| {
+                anyhow!(
+                    "{}x{} is not a supported icon size; use 16, 32, 64, 128, 256, 512, or 1024",
+                    width,
+                    height
+                )
+            })?;
+
+        if elements.iter().any(|(t, _)| *t == element_type) {
+            return Err(anyhow!("multiple {}x{} icon images", width, height));
+        }
+
+        elements.push((element_type, data));
+    }
+
+    if elements.is_empty() {
+        return Err(anyhow!("no icon images"));
+    }
+
+    // Lengths include the 8 byte header of the file and of each element.
+    let length = 8 + elements
+        .iter()
+        .map(|(_, data)| 8 + data.len())
+        .sum::<usize>();
+
+    let mut res = Vec::with_capacity(length);
+    res.extend_from_slice(ICNS_MAGIC);
+    res.extend_from_slice(&u32::try_from(length)?.to_be_bytes());
+
+    for (element_type, data) in elements {
+        res.extend_from_slice(element_type);
+        res.extend_from_slice(&u32::try_from(8 + data.len())?.to_be_bytes());
+        res.extend_from_slice(data);
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Construct the start of a PNG image, enough to read its dimensions.
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = PNG_MAGIC.to_vec();
+        data.extend_from_slice(&13u32.to_be_bytes());
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        data
+    }
+
+    #[test]
+    fn test_png_dimensions() -> Result<()> {
+        assert_eq!(png_dimensions(&png(64, 32))?, (64, 32));
+        assert!(png_dimensions(b"GIF89a").is_err());
+        assert!(png_dimensions(PNG_MAGIC).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_png_to_icns() -> Result<()> {
+        let small = png(16, 16);
+        let large = png(512, 512);
+
+        let icns = png_to_icns([small.as_slice(), large.as_slice()])?;
+        assert!(is_icns(&icns));
+        assert_eq!(icns.len(), 8 + 8 + small.len() + 8 + large.len());
+        assert_eq!(&icns[4..8], &(icns.len() as u32).to_be_bytes());
+        assert_eq!(&icns[8..12], b"icp4");
+        assert_eq!(&icns[12..16], &(8 + small.len() as u32).to_be_bytes());
+        assert_eq!(&icns[16..16 + small.len()], small.as_slice());
+        assert_eq!(&icns[16 + small.len()..20 + small.len()], b"ic09");
+
+        assert!(png_to_icns([png(16, 32).as_slice()]).is_err());
+        assert!(png_to_icns([png(100, 100).as_slice()]).is_err());
+        assert!(png_to_icns([small.as_slice(), small.as_slice()]).is_err());
+        assert!(png_to_icns(std::iter::empty()).is_err());
+
+        Ok(())
+    }
+}
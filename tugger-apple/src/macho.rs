@@ -5,7 +5,8 @@
 use {
     anyhow::Result,
     goblin::mach::{
-        fat::{FatArch, FAT_MAGIC, SIZEOF_FAT_ARCH, SIZEOF_FAT_HEADER},
+        fat::{FatArch, FAT_CIGAM, FAT_MAGIC, SIZEOF_FAT_ARCH, SIZEOF_FAT_HEADER},
+        header::{MH_CIGAM, MH_CIGAM_64, MH_MAGIC, MH_MAGIC_64},
         Mach,
     },
     scroll::{IOwrite, Pwrite},
@@ -25,6 +26,17 @@ pub enum UniversalMachOError {
     Scroll(#[from] scroll::Error),
 }
 
+/// Whether data is a Mach-O binary, thin or universal.
+pub fn is_macho(data: &[u8]) -> bool {
+    match goblin::mach::peek(data, 0) {
+        Ok(magic) => matches!(
+            magic,
+            MH_MAGIC | MH_CIGAM | MH_MAGIC_64 | MH_CIGAM_64 | FAT_MAGIC | FAT_CIGAM
+        ),
+        Err(_) => false,
+    }
+}
+
 /// Interface for constructing a universal Mach-O binary.
 #[derive(Clone, Default)]
 pub struct UniversalBinaryBuilder {
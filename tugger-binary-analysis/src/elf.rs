@@ -4,6 +4,7 @@
 
 use {
     crate::UndefinedSymbol,
+    anyhow::Result,
     byteorder::ReadBytesExt,
     std::{ffi::CStr, os::raw::c_char},
 };
@@ -160,3 +161,18 @@ pub fn find_undefined_elf_symbols(buffer: &[u8], elf: &goblin::elf::Elf) -> Vec<
 
     res
 }
+
+/// Find shared library dependencies of an ELF binary.
+///
+/// The program interpreter is included, if present. Statically linked
+/// binaries have no dependencies.
+pub fn find_elf_dependencies(data: &[u8]) -> Result<Vec<String>> {
+    let elf = goblin::elf::Elf::parse(data)?;
+
+    Ok(elf
+        .interpreter
+        .iter()
+        .chain(elf.libraries.iter())
+        .map(|l| (*l).to_string())
+        .collect())
+}
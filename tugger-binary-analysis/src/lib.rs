@@ -7,7 +7,7 @@
 mod audit;
 pub use audit::{analyze_data, analyze_elf_libraries, analyze_file};
 mod elf;
-pub use elf::find_undefined_elf_symbols;
+pub use elf::{find_elf_dependencies, find_undefined_elf_symbols};
 mod linux_distro_versions;
 pub use linux_distro_versions::{
     find_minimum_distro_version, GCC_VERSIONS_BY_DISTRO, GLIBC_VERSIONS_BY_DISTRO,
@@ -360,17 +360,21 @@ pub fn run_candle<P: AsRef<Path>, S: AsRef<str>>(
 /// `wixobjs` is an iterable of paths defining `.wixobj` files to link together.
 ///
 /// `variables` are extra variables to define via `-d<k>[=<v>]`.
+///
+/// `suppressed_ices` are ICE validations to skip via `-sice:<ice>`.
 pub fn run_light<
     P1: AsRef<Path>,
     P2: AsRef<Path>,
     P3: AsRef<Path>,
     P4: AsRef<Path>,
     S: AsRef<str>,
+    S2: AsRef<str>,
 >(
     wix_toolset_path: P1,
     build_path: P2,
     wixobjs: impl Iterator<Item = P3>,
     variables: impl Iterator<Item = (S, Option<S>)>,
+    suppressed_ices: impl Iterator<Item = S2>,
     output_path: P4,
 ) -> Result<()> {
     let light_path = wix_toolset_path.as_ref().join("light.exe");
@@ -395,6 +399,10 @@ pub fn run_light<
         }
     }
 
+    for ice in suppressed_ices {
+        args.push(format!("-sice:{}", ice.as_ref()));
+    }
+
     for p in wixobjs {
         args.push(format!("{}", p.as_ref().display()));
     }
@@ -0,0 +1,148 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    anyhow::{anyhow, Result},
+    std::{
+        fmt::{Display, Formatter},
+        io::Write,
+    },
+    xml::writer::{EventWriter, XmlEvent},
+};
+
+/// When a custom action runs relative to the installation transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CustomActionExecute {
+    /// Run when the action is encountered in the execute sequence.
+    Immediate,
+
+    /// Run as part of the installation script, after the system is changed.
+    Deferred,
+
+    /// Run after the installation script completes successfully.
+    Commit,
+
+    /// Run if the installation fails and is rolled back.
+    Rollback,
+}
+
+impl Display for CustomActionExecute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Immediate => "immediate",
+            Self::Deferred => "deferred",
+            Self::Commit => "commit",
+            Self::Rollback => "rollback",
+        })
+    }
+}
+
+impl TryFrom<&str> for CustomActionExecute {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "immediate" => Ok(Self::Immediate),
+            "deferred" => Ok(Self::Deferred),
+            "commit" => Ok(Self::Commit),
+            "rollback" => Ok(Self::Rollback),
+            _ => Err(anyhow!(
+                "{} is not a valid custom action execution; use 'immediate', 'deferred', 'commit', or 'rollback'",
+                value
+            )),
+        }
+    }
+}
+
+/// A custom action running a command during installation.
+///
+/// This represents a `<CustomAction>` WiX XML element running a command
+/// in a directory and the `<Custom>` element scheduling it in the
+/// `<InstallExecuteSequence>`.
+#[derive(Clone, Debug)]
+pub struct CustomAction {
+    /// The `Id` of the custom action.
+    pub id: String,
+
+    /// `Id` of the `<Directory>` to run the command in.
+    pub directory: String,
+
+    /// The command to run.
+    ///
+    /// Formatted references like `[APPLICATIONFOLDER]` are expanded.
+    pub command: String,
+
+    /// The standard or custom action to schedule this action after.
+    pub after: String,
+
+    /// Condition controlling whether the action runs.
+    pub condition: Option<String>,
+
+    /// When the action runs relative to the installation transaction.
+    pub execute: CustomActionExecute,
+
+    /// Whether to run the command as the installing user instead of `LocalSystem`.
+    pub impersonate: bool,
+
+    /// Whether to ignore the exit code of the command.
+    pub ignore_exit_code: bool,
+}
+
+impl CustomAction {
+    /// Create a new instance running a command in `APPLICATIONFOLDER` after files are installed.
+    pub fn new(id: impl ToString, command: impl ToString) -> Self {
+        Self {
+            id: id.to_string(),
+            directory: "APPLICATIONFOLDER".to_string(),
+            command: command.to_string(),
+            after: "InstallFiles".to_string(),
+            condition: None,
+            execute: CustomActionExecute::Deferred,
+            impersonate: false,
+            ignore_exit_code: false,
+        }
+    }
+
+    /// Write the `<CustomAction>` element defining this action.
+    pub fn write_xml<W: Write>(&self, writer: &mut EventWriter<W>) -> Result<()> {
+        let execute = self.execute.to_string();
+
+        writer.write(
+            XmlEvent::start_element("CustomAction")
+                .attr("Id", &self.id)
+                .attr("Directory", &self.directory)
+                .attr("ExeCommand", &self.command)
+                .attr("Execute", &execute)
+                .attr("Impersonate", if self.impersonate { "yes" } else { "no" })
+                .attr(
+                    "Return",
+                    if self.ignore_exit_code {
+                        "ignore"
+                    } else {
+                        "check"
+                    },
+                ),
+        )?;
+        writer.write(XmlEvent::end_element().name("CustomAction"))?;
+
+        Ok(())
+    }
+
+    /// Write the `<Custom>` element scheduling this action.
+    ///
+    /// This should be written inside an `<InstallExecuteSequence>`.
+    pub fn write_sequence_xml<W: Write>(&self, writer: &mut EventWriter<W>) -> Result<()> {
+        writer.write(
+            XmlEvent::start_element("Custom")
+                .attr("Action", &self.id)
+                .attr("After", &self.after),
+        )?;
+        if let Some(condition) = &self.condition {
+            writer.write(XmlEvent::Characters(condition))?;
+        }
+        writer.write(XmlEvent::end_element().name("Custom"))?;
+
+        Ok(())
+    }
+}
@@ -7,7 +7,7 @@ use {
     anyhow::{anyhow, Context, Result},
     simple_file_manifest::{FileEntry, FileManifest},
     std::{
-        collections::BTreeMap,
+        collections::{BTreeMap, BTreeSet},
         path::{Path, PathBuf},
     },
     tugger_windows_codesign::{is_file_signable, SigntoolSign},
@@ -41,6 +41,9 @@ pub struct WiXInstallerBuilder {
     /// Variables to define when running light.
     variables: BTreeMap<String, Option<String>>,
 
+    /// ICE validations to skip when running light.
+    suppressed_ices: BTreeSet<String>,
+
     /// wxs files defining the WiX installer.
     ///
     /// These files will be materialized and processed when building.
@@ -64,6 +67,7 @@ impl WiXInstallerBuilder {
             install_files_wxs_path: PathBuf::from("install-files.wxs"),
             install_files_root_directory_id: "APPLICATIONFOLDER".to_string(),
             variables: BTreeMap::new(),
+            suppressed_ices: BTreeSet::new(),
             wxs_files: BTreeMap::new(),
             extra_build_files: FileManifest::default(),
             auto_sign_signtool_settings: None,
@@ -93,6 +97,16 @@ impl WiXInstallerBuilder {
             .insert(key.to_string(), value.map(|x| x.to_string()));
     }
 
+    /// Obtain ICE validations that will be skipped by `light` invocations.
+    pub fn suppressed_ices(&self) -> impl Iterator<Item = &str> {
+        self.suppressed_ices.iter().map(|x| x.as_str())
+    }
+
+    /// Skip an ICE validation (e.g. `ICE38`) when running `light.exe`.
+    pub fn suppress_ice(&mut self, ice: impl ToString) {
+        self.suppressed_ices.insert(ice.to_string());
+    }
+
     /// Set the directory to use to build the installer in.
     pub fn set_build_path(&mut self, path: impl AsRef<Path>) {
         self.build_path = path.as_ref().to_path_buf();
@@ -222,6 +236,13 @@ impl WiXInstallerBuilder {
     /// The output could be an MSI, exe, or other file formats depending on what the
     /// wxs files define.
     pub fn build<P: AsRef<Path>>(&self, output_path: P) -> Result<()> {
+        // candle.exe only accepts `-arch arm64` as of WiX Toolset 3.14.
+        if self.arch == "arm64" {
+            return Err(anyhow!(
+                "building arm64 installers requires WiX Toolset 3.14 or newer; the WiX Toolset used is 3.11"
+            ));
+        }
+
         let wix_toolset_path = extract_wix(&self.build_path).context("extracting WiX Toolset")?;
 
         // Materialize FileManifest so we can reference files from WiX.
@@ -282,6 +303,7 @@ impl WiXInstallerBuilder {
             &self.build_path,
             wixobj_paths.iter(),
             self.variables.iter().map(|(k, v)| (k.clone(), v.clone())),
+            self.suppressed_ices(),
             output_path.as_ref(),
         )
         .context("running light")?;
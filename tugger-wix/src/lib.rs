@@ -5,9 +5,11 @@
 mod bundle_builder;
 mod chain;
 mod common;
+mod custom_action;
 mod exe_package;
 mod installer_builder;
 mod msi_package;
+mod registry_value;
 mod simple_msi_builder;
 mod wxs_builder;
 
@@ -15,9 +17,11 @@ pub use {
     bundle_builder::WiXBundleInstallerBuilder,
     chain::ChainElement,
     common::{run_candle, run_light, target_triple_to_wix_arch, write_file_manifest_to_wix},
+    custom_action::{CustomAction, CustomActionExecute},
     exe_package::{Behavior, ExePackage, ExitCode},
     installer_builder::WiXInstallerBuilder,
     msi_package::MsiPackage,
-    simple_msi_builder::WiXSimpleMsiBuilder,
+    registry_value::{RegistryRoot, RegistryValue, RegistryValueType},
+    simple_msi_builder::{InstallScope, WiXSimpleMsiBuilder},
     wxs_builder::WxsBuilder,
 };
@@ -0,0 +1,166 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    anyhow::{anyhow, Result},
+    std::{
+        fmt::{Display, Formatter},
+        io::Write,
+    },
+    xml::writer::{EventWriter, XmlEvent},
+};
+
+/// A root key of the Windows registry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RegistryRoot {
+    /// `HKEY_LOCAL_MACHINE`.
+    LocalMachine,
+
+    /// `HKEY_CURRENT_USER`.
+    CurrentUser,
+
+    /// `HKEY_CLASSES_ROOT`.
+    ClassesRoot,
+
+    /// `HKEY_USERS`.
+    Users,
+
+    /// `HKEY_LOCAL_MACHINE` for per-machine installs, else `HKEY_CURRENT_USER`.
+    MachineOrUser,
+}
+
+impl Display for RegistryRoot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::LocalMachine => "HKLM",
+            Self::CurrentUser => "HKCU",
+            Self::ClassesRoot => "HKCR",
+            Self::Users => "HKU",
+            Self::MachineOrUser => "HKMU",
+        })
+    }
+}
+
+impl TryFrom<&str> for RegistryRoot {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "HKLM" => Ok(Self::LocalMachine),
+            "HKCU" => Ok(Self::CurrentUser),
+            "HKCR" => Ok(Self::ClassesRoot),
+            "HKU" => Ok(Self::Users),
+            "HKMU" => Ok(Self::MachineOrUser),
+            _ => Err(anyhow!(
+                "{} is not a valid registry root; use 'HKLM', 'HKCU', 'HKCR', 'HKU', or 'HKMU'",
+                value
+            )),
+        }
+    }
+}
+
+/// The type of data in a registry value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RegistryValueType {
+    /// `REG_SZ`.
+    String,
+
+    /// `REG_EXPAND_SZ`.
+    Expandable,
+
+    /// `REG_DWORD`.
+    Integer,
+
+    /// `REG_MULTI_SZ`.
+    MultiString,
+
+    /// `REG_BINARY`, given as hex digits.
+    Binary,
+}
+
+impl Display for RegistryValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::String => "string",
+            Self::Expandable => "expandable",
+            Self::Integer => "integer",
+            Self::MultiString => "multiString",
+            Self::Binary => "binary",
+        })
+    }
+}
+
+impl TryFrom<&str> for RegistryValueType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "string" => Ok(Self::String),
+            "expandable" => Ok(Self::Expandable),
+            "integer" => Ok(Self::Integer),
+            "multiString" => Ok(Self::MultiString),
+            "binary" => Ok(Self::Binary),
+            _ => Err(anyhow!(
+                "{} is not a valid registry value type; use 'string', 'expandable', 'integer', 'multiString', or 'binary'",
+                value
+            )),
+        }
+    }
+}
+
+/// Represents the `<RegistryValue>` WiX XML element.
+///
+/// Values are written when the product is installed and removed when it
+/// is uninstalled.
+#[derive(Clone, Debug)]
+pub struct RegistryValue {
+    /// The root key holding the value.
+    pub root: RegistryRoot,
+
+    /// Path of the key holding the value, relative to `root`.
+    pub key: String,
+
+    /// Name of the value.
+    ///
+    /// `None` sets the default value of the key.
+    pub name: Option<String>,
+
+    /// The data to write.
+    ///
+    /// Formatted references like `[APPLICATIONFOLDER]` are expanded.
+    pub value: String,
+
+    /// The type of `value`.
+    pub value_type: RegistryValueType,
+}
+
+impl RegistryValue {
+    pub fn write_xml<W: Write>(&self, writer: &mut EventWriter<W>, key_path: bool) -> Result<()> {
+        let root = self.root.to_string();
+        let value_type = self.value_type.to_string();
+
+        let e = XmlEvent::start_element("RegistryValue")
+            .attr("Root", &root)
+            .attr("Key", &self.key)
+            .attr("Type", &value_type)
+            .attr("Value", &self.value);
+
+        let e = if let Some(name) = &self.name {
+            e.attr("Name", name)
+        } else {
+            e
+        };
+
+        let e = if key_path {
+            e.attr("KeyPath", "yes")
+        } else {
+            e
+        };
+
+        writer.write(e)?;
+        writer.write(XmlEvent::end_element().name("RegistryValue"))?;
+
+        Ok(())
+    }
+}
@@ -3,11 +3,12 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::{WiXInstallerBuilder, WxsBuilder},
+    crate::{CustomAction, RegistryRoot, RegistryValue, WiXInstallerBuilder, WxsBuilder},
     anyhow::{anyhow, Result},
     simple_file_manifest::FileManifest,
     std::{
         borrow::Cow,
+        fmt::{Display, Formatter},
         io::Write,
         path::{Path, PathBuf},
     },
@@ -20,6 +21,53 @@ use {
     },
 };
 
+/// Who an MSI installer installs the product for.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum InstallScope {
+    /// Install for all users of the machine.
+    ///
+    /// Files are installed in `Program Files`. Installing requires elevation.
+    #[default]
+    PerMachine,
+
+    /// Install for the current user.
+    ///
+    /// Files are installed in `%LOCALAPPDATA%\Programs`. Installing doesn't
+    /// require elevation.
+    PerUser,
+}
+
+impl Display for InstallScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::PerMachine => "perMachine",
+            Self::PerUser => "perUser",
+        })
+    }
+}
+
+impl TryFrom<&str> for InstallScope {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "perMachine" => Ok(Self::PerMachine),
+            "perUser" => Ok(Self::PerUser),
+            _ => Err(anyhow!(
+                "{} is not a valid install scope; use 'perMachine' or 'perUser'",
+                value
+            )),
+        }
+    }
+}
+
+/// ICE validations that fail for files installed in a user's profile.
+///
+/// Components installed per-user are expected to use registry keys under
+/// `HKCU` as their key path and to remove every directory they create.
+/// Files installed from the program files manifest do neither.
+const PER_USER_SUPPRESSED_ICES: &[&str] = &["ICE38", "ICE64", "ICE91"];
+
 /// Entity used to emit a simple `.wxs` for building an msi installer.
 ///
 /// Instances are constructed with mandatory fields, such as the
@@ -29,7 +77,11 @@ use {
 /// installer if provided.
 ///
 /// The MSI installer will materialize registered files in the
-/// `Program Files` directory on the target machine.
+/// `Program Files` directory on the target machine, or in the user's
+/// profile for per-user installs.
+///
+/// The generated `.wxs` can be extended with registry values, custom
+/// actions, and additional `.wxs` files defining fragments.
 #[derive(Clone, Default)]
 pub struct WiXSimpleMsiBuilder {
     id_prefix: String,
@@ -64,6 +116,21 @@ pub struct WiXSimpleMsiBuilder {
 
     /// Signtool settings to use to auto sign binaries and the installer.
     auto_sign_signtool_settings: Option<SigntoolSign>,
+
+    /// Who the product is installed for.
+    install_scope: InstallScope,
+
+    /// Registry values to write on install.
+    registry_values: Vec<RegistryValue>,
+
+    /// Custom actions to run on install.
+    custom_actions: Vec<CustomAction>,
+
+    /// Additional `.wxs` files to build.
+    wxs_files: Vec<WxsBuilder>,
+
+    /// `<ComponentGroup>` IDs to install as part of the main feature.
+    component_group_refs: Vec<String>,
 }
 
 impl WiXSimpleMsiBuilder {
@@ -186,6 +253,53 @@ impl WiXSimpleMsiBuilder {
         self
     }
 
+    /// Set who the product is installed for.
+    ///
+    /// The default is a per-machine install.
+    #[must_use]
+    pub fn install_scope(mut self, value: InstallScope) -> Self {
+        self.install_scope = value;
+        self
+    }
+
+    /// Add a registry value to write when the product is installed.
+    pub fn add_registry_value(&mut self, value: RegistryValue) {
+        self.registry_values.push(value);
+    }
+
+    /// Add a custom action to run when the product is installed.
+    pub fn add_custom_action(&mut self, action: CustomAction) -> Result<()> {
+        if self.custom_actions.iter().any(|a| a.id == action.id) {
+            return Err(anyhow!("custom action {} already defined", action.id));
+        }
+
+        self.custom_actions.push(action);
+
+        Ok(())
+    }
+
+    /// Add a `.wxs` file to build with the generated one.
+    ///
+    /// This can define fragments containing anything the generated `.wxs`
+    /// doesn't support. Its path must not be `main.wxs`.
+    pub fn add_wxs(&mut self, wxs: WxsBuilder) -> Result<()> {
+        if wxs.path() == Path::new("main.wxs") {
+            return Err(anyhow!("main.wxs is reserved for the generated wxs file"));
+        }
+
+        self.wxs_files.push(wxs);
+
+        Ok(())
+    }
+
+    /// Install a `<ComponentGroup>` as part of the main feature.
+    ///
+    /// This is how components defined by files registered with [Self::add_wxs]
+    /// get installed.
+    pub fn add_component_group_ref(&mut self, id: impl ToString) {
+        self.component_group_refs.push(id.to_string());
+    }
+
     /// Register signtool signing settings to be used to automatically sign binaries.
     ///
     /// This will automatically sign all installed binaries as well as the
@@ -211,6 +325,16 @@ impl WiXSimpleMsiBuilder {
             emitter.into_inner().into_inner()?,
         ));
 
+        for wxs in &self.wxs_files {
+            builder.add_wxs(wxs.clone());
+        }
+
+        if self.install_scope == InstallScope::PerUser {
+            for ice in PER_USER_SUPPRESSED_ICES {
+                builder.suppress_ice(ice);
+            }
+        }
+
         builder
             .install_files_mut()
             .add_manifest(&self.program_files_manifest)?;
@@ -301,16 +425,40 @@ impl WiXSimpleMsiBuilder {
                 .attr("Codepage", &self.product_codepage),
         )?;
 
+        let install_scope = self.install_scope.to_string();
+        let per_user = self.install_scope == InstallScope::PerUser;
+
+        if per_user {
+            if let Some(value) = self.registry_values.iter().find(|v| {
+                matches!(
+                    v.root,
+                    RegistryRoot::LocalMachine | RegistryRoot::ClassesRoot | RegistryRoot::Users
+                )
+            }) {
+                return Err(anyhow!(
+                    "registry value in {}\\{} can't be written by a per-user install",
+                    value.root,
+                    value.key
+                ));
+            }
+        }
+
         let package = XmlEvent::start_element("Package")
             .attr("Id", "*")
             .attr("Manufacturer", &self.product_manufacturer)
             .attr("InstallerVersion", &self.package_installer_version)
             .attr("Languages", &self.package_languages)
             .attr("Compressed", "yes")
-            .attr("InstallScope", "perMachine")
+            .attr("InstallScope", &install_scope)
             .attr("SummaryCodepage", "1252")
             .attr("Platform", "$(sys.BUILDARCH)");
 
+        let package = if per_user {
+            package.attr("InstallPrivileges", "limited")
+        } else {
+            package
+        };
+
         let package = if let Some(keywords) = &self.package_keywords {
             package.attr("Keywords", keywords)
         } else {
@@ -356,24 +504,42 @@ impl WiXSimpleMsiBuilder {
                 .attr("Id", "TARGETDIR")
                 .attr("Name", "SourceDir"),
         )?;
-        writer.write(
-            XmlEvent::start_element("Directory")
-                .attr("Id", "$(var.PlatformProgramFilesFolder)")
-                .attr("Name", "PFiles"),
-        )?;
+        if per_user {
+            writer.write(
+                XmlEvent::start_element("Directory")
+                    .attr("Id", "LocalAppDataFolder")
+                    .attr("Name", "LocalAppData"),
+            )?;
+            writer.write(
+                XmlEvent::start_element("Directory")
+                    .attr("Id", "UserProgramsFolder")
+                    .attr("Name", "Programs"),
+            )?;
+        } else {
+            writer.write(
+                XmlEvent::start_element("Directory")
+                    .attr("Id", "$(var.PlatformProgramFilesFolder)")
+                    .attr("Name", "PFiles"),
+            )?;
+        }
         writer.write(
             XmlEvent::start_element("Directory")
                 .attr("Id", "APPLICATIONFOLDER")
                 .attr("Name", &self.product_name),
         )?;
 
-        writer.write(
-            XmlEvent::start_element("Component")
-                .attr("Id", "Path")
-                .attr("Guid", &self.path_component_guid())
-                .attr("Win64", "$(var.Win64)")
-                .attr("KeyPath", "yes"),
-        )?;
+        let path_component_guid = self.path_component_guid();
+        let path_component = XmlEvent::start_element("Component")
+            .attr("Id", "Path")
+            .attr("Guid", &path_component_guid)
+            .attr("Win64", "$(var.Win64)");
+        // Per-user components need a registry key path and must remove the
+        // directories they create in the user's profile.
+        writer.write(if per_user {
+            path_component
+        } else {
+            path_component.attr("KeyPath", "yes")
+        })?;
         writer.write(
             XmlEvent::start_element("Environment")
                 .attr("Id", "PATH")
@@ -382,11 +548,48 @@ impl WiXSimpleMsiBuilder {
                 .attr("Permanent", "no")
                 .attr("Part", "last")
                 .attr("Action", "set")
-                .attr("System", "yes"),
+                .attr("System", if per_user { "no" } else { "yes" }),
         )?;
         writer.write(XmlEvent::end_element().name("Environment"))?;
+        if per_user {
+            writer.write(
+                XmlEvent::start_element("RegistryValue")
+                    .attr("Root", "HKCU")
+                    .attr("Key", "Software\\[Manufacturer]\\[ProductName]")
+                    .attr("Name", "Path")
+                    .attr("Type", "integer")
+                    .attr("Value", "1")
+                    .attr("KeyPath", "yes"),
+            )?;
+            writer.write(XmlEvent::end_element().name("RegistryValue"))?;
+
+            for directory in ["APPLICATIONFOLDER", "UserProgramsFolder"] {
+                writer.write(
+                    XmlEvent::start_element("RemoveFolder")
+                        .attr("Id", &format!("Remove{}", directory))
+                        .attr("Directory", directory)
+                        .attr("On", "uninstall"),
+                )?;
+                writer.write(XmlEvent::end_element().name("RemoveFolder"))?;
+            }
+        }
         writer.write(XmlEvent::end_element().name("Component"))?;
 
+        if !self.registry_values.is_empty() {
+            writer.write(
+                XmlEvent::start_element("Component")
+                    .attr("Id", "Registry")
+                    .attr("Guid", &self.registry_component_guid())
+                    .attr("Win64", "$(var.Win64)"),
+            )?;
+
+            for (i, value) in self.registry_values.iter().enumerate() {
+                value.write_xml(writer, i == 0)?;
+            }
+
+            writer.write(XmlEvent::end_element().name("Component"))?;
+        }
+
         if let Some(license_source) = &self.license_source {
             writer.write(
                 XmlEvent::start_element("Component")
@@ -411,6 +614,9 @@ impl WiXSimpleMsiBuilder {
         writer.write(XmlEvent::end_element().name("Directory"))?;
         writer.write(XmlEvent::end_element().name("Directory"))?;
         writer.write(XmlEvent::end_element().name("Directory"))?;
+        if per_user {
+            writer.write(XmlEvent::end_element().name("Directory"))?;
+        }
 
         writer.write(
             XmlEvent::start_element("Feature")
@@ -431,11 +637,21 @@ impl WiXSimpleMsiBuilder {
         )?;
         writer.write(XmlEvent::end_element().name("ComponentGroupRef"))?;
 
+        for id in &self.component_group_refs {
+            writer.write(XmlEvent::start_element("ComponentGroupRef").attr("Id", id))?;
+            writer.write(XmlEvent::end_element().name("ComponentGroupRef"))?;
+        }
+
         if self.license_source.is_some() {
             writer.write(XmlEvent::start_element("ComponentRef").attr("Id", "License"))?;
             writer.write(XmlEvent::end_element().name("ComponentRef"))?;
         }
 
+        if !self.registry_values.is_empty() {
+            writer.write(XmlEvent::start_element("ComponentRef").attr("Id", "Registry"))?;
+            writer.write(XmlEvent::end_element().name("ComponentRef"))?;
+        }
+
         writer.write(
             XmlEvent::start_element("Feature")
                 .attr("Id", "Environment")
@@ -461,6 +677,18 @@ impl WiXSimpleMsiBuilder {
         )?;
         writer.write(XmlEvent::end_element().name("SetProperty"))?;
 
+        if !self.custom_actions.is_empty() {
+            for action in &self.custom_actions {
+                action.write_xml(writer)?;
+            }
+
+            writer.write(XmlEvent::start_element("InstallExecuteSequence"))?;
+            for action in &self.custom_actions {
+                action.write_sequence_xml(writer)?;
+            }
+            writer.write(XmlEvent::end_element().name("InstallExecuteSequence"))?;
+        }
+
         if let Some(icon_path) = &self.product_icon {
             writer.write(
                 XmlEvent::start_element("Icon")
@@ -562,6 +790,16 @@ impl WiXSimpleMsiBuilder {
         }
     }
 
+    fn registry_component_guid(&self) -> String {
+        Uuid::new_v5(
+            &Uuid::NAMESPACE_DNS,
+            format!("tugger.registry_component.{}", self.product_name).as_bytes(),
+        )
+        .as_hyphenated()
+        .encode_upper(&mut Uuid::encode_buffer())
+        .to_string()
+    }
+
     fn path_component_guid(&self) -> String {
         Uuid::new_v5(
             &Uuid::NAMESPACE_DNS,
@@ -600,6 +838,80 @@ mod tests {
         Ok(())
     }
 
+    fn wxs_string(builder: &WiXSimpleMsiBuilder) -> Result<String> {
+        let mut emitter = EmitterConfig::new().create_writer(Vec::new());
+        builder.write_xml(&mut emitter)?;
+
+        Ok(String::from_utf8(emitter.into_inner())?)
+    }
+
+    #[test]
+    fn test_customization() -> Result<()> {
+        let mut builder = WiXSimpleMsiBuilder::new("prefix", "myapp", "0.1", "author")
+            .upgrade_code("6A4D5B9C-1F2E-4D3C-9B8A-7E6F5D4C3B2A".to_string());
+
+        let wxs = wxs_string(&builder)?;
+        assert!(wxs.contains("UpgradeCode=\"6A4D5B9C-1F2E-4D3C-9B8A-7E6F5D4C3B2A\""));
+        assert!(wxs.contains("InstallScope=\"perMachine\""));
+        assert!(wxs.contains("Id=\"$(var.PlatformProgramFilesFolder)\""));
+        assert!(!wxs.contains("InstallExecuteSequence"));
+
+        builder.add_registry_value(RegistryValue {
+            root: RegistryRoot::MachineOrUser,
+            key: "Software\\myapp".to_string(),
+            name: Some("InstallDir".to_string()),
+            value: "[APPLICATIONFOLDER]".to_string(),
+            value_type: crate::RegistryValueType::String,
+        });
+        builder.add_custom_action(CustomAction::new("RunSetup", "setup.exe --init"))?;
+        assert!(builder
+            .add_custom_action(CustomAction::new("RunSetup", "other.exe"))
+            .is_err());
+        builder.add_wxs(WxsBuilder::from_data("extra.wxs", vec![]))?;
+        assert!(builder
+            .add_wxs(WxsBuilder::from_data("main.wxs", vec![]))
+            .is_err());
+        builder.add_component_group_ref("Extra");
+
+        let wxs = wxs_string(&builder)?;
+        assert!(wxs.contains("<RegistryValue Root=\"HKMU\" Key=\"Software\\myapp\" Type=\"string\" Value=\"[APPLICATIONFOLDER]\" Name=\"InstallDir\" KeyPath=\"yes\""));
+        assert!(wxs.contains("<ComponentRef Id=\"Registry\""));
+        assert!(wxs.contains("<CustomAction Id=\"RunSetup\" Directory=\"APPLICATIONFOLDER\" ExeCommand=\"setup.exe --init\" Execute=\"deferred\" Impersonate=\"no\" Return=\"check\""));
+        assert!(wxs.contains("<Custom Action=\"RunSetup\" After=\"InstallFiles\""));
+        assert!(wxs.contains("<ComponentGroupRef Id=\"Extra\""));
+
+        let installer = builder.to_installer_builder("x64", DEFAULT_TEMP_DIR.path())?;
+        assert!(installer
+            .wxs_files()
+            .contains_key(&PathBuf::from("extra.wxs")));
+        assert_eq!(installer.suppressed_ices().count(), 0);
+
+        let builder = builder.install_scope(InstallScope::PerUser);
+        let wxs = wxs_string(&builder)?;
+        assert!(wxs.contains("InstallScope=\"perUser\""));
+        assert!(wxs.contains("InstallPrivileges=\"limited\""));
+        assert!(wxs.contains("Id=\"UserProgramsFolder\""));
+        assert!(wxs.contains("System=\"no\""));
+
+        let installer = builder.to_installer_builder("x64", DEFAULT_TEMP_DIR.path())?;
+        assert_eq!(
+            installer.suppressed_ices().collect::<Vec<_>>(),
+            PER_USER_SUPPRESSED_ICES.to_vec()
+        );
+
+        let mut builder = builder;
+        builder.add_registry_value(RegistryValue {
+            root: RegistryRoot::LocalMachine,
+            key: "Software\\myapp".to_string(),
+            name: None,
+            value: "1".to_string(),
+            value_type: crate::RegistryValueType::Integer,
+        });
+        assert!(wxs_string(&builder).is_err());
+
+        Ok(())
+    }
+
     #[cfg(target_family = "windows")]
     #[test]
     fn test_simple_msi_builder_build() -> Result<()> {
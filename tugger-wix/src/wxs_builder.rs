@@ -11,7 +11,7 @@ use {
 };
 
 /// Entity representing the build context for a .wxs file.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct WxsBuilder {
     /// Relative path/filename of this wxs file.
     path: PathBuf,
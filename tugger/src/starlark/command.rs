@@ -0,0 +1,489 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::starlark::file_manifest::FileManifestValue,
+    anyhow::{anyhow, Context, Result},
+    log::warn,
+    simple_file_manifest::{FileEntry, FileManifest},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            Mutable, TypedValue, Value, ValueResult,
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_dict_arg, optional_list_arg, optional_str_arg,
+        required_list_arg, EnvironmentContext, ResolvedTarget, ResolvedTargetValue, RunMode,
+    },
+    std::{
+        collections::BTreeMap,
+        io::{BufRead, BufReader},
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex, MutexGuard},
+        time::SystemTime,
+    },
+    tugger_common::glob::evaluate_glob,
+};
+
+fn error_context<F, T>(label: &str, f: F) -> Result<T, ValueError>
+where
+    F: FnOnce() -> anyhow::Result<T>,
+{
+    f().map_err(|e| {
+        ValueError::Runtime(RuntimeError {
+            code: "TUGGER_COMMAND",
+            message: format!("{:?}", e),
+            label: label.to_string(),
+        })
+    })
+}
+
+/// An external command run as a build step.
+#[derive(Clone, Debug)]
+pub struct CommandStep {
+    /// The program to run followed by its arguments.
+    pub args: Vec<String>,
+
+    /// Directory to run the command in.
+    pub cwd: PathBuf,
+
+    /// Environment variables to set for the command.
+    pub env: BTreeMap<String, String>,
+
+    /// Glob patterns of files the command reads, relative to `cwd`.
+    pub inputs: Vec<String>,
+
+    /// Paths of files or directories the command writes, relative to `cwd`.
+    pub outputs: Vec<PathBuf>,
+
+    /// Whether the command has already run or was found to be up to date.
+    pub complete: bool,
+}
+
+/// Obtain the modification time of files, walking directories.
+fn modified_times(path: &Path) -> Result<Vec<SystemTime>> {
+    let mut res = vec![];
+
+    for entry in walkdir::WalkDir::new(path) {
+        let entry = entry?;
+
+        if entry.file_type().is_file() {
+            res.push(
+                entry
+                    .metadata()?
+                    .modified()
+                    .with_context(|| format!("resolving mtime of {}", entry.path().display()))?,
+            );
+        }
+    }
+
+    Ok(res)
+}
+
+impl CommandStep {
+    /// Resolve the files matching `inputs`.
+    pub fn input_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut res = vec![];
+
+        for pattern in &self.inputs {
+            res.extend(
+                evaluate_glob(&self.cwd, pattern)
+                    .with_context(|| format!("evaluating glob {}", pattern))?,
+            );
+        }
+
+        Ok(res)
+    }
+
+    /// Whether all outputs exist and are newer than all inputs.
+    ///
+    /// Commands without outputs are never up to date.
+    pub fn is_up_to_date(&self) -> Result<bool> {
+        if self.outputs.is_empty() {
+            return Ok(false);
+        }
+
+        let mut output_times = vec![];
+        for output in &self.outputs {
+            let path = self.cwd.join(output);
+
+            if !path.exists() {
+                return Ok(false);
+            }
+
+            output_times.extend(modified_times(&path)?);
+        }
+
+        let mut input_times = vec![];
+        for path in self.input_paths()? {
+            input_times.extend(modified_times(&path)?);
+        }
+
+        let up_to_date = match (input_times.iter().max(), output_times.iter().min()) {
+            (Some(newest_input), Some(oldest_output)) => newest_input <= oldest_output,
+            (None, Some(_)) => true,
+            (_, None) => false,
+        };
+
+        Ok(up_to_date)
+    }
+
+    /// Run the command unless it has already run or is up to date.
+    pub fn ensure_run(&mut self) -> Result<()> {
+        if self.complete {
+            return Ok(());
+        }
+
+        if self.is_up_to_date()? {
+            warn!("{} is up to date; not running", self.args.join(" "));
+            self.complete = true;
+            return Ok(());
+        }
+
+        let (program, args) = self
+            .args
+            .split_first()
+            .ok_or_else(|| anyhow!("command has no arguments"))?;
+
+        warn!("running {} in {}", self.args.join(" "), self.cwd.display());
+        let mut command = duct::cmd(program, args)
+            .dir(&self.cwd)
+            .stderr_to_stdout()
+            .unchecked();
+        for (key, value) in &self.env {
+            command = command.env(key, value);
+        }
+
+        let reader = command
+            .reader()
+            .with_context(|| format!("running {}", program))?;
+        {
+            let reader = BufReader::new(&reader);
+            for line in reader.lines() {
+                warn!("{}", line?);
+            }
+        }
+
+        let output = reader
+            .try_wait()?
+            .ok_or_else(|| anyhow!("unable to wait on command"))?;
+        if !output.status.success() {
+            return Err(anyhow!("{} failed: {}", self.args.join(" "), output.status));
+        }
+
+        for output in &self.outputs {
+            if !self.cwd.join(output).exists() {
+                return Err(anyhow!(
+                    "{} did not produce declared output {}",
+                    self.args.join(" "),
+                    output.display()
+                ));
+            }
+        }
+
+        self.complete = true;
+
+        Ok(())
+    }
+
+    /// Obtain a manifest of the files in `outputs`.
+    ///
+    /// Manifest paths are relative to `cwd`.
+    pub fn output_manifest(&self) -> Result<FileManifest> {
+        let mut manifest = FileManifest::default();
+
+        for output in &self.outputs {
+            for entry in walkdir::WalkDir::new(self.cwd.join(output)) {
+                let entry = entry?;
+
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+
+                let manifest_path = entry.path().strip_prefix(&self.cwd).map_err(|_| {
+                    anyhow!(
+                        "output {} is not under {}",
+                        entry.path().display(),
+                        self.cwd.display()
+                    )
+                })?;
+
+                manifest.add_file_entry(manifest_path, FileEntry::try_from(entry.path())?)?;
+            }
+        }
+
+        Ok(manifest)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CommandValue {
+    inner: Arc<Mutex<CommandStep>>,
+}
+
+impl TypedValue for CommandValue {
+    type Holder = Mutable<CommandValue>;
+    const TYPE: &'static str = "Command";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl CommandValue {
+    /// Command(args, cwd=None, env=None, inputs=None, outputs=None)
+    pub fn new_from_args(
+        type_values: &TypeValues,
+        args: Value,
+        cwd: Value,
+        env: Value,
+        inputs: Value,
+        outputs: Value,
+    ) -> ValueResult {
+        const LABEL: &str = "Command()";
+
+        required_list_arg("args", "string", &args)?;
+        let cwd = optional_str_arg("cwd", &cwd)?;
+        optional_dict_arg("env", "string", "string", &env)?;
+        optional_list_arg("inputs", "string", &inputs)?;
+        optional_list_arg("outputs", "string", &outputs)?;
+
+        let args = args
+            .iter()?
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+
+        if args.is_empty() {
+            return Err(ValueError::from(RuntimeError {
+                code: "TUGGER_COMMAND",
+                message: "args must name the program to run".to_string(),
+                label: LABEL.to_string(),
+            }));
+        }
+
+        let env = match env.get_type() {
+            "dict" => env
+                .iter()?
+                .iter()
+                .map(|key| Ok((key.to_string(), env.at(key.clone())?.to_string())))
+                .collect::<Result<BTreeMap<_, _>, ValueError>>()?,
+            _ => BTreeMap::new(),
+        };
+
+        let list = |value: &Value| -> Result<Vec<String>, ValueError> {
+            Ok(match value.get_type() {
+                "list" => value.iter()?.iter().map(|x| x.to_string()).collect(),
+                _ => vec![],
+            })
+        };
+
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let cwd = match cwd {
+            Some(cwd) if Path::new(&cwd).is_absolute() => PathBuf::from(cwd),
+            Some(cwd) => context.cwd().join(cwd),
+            None => context.cwd().to_path_buf(),
+        };
+
+        Ok(Value::new(Self {
+            inner: Arc::new(Mutex::new(CommandStep {
+                args,
+                cwd,
+                env,
+                inputs: list(&inputs)?,
+                outputs: list(&outputs)?.into_iter().map(PathBuf::from).collect(),
+                complete: false,
+            })),
+        }))
+    }
+
+    pub fn inner(&self, label: &str) -> Result<MutexGuard<'_, CommandStep>, ValueError> {
+        self.inner.try_lock().map_err(|e| {
+            ValueError::Runtime(RuntimeError {
+                code: "TUGGER_COMMAND",
+                message: format!("error obtaining lock: {}", e),
+                label: label.to_string(),
+            })
+        })
+    }
+
+    pub fn run(&self) -> ValueResult {
+        const LABEL: &str = "Command.run()";
+
+        let mut inner = self.inner(LABEL)?;
+
+        error_context(LABEL, || inner.ensure_run())?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    pub fn to_file_manifest(&self, prefix: String) -> ValueResult {
+        const LABEL: &str = "Command.to_file_manifest()";
+
+        let mut inner = self.inner(LABEL)?;
+
+        let manifest = error_context(LABEL, || {
+            inner.ensure_run()?;
+
+            let outputs = inner.output_manifest()?;
+
+            let mut manifest = FileManifest::default();
+            for (path, entry) in outputs.iter_entries() {
+                manifest.add_file_entry(Path::new(&prefix).join(path), entry.clone())?;
+            }
+
+            Ok(manifest)
+        })?;
+
+        FileManifestValue::new_from_manifest(manifest)
+    }
+
+    pub fn build(&self, target: String) -> ValueResult {
+        const LABEL: &str = "Command.build()";
+
+        let mut inner = self.inner(LABEL)?;
+
+        error_context(LABEL, || {
+            inner
+                .ensure_run()
+                .with_context(|| format!("building target {}", target))
+        })?;
+
+        Ok(Value::new(ResolvedTargetValue {
+            inner: ResolvedTarget {
+                run_mode: RunMode::None,
+                output_path: inner.cwd.clone(),
+            },
+        }))
+    }
+}
+
+starlark_module! { command_module =>
+    #[allow(non_snake_case)]
+    Command(
+        env type_values,
+        args,
+        cwd = NoneType::None,
+        env = NoneType::None,
+        inputs = NoneType::None,
+        outputs = NoneType::None
+    ) {
+        CommandValue::new_from_args(type_values, args, cwd, env, inputs, outputs)
+    }
+
+    Command.build(this, target: String) {
+        let this = this.downcast_ref::<CommandValue>().unwrap();
+        this.build(target)
+    }
+
+    Command.run(this) {
+        let this = this.downcast_ref::<CommandValue>().unwrap();
+        this.run()
+    }
+
+    Command.to_file_manifest(this, prefix: String = "".to_string()) {
+        let this = this.downcast_ref::<CommandValue>().unwrap();
+        this.to_file_manifest(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, tugger_common::testutil::*};
+
+    #[test]
+    fn test_new() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let value = env.eval(
+            "Command(['make', 'all'], cwd = 'sub', env = {'A': 'b'}, inputs = ['*.po'], outputs = ['out'])",
+        )?;
+        assert_eq!(value.get_type(), "Command");
+
+        let command = value.downcast_ref::<CommandValue>().unwrap();
+        let inner = command.inner("ignored").unwrap();
+        assert_eq!(inner.args, vec!["make", "all"]);
+        assert_eq!(inner.cwd, std::env::current_dir()?.join("sub"));
+        assert_eq!(inner.env.get("A"), Some(&"b".to_string()));
+        assert_eq!(inner.inputs, vec!["*.po"]);
+        assert_eq!(inner.outputs, vec![PathBuf::from("out")]);
+
+        assert!(env.eval("Command([])").is_err());
+        assert!(env.eval("Command('make')").is_err());
+        assert!(env.eval("Command(['make'], outputs = 'out')").is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run() -> Result<()> {
+        let root = DEFAULT_TEMP_DIR.path().join("tugger-command-run");
+        if root.exists() {
+            std::fs::remove_dir_all(&root)?;
+        }
+        std::fs::create_dir_all(&root)?;
+        std::fs::write(root.join("input.txt"), b"input")?;
+        let root_s = root.to_string_lossy();
+
+        let mut env = StarlarkEnvironment::new()?;
+
+        // Each run appends to log.txt, so we can tell whether it ran.
+        let command = format!(
+            "Command(['sh', '-c', 'mkdir -p out && echo $VALUE > out/result.txt && echo ran >> log.txt'], cwd = '{}', env = {{'VALUE': 'hello'}}, inputs = ['input*.txt'], outputs = ['out'])",
+            root_s
+        );
+
+        env.eval(&format!("c = {}", command))?;
+        env.eval("c.run()")?;
+        assert_eq!(std::fs::read(root.join("out/result.txt"))?, b"hello\n");
+        assert_eq!(std::fs::read(root.join("log.txt"))?, b"ran\n");
+
+        // Commands run at most once.
+        env.eval("c.run()")?;
+        assert_eq!(std::fs::read(root.join("log.txt"))?, b"ran\n");
+
+        // Up to date outputs aren't regenerated.
+        let manifest_value = env.eval(&format!("{}.to_file_manifest('lib')", command))?;
+        assert_eq!(std::fs::read(root.join("log.txt"))?, b"ran\n");
+        let manifest_value = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+        let manifest = manifest_value.inner("ignored").unwrap();
+        assert!(manifest.has_path("lib/out/result.txt"));
+
+        // Missing outputs are regenerated.
+        std::fs::remove_dir_all(root.join("out"))?;
+        env.eval(&format!("{}.build('translations')", command))?;
+        assert_eq!(std::fs::read(root.join("log.txt"))?, b"ran\nran\n");
+
+        // Commands without outputs always run.
+        env.eval(&format!(
+            "Command(['sh', '-c', 'echo ran >> log.txt'], cwd = '{}').run()",
+            root_s
+        ))?;
+        assert_eq!(std::fs::read(root.join("log.txt"))?, b"ran\nran\nran\n");
+
+        assert!(env
+            .eval(&format!("Command(['false'], cwd = '{}').run()", root_s))
+            .is_err());
+        assert!(env
+            .eval(&format!(
+                "Command(['true'], cwd = '{}', outputs = ['missing']).run()",
+                root_s
+            ))
+            .is_err());
+
+        Ok(())
+    }
+}
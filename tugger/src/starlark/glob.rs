@@ -0,0 +1,176 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::starlark::file_manifest::FileManifestValue,
+    anyhow::{anyhow, Context},
+    simple_file_manifest::{FileEntry, FileManifest},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            Value, ValueResult,
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_list_arg, optional_str_arg, required_list_arg,
+        EnvironmentContext,
+    },
+    std::{collections::BTreeSet, path::PathBuf},
+    tugger_common::glob::evaluate_glob,
+};
+
+fn error_context<F, T>(label: &str, f: F) -> Result<T, ValueError>
+where
+    F: FnOnce() -> anyhow::Result<T>,
+{
+    f().map_err(|e| {
+        ValueError::Runtime(RuntimeError {
+            code: "TUGGER_GLOB",
+            message: format!("{:?}", e),
+            label: label.to_string(),
+        })
+    })
+}
+
+/// glob(include, exclude=None, strip_prefix=None)
+fn starlark_glob(
+    type_values: &TypeValues,
+    include: &Value,
+    exclude: &Value,
+    strip_prefix: &Value,
+) -> ValueResult {
+    const LABEL: &str = "glob()";
+
+    required_list_arg("include", "string", include)?;
+    optional_list_arg("exclude", "string", exclude)?;
+    let strip_prefix = optional_str_arg("strip_prefix", strip_prefix)?;
+
+    let include = include
+        .iter()?
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    let exclude = match exclude.get_type() {
+        "list" => exclude
+            .iter()?
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>(),
+        _ => vec![],
+    };
+
+    let context_value = get_context_value(type_values)?;
+    let context = context_value
+        .downcast_ref::<EnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let cwd = context.cwd().to_path_buf();
+    let strip_prefix = strip_prefix.map(|prefix| context.resolve_path(prefix));
+
+    let manifest = error_context(LABEL, || {
+        let mut paths = BTreeSet::new();
+
+        for pattern in &include {
+            paths.extend(
+                evaluate_glob(&cwd, pattern)
+                    .with_context(|| format!("evaluating glob {}", pattern))?,
+            );
+        }
+
+        for pattern in &exclude {
+            for path in evaluate_glob(&cwd, pattern)
+                .with_context(|| format!("evaluating glob {}", pattern))?
+            {
+                paths.remove(&path);
+            }
+        }
+
+        let mut manifest = FileManifest::default();
+
+        for path in paths {
+            let entry = FileEntry::try_from(path.as_path())?;
+
+            // Manifest paths are relative. Paths are relative to the config
+            // directory unless a prefix to strip is given.
+            let prefix = strip_prefix.as_ref().unwrap_or(&cwd);
+            let manifest_path: PathBuf = path
+                .strip_prefix(prefix)
+                .map_err(|_| {
+                    anyhow!(
+                        "{} is not under {}; specify strip_prefix to control its path",
+                        path.display(),
+                        prefix.display()
+                    )
+                })?
+                .to_path_buf();
+
+            manifest.add_file_entry(&manifest_path, entry)?;
+        }
+
+        Ok(manifest)
+    })?;
+
+    FileManifestValue::new_from_manifest(manifest)
+}
+
+starlark_module! { glob_module =>
+    glob(env env, include, exclude = NoneType::None, strip_prefix = NoneType::None) {
+        starlark_glob(env, &include, &exclude, &strip_prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result, tugger_common::testutil::*};
+
+    #[test]
+    fn test_glob() -> Result<()> {
+        let root = DEFAULT_TEMP_DIR.path().join("tugger-glob");
+        let root_s = root.to_string_lossy().replace('\\', "/");
+
+        std::fs::create_dir_all(root.join("assets/icons"))?;
+        std::fs::write(root.join("assets/a.png"), b"a")?;
+        std::fs::write(root.join("assets/icons/b.png"), b"b")?;
+        std::fs::write(root.join("assets/icons/c.png"), b"c")?;
+        std::fs::write(root.join("assets/readme.txt"), b"readme")?;
+
+        let mut env = StarlarkEnvironment::new()?;
+
+        let value = env.eval(&format!(
+            "glob(['{}/assets/**/*.png'], exclude = ['{}/assets/icons/c.png'], strip_prefix = '{}')",
+            root_s, root_s, root_s
+        ))?;
+        assert_eq!(value.get_type(), "FileManifest");
+
+        let manifest_value = value.downcast_ref::<FileManifestValue>().unwrap();
+        let manifest = manifest_value.inner("ignored").unwrap();
+        let paths = manifest
+            .iter_entries()
+            .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["assets/a.png", "assets/icons/b.png"]);
+
+        // Relative patterns and paths are relative to the config directory.
+        let value = env.eval("glob(['src/starlark/glob.rs'])")?;
+        let manifest_value = value.downcast_ref::<FileManifestValue>().unwrap();
+        let manifest = manifest_value.inner("ignored").unwrap();
+        assert!(manifest.has_path("src/starlark/glob.rs"));
+
+        // Files outside the config directory need a prefix to strip.
+        assert!(env
+            .eval(&format!("glob(['{}/assets/*.txt'])", root_s))
+            .is_err());
+
+        assert!(env.eval("glob('*.txt')").is_err());
+        assert!(env.eval("glob([1])").is_err());
+
+        Ok(())
+    }
+}
@@ -10,7 +10,7 @@ use {
     },
     anyhow::{anyhow, Context},
     apple_bundles::MacOsApplicationBundleBuilder,
-    simple_file_manifest::FileEntry,
+    simple_file_manifest::create_symlink,
     starlark::{
         environment::TypeValues,
         eval::call_stack::CallStack,
@@ -29,6 +29,7 @@ use {
         ResolvedTargetValue, RunMode,
     },
     std::path::{Path, PathBuf},
+    tugger_apple::{is_icns, is_png, png_to_icns},
     tugger_code_signing::SigningDestination,
 };
 
@@ -45,9 +46,38 @@ where
     })
 }
 
+/// Copy a directory, preserving symlinks.
+///
+/// Frameworks use symlinks to point at their current version. Copying
+/// the files they point to instead breaks code signing.
+fn copy_directory_preserving_symlinks(source: &Path, dest: &Path) -> anyhow::Result<()> {
+    for entry in walkdir::WalkDir::new(source).follow_links(false) {
+        let entry = entry?;
+        let dest_path = dest.join(entry.path().strip_prefix(source)?);
+        let file_type = entry.file_type();
+
+        if file_type.is_symlink() {
+            let target = std::fs::read_link(entry.path())?;
+            if dest_path.symlink_metadata().is_ok() {
+                std::fs::remove_file(&dest_path)?;
+            }
+            create_symlink(&dest_path, target)?;
+        } else if file_type.is_dir() {
+            std::fs::create_dir_all(&dest_path)?;
+        } else {
+            std::fs::copy(entry.path(), &dest_path)
+                .with_context(|| format!("copying {}", entry.path().display()))?;
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct MacOsApplicationBundleBuilderValue {
     pub inner: MacOsApplicationBundleBuilder,
+    /// Framework directories to copy into `Contents/Frameworks`.
+    pub frameworks: Vec<PathBuf>,
 }
 
 impl TypedValue for MacOsApplicationBundleBuilderValue {
@@ -65,13 +95,81 @@ impl MacOsApplicationBundleBuilderValue {
             MacOsApplicationBundleBuilder::new(bundle_name)
         })?;
 
-        Ok(Value::new(MacOsApplicationBundleBuilderValue { inner }))
+        Ok(Value::new(MacOsApplicationBundleBuilderValue {
+            inner,
+            frameworks: vec![],
+        }))
     }
 
-    pub fn add_icon(&mut self, path: String) -> ValueResult {
-        error_context("MacOsApplicationBundleBuilder.add_icon()", || {
+    pub fn add_icon(&mut self, path: Value) -> ValueResult {
+        const LABEL: &str = "MacOsApplicationBundleBuilder.add_icon()";
+
+        let paths = match path.get_type() {
+            "string" => vec![path.to_string()],
+            "list" => path.iter()?.iter().map(|x| x.to_string()).collect(),
+            t => {
+                return Err(ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: format!("function expects a string or list of strings; got {}", t),
+                    label: LABEL.to_string(),
+                }))
+            }
+        };
+
+        error_context(LABEL, || {
+            let images = paths
+                .iter()
+                .map(|path| std::fs::read(path).with_context(|| format!("reading {}", path)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let data = match images.as_slice() {
+                [data] if is_icns(data) => data.clone(),
+                _ if images.iter().all(|data| is_png(data)) => {
+                    png_to_icns(images.iter().map(|data| data.as_slice()))
+                        .context("converting PNG images to icns")?
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "icons must be a single .icns file or one or more PNG images"
+                    ))
+                }
+            };
+
+            self.inner.add_icon(data)?;
+
+            // The icon isn't used unless Info.plist refers to it.
+            let icon_file = format!("{}.icns", self.inner.bundle_name()?);
             self.inner
-                .add_icon(FileEntry::try_from(PathBuf::from(path))?)
+                .set_info_plist_key("CFBundleIconFile", icon_file)
+                .context("setting CFBundleIconFile")
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    pub fn add_framework(&mut self, path: String) -> ValueResult {
+        const LABEL: &str = "MacOsApplicationBundleBuilder.add_framework()";
+
+        error_context(LABEL, || {
+            let path = PathBuf::from(path);
+
+            if !path.is_dir() {
+                return Err(anyhow!("{} is not a directory", path.display()));
+            }
+            if path.extension().map(|x| x != "framework").unwrap_or(true) {
+                return Err(anyhow!("{} is not a .framework directory", path.display()));
+            }
+            if self
+                .frameworks
+                .iter()
+                .any(|existing| existing.file_name() == path.file_name())
+            {
+                return Err(anyhow!("framework {} already added", path.display()));
+            }
+
+            self.frameworks.push(path);
+
+            Ok(())
         })?;
 
         Ok(Value::new(NoneType::None))
@@ -237,6 +335,16 @@ impl MacOsApplicationBundleBuilderValue {
                 .materialize_bundle(dest_dir)
                 .context("materializing bundle")?;
 
+            for framework in &self.frameworks {
+                let dest = bundle_path
+                    .join("Contents")
+                    .join("Frameworks")
+                    .join(framework.file_name().expect("validated when added"));
+
+                copy_directory_preserving_symlinks(framework, &dest)
+                    .with_context(|| format!("copying framework {}", framework.display()))?;
+            }
+
             let filename = bundle_path
                 .file_name()
                 .ok_or_else(|| anyhow!("unable to resolve bundle file name"))?
@@ -312,11 +420,16 @@ starlark_module! { macos_application_bundle_builder_module =>
         MacOsApplicationBundleBuilderValue::new_from_args(bundle_name)
     }
 
-    MacOsApplicationBundleBuilder.add_icon(this, path: String) {
+    MacOsApplicationBundleBuilder.add_icon(this, path) {
         let mut this = this.downcast_mut::<MacOsApplicationBundleBuilderValue>().unwrap().unwrap();
         this.add_icon(path)
     }
 
+    MacOsApplicationBundleBuilder.add_framework(this, path: String) {
+        let mut this = this.downcast_mut::<MacOsApplicationBundleBuilderValue>().unwrap().unwrap();
+        this.add_framework(path)
+    }
+
     MacOsApplicationBundleBuilder.add_manifest(this, manifest: FileManifestValue) {
         let mut this = this.downcast_mut::<MacOsApplicationBundleBuilderValue>().unwrap().unwrap();
         this.add_manifest(manifest)
@@ -467,6 +580,94 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn add_icon() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let root = DEFAULT_TEMP_DIR
+            .path()
+            .join("macos-application-bundle-builder-add-icon");
+        std::fs::create_dir_all(&root)?;
+
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&128u32.to_be_bytes());
+        png.extend_from_slice(&128u32.to_be_bytes());
+        std::fs::write(root.join("icon.png"), &png)?;
+        std::fs::write(root.join("icon.gif"), b"GIF89a")?;
+        let root_s = root.to_string_lossy().replace('\\', "/");
+
+        env.eval("builder = MacOsApplicationBundleBuilder('myapp')")?;
+        env.eval(&format!("builder.add_icon(['{}/icon.png'])", root_s))?;
+        assert!(env
+            .eval(&format!("builder.add_icon('{}/icon.gif')", root_s))
+            .is_err());
+
+        let value = env.eval("builder")?;
+        let builder = value
+            .downcast_ref::<MacOsApplicationBundleBuilderValue>()
+            .unwrap();
+
+        let icon = builder
+            .inner
+            .files()
+            .get("Contents/Resources/myapp.icns")
+            .unwrap()
+            .resolve_content()?;
+        assert!(is_icns(&icon));
+        assert_eq!(
+            builder.inner.get_info_plist_key("CFBundleIconFile")?,
+            Some("myapp.icns".into())
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn add_framework() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let root = DEFAULT_TEMP_DIR
+            .path()
+            .join("macos-application-bundle-builder-add-framework");
+        let framework = root.join("Foo.framework");
+        std::fs::create_dir_all(framework.join("Versions/A"))?;
+        std::fs::write(framework.join("Versions/A/Foo"), b"foo")?;
+        create_symlink(framework.join("Versions/Current"), "A")?;
+        create_symlink(framework.join("Foo"), "Versions/Current/Foo")?;
+        let root_s = root.to_string_lossy().replace('\\', "/");
+
+        env.eval("builder = MacOsApplicationBundleBuilder('myapp')")?;
+        env.eval(&format!(
+            "builder.add_framework('{}/Foo.framework')",
+            root_s
+        ))?;
+        assert!(env
+            .eval(&format!(
+                "builder.add_framework('{}/Foo.framework')",
+                root_s
+            ))
+            .is_err());
+        assert!(env
+            .eval(&format!("builder.add_framework('{}')", root_s))
+            .is_err());
+
+        let path_value = env.eval(&format!("builder.write_to_directory('{}/out')", root_s))?;
+        let dest = PathBuf::from(path_value.to_string()).join("Contents/Frameworks/Foo.framework");
+
+        assert_eq!(std::fs::read(dest.join("Versions/A/Foo"))?, b"foo");
+        assert_eq!(
+            std::fs::read_link(dest.join("Versions/Current"))?,
+            PathBuf::from("A")
+        );
+        assert_eq!(
+            std::fs::read_link(dest.join("Foo"))?,
+            PathBuf::from("Versions/Current/Foo")
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn write_to_directory() -> Result<()> {
         let mut env = StarlarkEnvironment::new()?;
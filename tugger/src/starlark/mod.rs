@@ -10,12 +10,15 @@ Tugger.
 
 pub mod apple_universal_binary;
 pub mod code_signing;
+pub mod command;
 pub mod file_content;
 pub mod file_manifest;
 pub mod file_resource;
+pub mod glob;
 pub mod macos_application_bundle_builder;
 pub mod python_wheel_builder;
 pub mod snapcraft;
+pub mod template;
 pub mod terminal;
 #[cfg(test)]
 mod testutil;
@@ -127,12 +130,15 @@ pub fn register_starlark_dialect(
 ) -> Result<(), EnvironmentError> {
     apple_universal_binary::apple_universal_binary_module(env, type_values);
     code_signing::code_signing_module(env, type_values);
+    command::command_module(env, type_values);
     file_content::file_content_module(env, type_values);
     file_manifest::file_manifest_module(env, type_values);
     file_resource::file_resource_module(env, type_values);
+    glob::glob_module(env, type_values);
     macos_application_bundle_builder::macos_application_bundle_builder_module(env, type_values);
     python_wheel_builder::python_wheel_builder_module(env, type_values);
     snapcraft::snapcraft_module(env, type_values);
+    template::template_module(env, type_values);
     terminal::terminal_module(env, type_values);
     wix_bundle_builder::wix_bundle_builder_module(env, type_values);
     wix_installer::wix_installer_module(env, type_values);
@@ -0,0 +1,319 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::starlark::file_content::FileContentWrapper,
+    anyhow::{anyhow, Context},
+    handlebars::Handlebars,
+    simple_file_manifest::{FileData, FileEntry},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE},
+            none::NoneType,
+            Value, ValueResult,
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_bool_arg, optional_str_arg, EnvironmentContext,
+    },
+    std::path::PathBuf,
+};
+
+/// Extensions stripped from template paths to derive the rendered file name.
+const TEMPLATE_EXTENSIONS: &[&str] = &["hbs", "in"];
+
+fn error_context<F, T>(label: &str, f: F) -> Result<T, ValueError>
+where
+    F: FnOnce() -> anyhow::Result<T>,
+{
+    f().map_err(|e| {
+        ValueError::Runtime(RuntimeError {
+            code: "TUGGER_TEMPLATE",
+            message: format!("{:?}", e),
+            label: label.to_string(),
+        })
+    })
+}
+
+/// Convert a Starlark value to a value templates can refer to.
+fn value_to_json(value: &Value) -> Result<serde_json::Value, ValueError> {
+    Ok(match value.get_type() {
+        "NoneType" => serde_json::Value::Null,
+        "bool" => serde_json::Value::Bool(value.to_bool()),
+        "int" => serde_json::Value::from(value.to_int()?),
+        "string" => serde_json::Value::String(value.to_string()),
+        "list" | "tuple" => serde_json::Value::Array(
+            value
+                .iter()?
+                .iter()
+                .map(|v| value_to_json(&v))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        "dict" => {
+            let mut res = serde_json::Map::new();
+
+            for key in &value.iter()? {
+                res.insert(key.to_string(), value_to_json(&value.at(key.clone())?)?);
+            }
+
+            serde_json::Value::Object(res)
+        }
+        t => {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!("unsupported template variable type: {}", t),
+                label: "render_template()".to_string(),
+            }))
+        }
+    })
+}
+
+/// Render a template with variables.
+///
+/// Templates use the Handlebars syntax. Referring to an undefined variable
+/// is an error. Output isn't HTML escaped.
+pub fn render(template: &str, vars: &serde_json::Value) -> anyhow::Result<String> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+
+    Ok(handlebars.render_template(template, vars)?)
+}
+
+/// render_template(path=None, vars=None, content=None, filename=None, executable=None)
+fn starlark_render_template(
+    type_values: &TypeValues,
+    path: &Value,
+    vars: &Value,
+    content: &Value,
+    filename: &Value,
+    executable: &Value,
+) -> ValueResult {
+    const LABEL: &str = "render_template()";
+
+    let path = optional_str_arg("path", path)?;
+    let content = optional_str_arg("content", content)?;
+    let filename = optional_str_arg("filename", filename)?;
+    let executable = optional_bool_arg("executable", executable)?;
+
+    let vars = match vars.get_type() {
+        "NoneType" => serde_json::Value::Object(serde_json::Map::new()),
+        "dict" => value_to_json(vars)?,
+        t => {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!("vars must be None or dict; got {}", t),
+                label: LABEL.to_string(),
+            }))
+        }
+    };
+
+    let context_value = get_context_value(type_values)?;
+    let context = context_value
+        .downcast_ref::<EnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let path = path.map(|path| {
+        let path = PathBuf::from(path);
+
+        if path.is_relative() {
+            context.cwd().join(path)
+        } else {
+            path
+        }
+    });
+
+    let wrapper = error_context(LABEL, || {
+        if let Some(filename) = &filename {
+            if filename.contains('/') || filename.contains('\\') {
+                return Err(anyhow!(
+                    "directory separators aren't allowed in file names: {}",
+                    filename
+                ));
+            }
+        }
+
+        let (template, filename, source_executable) = match (path, content) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow!(
+                    "at most 1 of `path` and `content` arguments can be specified"
+                ));
+            }
+            (Some(path), None) => {
+                let entry = FileEntry::try_from(path.as_path())?;
+                let template = String::from_utf8(entry.resolve_content()?)
+                    .with_context(|| format!("decoding {} as UTF-8", path.display()))?;
+
+                let filename = if let Some(filename) = filename {
+                    filename
+                } else {
+                    template_filename(&path)?
+                };
+
+                (template, filename, entry.is_executable())
+            }
+            (None, Some(content)) => {
+                let filename = filename.ok_or_else(|| {
+                    anyhow!("filename argument is required when content is specified")
+                })?;
+
+                (content, filename, false)
+            }
+            (None, None) => {
+                return Err(anyhow!(
+                    "at least 1 of `path` or `content` arguments must be specified"
+                ));
+            }
+        };
+
+        let rendered = render(&template, &vars).context("rendering template")?;
+
+        Ok(FileContentWrapper {
+            content: FileEntry::new_from_data(
+                FileData::from(rendered.into_bytes()),
+                executable.unwrap_or(source_executable),
+            ),
+            filename,
+        })
+    })?;
+
+    Ok(wrapper.into())
+}
+
+/// Derive the file name of a rendered template from the template's path.
+fn template_filename(path: &std::path::Path) -> anyhow::Result<String> {
+    let path = match path.extension() {
+        Some(ext) if TEMPLATE_EXTENSIONS.iter().any(|x| ext == *x) => path.with_extension(""),
+        _ => PathBuf::from(path),
+    };
+
+    Ok(path
+        .file_name()
+        .ok_or_else(|| anyhow!("unable to resolve file name from path {}", path.display()))?
+        .to_string_lossy()
+        .to_string())
+}
+
+starlark_module! { template_module =>
+    render_template(
+        env env,
+        path = NoneType::None,
+        vars = NoneType::None,
+        content = NoneType::None,
+        filename = NoneType::None,
+        executable = NoneType::None
+    ) {
+        starlark_render_template(env, &path, &vars, &content, &filename, &executable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::starlark::{file_content::FileContentValue, testutil::*},
+        anyhow::Result,
+        tugger_common::testutil::*,
+    };
+
+    fn rendered(value: &Value) -> Result<(String, Vec<u8>, bool)> {
+        let value = value.downcast_ref::<FileContentValue>().unwrap();
+        let inner = value.inner("ignored").unwrap();
+
+        Ok((
+            inner.filename.clone(),
+            inner.content.resolve_content()?,
+            inner.content.is_executable(),
+        ))
+    }
+
+    #[test]
+    fn test_render_content() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let value = env.eval(
+            "render_template(content = '{{name}} {{version}}{{#each tags}} {{this}}{{/each}}{{#if gui}} <gui>{{/if}}', vars = {'name': 'myapp', 'version': 1, 'tags': ['a', 'b'], 'gui': True}, filename = 'out.txt')",
+        )?;
+        assert_eq!(value.get_type(), "FileContent");
+        assert_eq!(
+            rendered(&value)?,
+            ("out.txt".to_string(), b"myapp 1 a b <gui>".to_vec(), false)
+        );
+
+        let value = env.eval(
+            "render_template(content = '{{app.name}}', vars = {'app': {'name': 'myapp'}}, filename = 'run.sh', executable = True)",
+        )?;
+        assert_eq!(
+            rendered(&value)?,
+            ("run.sh".to_string(), b"myapp".to_vec(), true)
+        );
+
+        // Undefined variables are errors.
+        assert!(env
+            .eval("render_template(content = '{{missing}}', filename = 'out.txt')")
+            .is_err());
+        assert!(env.eval("render_template(content = 'foo')").is_err());
+        assert!(env
+            .eval("render_template(content = 'foo', filename = 'a/b')")
+            .is_err());
+        assert!(env.eval("render_template()").is_err());
+        assert!(env
+            .eval("render_template(content = 'foo', vars = ['x'], filename = 'out.txt')")
+            .is_err());
+        assert!(env
+            .eval("render_template(content = '{{x}}', vars = {'x': FileManifest()}, filename = 'out.txt')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_path() -> Result<()> {
+        let temp_path = DEFAULT_TEMP_DIR
+            .path()
+            .join("tugger-render-template")
+            .join("Info.plist.in");
+        std::fs::create_dir_all(temp_path.parent().unwrap())?;
+        std::fs::write(&temp_path, b"<string>{{identifier}}</string>")?;
+        let temp_path_normalized = format!("{}", temp_path.display()).replace('\\', "/");
+
+        let mut env = StarlarkEnvironment::new()?;
+
+        let value = env.eval(&format!(
+            "render_template('{}', {{'identifier': 'com.example.myapp'}})",
+            temp_path_normalized
+        ))?;
+        assert_eq!(
+            rendered(&value)?,
+            (
+                "Info.plist".to_string(),
+                b"<string>com.example.myapp</string>".to_vec(),
+                false
+            )
+        );
+
+        let value = env.eval(&format!(
+            "render_template('{}', {{'identifier': 'x'}}, filename = 'other.plist')",
+            temp_path_normalized
+        ))?;
+        assert_eq!(rendered(&value)?.0, "other.plist");
+
+        assert!(env
+            .eval(&format!(
+                "render_template('{}', content = 'foo', filename = 'out.txt')",
+                temp_path_normalized
+            ))
+            .is_err());
+        assert!(env
+            .eval("render_template('/does/not/exist.in', {})")
+            .is_err());
+
+        Ok(())
+    }
+}
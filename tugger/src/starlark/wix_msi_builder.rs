@@ -27,7 +27,8 @@ use {
         },
     },
     starlark_dialect_build_targets::{
-        get_context_value, EnvironmentContext, ResolvedTarget, ResolvedTargetValue, RunMode,
+        get_context_value, optional_dict_arg, optional_list_arg, optional_str_arg,
+        EnvironmentContext, ResolvedTarget, ResolvedTargetValue, RunMode,
     },
     std::{
         path::{Path, PathBuf},
@@ -35,7 +36,10 @@ use {
     },
     tugger_code_signing::SigningDestination,
     tugger_windows::VcRedistributablePlatform,
-    tugger_wix::WiXSimpleMsiBuilder,
+    tugger_wix::{
+        CustomAction, CustomActionExecute, InstallScope, RegistryRoot, RegistryValue,
+        RegistryValueType, WiXSimpleMsiBuilder, WxsBuilder,
+    },
 };
 
 fn error_context<F, T>(label: &str, f: F) -> Result<T, ValueError>
@@ -88,6 +92,12 @@ impl TypedValue for WiXMsiBuilderValue {
             "eula_rtf_path" => {
                 inner.builder = inner.builder.clone().eula_rtf_path(value.to_string());
             }
+            "install_scope" => {
+                let scope = error_context(&format!("{}.{}", Self::TYPE, attribute), || {
+                    InstallScope::try_from(value.to_string().as_str())
+                })?;
+                inner.builder = inner.builder.clone().install_scope(scope);
+            }
             "help_url" => {
                 inner.builder = inner.builder.clone().help_url(value.to_string());
             }
@@ -207,6 +217,116 @@ impl WiXMsiBuilderValue {
         Ok(Value::new(NoneType::None))
     }
 
+    pub fn add_registry_value(
+        &mut self,
+        root: String,
+        key: String,
+        value: String,
+        name: &Value,
+        value_type: String,
+    ) -> ValueResult {
+        const LABEL: &str = "WiXMSIBuilder.add_registry_value()";
+
+        let name = optional_str_arg("name", name)?;
+
+        let mut inner = self.inner(LABEL)?;
+
+        error_context(LABEL, || {
+            let value = RegistryValue {
+                root: RegistryRoot::try_from(root.as_str())?,
+                key,
+                name,
+                value,
+                value_type: RegistryValueType::try_from(value_type.as_str())?,
+            };
+
+            inner.builder.add_registry_value(value);
+
+            Ok(())
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_custom_action(
+        &mut self,
+        id: String,
+        command: String,
+        after: String,
+        condition: &Value,
+        execute: String,
+        impersonate: bool,
+        ignore_exit_code: bool,
+    ) -> ValueResult {
+        const LABEL: &str = "WiXMSIBuilder.add_custom_action()";
+
+        let condition = optional_str_arg("condition", condition)?;
+
+        let mut inner = self.inner(LABEL)?;
+
+        error_context(LABEL, || {
+            let mut action = CustomAction::new(id, command);
+            action.after = after;
+            action.condition = condition;
+            action.execute = CustomActionExecute::try_from(execute.as_str())?;
+            action.impersonate = impersonate;
+            action.ignore_exit_code = ignore_exit_code;
+
+            inner
+                .builder
+                .add_custom_action(action)
+                .context("adding custom action")
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    pub fn add_wxs_file(
+        &mut self,
+        path: String,
+        component_groups: &Value,
+        preprocessor_parameters: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "WiXMSIBuilder.add_wxs_file()";
+
+        optional_list_arg("component_groups", "string", component_groups)?;
+        optional_dict_arg(
+            "preprocessor_parameters",
+            "string",
+            "string",
+            preprocessor_parameters,
+        )?;
+
+        let mut wxs = error_context(LABEL, || {
+            WxsBuilder::from_path(&path).with_context(|| format!("reading {}", path))
+        })?;
+
+        if preprocessor_parameters.get_type() == "dict" {
+            for key in preprocessor_parameters.iter()?.iter() {
+                let v = preprocessor_parameters.at(key.clone())?.to_string();
+                wxs.set_preprocessor_parameter(key.to_string(), v);
+            }
+        }
+
+        let mut inner = self.inner(LABEL)?;
+
+        error_context(LABEL, || {
+            inner
+                .builder
+                .add_wxs(wxs)
+                .with_context(|| format!("adding {}", path))
+        })?;
+
+        if component_groups.get_type() == "list" {
+            for id in component_groups.iter()?.iter() {
+                inner.builder.add_component_group_ref(id.to_string());
+            }
+        }
+
+        Ok(Value::new(NoneType::None))
+    }
+
     pub fn materialize(
         &self,
         type_values: &TypeValues,
@@ -398,6 +518,42 @@ starlark_module! { wix_msi_builder_module =>
         this.add_visual_cpp_redistributable(redist_version, platform)
     }
 
+    WiXMSIBuilder.add_registry_value(
+        this,
+        root: String,
+        key: String,
+        value: String,
+        name = NoneType::None,
+        value_type: String = "string".to_string()
+    ) {
+        let mut this = this.downcast_mut::<WiXMsiBuilderValue>().unwrap().unwrap();
+        this.add_registry_value(root, key, value, &name, value_type)
+    }
+
+    WiXMSIBuilder.add_custom_action(
+        this,
+        id: String,
+        command: String,
+        after: String = "InstallFiles".to_string(),
+        condition = NoneType::None,
+        execute: String = "deferred".to_string(),
+        impersonate: bool = false,
+        ignore_exit_code: bool = false
+    ) {
+        let mut this = this.downcast_mut::<WiXMsiBuilderValue>().unwrap().unwrap();
+        this.add_custom_action(id, command, after, &condition, execute, impersonate, ignore_exit_code)
+    }
+
+    WiXMSIBuilder.add_wxs_file(
+        this,
+        path: String,
+        component_groups = NoneType::None,
+        preprocessor_parameters = NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<WiXMsiBuilderValue>().unwrap().unwrap();
+        this.add_wxs_file(path, &component_groups, &preprocessor_parameters)
+    }
+
     WiXMSIBuilder.build(env env, call_stack cs, this, target: String) {
         let this = this.downcast_ref::<WiXMsiBuilderValue>().unwrap();
         this.build(env, cs, target)
@@ -416,9 +572,9 @@ starlark_module! { wix_msi_builder_module =>
 
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::starlark::testutil::*};
     #[cfg(windows)]
-    use {crate::starlark::file_content::FileContentValue, tugger_common::testutil::*};
+    use crate::starlark::file_content::FileContentValue;
+    use {super::*, crate::starlark::testutil::*, tugger_common::testutil::*};
 
     #[test]
     fn test_new() -> Result<()> {
@@ -432,6 +588,58 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_customization() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let wxs_path = DEFAULT_TEMP_DIR.path().join("wix-msi-builder-extra.wxs");
+        std::fs::write(&wxs_path, "<Wix />")?;
+        let wxs_path_s = wxs_path.to_string_lossy().replace('\\', "/");
+
+        env.eval("msi = WiXMSIBuilder('prefix', 'name', '0.1', 'manufacturer')")?;
+        env.eval("msi.install_scope = 'perUser'")?;
+        assert!(env.eval("msi.install_scope = 'everyone'").is_err());
+
+        env.eval("msi.add_registry_value('HKCU', 'Software\\\\name', '[APPLICATIONFOLDER]', name = 'InstallDir')")?;
+        env.eval(
+            "msi.add_registry_value('HKMU', 'Software\\\\name', '1', value_type = 'integer')",
+        )?;
+        assert!(env
+            .eval("msi.add_registry_value('HKXX', 'Software', '1')")
+            .is_err());
+        assert!(env
+            .eval("msi.add_registry_value('HKCU', 'Software', '1', value_type = 'dword')")
+            .is_err());
+
+        env.eval("msi.add_custom_action('Init', 'name.exe --init', condition = 'NOT Installed', impersonate = True)")?;
+        assert!(env
+            .eval("msi.add_custom_action('Init', 'name.exe --init')")
+            .is_err());
+        assert!(env
+            .eval("msi.add_custom_action('Other', 'name.exe', execute = 'later')")
+            .is_err());
+
+        env.eval(&format!(
+            "msi.add_wxs_file('{}', component_groups = ['Extra'], preprocessor_parameters = {{'A': 'B'}})",
+            wxs_path_s
+        ))?;
+        assert!(env.eval("msi.add_wxs_file('does-not-exist')").is_err());
+
+        let builder_value = env.eval("msi")?;
+        let builder = builder_value.downcast_ref::<WiXMsiBuilderValue>().unwrap();
+        let installer = builder
+            .inner("ignored")
+            .unwrap()
+            .builder
+            .to_installer_builder("x64", DEFAULT_TEMP_DIR.path())?;
+        assert!(installer
+            .wxs_files()
+            .contains_key(&PathBuf::from("wix-msi-builder-extra.wxs")));
+        assert!(installer.suppressed_ices().any(|ice| ice == "ICE38"));
+
+        Ok(())
+    }
+
     #[cfg(windows)]
     #[test]
     fn test_build() -> Result<()> {